@@ -0,0 +1,133 @@
+use std::fmt::Debug;
+
+use clap::{Parser, ValueEnum};
+use cml_chain::plutus::PlutusData;
+use cml_core::serialization::Deserialize;
+use cml_multi_era::babbage::BabbageTransactionOutput;
+use serde_json::{json, Value};
+
+use bloom_offchain_cardano::orders::grid::DatumNative;
+use bloom_offchain_cardano::orders::limit::Datum;
+use spectrum_cardano_lib::plutus_data::DatumExtension;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::types::TryFromPData;
+use spectrum_offchain_cardano::data::balance_pool::BalancePoolConfig;
+use spectrum_offchain_cardano::data::cfmm_pool::LegacyCFMMPoolConfig;
+use spectrum_offchain_cardano::data::fee_switch_bidirectional_fee::FeeSwitchBidirectionalPoolConfig;
+use spectrum_offchain_cardano::data::fee_switch_pool::FeeSwitchPoolConfig;
+use spectrum_offchain_cardano::data::stable_pool_t2t::StablePoolT2TConfig;
+
+/// Decodes datums of protocol entities for debugging.
+#[derive(Parser)]
+#[command(name = "splash-inspect")]
+struct AppArgs {
+    /// CBOR-hex of a UTxO (transaction output) or of a bare datum.
+    cbor_hex: String,
+    /// Entity to decode the datum as. All known entities are tried if omitted.
+    #[arg(long, short, value_enum)]
+    entity: Option<Entity>,
+    /// Print JSON instead of human-readable output.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum Entity {
+    CfmmPool,
+    FeeSwitchPool,
+    FeeSwitchBidirPool,
+    BalancePool,
+    StablePool,
+    LimitOrder,
+    GridOrder,
+}
+
+impl Entity {
+    const ALL: [Entity; 7] = [
+        Entity::CfmmPool,
+        Entity::FeeSwitchPool,
+        Entity::FeeSwitchBidirPool,
+        Entity::BalancePool,
+        Entity::StablePool,
+        Entity::LimitOrder,
+        Entity::GridOrder,
+    ];
+
+    fn decode(self, datum: PlutusData) -> Option<String> {
+        match self {
+            Entity::CfmmPool => pretty::<LegacyCFMMPoolConfig>(datum),
+            Entity::FeeSwitchPool => pretty::<FeeSwitchPoolConfig>(datum),
+            Entity::FeeSwitchBidirPool => pretty::<FeeSwitchBidirectionalPoolConfig>(datum),
+            Entity::BalancePool => pretty::<BalancePoolConfig>(datum),
+            Entity::StablePool => pretty::<StablePoolT2TConfig>(datum),
+            Entity::LimitOrder => pretty::<Datum>(datum),
+            Entity::GridOrder => pretty::<DatumNative>(datum),
+        }
+    }
+}
+
+fn pretty<T: TryFromPData + Debug>(datum: PlutusData) -> Option<String> {
+    T::try_from_pd(datum).map(|entity| format!("{:#?}", entity))
+}
+
+/// Extracts the datum from the given CBOR, which is either a UTxO or a datum itself.
+fn read_datum(cbor_hex: &str) -> Result<PlutusData, String> {
+    let bytes = hex::decode(cbor_hex.trim()).map_err(|err| format!("Invalid hex: {}", err))?;
+    if let Ok(utxo) = BabbageTransactionOutput::from_cbor_bytes(&bytes) {
+        return utxo
+            .datum()
+            .ok_or("UTxO carries no datum".to_string())?
+            .into_pd()
+            .ok_or("UTxO carries only a datum hash".to_string());
+    }
+    PlutusData::from_cbor_bytes(&bytes).map_err(|err| format!("Neither a UTxO nor a datum: {}", err))
+}
+
+/// Generic JSON view of raw plutus data.
+fn pd_to_json(pd: &PlutusData) -> Value {
+    match pd {
+        PlutusData::ConstrPlutusData(cpd) => json!({
+            "constructor": cpd.alternative,
+            "fields": cpd.fields.iter().map(pd_to_json).collect::<Vec<_>>(),
+        }),
+        PlutusData::Map(map) => Value::Array(
+            map.entries
+                .iter()
+                .map(|(k, v)| json!({"k": pd_to_json(k), "v": pd_to_json(v)}))
+                .collect(),
+        ),
+        PlutusData::List { list, .. } => Value::Array(list.iter().map(pd_to_json).collect()),
+        PlutusData::Integer(int) => json!({ "int": int.to_string() }),
+        PlutusData::Bytes { bytes, .. } => json!({ "bytes": hex::encode(bytes) }),
+    }
+}
+
+fn main() {
+    let args = AppArgs::parse();
+    let datum = match read_datum(&args.cbor_hex) {
+        Ok(datum) => datum,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let candidates = args.entity.map(|e| vec![e]).unwrap_or(Entity::ALL.to_vec());
+    let decoded = candidates
+        .into_iter()
+        .filter_map(|entity| entity.decode(datum.clone()).map(|repr| (entity, repr)))
+        .collect::<Vec<_>>();
+    if args.json {
+        let entities = decoded
+            .iter()
+            .map(|(entity, repr)| json!({ "entity": format!("{:?}", entity), "decoded": repr }))
+            .collect::<Vec<_>>();
+        let out = json!({ "datum": pd_to_json(&datum), "matches": entities });
+        println!("{}", serde_json::to_string_pretty(&out).unwrap());
+    } else if decoded.is_empty() {
+        println!("Datum does not match any known entity:\n{:#?}", datum);
+    } else {
+        for (entity, repr) in decoded {
+            println!("{:?}:\n{}", entity, repr);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use std::fs::File;
+use std::io::{stdout, BufWriter, Write};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use cml_chain::PolicyId;
+use cml_crypto::TransactionHash;
+use serde::Serialize;
+
+use bloom_offchain::execution_engine::execution_report::ExecutionReportsRocksDB;
+use spectrum_offchain::journal::Journal;
+use spectrum_offchain::rocks::RocksConfig;
+use spectrum_offchain_cardano::pnl::PnlJournalRocksDB;
+
+/// Works with journals written by the agent.
+#[derive(Parser)]
+#[command(name = "splash-logs")]
+struct AppArgs {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Extract entries recorded within the given period as JSON lines for offline analysis.
+    /// Safe to run alongside the agent.
+    Export {
+        #[arg(long, short, value_enum)]
+        journal: JournalKind,
+        /// Path to the journal DB.
+        #[arg(long, short)]
+        db_path: String,
+        /// UNIX time (seconds) of the beginning of the period, inclusive.
+        #[arg(long, default_value_t = 0)]
+        from: u64,
+        /// UNIX time (seconds) of the end of the period, exclusive.
+        #[arg(long, default_value_t = u64::MAX)]
+        to: u64,
+        /// File to write entries to, stdout if omitted.
+        #[arg(long, short)]
+        out: Option<String>,
+    },
+}
+
+#[derive(Copy, Clone, Debug, ValueEnum)]
+enum JournalKind {
+    ExecutionReports,
+    Pnl,
+}
+
+fn export<J>(journal: J, from: u64, to: u64, out: &mut dyn Write) -> std::io::Result<usize>
+where
+    J: Journal,
+    J::Entry: Serialize,
+{
+    let entries = journal.entries_between(from, to);
+    for entry in &entries {
+        serde_json::to_writer(&mut *out, entry)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(entries.len())
+}
+
+fn exit_on<T>(err: impl std::fmt::Display) -> T {
+    eprintln!("{}", err);
+    std::process::exit(1)
+}
+
+fn main() {
+    let args = AppArgs::parse();
+    match args.command {
+        Command::Export {
+            journal,
+            db_path,
+            from,
+            to,
+            out,
+        } => {
+            let mut out: Box<dyn Write> = match out {
+                Some(path) => Box::new(BufWriter::new(File::create(path).unwrap_or_else(exit_on))),
+                None => Box::new(BufWriter::new(stdout())),
+            };
+            let conf = RocksConfig { db_path };
+            let exported = match journal {
+                JournalKind::ExecutionReports => export(
+                    ExecutionReportsRocksDB::<PolicyId, TransactionHash>::read_only(conf)
+                        .unwrap_or_else(exit_on),
+                    from,
+                    to,
+                    &mut out,
+                ),
+                JournalKind::Pnl => export(
+                    PnlJournalRocksDB::read_only(conf).unwrap_or_else(exit_on),
+                    from,
+                    to,
+                    &mut out,
+                ),
+            };
+            match exported {
+                Ok(n) => eprintln!("Exported {} entries", n),
+                Err(err) => exit_on(err),
+            }
+        }
+    }
+}
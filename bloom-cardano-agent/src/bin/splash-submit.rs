@@ -0,0 +1,367 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use cml_chain::address::{Address, EnterpriseAddress};
+use cml_chain::assets::MultiAsset;
+use cml_chain::builders::input_builder::SingleInputBuilder;
+use cml_chain::builders::output_builder::{SingleOutputBuilderResult, TransactionOutputBuilder};
+use cml_chain::builders::tx_builder::ChangeSelectionAlgo;
+use cml_chain::certs::Credential;
+use cml_chain::plutus::PlutusData;
+use cml_chain::transaction::{DatumOption, Transaction, TransactionOutput};
+use cml_chain::Value;
+use cml_core::serialization::Serialize;
+use cml_crypto::ScriptHash;
+use num_rational::Ratio;
+use pallas_network::miniprotocols::localtxsubmission::Response;
+
+use bloom_offchain_cardano::orders::limit::{beacon_from_oref, BeaconScheme, Datum};
+use cardano_explorer::{CardanoNetwork, Maestro};
+use cardano_submit_api::client::LocalTxSubmissionClient;
+use spectrum_cardano_lib::address::{PlutusAddress, PlutusCredential};
+use spectrum_cardano_lib::constants::BABBAGE_ERA_ID;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, COINS_PER_UTXO_BYTE};
+use spectrum_cardano_lib::{AssetClass, NetworkId, OutputRef, TaggedAssetClass};
+use spectrum_offchain::tx_prover::TxProver;
+use spectrum_offchain_cardano::creds::operator_creds;
+use spectrum_offchain_cardano::data::deposit::OnChainDepositConfig;
+use spectrum_offchain_cardano::data::redeem::OnChainRedeemConfig;
+use spectrum_offchain_cardano::deployment::DeployedValidators;
+use spectrum_offchain_cardano::prover::operator::OperatorProver;
+
+/// Builds and submits orders on behalf of the given key.
+/// Meant for end-to-end testing of the executor.
+#[derive(Parser)]
+#[command(name = "splash-submit")]
+struct AppArgs {
+    /// Path to the deployment JSON configuration file.
+    #[arg(long, short)]
+    deployment_path: String,
+    /// Path to the file holding Maestro API key.
+    #[arg(long)]
+    maestro_key_path: String,
+    /// Network ID: 0 for testnets, 1 for mainnet.
+    #[arg(long)]
+    network_id: u8,
+    /// Path to the node socket.
+    #[arg(long)]
+    node_path: String,
+    /// Network magic of the node.
+    #[arg(long)]
+    node_magic: u64,
+    /// Bech32-encoded BIP32 private key. Orders are paid from and owned by its enterprise address.
+    #[arg(long, short)]
+    key: String,
+    /// Print the signed TX instead of submitting it.
+    #[arg(long)]
+    dry_run: bool,
+    /// UTxO to pay for the order from, `<tx_hash_hex>#<index>`. May be repeated.
+    /// All UTxOs at the own address are used if omitted.
+    #[arg(long = "utxo")]
+    utxos: Vec<OutputRef>,
+    #[command(subcommand)]
+    order: OrderArgs,
+}
+
+#[derive(Subcommand)]
+enum OrderArgs {
+    /// Limit order selling `input` for `output`.
+    Limit {
+        /// Asset to sell, `Native` or `<policy_id_hex>.<asset_name_hex>`.
+        #[arg(long)]
+        input: String,
+        /// Asset to buy, `Native` or `<policy_id_hex>.<asset_name_hex>`.
+        #[arg(long)]
+        output: String,
+        /// Amount of `input` to sell.
+        #[arg(long)]
+        amount: u64,
+        /// Worst acceptable price in `output` per unit of `input`, `num/denom`.
+        #[arg(long)]
+        price: String,
+        #[arg(long, default_value_t = 1000)]
+        min_marginal_output: u64,
+        #[arg(long, default_value_t = 500_000)]
+        cost_per_ex_step: u64,
+        /// Number of execution steps to fund.
+        #[arg(long, default_value_t = 4)]
+        max_ex_steps: u64,
+        #[arg(long, default_value_t = 500_000)]
+        fee: u64,
+    },
+    /// Deposit of liquidity into a pool.
+    Deposit {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount_x: u64,
+        #[arg(long)]
+        amount_y: u64,
+        #[arg(long, default_value_t = 1_500_000)]
+        ex_fee: u64,
+        /// ADA returned along with LQ tokens.
+        #[arg(long, default_value_t = 2_000_000)]
+        collateral_ada: u64,
+    },
+    /// Redeem of liquidity from a pool.
+    Redeem {
+        #[command(flatten)]
+        pool: PoolArgs,
+        #[arg(long)]
+        amount_lq: u64,
+        #[arg(long, default_value_t = 1_500_000)]
+        ex_fee: u64,
+    },
+}
+
+#[derive(Args)]
+struct PoolArgs {
+    #[arg(long, value_enum)]
+    family: PoolFamily,
+    #[arg(long)]
+    pool_nft: String,
+    #[arg(long)]
+    x: String,
+    #[arg(long)]
+    y: String,
+    #[arg(long)]
+    lq: String,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum PoolFamily {
+    ConstFn,
+    ConstFnFeeSwitch,
+    Balance,
+    Stable,
+}
+
+impl PoolFamily {
+    fn deposit_script(self, deployment: &DeployedValidators) -> ScriptHash {
+        match self {
+            PoolFamily::ConstFn => deployment.const_fn_pool_deposit.hash,
+            PoolFamily::ConstFnFeeSwitch => deployment.const_fn_fee_switch_pool_deposit.hash,
+            PoolFamily::Balance => deployment.balance_fn_pool_deposit.hash,
+            PoolFamily::Stable => deployment.stable_fn_pool_t2t_deposit.hash,
+        }
+    }
+
+    fn redeem_script(self, deployment: &DeployedValidators) -> ScriptHash {
+        match self {
+            PoolFamily::ConstFn => deployment.const_fn_pool_redeem.hash,
+            PoolFamily::ConstFnFeeSwitch => deployment.const_fn_fee_switch_pool_redeem.hash,
+            PoolFamily::Balance => deployment.balance_fn_pool_redeem.hash,
+            PoolFamily::Stable => deployment.stable_fn_pool_t2t_redeem.hash,
+        }
+    }
+}
+
+const UTXO_LOOKUP_LIMIT: u16 = 50;
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(AppArgs::parse()).await {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: AppArgs) -> Result<(), String> {
+    let raw_deployment = std::fs::read_to_string(&args.deployment_path).map_err(|err| err.to_string())?;
+    let deployment: DeployedValidators =
+        serde_json::from_str(&raw_deployment).map_err(|err| err.to_string())?;
+    let network_id = NetworkId::from(args.network_id);
+    let (sk, owner, own_address, _) = operator_creds(&args.key, network_id);
+    let own_address = own_address.address();
+    let explorer = Maestro::new(&args.maestro_key_path, network_id.into())
+        .await
+        .map_err(|err| err.to_string())?;
+    let utxos = explorer
+        .utxos_by_address(own_address.clone(), 0, UTXO_LOOKUP_LIMIT)
+        .await;
+    let utxos = if args.utxos.is_empty() {
+        utxos
+    } else {
+        let selected = utxos
+            .into_iter()
+            .filter(|utxo| args.utxos.contains(&OutputRef::from(utxo.input.clone())))
+            .collect::<Vec<_>>();
+        if let Some(missing) = args.utxos.iter().find(|oref| {
+            !selected
+                .iter()
+                .any(|utxo| OutputRef::from(utxo.input.clone()) == **oref)
+        }) {
+            return Err(format!("UTxO {} not found at the own address", missing));
+        }
+        selected
+    };
+    // Beacon of a limit order is derived from the first input of the TX.
+    let first_input = utxos
+        .iter()
+        .map(|utxo| OutputRef::from(utxo.input.clone()))
+        .min()
+        .ok_or(format!("No UTxOs at {}", own_address.to_bech32(None).unwrap()))?;
+
+    let owner_pkh = owner.0;
+    let (script, datum, assets) = match args.order {
+        OrderArgs::Limit {
+            input,
+            output,
+            amount,
+            price,
+            min_marginal_output,
+            cost_per_ex_step,
+            max_ex_steps,
+            fee,
+        } => {
+            let input = parse_asset(&input)?;
+            let datum = Datum {
+                beacon: beacon_from_oref(first_input, BeaconScheme::V1),
+                input,
+                tradable_input: amount,
+                cost_per_ex_step,
+                min_marginal_output,
+                output: parse_asset(&output)?,
+                base_price: parse_price(&price)?,
+                fee,
+                redeemer_address: PlutusAddress {
+                    payment_cred: PlutusCredential::PubKey(owner_pkh),
+                    stake_cred: None,
+                },
+                cancellation_pkh: owner_pkh,
+                permitted_executors: vec![],
+                fee_asset: AssetClass::Native,
+            };
+            let lovelace = fee + cost_per_ex_step * max_ex_steps;
+            let assets = vec![(AssetClass::Native, lovelace), (input, amount)];
+            (deployment.limit_order.hash, datum.into_pd(), assets)
+        }
+        OrderArgs::Deposit {
+            pool,
+            amount_x,
+            amount_y,
+            ex_fee,
+            collateral_ada,
+        } => {
+            let conf = OnChainDepositConfig {
+                pool_nft: TaggedAssetClass::new(parse_asset(&pool.pool_nft)?),
+                token_x: TaggedAssetClass::new(parse_asset(&pool.x)?),
+                token_y: TaggedAssetClass::new(parse_asset(&pool.y)?),
+                token_lq: TaggedAssetClass::new(parse_asset(&pool.lq)?),
+                ex_fee,
+                reward_pkh: owner_pkh,
+                reward_stake_pkh: None,
+                collateral_ada,
+            };
+            let assets = vec![
+                (AssetClass::Native, ex_fee + collateral_ada),
+                (conf.token_x.untag(), amount_x),
+                (conf.token_y.untag(), amount_y),
+            ];
+            (pool.family.deposit_script(&deployment), conf.into_pd(), assets)
+        }
+        OrderArgs::Redeem {
+            pool,
+            amount_lq,
+            ex_fee,
+        } => {
+            let conf = OnChainRedeemConfig {
+                pool_nft: TaggedAssetClass::new(parse_asset(&pool.pool_nft)?),
+                token_x: TaggedAssetClass::new(parse_asset(&pool.x)?),
+                token_y: TaggedAssetClass::new(parse_asset(&pool.y)?),
+                token_lq: TaggedAssetClass::new(parse_asset(&pool.lq)?),
+                ex_fee,
+                reward_pkh: owner_pkh,
+                reward_stake_pkh: None,
+            };
+            let assets = vec![(AssetClass::Native, ex_fee), (conf.token_lq.untag(), amount_lq)];
+            (pool.family.redeem_script(&deployment), conf.into_pd(), assets)
+        }
+    };
+
+    let order_address = EnterpriseAddress::new(args.network_id, Credential::new_script(script)).to_address();
+    let order_output = order_output(order_address, datum, assets)?;
+
+    let mut tx_builder = constant_tx_builder();
+    for utxo in utxos {
+        let input = SingleInputBuilder::new(utxo.input, utxo.output)
+            .payment_key()
+            .map_err(|err| format!("{:?}", err))?;
+        tx_builder.add_input(input).map_err(|err| format!("{:?}", err))?;
+    }
+    tx_builder
+        .add_output(SingleOutputBuilderResult::new(order_output))
+        .map_err(|err| format!("{:?}", err))?;
+    let signed_tx_builder = tx_builder
+        .build(ChangeSelectionAlgo::Default, &own_address)
+        .map_err(|err| format!("{:?}", err))?;
+    let tx: Transaction = (*OperatorProver::new(&sk).prove(signed_tx_builder)).clone();
+    let tx_hash = hash_transaction_canonical(&tx.body);
+
+    if args.dry_run {
+        println!("{}", hex::encode(tx.to_cbor_bytes()));
+        return Ok(());
+    }
+    let mut client =
+        LocalTxSubmissionClient::<BABBAGE_ERA_ID, Transaction>::init(args.node_path, args.node_magic)
+            .await
+            .map_err(|err| err.to_string())?;
+    match client.submit_tx(tx).await.map_err(|err| err.to_string())? {
+        Response::Accepted => println!("Submitted {}", tx_hash.to_hex()),
+        Response::Rejected(errors) => return Err(format!("TX {} rejected: {:?}", tx_hash.to_hex(), errors)),
+    }
+    client.close().await;
+    Ok(())
+}
+
+/// Output locking the given assets along with the datum.
+/// ADA is topped up to the minimum required by the ledger.
+fn order_output(
+    address: Address,
+    datum: PlutusData,
+    assets: Vec<(AssetClass, u64)>,
+) -> Result<TransactionOutput, String> {
+    let mut lovelace = 0;
+    let mut ma = MultiAsset::new();
+    for (asset, amount) in assets {
+        match asset.into_token() {
+            Some((policy, name)) => {
+                let name = name.into();
+                let prev = ma.get(&policy, &name).unwrap_or(0);
+                ma.set(policy, name, prev + amount);
+            }
+            None => lovelace += amount,
+        }
+    }
+    let output = || {
+        TransactionOutputBuilder::new()
+            .with_address(address.clone())
+            .with_data(DatumOption::new_datum(datum.clone()))
+            .next()
+    };
+    let min_required = output()
+        .and_then(|out| out.with_asset_and_min_required_coin(ma.clone(), COINS_PER_UTXO_BYTE))
+        .and_then(|out| out.build())
+        .map_err(|err| format!("{:?}", err))?
+        .output
+        .amount()
+        .coin;
+    output()
+        .and_then(|out| out.with_value(Value::new(lovelace.max(min_required), ma)).build())
+        .map(|out| out.output)
+        .map_err(|err| format!("{:?}", err))
+}
+
+fn parse_asset(raw: &str) -> Result<AssetClass, String> {
+    AssetClass::try_from(raw).map_err(|err| format!("{}: {}", err, raw))
+}
+
+fn parse_price(raw: &str) -> Result<Ratio<u128>, String> {
+    let (numer, denom) = raw.split_once('/').unwrap_or((raw, "1"));
+    let numer = numer.trim().parse::<u128>().map_err(|err| err.to_string())?;
+    let denom = denom.trim().parse::<u128>().map_err(|err| err.to_string())?;
+    if denom == 0 {
+        return Err("Price denominator must be positive".to_string());
+    }
+    Ok(Ratio::new(numer, denom))
+}
@@ -1,13 +1,39 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use cml_core::Slot;
+use cml_crypto::{Ed25519KeyHash, ScriptHash};
+use num_rational::Ratio;
 
+use bloom_offchain::execution_engine::circuit_breaker::CircuitBreakerConfig;
 use bloom_offchain::execution_engine::liquidity_book;
+use bloom_offchain::execution_engine::liquidity_book::config::{
+    MakerSelection, MatchingMode, RecipeLimits, StashPolicy, TxSizeCap,
+};
+use bloom_offchain::execution_engine::pool_stats::EpochSchedule;
+use bloom_offchain::execution_engine::reserve_history::ReserveHistoryConfig;
+use bloom_offchain::pair_registry::ListingTarget;
 use bloom_offchain::partitioning::Partitioning;
+use bloom_offchain_cardano::execution_engine::babel_fee::MinConversionRate;
+use bloom_offchain_cardano::execution_engine::exposure::ExposureLimit;
+use bloom_offchain_cardano::market_making::MarketMakingConfig;
 use cardano_chain_sync::client::Point;
 use spectrum_cardano_lib::ex_units::ExUnits;
-use spectrum_cardano_lib::NetworkId;
+use spectrum_cardano_lib::{AssetClass, NetworkId};
+use spectrum_offchain::journal::JournalRetention;
+use spectrum_offchain::kv_backend::KvBackendConfig;
+use spectrum_offchain::network::RetryPolicy;
+use spectrum_offchain_cardano::creds::OperatorKeySource;
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::deployment::DeployedValidatorRef;
+use spectrum_offchain_cardano::fee_tuning::FeeTuningConfig;
 use spectrum_offchain_cardano::node::NodeConfig;
+use spectrum_offchain_cardano::sweep::ResidualSweepConfig;
+use spectrum_offchain_cardano::treasury::{TreasuryAddress, TreasuryWithdrawalConfig};
+use spectrum_offchain_cardano::vesting::VestingClaimConfig;
+
+use algebra_core::semigroup::Semigroup;
 
 use crate::integrity::{CheckIntegrity, IntegrityViolations};
 
@@ -17,8 +43,18 @@ use crate::integrity::{CheckIntegrity, IntegrityViolations};
 pub struct AppConfig<'a> {
     pub chain_sync: ChainSyncConfig<'a>,
     pub node: NodeConfig<'a>,
+    /// Additional nodes every TX is broadcast to along with the main one.
+    #[serde(default)]
+    pub extra_submission_endpoints: Vec<NodeConfig<'a>>,
     pub tx_submission_buffer_size: usize,
-    pub operator_key: &'a str, //todo: store encrypted
+    /// Backoff for transient TX submission failures.
+    #[serde(default)]
+    pub tx_submission_retry: RetryPolicy,
+    pub operator_key: OperatorKeySource,
+    /// Key being rotated out. While set, orders bound to either key are executed
+    /// and every TX is signed by both.
+    #[serde(default)]
+    pub retiring_operator_key: Option<OperatorKeySource>,
     pub cardano_finalization_delay: Duration,
     pub backlog_capacity: u32,
     pub network_id: NetworkId,
@@ -28,6 +64,116 @@ pub struct AppConfig<'a> {
     pub mempool_buffering_duration: Duration,
     pub ledger_buffering_duration: Duration,
     pub partitioning: Partitioning,
+    /// Chain sync, entity parsing, persistence and APIs run as usual,
+    /// while no TXs are ever built, signed or submitted.
+    #[serde(default)]
+    pub read_only: bool,
+    /// Address to serve liveness/readiness probes on.
+    pub health_check_addr: Option<SocketAddr>,
+    /// Matchmaking is paused while chain sync lags behind the tip by more than this number of slots.
+    pub max_sync_lag_slots: u64,
+    /// Where states of pools and orders are persisted along with TXs awaiting submission outcome.
+    pub state_db: KvBackendConfig,
+    /// Where confirmed specialized orders are persisted so that the backlog survives restarts.
+    pub backlog_journal_db_path: &'a str,
+    /// How long a TX left in-flight by a previous run may block matchmaking in its pair.
+    pub pending_tx_ttl: Duration,
+    /// Unconfirmed states of pools and orders are dropped unless confirmed within this number of slots.
+    pub unconfirmed_ttl_slots: u64,
+    /// Pools which keep breaking recipes are excluded from matchmaking until retested.
+    pub pool_quarantine: PoolQuarantineConfig<'a>,
+    /// Where per-order execution reports are persisted.
+    pub execution_reports_db_path: &'a str,
+    /// Retention of execution reports and PnL entries, kept forever if not set.
+    #[serde(default)]
+    pub journal_retention: Option<JournalRetention>,
+    /// Epochs per-pool execution stats are aggregated by.
+    pub epoch_schedule: EpochSchedule,
+    /// Endpoint order owners' fill/removal notifications are posted to, disabled if not set.
+    #[serde(default)]
+    pub fill_webhook_url: Option<String>,
+    /// Endpoint pool lifecycle events (created, paused, drained, ...) are posted to, disabled if not set.
+    #[serde(default)]
+    pub pool_lifecycle_webhook_url: Option<String>,
+    /// Endpoint UTxOs at protocol validators which failed to parse are posted to, disabled if not set.
+    #[serde(default)]
+    pub invalid_entity_webhook_url: Option<String>,
+    /// Pausing of matching in pairs whose pools quote anomalous prices, disabled if not set.
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// Endpoint circuit breaker trips are posted to, disabled if not set.
+    #[serde(default)]
+    pub circuit_breaker_webhook_url: Option<String>,
+    /// Per-block reserve history of pools recent volatility of pairs is estimated from, disabled if not set.
+    #[serde(default)]
+    pub reserve_history: Option<ReserveHistoryConfig>,
+    /// Where the set of discovered pairs is published, disabled if not set.
+    #[serde(default)]
+    pub pair_listing: Option<PairListingConfig>,
+    /// Address to serve trade quotes and execution reports on, disabled if not set.
+    #[serde(default)]
+    pub quote_api_addr: Option<SocketAddr>,
+    /// Matching of limit orders against off-chain quotes of market makers, disabled if not set.
+    #[serde(default)]
+    pub rfq: Option<RfqConfig>,
+    /// UTxOs validators read external state from, e.g. oracle price feeds.
+    #[serde(default)]
+    pub reference_inputs: Vec<RefInputConfig>,
+    /// Rebalancing of pairs across executor partitions according to observed load, disabled if not set.
+    #[serde(default)]
+    pub partition_rebalancing: Option<PartitionRebalancingConfig<'a>>,
+    /// Per-asset bounds on what the operator accumulates through execution, unbounded if not set.
+    #[serde(default)]
+    pub exposure_limits: Vec<ExposureLimitConfig>,
+    /// Minimal batcher fee of classical orders tuned to recent execution costs, static order fees are
+    /// accepted if not set.
+    #[serde(default)]
+    pub fee_tuning: Option<FeeTuningConfig>,
+    /// Tokens the operator accepts fees in from orders trading tokens for tokens, none if not set.
+    #[serde(default)]
+    pub babel_fees: Vec<BabelFeeConfig>,
+    /// Consolidation of execution residuals into the main funding wallet, disabled if not set.
+    #[serde(default)]
+    pub residual_sweep: Option<ResidualSweepAgentConfig<'a>>,
+    /// Withdrawal of protocol fees accumulated by fee-switch pools, disabled if not set.
+    #[serde(default)]
+    pub treasury: Option<TreasuryAgentConfig>,
+    /// Claiming of unlocked vesting locks on behalf of their beneficiaries, disabled if not set.
+    #[serde(default)]
+    pub vesting: Option<VestingAgentConfig>,
+    /// Indexer books and backlogs are seeded from before chain sync starts, disabled if not set.
+    #[serde(default)]
+    pub warm_start: Option<WarmStartConfig>,
+    /// Quoting of operator inventory in configured pairs, disabled if not set.
+    /// Index prices are taken from the reserve history, so it must be configured as well.
+    #[serde(default)]
+    pub market_making: Option<MarketMakingAgentConfig>,
+}
+
+impl<'a> AppConfig<'a> {
+    pub fn ref_inputs_by_validator(&self) -> HashMap<ScriptHash, Vec<AssetClass>> {
+        let mut by_validator: HashMap<ScriptHash, Vec<AssetClass>> = HashMap::new();
+        for conf in &self.reference_inputs {
+            for validator in &conf.required_by {
+                by_validator.entry(*validator).or_default().push(conf.nft);
+            }
+        }
+        by_validator
+    }
+
+    pub fn exposure_limits(&self) -> HashMap<AssetClass, ExposureLimit> {
+        self.exposure_limits
+            .iter()
+            .map(|conf| (conf.asset, conf.limit))
+            .collect()
+    }
+
+    pub fn babel_fees(&self) -> HashMap<AssetClass, MinConversionRate> {
+        self.babel_fees
+            .iter()
+            .map(|conf| (conf.asset, conf.min_rate))
+            .collect()
+    }
 }
 
 impl<'a> CheckIntegrity for AppConfig<'a> {
@@ -37,15 +183,208 @@ impl<'a> CheckIntegrity for AppConfig<'a> {
             .assigned_partitions
             .iter()
             .all(|p| *p < self.partitioning.num_partitions_total)
+            && !self.partitioning.assigned_partitions.is_empty()
         {
             IntegrityViolations::empty()
         } else {
             IntegrityViolations::one("Bad partitioning".to_string())
         };
+        let buffer_violations = if self.tx_submission_buffer_size > 0 && self.channel_buffer_size > 0 {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Buffer sizes must be positive".to_string())
+        };
+        let retry_violations = if self.tx_submission_retry.base_delay <= self.tx_submission_retry.max_delay {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Retry base delay exceeds max delay".to_string())
+        };
+        let quarantine_violations = if self.pool_quarantine.max_consecutive_failures > 0 {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Pools cannot be quarantined after 0 failures".to_string())
+        };
+        let epoch_violations = if self.epoch_schedule.epoch_length_slots > 0 {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Epoch length must be positive".to_string())
+        };
+        let reserve_history_violations = match self.reserve_history {
+            Some(conf) if conf.retained_blocks == 0 => {
+                IntegrityViolations::one("Reserve history must retain at least one block".to_string())
+            }
+            _ => IntegrityViolations::empty(),
+        };
+        let market_making_violations = match &self.market_making {
+            Some(_) if self.reserve_history.is_none() => {
+                IntegrityViolations::one("Market making requires reserve history".to_string())
+            }
+            Some(conf) => conf.check_integrity(),
+            None => IntegrityViolations::empty(),
+        };
         partitioning_violations
+            .combine(buffer_violations)
+            .combine(retry_violations)
+            .combine(quarantine_violations)
+            .combine(epoch_violations)
+            .combine(reserve_history_violations)
+            .combine(market_making_violations)
+            .combine(
+                self.rfq
+                    .as_ref()
+                    .map_or(IntegrityViolations::empty(), |rfq| rfq.check_integrity()),
+            )
+            .combine(self.execution.check_integrity())
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RfqConfig {
+    /// Address quotes are accepted on.
+    pub listen_addr: SocketAddr,
+    /// Quotes firm for longer than this are rejected.
+    pub max_quote_ttl: Duration,
+    pub makers: Vec<RfqMaker>,
+}
+
+/// Market maker allowed to stream quotes.
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RfqMaker {
+    pub key_hash: Ed25519KeyHash,
+    /// Endpoint TXs spending the maker's funds are posted to for co-signature.
+    pub cosign_endpoint: String,
+}
+
+impl CheckIntegrity for RfqConfig {
+    fn check_integrity(&self) -> IntegrityViolations {
+        if self.makers.is_empty() {
+            IntegrityViolations::one("No RFQ makers configured".to_string())
+        } else {
+            IntegrityViolations::empty()
+        }
     }
 }
 
+/// UTxO attached as a reference input to every TX spending outputs of the given validators.
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefInputConfig {
+    /// NFT identifying the UTxO.
+    pub nft: AssetClass,
+    pub required_by: Vec<ScriptHash>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResidualSweepAgentConfig<'a> {
+    /// Where sweeps are journaled as PnL entries.
+    pub pnl_journal_db_path: &'a str,
+    pub sweep: ResidualSweepConfig,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TreasuryAgentConfig {
+    pub treasury_address: TreasuryAddress,
+    /// Staking script the DAO authorizes withdrawals with.
+    /// Only pools listing it among their DAO policies are withdrawn from.
+    pub dao_authority: DeployedValidatorRef,
+    pub withdrawal: TreasuryWithdrawalConfig,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VestingAgentConfig {
+    /// Vesting validator, deployed separately from the DEX validators.
+    pub validator: DeployedValidatorRef,
+    pub claim: VestingClaimConfig,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketMakingAgentConfig {
+    pub pairs: Vec<QuotedPairConfig>,
+    #[serde(flatten)]
+    pub quoting: MarketMakingConfig,
+}
+
+/// Pair quoted by the operator along with inventory allocated to quoting in it.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotedPairConfig {
+    /// Base and quote assets in canonical order.
+    pub pair: PairId,
+    pub base: u64,
+    pub quote: u64,
+}
+
+impl CheckIntegrity for MarketMakingAgentConfig {
+    fn check_integrity(&self) -> IntegrityViolations {
+        if self.pairs.iter().all(|p| {
+            let [base, quote] = p.pair.assets();
+            PairId::canonical(base, quote) == p.pair
+        }) {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Quoted pairs must be in canonical order".to_string())
+        }
+    }
+}
+
+/// Source of the snapshot of UTxOs locked by protocol validators.
+/// Only indexers reporting the point they are synchronized to are supported,
+/// since chain sync resumes from it.
+#[derive(serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum WarmStartConfig {
+    Kupo { url: String },
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExposureLimitConfig {
+    pub asset: AssetClass,
+    #[serde(flatten)]
+    pub limit: ExposureLimit,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BabelFeeConfig {
+    pub asset: AssetClass,
+    pub min_rate: MinConversionRate,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairListingConfig {
+    pub target: ListingTarget,
+    /// How often the listing is checked for changes.
+    pub publish_interval: Duration,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartitionRebalancingConfig<'a> {
+    /// Where the planned assignment of pairs to partitions is persisted, it takes effect on restart.
+    pub db_path: &'a str,
+    /// Window load of pairs is measured within before the assignment is replanned.
+    pub interval: Duration,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolQuarantineConfig<'a> {
+    /// Where quarantined pools are persisted.
+    pub db_path: &'a str,
+    /// Consecutive script failures of recipes involving a pool after which it is quarantined.
+    pub max_consecutive_failures: u32,
+    /// How long a pool stays in quarantine before it is retested.
+    pub retest_after: Duration,
+}
+
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ChainSyncConfig<'a> {
@@ -53,12 +392,18 @@ pub struct ChainSyncConfig<'a> {
     pub replay_from_point: Option<Point>,
     pub disable_rollbacks_until: Slot,
     pub db_path: &'a str,
+    /// Number of TXs prepared for handlers concurrently on worker threads.
+    /// TXs are prepared one by one on the event loop if not set.
+    #[serde(default)]
+    pub parallelism: Option<usize>,
 }
 
 #[derive(Copy, Clone, serde::Deserialize)]
 pub struct ExecutionCap {
     pub soft: ExUnits,
     pub hard: ExUnits,
+    #[serde(rename = "txSize", default)]
+    pub tx_size: Option<TxSizeCap>,
 }
 
 impl From<ExecutionCap> for liquidity_book::config::ExecutionCap<ExUnits> {
@@ -66,23 +411,249 @@ impl From<ExecutionCap> for liquidity_book::config::ExecutionCap<ExUnits> {
         Self {
             soft: value.soft,
             hard: value.hard,
+            tx_size: value.tx_size,
         }
     }
 }
 
+/// Execution cap applied to a particular pair instead of the default one.
+#[derive(Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionCapOverride {
+    pub base: AssetClass,
+    pub quote: AssetClass,
+    pub execution_cap: ExecutionCap,
+}
+
+/// How the price overlapping orders are matched at is picked within the overlap.
+#[derive(Copy, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SettlementPolicy {
+    /// Orders are settled at the pool price clamped into the overlap, or the middle of it.
+    NoBias,
+    /// The price is shifted in favour of the order paying the higher operator fee.
+    #[serde(rename_all = "camelCase")]
+    FeeBias { max_bias_bps: u64 },
+    /// The price is shifted against the order the match is made for, in favour of the resting one.
+    #[serde(rename_all = "camelCase")]
+    TakerPays { max_bias_bps: u64 },
+}
+
+impl SettlementPolicy {
+    fn max_bias_bps(&self) -> Option<u64> {
+        match self {
+            SettlementPolicy::NoBias => None,
+            SettlementPolicy::FeeBias { max_bias_bps } | SettlementPolicy::TakerPays { max_bias_bps } => {
+                Some(*max_bias_bps)
+            }
+        }
+    }
+}
+
+impl From<SettlementPolicy> for liquidity_book::config::SettlementPolicy {
+    fn from(value: SettlementPolicy) -> Self {
+        match value {
+            SettlementPolicy::NoBias => Self::NoBias,
+            SettlementPolicy::FeeBias { max_bias_bps } => Self::FeeBias {
+                max_bias: Ratio::new(max_bias_bps as u128, BPS_DENOM),
+            },
+            SettlementPolicy::TakerPays { max_bias_bps } => Self::TakerPays {
+                max_bias: Ratio::new(max_bias_bps as u128, BPS_DENOM),
+            },
+        }
+    }
+}
+
+/// Settlement policy applied to a particular pair instead of the default one.
+#[derive(Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementPolicyOverride {
+    pub base: AssetClass,
+    pub quote: AssetClass,
+    pub settlement_policy: SettlementPolicy,
+}
+
+/// Pair matched in periodic batch auctions instead of continuously.
 #[derive(Copy, Clone, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct BatchAuctionPair {
+    pub base: AssetClass,
+    pub quote: AssetClass,
+    /// Length of a window orders are accumulated for before being cleared.
+    pub window_secs: u64,
+}
+
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ExecutionConfig {
     pub execution_cap: ExecutionCap,
+    #[serde(default)]
+    pub execution_cap_overrides: Vec<ExecutionCapOverride>,
     /// Order-order matchmaking allowed.
     pub o2o_allowed: bool,
+    /// Max move of a pool's spot price a single recipe may cause, in basis points.
+    pub max_price_impact_bps: Option<u64>,
+    /// Pools quoting within this distance (in basis points) from the best price are ranked
+    /// by failure rate, execution cost and liquidity instead of price.
+    pub pool_price_tolerance_bps: Option<u64>,
+    /// Max share of a pool's reserves (in basis points) a single recipe may swap into it.
+    pub max_pool_reserves_share_bps: Option<u64>,
+    /// Number of matchmaking attempts orders with unsatisfiable limits sit out for,
+    /// they are retried as soon as the recipe settles if not set.
+    #[serde(default)]
+    pub stash_ttl_attempts: Option<u32>,
+    /// Max number of orders filled by a single recipe, unbounded if not set.
+    #[serde(default)]
+    pub max_fills_per_recipe: Option<usize>,
+    /// Max number of pools swapped against in a single recipe, unbounded if not set.
+    #[serde(default)]
+    pub max_swaps_per_recipe: Option<usize>,
+    /// Recipes filling fewer orders are discarded, 1 if not set.
+    #[serde(default)]
+    pub min_fills_per_recipe: Option<usize>,
+    /// Orders opposite to the flow already routed through a pool of a recipe are netted
+    /// against that pool in the same recipe, disabled if not set.
+    #[serde(default)]
+    pub net_opposite_flows: bool,
+    /// Pairs matched in periodic batch auctions, all pairs are matched continuously if not set.
+    #[serde(default)]
+    pub batch_auction_pairs: Vec<BatchAuctionPair>,
+    /// How overlapping orders are settled, fee-biased by up to 3% if not set.
+    #[serde(default)]
+    pub settlement_policy: Option<SettlementPolicy>,
+    #[serde(default)]
+    pub settlement_policy_overrides: Vec<SettlementPolicyOverride>,
 }
 
+impl CheckIntegrity for ExecutionCap {
+    fn check_integrity(&self) -> IntegrityViolations {
+        let ex_units = if self.soft.mem <= self.hard.mem && self.soft.steps <= self.hard.steps {
+            IntegrityViolations::empty()
+        } else {
+            IntegrityViolations::one("Soft execution cap exceeds hard one".to_string())
+        };
+        let tx_size = match self.tx_size {
+            Some(cap) if cap.base_size >= cap.max_tx_size => {
+                IntegrityViolations::one("Base TX size exceeds max TX size".to_string())
+            }
+            _ => IntegrityViolations::empty(),
+        };
+        ex_units.combine(tx_size)
+    }
+}
+
+impl CheckIntegrity for ExecutionConfig {
+    fn check_integrity(&self) -> IntegrityViolations {
+        let bps_violations = [
+            ("maxPriceImpactBps", self.max_price_impact_bps),
+            ("poolPriceToleranceBps", self.pool_price_tolerance_bps),
+            ("maxPoolReservesShareBps", self.max_pool_reserves_share_bps),
+        ]
+        .into_iter()
+        .filter(|(_, bps)| bps.map_or(false, |bps| bps as u128 > BPS_DENOM))
+        .fold(IntegrityViolations::empty(), |acc, (field, _)| {
+            acc.combine(IntegrityViolations::one(format!(
+                "{} exceeds {}",
+                field, BPS_DENOM
+            )))
+        });
+        let fills_violations = match (self.min_fills_per_recipe, self.max_fills_per_recipe) {
+            (Some(min_fills), Some(max_fills)) if min_fills > max_fills => {
+                IntegrityViolations::one("minFillsPerRecipe exceeds maxFillsPerRecipe".to_string())
+            }
+            _ => IntegrityViolations::empty(),
+        };
+        let auction_violations = if self.batch_auction_pairs.iter().any(|p| p.window_secs == 0) {
+            IntegrityViolations::one("windowSecs of a batch auction pair is 0".to_string())
+        } else {
+            IntegrityViolations::empty()
+        };
+        let bias_violations = if self
+            .settlement_policy
+            .iter()
+            .chain(
+                self.settlement_policy_overrides
+                    .iter()
+                    .map(|ov| &ov.settlement_policy),
+            )
+            .any(|policy| policy.max_bias_bps().map_or(false, |bps| bps as u128 > BPS_DENOM))
+        {
+            IntegrityViolations::one(format!("maxBiasBps exceeds {}", BPS_DENOM))
+        } else {
+            IntegrityViolations::empty()
+        };
+        self.execution_cap_overrides.iter().fold(
+            self.execution_cap
+                .check_integrity()
+                .combine(bps_violations)
+                .combine(fills_violations)
+                .combine(auction_violations)
+                .combine(bias_violations),
+            |acc, ov| acc.combine(ov.execution_cap.check_integrity()),
+        )
+    }
+}
+
+impl ExecutionConfig {
+    pub fn execution_caps_by_pair(&self) -> HashMap<PairId, liquidity_book::config::ExecutionCap<ExUnits>> {
+        self.execution_cap_overrides
+            .iter()
+            .map(|ov| (PairId::canonical(ov.base, ov.quote), ov.execution_cap.into()))
+            .collect()
+    }
+
+    pub fn settlement_policies_by_pair(&self) -> HashMap<PairId, liquidity_book::config::SettlementPolicy> {
+        self.settlement_policy_overrides
+            .iter()
+            .map(|ov| (PairId::canonical(ov.base, ov.quote), ov.settlement_policy.into()))
+            .collect()
+    }
+
+    pub fn matching_modes_by_pair(&self) -> HashMap<PairId, MatchingMode> {
+        self.batch_auction_pairs
+            .iter()
+            .map(|p| {
+                (
+                    PairId::canonical(p.base, p.quote),
+                    MatchingMode::BatchAuction {
+                        window_secs: p.window_secs,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+const BPS_DENOM: u128 = 10_000;
+
 impl From<ExecutionConfig> for liquidity_book::config::ExecutionConfig<ExUnits> {
     fn from(conf: ExecutionConfig) -> Self {
         Self {
             execution_cap: conf.execution_cap.into(),
             o2o_allowed: conf.o2o_allowed,
+            max_price_impact: conf
+                .max_price_impact_bps
+                .map(|bps| Ratio::new(bps as u128, BPS_DENOM)),
+            maker_selection: MakerSelection {
+                price_tolerance: Ratio::new(conf.pool_price_tolerance_bps.unwrap_or(0) as u128, BPS_DENOM),
+                max_reserves_share: conf
+                    .max_pool_reserves_share_bps
+                    .map(|bps| Ratio::new(bps as u128, BPS_DENOM)),
+            },
+            stash_policy: conf
+                .stash_ttl_attempts
+                .map(StashPolicy::Attempts)
+                .unwrap_or_default(),
+            recipe_limits: RecipeLimits {
+                max_fills: conf.max_fills_per_recipe,
+                max_swaps: conf.max_swaps_per_recipe,
+                min_fills: conf
+                    .min_fills_per_recipe
+                    .unwrap_or(RecipeLimits::default().min_fills),
+            },
+            net_opposite_flows: conf.net_opposite_flows,
+            matching_mode: MatchingMode::Continuous,
+            settlement_policy: conf.settlement_policy.map(Into::into).unwrap_or_default(),
         }
     }
 }
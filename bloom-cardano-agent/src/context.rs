@@ -1,28 +1,63 @@
-use bloom_offchain::execution_engine::liquidity_book::config::ExecutionConfig;
+use std::collections::HashMap;
+
+use bloom_offchain::execution_engine::liquidity_book::config::{
+    ExecutionCap, ExecutionConfig, MatchingMode, SettlementPolicy,
+};
 use bloom_offchain::execution_engine::types::Time;
+use bloom_offchain_cardano::execution_engine::babel_fee::BabelFees;
+use bloom_offchain_cardano::execution_engine::exposure::OperatorInventory;
+use bloom_offchain_cardano::execution_engine::ref_inputs::RefInputRegistry;
 use spectrum_cardano_lib::collateral::Collateral;
 use spectrum_cardano_lib::ex_units::ExUnits;
 use spectrum_cardano_lib::NetworkId;
 use spectrum_offchain::backlog::BacklogCapacity;
+use spectrum_offchain::clock::SharedClock;
 use spectrum_offchain::data::Has;
-use spectrum_offchain_cardano::creds::{OperatorCred, OperatorRewardAddress};
+use spectrum_offchain::maker::Specialize;
+use spectrum_offchain_cardano::creds::{OperatorCredSet, OperatorRewardAddress};
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::data::refund::Refunds;
 use spectrum_offchain_cardano::deployment::ProtocolValidator::{
     BalanceFnPoolDeposit, BalanceFnPoolRedeem, BalanceFnPoolV1, BalanceFnPoolV2, ConstFnFeeSwitchPoolDeposit,
     ConstFnFeeSwitchPoolRedeem, ConstFnFeeSwitchPoolSwap, ConstFnPoolDeposit, ConstFnPoolFeeSwitch,
     ConstFnPoolFeeSwitchBiDirFee, ConstFnPoolFeeSwitchV2, ConstFnPoolRedeem, ConstFnPoolSwap, ConstFnPoolV1,
-    ConstFnPoolV2, GridOrderNative, LimitOrderV1, LimitOrderWitnessV1, StableFnPoolT2T,
-    StableFnPoolT2TDeposit, StableFnPoolT2TRedeem,
+    ConstFnPoolV2, DaoAuthority, GridOrderNative, LimitOrderV1, LimitOrderWitnessV1, StableFnPoolT2T,
+    StableFnPoolT2TDeposit, StableFnPoolT2TRedeem, Vesting,
 };
-use spectrum_offchain_cardano::deployment::{DeployedValidator, ProtocolDeployment};
+use spectrum_offchain_cardano::deployment::{DeployedScriptInfo, DeployedValidator, ProtocolDeployment};
+use spectrum_offchain_cardano::fee_tuning::SharedFeeTuner;
+use spectrum_offchain_cardano::treasury::TreasuryAddress;
 use type_equalities::IsEqual;
 
 #[derive(Debug, Clone)]
 pub struct MakerContext {
     pub time: Time,
     pub execution_conf: ExecutionConfig<ExUnits>,
+    /// Pairs executed under caps different from the default one.
+    pub execution_caps_by_pair: HashMap<PairId, ExecutionCap<ExUnits>>,
+    /// Pairs matched otherwise than continuously.
+    pub matching_modes_by_pair: HashMap<PairId, MatchingMode>,
+    /// Pairs settling overlapping orders under a policy different from the default one.
+    pub settlement_policies_by_pair: HashMap<PairId, SettlementPolicy>,
     pub backlog_capacity: BacklogCapacity,
 }
 
+impl Specialize<PairId> for MakerContext {
+    fn specialize(&self, pair: &PairId) -> Self {
+        let mut ctx = self.clone();
+        if let Some(cap) = self.execution_caps_by_pair.get(pair) {
+            ctx.execution_conf.execution_cap = *cap;
+        }
+        if let Some(mode) = self.matching_modes_by_pair.get(pair) {
+            ctx.execution_conf.matching_mode = *mode;
+        }
+        if let Some(policy) = self.settlement_policies_by_pair.get(pair) {
+            ctx.execution_conf.settlement_policy = *policy;
+        }
+        ctx
+    }
+}
+
 impl Has<BacklogCapacity> for MakerContext {
     fn select<U: IsEqual<BacklogCapacity>>(&self) -> BacklogCapacity {
         self.backlog_capacity
@@ -49,7 +84,13 @@ pub struct ExecutionContext {
     pub reward_addr: OperatorRewardAddress,
     pub backlog_capacity: BacklogCapacity,
     pub network_id: NetworkId,
-    pub operator_cred: OperatorCred,
+    pub operator_creds: OperatorCredSet,
+    pub ref_inputs: RefInputRegistry,
+    pub inventory: OperatorInventory,
+    pub babel_fees: BabelFees,
+    pub refunds: Refunds,
+    pub fee_tuner: SharedFeeTuner,
+    pub clock: SharedClock,
 }
 
 impl Has<NetworkId> for ExecutionContext {
@@ -58,9 +99,9 @@ impl Has<NetworkId> for ExecutionContext {
     }
 }
 
-impl Has<OperatorCred> for ExecutionContext {
-    fn select<U: IsEqual<OperatorCred>>(&self) -> OperatorCred {
-        self.operator_cred
+impl Has<OperatorCredSet> for ExecutionContext {
+    fn select<U: IsEqual<OperatorCredSet>>(&self) -> OperatorCredSet {
+        self.operator_creds
     }
 }
 
@@ -82,6 +123,42 @@ impl Has<Collateral> for ExecutionContext {
     }
 }
 
+impl Has<RefInputRegistry> for ExecutionContext {
+    fn select<U: IsEqual<RefInputRegistry>>(&self) -> RefInputRegistry {
+        self.ref_inputs.clone()
+    }
+}
+
+impl Has<OperatorInventory> for ExecutionContext {
+    fn select<U: IsEqual<OperatorInventory>>(&self) -> OperatorInventory {
+        self.inventory.clone()
+    }
+}
+
+impl Has<BabelFees> for ExecutionContext {
+    fn select<U: IsEqual<BabelFees>>(&self) -> BabelFees {
+        self.babel_fees.clone()
+    }
+}
+
+impl Has<Refunds> for ExecutionContext {
+    fn select<U: IsEqual<Refunds>>(&self) -> Refunds {
+        self.refunds.clone()
+    }
+}
+
+impl Has<SharedFeeTuner> for ExecutionContext {
+    fn select<U: IsEqual<SharedFeeTuner>>(&self) -> SharedFeeTuner {
+        self.fee_tuner.clone()
+    }
+}
+
+impl Has<SharedClock> for ExecutionContext {
+    fn select<U: IsEqual<SharedClock>>(&self) -> SharedClock {
+        self.clock.clone()
+    }
+}
+
 impl Has<OperatorRewardAddress> for ExecutionContext {
     fn select<U: IsEqual<OperatorRewardAddress>>(&self) -> OperatorRewardAddress {
         self.reward_addr.clone()
@@ -255,3 +332,129 @@ impl Has<DeployedValidator<{ GridOrderNative as u8 }>> for ExecutionContext {
         self.deployment.grid_order_native.clone()
     }
 }
+
+/// Context of treasury withdrawals, extends the one of the first partition.
+#[derive(Debug, Clone)]
+pub struct TreasuryContext {
+    pub execution: ExecutionContext,
+    pub treasury_address: TreasuryAddress,
+    pub dao_authority: DeployedValidator<{ DaoAuthority as u8 }>,
+}
+
+impl Has<TreasuryAddress> for TreasuryContext {
+    fn select<U: IsEqual<TreasuryAddress>>(&self) -> TreasuryAddress {
+        self.treasury_address.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ DaoAuthority as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ DaoAuthority as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ DaoAuthority as u8 }> {
+        self.dao_authority.clone()
+    }
+}
+
+impl Has<NetworkId> for TreasuryContext {
+    fn select<U: IsEqual<NetworkId>>(&self) -> NetworkId {
+        self.execution.select::<NetworkId>()
+    }
+}
+
+impl Has<Collateral> for TreasuryContext {
+    fn select<U: IsEqual<Collateral>>(&self) -> Collateral {
+        self.execution.select::<Collateral>()
+    }
+}
+
+impl Has<OperatorRewardAddress> for TreasuryContext {
+    fn select<U: IsEqual<OperatorRewardAddress>>(&self) -> OperatorRewardAddress {
+        self.execution.select::<OperatorRewardAddress>()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolV1 as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolV1 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolV1 as u8 }> {
+        self.execution
+            .select::<DeployedValidator<{ ConstFnPoolV1 as u8 }>>()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolV2 as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolV2 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolV2 as u8 }> {
+        self.execution
+            .select::<DeployedValidator<{ ConstFnPoolV2 as u8 }>>()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }> {
+        self.execution
+            .select::<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>>()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }> {
+        self.execution
+            .select::<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>>()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>> for TreasuryContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }> {
+        self.execution
+            .select::<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>>()
+    }
+}
+
+/// Context of vesting claims, extends the one of the first partition.
+#[derive(Debug, Clone)]
+pub struct VestingContext {
+    pub execution: ExecutionContext,
+    pub vesting: DeployedValidator<{ Vesting as u8 }>,
+}
+
+impl Has<DeployedValidator<{ Vesting as u8 }>> for VestingContext {
+    fn select<U: IsEqual<DeployedValidator<{ Vesting as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ Vesting as u8 }> {
+        self.vesting.clone()
+    }
+}
+
+impl Has<DeployedScriptInfo<{ Vesting as u8 }>> for VestingContext {
+    fn select<U: IsEqual<DeployedScriptInfo<{ Vesting as u8 }>>>(
+        &self,
+    ) -> DeployedScriptInfo<{ Vesting as u8 }> {
+        DeployedScriptInfo::from(&self.vesting)
+    }
+}
+
+impl Has<NetworkId> for VestingContext {
+    fn select<U: IsEqual<NetworkId>>(&self) -> NetworkId {
+        self.execution.select::<NetworkId>()
+    }
+}
+
+impl Has<Collateral> for VestingContext {
+    fn select<U: IsEqual<Collateral>>(&self) -> Collateral {
+        self.execution.select::<Collateral>()
+    }
+}
+
+impl Has<OperatorRewardAddress> for VestingContext {
+    fn select<U: IsEqual<OperatorRewardAddress>>(&self) -> OperatorRewardAddress {
+        self.execution.select::<OperatorRewardAddress>()
+    }
+}
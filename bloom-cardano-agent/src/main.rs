@@ -1,45 +1,71 @@
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
+use cml_chain::certs::StakeCredential;
 use cml_chain::transaction::Transaction;
+use cml_chain::PolicyId;
 use cml_multi_era::babbage::BabbageTransaction;
 use either::Either;
 use futures::channel::mpsc;
-use futures::stream::select_all;
+use futures::stream::{self, select_all};
 use futures::{stream_select, Stream, StreamExt};
-use log::info;
+use log::{info, warn};
 use tokio::sync::{broadcast, Mutex};
-use tracing_subscriber::fmt::Subscriber;
 
-use crate::config::AppConfig;
-use crate::context::{ExecutionContext, MakerContext};
+use crate::config::{AppConfig, WarmStartConfig};
+use crate::context::{ExecutionContext, MakerContext, TreasuryContext, VestingContext};
 use crate::integrity::CheckIntegrity;
 use crate::partitioning::select_partition;
+use crate::quote_api::{serve_quotes, AgentQuoteBooks, AgentReserveHistory};
+use crate::rfq::{serve_rfq, CosigningNetwork, RfqFunds};
 use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain::execution_engine::circuit_breaker::PairCircuitBreaker;
 use bloom_offchain::execution_engine::execution_part_stream;
+use bloom_offchain::execution_engine::execution_report::ExecutionReportsRocksDB;
 use bloom_offchain::execution_engine::funding_effect::FundingEvent;
-use bloom_offchain::execution_engine::liquidity_book::TLB;
+use bloom_offchain::execution_engine::liquidity_book::AnyBook;
 use bloom_offchain::execution_engine::multi_pair::MultiPair;
-use bloom_offchain::execution_engine::storage::kv_store::InMemoryKvStore;
-use bloom_offchain::execution_engine::storage::{InMemoryStateIndex, StateIndexTracing};
+use bloom_offchain::execution_engine::notifier::WebhookNotifier;
+use bloom_offchain::execution_engine::pool_stats::PoolStatsRegistry;
+use bloom_offchain::execution_engine::quarantine::QuarantinePolicy;
+use bloom_offchain::execution_engine::reserve_history::ReserveHistory;
+use bloom_offchain::execution_engine::storage::kv_store::PersistentKvStore;
+use bloom_offchain::execution_engine::storage::{PersistentStateIndex, StateIndexTracing};
+use bloom_offchain::market_making::{Inventory, QuotingBook};
+use bloom_offchain::pair_registry::{publish_listing, PairRegistry};
 use bloom_offchain_cardano::bounds::Bounds;
+use bloom_offchain_cardano::event_sink::backlog_journal::BacklogJournalRocksDB;
 use bloom_offchain_cardano::event_sink::context::HandlerContextProto;
 use bloom_offchain_cardano::event_sink::entity_index::InMemoryEntityIndex;
 use bloom_offchain_cardano::event_sink::handler::{
-    FundingEventHandler, PairUpdateHandler, SpecializedHandler,
+    FeeTuningHandler, FundingEventHandler, PairUpdateHandler, RefInputHandler, SpecializedHandler,
 };
-use bloom_offchain_cardano::event_sink::order_index::InMemoryKvIndex;
+use bloom_offchain_cardano::event_sink::invalid_entity::{
+    InvalidEntity, InvalidEntityHandler, ValidatorRegistry,
+};
+use bloom_offchain_cardano::event_sink::order_index::{InMemoryKvIndex, KvIndex};
+use bloom_offchain_cardano::event_sink::pool_lifecycle::PoolLifecycleTracker;
+use bloom_offchain_cardano::event_sink::prefilter::{CredentialFilter, OutputPrefilter};
 use bloom_offchain_cardano::event_sink::processed_tx::ProcessedTransaction;
+use bloom_offchain_cardano::event_sink::warm_start::{entity_validators, pull_snapshot};
 use bloom_offchain_cardano::event_sink::{AtomicCardanoEntity, EvolvingCardanoEntity};
+use bloom_offchain_cardano::execution_engine::babel_fee::BabelFees;
 use bloom_offchain_cardano::execution_engine::backlog::interpreter::SpecializedInterpreterViaRunOrder;
+use bloom_offchain_cardano::execution_engine::exposure::OperatorInventory;
 use bloom_offchain_cardano::execution_engine::interpreter::CardanoRecipeInterpreter;
+use bloom_offchain_cardano::execution_engine::ref_inputs::RefInputRegistry;
+use bloom_offchain_cardano::market_making::market_making_stream;
 use bloom_offchain_cardano::orders::AnyOrder;
-use cardano_chain_sync::cache::LedgerCacheRocksDB;
+use cardano_chain_sync::cache::{LedgerCache, LedgerCacheRocksDB};
 use cardano_chain_sync::chain_sync_stream;
 use cardano_chain_sync::client::ChainSyncClient;
 use cardano_chain_sync::data::LedgerTxEvent;
 use cardano_chain_sync::event_source::ledger_transactions;
+use cardano_explorer::kupo::Kupo;
 use cardano_explorer::Maestro;
 use cardano_mempool_sync::client::LocalTxMonitorClient;
 use cardano_mempool_sync::data::MempoolUpdate;
@@ -50,46 +76,79 @@ use spectrum_cardano_lib::output::FinalizedTxOut;
 use spectrum_cardano_lib::transaction::OutboundTransaction;
 use spectrum_cardano_lib::OutputRef;
 use spectrum_offchain::backlog::{BacklogCapacity, HotPriorityBacklog};
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain::config::{parse, read_json_source};
 use spectrum_offchain::data::event::{Channel, StateUpdate};
-use spectrum_offchain::data::order::OrderUpdate;
+use spectrum_offchain::data::order::{OrderUpdate, SpecializedOrder};
 use spectrum_offchain::data::Baked;
 use spectrum_offchain::event_sink::event_handler::EventHandler;
 use spectrum_offchain::event_sink::process_events;
-use spectrum_offchain::partitioning::Partitioned;
-use spectrum_offchain::streaming::boxed;
+use spectrum_offchain::health::{serve_health_checks, HealthState};
+use spectrum_offchain::journal::retention_stream;
+use spectrum_offchain::kv_backend::{open_backend, Batched};
+use spectrum_offchain::network::{Broadcast, SubmissionMetrics};
+use spectrum_offchain::partitioning::{rebalance_periodically, AssignmentRocksDB, LoadMeter, Partitioned};
+use spectrum_offchain::quarantine::QuarantineRocksDB;
+use spectrum_offchain::rocks::RocksConfig;
+use spectrum_offchain::streaming::{boxed, map_parallel_ordered};
+use spectrum_offchain::sync_progress::{SyncLagGuard, SyncProgress};
+use spectrum_offchain::tx_journal::PersistentTxJournal;
 use spectrum_offchain_cardano::collateral::pull_collateral;
-use spectrum_offchain_cardano::creds::operator_creds;
+use spectrum_offchain_cardano::creds::{operator_creds, OperatorCredSet};
 use spectrum_offchain_cardano::data::order::ClassicalAMMOrder;
 use spectrum_offchain_cardano::data::pair::PairId;
 use spectrum_offchain_cardano::data::pool::AnyPool;
-use spectrum_offchain_cardano::deployment::{DeployedValidators, ProtocolDeployment, ProtocolScriptHashes};
+use spectrum_offchain_cardano::data::refund::Refunds;
+use spectrum_offchain_cardano::deployment::{
+    DeployedValidator, DeployedValidators, ProtocolDeployment, ProtocolScriptHashes,
+};
+use spectrum_offchain_cardano::fee_tuning::SharedFeeTuner;
+use spectrum_offchain_cardano::pnl::PnlJournalRocksDB;
 use spectrum_offchain_cardano::prover::operator::OperatorProver;
+use spectrum_offchain_cardano::sweep::residual_sweep_stream;
+use spectrum_offchain_cardano::treasury::{treasury_withdrawal_stream, TreasuryWatch};
 use spectrum_offchain_cardano::tx_submission::{tx_submission_agent_stream, TxSubmissionAgent};
+use spectrum_offchain_cardano::tx_validator::DryRunValidator;
+use spectrum_offchain_cardano::vesting::vesting_claim_stream;
 use spectrum_streaming::StreamExt as StreamExt1;
 
 mod config;
 mod context;
 mod integrity;
 mod partitioning;
+mod quote_api;
+mod rfq;
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 8)]
 async fn main() {
-    let subscriber = Subscriber::new();
-    tracing::subscriber::set_global_default(subscriber).expect("setting tracing default failed");
+    init_tracing();
     let args = AppArgs::parse();
-    let raw_config = std::fs::read_to_string(args.config_path).expect("Cannot load configuration file");
-    let config: AppConfig = serde_json::from_str(&raw_config).expect("Invalid configuration file");
+    let raw_config = read_json_source(&args.config_path, Some(CONFIG_ENV_PREFIX)).unwrap_or_else(exit_on);
+    let config: AppConfig = parse(&args.config_path, &raw_config).unwrap_or_else(exit_on);
     let config_integrity_violations = config.check_integrity();
     if !config_integrity_violations.is_empty() {
-        panic!("Malformed configuration: {}", config_integrity_violations);
+        exit_on(format!(
+            "Malformed configuration: {}",
+            config_integrity_violations
+        ))
     }
 
-    let raw_deployment = std::fs::read_to_string(args.deployment_path).expect("Cannot load deployment file");
+    let raw_deployment = read_json_source(&args.deployment_path, None).unwrap_or_else(exit_on);
     let deployment: DeployedValidators =
-        serde_json::from_str(&raw_deployment).expect("Invalid deployment file");
+        parse(&args.deployment_path, &raw_deployment).unwrap_or_else(exit_on);
 
-    let raw_bounds = std::fs::read_to_string(args.bounds_path).expect("Cannot load bounds file");
-    let bounds: Bounds = serde_json::from_str(&raw_bounds).expect("Invalid bounds file");
+    let raw_bounds = read_json_source(&args.bounds_path, None).unwrap_or_else(exit_on);
+    let bounds: Bounds = parse(&args.bounds_path, &raw_bounds).unwrap_or_else(exit_on);
+
+    if args.check_config {
+        println!("Configuration is valid");
+        return;
+    }
+    let operator_key = config.operator_key.load().unwrap_or_else(exit_on);
+    let retiring_operator_key = config
+        .retiring_operator_key
+        .as_ref()
+        .map(|source| source.load().unwrap_or_else(exit_on));
 
     log4rs::init_file(args.log4rs_path, Default::default()).unwrap();
 
@@ -97,13 +156,60 @@ async fn main() {
 
     let rollback_in_progress = Arc::new(AtomicBool::new(false));
 
+    let sync_progress = Arc::new(SyncProgress::new());
+    let submission_metrics = Arc::new(SubmissionMetrics::new());
+    let health = Arc::new(
+        HealthState::new()
+            .with_sync_progress(Arc::clone(&sync_progress))
+            .with_submission_metrics(Arc::clone(&submission_metrics)),
+    );
+    if let Some(addr) = config.health_check_addr {
+        tokio::spawn(serve_health_checks(
+            addr,
+            Arc::clone(&health),
+            env!("CARGO_PKG_VERSION"),
+        ));
+    }
+    let pair_registry = PairRegistry::new();
+    if let Some(conf) = config.pair_listing {
+        tokio::spawn(publish_listing(
+            pair_registry.clone(),
+            conf.target,
+            conf.publish_interval,
+        ));
+    }
+
     let explorer = Maestro::new(config.maestro_key_path, config.network_id.into())
         .await
         .expect("Maestro instantiation failed");
 
     let protocol_deployment = ProtocolDeployment::unsafe_pull(deployment, &explorer).await;
 
+    let warm_start = match config.warm_start {
+        Some(WarmStartConfig::Kupo { url }) => {
+            let kupo = Kupo::new(url);
+            // Tip is read ahead of UTxOs, so that the snapshot is never behind it.
+            match kupo.tip().await {
+                Some(tip) => {
+                    let validators = entity_validators(&ProtocolScriptHashes::from(&protocol_deployment));
+                    Some((tip, pull_snapshot(validators, &kupo).await))
+                }
+                None => {
+                    warn!("Kupo tip is unavailable, warm start is skipped");
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+    let (warm_start_point, warm_start_snapshot) = warm_start.unzip();
+
     let chain_sync_cache = Arc::new(Mutex::new(LedgerCacheRocksDB::new(config.chain_sync.db_path)));
+    health.set_db_open(true);
+    // Chain sync resumes from the tip of the snapshot, so that it only evolves states the snapshot restores.
+    if let Some(tip) = warm_start_point {
+        chain_sync_cache.lock().await.set_tip(tip).await;
+    }
     let chain_sync = ChainSyncClient::init(
         Arc::clone(&chain_sync_cache),
         config.node.path,
@@ -122,15 +228,51 @@ async fn main() {
         TxSubmissionAgent::<BABBAGE_ERA_ID, OutboundTransaction<Transaction>, Transaction>::new(
             config.node,
             config.tx_submission_buffer_size,
+            config.tx_submission_retry,
+            Arc::clone(&submission_metrics),
         )
         .await
         .expect("LocalTxSubmission initialization failed");
 
+    let mut tx_submission_streams = vec![boxed(tx_submission_agent_stream(tx_submission_agent))];
+    let mut extra_submission_channels = vec![];
+    for endpoint in config.extra_submission_endpoints {
+        let (agent, channel) =
+            TxSubmissionAgent::<BABBAGE_ERA_ID, OutboundTransaction<Transaction>, Transaction>::new(
+                endpoint,
+                config.tx_submission_buffer_size,
+                config.tx_submission_retry,
+                Arc::clone(&submission_metrics),
+            )
+            .await
+            .expect("LocalTxSubmission initialization failed");
+        tx_submission_streams.push(boxed(tx_submission_agent_stream(agent)));
+        extra_submission_channels.push(channel);
+    }
+    let rfq_funds = RfqFunds::new();
+    let tx_submission_channel = CosigningNetwork::new(
+        Broadcast::new(tx_submission_channel, extra_submission_channels),
+        rfq_funds.clone(),
+        config.rfq.as_ref().map_or(vec![], |rfq| rfq.makers.clone()),
+    );
+
+    health.set_network_reachable(true);
+
     // prepare upstreams
-    let tx_submission_stream = tx_submission_agent_stream(tx_submission_agent);
 
     let (operator_sk, operator_paycred, collateral_address, funding_addresses) =
-        operator_creds(config.operator_key, config.network_id);
+        operator_creds(operator_key.expose(), config.network_id);
+    let mut operator_sks = vec![operator_sk];
+    let mut operator_cred_set = OperatorCredSet::new(operator_paycred);
+    if let Some(retiring_key) = retiring_operator_key {
+        let (retiring_sk, retiring_cred, _, _) = operator_creds(retiring_key.expose(), config.network_id);
+        info!(
+            "Operator key rotation in progress, retiring credential: {}",
+            retiring_cred.0
+        );
+        operator_sks.push(retiring_sk);
+        operator_cred_set = operator_cred_set.with_retiring(retiring_cred);
+    }
 
     info!(
         "Expecting collateral at {}",
@@ -150,8 +292,25 @@ async fn main() {
     let (pair_upd_snd_p4, pair_upd_recv_p4) =
         mpsc::channel::<(PairId, Channel<StateUpdate<EvolvingCardanoEntity>>)>(config.channel_buffer_size);
 
+    let pair_load = LoadMeter::new();
+    let mut pair_assignment = HashMap::new();
+    if let Some(conf) = &config.partition_rebalancing {
+        let store = AssignmentRocksDB::new(RocksConfig {
+            db_path: conf.db_path.into(),
+        });
+        pair_assignment = store.load::<PairId>();
+        info!("{} pairs pinned to partitions", pair_assignment.len());
+        tokio::spawn(rebalance_periodically(
+            pair_load.clone(),
+            store,
+            NUM_PARTITIONS,
+            conf.interval,
+        ));
+    }
+
     let partitioned_pair_upd_snd =
-        Partitioned::new([pair_upd_snd_p1, pair_upd_snd_p2, pair_upd_snd_p3, pair_upd_snd_p4]);
+        Partitioned::new([pair_upd_snd_p1, pair_upd_snd_p2, pair_upd_snd_p3, pair_upd_snd_p4])
+            .with_assignment(pair_assignment.clone());
 
     let (spec_upd_snd_p1, spec_upd_recv_p1) = mpsc::channel::<(
         PairId,
@@ -171,7 +330,8 @@ async fn main() {
     )>(config.channel_buffer_size);
 
     let partitioned_spec_upd_snd =
-        Partitioned::new([spec_upd_snd_p1, spec_upd_snd_p2, spec_upd_snd_p3, spec_upd_snd_p4]);
+        Partitioned::new([spec_upd_snd_p1, spec_upd_snd_p2, spec_upd_snd_p3, spec_upd_snd_p4])
+            .with_assignment(pair_assignment.clone());
 
     let (funding_upd_snd_p1, funding_upd_recv_p1) =
         mpsc::channel::<FundingEvent<FinalizedTxOut>>(config.channel_buffer_size);
@@ -199,15 +359,53 @@ async fn main() {
         config.cardano_finalization_delay,
     )));
     let handler_context = HandlerContextProto {
-        executor_cred: operator_paycred,
+        executor_creds: operator_cred_set,
         scripts: ProtocolScriptHashes::from(&protocol_deployment),
         bounds,
     };
+    let backlog_journal = BacklogJournalRocksDB::new(RocksConfig {
+        db_path: config.backlog_journal_db_path.into(),
+    });
+    let mut recovered_spec_orders =
+        Partitioned::new([vec![], vec![], vec![], vec![]]).with_assignment(pair_assignment);
+    {
+        let mut index = spec_order_index.lock().await;
+        for (pair, order) in backlog_journal.recover(handler_context) {
+            index.put(order.get_self_ref(), order.clone());
+            recovered_spec_orders
+                .get_mut(pair)
+                .push((pair, Channel::ledger(OrderUpdate::Created(order))));
+        }
+    }
+    let [recovered_spec_p1, recovered_spec_p2, recovered_spec_p3, recovered_spec_p4] =
+        recovered_spec_orders.into_inner();
+    info!(
+        "{} orders recovered from backlog journal",
+        recovered_spec_p1.len() + recovered_spec_p2.len() + recovered_spec_p3.len() + recovered_spec_p4.len()
+    );
+    if config.read_only {
+        info!("Running in read-only mode, no TXs will be submitted");
+    }
+    let clock = SharedClock::system();
+    if let Some(rfq_conf) = config.rfq.clone().filter(|_| !config.read_only) {
+        tokio::spawn(serve_rfq(
+            rfq_conf,
+            rfq_funds,
+            partitioned_pair_upd_snd.clone(),
+            clock.clone(),
+        ));
+    }
     let general_upd_handler = PairUpdateHandler::new(
         partitioned_pair_upd_snd,
         Arc::clone(&entity_index),
         handler_context,
-    );
+    )
+    .observed_by(PoolLifecycleTracker::new(
+        config
+            .pool_lifecycle_webhook_url
+            .clone()
+            .map(WebhookNotifier::new),
+    ));
     let spec_upd_handler = SpecializedHandler::new(
         PairUpdateHandler::new(partitioned_spec_upd_snd, entity_index, handler_context),
         spec_order_index,
@@ -221,23 +419,62 @@ async fn main() {
 
     info!("Derived funding addresses: {}", funding_addresses);
 
-    let handlers_ledger: Vec<Box<dyn EventHandler<LedgerTxEvent<ProcessedTransaction>>>> = vec![
+    let ref_inputs = RefInputRegistry::new(config.ref_inputs_by_validator());
+    let ref_input_handler = RefInputHandler::new(ref_inputs.clone());
+    // Only outputs at protocol validators and funding addresses are of interest past ref inputs.
+    let relevant_creds = entity_validators(&handler_context.scripts)
+        .into_iter()
+        .map(StakeCredential::new_script)
+        .chain(funding_addresses.payment_creds().cloned())
+        .collect::<Vec<_>>();
+    let output_prefilter = OutputPrefilter::new(CredentialFilter::new(&relevant_creds));
+    let inventory = OperatorInventory::new(config.exposure_limits());
+    let babel_fees = BabelFees::new(config.babel_fees());
+    // State index, cache, TX journal and refunds share the backend, so that they are updated atomically.
+    let state_backend = Batched::new(open_backend(&config.state_db));
+    let refunds = Refunds::persistent(Arc::new(state_backend.clone()));
+    let fee_tuner = SharedFeeTuner::new(config.fee_tuning);
+
+    let mut handlers_ledger: Vec<Box<dyn EventHandler<LedgerTxEvent<ProcessedTransaction>>>> = vec![
+        Box::new(ref_input_handler),
+        Box::new(FeeTuningHandler::new(fee_tuner.clone())),
+        Box::new(output_prefilter.clone()),
         Box::new(general_upd_handler.clone()),
         Box::new(spec_upd_handler.clone()),
         Box::new(funding_event_handler.clone()),
     ];
+    if let Some(url) = config.invalid_entity_webhook_url.clone() {
+        let (invalid_entity_snd, mut invalid_entity_recv) =
+            mpsc::channel::<InvalidEntity>(config.channel_buffer_size);
+        let notifier = WebhookNotifier::new(url);
+        tokio::spawn(async move {
+            while let Some(entity) = invalid_entity_recv.next().await {
+                notifier.post(serde_json::json!({ "event": "invalidEntity", "entity": entity }));
+            }
+        });
+        // Must go last so that only outputs no other handler recognized are inspected.
+        handlers_ledger.push(Box::new(InvalidEntityHandler::new(
+            ValidatorRegistry::from(&handler_context.scripts),
+            invalid_entity_snd,
+        )));
+    }
 
     let handlers_mempool: Vec<Box<dyn EventHandler<MempoolUpdate<ProcessedTransaction>>>> = vec![
+        Box::new(output_prefilter),
         Box::new(general_upd_handler),
         Box::new(spec_upd_handler),
         Box::new(funding_event_handler),
     ];
 
-    let prover = OperatorProver::new(&operator_sk);
+    let prover = OperatorProver::with_keys(&operator_sks);
     let recipe_interpreter = CardanoRecipeInterpreter;
     let spec_interpreter = SpecializedInterpreterViaRunOrder;
+    let validator = DryRunValidator::new();
     let maker_context = MakerContext {
         time: 0.into(),
+        execution_caps_by_pair: config.execution.execution_caps_by_pair(),
+        matching_modes_by_pair: config.execution.matching_modes_by_pair(),
+        settlement_policies_by_pair: config.execution.settlement_policies_by_pair(),
         execution_conf: config.execution.into(),
         backlog_capacity: BacklogCapacity::from(config.backlog_capacity),
     };
@@ -248,7 +485,13 @@ async fn main() {
         backlog_capacity: BacklogCapacity::from(config.backlog_capacity),
         collateral: collateral.clone(),
         network_id: config.network_id,
-        operator_cred: operator_paycred,
+        operator_creds: operator_cred_set,
+        ref_inputs: ref_inputs.clone(),
+        inventory: inventory.clone(),
+        babel_fees: babel_fees.clone(),
+        refunds: refunds.clone(),
+        fee_tuner: fee_tuner.clone(),
+        clock: clock.clone(),
     };
     let context_p2 = ExecutionContext {
         time: 0.into(),
@@ -257,7 +500,13 @@ async fn main() {
         backlog_capacity: BacklogCapacity::from(config.backlog_capacity),
         collateral: collateral.clone(),
         network_id: config.network_id,
-        operator_cred: operator_paycred,
+        operator_creds: operator_cred_set,
+        ref_inputs: ref_inputs.clone(),
+        inventory: inventory.clone(),
+        babel_fees: babel_fees.clone(),
+        refunds: refunds.clone(),
+        fee_tuner: fee_tuner.clone(),
+        clock: clock.clone(),
     };
     let context_p3 = ExecutionContext {
         time: 0.into(),
@@ -266,7 +515,13 @@ async fn main() {
         backlog_capacity: BacklogCapacity::from(config.backlog_capacity),
         collateral: collateral.clone(),
         network_id: config.network_id,
-        operator_cred: operator_paycred,
+        operator_creds: operator_cred_set,
+        ref_inputs: ref_inputs.clone(),
+        inventory: inventory.clone(),
+        babel_fees: babel_fees.clone(),
+        refunds: refunds.clone(),
+        fee_tuner: fee_tuner.clone(),
+        clock: clock.clone(),
     };
     let context_p4 = ExecutionContext {
         time: 0.into(),
@@ -275,17 +530,181 @@ async fn main() {
         backlog_capacity: BacklogCapacity::from(config.backlog_capacity),
         collateral,
         network_id: config.network_id,
-        operator_cred: operator_paycred,
+        operator_creds: operator_cred_set,
+        ref_inputs: ref_inputs.clone(),
+        inventory: inventory.clone(),
+        babel_fees: babel_fees.clone(),
+        refunds: refunds.clone(),
+        fee_tuner: fee_tuner.clone(),
+        clock: clock.clone(),
     };
-    let multi_book = MultiPair::new::<TLB<AnyOrder, AnyPool, ExUnits>>(maker_context.clone(), "Book");
+    let (sweep_stream, pnl_journal) = config
+        .residual_sweep
+        .filter(|_| !config.read_only)
+        .map(|conf| {
+            let journal = PnlJournalRocksDB::new(RocksConfig {
+                db_path: conf.pnl_journal_db_path.into(),
+            });
+            let sweep = residual_sweep_stream(
+                (0..NUM_PARTITIONS)
+                    .map(|ix| funding_addresses[ix].clone())
+                    .collect(),
+                funding_addresses[0].clone(),
+                explorer,
+                tx_submission_channel.clone(),
+                prover,
+                journal.clone(),
+                conf.sweep,
+                context_p1.clone(),
+            );
+            (sweep, journal)
+        })
+        .unzip();
+    let treasury_watch = TreasuryWatch::default();
+    let treasury_stream = match config.treasury.filter(|_| !config.read_only) {
+        Some(conf) => {
+            let explorer = Maestro::new(config.maestro_key_path, config.network_id.into())
+                .await
+                .expect("Maestro instantiation failed");
+            let dao_authority = DeployedValidator::unsafe_pull(conf.dao_authority, &explorer).await;
+            Some(treasury_withdrawal_stream(
+                treasury_watch.clone(),
+                explorer,
+                tx_submission_channel.clone(),
+                prover,
+                conf.withdrawal,
+                TreasuryContext {
+                    execution: context_p1.clone(),
+                    treasury_address: conf.treasury_address,
+                    dao_authority,
+                },
+            ))
+        }
+        None => None,
+    };
+    let vesting_stream = match config.vesting.filter(|_| !config.read_only) {
+        Some(conf) => {
+            let explorer = Maestro::new(config.maestro_key_path, config.network_id.into())
+                .await
+                .expect("Maestro instantiation failed");
+            let vesting = DeployedValidator::unsafe_pull(conf.validator, &explorer).await;
+            Some(vesting_claim_stream(
+                explorer,
+                tx_submission_channel.clone(),
+                prover,
+                clock.clone(),
+                conf.claim,
+                VestingContext {
+                    execution: context_p1.clone(),
+                    vesting,
+                },
+            ))
+        }
+        None => None,
+    };
+    let quote_books = AgentQuoteBooks::new(maker_context.clone());
+    let execution_reports = ExecutionReportsRocksDB::new(
+        RocksConfig {
+            db_path: config.execution_reports_db_path.into(),
+        },
+        clock.clone(),
+    );
+    let execution_reports_journal = execution_reports.clone();
+    let pool_stats = PoolStatsRegistry::new(config.epoch_schedule);
+    let reserve_history = ReserveHistory::new(config.reserve_history);
+    let market_making = match config.market_making.filter(|_| !config.read_only) {
+        Some(conf) => {
+            let explorer = Maestro::new(config.maestro_key_path, config.network_id.into())
+                .await
+                .expect("Maestro instantiation failed");
+            let quoting_book = QuotingBook::new(
+                conf.pairs
+                    .iter()
+                    .map(|p| {
+                        let inventory = Inventory {
+                            base: p.base,
+                            quote: p.quote,
+                            position: 0,
+                        };
+                        (p.pair, inventory)
+                    })
+                    .collect(),
+            );
+            let quoting = market_making_stream(
+                quoting_book.clone(),
+                reserve_history.clone(),
+                explorer,
+                tx_submission_channel.clone(),
+                prover,
+                conf.quoting,
+                context_p1.clone(),
+            );
+            Some((quoting, quoting_book))
+        }
+        None => None,
+    };
+    let (quoting_stream, quoting_book) = market_making.unzip();
+    if let Some(addr) = config.quote_api_addr {
+        tokio::spawn(serve_quotes(
+            addr,
+            quote_books.clone(),
+            execution_reports.clone(),
+            pool_stats.clone(),
+            reserve_history.clone(),
+            refunds.clone(),
+            clock.clone(),
+        ));
+    }
+    let multi_book = MultiPair::new::<AnyBook<AnyOrder, AnyPool, ExUnits>>(maker_context.clone(), "Book");
     let multi_backlog = MultiPair::new::<HotPriorityBacklog<Bundled<ClassicalAMMOrder, FinalizedTxOut>>>(
         maker_context,
         "Backlog",
     );
-    let state_index = StateIndexTracing(InMemoryStateIndex::new());
-    let state_cache = InMemoryKvStore::new();
+    let state_index = StateIndexTracing(PersistentStateIndex::new(state_backend.clone()));
+    let state_cache = PersistentKvStore::new(state_backend.clone());
+    let tx_journal = PersistentTxJournal::new(state_backend);
 
     let (signal_tip_reached_snd, signal_tip_reached_recv) = broadcast::channel(1);
+    let mut health_tip_reached_recv = signal_tip_reached_snd.subscribe();
+    let health_sync = Arc::clone(&health);
+    tokio::spawn(async move {
+        while let Ok(tip_reached) = health_tip_reached_recv.recv().await {
+            health_sync.set_synced(tip_reached);
+        }
+    });
+    let lag_guard = SyncLagGuard::new(Arc::clone(&sync_progress), config.max_sync_lag_slots);
+    let pool_quarantine = QuarantineRocksDB::new(RocksConfig {
+        db_path: config.pool_quarantine.db_path.into(),
+    });
+    let quarantine_policy = QuarantinePolicy {
+        max_consecutive_failures: config.pool_quarantine.max_consecutive_failures,
+        retest_after: config.pool_quarantine.retest_after,
+    };
+    let circuit_breaker = PairCircuitBreaker::new(
+        config.circuit_breaker,
+        config.circuit_breaker_webhook_url.map(WebhookNotifier::new),
+    );
+    let fill_notifier = (
+        (
+            execution_reports,
+            config.fill_webhook_url.map(WebhookNotifier::new),
+        ),
+        quoting_book,
+    );
+    let sync_progress_report = Arc::clone(&sync_progress);
+    tokio::spawn(async move {
+        loop {
+            info!(
+                target: "sync_progress",
+                "point: {}, tip: {}, lag: {} slots ({}s)",
+                sync_progress_report.current_slot(),
+                sync_progress_report.tip_slot(),
+                sync_progress_report.lag_slots(),
+                sync_progress_report.lag().as_secs()
+            );
+            tokio::time::sleep(SYNC_PROGRESS_REPORT_INTERVAL).await;
+        }
+    });
 
     let execution_stream_p1 = execution_part_stream(
         state_index.clone(),
@@ -295,14 +714,39 @@ async fn main() {
         context_p1,
         recipe_interpreter,
         spec_interpreter,
+        validator,
         prover,
+        tx_journal.clone(),
+        config.pending_tx_ttl,
+        config.unconfirmed_ttl_slots,
+        quarantine_policy,
+        pool_quarantine.clone(),
+        fill_notifier.clone(),
+        pool_stats.clone(),
+        circuit_breaker.clone(),
         select_partition(
-            merge_upstreams(pair_upd_recv_p1, spec_upd_recv_p1),
+            merge_upstreams(
+                pair_upd_recv_p1,
+                stream::iter(recovered_spec_p1).chain(spec_upd_recv_p1),
+                backlog_journal.clone(),
+                pair_load.clone(),
+                pair_registry.clone(),
+                quote_books.clone(),
+                babel_fees.clone(),
+                refunds.clone(),
+                treasury_watch.clone(),
+                circuit_breaker.clone(),
+                reserve_history.clone(),
+                Arc::clone(&sync_progress),
+                clock.clone(),
+            ),
             config.partitioning.clone(),
         ),
         funding_upd_recv_p1,
         tx_submission_channel.clone(),
         signal_tip_reached_snd.subscribe(),
+        lag_guard.clone(),
+        config.read_only,
     );
     let execution_stream_p2 = execution_part_stream(
         state_index.clone(),
@@ -312,14 +756,39 @@ async fn main() {
         context_p2,
         recipe_interpreter,
         spec_interpreter,
+        validator,
         prover,
+        tx_journal.clone(),
+        config.pending_tx_ttl,
+        config.unconfirmed_ttl_slots,
+        quarantine_policy,
+        pool_quarantine.clone(),
+        fill_notifier.clone(),
+        pool_stats.clone(),
+        circuit_breaker.clone(),
         select_partition(
-            merge_upstreams(pair_upd_recv_p2, spec_upd_recv_p2),
+            merge_upstreams(
+                pair_upd_recv_p2,
+                stream::iter(recovered_spec_p2).chain(spec_upd_recv_p2),
+                backlog_journal.clone(),
+                pair_load.clone(),
+                pair_registry.clone(),
+                quote_books.clone(),
+                babel_fees.clone(),
+                refunds.clone(),
+                treasury_watch.clone(),
+                circuit_breaker.clone(),
+                reserve_history.clone(),
+                Arc::clone(&sync_progress),
+                clock.clone(),
+            ),
             config.partitioning.clone(),
         ),
         funding_upd_recv_p2,
         tx_submission_channel.clone(),
         signal_tip_reached_snd.subscribe(),
+        lag_guard.clone(),
+        config.read_only,
     );
     let execution_stream_p3 = execution_part_stream(
         state_index.clone(),
@@ -329,14 +798,39 @@ async fn main() {
         context_p3,
         recipe_interpreter,
         spec_interpreter,
+        validator,
         prover,
+        tx_journal.clone(),
+        config.pending_tx_ttl,
+        config.unconfirmed_ttl_slots,
+        quarantine_policy,
+        pool_quarantine.clone(),
+        fill_notifier.clone(),
+        pool_stats.clone(),
+        circuit_breaker.clone(),
         select_partition(
-            merge_upstreams(pair_upd_recv_p3, spec_upd_recv_p3),
+            merge_upstreams(
+                pair_upd_recv_p3,
+                stream::iter(recovered_spec_p3).chain(spec_upd_recv_p3),
+                backlog_journal.clone(),
+                pair_load.clone(),
+                pair_registry.clone(),
+                quote_books.clone(),
+                babel_fees.clone(),
+                refunds.clone(),
+                treasury_watch.clone(),
+                circuit_breaker.clone(),
+                reserve_history.clone(),
+                Arc::clone(&sync_progress),
+                clock.clone(),
+            ),
             config.partitioning.clone(),
         ),
         funding_upd_recv_p3,
         tx_submission_channel.clone(),
         signal_tip_reached_snd.subscribe(),
+        lag_guard.clone(),
+        config.read_only,
     );
     let execution_stream_p4 = execution_part_stream(
         state_index,
@@ -346,31 +840,77 @@ async fn main() {
         context_p4,
         recipe_interpreter,
         spec_interpreter,
+        validator,
         prover,
+        tx_journal,
+        config.pending_tx_ttl,
+        config.unconfirmed_ttl_slots,
+        quarantine_policy,
+        pool_quarantine,
+        fill_notifier,
+        pool_stats,
+        circuit_breaker.clone(),
         select_partition(
-            merge_upstreams(pair_upd_recv_p4, spec_upd_recv_p4),
+            merge_upstreams(
+                pair_upd_recv_p4,
+                stream::iter(recovered_spec_p4).chain(spec_upd_recv_p4),
+                backlog_journal,
+                pair_load,
+                pair_registry,
+                quote_books,
+                babel_fees,
+                refunds,
+                treasury_watch,
+                circuit_breaker,
+                reserve_history,
+                Arc::clone(&sync_progress),
+                clock.clone(),
+            ),
             config.partitioning,
         ),
         funding_upd_recv_p4,
         tx_submission_channel,
         signal_tip_reached_snd.subscribe(),
+        lag_guard,
+        config.read_only,
     );
 
     let ledger_stream = Box::pin(ledger_transactions(
         chain_sync_cache,
-        chain_sync_stream(chain_sync, signal_tip_reached_snd),
+        chain_sync_stream(chain_sync, signal_tip_reached_snd, Arc::clone(&sync_progress)),
         config.chain_sync.disable_rollbacks_until,
-        config.chain_sync.replay_from_point,
+        config
+            .chain_sync
+            .replay_from_point
+            .filter(|_| warm_start_point.is_none()),
         rollback_in_progress,
     ))
-    .await
-    .map(|ev| match ev {
-        LedgerTxEvent::TxApplied { tx, slot } => LedgerTxEvent::TxApplied {
-            tx: ProcessedTransaction::from(tx),
-            slot,
-        },
-        LedgerTxEvent::TxUnapplied(tx) => LedgerTxEvent::TxUnapplied(ProcessedTransaction::from(tx)),
+    .await;
+    // Hashing and unpacking of TXs is stateless, so it may run in parallel as long as
+    // handlers still see TXs in the order they appear on chain.
+    let prepare = |ev: LedgerTxEvent<BabbageTransaction>| ev.map(ProcessedTransaction::from);
+    let ledger_stream = match config.chain_sync.parallelism {
+        Some(parallelism) => boxed(map_parallel_ordered(ledger_stream, parallelism, prepare)),
+        None => boxed(ledger_stream.map(prepare)),
+    }
+    .map(move |ev| {
+        if let LedgerTxEvent::TxApplied { slot, .. } = &ev {
+            sync_progress.on_point(*slot);
+        }
+        ev
     });
+    // Snapshot is applied as of the point it was taken at, entities it restores are then evolved by chain sync.
+    let warm_start_slot = warm_start_point.map_or(0, |tip| tip.get_slot());
+    let ledger_stream = stream::iter(
+        warm_start_snapshot
+            .into_iter()
+            .flatten()
+            .map(move |tx| LedgerTxEvent::TxApplied {
+                tx,
+                slot: warm_start_slot,
+            }),
+    )
+    .chain(ledger_stream);
     let mempool_stream = mempool_stream(&mempool_sync, signal_tip_reached_recv).map(|ev| match ev {
         MempoolUpdate::TxAccepted(tx) => MempoolUpdate::TxAccepted(ProcessedTransaction::from(tx)),
     });
@@ -380,21 +920,52 @@ async fn main() {
     let process_mempool_events_stream =
         process_events(mempool_stream, handlers_mempool).buffered_within(config.mempool_buffering_duration);
 
-    let mut app = select_all(vec![
+    let mut streams = vec![
         boxed(process_ledger_events_stream),
         boxed(process_mempool_events_stream),
         boxed(execution_stream_p1),
         boxed(execution_stream_p2),
         boxed(execution_stream_p3),
         boxed(execution_stream_p4),
-        boxed(tx_submission_stream),
-    ]);
+    ];
+    if !config.read_only {
+        streams.extend(tx_submission_streams);
+    }
+    if let Some(sweep) = sweep_stream {
+        streams.push(boxed(sweep));
+    }
+    if let Some(treasury) = treasury_stream {
+        streams.push(boxed(treasury));
+    }
+    if let Some(vesting) = vesting_stream {
+        streams.push(boxed(vesting));
+    }
+    if let Some(quoting) = quoting_stream {
+        streams.push(boxed(quoting));
+    }
+    if let Some(retention) = config.journal_retention {
+        streams.push(boxed(retention_stream(
+            execution_reports_journal,
+            "execution_reports",
+            retention,
+            clock.clone(),
+        )));
+        if let Some(journal) = pnl_journal {
+            streams.push(boxed(retention_stream(journal, "pnl", retention, clock)));
+        }
+    }
+    let mut app = select_all(streams);
 
     loop {
         app.select_next_some().await;
     }
 }
 
+/// Executor partitions within the process.
+const NUM_PARTITIONS: usize = 4;
+
+const SYNC_PROGRESS_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
 fn merge_upstreams(
     xs: impl Stream<Item = (PairId, Channel<StateUpdate<EvolvingCardanoEntity>>)> + Unpin,
     ys: impl Stream<
@@ -403,6 +974,17 @@ fn merge_upstreams(
                 Channel<OrderUpdate<AtomicCardanoEntity, AtomicCardanoEntity>>,
             ),
         > + Unpin,
+    backlog_journal: BacklogJournalRocksDB,
+    pair_load: LoadMeter<PairId>,
+    pair_registry: PairRegistry<PairId, PolicyId, OutputRef>,
+    quote_books: AgentQuoteBooks,
+    babel_fees: BabelFees,
+    refunds: Refunds,
+    treasury_watch: TreasuryWatch,
+    circuit_breaker: PairCircuitBreaker<PairId>,
+    reserve_history: AgentReserveHistory,
+    sync_progress: Arc<SyncProgress>,
+    clock: SharedClock,
 ) -> impl Stream<
     Item = (
         PairId,
@@ -418,14 +1000,29 @@ fn merge_upstreams(
 > {
     stream_select!(
         xs.map(|(p, m)| (p, Either::Left(m.map(|s| s.map(|EvolvingCardanoEntity(e)| e))))),
-        ys.map(|(p, m)| (
-            p,
-            Either::Right(m.map(|upd| match upd {
-                OrderUpdate::Created(AtomicCardanoEntity(i)) => OrderUpdate::Created(i),
-                OrderUpdate::Eliminated(AtomicCardanoEntity(Bundled(i, _))) => OrderUpdate::Eliminated(i),
-            }))
-        ))
+        ys.inspect(move |(p, m)| backlog_journal.observe(*p, m))
+            .map(|(p, m)| (
+                p,
+                Either::Right(m.map(|upd| match upd {
+                    OrderUpdate::Created(AtomicCardanoEntity(i)) => OrderUpdate::Created(i),
+                    OrderUpdate::Eliminated(AtomicCardanoEntity(Bundled(i, _))) => OrderUpdate::Eliminated(i),
+                }))
+            ))
     )
+    .inspect(move |(pair, event)| {
+        pair_load.observe(*pair);
+        pair_registry.observe(*pair, event);
+        quote_books.observe(*pair, event);
+        babel_fees.observe(pair.assets(), event);
+        if let Either::Right(upd) = event {
+            refunds.observe(upd);
+        }
+        treasury_watch.observe(event);
+        circuit_breaker.observe(*pair, event, clock.unix_time_secs());
+        if let Some(volatility) = reserve_history.observe(*pair, event, sync_progress.current_slot()) {
+            circuit_breaker.observe_volatility(*pair, volatility, clock.unix_time_secs());
+        }
+    })
 }
 
 #[derive(Parser)]
@@ -446,4 +1043,26 @@ struct AppArgs {
     /// Path to the log4rs YAML configuration file.
     #[arg(long, short)]
     log4rs_path: String,
+    /// Validate configuration files and exit without starting the agent.
+    #[arg(long)]
+    check_config: bool,
+}
+
+/// Spans of the executor poll loop (upstream ingestion, book sync, recipe attempts,
+/// interpretation, proving, submission) are reported to the global subscriber.
+/// With the `tokio-console` feature task diagnostics are additionally served to tokio-console.
+fn init_tracing() {
+    #[cfg(feature = "tokio-console")]
+    console_subscriber::init();
+    #[cfg(not(feature = "tokio-console"))]
+    tracing::subscriber::set_global_default(tracing_subscriber::fmt::Subscriber::new())
+        .expect("setting tracing default failed");
+}
+
+/// Environment variables starting with this prefix override fields of the configuration file.
+const CONFIG_ENV_PREFIX: &str = "BLOOM_";
+
+fn exit_on<T>(err: impl Display) -> T {
+    eprintln!("{}", err);
+    std::process::exit(1)
 }
@@ -0,0 +1,364 @@
+use std::net::SocketAddr;
+
+use cml_chain::PolicyId;
+use cml_crypto::{Ed25519KeyHash, TransactionHash};
+use log::{trace, warn};
+use num_rational::Ratio;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use bloom_offchain::execution_engine::execution_report::{ExecutionReports, ExecutionReportsRocksDB};
+use bloom_offchain::execution_engine::liquidity_book::side::Side;
+use bloom_offchain::execution_engine::liquidity_book::TLB;
+use bloom_offchain::execution_engine::pool_stats::PoolStatsRegistry;
+use bloom_offchain::execution_engine::reserve_history::ReserveHistory;
+use bloom_offchain::quote::QuoteBooks;
+use bloom_offchain_cardano::orders::limit::LimitOrder;
+use bloom_offchain_cardano::orders::AnyOrder;
+use spectrum_cardano_lib::address::{PlutusAddress, PlutusCredential};
+use spectrum_cardano_lib::ex_units::ExUnits;
+use spectrum_cardano_lib::AssetClass;
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::data::pool::AnyPool;
+use spectrum_offchain_cardano::data::refund::Refunds;
+
+use crate::context::MakerContext;
+
+pub type AgentQuoteBooks = QuoteBooks<PairId, TLB<AnyOrder, AnyPool, ExUnits>, MakerContext>;
+pub type AgentExecutionReports = ExecutionReportsRocksDB<PolicyId, TransactionHash>;
+pub type AgentPoolStats = PoolStatsRegistry<PolicyId>;
+pub type AgentReserveHistory = ReserveHistory<PairId, PolicyId>;
+
+/// Order lookup request, e.g. `id=<beacon>`.
+fn parse_order_id(query: &str) -> Option<PolicyId> {
+    match query.split_once('=')? {
+        ("id", value) => PolicyId::from_hex(value).ok(),
+        _ => None,
+    }
+}
+
+/// Reserve history request, e.g. `pool=<policy>`.
+fn parse_pool_id(query: &str) -> Option<PolicyId> {
+    match query.split_once('=')? {
+        ("pool", value) => PolicyId::from_hex(value).ok(),
+        _ => None,
+    }
+}
+
+/// Refunds request, e.g. `owner=<payment key hash>`.
+fn parse_owner(query: &str) -> Option<Ed25519KeyHash> {
+    match query.split_once('=')? {
+        ("owner", value) => Ed25519KeyHash::from_hex(value).ok(),
+        _ => None,
+    }
+}
+
+/// Volatility request, e.g. `pair=Native-<policy>.<name>`.
+fn parse_pair(query: &str) -> Option<PairId> {
+    match query.split_once('=')? {
+        ("pair", value) => parse_assets(value).map(|(x, y)| PairId::canonical(x, y)),
+        _ => None,
+    }
+}
+
+fn parse_assets(value: &str) -> Option<(AssetClass, AssetClass)> {
+    let (x, y) = value.split_once('-')?;
+    Some((AssetClass::try_from(x).ok()?, AssetClass::try_from(y).ok()?))
+}
+
+/// Pool stats request, e.g. `epoch=512`, stats of all retained epochs if empty.
+fn parse_epoch(query: &str) -> Option<Option<u64>> {
+    if query.is_empty() {
+        return Some(None);
+    }
+    match query.split_once('=')? {
+        ("epoch", value) => value.parse().ok().map(Some),
+        _ => None,
+    }
+}
+
+/// Quote request, e.g. `pair=Native-<policy>.<name>&side=bid&amount=1000000`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct QuoteRequest {
+    base: AssetClass,
+    quote: AssetClass,
+    side: Side,
+    amount: u64,
+    /// Number of rungs in case a ladder is requested.
+    steps: usize,
+}
+
+impl QuoteRequest {
+    fn parse(query: &str) -> Option<Self> {
+        let mut pair = None;
+        let mut side = None;
+        let mut amount = None;
+        let mut steps = DEFAULT_LADDER_STEPS;
+        for param in query.split('&') {
+            match param.split_once('=')? {
+                ("pair", value) => pair = Some(parse_assets(value)?),
+                ("side", "bid") => side = Some(Side::Bid),
+                ("side", "ask") => side = Some(Side::Ask),
+                ("amount", value) => amount = value.parse().ok(),
+                ("steps", value) => steps = value.parse().ok()?,
+                _ => return None,
+            }
+        }
+        let (base, quote) = pair?;
+        Some(Self {
+            base,
+            quote,
+            side: side?,
+            amount: amount.filter(|a| *a > 0)?,
+            steps,
+        })
+    }
+
+    fn pair(&self) -> PairId {
+        PairId::canonical(self.base, self.quote)
+    }
+
+    /// Taker that accepts any price so that the book decides how much it gets.
+    fn to_taker(self, amount: u64) -> AnyOrder {
+        let (input_asset, output_asset) = self.side.select((self.quote, self.base), (self.base, self.quote));
+        AnyOrder::Limit(LimitOrder {
+            beacon: PolicyId::from([0u8; 28]),
+            input_asset,
+            input_amount: amount,
+            output_asset,
+            output_amount: 0,
+            base_price: Ratio::new(1, PRICE_FLOOR_DENOM),
+            fee_asset: AssetClass::Native,
+            execution_budget: PREVIEW_EXECUTION_BUDGET,
+            fee: 0,
+            max_cost_per_ex_step: 1,
+            min_marginal_output: 0,
+            redeemer_address: PlutusAddress {
+                payment_cred: PlutusCredential::PubKey(Ed25519KeyHash::from([0u8; 28])),
+                stake_cred: None,
+            },
+            cancellation_pkh: Ed25519KeyHash::from([0u8; 28]),
+            requires_executor_sig: false,
+            virgin: false,
+            marginal_cost: ExUnits { mem: 0, steps: 0 },
+            arrived_at: 0,
+        })
+    }
+}
+
+const DEFAULT_LADDER_STEPS: usize = 10;
+const PRICE_FLOOR_DENOM: u128 = 1_000_000_000;
+const PREVIEW_EXECUTION_BUDGET: u64 = 1_000_000_000_000;
+
+/// Serves `/quote`, `/ladder`, `/order`, `/pools`, `/reserves`, `/volatility` and `/refunds` over plain HTTP.
+pub async fn serve_quotes(
+    addr: SocketAddr,
+    books: AgentQuoteBooks,
+    reports: AgentExecutionReports,
+    pool_stats: AgentPoolStats,
+    reserve_history: AgentReserveHistory,
+    refunds: Refunds,
+    clock: SharedClock,
+) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Cannot bind quote endpoint to {}: {}", addr, err);
+            return;
+        }
+    };
+    loop {
+        if let Ok((stream, _)) = listener.accept().await {
+            let books = books.clone();
+            let reports = reports.clone();
+            let pool_stats = pool_stats.clone();
+            let reserve_history = reserve_history.clone();
+            let refunds = refunds.clone();
+            let clock = clock.clone();
+            tokio::spawn(async move {
+                if let Err(err) = respond(
+                    stream,
+                    &books,
+                    &reports,
+                    &pool_stats,
+                    &reserve_history,
+                    &refunds,
+                    &clock,
+                )
+                .await
+                {
+                    trace!("Quote connection failed: {}", err);
+                }
+            });
+        }
+    }
+}
+
+const MAX_REQUEST_LEN: usize = 2048;
+
+async fn respond(
+    mut stream: TcpStream,
+    books: &AgentQuoteBooks,
+    reports: &AgentExecutionReports,
+    pool_stats: &AgentPoolStats,
+    reserve_history: &AgentReserveHistory,
+    refunds: &Refunds,
+    clock: &SharedClock,
+) -> std::io::Result<()> {
+    let mut buf = [0u8; MAX_REQUEST_LEN];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.lines().next().and_then(|line| {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("GET") => parts.next(),
+            _ => None,
+        }
+    });
+    let (status, body) = match path.map(|p| p.split_once('?').unwrap_or((p, ""))) {
+        Some(("/quote", query)) => match QuoteRequest::parse(query) {
+            Some(req) => {
+                let quote = books.quote(&req.pair(), req.to_taker(req.amount), clock.unix_time_secs());
+                ("200 OK", serde_json::to_string(&quote).unwrap())
+            }
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/ladder", query)) => match QuoteRequest::parse(query) {
+            Some(req) => {
+                let ladder = books.ladder(
+                    &req.pair(),
+                    req.amount,
+                    req.steps,
+                    clock.unix_time_secs(),
+                    |amount| req.to_taker(amount),
+                );
+                ("200 OK", serde_json::to_string(&ladder).unwrap())
+            }
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/order", query)) => match parse_order_id(query) {
+            Some(order_id) => (
+                "200 OK",
+                serde_json::to_string(&reports.reports(&order_id)).unwrap(),
+            ),
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/pools", query)) => match parse_epoch(query) {
+            Some(epoch) => (
+                "200 OK",
+                serde_json::to_string(&pool_stats.snapshot(epoch)).unwrap(),
+            ),
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/reserves", query)) => match parse_pool_id(query) {
+            Some(pool) => (
+                "200 OK",
+                serde_json::to_string(&reserve_history.series(&pool)).unwrap(),
+            ),
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/volatility", query)) => match parse_pair(query) {
+            Some(pair) => (
+                "200 OK",
+                serde_json::to_string(&reserve_history.volatility(&pair)).unwrap(),
+            ),
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(("/refunds", query)) => match parse_owner(query) {
+            Some(owner) => (
+                "200 OK",
+                serde_json::to_string(&refunds.of_owner(&owner)).unwrap(),
+            ),
+            None => ("400 Bad Request", String::new()),
+        },
+        Some(_) => ("404 Not Found", String::new()),
+        None => ("400 Bad Request", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use bloom_offchain::execution_engine::liquidity_book::side::Side;
+    use spectrum_cardano_lib::AssetClass;
+
+    use spectrum_offchain_cardano::data::pair::PairId;
+
+    use crate::quote_api::{
+        parse_epoch, parse_order_id, parse_owner, parse_pair, parse_pool_id, QuoteRequest,
+    };
+
+    const TOKEN: &str = "f6099832f9563e4cf59602b3351c3c5a8a7dda2d44575ef69b82cf8d.4144414f";
+
+    #[test]
+    fn parses_quote_request() {
+        let query = format!("pair=Native-{}&side=bid&amount=1000000&steps=4", TOKEN);
+        let req = QuoteRequest::parse(&query).unwrap();
+        assert_eq!(req.base, AssetClass::Native);
+        assert_eq!(req.quote, AssetClass::try_from(TOKEN).unwrap());
+        assert_eq!(req.side, Side::Bid);
+        assert_eq!(req.amount, 1_000_000);
+        assert_eq!(req.steps, 4);
+    }
+
+    #[test]
+    fn rejects_incomplete_request() {
+        assert!(QuoteRequest::parse("pair=Native-Native&amount=10").is_none());
+        assert!(QuoteRequest::parse("pair=Native&side=ask&amount=10").is_none());
+        assert!(QuoteRequest::parse("pair=Native-Native&side=ask&amount=0").is_none());
+        assert!(QuoteRequest::parse("pair=Native-Native&side=ask&amount=10&steps=x").is_none());
+    }
+
+    #[test]
+    fn parses_order_lookup_request() {
+        let beacon = "f6099832f9563e4cf59602b3351c3c5a8a7dda2d44575ef69b82cf8d";
+        assert_eq!(
+            parse_order_id(&format!("id={}", beacon)).map(|id| id.to_hex()),
+            Some(beacon.to_string())
+        );
+        assert!(parse_order_id("id=xyz").is_none());
+        assert!(parse_order_id(&format!("beacon={}", beacon)).is_none());
+    }
+
+    #[test]
+    fn parses_pool_stats_request() {
+        assert_eq!(parse_epoch(""), Some(None));
+        assert_eq!(parse_epoch("epoch=512"), Some(Some(512)));
+        assert!(parse_epoch("epoch=last").is_none());
+    }
+
+    #[test]
+    fn parses_reserve_history_requests() {
+        let pool = "f6099832f9563e4cf59602b3351c3c5a8a7dda2d44575ef69b82cf8d";
+        assert_eq!(
+            parse_pool_id(&format!("pool={}", pool)).map(|id| id.to_hex()),
+            Some(pool.to_string())
+        );
+        assert!(parse_pool_id(&format!("id={}", pool)).is_none());
+        let token = AssetClass::try_from(TOKEN).unwrap();
+        assert_eq!(
+            parse_pair(&format!("pair={}-Native", TOKEN)),
+            Some(PairId::canonical(AssetClass::Native, token))
+        );
+        assert!(parse_pair("pair=Native").is_none());
+    }
+
+    #[test]
+    fn parses_refunds_request() {
+        let owner = "f6099832f9563e4cf59602b3351c3c5a8a7dda2d44575ef69b82cf8d";
+        assert_eq!(
+            parse_owner(&format!("owner={}", owner)).map(|pkh| pkh.to_hex()),
+            Some(owner.to_string())
+        );
+        assert!(parse_owner("owner=xyz").is_none());
+        assert!(parse_owner(&format!("pkh={}", owner)).is_none());
+    }
+}
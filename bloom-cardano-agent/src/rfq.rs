@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use cml_chain::certs::StakeCredential;
+use cml_chain::crypto::Vkeywitness;
+use cml_chain::transaction::Transaction;
+use cml_core::serialization::{Deserialize, Serialize};
+use cml_crypto::{Ed25519KeyHash, RawBytesEncoding};
+use either::Either;
+use futures::channel::mpsc;
+use futures::SinkExt;
+use isahc::{AsyncReadResponseExt, Request, RequestExt};
+use log::{trace, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::{RfqConfig, RfqMaker};
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain_cardano::event_sink::EvolvingCardanoEntity;
+use bloom_offchain_cardano::orders::rfq::RfqQuote;
+use bloom_offchain_cardano::orders::AnyOrder;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::transaction::{OutboundTransaction, TransactionOutputExtension};
+use spectrum_cardano_lib::OutputRef;
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, StateUpdate};
+use spectrum_offchain::data::{Baked, Tradable};
+use spectrum_offchain::network::Network;
+use spectrum_offchain::partitioning::Partitioned;
+use spectrum_offchain::tx_hash::CanonicalHash;
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::tx_submission::RejectReasons;
+
+/// UTxOs backing live quotes along with their makers and expiration time.
+#[derive(Clone, Default)]
+pub struct RfqFunds(Arc<Mutex<HashMap<OutputRef, (Ed25519KeyHash, u64)>>>);
+
+impl RfqFunds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns `false` if the UTxO already backs a live quote.
+    fn register(&self, funds: OutputRef, maker: Ed25519KeyHash, expires_at: u64, now: u64) -> bool {
+        let mut funds_by_ref = self.0.lock().unwrap();
+        funds_by_ref.retain(|_, (_, exp)| *exp > now);
+        if funds_by_ref.contains_key(&funds) {
+            return false;
+        }
+        funds_by_ref.insert(funds, (maker, expires_at));
+        true
+    }
+
+    fn maker_of(&self, funds: &OutputRef) -> Option<Ed25519KeyHash> {
+        self.0.lock().unwrap().get(funds).map(|(maker, _)| *maker)
+    }
+
+    /// Quotes partially taken in `tx` stay backed by the change returned to their makers.
+    fn on_settled(&self, tx: &Transaction) {
+        let tx_hash = hash_transaction_canonical(&tx.body);
+        let mut funds_by_ref = self.0.lock().unwrap();
+        let mut settled = vec![];
+        for input in &tx.body.inputs {
+            if let Some(funds) = funds_by_ref.remove(&OutputRef::from(input.clone())) {
+                settled.push(funds);
+            }
+        }
+        for (ix, output) in tx.body.outputs.iter().enumerate() {
+            if let Some(StakeCredential::PubKey { hash, .. }) = output.address().payment_cred() {
+                if let Some(funds) = settled.iter().find(|(maker, _)| maker == hash) {
+                    funds_by_ref.insert(OutputRef::new(tx_hash, ix as u64), *funds);
+                }
+            }
+        }
+    }
+}
+
+pub type RfqTopic =
+    Partitioned<4, PairId, mpsc::Sender<(PairId, Channel<StateUpdate<EvolvingCardanoEntity>>)>>;
+
+/// Accepts quotes POSTed as JSON to `/quote` and feeds them into the books as fragments.
+pub async fn serve_rfq(conf: RfqConfig, funds: RfqFunds, topic: RfqTopic, clock: SharedClock) {
+    let listener = match TcpListener::bind(conf.listen_addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Cannot bind RFQ endpoint to {}: {}", conf.listen_addr, err);
+            return;
+        }
+    };
+    loop {
+        if let Ok((stream, _)) = listener.accept().await {
+            let conf = conf.clone();
+            let funds = funds.clone();
+            let topic = topic.clone();
+            let clock = clock.clone();
+            tokio::spawn(async move {
+                if let Err(err) = respond(stream, &conf, &funds, topic, &clock).await {
+                    trace!("RFQ connection failed: {}", err);
+                }
+            });
+        }
+    }
+}
+
+const MAX_REQUEST_LEN: usize = 16384;
+
+async fn respond(
+    mut stream: TcpStream,
+    conf: &RfqConfig,
+    funds: &RfqFunds,
+    mut topic: RfqTopic,
+    clock: &SharedClock,
+) -> std::io::Result<()> {
+    let mut buf = vec![0u8; MAX_REQUEST_LEN];
+    let mut n = 0;
+    // Read until the whole body announced by Content-Length is there.
+    let (head_len, body_len) = loop {
+        let read = stream.read(&mut buf[n..]).await?;
+        n += read;
+        let request = String::from_utf8_lossy(&buf[..n]);
+        if let Some(head_len) = request.find("\r\n\r\n").map(|ix| ix + 4) {
+            let body_len = request[..head_len]
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, len)| len.trim().parse::<usize>().ok())
+                .unwrap_or(0);
+            if n >= head_len + body_len || read == 0 || n == MAX_REQUEST_LEN {
+                break (head_len, body_len);
+            }
+        } else if read == 0 || n == MAX_REQUEST_LEN {
+            break (n, 0);
+        }
+    };
+    let request_line = String::from_utf8_lossy(&buf[..head_len])
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let body = &buf[head_len..n.min(head_len + body_len)];
+    let (status, body) = match request_line.split_whitespace().take(2).collect::<Vec<_>>()[..] {
+        ["POST", "/quote"] => match serde_json::from_slice::<RfqQuote>(body) {
+            Ok(quote) => accept_quote(quote, conf, funds, &mut topic, clock.unix_time_secs()).await,
+            Err(err) => ("400 Bad Request", err.to_string()),
+        },
+        [_, _] => ("404 Not Found", String::new()),
+        _ => ("400 Bad Request", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn accept_quote(
+    quote: RfqQuote,
+    conf: &RfqConfig,
+    funds: &RfqFunds,
+    topic: &mut RfqTopic,
+    now: u64,
+) -> (&'static str, String) {
+    let (order, bearer) = match quote.verify(now, conf.max_quote_ttl.as_secs()) {
+        Ok(verified) => verified,
+        Err(err) => {
+            return (
+                "400 Bad Request",
+                serde_json::json!({ "error": err.to_string() }).to_string(),
+            )
+        }
+    };
+    if !conf.makers.iter().any(|m| m.key_hash == order.maker) {
+        return ("403 Forbidden", String::new());
+    }
+    if !funds.register(bearer.1, order.maker, order.expires_at, now) {
+        return ("409 Conflict", String::new());
+    }
+    let pair = order.pair_id();
+    let id = order.id.to_hex();
+    let version = bearer.1;
+    let entity = EvolvingCardanoEntity(Bundled(
+        Either::Left(Baked::new(AnyOrder::Rfq(order), version)),
+        bearer,
+    ));
+    // Quotes never make it on-chain, so they are fed as unconfirmed states.
+    let upd = Channel::mempool(StateUpdate::Transition(Ior::Right(entity)));
+    if topic.get_mut(pair).send((pair, upd)).await.is_err() {
+        return ("503 Service Unavailable", String::new());
+    }
+    trace!("Accepted RFQ quote {} in pair {}", id, pair);
+    ("202 Accepted", serde_json::json!({ "id": id }).to_string())
+}
+
+#[derive(serde::Deserialize)]
+struct CosignResponse {
+    /// Hex-encoded CBOR of the maker's vkey witness.
+    witness: String,
+}
+
+/// Obtains co-signatures of RFQ makers whose funds a TX spends before submitting it.
+#[derive(Clone)]
+pub struct CosigningNetwork<N> {
+    inner: N,
+    funds: RfqFunds,
+    endpoints: Arc<HashMap<Ed25519KeyHash, String>>,
+}
+
+impl<N> CosigningNetwork<N> {
+    pub fn new(inner: N, funds: RfqFunds, makers: Vec<RfqMaker>) -> Self {
+        Self {
+            inner,
+            funds,
+            endpoints: Arc::new(
+                makers
+                    .into_iter()
+                    .map(|m| (m.key_hash, m.cosign_endpoint))
+                    .collect(),
+            ),
+        }
+    }
+
+    async fn cosign(&self, tx: &Transaction, maker: Ed25519KeyHash) -> Option<Vkeywitness> {
+        let endpoint = self.endpoints.get(&maker)?;
+        let tx_hash = hash_transaction_canonical(&tx.body);
+        let body = serde_json::json!({
+            "txHash": tx_hash.to_hex(),
+            "tx": hex::encode(tx.to_cbor_bytes()),
+        });
+        let mut resp = Request::post(endpoint)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .ok()?
+            .send_async()
+            .await
+            .ok()?;
+        let CosignResponse { witness } = resp.json().await.ok()?;
+        let witness = Vkeywitness::from_cbor_bytes(&hex::decode(witness).ok()?).ok()?;
+        let valid = witness.vkey.hash() == maker
+            && witness
+                .vkey
+                .verify(tx_hash.to_raw_bytes(), &witness.ed25519_signature);
+        valid.then_some(witness)
+    }
+}
+
+#[async_trait::async_trait]
+impl<N> Network<OutboundTransaction<Transaction>, RejectReasons> for CosigningNetwork<N>
+where
+    N: Network<OutboundTransaction<Transaction>, RejectReasons> + Send + Sync,
+{
+    async fn submit_tx(&mut self, tx: OutboundTransaction<Transaction>) -> Result<(), RejectReasons> {
+        let mut makers = tx
+            .body
+            .inputs
+            .iter()
+            .filter_map(|i| self.funds.maker_of(&OutputRef::from(i.clone())))
+            .collect::<Vec<_>>();
+        if makers.is_empty() {
+            return self.inner.submit_tx(tx).await;
+        }
+        makers.sort();
+        makers.dedup();
+        let mut signed: Transaction = (*tx).clone();
+        for maker in makers {
+            match self.cosign(&signed, maker).await {
+                Some(witness) => signed
+                    .witness_set
+                    .vkeywitnesses
+                    .get_or_insert_with(Vec::new)
+                    .push(witness),
+                None => {
+                    warn!("Maker {} did not co-sign TX {}", maker, tx.canonical_hash());
+                    return Err(RejectReasons(vec![]));
+                }
+            }
+        }
+        let result = self.inner.submit_tx(signed.clone().into()).await;
+        if result.is_ok() {
+            self.funds.on_settled(&signed);
+        }
+        result
+    }
+}
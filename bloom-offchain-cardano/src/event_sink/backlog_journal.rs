@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use cml_chain::transaction::TransactionOutput;
+use cml_core::serialization::{Deserialize, Serialize};
+use cml_multi_era::babbage::BabbageTransactionOutput;
+use log::{trace, warn};
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use spectrum_cardano_lib::era::AnyEraOutput;
+use spectrum_cardano_lib::OutputRef;
+use spectrum_offchain::data::event::{Channel, Confirmed};
+use spectrum_offchain::data::order::{OrderUpdate, SpecializedOrder};
+use spectrum_offchain::ledger::TryFromLedger;
+use spectrum_offchain::rocks::{migrate, Migration, RocksConfig};
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::utxo::ConsumedInputs;
+
+use crate::event_sink::context::{HandlerContext, HandlerContextProto};
+use crate::event_sink::AtomicCardanoEntity;
+
+/// Order as it is persisted in the journal.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct JournalEntry {
+    pair: PairId,
+    output_ref: OutputRef,
+    /// CBOR of the bearer, the order itself is parsed out of it upon recovery.
+    bearer: Vec<u8>,
+}
+
+/// Migrations of the journal, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+/// Durable record of confirmed specialized orders which lets the backlog survive restarts of the agent.
+/// An order is recorded once its creation is confirmed and forgotten once its elimination is confirmed.
+#[derive(Clone)]
+pub struct BacklogJournalRocksDB {
+    db: Arc<rocksdb::DB>,
+}
+
+impl BacklogJournalRocksDB {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = rocksdb::DB::open_default(conf.db_path).unwrap();
+        migrate(&db, "backlog_journal", MIGRATIONS);
+        Self { db: Arc::new(db) }
+    }
+
+    pub fn observe(
+        &self,
+        pair: PairId,
+        update: &Channel<OrderUpdate<AtomicCardanoEntity, AtomicCardanoEntity>>,
+    ) {
+        if let Channel::Ledger(Confirmed(upd)) = update {
+            match upd {
+                OrderUpdate::Created(order) => self.record(pair, order),
+                OrderUpdate::Eliminated(order) => self.forget(order.get_self_ref()),
+            }
+        }
+    }
+
+    fn record(&self, pair: PairId, AtomicCardanoEntity(Bundled(_, bearer)): &AtomicCardanoEntity) {
+        let output_ref = bearer.reference();
+        let entry = JournalEntry {
+            pair,
+            output_ref,
+            bearer: bearer.0.to_cbor_bytes(),
+        };
+        self.db
+            .put(
+                bincode::serialize(&output_ref).unwrap(),
+                bincode::serialize(&entry).unwrap(),
+            )
+            .unwrap();
+    }
+
+    fn forget(&self, output_ref: OutputRef) {
+        self.db.delete(bincode::serialize(&output_ref).unwrap()).unwrap();
+    }
+
+    /// Restore orders recorded by previous runs.
+    /// Entries which no longer parse into an order under the given context are dropped.
+    pub fn recover(&self, context: HandlerContextProto) -> Vec<(PairId, AtomicCardanoEntity)> {
+        let entries = self
+            .db
+            .iterator(rocksdb::IteratorMode::Start)
+            .filter_map(|i| {
+                let (_, v) = i.unwrap();
+                bincode::deserialize::<JournalEntry>(&v).ok()
+            })
+            .collect::<Vec<_>>();
+        let mut recovered = vec![];
+        for entry in entries {
+            match parse_order(&entry, context) {
+                Some(order) => {
+                    trace!("Order {} recovered from journal", entry.output_ref);
+                    recovered.push((entry.pair, order));
+                }
+                None => {
+                    warn!("Dropping unrecognized journal entry {}", entry.output_ref);
+                    self.forget(entry.output_ref);
+                }
+            }
+        }
+        recovered
+    }
+}
+
+fn parse_order(entry: &JournalEntry, context: HandlerContextProto) -> Option<AtomicCardanoEntity> {
+    let bearer = TransactionOutput::from_cbor_bytes(&entry.bearer).ok()?;
+    let bearer = BabbageTransactionOutput::try_from(AnyEraOutput::from(bearer)).ok()?;
+    let ctx = HandlerContext::new(entry.output_ref, ConsumedInputs::new(std::iter::empty()), context);
+    AtomicCardanoEntity::try_from_ledger(&bearer, &ctx)
+}
@@ -1,8 +1,12 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use type_equalities::IsEqual;
 
+use bloom_offchain::execution_engine::types::Time;
+
 use spectrum_cardano_lib::OutputRef;
 use spectrum_offchain::data::Has;
-use spectrum_offchain_cardano::creds::OperatorCred;
+use spectrum_offchain_cardano::creds::OperatorCredSet;
 use spectrum_offchain_cardano::data::deposit::DepositOrderBounds;
 use spectrum_offchain_cardano::data::pool::PoolBounds;
 use spectrum_offchain_cardano::data::redeem::RedeemOrderBounds;
@@ -21,7 +25,7 @@ use crate::orders::limit::LimitOrderBounds;
 
 #[derive(Copy, Clone, Debug)]
 pub struct HandlerContextProto {
-    pub executor_cred: OperatorCred,
+    pub executor_creds: OperatorCredSet,
     pub scripts: ProtocolScriptHashes,
     pub bounds: Bounds,
 }
@@ -30,9 +34,17 @@ pub struct HandlerContextProto {
 pub struct HandlerContext {
     pub output_ref: OutputRef,
     pub consumed_utxos: ConsumedInputs,
-    pub executor_cred: OperatorCred,
+    pub executor_creds: OperatorCredSet,
     pub scripts: ProtocolScriptHashes,
     pub bounds: Bounds,
+    /// When the output was observed.
+    pub time: Time,
+}
+
+impl Has<Time> for HandlerContext {
+    fn select<U: IsEqual<Time>>(&self) -> Time {
+        self.time
+    }
 }
 
 impl Has<LimitOrderBounds> for HandlerContext {
@@ -234,9 +246,14 @@ impl HandlerContext {
         Self {
             output_ref,
             consumed_utxos,
-            executor_cred: prototype.executor_cred,
+            executor_creds: prototype.executor_creds,
             scripts: prototype.scripts,
             bounds: prototype.bounds,
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+                .into(),
         }
     }
 }
@@ -247,8 +264,8 @@ impl Has<OutputRef> for HandlerContext {
     }
 }
 
-impl Has<OperatorCred> for HandlerContext {
-    fn select<U: IsEqual<OperatorCred>>(&self) -> OperatorCred {
-        self.executor_cred
+impl Has<OperatorCredSet> for HandlerContext {
+    fn select<U: IsEqual<OperatorCredSet>>(&self) -> OperatorCredSet {
+        self.executor_creds
     }
 }
@@ -15,12 +15,17 @@ pub trait TradableEntityIndex<T: EntitySnapshot + Tradable> {
     fn register_for_eviction(&mut self, ver: T::Version);
     /// Evict outdated entries.
     fn run_eviction(&mut self);
+    /// Latest version of the entity delivered downstream and whether it was confirmed.
+    fn last_delivered(&self, id: &T::StableId) -> Option<(T::Version, bool)>;
+    fn set_delivered(&mut self, id: T::StableId, ver: T::Version, confirmed: bool);
+    fn forget_delivered(&mut self, id: &T::StableId);
 }
 
 #[derive(Clone)]
 pub struct InMemoryEntityIndex<T: EntitySnapshot + Tradable> {
     store: HashMap<T::Version, T>,
     permanent_pairs: HashMap<T::StableId, T::PairId>,
+    delivered: HashMap<T::StableId, (T::Version, bool)>,
     eviction_queue: VecDeque<(SystemTime, T::Version)>,
     eviction_delay: Duration,
 }
@@ -30,6 +35,7 @@ impl<T: EntitySnapshot + Tradable> InMemoryEntityIndex<T> {
         Self {
             store: Default::default(),
             permanent_pairs: Default::default(),
+            delivered: Default::default(),
             eviction_queue: Default::default(),
             eviction_delay,
         }
@@ -81,6 +87,18 @@ where
             break;
         }
     }
+
+    fn last_delivered(&self, id: &T::StableId) -> Option<(T::Version, bool)> {
+        self.delivered.get(id).copied()
+    }
+
+    fn set_delivered(&mut self, id: T::StableId, ver: T::Version, confirmed: bool) {
+        self.delivered.insert(id, (ver, confirmed));
+    }
+
+    fn forget_delivered(&mut self, id: &T::StableId) {
+        self.delivered.remove(id);
+    }
 }
 
 pub struct EntityIndexTracing<R> {
@@ -129,4 +147,18 @@ where
         trace!(target: "offchain", "EntityIndex::run_eviction()");
         self.inner.run_eviction()
     }
+
+    fn last_delivered(&self, id: &T::StableId) -> Option<(T::Version, bool)> {
+        self.inner.last_delivered(id)
+    }
+
+    fn set_delivered(&mut self, id: T::StableId, ver: T::Version, confirmed: bool) {
+        trace!(target: "offchain", "EntityIndex::set_delivered({}, {}, {})", id, ver, confirmed);
+        self.inner.set_delivered(id, ver, confirmed)
+    }
+
+    fn forget_delivered(&mut self, id: &T::StableId) {
+        trace!(target: "offchain", "EntityIndex::forget_delivered({})", id);
+        self.inner.forget_delivered(id)
+    }
 }
@@ -8,7 +8,9 @@ use std::sync::Arc;
 use crate::event_sink::context::{HandlerContext, HandlerContextProto};
 use crate::event_sink::entity_index::TradableEntityIndex;
 use crate::event_sink::order_index::KvIndex;
+use crate::event_sink::pool_lifecycle::TransitionObserver;
 use crate::event_sink::processed_tx::ProcessedTransaction;
+use crate::execution_engine::ref_inputs::RefInputRegistry;
 use async_trait::async_trait;
 use bloom_offchain::execution_engine::funding_effect::FundingEvent;
 use cardano_chain_sync::data::LedgerTxEvent;
@@ -30,6 +32,7 @@ use spectrum_offchain::data::Tradable;
 use spectrum_offchain::event_sink::event_handler::EventHandler;
 use spectrum_offchain::ledger::TryFromLedger;
 use spectrum_offchain::partitioning::Partitioned;
+use spectrum_offchain_cardano::fee_tuning::SharedFeeTuner;
 use spectrum_offchain_cardano::funding::FundingAddresses;
 use spectrum_offchain_cardano::utxo::ConsumedInputs;
 use tokio::sync::{Mutex, MutexGuard};
@@ -68,10 +71,6 @@ async fn extract_funding_events<const N: usize, Index>(
 where
     Index: KvIndex<OutputRef, (usize, FinalizedTxOut)>,
 {
-    let num_outputs = tx.outputs.len();
-    if num_outputs == 0 {
-        return Err(tx);
-    }
     let mut consumed_utxos = vec![];
     for i in &tx.inputs {
         let oref = OutputRef::from((i.transaction_id, i.index));
@@ -263,12 +262,73 @@ where
 
 /// A handler for updates that routes resulted [Entity] updates
 /// into different topics [Topic] according to partitioning key [PairId].
+/// Keeps [RefInputRegistry] up to date with confirmed UTxOs holding tracked NFTs.
 #[derive(Clone)]
-pub struct PairUpdateHandler<const N: usize, PairId, Topic, Entity, Index> {
+pub struct RefInputHandler {
+    registry: RefInputRegistry,
+}
+
+impl RefInputHandler {
+    pub fn new(registry: RefInputRegistry) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait(?Send)]
+impl EventHandler<LedgerTxEvent<ProcessedTransaction>> for RefInputHandler {
+    async fn try_handle(
+        &mut self,
+        ev: LedgerTxEvent<ProcessedTransaction>,
+    ) -> Option<LedgerTxEvent<ProcessedTransaction>> {
+        match &ev {
+            LedgerTxEvent::TxApplied { tx, .. } => {
+                for (ix, o) in &tx.outputs {
+                    if self.registry.is_tracked(o.value()) {
+                        trace!("Reference input {}#{} observed", tx.hash, ix);
+                        self.registry
+                            .observe(TransactionInput::new(tx.hash, *ix as u64), &o.clone().upcast());
+                    }
+                }
+            }
+            LedgerTxEvent::TxUnapplied(tx) => self.registry.forget(tx.hash),
+        }
+        Some(ev)
+    }
+}
+
+/// Feeds confirmations of TXs into [SharedFeeTuner], so that only executions which made it on-chain are observed.
+#[derive(Clone)]
+pub struct FeeTuningHandler {
+    fee_tuner: SharedFeeTuner,
+}
+
+impl FeeTuningHandler {
+    pub fn new(fee_tuner: SharedFeeTuner) -> Self {
+        Self { fee_tuner }
+    }
+}
+
+#[async_trait(?Send)]
+impl EventHandler<LedgerTxEvent<ProcessedTransaction>> for FeeTuningHandler {
+    async fn try_handle(
+        &mut self,
+        ev: LedgerTxEvent<ProcessedTransaction>,
+    ) -> Option<LedgerTxEvent<ProcessedTransaction>> {
+        if let LedgerTxEvent::TxApplied { tx, .. } = &ev {
+            self.fee_tuner.on_tx_confirmed(&tx.hash);
+        }
+        Some(ev)
+    }
+}
+
+#[derive(Clone)]
+pub struct PairUpdateHandler<const N: usize, PairId, Topic, Entity, Index, Observer = ()> {
     pub topic: Partitioned<N, PairId, Topic>,
     /// Index of all non-consumed states of [Entity].
     pub index: Arc<Mutex<Index>>,
     pub context: HandlerContextProto,
+    /// Notified of every confirmed transition.
+    pub observer: Observer,
     pub pd: PhantomData<Entity>,
 }
 
@@ -282,9 +342,23 @@ impl<const N: usize, PairId, Topic, Entity, Index> PairUpdateHandler<N, PairId,
             topic,
             index,
             context,
+            observer: (),
             pd: Default::default(),
         }
     }
+
+    pub fn observed_by<Observer>(
+        self,
+        observer: Observer,
+    ) -> PairUpdateHandler<N, PairId, Topic, Entity, Index, Observer> {
+        PairUpdateHandler {
+            topic: self.topic,
+            index: self.index,
+            context: self.context,
+            observer,
+            pd: self.pd,
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -341,6 +415,9 @@ where
                         let mut index = self.order_index.lock().await;
                         index.run_eviction();
                         for tr in transitions {
+                            if is_known_order(&index, &tr) {
+                                continue;
+                            }
                             if let Some(pair) = pool_index.pair_of(&pool_ref_of(&tr)) {
                                 index_atomic_transition(&mut index, &tr);
                                 let upd = Channel::ledger(tr.into());
@@ -443,6 +520,9 @@ where
                         let mut index = self.order_index.lock().await;
                         index.run_eviction();
                         for tr in transitions {
+                            if is_known_order(&index, &tr) {
+                                continue;
+                            }
                             if let Some(pair) = pool_index.pair_of(&pool_ref_of(&tr)) {
                                 index_atomic_transition(&mut index, &tr);
                                 let upd = Channel::mempool(tr.into());
@@ -492,10 +572,6 @@ where
     Order::TOrderId: From<OutputRef> + Display,
     Index: KvIndex<Order::TOrderId, Order>,
 {
-    let num_outputs = tx.outputs.len();
-    if num_outputs == 0 {
-        return Err(tx);
-    }
     let mut consumed_orders = HashMap::<Order::TOrderId, Order>::new();
     let mut consumed_utxos = Vec::new();
     for i in &tx.inputs {
@@ -560,10 +636,6 @@ where
     Entity::Version: From<OutputRef>,
     Index: TradableEntityIndex<Entity>,
 {
-    let num_outputs = tx.outputs.len();
-    if num_outputs == 0 {
-        return Err(tx);
-    }
     let mut consumed_entities = HashMap::<Entity::StableId, Entity>::new();
     let mut consumed_utxos = Vec::new();
     for i in &tx.inputs {
@@ -627,9 +699,10 @@ fn pair_id_of<T: Tradable>(xa: &Ior<T, T>) -> T::PairId {
 }
 
 #[async_trait(?Send)]
-impl<const N: usize, PairId, Topic, Entity, Index> EventHandler<LedgerTxEvent<ProcessedTransaction>>
-    for PairUpdateHandler<N, PairId, Topic, Entity, Index>
+impl<const N: usize, PairId, Topic, Entity, Index, Observer> EventHandler<LedgerTxEvent<ProcessedTransaction>>
+    for PairUpdateHandler<N, PairId, Topic, Entity, Index, Observer>
 where
+    Observer: TransitionObserver<Entity>,
     PairId: Copy + Hash + Eq,
     Topic: Sink<(PairId, Channel<StateUpdate<Entity>>)> + Unpin,
     Topic::Error: Debug,
@@ -654,7 +727,11 @@ where
                         let mut index = self.index.lock().await;
                         index.run_eviction();
                         for tr in transitions {
+                            if !admit_transition(&mut index, &tr, true) {
+                                continue;
+                            }
                             index_transition(&mut index, &tr);
+                            self.observer.on_confirmed(&tr);
                             let pair = pair_id_of(&tr);
                             let upd = Channel::ledger(StateUpdate::Transition(tr));
                             match updates.entry(pair) {
@@ -679,6 +756,9 @@ where
                         index.run_eviction();
                         for tr in transitions {
                             let inverse_tr = tr.swap();
+                            if !admit_transition(&mut index, &inverse_tr, true) {
+                                continue;
+                            }
                             index_transition(&mut index, &inverse_tr);
                             let pair = pair_id_of(&inverse_tr);
                             let upd = Channel::ledger(StateUpdate::TransitionRollback(inverse_tr));
@@ -711,9 +791,10 @@ where
 }
 
 #[async_trait(?Send)]
-impl<const N: usize, PairId, Topic, Entity, Index> EventHandler<MempoolUpdate<ProcessedTransaction>>
-    for PairUpdateHandler<N, PairId, Topic, Entity, Index>
+impl<const N: usize, PairId, Topic, Entity, Index, Observer> EventHandler<MempoolUpdate<ProcessedTransaction>>
+    for PairUpdateHandler<N, PairId, Topic, Entity, Index, Observer>
 where
+    Observer: TransitionObserver<Entity>,
     PairId: Copy + Hash + Eq,
     Topic: Sink<(PairId, Channel<StateUpdate<Entity>>)> + Unpin,
     Topic::Error: Debug,
@@ -738,6 +819,9 @@ where
                         let mut index = self.index.lock().await;
                         index.run_eviction();
                         for tr in transitions {
+                            if !admit_transition(&mut index, &tr, false) {
+                                continue;
+                            }
                             index_transition(&mut index, &tr);
                             let pair = pair_id_of(&tr);
                             let upd = Channel::mempool(StateUpdate::Transition(tr));
@@ -769,6 +853,59 @@ where
     }
 }
 
+/// Order created by a TX seen earlier, e.g. in mempool.
+fn is_known_order<Index, T>(index: &MutexGuard<Index>, tr: &Either<T, T>) -> bool
+where
+    T: SpecializedOrder,
+    T::TOrderId: Display,
+    Index: KvIndex<T::TOrderId, T>,
+{
+    match tr {
+        Either::Right(produced) if index.exists(&produced.get_self_ref()) => {
+            trace!("Order {} is already known", produced.get_self_ref());
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The same state may be delivered twice, by mempool and by ledger, in any order.
+/// Only the first delivery of a state passes downstream along with the one confirming it.
+/// Mempool transitions of entities from states other than the last confirmed one are stale.
+fn admit_transition<Index, T>(index: &mut MutexGuard<Index>, tr: &Ior<T, T>, confirmed: bool) -> bool
+where
+    T: EntitySnapshot + Tradable,
+    Index: TradableEntityIndex<T>,
+{
+    let id = match tr {
+        Ior::Left(st) | Ior::Right(st) | Ior::Both(st, _) => st.stable_id(),
+    };
+    let last_delivered = index.last_delivered(&id);
+    if let (Some((last_ver, true)), false, Ior::Left(consumed) | Ior::Both(consumed, _)) =
+        (last_delivered, confirmed, tr)
+    {
+        if consumed.version() != last_ver {
+            trace!("Stale transition of {} from {}", id, consumed.version());
+            return false;
+        }
+    }
+    match tr {
+        Ior::Right(produced) | Ior::Both(_, produced) => {
+            let ver = produced.version();
+            if let Some((last_ver, last_confirmed)) = last_delivered {
+                if last_ver == ver && (last_confirmed || !confirmed) {
+                    trace!("State {} of {} is already delivered", ver, id);
+                    return false;
+                }
+            }
+            index.set_delivered(id, ver, confirmed);
+        }
+        Ior::Left(_) if confirmed => index.forget_delivered(&id),
+        Ior::Left(_) => {}
+    }
+    true
+}
+
 fn index_atomic_transition<Index, T>(index: &mut MutexGuard<Index>, tr: &Either<T, T>)
 where
     T: SpecializedOrder + Clone,
@@ -812,7 +949,7 @@ mod tests {
     use cml_chain::address::{Address, RewardAddress};
     use cml_chain::certs::Credential;
     use cml_chain::transaction::TransactionInput;
-    use cml_crypto::{Ed25519KeyHash, ScriptHash};
+    use cml_crypto::{Ed25519KeyHash, ScriptHash, TransactionHash};
     use cml_multi_era::babbage::{
         BabbageFormatTxOut, BabbageTransaction, BabbageTransactionBody, BabbageTransactionOutput,
         BabbageTransactionWitnessSet,
@@ -835,14 +972,14 @@ mod tests {
     use spectrum_offchain::event_sink::event_handler::EventHandler;
     use spectrum_offchain::ledger::TryFromLedger;
     use spectrum_offchain::partitioning::Partitioned;
-    use spectrum_offchain_cardano::creds::OperatorCred;
+    use spectrum_offchain_cardano::creds::{OperatorCred, OperatorCredSet};
     use spectrum_offchain_cardano::data::deposit::DepositOrderBounds;
     use spectrum_offchain_cardano::data::pool::PoolBounds;
     use spectrum_offchain_cardano::data::redeem::RedeemOrderBounds;
     use spectrum_offchain_cardano::deployment::{DeployedScriptInfo, ProtocolScriptHashes};
 
     use crate::event_sink::entity_index::InMemoryEntityIndex;
-    use crate::event_sink::handler::{PairUpdateHandler, ProcessedTransaction};
+    use crate::event_sink::handler::{admit_transition, PairUpdateHandler, ProcessedTransaction};
     use crate::orders::limit::LimitOrderBounds;
 
     #[derive(Clone, Eq, PartialEq)]
@@ -949,9 +1086,12 @@ mod tests {
                 pool: PoolBounds {
                     min_n2t_lovelace: 1000,
                     min_t2t_lovelace: 1000,
+                    bootstrap_lovelace: 0,
+                    bootstrap_liquidity: 0,
+                    swap_deposit_surplus: false,
                 },
             },
-            executor_cred: ex_cred,
+            executor_creds: OperatorCredSet::new(ex_cred),
             scripts: ProtocolScriptHashes {
                 limit_order_witness: DeployedScriptInfo {
                     script_hash: ScriptHash::from([0u8; 28]),
@@ -1083,4 +1223,21 @@ mod tests {
         assert_eq!(e2_reversed, e2);
         assert_eq!(e1_revived, e1);
     }
+
+    #[tokio::test]
+    async fn state_delivered_by_mempool_and_ledger_passes_once_per_channel() {
+        let index = Mutex::new(InMemoryEntityIndex::new(Duration::from_secs(60)));
+        let mut index = index.lock().await;
+        let v1 = TrivialEntity(OutputRef::new(TransactionHash::from([0u8; 32]), 0), 1);
+        let v2 = TrivialEntity(OutputRef::new(TransactionHash::from([1u8; 32]), 0), 2);
+        assert!(admit_transition(&mut index, &Ior::Right(v1.clone()), true));
+        let tr = Ior::Both(v1.clone(), v2.clone());
+        assert!(admit_transition(&mut index, &tr, false));
+        assert!(!admit_transition(&mut index, &tr, false));
+        // Confirmation of the state seen in mempool.
+        assert!(admit_transition(&mut index, &tr, true));
+        assert!(!admit_transition(&mut index, &tr, true));
+        // Mempool lagging behind the ledger.
+        assert!(!admit_transition(&mut index, &tr, false));
+    }
 }
@@ -0,0 +1,299 @@
+use std::fmt::Debug;
+
+use async_trait::async_trait;
+use cml_chain::certs::StakeCredential;
+use cml_chain::plutus::PlutusData;
+use cml_crypto::ScriptHash;
+use cml_multi_era::babbage::BabbageTransactionOutput;
+use derive_more::Display;
+use futures::{Sink, SinkExt};
+use log::{trace, warn};
+
+use cardano_chain_sync::data::LedgerTxEvent;
+use spectrum_cardano_lib::plutus_data::DatumExtension;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::types::TryFromPData;
+use spectrum_cardano_lib::OutputRef;
+use spectrum_offchain::event_sink::event_handler::EventHandler;
+use spectrum_offchain_cardano::data::balance_pool::BalancePoolConfig;
+use spectrum_offchain_cardano::data::cfmm_pool::LegacyCFMMPoolConfig;
+use spectrum_offchain_cardano::data::deposit::OnChainDepositConfig;
+use spectrum_offchain_cardano::data::fee_switch_bidirectional_fee::FeeSwitchBidirectionalPoolConfig;
+use spectrum_offchain_cardano::data::fee_switch_pool::FeeSwitchPoolConfig;
+use spectrum_offchain_cardano::data::limit_swap::OnChainLimitSwapConfig;
+use spectrum_offchain_cardano::data::redeem::OnChainRedeemConfig;
+use spectrum_offchain_cardano::data::stable_pool_t2t::StablePoolT2TConfig;
+use spectrum_offchain_cardano::deployment::ProtocolScriptHashes;
+
+use crate::event_sink::processed_tx::ProcessedTransaction;
+use crate::orders::grid::DatumNative;
+use crate::orders::limit::Datum;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Display, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InvalidEntityReason {
+    /// Output carries no datum.
+    MissingDatum,
+    /// Datum is not inlined, so it can't be inspected.
+    DatumHashOnly,
+    /// Datum doesn't parse into the structure expected by the validator.
+    MalformedDatum,
+    /// Datum is well-formed, yet the entity was rejected: unexpected value, bounds violated,
+    /// or the entity is not meant to be executed by us.
+    Rejected,
+}
+
+/// UTxO at a known validator address which didn't parse into any entity.
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidEntity {
+    pub output_ref: OutputRef,
+    pub validator: &'static str,
+    pub reason: InvalidEntityReason,
+}
+
+type DatumCheck = fn(PlutusData) -> bool;
+
+fn parses<T: TryFromPData>(datum: PlutusData) -> bool {
+    T::try_from_pd(datum).is_some()
+}
+
+/// Known validators along with their names and checks of their datums.
+pub struct ValidatorRegistry(Vec<(ScriptHash, &'static str, DatumCheck)>);
+
+impl From<&ProtocolScriptHashes> for ValidatorRegistry {
+    fn from(scripts: &ProtocolScriptHashes) -> Self {
+        Self(vec![
+            (scripts.limit_order.script_hash, "limitOrder", parses::<Datum>),
+            (
+                scripts.grid_order_native.script_hash,
+                "gridOrderNative",
+                parses::<DatumNative>,
+            ),
+            (
+                scripts.const_fn_pool_v1.script_hash,
+                "constFnPoolV1",
+                parses::<LegacyCFMMPoolConfig>,
+            ),
+            (
+                scripts.const_fn_pool_v2.script_hash,
+                "constFnPoolV2",
+                parses::<LegacyCFMMPoolConfig>,
+            ),
+            (
+                scripts.const_fn_pool_fee_switch.script_hash,
+                "constFnPoolFeeSwitch",
+                parses::<FeeSwitchPoolConfig>,
+            ),
+            (
+                scripts.const_fn_pool_fee_switch_v2.script_hash,
+                "constFnPoolFeeSwitchV2",
+                parses::<FeeSwitchPoolConfig>,
+            ),
+            (
+                scripts.const_fn_pool_fee_switch_bidir_fee.script_hash,
+                "constFnPoolFeeSwitchBiDirFee",
+                parses::<FeeSwitchBidirectionalPoolConfig>,
+            ),
+            (
+                scripts.const_fn_pool_swap.script_hash,
+                "constFnPoolSwap",
+                parses::<OnChainLimitSwapConfig>,
+            ),
+            (
+                scripts.const_fn_pool_deposit.script_hash,
+                "constFnPoolDeposit",
+                parses::<OnChainDepositConfig>,
+            ),
+            (
+                scripts.const_fn_pool_redeem.script_hash,
+                "constFnPoolRedeem",
+                parses::<OnChainRedeemConfig>,
+            ),
+            (
+                scripts.const_fn_fee_switch_pool_swap.script_hash,
+                "constFnFeeSwitchPoolSwap",
+                parses::<OnChainLimitSwapConfig>,
+            ),
+            (
+                scripts.const_fn_fee_switch_pool_deposit.script_hash,
+                "constFnFeeSwitchPoolDeposit",
+                parses::<OnChainDepositConfig>,
+            ),
+            (
+                scripts.const_fn_fee_switch_pool_redeem.script_hash,
+                "constFnFeeSwitchPoolRedeem",
+                parses::<OnChainRedeemConfig>,
+            ),
+            (
+                scripts.balance_fn_pool_v1.script_hash,
+                "balanceFnPoolV1",
+                parses::<BalancePoolConfig>,
+            ),
+            (
+                scripts.balance_fn_pool_v2.script_hash,
+                "balanceFnPoolV2",
+                parses::<BalancePoolConfig>,
+            ),
+            (
+                scripts.balance_fn_pool_deposit.script_hash,
+                "balanceFnPoolDeposit",
+                parses::<OnChainDepositConfig>,
+            ),
+            (
+                scripts.balance_fn_pool_redeem.script_hash,
+                "balanceFnPoolRedeem",
+                parses::<OnChainRedeemConfig>,
+            ),
+            (
+                scripts.stable_fn_pool_t2t.script_hash,
+                "stableFnPoolT2T",
+                parses::<StablePoolT2TConfig>,
+            ),
+            (
+                scripts.stable_fn_pool_t2t_deposit.script_hash,
+                "stableFnPoolT2TDeposit",
+                parses::<OnChainDepositConfig>,
+            ),
+            (
+                scripts.stable_fn_pool_t2t_redeem.script_hash,
+                "stableFnPoolT2TRedeem",
+                parses::<OnChainRedeemConfig>,
+            ),
+        ])
+    }
+}
+
+impl ValidatorRegistry {
+    /// Explain why the given output failed to parse, if it is locked by a known validator.
+    pub fn diagnose(&self, output: &BabbageTransactionOutput) -> Option<(&'static str, InvalidEntityReason)> {
+        let hash = match output.address().payment_cred()? {
+            StakeCredential::Script { hash, .. } => *hash,
+            StakeCredential::PubKey { .. } => return None,
+        };
+        let (_, validator, check) = self.0.iter().find(|(h, _, _)| *h == hash)?;
+        let reason = match output.datum() {
+            None => InvalidEntityReason::MissingDatum,
+            Some(datum) => match datum.into_pd() {
+                None => InvalidEntityReason::DatumHashOnly,
+                Some(pd) if !check(pd) => InvalidEntityReason::MalformedDatum,
+                Some(_) => InvalidEntityReason::Rejected,
+            },
+        };
+        Some((*validator, reason))
+    }
+}
+
+/// Reports outputs at known validator addresses left unrecognized by preceding handlers.
+/// Meant to be the last handler in the chain. Only confirmed TXs are inspected.
+pub struct InvalidEntityHandler<Topic> {
+    registry: ValidatorRegistry,
+    topic: Topic,
+}
+
+impl<Topic> InvalidEntityHandler<Topic> {
+    pub fn new(registry: ValidatorRegistry, topic: Topic) -> Self {
+        Self { registry, topic }
+    }
+}
+
+#[async_trait(?Send)]
+impl<Topic> EventHandler<LedgerTxEvent<ProcessedTransaction>> for InvalidEntityHandler<Topic>
+where
+    Topic: Sink<InvalidEntity> + Unpin,
+    Topic::Error: Debug,
+{
+    async fn try_handle(
+        &mut self,
+        ev: LedgerTxEvent<ProcessedTransaction>,
+    ) -> Option<LedgerTxEvent<ProcessedTransaction>> {
+        if let LedgerTxEvent::TxApplied { tx, .. } = &ev {
+            let mut num_reported = 0;
+            for (ix, o) in &tx.outputs {
+                if let Some((validator, reason)) = self.registry.diagnose(o) {
+                    let output_ref = OutputRef::new(tx.hash, *ix as u64);
+                    warn!("{} at {} failed to parse: {}", output_ref, validator, reason);
+                    self.topic
+                        .feed(InvalidEntity {
+                            output_ref,
+                            validator,
+                            reason,
+                        })
+                        .await
+                        .expect("Channel is closed");
+                    num_reported += 1;
+                }
+            }
+            if num_reported > 0 {
+                self.topic.flush().await.expect("Failed to commit updates");
+                trace!("{} invalid entities reported", num_reported);
+            }
+        }
+        Some(ev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::Credential;
+    use cml_chain::plutus::{ConstrPlutusData, PlutusData};
+    use cml_chain::transaction::DatumOption;
+    use cml_chain::Value;
+    use cml_crypto::{DatumHash, Ed25519KeyHash, ScriptHash};
+    use cml_multi_era::babbage::{BabbageFormatTxOut, BabbageTransactionOutput};
+
+    use crate::event_sink::invalid_entity::{parses, InvalidEntityReason, ValidatorRegistry};
+    use spectrum_offchain_cardano::data::deposit::OnChainDepositConfig;
+
+    const DEPOSIT_SCRIPT: [u8; 28] = [1u8; 28];
+
+    fn registry() -> ValidatorRegistry {
+        ValidatorRegistry(vec![(
+            ScriptHash::from(DEPOSIT_SCRIPT),
+            "constFnPoolDeposit",
+            parses::<OnChainDepositConfig>,
+        )])
+    }
+
+    fn output(cred: Credential, datum: Option<DatumOption>) -> BabbageTransactionOutput {
+        BabbageTransactionOutput::new_babbage_format_tx_out(BabbageFormatTxOut {
+            address: EnterpriseAddress::new(0, cred).to_address(),
+            amount: Value::from(5_000_000),
+            datum_option: datum,
+            script_reference: None,
+            encodings: None,
+        })
+    }
+
+    #[test]
+    fn outputs_at_unknown_addresses_are_ignored() {
+        let registry = registry();
+        let key_locked = output(Credential::new_pub_key(Ed25519KeyHash::from([1u8; 28])), None);
+        let foreign_script = output(Credential::new_script(ScriptHash::from([2u8; 28])), None);
+        assert_eq!(registry.diagnose(&key_locked), None);
+        assert_eq!(registry.diagnose(&foreign_script), None);
+    }
+
+    #[test]
+    fn failure_reason_is_derived_from_datum() {
+        let registry = registry();
+        let cred = || Credential::new_script(ScriptHash::from(DEPOSIT_SCRIPT));
+        let garbage = PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, vec![]));
+        assert_eq!(
+            registry.diagnose(&output(cred(), None)),
+            Some(("constFnPoolDeposit", InvalidEntityReason::MissingDatum))
+        );
+        assert_eq!(
+            registry.diagnose(&output(
+                cred(),
+                Some(DatumOption::new_hash(DatumHash::from([0u8; 32])))
+            )),
+            Some(("constFnPoolDeposit", InvalidEntityReason::DatumHashOnly))
+        );
+        assert_eq!(
+            registry.diagnose(&output(cred(), Some(DatumOption::new_datum(garbage)))),
+            Some(("constFnPoolDeposit", InvalidEntityReason::MalformedDatum))
+        );
+    }
+}
@@ -4,12 +4,13 @@ use cml_multi_era::babbage::BabbageTransactionOutput;
 use either::Either;
 
 use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain::execution_engine::types::Time;
 use spectrum_cardano_lib::output::FinalizedTxOut;
 use spectrum_cardano_lib::OutputRef;
 use spectrum_offchain::data::order::SpecializedOrder;
 use spectrum_offchain::data::{Baked, EntitySnapshot, Has, Stable, Tradable};
 use spectrum_offchain::ledger::TryFromLedger;
-use spectrum_offchain_cardano::creds::OperatorCred;
+use spectrum_offchain_cardano::creds::OperatorCredSet;
 use spectrum_offchain_cardano::data::deposit::DepositOrderBounds;
 use spectrum_offchain_cardano::data::order::ClassicalAMMOrder;
 use spectrum_offchain_cardano::data::pair::PairId;
@@ -27,11 +28,16 @@ use spectrum_offchain_cardano::utxo::ConsumedInputs;
 use crate::orders::limit::LimitOrderBounds;
 use crate::orders::AnyOrder;
 
+pub mod backlog_journal;
 pub mod context;
 pub mod entity_index;
 pub mod handler;
+pub mod invalid_entity;
 pub mod order_index;
+pub mod pool_lifecycle;
+pub mod prefilter;
 pub mod processed_tx;
+pub mod warm_start;
 
 #[repr(transparent)]
 #[derive(Debug, Clone)]
@@ -48,12 +54,16 @@ impl SpecializedOrder for AtomicCardanoEntity {
     fn get_pool_ref(&self) -> Self::TPoolId {
         self.0.get_pool_ref()
     }
+
+    fn is_deposit(&self) -> bool {
+        self.0.is_deposit()
+    }
 }
 
 impl<C> TryFromLedger<BabbageTransactionOutput, C> for AtomicCardanoEntity
 where
     C: Copy
-        + Has<OperatorCred>
+        + Has<OperatorCredSet>
         + Has<OutputRef>
         + Has<DeployedScriptInfo<{ ConstFnPoolSwap as u8 }>>
         + Has<DeployedScriptInfo<{ ConstFnPoolDeposit as u8 }>>
@@ -111,7 +121,7 @@ impl Tradable for EvolvingCardanoEntity {
 impl<C> TryFromLedger<BabbageTransactionOutput, C> for EvolvingCardanoEntity
 where
     C: Copy
-        + Has<OperatorCred>
+        + Has<OperatorCredSet>
         + Has<OutputRef>
         + Has<ConsumedInputs>
         + Has<DeployedScriptInfo<{ ConstFnPoolV1 as u8 }>>
@@ -125,7 +135,8 @@ where
         + Has<DeployedScriptInfo<{ StableFnPoolT2T as u8 }>>
         + Has<LimitOrderBounds>
         + Has<DepositOrderBounds>
-        + Has<PoolBounds>,
+        + Has<PoolBounds>
+        + Has<Time>,
 {
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &C) -> Option<Self> {
         <Either<Baked<AnyOrder, OutputRef>, Baked<AnyPool, OutputRef>>>::try_from_ledger(repr, ctx).map(
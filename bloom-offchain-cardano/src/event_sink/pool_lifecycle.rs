@@ -0,0 +1,143 @@
+use cml_crypto::RawBytesEncoding;
+use derive_more::Display;
+use either::Either;
+use log::info;
+
+use bloom_offchain::execution_engine::liquidity_book::market_maker::{MarketMaker, PoolLifecycle};
+use bloom_offchain::execution_engine::notifier::WebhookNotifier;
+use spectrum_cardano_lib::OutputRef;
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::{Baked, Stable, Tradable};
+use spectrum_offchain_cardano::data::pool::AnyPool;
+
+use crate::event_sink::EvolvingCardanoEntity;
+
+/// Observes confirmed transitions of entities.
+pub trait TransitionObserver<Entity> {
+    fn on_confirmed(&self, transition: &Ior<Entity, Entity>);
+}
+
+impl<Entity> TransitionObserver<Entity> for () {
+    fn on_confirmed(&self, _: &Ior<Entity, Entity>) {}
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Display, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PoolLifecycleEvent {
+    /// Pool appeared on-chain.
+    Created,
+    /// Reserves fell below operational bounds.
+    Paused,
+    /// Pool is available for matchmaking again.
+    Resumed,
+    /// Liquidity was withdrawn down to zero or dust.
+    Drained,
+    /// Pool NFT was burned.
+    Retired,
+}
+
+impl PoolLifecycleEvent {
+    /// Derive event from stages of consecutive snapshots of a pool.
+    pub fn derive(transition: Ior<PoolLifecycle, PoolLifecycle>) -> Option<Self> {
+        match transition {
+            Ior::Right(_) => Some(PoolLifecycleEvent::Created),
+            Ior::Left(_) => Some(PoolLifecycleEvent::Retired),
+            Ior::Both(prev, next) if prev == next => None,
+            Ior::Both(_, PoolLifecycle::Bootstrapping) => Some(PoolLifecycleEvent::Drained),
+            Ior::Both(_, PoolLifecycle::Paused) => Some(PoolLifecycleEvent::Paused),
+            Ior::Both(PoolLifecycle::Paused, PoolLifecycle::Active) => Some(PoolLifecycleEvent::Resumed),
+            // Initial liquidity was provided.
+            Ior::Both(PoolLifecycle::Bootstrapping, PoolLifecycle::Active) => None,
+            Ior::Both(PoolLifecycle::Active, PoolLifecycle::Active) => None,
+        }
+    }
+}
+
+/// Derives lifecycle events of pools from their confirmed transitions
+/// and reports them for alerting and display.
+#[derive(Debug, Clone)]
+pub struct PoolLifecycleTracker {
+    webhook: Option<WebhookNotifier>,
+}
+
+impl PoolLifecycleTracker {
+    pub fn new(webhook: Option<WebhookNotifier>) -> Self {
+        Self { webhook }
+    }
+}
+
+impl TransitionObserver<EvolvingCardanoEntity> for PoolLifecycleTracker {
+    fn on_confirmed(&self, transition: &Ior<EvolvingCardanoEntity, EvolvingCardanoEntity>) {
+        let (stages, pool) = match transition {
+            Ior::Left(prev) => match pool_of(prev) {
+                Some(pool) => (Ior::Left(pool.entity.lifecycle()), pool),
+                None => return,
+            },
+            Ior::Right(next) => match pool_of(next) {
+                Some(pool) => (Ior::Right(pool.entity.lifecycle()), pool),
+                None => return,
+            },
+            Ior::Both(prev, next) => match (pool_of(prev), pool_of(next)) {
+                (Some(prev), Some(next)) => {
+                    (Ior::Both(prev.entity.lifecycle(), next.entity.lifecycle()), next)
+                }
+                _ => return,
+            },
+        };
+        if let Some(event) = PoolLifecycleEvent::derive(stages) {
+            let pool_id = pool.entity.stable_id().to_hex();
+            info!("Pool {} in pair {}: {}", pool_id, pool.entity.pair_id(), event);
+            if let Some(webhook) = &self.webhook {
+                webhook.post(serde_json::json!({
+                    "event": "poolLifecycle",
+                    "transition": event,
+                    "poolId": pool_id,
+                    "pair": pool.entity.pair_id().to_string(),
+                    "outputRef": pool.version.to_string(),
+                }));
+            }
+        }
+    }
+}
+
+fn pool_of(entity: &EvolvingCardanoEntity) -> Option<&Baked<AnyPool, OutputRef>> {
+    match &entity.0 .0 {
+        Either::Right(pool) => Some(pool),
+        Either::Left(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bloom_offchain::execution_engine::liquidity_book::market_maker::PoolLifecycle;
+    use spectrum_offchain::combinators::Ior;
+
+    use crate::event_sink::pool_lifecycle::PoolLifecycleEvent;
+
+    #[test]
+    fn events_are_derived_from_consecutive_stages() {
+        use PoolLifecycle::*;
+        assert_eq!(
+            PoolLifecycleEvent::derive(Ior::Right(Bootstrapping)),
+            Some(PoolLifecycleEvent::Created)
+        );
+        assert_eq!(
+            PoolLifecycleEvent::derive(Ior::Left(Active)),
+            Some(PoolLifecycleEvent::Retired)
+        );
+        assert_eq!(PoolLifecycleEvent::derive(Ior::Both(Bootstrapping, Active)), None);
+        assert_eq!(
+            PoolLifecycleEvent::derive(Ior::Both(Active, Paused)),
+            Some(PoolLifecycleEvent::Paused)
+        );
+        assert_eq!(
+            PoolLifecycleEvent::derive(Ior::Both(Paused, Active)),
+            Some(PoolLifecycleEvent::Resumed)
+        );
+        assert_eq!(
+            PoolLifecycleEvent::derive(Ior::Both(Active, Bootstrapping)),
+            Some(PoolLifecycleEvent::Drained)
+        );
+        assert_eq!(PoolLifecycleEvent::derive(Ior::Both(Paused, Paused)), None);
+    }
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use cml_chain::certs::StakeCredential;
+use cml_crypto::RawBytesEncoding;
+use log::trace;
+
+use cardano_chain_sync::data::LedgerTxEvent;
+use cardano_mempool_sync::data::MempoolUpdate;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_offchain::event_sink::event_handler::EventHandler;
+
+use crate::event_sink::processed_tx::ProcessedTransaction;
+
+const BITS_PER_ENTRY: usize = 24;
+const NUM_PROBES: u64 = 8;
+
+/// Bloom filter over payment credentials.
+/// Credentials are hashes already, so probe positions are derived straight from their bytes.
+#[derive(Debug, Clone)]
+pub struct CredentialFilter {
+    bits: Vec<u64>,
+}
+
+impl CredentialFilter {
+    pub fn new<'a, I>(creds: I) -> Self
+    where
+        I: IntoIterator<Item = &'a StakeCredential>,
+    {
+        let creds = creds.into_iter().collect::<Vec<_>>();
+        let num_words = (creds.len() * BITS_PER_ENTRY).div_ceil(64).max(1);
+        let mut filter = Self {
+            bits: vec![0; num_words],
+        };
+        for cred in creds {
+            for pos in filter.probes(cred) {
+                filter.bits[pos / 64] |= 1 << (pos % 64);
+            }
+        }
+        filter
+    }
+
+    /// False positives are possible, false negatives are not.
+    pub fn may_contain(&self, cred: &StakeCredential) -> bool {
+        self.probes(cred)
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    fn probes(&self, cred: &StakeCredential) -> impl Iterator<Item = usize> {
+        let raw = match cred {
+            StakeCredential::PubKey { hash, .. } => hash.to_raw_bytes(),
+            StakeCredential::Script { hash, .. } => hash.to_raw_bytes(),
+        };
+        let h1 = u64::from_le_bytes(raw[0..8].try_into().unwrap());
+        let h2 = u64::from_le_bytes(raw[8..16].try_into().unwrap()) | 1;
+        let num_bits = (self.bits.len() * 64) as u64;
+        (0..NUM_PROBES).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+    }
+}
+
+/// Drops outputs whose payment credential is surely not in the given set before any entity parser
+/// sees them. Inputs are left intact, so consumption of known entities is still tracked.
+#[derive(Clone)]
+pub struct OutputPrefilter {
+    filter: CredentialFilter,
+}
+
+impl OutputPrefilter {
+    pub fn new(filter: CredentialFilter) -> Self {
+        Self { filter }
+    }
+
+    fn retain_relevant(&self, tx: &mut ProcessedTransaction) {
+        let num_outputs = tx.outputs.len();
+        tx.outputs.retain(|(_, o)| {
+            o.address()
+                .payment_cred()
+                .map(|cred| self.filter.may_contain(cred))
+                .unwrap_or(false)
+        });
+        trace!(
+            "{} of {} outputs of TX {} passed prefilter",
+            tx.outputs.len(),
+            num_outputs,
+            tx.hash
+        );
+    }
+}
+
+#[async_trait(?Send)]
+impl EventHandler<LedgerTxEvent<ProcessedTransaction>> for OutputPrefilter {
+    async fn try_handle(
+        &mut self,
+        mut ev: LedgerTxEvent<ProcessedTransaction>,
+    ) -> Option<LedgerTxEvent<ProcessedTransaction>> {
+        match &mut ev {
+            LedgerTxEvent::TxApplied { tx, .. } | LedgerTxEvent::TxUnapplied(tx) => self.retain_relevant(tx),
+        }
+        Some(ev)
+    }
+}
+
+#[async_trait(?Send)]
+impl EventHandler<MempoolUpdate<ProcessedTransaction>> for OutputPrefilter {
+    async fn try_handle(
+        &mut self,
+        mut ev: MempoolUpdate<ProcessedTransaction>,
+    ) -> Option<MempoolUpdate<ProcessedTransaction>> {
+        match &mut ev {
+            MempoolUpdate::TxAccepted(tx) => self.retain_relevant(tx),
+        }
+        Some(ev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::certs::StakeCredential;
+    use cml_crypto::{Ed25519KeyHash, ScriptHash};
+    use rand::rngs::StdRng;
+    use rand::{RngCore, SeedableRng};
+
+    use crate::event_sink::prefilter::CredentialFilter;
+
+    fn random_script(rng: &mut StdRng) -> StakeCredential {
+        let mut raw = [0u8; 28];
+        rng.fill_bytes(&mut raw);
+        StakeCredential::new_script(ScriptHash::from(raw))
+    }
+
+    #[test]
+    fn known_credentials_always_pass() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let known = (0..20).map(|_| random_script(&mut rng)).collect::<Vec<_>>();
+        let key = StakeCredential::new_pub_key(Ed25519KeyHash::from([7u8; 28]));
+        let filter = CredentialFilter::new(known.iter().chain([&key]));
+        assert!(known.iter().all(|c| filter.may_contain(c)));
+        assert!(filter.may_contain(&key));
+    }
+
+    #[test]
+    fn unknown_credentials_are_mostly_rejected() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let known = (0..20).map(|_| random_script(&mut rng)).collect::<Vec<_>>();
+        let filter = CredentialFilter::new(known.iter());
+        let false_positives = (0..10_000)
+            .filter(|_| filter.may_contain(&random_script(&mut rng)))
+            .count();
+        assert!(false_positives < 10, "{} false positives", false_positives);
+    }
+}
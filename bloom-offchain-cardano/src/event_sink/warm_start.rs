@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use cardano_explorer::CardanoNetwork;
+use cml_crypto::{ScriptHash, TransactionHash};
+use cml_multi_era::babbage::BabbageTransactionOutput;
+use log::{info, warn};
+
+use spectrum_cardano_lib::era::AnyEraOutput;
+use spectrum_cardano_lib::PaymentCredential;
+use spectrum_offchain_cardano::deployment::ProtocolScriptHashes;
+
+use crate::event_sink::processed_tx::ProcessedTransaction;
+
+/// Validators whose outputs are parsed into orders and pools.
+pub fn entity_validators(scripts: &ProtocolScriptHashes) -> Vec<ScriptHash> {
+    vec![
+        scripts.limit_order.script_hash,
+        scripts.grid_order_native.script_hash,
+        scripts.const_fn_pool_v1.script_hash,
+        scripts.const_fn_pool_v2.script_hash,
+        scripts.const_fn_pool_fee_switch.script_hash,
+        scripts.const_fn_pool_fee_switch_v2.script_hash,
+        scripts.const_fn_pool_fee_switch_bidir_fee.script_hash,
+        scripts.const_fn_pool_swap.script_hash,
+        scripts.const_fn_pool_deposit.script_hash,
+        scripts.const_fn_pool_redeem.script_hash,
+        scripts.const_fn_fee_switch_pool_swap.script_hash,
+        scripts.const_fn_fee_switch_pool_deposit.script_hash,
+        scripts.const_fn_fee_switch_pool_redeem.script_hash,
+        scripts.balance_fn_pool_v1.script_hash,
+        scripts.balance_fn_pool_v2.script_hash,
+        scripts.balance_fn_pool_deposit.script_hash,
+        scripts.balance_fn_pool_redeem.script_hash,
+        scripts.stable_fn_pool_t2t.script_hash,
+        scripts.stable_fn_pool_t2t_deposit.script_hash,
+        scripts.stable_fn_pool_t2t_redeem.script_hash,
+    ]
+}
+
+/// Pull UTxOs currently locked by the given validators from an indexer.
+/// Chain sync is supposed to resume from a point read from the indexer ahead of the snapshot,
+/// otherwise it replays states older than the ones the snapshot restores.
+/// UTxOs are grouped into pseudo-transactions by the TX that created them, so that they can be
+/// fed to ledger event handlers ahead of chain sync and seed books and backlogs.
+/// Pseudo-transactions consume nothing, thus orders restored this way are never considered fresh.
+pub async fn pull_snapshot<Net: CardanoNetwork>(
+    validators: Vec<ScriptHash>,
+    indexer: &Net,
+) -> Vec<ProcessedTransaction> {
+    let mut outputs_by_tx: HashMap<TransactionHash, Vec<(usize, BabbageTransactionOutput)>> = HashMap::new();
+    let mut num_utxos = 0;
+    for validator in validators {
+        for utxo in indexer
+            .all_utxos_by_pay_cred(PaymentCredential::from(validator))
+            .await
+        {
+            let oref = utxo.input;
+            match BabbageTransactionOutput::try_from(AnyEraOutput::from(utxo.output)) {
+                Ok(output) => {
+                    num_utxos += 1;
+                    outputs_by_tx
+                        .entry(oref.transaction_id)
+                        .or_default()
+                        .push((oref.index as usize, output));
+                }
+                Err(err) => warn!(
+                    "UTxO {}#{} skipped during warm start: {}",
+                    oref.transaction_id.to_hex(),
+                    oref.index,
+                    err
+                ),
+            }
+        }
+    }
+    info!("{} UTxOs pulled from indexer", num_utxos);
+    outputs_by_tx
+        .into_iter()
+        .map(|(hash, mut outputs)| {
+            outputs.sort_by_key(|(ix, _)| *ix);
+            ProcessedTransaction {
+                hash,
+                inputs: vec![],
+                outputs,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cardano_explorer::CardanoNetwork;
+    use cml_chain::address::{Address, EnterpriseAddress};
+    use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+    use cml_chain::certs::Credential;
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::Value;
+    use cml_crypto::{ScriptHash, TransactionHash};
+    use futures::executor::block_on;
+
+    use spectrum_cardano_lib::{OutputRef, PaymentCredential};
+
+    use crate::event_sink::warm_start::pull_snapshot;
+
+    /// Indexer serving UTxOs by validator.
+    struct Indexer(HashMap<ScriptHash, Vec<TransactionUnspentOutput>>);
+
+    impl CardanoNetwork for Indexer {
+        async fn utxo_by_ref(&self, _: OutputRef) -> Option<TransactionUnspentOutput> {
+            None
+        }
+
+        async fn utxos_by_pay_cred(
+            &self,
+            payment_credential: PaymentCredential,
+            offset: u32,
+            limit: u16,
+        ) -> Vec<TransactionUnspentOutput> {
+            let hash = ScriptHash::from_bech32(String::from(payment_credential).as_str()).unwrap();
+            self.0
+                .get(&hash)
+                .into_iter()
+                .flatten()
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect()
+        }
+
+        async fn utxos_by_address(&self, _: Address, _: u32, _: u16) -> Vec<TransactionUnspentOutput> {
+            vec![]
+        }
+    }
+
+    fn utxo(validator: ScriptHash, tx: u8, ix: u64) -> TransactionUnspentOutput {
+        let address = EnterpriseAddress::new(0, Credential::new_script(validator)).to_address();
+        TransactionUnspentOutput::new(
+            TransactionInput::new(TransactionHash::from([tx; 32]), ix),
+            TransactionOutput::new(address, Value::from(2_000_000 + ix), None, None),
+        )
+    }
+
+    #[test]
+    fn utxos_are_grouped_by_creating_tx() {
+        let order_validator = ScriptHash::from([1u8; 28]);
+        let pool_validator = ScriptHash::from([2u8; 28]);
+        let indexer = Indexer(HashMap::from([
+            (
+                order_validator,
+                vec![utxo(order_validator, 1, 2), utxo(order_validator, 2, 0)],
+            ),
+            (pool_validator, vec![utxo(pool_validator, 1, 0)]),
+        ]));
+        let snapshot = block_on(pull_snapshot(vec![order_validator, pool_validator], &indexer));
+        assert_eq!(snapshot.len(), 2);
+        let shared_tx = snapshot
+            .iter()
+            .find(|tx| tx.hash == TransactionHash::from([1u8; 32]))
+            .unwrap();
+        assert_eq!(
+            shared_tx.outputs.iter().map(|(ix, _)| *ix).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert!(snapshot.iter().all(|tx| tx.inputs.is_empty()));
+    }
+}
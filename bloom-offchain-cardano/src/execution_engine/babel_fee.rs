@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, RwLock};
+
+use either::Either;
+use num_rational::Ratio;
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain::execution_engine::liquidity_book::market_maker::MarketMaker;
+use bloom_offchain::execution_engine::liquidity_book::types::Lovelace;
+use bloom_offchain::execution_engine::Event;
+use spectrum_cardano_lib::AssetClass;
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, StateUpdate};
+
+/// Least amount of an asset the operator accepts in exchange for `per_lovelace` of fee.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MinConversionRate {
+    pub units: u64,
+    pub per_lovelace: u64,
+}
+
+impl MinConversionRate {
+    fn ratio(&self) -> Ratio<u128> {
+        Ratio::new(self.units as u128, self.per_lovelace.max(1) as u128)
+    }
+}
+
+/// Fee of an order paying in a token cannot be taken.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BabelFeeRejected {
+    /// Operator did not opt in to take fees in the asset.
+    NotOptedIn(AssetClass),
+    /// No ADA pool of the asset has been observed yet.
+    NoConversionPool(AssetClass),
+    /// ADA pool of the asset prices it below the configured minimum.
+    RateBelowMinimum(AssetClass),
+    /// Output of the order does not cover the fee.
+    InsufficientOutput(AssetClass),
+}
+
+impl Display for BabelFeeRejected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BabelFeeRejected::NotOptedIn(asset) => write!(f, "Fees in {} are not accepted", asset),
+            BabelFeeRejected::NoConversionPool(asset) => write!(f, "No ADA pool of {} is known", asset),
+            BabelFeeRejected::RateBelowMinimum(asset) => {
+                write!(f, "Conversion rate of {} is below the minimum", asset)
+            }
+            BabelFeeRejected::InsufficientOutput(asset) => {
+                write!(f, "Output in {} does not cover the fee", asset)
+            }
+        }
+    }
+}
+
+/// Conversion of fees of orders trading tokens for tokens into the token they receive.
+/// The fee is priced at the spot rate of the asset's ADA pool,
+/// only assets the operator opted in to are accepted, and only at or above their minimum rate.
+#[derive(Debug, Clone, Default)]
+pub struct BabelFees {
+    min_rates: Arc<HashMap<AssetClass, MinConversionRate>>,
+    /// Units of the asset per lovelace quoted by its ADA pool.
+    rates: Arc<RwLock<HashMap<AssetClass, Ratio<u128>>>>,
+}
+
+impl BabelFees {
+    pub fn new(min_rates: HashMap<AssetClass, MinConversionRate>) -> Self {
+        Self {
+            min_rates: Arc::new(min_rates),
+            rates: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Track spot rates of ADA pools of opted-in assets within the given canonical pair.
+    pub fn observe<CO, SO, P, B, Ver>(&self, [base, quote]: [AssetClass; 2], event: &Event<CO, SO, P, B, Ver>)
+    where
+        P: MarketMaker,
+    {
+        if base != AssetClass::Native || !self.min_rates.contains_key(&quote) {
+            return;
+        }
+        let Either::Left(Channel::Ledger(Confirmed(
+            StateUpdate::Transition(tr) | StateUpdate::TransitionRollback(tr),
+        ))) = event
+        else {
+            return;
+        };
+        if let Ior::Right(Bundled(Either::Right(pool), _)) | Ior::Both(_, Bundled(Either::Right(pool), _)) =
+            tr
+        {
+            if pool.entity.is_active() {
+                self.rates
+                    .write()
+                    .unwrap()
+                    .insert(quote, pool.entity.static_price().unwrap());
+            }
+        }
+    }
+
+    /// Units of `asset` worth the given amount of lovelace.
+    pub fn convert(&self, asset: AssetClass, lovelace: Lovelace) -> Result<u64, BabelFeeRejected> {
+        let min_rate = self
+            .min_rates
+            .get(&asset)
+            .ok_or(BabelFeeRejected::NotOptedIn(asset))?;
+        let rate = self
+            .rates
+            .read()
+            .unwrap()
+            .get(&asset)
+            .copied()
+            .ok_or(BabelFeeRejected::NoConversionPool(asset))?;
+        if rate < min_rate.ratio() {
+            return Err(BabelFeeRejected::RateBelowMinimum(asset));
+        }
+        let units = (rate * Ratio::from_integer(lovelace as u128)).ceil().to_integer();
+        u64::try_from(units).map_err(|_| BabelFeeRejected::InsufficientOutput(asset))
+    }
+
+    #[cfg(test)]
+    fn set_rate(&self, asset: AssetClass, rate: Ratio<u128>) {
+        self.rates.write().unwrap().insert(asset, rate);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cml_chain::PolicyId;
+    use num_rational::Ratio;
+
+    use spectrum_cardano_lib::{AssetClass, AssetName};
+
+    use crate::execution_engine::babel_fee::{BabelFeeRejected, BabelFees, MinConversionRate};
+
+    fn token(ix: u8) -> AssetClass {
+        AssetClass::Token((PolicyId::from([ix; 28]), AssetName::from((3, [ix; 32]))))
+    }
+
+    #[test]
+    fn fee_is_converted_at_pool_rate_above_minimum() {
+        let fees = BabelFees::new(HashMap::from([(
+            token(1),
+            MinConversionRate {
+                units: 2,
+                per_lovelace: 1_000,
+            },
+        )]));
+        assert_eq!(
+            fees.convert(token(2), 1_000_000),
+            Err(BabelFeeRejected::NotOptedIn(token(2)))
+        );
+        assert_eq!(
+            fees.convert(token(1), 1_000_000),
+            Err(BabelFeeRejected::NoConversionPool(token(1)))
+        );
+        fees.set_rate(token(1), Ratio::new(1, 1_000));
+        assert_eq!(
+            fees.convert(token(1), 1_000_000),
+            Err(BabelFeeRejected::RateBelowMinimum(token(1)))
+        );
+        fees.set_rate(token(1), Ratio::new(3, 1_000));
+        assert_eq!(fees.convert(token(1), 1_000_001), Ok(3_001));
+    }
+}
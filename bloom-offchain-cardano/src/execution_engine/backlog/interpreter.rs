@@ -1,7 +1,3 @@
-use std::fmt::Display;
-
-use log::info;
-
 use bloom_offchain::execution_engine::backlog::SpecializedInterpreter;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use spectrum_cardano_lib::output::FinalizedTxOut;
@@ -22,7 +18,6 @@ where
     Pl: Stable,
     PoolMagnet<Bundled<Pl, FinalizedTxOut>>: RunOrder<Bundled<Ord, FinalizedTxOut>, Ctx, Txc>,
     Ord: SpecializedOrder<TPoolId = Pl::StableId> + Clone,
-    Ord::TOrderId: Display,
     Ver: From<OutputRef>,
     Ctx: Clone,
 {
@@ -31,24 +26,20 @@ where
         pool: Bundled<Pl, FinalizedTxOut>,
         order: Bundled<Ord, FinalizedTxOut>,
         context: Ctx,
-    ) -> Option<(
-        Txc,
-        Bundled<Baked<Pl, Ver>, FinalizedTxOut>,
-        Bundled<Ord, FinalizedTxOut>,
-    )> {
-        let op_ref = order.get_self_ref();
-        match PoolMagnet(pool).try_run(order.clone(), context.clone()) {
-            Ok((tx_candidate, Predicted(PoolMagnet(Bundled(pool, bearer))))) => {
-                return Some((
-                    tx_candidate,
-                    Bundled(Baked::new(pool, bearer.1.into()), bearer),
-                    order,
-                ))
-            }
-            Err(RunOrderError::NonFatal(err, _) | RunOrderError::Fatal(err, _)) => {
-                info!("Order {} dropped due to error: {}", op_ref, err);
-            }
-        }
-        None
+    ) -> Result<
+        (
+            Txc,
+            Bundled<Baked<Pl, Ver>, FinalizedTxOut>,
+            Bundled<Ord, FinalizedTxOut>,
+        ),
+        RunOrderError<Bundled<Ord, FinalizedTxOut>>,
+    > {
+        let (tx_candidate, Predicted(PoolMagnet(Bundled(pool, bearer)))) =
+            PoolMagnet(pool).try_run(order.clone(), context)?;
+        Ok((
+            tx_candidate,
+            Bundled(Baked::new(pool, bearer.1.into()), bearer),
+            order,
+        ))
     }
 }
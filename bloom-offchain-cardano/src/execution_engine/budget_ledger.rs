@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use bloom_offchain::execution_engine::liquidity_book::types::Lovelace;
+use spectrum_cardano_lib::ex_units::ExUnits;
+
+/// Budget of a single fill.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct Allocation {
+    /// Budget the fill gives up to cover TX fee.
+    consumed: Lovelace,
+    /// The most the fill is able to give up.
+    capacity: Lovelace,
+}
+
+/// Tracks execution budget allocated by each instruction of a recipe being interpreted.
+/// Allocations beyond what fills hold or what a TX may consume are rejected upfront.
+#[derive(Debug, Clone)]
+pub struct BudgetLedger {
+    allocations: BTreeMap<usize, Allocation>,
+    ex_units: ExUnits,
+    max_ex_units: ExUnits,
+}
+
+impl BudgetLedger {
+    pub fn new(max_ex_units: ExUnits) -> Self {
+        Self {
+            allocations: BTreeMap::new(),
+            ex_units: ExUnits { mem: 0, steps: 0 },
+            max_ex_units,
+        }
+    }
+
+    /// Reserve budget consumed by the instruction [ix].
+    pub fn reserve(
+        &mut self,
+        ix: usize,
+        consumed: Lovelace,
+        capacity: Lovelace,
+    ) -> Result<(), BudgetOverallocation> {
+        if consumed > capacity {
+            return Err(BudgetOverallocation::Lovelace {
+                ix,
+                requested: consumed,
+                capacity,
+            });
+        }
+        self.allocations.insert(ix, Allocation { consumed, capacity });
+        Ok(())
+    }
+
+    /// Reserve execution units consumed by an instruction.
+    pub fn reserve_ex_units(&mut self, ex_units: ExUnits) -> Result<(), BudgetOverallocation> {
+        let requested = self.ex_units + ex_units;
+        if requested.mem > self.max_ex_units.mem || requested.steps > self.max_ex_units.steps {
+            return Err(BudgetOverallocation::ExUnits {
+                requested,
+                limit: self.max_ex_units,
+            });
+        }
+        self.ex_units = requested;
+        Ok(())
+    }
+
+    pub fn consumed(&self) -> Lovelace {
+        self.allocations.values().map(|a| a.consumed).sum()
+    }
+
+    fn capacity(&self) -> Lovelace {
+        self.allocations.values().map(|a| a.capacity).sum()
+    }
+
+    /// Plan how consumed budget changes per instruction so that it adds up to [required] exactly.
+    /// Shares are kept proportional to current allocations where possible, the rest is
+    /// allocated in order of instructions.
+    pub fn rebalance(&self, required: Lovelace) -> Result<BTreeMap<usize, i64>, BudgetImbalance> {
+        let consumed = self.consumed();
+        let capacity = self.capacity();
+        if capacity < required {
+            return Err(BudgetImbalance {
+                required: required as i64 - consumed as i64,
+                allocated: capacity as i64 - consumed as i64,
+            });
+        }
+        let mut targets = self
+            .allocations
+            .iter()
+            .map(|(ix, a)| {
+                let share = (a.consumed as u128 * required as u128)
+                    .checked_div(consumed as u128)
+                    .unwrap_or(0) as u64;
+                (*ix, share.min(a.capacity))
+            })
+            .collect::<BTreeMap<_, _>>();
+        let mut remainder = required - targets.values().sum::<u64>();
+        for (ix, target) in targets.iter_mut() {
+            if remainder == 0 {
+                break;
+            }
+            let headroom = self.allocations[ix].capacity - *target;
+            let extra = headroom.min(remainder);
+            *target += extra;
+            remainder -= extra;
+        }
+        Ok(targets
+            .into_iter()
+            .map(|(ix, target)| (ix, target as i64 - self.allocations[&ix].consumed as i64))
+            .collect())
+    }
+}
+
+/// Instruction requests more budget than available.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BudgetOverallocation {
+    Lovelace {
+        ix: usize,
+        requested: Lovelace,
+        capacity: Lovelace,
+    },
+    ExUnits {
+        requested: ExUnits,
+        limit: ExUnits,
+    },
+}
+
+impl Display for BudgetOverallocation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BudgetOverallocation::Lovelace {
+                ix,
+                requested,
+                capacity,
+            } => write!(
+                f,
+                "Instruction #{} requests {} lovelace of budget, holds {}",
+                ix, requested, capacity
+            ),
+            BudgetOverallocation::ExUnits { requested, limit } => write!(
+                f,
+                "Recipe requests {:?} ex-units, limit is {:?}",
+                requested, limit
+            ),
+        }
+    }
+}
+
+/// Budget redistribution across fills does not cover the change of TX fee.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BudgetImbalance {
+    /// Change of total consumed budget needed to cover the fee.
+    pub required: i64,
+    /// Change of total consumed budget actually allocated across fills.
+    pub allocated: i64,
+}
+
+impl Display for BudgetImbalance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Budget imbalance: required delta {}, allocated delta {}",
+            self.required, self.allocated
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spectrum_cardano_lib::ex_units::ExUnits;
+
+    use crate::execution_engine::budget_ledger::{BudgetImbalance, BudgetLedger, BudgetOverallocation};
+
+    const MAX_EX_UNITS: ExUnits = ExUnits { mem: 100, steps: 100 };
+
+    #[test]
+    fn rebalance_is_exact_and_within_capacity() {
+        let mut ledger = BudgetLedger::new(MAX_EX_UNITS);
+        ledger.reserve(0, 100_000, 150_000).unwrap();
+        ledger.reserve(1, 100_000, 400_000).unwrap();
+        let deltas = ledger.rebalance(500_000).unwrap();
+        assert_eq!(deltas.values().sum::<i64>(), 300_000);
+        assert_eq!(deltas[&0], 50_000);
+        assert_eq!(deltas[&1], 250_000);
+    }
+
+    #[test]
+    fn overallocation_is_rejected() {
+        let mut ledger = BudgetLedger::new(MAX_EX_UNITS);
+        assert_eq!(
+            ledger.reserve(0, 200_000, 100_000),
+            Err(BudgetOverallocation::Lovelace {
+                ix: 0,
+                requested: 200_000,
+                capacity: 100_000
+            })
+        );
+        ledger.reserve(0, 100_000, 100_000).unwrap();
+        assert_eq!(
+            ledger.rebalance(300_000),
+            Err(BudgetImbalance {
+                required: 200_000,
+                allocated: 0
+            })
+        );
+        ledger.reserve_ex_units(ExUnits { mem: 60, steps: 10 }).unwrap();
+        assert!(ledger.reserve_ex_units(ExUnits { mem: 60, steps: 10 }).is_err());
+    }
+}
@@ -29,6 +29,11 @@ use spectrum_offchain_cardano::script::{
     DelayedRedeemer, ScriptContextPreview, ScriptWitness, TxInputsOrdering,
 };
 
+use crate::execution_engine::babel_fee::BabelFeeRejected;
+use crate::execution_engine::exposure::AccountingDelta;
+use crate::execution_engine::pool_datum::PoolDatumRejected;
+use crate::execution_engine::slippage::SlippageExceeded;
+
 pub struct ScriptInputBlueprint {
     pub reference: OutputRef,
     pub utxo: TransactionOutput,
@@ -44,6 +49,8 @@ pub type ScalingFactor = u64;
 /// that executes a batch of DEX operations.
 pub struct TxBlueprint {
     pub script_io: Vec<(ScriptInputBlueprint, TransactionOutput)>,
+    /// Inputs spent with a key witness of a third party, e.g. funds of RFQ makers.
+    pub pubkey_io: Vec<(FinalizedTxOut, TransactionOutput)>,
     pub reference_inputs: HashSet<(TransactionInput, TransactionOutput)>,
     pub witness_scripts: HashMap<DeployedValidatorErased, (PlutusData, ScalingFactor)>,
 }
@@ -81,6 +88,7 @@ impl TxBlueprint {
     pub fn new() -> Self {
         Self {
             script_io: Vec::new(),
+            pubkey_io: Vec::new(),
             reference_inputs: HashSet::new(),
             witness_scripts: HashMap::new(),
         }
@@ -90,6 +98,10 @@ impl TxBlueprint {
         self.script_io.push((input, output));
     }
 
+    pub fn add_pubkey_io(&mut self, input: FinalizedTxOut, output: TransactionOutput) {
+        self.pubkey_io.push((input, output));
+    }
+
     pub fn add_ref_input(&mut self, utxo: TransactionUnspentOutput) {
         self.reference_inputs.insert((utxo.input, utxo.output));
     }
@@ -113,14 +125,32 @@ impl TxBlueprint {
         operator_address: OperatorRewardAddress,
         operator_funding: FinalizedTxOut,
         operator_interest: u64,
+        advanced_tx_fee: u64,
+        operator_interest_in_kind: Vec<(AssetClass, u64)>,
     ) -> (TransactionBuilder, FundingIO<FinalizedTxOut, TransactionOutput>) {
         let TxBlueprint {
             script_io,
+            pubkey_io,
             reference_inputs,
             witness_scripts,
         } = self;
         let mut all_io = script_io.into_iter().map(Either::Left).collect::<Vec<_>>();
-        let funding_io = if operator_interest > 0 {
+        all_io.extend(pubkey_io.into_iter().map(|(i, o)| Either::Right((Some(i), o))));
+        let funding_io = if advanced_tx_fee > 0 || !operator_interest_in_kind.is_empty() {
+            // TX fee of orders paying in tokens is covered out of the funding, tokens go there instead.
+            let mut value = operator_funding.0.value().clone();
+            value.add_unsafe(AssetClass::Native, operator_interest);
+            for (asset, amount) in operator_interest_in_kind {
+                value.add_unsafe(asset, amount);
+            }
+            value.sub_unsafe(AssetClass::Native, advanced_tx_fee);
+            let operator_output = TransactionOutput::new(operator_address.into(), value, None, None);
+            all_io.push(Either::Right((
+                Some(operator_funding.clone()),
+                operator_output.clone(),
+            )));
+            FundingIO::Replaced(operator_funding, operator_output)
+        } else if operator_interest > 0 {
             if operator_interest >= MIN_SAFE_LOVELACE_VALUE {
                 let operator_output = TransactionOutput::new(
                     operator_address.into(),
@@ -151,6 +181,9 @@ impl TxBlueprint {
             (Either::Right((Some(lh_in), _)), Either::Left((rh_in, _))) => {
                 lh_in.reference().cmp(&rh_in.reference)
             }
+            (Either::Right((Some(lh_in), _)), Either::Right((Some(rh_in), _))) => {
+                lh_in.reference().cmp(&rh_in.reference())
+            }
             (_, Either::Right((None, _))) => Ordering::Less,
             _ => Ordering::Greater,
         });
@@ -198,10 +231,10 @@ impl TxBlueprint {
                         let input = SingleInputBuilder::new(reference.into(), utxo)
                             .payment_key()
                             .unwrap();
-                        txb.add_input(input).expect("add funding input ok");
+                        txb.add_input(input).expect("add key input ok");
                     }
                     let output = SingleOutputBuilderResult::new(funding_output);
-                    txb.add_output(output).expect("add funding output ok");
+                    txb.add_output(output).expect("add key output ok");
                 }
             }
         }
@@ -232,6 +265,17 @@ pub struct ExecutionState {
     pub tx_blueprint: TxBlueprint,
     pub reserved_tx_fee: Lovelace,
     pub operator_interest: Lovelace,
+    /// Part of TX fee operator covers on behalf of orders paying in tokens.
+    pub advanced_tx_fee: Lovelace,
+    pub operator_interest_in_kind: Vec<(AssetClass, u64)>,
+    /// What the operator spends and earns per asset.
+    pub accounting: AccountingDelta,
+    /// Fee of one of the orders could not be taken in the asset it pays in.
+    pub rejected_fee: Option<BabelFeeRejected>,
+    /// One of the fills violates the worst price declared by its order.
+    pub slippage_exceeded: Option<SlippageExceeded>,
+    /// Datum of one of the pools can't be updated.
+    pub rejected_datum: Option<PoolDatumRejected>,
 }
 
 impl ExecutionState {
@@ -240,6 +284,12 @@ impl ExecutionState {
             tx_blueprint: TxBlueprint::new(),
             reserved_tx_fee: 0,
             operator_interest: 0,
+            advanced_tx_fee: 0,
+            operator_interest_in_kind: Vec::new(),
+            accounting: AccountingDelta::default(),
+            rejected_fee: None,
+            slippage_exceeded: None,
+            rejected_datum: None,
         }
     }
 
@@ -249,6 +299,29 @@ impl ExecutionState {
 
     pub fn add_operator_interest(&mut self, amount: Lovelace) {
         self.operator_interest += amount;
+        self.accounting.earn_fee(AssetClass::Native, amount);
+    }
+
+    pub fn advance_tx_fee(&mut self, on_behalf_of: AssetClass, amount: Lovelace) {
+        self.advanced_tx_fee += amount;
+        self.accounting.spend_budget(on_behalf_of, amount);
+    }
+
+    pub fn add_operator_interest_in_kind(&mut self, asset: AssetClass, amount: u64) {
+        self.operator_interest_in_kind.push((asset, amount));
+        self.accounting.accumulate_residual(asset, amount);
+    }
+
+    pub fn reject_fee(&mut self, reason: BabelFeeRejected) {
+        self.rejected_fee.get_or_insert(reason);
+    }
+
+    pub fn reject_slippage(&mut self, violation: SlippageExceeded) {
+        self.slippage_exceeded.get_or_insert(violation);
+    }
+
+    pub fn reject_datum(&mut self, reason: PoolDatumRejected) {
+        self.rejected_datum.get_or_insert(reason);
     }
 }
 
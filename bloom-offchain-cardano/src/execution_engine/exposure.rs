@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, RwLock};
+
+use bloom_offchain::execution_engine::liquidity_book::types::Lovelace;
+use spectrum_cardano_lib::AssetClass;
+
+/// What execution left the operator with in a particular asset.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct AssetAccount {
+    /// Lovelace the operator advanced on behalf of orders paying in this asset.
+    pub budget_spent: Lovelace,
+    /// Fees earned in this asset.
+    pub fees_earned: u64,
+    /// Units of this asset collected in kind and accumulated in the funding.
+    pub residuals: u64,
+}
+
+impl AssetAccount {
+    fn combine(&mut self, other: AssetAccount) {
+        self.budget_spent = self.budget_spent.saturating_add(other.budget_spent);
+        self.fees_earned = self.fees_earned.saturating_add(other.fees_earned);
+        self.residuals = self.residuals.saturating_add(other.residuals);
+    }
+}
+
+/// Accounting effects of a single TX.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct AccountingDelta(HashMap<AssetClass, AssetAccount>);
+
+impl AccountingDelta {
+    pub fn spend_budget(&mut self, asset: AssetClass, amount: Lovelace) {
+        self.0.entry(asset).or_default().budget_spent += amount;
+    }
+
+    pub fn earn_fee(&mut self, asset: AssetClass, amount: u64) {
+        self.0.entry(asset).or_default().fees_earned += amount;
+    }
+
+    pub fn accumulate_residual(&mut self, asset: AssetClass, amount: u64) {
+        self.0.entry(asset).or_default().residuals += amount;
+    }
+
+    pub fn assets(&self) -> impl Iterator<Item = &AssetClass> {
+        self.0.keys()
+    }
+}
+
+/// Bounds on what the operator is willing to hold in an asset.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExposureLimit {
+    pub max_budget_spent: Option<Lovelace>,
+    pub max_residuals: Option<u64>,
+}
+
+impl ExposureLimit {
+    fn is_breached_by(&self, account: &AssetAccount) -> bool {
+        self.max_budget_spent
+            .map_or(false, |max| account.budget_spent >= max)
+            || self.max_residuals.map_or(false, |max| account.residuals >= max)
+    }
+}
+
+/// Execution in the asset is paused as its exposure limit is breached.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ExposureLimitBreached(pub AssetClass);
+
+impl Display for ExposureLimitBreached {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exposure limit of {} is breached", self.0)
+    }
+}
+
+/// Inventory of the operator per asset, updated by execution effects.
+/// Execution of orders accruing to an asset is paused while the asset exceeds its exposure limit.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorInventory {
+    limits: Arc<HashMap<AssetClass, ExposureLimit>>,
+    accounts: Arc<RwLock<HashMap<AssetClass, AssetAccount>>>,
+}
+
+impl OperatorInventory {
+    pub fn new(limits: HashMap<AssetClass, ExposureLimit>) -> Self {
+        Self {
+            limits: Arc::new(limits),
+            accounts: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Check that none of the assets touched by `delta` is paused.
+    pub fn admit(&self, delta: &AccountingDelta) -> Result<(), ExposureLimitBreached> {
+        let accounts = self.accounts.read().unwrap();
+        for asset in delta.assets() {
+            if let (Some(limit), Some(account)) = (self.limits.get(asset), accounts.get(asset)) {
+                if limit.is_breached_by(account) {
+                    return Err(ExposureLimitBreached(*asset));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn apply(&self, AccountingDelta(delta): AccountingDelta) {
+        let mut accounts = self.accounts.write().unwrap();
+        for (asset, account) in delta {
+            accounts.entry(asset).or_default().combine(account);
+        }
+    }
+
+    pub fn account(&self, asset: AssetClass) -> AssetAccount {
+        self.accounts
+            .read()
+            .unwrap()
+            .get(&asset)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Reset the account once the operator has disposed of the accumulated asset, e.g. sold residuals.
+    pub fn settle(&self, asset: AssetClass) {
+        self.accounts.write().unwrap().remove(&asset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cml_chain::PolicyId;
+
+    use spectrum_cardano_lib::{AssetClass, AssetName};
+
+    use crate::execution_engine::exposure::{
+        AccountingDelta, ExposureLimit, ExposureLimitBreached, OperatorInventory,
+    };
+
+    fn token() -> AssetClass {
+        AssetClass::Token((PolicyId::from([1u8; 28]), AssetName::from((3, [1u8; 32]))))
+    }
+
+    #[test]
+    fn asset_is_paused_once_limit_is_breached() {
+        let inventory = OperatorInventory::new(HashMap::from([(
+            token(),
+            ExposureLimit {
+                max_budget_spent: None,
+                max_residuals: Some(100),
+            },
+        )]));
+        let mut delta = AccountingDelta::default();
+        delta.spend_budget(token(), 300_000);
+        delta.accumulate_residual(token(), 60);
+        assert_eq!(inventory.admit(&delta), Ok(()));
+        inventory.apply(delta.clone());
+        assert_eq!(inventory.admit(&delta), Ok(()));
+        inventory.apply(delta.clone());
+        assert_eq!(inventory.account(token()).residuals, 120);
+        assert_eq!(inventory.admit(&delta), Err(ExposureLimitBreached(token())));
+        let mut native_only = AccountingDelta::default();
+        native_only.earn_fee(AssetClass::Native, 1_000);
+        assert_eq!(inventory.admit(&native_only), Ok(()));
+        inventory.settle(token());
+        assert_eq!(inventory.admit(&delta), Ok(()));
+    }
+}
@@ -0,0 +1,494 @@
+//! Golden tests of the recipe interpreter.
+//! Each canned recipe is interpreted in a fixed context and the resulting TX body is compared
+//! byte-for-byte against a fixture in `tests/golden`. Missing fixtures are recorded on the first run,
+//! run with `UPDATE_GOLDEN=1` to re-record all of them after an intended change of TX layout.
+
+use std::path::PathBuf;
+use std::{env, fs};
+
+use cml_chain::address::{Address, EnterpriseAddress};
+use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+use cml_chain::certs::Credential;
+use cml_chain::plutus::{ConstrPlutusData, PlutusData, PlutusV2Script};
+use cml_chain::transaction::{DatumOption, TransactionBody, TransactionInput, TransactionOutput};
+use cml_chain::{PolicyId, Script, Value};
+use cml_core::serialization::{Deserialize, Serialize};
+use cml_crypto::{Ed25519KeyHash, ScriptHash, TransactionHash};
+use either::Either;
+use num_rational::Ratio;
+use type_equalities::IsEqual;
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain::execution_engine::liquidity_book::core::{ExecutionRecipe, Make, Next, Take, Trans};
+use bloom_offchain::execution_engine::liquidity_book::interpreter::RecipeInterpreter;
+use bloom_offchain::execution_engine::liquidity_book::market_maker::MakerBehavior;
+use bloom_offchain::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use bloom_offchain::execution_engine::liquidity_book::side::OnSide;
+use spectrum_cardano_lib::address::{PlutusAddress, PlutusCredential};
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::ex_units::ExUnits;
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use spectrum_cardano_lib::value::ValueExtension;
+use spectrum_cardano_lib::{
+    AssetClass, AssetName, NetworkId, OutputRef, TaggedAmount, TaggedAssetClass, Token,
+};
+use spectrum_offchain::data::Has;
+use spectrum_offchain_cardano::creds::{OperatorCred, OperatorCredSet, OperatorRewardAddress};
+use spectrum_offchain_cardano::data::cfmm_pool::{ConstFnPool, ConstFnPoolVer};
+use spectrum_offchain_cardano::data::pool::PoolBounds;
+use spectrum_offchain_cardano::data::PoolId;
+use spectrum_offchain_cardano::deployment::DeployedValidator;
+use spectrum_offchain_cardano::deployment::ProtocolValidator::{
+    ConstFnPoolFeeSwitch, ConstFnPoolFeeSwitchBiDirFee, ConstFnPoolFeeSwitchV2, ConstFnPoolV1, ConstFnPoolV2,
+    LimitOrderV1, LimitOrderWitnessV1,
+};
+
+use crate::execution_engine::babel_fee::BabelFees;
+use crate::execution_engine::exposure::OperatorInventory;
+use crate::execution_engine::interpreter::CardanoRecipeInterpreter;
+use crate::execution_engine::ref_inputs::RefInputRegistry;
+use crate::orders::limit::{Datum, LimitOrder};
+
+#[test]
+fn cfmm_swap() {
+    let ctx = GoldenContext::new();
+    let order = limit_order(1, AssetClass::Native, 10_000_000, token(), &ctx);
+    let (make, gain, loss) = make(cfmm_pool(2, ConstFnPoolVer::V1, &ctx), OnSide::Ask(10_000_000));
+    let recipe = vec![Either::Left(take(order, gain, loss)), Either::Right(make)];
+    assert_golden("cfmm_swap", &interpret(recipe, ctx));
+}
+
+#[test]
+fn fee_switch_swap() {
+    let ctx = GoldenContext::new();
+    let order = limit_order(1, token(), 30_000_000, AssetClass::Native, &ctx);
+    let (make, gain, loss) = make(
+        cfmm_pool(2, ConstFnPoolVer::FeeSwitch, &ctx),
+        OnSide::Bid(20_000_000),
+    );
+    let recipe = vec![Either::Left(take(order, gain, loss)), Either::Right(make)];
+    assert_golden("fee_switch_swap", &interpret(recipe, ctx));
+}
+
+#[test]
+fn two_fragment_fill() {
+    let ctx = GoldenContext::new();
+    let ask = limit_order(1, AssetClass::Native, 10_000_000, token(), &ctx);
+    let bid = limit_order(2, token(), 25_000_000, AssetClass::Native, &ctx);
+    let recipe = vec![
+        Either::Left(take(ask, 10_000_000, 20_000_000)),
+        Either::Left(take(bid, 20_000_000, 10_000_000)),
+    ];
+    assert_golden("two_fragment_fill", &interpret(recipe, ctx));
+}
+
+#[test]
+fn pool_fragment_mix() {
+    let ctx = GoldenContext::new();
+    let ask = limit_order(1, AssetClass::Native, 20_000_000, token(), &ctx);
+    let bid = limit_order(2, token(), 10_000_000, AssetClass::Native, &ctx);
+    let (make, gain, loss) = make(
+        cfmm_pool(3, ConstFnPoolVer::FeeSwitch, &ctx),
+        OnSide::Ask(15_000_000),
+    );
+    let recipe = vec![
+        Either::Left(take(ask, 5_000_000 + gain, 10_000_000 + loss)),
+        Either::Left(take(bid, 10_000_000, 5_000_000)),
+        Either::Right(make),
+    ];
+    assert_golden("pool_fragment_mix", &interpret(recipe, ctx));
+}
+
+fn interpret(
+    recipe: Vec<Either<Take<LimitOrder, FinalizedTxOut>, Make<ConstFnPool, FinalizedTxOut>>>,
+    ctx: GoldenContext,
+) -> TransactionBody {
+    let funding = FinalizedTxOut(
+        TransactionOutput::new(operator_address(), Value::from(5_000_000), None, None),
+        OutputRef::new(TransactionHash::from([0xf0; 32]), 0),
+    );
+    CardanoRecipeInterpreter
+        .run(ExecutionRecipe(recipe), funding, ctx)
+        .expect("Recipe must be interpretable")
+        .txc
+        .body()
+}
+
+/// Compare the given TX body with the recorded one.
+fn assert_golden(name: &str, body: &TransactionBody) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.cbor.hex", name));
+    let actual = hex::encode(body.to_cbor_bytes());
+    match fs::read_to_string(&path) {
+        Ok(expected) if env::var("UPDATE_GOLDEN").is_err() => {
+            let expected = expected.trim();
+            if expected != actual {
+                let expected_body =
+                    TransactionBody::from_cbor_bytes(&hex::decode(expected).expect("Fixture must be hex"))
+                        .expect("Fixture must be a TX body");
+                panic!(
+                    "TX body of `{}` diverged from {}:\n{}",
+                    name,
+                    path.display(),
+                    diff(&format!("{:#?}", expected_body), &format!("{:#?}", body))
+                );
+            }
+        }
+        _ => {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("{}\n", actual)).unwrap();
+            eprintln!("Recorded golden fixture {}", path.display());
+        }
+    }
+}
+
+/// Line-wise diff of two debug representations, only diverging lines are shown.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    let mut out = String::new();
+    for ix in 0..expected.len().max(actual.len()) {
+        let (lh, rh) = (expected.get(ix), actual.get(ix));
+        if lh != rh {
+            if let Some(lh) = lh {
+                out.push_str(&format!("{:>5} - {}\n", ix + 1, lh));
+            }
+            if let Some(rh) = rh {
+                out.push_str(&format!("{:>5} + {}\n", ix + 1, rh));
+            }
+        }
+    }
+    out
+}
+
+/// Fill the order by `removed_input` in exchange for `added_output` in a single execution step.
+pub(super) fn take(
+    Bundled(order, bearer): Bundled<LimitOrder, FinalizedTxOut>,
+    removed_input: u64,
+    added_output: u64,
+) -> Take<LimitOrder, FinalizedTxOut> {
+    let step_cost = -(order.max_cost_per_ex_step as i64);
+    let result = match order
+        .with_fee_charged(order.operator_fee(removed_input))
+        .with_applied_trade(removed_input, added_output)
+    {
+        Next::Succ(next) => Next::Succ(next.with_budget_corrected(step_cost).1),
+        Next::Term(term) => Next::Term(term.with_budget_corrected(step_cost).1),
+    };
+    Trans::new(Bundled(order, bearer), result)
+}
+
+/// Swap against the pool, returning the transition along with pool's gain and loss.
+fn make(
+    pool: Bundled<ConstFnPool, FinalizedTxOut>,
+    input: OnSide<u64>,
+) -> (Make<ConstFnPool, FinalizedTxOut>, u64, u64) {
+    let Next::Succ(next) = pool.0.swap(input) else {
+        panic!("Pool must survive the swap")
+    };
+    let make = Trans::new(pool, Next::Succ(next));
+    let (gain, loss) = (make.gain().unwrap(), make.loss().unwrap());
+    (make, gain, loss)
+}
+
+const MIN_LOVELACE: u64 = 1_500_000;
+const ORDER_FEE: u64 = 300_000;
+const ORDER_BUDGET: u64 = 3_000_000;
+const ORDER_STEP_COST: u64 = 1_500_000;
+
+pub(super) fn limit_order(
+    seed: u8,
+    input: AssetClass,
+    tradable_input: u64,
+    output: AssetClass,
+    ctx: &GoldenContext,
+) -> Bundled<LimitOrder, FinalizedTxOut> {
+    let owner = Ed25519KeyHash::from([seed; 28]);
+    let redeemer_address = PlutusAddress {
+        payment_cred: PlutusCredential::PubKey(owner),
+        stake_cred: None,
+    };
+    // Loose enough for every fill in this suite to respect it.
+    let base_price = Ratio::new(1, 4);
+    let datum = Datum {
+        beacon: PolicyId::from([seed; 28]),
+        input,
+        tradable_input,
+        cost_per_ex_step: ORDER_STEP_COST,
+        min_marginal_output: 1,
+        output,
+        base_price,
+        fee: ORDER_FEE,
+        redeemer_address,
+        cancellation_pkh: owner,
+        permitted_executors: vec![],
+        fee_asset: AssetClass::Native,
+    };
+    let mut value = Value::from(MIN_LOVELACE + ORDER_FEE + ORDER_BUDGET);
+    value.add_unsafe(input, tradable_input);
+    let utxo = TransactionOutput::new(
+        script_address(ctx.limit_order.hash),
+        value,
+        Some(DatumOption::new_datum(datum.into_pd())),
+        None,
+    );
+    let order = LimitOrder {
+        beacon: PolicyId::from([seed; 28]),
+        input_asset: input,
+        input_amount: tradable_input,
+        output_asset: output,
+        output_amount: 0,
+        base_price,
+        fee_asset: AssetClass::Native,
+        execution_budget: ORDER_BUDGET,
+        fee: ORDER_FEE,
+        max_cost_per_ex_step: ORDER_STEP_COST,
+        min_marginal_output: 1,
+        redeemer_address,
+        cancellation_pkh: owner,
+        requires_executor_sig: false,
+        virgin: false,
+        marginal_cost: ctx.limit_order.marginal_cost,
+        arrived_at: 0,
+    };
+    Bundled(
+        order,
+        FinalizedTxOut(utxo, OutputRef::new(TransactionHash::from([seed; 32]), 0)),
+    )
+}
+
+fn cfmm_pool(seed: u8, ver: ConstFnPoolVer, ctx: &GoldenContext) -> Bundled<ConstFnPool, FinalizedTxOut> {
+    let (reserves_x, reserves_y, liquidity) = (1_000_000_000, 2_000_000_000, 1_000_000_000);
+    let nft = token_of(seed, b"nft");
+    let lq = AssetClass::Token(token_of(seed, b"lq"));
+    let (hash, treasury_fee) = match ver {
+        ConstFnPoolVer::V1 => (ctx.cfmm_v1.hash, 0),
+        _ => (ctx.cfmm_fee_switch.hash, 100),
+    };
+    let pool = ConstFnPool {
+        id: PoolId::from(nft),
+        reserves_x: TaggedAmount::new(reserves_x),
+        reserves_y: TaggedAmount::new(reserves_y),
+        liquidity: TaggedAmount::new(liquidity),
+        asset_x: TaggedAssetClass::new(AssetClass::Native),
+        asset_y: TaggedAssetClass::new(token()),
+        asset_lq: TaggedAssetClass::new(lq),
+        lp_fee_x: Ratio::new_raw(99700, 100000),
+        lp_fee_y: Ratio::new_raw(99700, 100000),
+        treasury_fee: Ratio::new_raw(treasury_fee, 100000),
+        treasury_x: TaggedAmount::new(0),
+        treasury_y: TaggedAmount::new(0),
+        lq_lower_bound: TaggedAmount::new(0),
+        ver,
+        marginal_cost: ExUnits {
+            mem: 10_000,
+            steps: 5_000_000,
+        },
+        bounds: PoolBounds {
+            min_n2t_lovelace: 10_000_000,
+            min_t2t_lovelace: 10_000_000,
+            bootstrap_lovelace: 0,
+            bootstrap_liquidity: 0,
+            swap_deposit_surplus: false,
+        },
+    };
+    let mut value = Value::from(reserves_x);
+    value.add_unsafe(token(), reserves_y);
+    value.add_unsafe(lq, 9_000_000_000 - liquidity);
+    value.add_unsafe(AssetClass::Token(nft), 1);
+    // The interpreter only rewrites treasury fields of the datum, so its remaining content is irrelevant.
+    let datum = PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+        0,
+        (0..10).map(|_| 0u64.into_pd()).collect(),
+    ));
+    let utxo = TransactionOutput::new(
+        script_address(hash),
+        value,
+        Some(DatumOption::new_datum(datum)),
+        None,
+    );
+    Bundled(
+        pool,
+        FinalizedTxOut(utxo, OutputRef::new(TransactionHash::from([seed; 32]), 1)),
+    )
+}
+
+fn token_of(seed: u8, name: &[u8]) -> Token {
+    (
+        PolicyId::from([seed; 28]),
+        AssetName::try_from(name.to_vec()).unwrap(),
+    )
+}
+
+pub(super) fn token() -> AssetClass {
+    AssetClass::Token(token_of(0x7e, b"GOLD"))
+}
+
+const NETWORK: u8 = 0;
+
+fn operator_address() -> Address {
+    EnterpriseAddress::new(NETWORK, Credential::new_pub_key(Ed25519KeyHash::from([0xee; 28]))).to_address()
+}
+
+fn script_address(hash: ScriptHash) -> Address {
+    EnterpriseAddress::new(NETWORK, Credential::new_script(hash)).to_address()
+}
+
+/// Validator whose reference script is a stub unique to the given type.
+fn deployed<const TYP: u8>() -> DeployedValidator<TYP> {
+    let script = Script::new_plutus_v2(PlutusV2Script::new(vec![0x4d, 0x01, 0x00, 0x00, TYP]));
+    let hash = script.hash();
+    let reference_utxo = TransactionUnspentOutput::new(
+        TransactionInput::new(TransactionHash::from([0xaa; 32]), TYP as u64),
+        TransactionOutput::new(operator_address(), Value::from(10_000_000), None, Some(script)),
+    );
+    DeployedValidator {
+        reference_utxo,
+        hash,
+        cost: ExUnits {
+            mem: 500_000,
+            steps: 200_000_000,
+        },
+        marginal_cost: ExUnits {
+            mem: 10_000,
+            steps: 5_000_000,
+        },
+    }
+}
+
+#[derive(Clone)]
+pub(super) struct GoldenContext {
+    collateral: Collateral,
+    ref_inputs: RefInputRegistry,
+    inventory: OperatorInventory,
+    babel_fees: BabelFees,
+    limit_order: DeployedValidator<{ LimitOrderV1 as u8 }>,
+    limit_order_witness: DeployedValidator<{ LimitOrderWitnessV1 as u8 }>,
+    cfmm_v1: DeployedValidator<{ ConstFnPoolV1 as u8 }>,
+    cfmm_v2: DeployedValidator<{ ConstFnPoolV2 as u8 }>,
+    cfmm_fee_switch: DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>,
+    cfmm_fee_switch_v2: DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>,
+    cfmm_fee_switch_bidir: DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>,
+}
+
+impl GoldenContext {
+    pub(super) fn new() -> Self {
+        let collateral = TransactionUnspentOutput::new(
+            TransactionInput::new(TransactionHash::from([0xcc; 32]), 0),
+            TransactionOutput::new(operator_address(), Value::from(5_000_000), None, None),
+        );
+        Self {
+            collateral: Collateral::from(collateral),
+            ref_inputs: RefInputRegistry::default(),
+            inventory: OperatorInventory::default(),
+            babel_fees: BabelFees::default(),
+            limit_order: deployed(),
+            limit_order_witness: deployed(),
+            cfmm_v1: deployed(),
+            cfmm_v2: deployed(),
+            cfmm_fee_switch: deployed(),
+            cfmm_fee_switch_v2: deployed(),
+            cfmm_fee_switch_bidir: deployed(),
+        }
+    }
+}
+
+impl Has<Collateral> for GoldenContext {
+    fn select<U: IsEqual<Collateral>>(&self) -> Collateral {
+        self.collateral.clone()
+    }
+}
+
+impl Has<RefInputRegistry> for GoldenContext {
+    fn select<U: IsEqual<RefInputRegistry>>(&self) -> RefInputRegistry {
+        self.ref_inputs.clone()
+    }
+}
+
+impl Has<OperatorInventory> for GoldenContext {
+    fn select<U: IsEqual<OperatorInventory>>(&self) -> OperatorInventory {
+        self.inventory.clone()
+    }
+}
+
+impl Has<BabelFees> for GoldenContext {
+    fn select<U: IsEqual<BabelFees>>(&self) -> BabelFees {
+        self.babel_fees.clone()
+    }
+}
+
+impl Has<NetworkId> for GoldenContext {
+    fn select<U: IsEqual<NetworkId>>(&self) -> NetworkId {
+        NetworkId::from(NETWORK)
+    }
+}
+
+impl Has<OperatorRewardAddress> for GoldenContext {
+    fn select<U: IsEqual<OperatorRewardAddress>>(&self) -> OperatorRewardAddress {
+        OperatorRewardAddress(operator_address())
+    }
+}
+
+impl Has<OperatorCredSet> for GoldenContext {
+    fn select<U: IsEqual<OperatorCredSet>>(&self) -> OperatorCredSet {
+        OperatorCredSet::new(OperatorCred(Ed25519KeyHash::from([0xee; 28])))
+    }
+}
+
+impl Has<DeployedValidator<{ LimitOrderV1 as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ LimitOrderV1 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ LimitOrderV1 as u8 }> {
+        self.limit_order.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ LimitOrderWitnessV1 as u8 }> {
+        self.limit_order_witness.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolV1 as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolV1 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolV1 as u8 }> {
+        self.cfmm_v1.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolV2 as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolV2 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolV2 as u8 }> {
+        self.cfmm_v2.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }> {
+        self.cfmm_fee_switch.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }> {
+        self.cfmm_fee_switch_v2.clone()
+    }
+}
+
+impl Has<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>> for GoldenContext {
+    fn select<U: IsEqual<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>>>(
+        &self,
+    ) -> DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }> {
+        self.cfmm_fee_switch_bidir.clone()
+    }
+}
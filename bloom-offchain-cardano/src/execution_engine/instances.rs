@@ -7,15 +7,20 @@ use bloom_offchain::execution_engine::batch_exec::BatchExec;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use bloom_offchain::execution_engine::execution_effect::ExecutionEff;
 use bloom_offchain::execution_engine::liquidity_book::core::{Make, Next, Take, Trans};
+use bloom_offchain::execution_engine::liquidity_book::types::{InputAsset, OutputAsset};
 use spectrum_cardano_lib::output::FinalizedTxOut;
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::value::ValueExtension;
 use spectrum_cardano_lib::{AssetClass, NetworkId};
 use spectrum_offchain::data::Has;
-use spectrum_offchain_cardano::creds::OperatorCred;
+use spectrum_offchain_cardano::creds::OperatorCredSet;
 use spectrum_offchain_cardano::data::balance_pool::{BalancePool, BalancePoolRedeemer};
 use spectrum_offchain_cardano::data::cfmm_pool::ConstFnPoolVer::{FeeSwitch, FeeSwitchV2};
 use spectrum_offchain_cardano::data::cfmm_pool::{CFMMPoolRedeemer, ConstFnPool};
 use spectrum_offchain_cardano::data::pool::{AnyPool, CFMMPoolAction, PoolAssetMapping};
+use spectrum_offchain_cardano::data::pool_datum_diff::{
+    is_treasury_update, FEE_SWITCH_POOL_LAYOUT, STABLE_POOL_T2T_LAYOUT,
+};
 use spectrum_offchain_cardano::data::stable_pool_t2t::{StablePoolRedeemer, StablePoolT2T};
 use spectrum_offchain_cardano::data::{balance_pool, cfmm_pool, stable_pool_t2t};
 use spectrum_offchain_cardano::deployment::ProtocolValidator::{
@@ -28,9 +33,13 @@ use spectrum_offchain_cardano::script::{
     delayed_cost, delayed_redeemer, ready_cost, ready_redeemer, ScriptWitness,
 };
 
+use crate::execution_engine::babel_fee::{BabelFeeRejected, BabelFees};
 use crate::execution_engine::execution_state::{ExecutionState, ScriptInputBlueprint};
+use crate::execution_engine::pool_datum::PoolDatumRejected;
+use crate::execution_engine::slippage;
 use crate::orders::grid::GridOrder;
 use crate::orders::limit::LimitOrder;
+use crate::orders::rfq::RfqOrder;
 use crate::orders::{grid, limit, AnyOrder};
 
 /// Magnet for local instances.
@@ -43,10 +52,11 @@ pub type FinalizedEffect<T> = ExecutionEff<Bundled<T, FinalizedTxOut>, Bundled<T
 impl<Ctx> BatchExec<ExecutionState, EffectPreview<AnyOrder>, Ctx> for Magnet<Take<AnyOrder, FinalizedTxOut>>
 where
     Ctx: Has<NetworkId>
-        + Has<OperatorCred>
+        + Has<OperatorCredSet>
         + Has<DeployedValidator<{ GridOrderNative as u8 }>>
         + Has<DeployedValidator<{ LimitOrderV1 as u8 }>>
-        + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>,
+        + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>
+        + Has<BabelFees>,
 {
     fn exec(self, state: ExecutionState, context: Ctx) -> (ExecutionState, EffectPreview<AnyOrder>, Ctx) {
         match self {
@@ -86,6 +96,24 @@ where
                     ctx,
                 )
             }
+            Magnet(Trans {
+                target: Bundled(AnyOrder::Rfq(o), src),
+                result,
+            }) => {
+                let (st, res, ctx) = Magnet(Trans {
+                    target: Bundled(o, src),
+                    result: result.map_succ(|ord| match ord {
+                        AnyOrder::Rfq(o2) => o2,
+                        _ => unreachable!(),
+                    }),
+                })
+                .exec(state, context);
+                (
+                    st,
+                    res.bimap(|u| u.map(AnyOrder::Rfq), |e| e.map(AnyOrder::Rfq)),
+                    ctx,
+                )
+            }
         }
     }
 }
@@ -94,9 +122,10 @@ impl<Ctx> BatchExec<ExecutionState, EffectPreview<LimitOrder>, Ctx>
     for Magnet<Take<LimitOrder, FinalizedTxOut>>
 where
     Ctx: Has<NetworkId>
-        + Has<OperatorCred>
+        + Has<OperatorCredSet>
         + Has<DeployedValidator<{ LimitOrderV1 as u8 }>>
-        + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>,
+        + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>
+        + Has<BabelFees>,
 {
     fn exec(
         self,
@@ -136,19 +165,56 @@ where
                 cost: ready_cost(ex_budget),
             },
             redeemer: ready_redeemer(limit::EXEC_REDEEMER),
+            // Orders may be bound to any of operator's credentials during a key rotation.
             required_signers: if ord.requires_executor_sig {
-                vec![Ed25519KeyHash::from(context.select::<OperatorCred>())]
+                context
+                    .select::<OperatorCredSet>()
+                    .iter()
+                    .map(Ed25519KeyHash::from)
+                    .collect()
             } else {
                 vec![]
             },
         };
         let mut candidate = consumed_out.clone();
-        // Subtract budget + fee used to facilitate execution.
-        candidate.sub_asset(ord.fee_asset, consumed_budget + consumed_fee);
         // Subtract tradable input used in exchange.
         candidate.sub_asset(ord.input_asset, removed_input);
         // Add output resulted from exchange.
         candidate.add_asset(ord.output_asset, added_output);
+        let paid_in_fee_asset = if ord.fee_asset == AssetClass::Native {
+            consumed_budget + consumed_fee
+        } else if ord.input_asset == AssetClass::Native {
+            lovelace_to_output(consumed_budget + consumed_fee, removed_input, added_output)
+        } else {
+            // Neither side is ADA, the fee is converted at the rate of the ADA pool of the output.
+            let available = candidate.value().amount_of(ord.fee_asset).unwrap_or(0);
+            match context
+                .select::<BabelFees>()
+                .convert(ord.fee_asset, consumed_budget + consumed_fee)
+            {
+                Ok(units) if units <= available => units,
+                Ok(_) => {
+                    state.reject_fee(BabelFeeRejected::InsufficientOutput(ord.fee_asset));
+                    0
+                }
+                Err(rejected) => {
+                    state.reject_fee(rejected);
+                    0
+                }
+            }
+        };
+        // Subtract budget + fee used to facilitate execution.
+        candidate.sub_asset(ord.fee_asset, paid_in_fee_asset);
+        // Budget and fee in ADA are covered by the deposit, only babel fees are taken from proceeds.
+        let net_output = if ord.fee_asset != AssetClass::Native && ord.fee_asset == ord.output_asset {
+            added_output.saturating_sub(paid_in_fee_asset)
+        } else {
+            added_output
+        };
+        if let Err(violation) = slippage::check_fill(in_ref, removed_input, net_output, ord.base_price) {
+            state.reject_slippage(violation);
+        }
+        let fee_asset = ord.fee_asset;
         let consumed_bundle = Bundled(ord, FinalizedTxOut(consumed_out, in_ref));
         let (residual_order, effect) = match result {
             Next::Succ(next) => {
@@ -168,7 +234,12 @@ where
         };
         let witness = context.select::<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>();
         state.add_tx_fee(consumed_budget);
-        state.add_operator_interest(consumed_fee);
+        if fee_asset == AssetClass::Native {
+            state.add_operator_interest(consumed_fee);
+        } else {
+            state.advance_tx_fee(fee_asset, consumed_budget);
+            state.add_operator_interest_in_kind(fee_asset, paid_in_fee_asset);
+        }
         state
             .tx_blueprint
             .add_witness(witness.erased(), PlutusData::new_list(vec![]));
@@ -178,6 +249,39 @@ where
     }
 }
 
+/// Amount of output worth [lovelace] at the price the order is filled at.
+fn lovelace_to_output(lovelace: u64, removed_input: InputAsset<u64>, added_output: OutputAsset<u64>) -> u64 {
+    (lovelace as u128 * added_output as u128)
+        .checked_div(removed_input as u128)
+        .map_or(0, |output| output.min(added_output as u128) as u64)
+}
+
+impl<Ctx> BatchExec<ExecutionState, EffectPreview<RfqOrder>, Ctx> for Magnet<Take<RfqOrder, FinalizedTxOut>> {
+    fn exec(self, mut state: ExecutionState, context: Ctx) -> (ExecutionState, EffectPreview<RfqOrder>, Ctx) {
+        let Magnet(trans) = self;
+        trace!("Running transition: {}", trans);
+        let removed_input = trans.removed_input();
+        let added_output = trans.added_output();
+        let Trans {
+            target: Bundled(ord, FinalizedTxOut(consumed_out, in_ref)),
+            result,
+        } = trans;
+        // Funds of the maker are returned to the same address, the maker co-signs the TX.
+        let mut candidate = consumed_out.clone();
+        candidate.sub_asset(ord.input_asset, removed_input);
+        candidate.add_asset(ord.output_asset, added_output);
+        let consumed_bundle = Bundled(ord, FinalizedTxOut(consumed_out.clone(), in_ref));
+        let effect = match result {
+            Next::Succ(next) => ExecutionEff::Updated(consumed_bundle, Bundled(next, candidate.clone())),
+            Next::Term(_) => ExecutionEff::Eliminated(consumed_bundle),
+        };
+        state
+            .tx_blueprint
+            .add_pubkey_io(FinalizedTxOut(consumed_out, in_ref), candidate);
+        (state, effect, context)
+    }
+}
+
 impl<Ctx> BatchExec<ExecutionState, EffectPreview<GridOrder>, Ctx> for Magnet<Take<GridOrder, FinalizedTxOut>>
 where
     Ctx: Has<NetworkId> + Has<DeployedValidator<{ GridOrderNative as u8 }>>,
@@ -380,11 +484,19 @@ where
 
         if transition.ver == FeeSwitch || transition.ver == FeeSwitchV2 {
             if let Some(data) = produced_out.data_mut() {
-                cfmm_pool::unsafe_update_pd(
+                let prev_data = data.clone();
+                let (treasury_x, treasury_y) = (transition.treasury_x.untag(), transition.treasury_y.untag());
+                if let Err(err) = cfmm_pool::update_treasury(data, treasury_x, treasury_y) {
+                    state.reject_datum(PoolDatumRejected::Update(in_ref, err));
+                } else if !is_treasury_update(
+                    &prev_data,
                     data,
-                    transition.treasury_x.untag(),
-                    transition.treasury_y.untag(),
-                );
+                    FEE_SWITCH_POOL_LAYOUT,
+                    treasury_x,
+                    treasury_y,
+                ) {
+                    state.reject_datum(PoolDatumRejected::UnexpectedMutation(in_ref));
+                }
             }
         }
 
@@ -457,11 +569,13 @@ where
         };
 
         if let Some(data) = produced_out.data_mut() {
-            balance_pool::unsafe_update_datum(
-                data,
-                transition.treasury_x.untag(),
-                transition.treasury_y.untag(),
-            );
+            let prev_data = data.clone();
+            let (treasury_x, treasury_y) = (transition.treasury_x.untag(), transition.treasury_y.untag());
+            if let Err(err) = balance_pool::update_treasury(data, treasury_x, treasury_y) {
+                state.reject_datum(PoolDatumRejected::Update(in_ref, err));
+            } else if !is_treasury_update(&prev_data, data, FEE_SWITCH_POOL_LAYOUT, treasury_x, treasury_y) {
+                state.reject_datum(PoolDatumRejected::UnexpectedMutation(in_ref));
+            }
         }
 
         let consumed = Bundled(pool, FinalizedTxOut(consumed_out, in_ref));
@@ -531,11 +645,13 @@ where
         };
 
         if let Some(data) = produced_out.data_mut() {
-            stable_pool_t2t::unsafe_update_datum(
-                data,
-                transition.treasury_x.untag(),
-                transition.treasury_y.untag(),
-            );
+            let prev_data = data.clone();
+            let (treasury_x, treasury_y) = (transition.treasury_x.untag(), transition.treasury_y.untag());
+            if let Err(err) = stable_pool_t2t::update_treasury(data, treasury_x, treasury_y) {
+                state.reject_datum(PoolDatumRejected::Update(in_ref, err));
+            } else if !is_treasury_update(&prev_data, data, STABLE_POOL_T2T_LAYOUT, treasury_x, treasury_y) {
+                state.reject_datum(PoolDatumRejected::UnexpectedMutation(in_ref));
+            }
         }
 
         let consumed = Bundled(pool, FinalizedTxOut(consumed_out, in_ref));
@@ -547,3 +663,25 @@ where
         (state, effect, context)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bloom_offchain::execution_engine::batch_exec::BatchExec;
+    use spectrum_cardano_lib::AssetClass;
+
+    use crate::execution_engine::execution_state::ExecutionState;
+    use crate::execution_engine::golden::{limit_order, take, token, GoldenContext};
+    use crate::execution_engine::instances::Magnet;
+
+    #[test]
+    fn ada_output_order_filled_at_its_limit_passes_slippage_check() {
+        let ctx = GoldenContext::new();
+        // Worst price of the order is 1/4 ADA per token.
+        let order = limit_order(1, token(), 4_000_000, AssetClass::Native, &ctx);
+        let (state, _, _) =
+            Magnet(take(order.clone(), 4_000_000, 1_000_000)).exec(ExecutionState::new(), ctx.clone());
+        assert_eq!(state.slippage_exceeded, None);
+        let (state, _, _) = Magnet(take(order, 4_000_000, 999_999)).exec(ExecutionState::new(), ctx);
+        assert_eq!(state.slippage_exceeded.map(|v| v.min_output), Some(1_000_000));
+    }
+}
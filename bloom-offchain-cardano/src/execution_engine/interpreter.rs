@@ -1,30 +1,42 @@
-use std::fmt::Debug;
+use std::fmt::{Debug, Display, Formatter};
 
 use cml_chain::builders::tx_builder::{ChangeSelectionAlgo, SignedTxBuilder, TransactionBuilder};
 use cml_chain::transaction::TransactionOutput;
 use either::Either;
-use log::trace;
-use num_rational::Ratio;
+use log::{trace, warn};
 use tailcall::tailcall;
 
 use bloom_offchain::execution_engine::batch_exec::BatchExec;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use bloom_offchain::execution_engine::funding_effect::FundingIO;
 use bloom_offchain::execution_engine::liquidity_book::core::{Execution, ExecutionRecipe, Make, Take};
-use bloom_offchain::execution_engine::liquidity_book::interpreter::{ExecutionResult, RecipeInterpreter};
+use bloom_offchain::execution_engine::liquidity_book::interpreter::{
+    ExecutionResult, RecipeInterpreter, RecipeRejected,
+};
+use bloom_offchain::execution_engine::liquidity_book::market_maker::MarketMaker;
 use bloom_offchain::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use bloom_offchain::execution_engine::liquidity_book::types::Lovelace;
 use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::ex_units::ExUnits;
 use spectrum_cardano_lib::hash::hash_transaction_canonical;
 use spectrum_cardano_lib::output::FinalizedTxOut;
-use spectrum_cardano_lib::protocol_params::constant_tx_builder;
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, MAX_TX_EX_UNITS, MAX_TX_SIZE};
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::{NetworkId, OutputRef};
 use spectrum_offchain::data::{Baked, Has};
+use spectrum_offchain_cardano::constants::MIN_SAFE_LOVELACE_VALUE;
 use spectrum_offchain_cardano::creds::{OperatorCred, OperatorRewardAddress};
 use spectrum_offchain_cardano::deployment::DeployedValidator;
 use spectrum_offchain_cardano::deployment::ProtocolValidator::{GridOrderNative, LimitOrderWitnessV1};
 
+use crate::execution_engine::babel_fee::{BabelFeeRejected, BabelFees};
+use crate::execution_engine::budget_ledger::{BudgetImbalance, BudgetLedger, BudgetOverallocation};
 use crate::execution_engine::execution_state::ExecutionState;
+use crate::execution_engine::exposure::{AccountingDelta, ExposureLimitBreached, OperatorInventory};
 use crate::execution_engine::instances::{EffectPreview, FinalizedEffect, Magnet};
+use crate::execution_engine::pool_datum::PoolDatumRejected;
+use crate::execution_engine::ref_inputs::{RefInputRegistry, UnresolvedRefInput};
+use crate::execution_engine::slippage::SlippageExceeded;
 
 /// A short-living interpreter.
 #[derive(Debug, Copy, Clone)]
@@ -33,8 +45,8 @@ pub struct CardanoRecipeInterpreter;
 impl<'a, Fr, Pl, Ctx> RecipeInterpreter<Fr, Pl, Ctx, OutputRef, FinalizedTxOut, SignedTxBuilder>
     for CardanoRecipeInterpreter
 where
-    Fr: MarketTaker + TakerBehaviour + Copy + Debug,
-    Pl: Copy + Debug,
+    Fr: MarketTaker<U = ExUnits> + TakerBehaviour + Copy + Debug,
+    Pl: MarketMaker<U = ExUnits> + Copy + Debug,
     Magnet<Take<Fr, FinalizedTxOut>>: BatchExec<ExecutionState, EffectPreview<Fr>, Ctx>,
     Magnet<Make<Pl, FinalizedTxOut>>: BatchExec<ExecutionState, EffectPreview<Pl>, Ctx>,
     Ctx: Clone
@@ -42,6 +54,9 @@ where
         + Has<Collateral>
         + Has<NetworkId>
         + Has<OperatorRewardAddress>
+        + Has<RefInputRegistry>
+        + Has<OperatorInventory>
+        + Has<BabelFees>
         + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>,
 {
     fn run(
@@ -49,8 +64,19 @@ where
         ExecutionRecipe(instructions): ExecutionRecipe<Fr, Pl, FinalizedTxOut>,
         funding: FinalizedTxOut,
         ctx: Ctx,
-    ) -> ExecutionResult<Fr, Pl, OutputRef, FinalizedTxOut, SignedTxBuilder> {
-        let (mut tx_builder, effects, funding_io_preview, ctx) = execute_recipe(funding, ctx, instructions);
+    ) -> Result<ExecutionResult<Fr, Pl, OutputRef, FinalizedTxOut, SignedTxBuilder>, RecipeRejected> {
+        let (mut tx_builder, effects, funding_io_preview, accounting, ctx) =
+            match execute_recipe(funding, ctx, instructions) {
+                Ok(result) => result,
+                Err(err @ RecipeDropped::Oversized { .. }) => {
+                    trace!("Recipe dropped: {}", err);
+                    return Err(RecipeRejected::Oversized);
+                }
+                Err(err) => {
+                    warn!("Recipe dropped: {}", err);
+                    return Err(RecipeRejected::Inconsistent);
+                }
+            };
         let execution_fee_address = ctx.select::<OperatorRewardAddress>().into();
         // Build tx, change is execution fee.
         let tx = tx_builder
@@ -59,6 +85,7 @@ where
         let tx_body_cloned = tx.body();
         let tx_hash = hash_transaction_canonical(&tx_body_cloned);
         let tx_outputs = tx_body_cloned.outputs;
+        ctx.select::<OperatorInventory>().apply(accounting);
 
         // Map finalized outputs to states of corresponding domain entities.
         let mut finalized_effects = vec![];
@@ -97,11 +124,11 @@ where
         });
 
         trace!("Finished Tx: {}", tx_hash);
-        ExecutionResult {
+        Ok(ExecutionResult {
             txc: tx,
             matchmaking_effects: finalized_effects,
             funding_io: finalized_funding_io,
-        }
+        })
     }
 }
 
@@ -110,15 +137,19 @@ fn execute_recipe<Fr, Pl, Ctx>(
     funding: FinalizedTxOut,
     ctx: Ctx,
     instructions: Vec<Execution<Fr, Pl, FinalizedTxOut>>,
-) -> (
-    TransactionBuilder,
-    Vec<EffectPreview<Either<Fr, Pl>>>,
-    FundingIO<FinalizedTxOut, TransactionOutput>,
-    Ctx,
-)
+) -> Result<
+    (
+        TransactionBuilder,
+        Vec<EffectPreview<Either<Fr, Pl>>>,
+        FundingIO<FinalizedTxOut, TransactionOutput>,
+        AccountingDelta,
+        Ctx,
+    ),
+    RecipeDropped,
+>
 where
-    Fr: MarketTaker + TakerBehaviour + Copy,
-    Pl: Copy,
+    Fr: MarketTaker<U = ExUnits> + TakerBehaviour + Copy,
+    Pl: MarketMaker<U = ExUnits> + Copy,
     Magnet<Take<Fr, FinalizedTxOut>>: BatchExec<ExecutionState, EffectPreview<Fr>, Ctx>,
     Magnet<Make<Pl, FinalizedTxOut>>: BatchExec<ExecutionState, EffectPreview<Pl>, Ctx>,
     Ctx: Clone
@@ -126,18 +157,72 @@ where
         + Has<Collateral>
         + Has<NetworkId>
         + Has<OperatorRewardAddress>
+        + Has<RefInputRegistry>
+        + Has<OperatorInventory>
+        + Has<BabelFees>
         + Has<DeployedValidator<{ LimitOrderWitnessV1 as u8 }>>,
 {
+    let mut ledger = budget_ledger(&instructions).map_err(RecipeDropped::Overallocation)?;
+    for i in &instructions {
+        let ex_units = match i {
+            Either::Left(take) => take.target.0.marginal_cost_hint(),
+            Either::Right(make) => make.target.0.marginal_cost_hint(),
+        };
+        ledger
+            .reserve_ex_units(ex_units)
+            .map_err(RecipeDropped::Overallocation)?;
+    }
+    let registry = ctx.select::<RefInputRegistry>();
+    let mut ref_inputs = vec![];
+    for i in &instructions {
+        let bearer = match i {
+            Either::Left(take) => &take.target.1,
+            Either::Right(make) => &make.target.1,
+        };
+        ref_inputs.extend(
+            registry
+                .resolve_for(&bearer.0)
+                .map_err(RecipeDropped::UnresolvedRefInput)?,
+        );
+    }
     let state = ExecutionState::new();
     let (
         ExecutionState {
-            tx_blueprint,
+            mut tx_blueprint,
             reserved_tx_fee,
             operator_interest,
+            advanced_tx_fee,
+            operator_interest_in_kind,
+            accounting,
+            rejected_fee,
+            slippage_exceeded,
+            rejected_datum,
         },
         effects,
         ctx,
     ) = execute(ctx, state, Vec::new(), instructions.clone());
+    if let Some(rejected) = rejected_fee {
+        return Err(RecipeDropped::FeeRejected(rejected));
+    }
+    if let Some(violation) = slippage_exceeded {
+        return Err(RecipeDropped::SlippageExceeded(violation));
+    }
+    if let Some(rejected) = rejected_datum {
+        return Err(RecipeDropped::DatumRejected(rejected));
+    }
+    ctx.select::<OperatorInventory>()
+        .admit(&accounting)
+        .map_err(RecipeDropped::ExposureLimit)?;
+    let available = funding.0.value().coin + operator_interest;
+    if advanced_tx_fee > 0 && available < advanced_tx_fee + MIN_SAFE_LOVELACE_VALUE {
+        return Err(RecipeDropped::FundingShortfall {
+            required: advanced_tx_fee + MIN_SAFE_LOVELACE_VALUE,
+            available,
+        });
+    }
+    for utxo in ref_inputs {
+        tx_blueprint.add_ref_input(utxo);
+    }
     trace!("Going to interpret blueprint: {}", tx_blueprint);
     let (mut tx_builder, funding_io) = tx_blueprint.project_onto_builder(
         constant_tx_builder(),
@@ -145,6 +230,8 @@ where
         ctx.select::<OperatorRewardAddress>(),
         funding.clone(),
         operator_interest,
+        advanced_tx_fee,
+        operator_interest_in_kind,
     );
     tx_builder
         .add_collateral(ctx.select::<Collateral>().into())
@@ -159,39 +246,117 @@ where
         fee_mismatch
     );
     if fee_mismatch != 0 {
-        let fee_rescale_factor = Ratio::new(estimated_fee, reserved_tx_fee);
-        let corrected_recipe = balance_fee(fee_mismatch, fee_rescale_factor, instructions);
-        execute_recipe(funding, ctx, corrected_recipe)
+        match balance_fee(&ledger, estimated_fee, instructions) {
+            Ok(corrected_recipe) => execute_recipe(funding, ctx, corrected_recipe),
+            Err(err) => Err(RecipeDropped::Imbalance(err)),
+        }
     } else {
-        (tx_builder, effects, funding_io, ctx)
+        let size = tx_builder.full_size().unwrap_or(usize::MAX);
+        let max_size = MAX_TX_SIZE as usize - CHANGE_OUTPUT_SIZE_RESERVE;
+        if size > max_size {
+            return Err(RecipeDropped::Oversized { size, max_size });
+        }
+        Ok((tx_builder, effects, funding_io, accounting, ctx))
     }
 }
 
+/// Room (in bytes) left for the change output added once the TX is built.
+const CHANGE_OUTPUT_SIZE_RESERVE: usize = 128;
+
+/// Reason a recipe cannot be turned into a TX.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecipeDropped {
+    Overallocation(BudgetOverallocation),
+    Imbalance(BudgetImbalance),
+    /// UTxO some validator reads external state from is unknown.
+    UnresolvedRefInput(UnresolvedRefInput),
+    /// Funding cannot cover TX fee advanced on behalf of orders paying in tokens.
+    FundingShortfall {
+        required: u64,
+        available: u64,
+    },
+    /// Execution in one of involved assets is paused.
+    ExposureLimit(ExposureLimitBreached),
+    /// Fee of one of the orders cannot be taken in the token it pays in.
+    FeeRejected(BabelFeeRejected),
+    /// One of the orders would be filled below its declared price.
+    SlippageExceeded(SlippageExceeded),
+    /// Datum of one of the pools can't be updated.
+    DatumRejected(PoolDatumRejected),
+    /// TX would exceed max size.
+    Oversized {
+        size: usize,
+        max_size: usize,
+    },
+}
+
+impl Display for RecipeDropped {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecipeDropped::Overallocation(overallocation) => Display::fmt(overallocation, f),
+            RecipeDropped::Imbalance(imbalance) => Display::fmt(imbalance, f),
+            RecipeDropped::UnresolvedRefInput(unresolved) => Display::fmt(unresolved, f),
+            RecipeDropped::FundingShortfall { required, available } => write!(
+                f,
+                "Funding shortfall: required {} lovelace, available {}",
+                required, available
+            ),
+            RecipeDropped::ExposureLimit(breached) => Display::fmt(breached, f),
+            RecipeDropped::FeeRejected(rejected) => Display::fmt(rejected, f),
+            RecipeDropped::SlippageExceeded(violation) => Display::fmt(violation, f),
+            RecipeDropped::DatumRejected(rejected) => Display::fmt(rejected, f),
+            RecipeDropped::Oversized { size, max_size } => {
+                write!(f, "TX size {} exceeds max {}", size, max_size)
+            }
+        }
+    }
+}
+
+/// Reserve budget and execution units consumed by each instruction of the recipe.
+fn budget_ledger<Fr, Pl, Bearer>(
+    instructions: &[Execution<Fr, Pl, Bearer>],
+) -> Result<BudgetLedger, BudgetOverallocation>
+where
+    Fr: MarketTaker,
+{
+    let mut ledger = BudgetLedger::new(MAX_TX_EX_UNITS);
+    for (ix, i) in instructions.iter().enumerate() {
+        if let Either::Left(take) = i {
+            ledger.reserve(ix, take.consumed_budget(), take.target.0.budget())?;
+        }
+    }
+    Ok(ledger)
+}
+
+/// Redistribute consumed budget across fills so that it matches [required_fee] exactly.
 fn balance_fee<Fr, Pl, Bearer>(
-    mut fee_mismatch: i64,
-    rescale_factor: Ratio<u64>,
+    ledger: &BudgetLedger,
+    required_fee: Lovelace,
     mut instructions: Vec<Execution<Fr, Pl, Bearer>>,
-) -> Vec<Execution<Fr, Pl, Bearer>>
+) -> Result<Vec<Execution<Fr, Pl, Bearer>>, BudgetImbalance>
 where
     Fr: MarketTaker + TakerBehaviour + Copy,
 {
-    for i in &mut instructions {
-        if let Either::Left(take) = i {
-            let delta = take.scale_consumed_budget(rescale_factor);
-            fee_mismatch += delta;
+    let deltas = ledger.rebalance(required_fee)?;
+    let mut allocated = 0;
+    for (ix, i) in instructions.iter_mut().enumerate() {
+        if let (Either::Left(take), Some(planned)) = (i, deltas.get(&ix)) {
+            let delta = take.correct_consumed_budget(*planned);
+            trace!(
+                target: "budget",
+                "Fill #{}: consumed budget {} (delta {})",
+                ix,
+                take.consumed_budget(),
+                delta
+            );
+            allocated += delta;
         }
     }
-    for i in &mut instructions {
-        if let Either::Left(take) = i {
-            if fee_mismatch != 0 {
-                let delta = take.correct_consumed_budget(-fee_mismatch);
-                fee_mismatch += delta;
-            } else {
-                break;
-            }
-        }
+    let required = required_fee as i64 - ledger.consumed() as i64;
+    if allocated != required {
+        return Err(BudgetImbalance { required, allocated });
     }
-    instructions
+    Ok(instructions)
 }
 
 #[tailcall]
@@ -232,7 +397,6 @@ mod tests {
     use std::fmt::{Display, Formatter};
 
     use either::Either;
-    use num_rational::Ratio;
 
     use bloom_offchain::execution_engine::bundled::Bundled;
     use bloom_offchain::execution_engine::liquidity_book::core::{Next, TerminalTake, Trans, Unit};
@@ -243,7 +407,9 @@ mod tests {
         AbsolutePrice, ExCostUnits, FeeAsset, InputAsset, OutputAsset,
     };
 
-    use crate::execution_engine::interpreter::balance_fee;
+    use crate::execution_engine::babel_fee::{BabelFeeRejected, BabelFees};
+    use crate::execution_engine::budget_ledger::BudgetImbalance;
+    use crate::execution_engine::interpreter::{balance_fee, budget_ledger};
 
     #[test]
     fn fee_overuse_balancing() {
@@ -255,11 +421,13 @@ mod tests {
             Either::Left(Trans::new(Bundled(t0_0, ()), Next::Succ(t0_1))),
             Either::Left(Trans::new(Bundled(t1_0, ()), Next::Succ(t1_1))),
         ];
-        let reserved_fee = 500000;
         let estimated_fee = 456325;
-        let rescale_factor = Ratio::new(estimated_fee, reserved_fee);
-        let fee_mismatch = reserved_fee as i64 - estimated_fee as i64;
-        let balanced_instructions = balance_fee::<_, (), _>(fee_mismatch, rescale_factor, instructions);
+        let balanced_instructions = balance_fee(
+            &budget_ledger::<_, (), _>(&instructions).unwrap(),
+            estimated_fee,
+            instructions,
+        )
+        .unwrap();
         assert_eq!(
             balanced_instructions
                 .iter()
@@ -280,8 +448,12 @@ mod tests {
         let reserved_fee = 2000000u64;
         let fee_mismatch = 1658040i64;
         let estimated_fee = reserved_fee - fee_mismatch as u64;
-        let rescale_factor = Ratio::new(estimated_fee, reserved_fee);
-        let balanced_instructions = balance_fee::<_, (), _>(fee_mismatch, rescale_factor, instructions);
+        let balanced_instructions = balance_fee(
+            &budget_ledger::<_, (), _>(&instructions).unwrap(),
+            estimated_fee,
+            instructions,
+        )
+        .unwrap();
         dbg!(balanced_instructions.clone());
         assert_eq!(
             balanced_instructions
@@ -305,11 +477,13 @@ mod tests {
             Either::Left(Trans::new(Bundled(t0_0, ()), Next::Succ(t0_1))),
             Either::Left(Trans::new(Bundled(t1_0, ()), Next::Succ(t1_1))),
         ];
-        let reserved_fee = 300000;
         let estimated_fee = 500000;
-        let rescale_factor = Ratio::new(estimated_fee, reserved_fee);
-        let fee_mismatch = reserved_fee as i64 - estimated_fee as i64;
-        let balanced_instructions = balance_fee::<_, (), _>(fee_mismatch, rescale_factor, instructions);
+        let balanced_instructions = balance_fee(
+            &budget_ledger::<_, (), _>(&instructions).unwrap(),
+            estimated_fee,
+            instructions,
+        )
+        .unwrap();
         assert_eq!(
             balanced_instructions
                 .iter()
@@ -332,11 +506,13 @@ mod tests {
             Either::Left(Trans::new(Bundled(t0_0, ()), Next::Succ(t0_1))),
             Either::Left(Trans::new(Bundled(t1_0, ()), Next::Succ(t1_1))),
         ];
-        let reserved_fee = 350000;
         let estimated_fee = 500000;
-        let rescale_factor = Ratio::new(estimated_fee, reserved_fee);
-        let fee_mismatch = reserved_fee as i64 - estimated_fee as i64;
-        let balanced_instructions = balance_fee::<_, (), _>(fee_mismatch, rescale_factor, instructions);
+        let balanced_instructions = balance_fee(
+            &budget_ledger::<_, (), _>(&instructions).unwrap(),
+            estimated_fee,
+            instructions,
+        )
+        .unwrap();
         assert_eq!(
             balanced_instructions
                 .iter()
@@ -349,6 +525,26 @@ mod tests {
         )
     }
 
+    #[test]
+    fn fee_underuse_beyond_remaining_budget_is_rejected() {
+        let t0_0 = SimpleOrderPF::new(0, 100000);
+        let t0_1 = SimpleOrderPF::new(0, 0);
+        let instructions = vec![Either::Left(Trans::new(Bundled(t0_0, ()), Next::Succ(t0_1)))];
+        let estimated_fee = 300000;
+        assert_eq!(
+            balance_fee(
+                &budget_ledger::<_, (), _>(&instructions).unwrap(),
+                estimated_fee,
+                instructions
+            )
+            .map(|_| ()),
+            Err(BudgetImbalance {
+                required: 200000,
+                allocated: 0
+            })
+        );
+    }
+
     /// Order that supports partial filling.
     #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
     pub struct SimpleOrderPF {
@@ -1,4 +1,12 @@
+pub mod babel_fee;
 pub mod backlog;
+pub mod budget_ledger;
 mod execution_state;
+pub mod exposure;
+#[cfg(test)]
+mod golden;
 pub mod instances;
 pub mod interpreter;
+pub mod pool_datum;
+pub mod ref_inputs;
+pub mod slippage;
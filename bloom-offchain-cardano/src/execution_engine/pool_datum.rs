@@ -0,0 +1,28 @@
+use std::fmt::{Display, Formatter};
+
+use spectrum_cardano_lib::plutus_data::DatumUpdateError;
+use spectrum_cardano_lib::OutputRef;
+
+/// Datum of a pool output can't be brought in line with the new state of the pool,
+/// so the TX would be rejected by the pool's validator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PoolDatumRejected {
+    /// Datum of the pool spent at the given output can't be updated.
+    Update(OutputRef, DatumUpdateError),
+    /// Updated datum of the pool spent at the given output differs from the original one
+    /// in more than just treasury.
+    UnexpectedMutation(OutputRef),
+}
+
+impl Display for PoolDatumRejected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolDatumRejected::Update(pool, err) => {
+                write!(f, "Cannot update datum of pool at {}: {}", pool, err)
+            }
+            PoolDatumRejected::UnexpectedMutation(pool) => {
+                write!(f, "Unexpected mutation of datum of pool at {}", pool)
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, RwLock};
+
+use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+use cml_chain::certs::StakeCredential;
+use cml_chain::transaction::{TransactionInput, TransactionOutput};
+use cml_chain::Value;
+use cml_crypto::{ScriptHash, TransactionHash};
+
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::value::ValueExtension;
+use spectrum_cardano_lib::AssetClass;
+
+/// Resolves UTxOs validators read external state from, e.g. oracle price feeds or global configs.
+/// Each such UTxO is identified by the NFT it holds and is required by a set of validators.
+#[derive(Debug, Clone, Default)]
+pub struct RefInputRegistry {
+    required_by: Arc<HashMap<ScriptHash, Vec<AssetClass>>>,
+    latest: Arc<RwLock<HashMap<AssetClass, TransactionUnspentOutput>>>,
+}
+
+impl RefInputRegistry {
+    pub fn new(required_by: HashMap<ScriptHash, Vec<AssetClass>>) -> Self {
+        Self {
+            required_by: Arc::new(required_by),
+            latest: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    fn tracked_nfts(&self) -> impl Iterator<Item = &AssetClass> {
+        self.required_by.values().flatten()
+    }
+
+    /// Whether the value holds any of tracked NFTs.
+    pub fn is_tracked(&self, value: &Value) -> bool {
+        self.tracked_nfts()
+            .any(|nft| value.amount_of(*nft).unwrap_or(0) > 0)
+    }
+
+    /// Remember the UTxO if it holds any of tracked NFTs.
+    pub fn observe(&self, input: TransactionInput, output: &TransactionOutput) {
+        let value = output.value();
+        let nfts = self
+            .tracked_nfts()
+            .filter(|nft| value.amount_of(**nft).unwrap_or(0) > 0)
+            .copied()
+            .collect::<Vec<_>>();
+        if !nfts.is_empty() {
+            let mut latest = self.latest.write().unwrap();
+            for nft in nfts {
+                latest.insert(nft, TransactionUnspentOutput::new(input.clone(), output.clone()));
+            }
+        }
+    }
+
+    /// Forget UTxOs produced by a rolled back TX.
+    pub fn forget(&self, tx_hash: TransactionHash) {
+        self.latest
+            .write()
+            .unwrap()
+            .retain(|_, utxo| utxo.input.transaction_id != tx_hash);
+    }
+
+    /// Reference inputs required to spend the given UTxO.
+    pub fn resolve_for(
+        &self,
+        bearer: &TransactionOutput,
+    ) -> Result<Vec<TransactionUnspentOutput>, UnresolvedRefInput> {
+        let Some(StakeCredential::Script { hash, .. }) = bearer.address().payment_cred() else {
+            return Ok(vec![]);
+        };
+        let Some(nfts) = self.required_by.get(hash) else {
+            return Ok(vec![]);
+        };
+        let latest = self.latest.read().unwrap();
+        nfts.iter()
+            .map(|nft| latest.get(nft).cloned().ok_or(UnresolvedRefInput(*nft)))
+            .collect()
+    }
+}
+
+/// No UTxO holding the NFT is known.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct UnresolvedRefInput(pub AssetClass);
+
+impl Display for UnresolvedRefInput {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reference input holding {} is not resolved", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::Value;
+    use cml_crypto::{ScriptHash, TransactionHash};
+
+    use spectrum_cardano_lib::value::ValueExtension;
+    use spectrum_cardano_lib::{AssetClass, AssetName};
+
+    use crate::execution_engine::ref_inputs::{RefInputRegistry, UnresolvedRefInput};
+
+    fn script_output(hash: ScriptHash, value: Value) -> TransactionOutput {
+        let addr = EnterpriseAddress::new(0, StakeCredential::new_script(hash)).to_address();
+        TransactionOutput::new(addr, value, None, None)
+    }
+
+    #[test]
+    fn ref_inputs_are_resolved_by_requiring_validator() {
+        let pool_script = ScriptHash::from([1u8; 28]);
+        let oracle_script = ScriptHash::from([2u8; 28]);
+        let oracle_nft = AssetClass::Token((oracle_script, AssetName::from((3, [3u8; 32]))));
+        let registry = RefInputRegistry::new(HashMap::from([(pool_script, vec![oracle_nft])]));
+        let pool_utxo = script_output(pool_script, Value::from(10_000_000));
+        assert_eq!(
+            registry.resolve_for(&pool_utxo).err(),
+            Some(UnresolvedRefInput(oracle_nft))
+        );
+        let mut oracle_value = Value::from(2_000_000);
+        oracle_value.add_unsafe(oracle_nft, 1);
+        let oracle_in = TransactionInput::new(TransactionHash::from([4u8; 32]), 0);
+        registry.observe(oracle_in.clone(), &script_output(oracle_script, oracle_value));
+        let resolved = registry.resolve_for(&pool_utxo).unwrap();
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].input, oracle_in);
+        // Oracle itself does not require anything.
+        assert!(registry
+            .resolve_for(&script_output(oracle_script, Value::from(1)))
+            .unwrap()
+            .is_empty());
+        registry.forget(TransactionHash::from([4u8; 32]));
+        assert!(registry.resolve_for(&pool_utxo).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::fmt::{Display, Formatter};
+
+use bloom_offchain::execution_engine::liquidity_book::types::{InputAsset, OutputAsset, RelativePrice};
+use spectrum_cardano_lib::OutputRef;
+
+/// Fill of an order delivers less than the worst price declared in its datum allows,
+/// so the TX would be rejected by the order's validator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SlippageExceeded {
+    pub order: OutputRef,
+    pub removed_input: InputAsset<u64>,
+    /// Output the order is left with once fees taken in the output asset are deducted.
+    pub net_output: OutputAsset<u64>,
+    pub min_output: OutputAsset<u64>,
+}
+
+impl Display for SlippageExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Fill of {} yields {} for {} of input, at least {} required",
+            self.order, self.net_output, self.removed_input, self.min_output
+        )
+    }
+}
+
+/// Ensure `net_output` paid for `removed_input` is no worse than `worst_price` (Output/Input).
+/// Compared exactly, so that rounding in matchmaking can't sneak past the on-chain check.
+pub fn check_fill(
+    order: OutputRef,
+    removed_input: InputAsset<u64>,
+    net_output: OutputAsset<u64>,
+    worst_price: RelativePrice,
+) -> Result<(), SlippageExceeded> {
+    let required = removed_input as u128 * *worst_price.numer();
+    if net_output as u128 * *worst_price.denom() >= required {
+        Ok(())
+    } else {
+        Err(SlippageExceeded {
+            order,
+            removed_input,
+            net_output,
+            min_output: required.div_ceil(*worst_price.denom()).min(u64::MAX as u128) as u64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_crypto::TransactionHash;
+    use num_rational::Ratio;
+
+    use spectrum_cardano_lib::OutputRef;
+
+    use crate::execution_engine::slippage::{check_fill, SlippageExceeded};
+
+    #[test]
+    fn fill_at_worst_price_passes() {
+        let order = OutputRef::new(TransactionHash::from([0; 32]), 0);
+        assert_eq!(check_fill(order, 300, 100, Ratio::new(1, 3)), Ok(()));
+        assert_eq!(check_fill(order, 300, 101, Ratio::new(1, 3)), Ok(()));
+    }
+
+    #[test]
+    fn rounding_drift_is_caught() {
+        let order = OutputRef::new(TransactionHash::from([0; 32]), 0);
+        // 1000 * 2/3 = 666.(6), so 666 falls short of the declared price.
+        assert_eq!(
+            check_fill(order, 1000, 666, Ratio::new(2, 3)),
+            Err(SlippageExceeded {
+                order,
+                removed_input: 1000,
+                net_output: 666,
+                min_output: 667,
+            })
+        );
+    }
+}
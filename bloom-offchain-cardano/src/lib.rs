@@ -1,6 +1,7 @@
 pub mod bounds;
 pub mod event_sink;
 pub mod execution_engine;
+pub mod market_making;
 pub mod orders;
 pub mod pools;
 mod relative_side;
@@ -0,0 +1,431 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use cml_chain::address::{Address, EnterpriseAddress};
+use cml_chain::builders::input_builder::{InputBuilderError, SingleInputBuilder};
+use cml_chain::builders::output_builder::{OutputBuilderError, TransactionOutputBuilder};
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::certs::Credential;
+use cml_chain::transaction::DatumOption;
+use cml_chain::{PolicyId, Value};
+use cml_crypto::Ed25519KeyHash;
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::{info, trace, warn};
+use num_rational::Ratio;
+
+use bloom_offchain::execution_engine::liquidity_book::side::Side;
+use bloom_offchain::execution_engine::reserve_history::ReserveHistory;
+use bloom_offchain::market_making::{target_quotes, QuotingBook, QuotingConfig, TargetQuote};
+use cardano_explorer::CardanoNetwork;
+use spectrum_cardano_lib::address::{PlutusAddress, PlutusCredential};
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use spectrum_cardano_lib::protocol_params::constant_tx_builder;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::value::ValueExtension;
+use spectrum_cardano_lib::{AssetClass, NetworkId, OutputRef};
+use spectrum_offchain::data::Has;
+use spectrum_offchain::network::Network;
+use spectrum_offchain::tx_prover::TxProver;
+use spectrum_offchain_cardano::creds::{OperatorCredSet, OperatorRewardAddress};
+use spectrum_offchain_cardano::data::pair::PairId;
+use spectrum_offchain_cardano::deployment::DeployedValidator;
+use spectrum_offchain_cardano::deployment::ProtocolValidator::LimitOrderV1;
+
+use crate::orders::limit::{beacon_from_oref, BeaconScheme, Datum, MIN_LOVELACE};
+
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketMakingConfig {
+    pub poll_interval_secs: u64,
+    pub quoting: QuotingConfig,
+    pub order: MakerOrderConfig,
+}
+
+/// Parameters of limit orders our quotes are posted as.
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MakerOrderConfig {
+    /// Cost (in Lovelace) of one execution step offered to executors.
+    pub cost_per_ex_step: u64,
+    /// Number of partial fills each quote can be executed in.
+    pub max_fills: u64,
+    /// Fee (in Lovelace) reserved for the whole swap.
+    pub fee: u64,
+}
+
+/// Assets the quote trades, as `(input, tradable_input, output, total_output)`.
+fn quote_terms(target: TargetQuote, [base, quote]: [AssetClass; 2]) -> (AssetClass, u64, AssetClass, u64) {
+    match target.side {
+        Side::Ask => (base, target.base_amount, quote, target.quote_amount),
+        Side::Bid => (quote, target.quote_amount, base, target.base_amount),
+    }
+}
+
+fn min_marginal_output(total_output: u64, conf: MakerOrderConfig) -> u64 {
+    (total_output / conf.max_fills.max(1)).max(1)
+}
+
+/// Value locked by the limit order resting at the given quote in the pair of `[base, quote]` assets.
+pub fn order_value(target: TargetQuote, assets: [AssetClass; 2], conf: MakerOrderConfig) -> Option<Value> {
+    let (input, tradable_input, _, total_output) = quote_terms(target, assets);
+    let execution_steps = total_output / min_marginal_output(total_output, conf);
+    let execution_budget = execution_steps.checked_mul(conf.cost_per_ex_step)?;
+    // Reserve is always put aside to keep the order above min UTxO,
+    // for orders receiving ADA it is returned to the owner as a part of unused budget.
+    let tradable_lovelace = if input == AssetClass::Native {
+        tradable_input
+    } else {
+        0
+    };
+    let lovelace = MIN_LOVELACE
+        .checked_add(tradable_lovelace)?
+        .checked_add(conf.fee)?
+        .checked_add(execution_budget)?;
+    let mut value = Value::from(lovelace);
+    if input != AssetClass::Native {
+        value.add_unsafe(input, tradable_input);
+    }
+    Some(value)
+}
+
+/// Datum and value of the limit order resting at the given quote in the pair of `[base, quote]` assets.
+/// Proceeds of fills as well as cancellations are paid to `owner`.
+pub fn quote_order(
+    target: TargetQuote,
+    assets: [AssetClass; 2],
+    beacon: PolicyId,
+    owner: Ed25519KeyHash,
+    conf: MakerOrderConfig,
+) -> Option<(Datum, Value)> {
+    let value = order_value(target, assets, conf)?;
+    let (input, tradable_input, output, total_output) = quote_terms(target, assets);
+    let min_marginal_output = min_marginal_output(total_output, conf);
+    let datum = Datum {
+        beacon,
+        input,
+        tradable_input,
+        cost_per_ex_step: conf.cost_per_ex_step,
+        min_marginal_output,
+        output,
+        base_price: Ratio::new(total_output as u128, tradable_input as u128),
+        fee: conf.fee,
+        redeemer_address: PlutusAddress {
+            payment_cred: PlutusCredential::PubKey(owner),
+            stake_cred: None,
+        },
+        cancellation_pkh: owner,
+        permitted_executors: vec![],
+        fee_asset: AssetClass::Native,
+    };
+    Some((datum, value))
+}
+
+/// Lovelace put aside for the TX fee and change on top of what quotes lock.
+const CHANGE_RESERVE_LOVELACE: u64 = 5_000_000;
+
+/// Operator UTxOs covering the `required` assets, at least `min_inputs` of them.
+/// The `excluded` UTxO (e.g. collateral) is never selected.
+pub fn select_funding(
+    utxos: Vec<TransactionUnspentOutput>,
+    excluded: OutputRef,
+    required: &BTreeMap<AssetClass, u64>,
+    min_inputs: usize,
+) -> Option<Vec<TransactionUnspentOutput>> {
+    let mut missing = required.clone();
+    let mut selected = vec![];
+    for utxo in utxos {
+        if missing.is_empty() && selected.len() >= min_inputs {
+            break;
+        }
+        if OutputRef::from(utxo.input.clone()) == excluded {
+            continue;
+        }
+        let value = utxo.output.value();
+        let contributes = missing
+            .keys()
+            .any(|asset| value.amount_of(*asset).unwrap_or(0) > 0);
+        if contributes || selected.len() < min_inputs {
+            missing.retain(|asset, amount| {
+                *amount = amount.saturating_sub(value.amount_of(*asset).unwrap_or(0));
+                *amount > 0
+            });
+            selected.push(utxo);
+        }
+    }
+    (missing.is_empty() && selected.len() >= min_inputs).then_some(selected)
+}
+
+#[derive(Debug, derive_more::From)]
+pub enum QuotePlacementError {
+    /// Quote cannot be posted as a limit order.
+    Unquotable(TargetQuote),
+    /// Operator UTxOs don't cover the quotes.
+    InsufficientFunds,
+    InputBuilder(InputBuilderError),
+    OutputBuilder(OutputBuilderError),
+    TxBuilder(TxBuilderError),
+}
+
+/// Builds a TX posting given quotes in the pair as limit orders funded from `utxos`.
+/// Beacon of each order is derived from a distinct input of the TX, change goes back to the operator.
+/// Returns the TX along with beacons of the orders.
+pub fn build_quotes_tx<Ctx>(
+    pair: PairId,
+    targets: Vec<TargetQuote>,
+    utxos: Vec<TransactionUnspentOutput>,
+    conf: MakerOrderConfig,
+    ctx: &Ctx,
+) -> Result<(SignedTxBuilder, Vec<PolicyId>), QuotePlacementError>
+where
+    Ctx: Has<Collateral>
+        + Has<NetworkId>
+        + Has<OperatorCredSet>
+        + Has<OperatorRewardAddress>
+        + Has<DeployedValidator<{ LimitOrderV1 as u8 }>>,
+{
+    let mut required = BTreeMap::from([(AssetClass::Native, CHANGE_RESERVE_LOVELACE)]);
+    for target in &targets {
+        let value =
+            order_value(*target, pair.assets(), conf).ok_or(QuotePlacementError::Unquotable(*target))?;
+        *required.entry(AssetClass::Native).or_default() += value.coin;
+        for (policy, assets) in value.multiasset.iter() {
+            for (an, amount) in assets.iter() {
+                *required
+                    .entry(AssetClass::Token((*policy, an.clone().into())))
+                    .or_default() += *amount;
+            }
+        }
+    }
+    let funding = select_funding(
+        utxos,
+        ctx.select::<Collateral>().reference(),
+        &required,
+        targets.len(),
+    )
+    .ok_or(QuotePlacementError::InsufficientFunds)?;
+    let owner = ctx.select::<OperatorCredSet>().current.0;
+    let order_address = EnterpriseAddress::new(
+        ctx.select::<NetworkId>().into(),
+        Credential::new_script(ctx.select::<DeployedValidator<{ LimitOrderV1 as u8 }>>().hash),
+    )
+    .to_address();
+    let mut tx_builder = constant_tx_builder();
+    let mut beacons = vec![];
+    for (ix, utxo) in funding.into_iter().enumerate() {
+        if let Some(target) = targets.get(ix) {
+            let beacon = beacon_from_oref(OutputRef::from(utxo.input.clone()), BeaconScheme::V2);
+            let (datum, value) = quote_order(*target, pair.assets(), beacon, owner, conf)
+                .ok_or(QuotePlacementError::Unquotable(*target))?;
+            let order = TransactionOutputBuilder::new()
+                .with_address(order_address.clone())
+                .with_data(DatumOption::new_datum(datum.into_pd()))
+                .next()?
+                .with_value(value)
+                .build()?;
+            tx_builder.add_output(order)?;
+            beacons.push(beacon);
+        }
+        tx_builder.add_input(SingleInputBuilder::new(utxo.input, utxo.output).payment_key()?)?;
+    }
+    let change_address: Address = ctx.select::<OperatorRewardAddress>().address();
+    let tx = tx_builder.build(ChangeSelectionAlgo::Default, &change_address)?;
+    Ok((tx, beacons))
+}
+
+const FUNDING_LOOKUP_LIMIT: u16 = 50;
+
+/// Periodically posts quotes around the index price in pairs where none of our quotes rest.
+/// Index price and volatility of a pair are taken from the reserve history of its pools.
+pub fn market_making_stream<'a, Net, Explorer, Prover, Tx, Err, Ctx>(
+    book: QuotingBook<PairId, PolicyId>,
+    history: ReserveHistory<PairId, PolicyId>,
+    explorer: Explorer,
+    network: Net,
+    prover: Prover,
+    conf: MarketMakingConfig,
+    ctx: Ctx,
+) -> impl Stream<Item = ()> + 'a
+where
+    Explorer: CardanoNetwork + 'a,
+    Net: Network<Tx, Err> + 'a,
+    Prover: TxProver<SignedTxBuilder, Tx> + 'a,
+    Err: std::fmt::Debug,
+    Ctx: Has<Collateral>
+        + Has<NetworkId>
+        + Has<OperatorCredSet>
+        + Has<OperatorRewardAddress>
+        + Has<DeployedValidator<{ LimitOrderV1 as u8 }>>
+        + 'a,
+{
+    let interval = Duration::from_secs(conf.poll_interval_secs);
+    stream::unfold(
+        (book, history, explorer, network, prover, ctx),
+        move |(book, history, explorer, mut network, prover, ctx)| async move {
+            Delay::new(interval).await;
+            for (pair, inventory) in book.idle() {
+                let Some(index_price) = history.spot_price(&pair) else {
+                    trace!("No index price in pair {} yet", pair);
+                    continue;
+                };
+                let quoting = conf.quoting.widened_by(history.volatility(&pair));
+                let targets = target_quotes(index_price, inventory, quoting);
+                if targets.is_empty() {
+                    trace!("Inventory in pair {} backs no quotes", pair);
+                    continue;
+                }
+                let utxos = explorer
+                    .utxos_by_address(
+                        ctx.select::<OperatorRewardAddress>().address(),
+                        0,
+                        FUNDING_LOOKUP_LIMIT,
+                    )
+                    .await;
+                match build_quotes_tx(pair, targets, utxos, conf.order, &ctx) {
+                    Ok((tx_candidate, beacons)) => {
+                        let tx_hash = hash_transaction_canonical(&tx_candidate.body());
+                        let tx = prover.prove(tx_candidate);
+                        match network.submit_tx(tx).await {
+                            Ok(()) => {
+                                info!(
+                                    "Posted {} quotes in pair {} in TX {}",
+                                    beacons.len(),
+                                    pair,
+                                    tx_hash
+                                );
+                                book.placed(pair, beacons);
+                            }
+                            Err(err) => warn!("Failed to submit quotes in pair {}: {:?}", pair, err),
+                        }
+                        // Change of funding UTxOs is not visible until the TX is settled.
+                        break;
+                    }
+                    Err(err) => warn!("Failed to build quotes in pair {}: {:?}", pair, err),
+                }
+            }
+            Some(((), (book, history, explorer, network, prover, ctx)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::{PolicyId, Value};
+    use cml_crypto::{Ed25519KeyHash, TransactionHash};
+    use num_rational::Ratio;
+
+    use bloom_offchain::execution_engine::liquidity_book::side::Side;
+    use bloom_offchain::execution_engine::liquidity_book::types::AbsolutePrice;
+    use bloom_offchain::market_making::TargetQuote;
+    use spectrum_cardano_lib::value::ValueExtension;
+    use spectrum_cardano_lib::{AssetClass, AssetName, OutputRef};
+
+    use crate::market_making::{quote_order, select_funding, MakerOrderConfig};
+    use crate::orders::limit::MIN_LOVELACE;
+
+    const CONF: MakerOrderConfig = MakerOrderConfig {
+        cost_per_ex_step: 300_000,
+        max_fills: 4,
+        fee: 500_000,
+    };
+
+    fn token() -> AssetClass {
+        AssetClass::Token((PolicyId::from([1u8; 28]), AssetName::from((3, [1u8; 32]))))
+    }
+
+    #[test]
+    fn ask_sells_base_for_quote() {
+        let target = TargetQuote {
+            side: Side::Ask,
+            price: AbsolutePrice::new_unsafe(3, 1),
+            base_amount: 1_000,
+            quote_amount: 3_000,
+        };
+        let owner = Ed25519KeyHash::from([2u8; 28]);
+        let (datum, value) = quote_order(
+            target,
+            [token(), AssetClass::Native],
+            PolicyId::from([3u8; 28]),
+            owner,
+            CONF,
+        )
+        .unwrap();
+        assert_eq!(datum.input, token());
+        assert_eq!(datum.tradable_input, 1_000);
+        assert_eq!(datum.base_price, Ratio::new(3, 1));
+        assert_eq!(datum.min_marginal_output, 750);
+        assert_eq!(value.amount_of(token()), Some(1_000));
+        assert_eq!(
+            value.amount_of(AssetClass::Native),
+            Some(MIN_LOVELACE + CONF.fee + 4 * CONF.cost_per_ex_step)
+        );
+    }
+
+    #[test]
+    fn bid_pays_quote_for_base() {
+        let target = TargetQuote {
+            side: Side::Bid,
+            price: AbsolutePrice::new_unsafe(3, 1),
+            base_amount: 1_000,
+            quote_amount: 2_970,
+        };
+        let (datum, value) = quote_order(
+            target,
+            [token(), AssetClass::Native],
+            PolicyId::from([3u8; 28]),
+            Ed25519KeyHash::from([2u8; 28]),
+            CONF,
+        )
+        .unwrap();
+        assert_eq!((datum.input, datum.output), (AssetClass::Native, token()));
+        assert_eq!(datum.base_price, Ratio::new(1_000, 2_970));
+        assert_eq!(
+            value.amount_of(AssetClass::Native),
+            Some(MIN_LOVELACE + 2_970 + CONF.fee + 4 * CONF.cost_per_ex_step)
+        );
+    }
+
+    fn utxo(ix: u64, value: Value) -> TransactionUnspentOutput {
+        let address =
+            EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([2u8; 28])))
+                .to_address();
+        TransactionUnspentOutput::new(
+            TransactionInput::new(TransactionHash::from([0xaa; 32]), ix),
+            TransactionOutput::new(address, value, None, None),
+        )
+    }
+
+    #[test]
+    fn funding_covers_assets_with_an_input_per_quote() {
+        let collateral = utxo(0, Value::from(5_000_000));
+        let excluded = OutputRef::from(collateral.input.clone());
+        let mut with_token = Value::from(2_000_000);
+        with_token.add_unsafe(token(), 1_000);
+        let utxos = vec![
+            collateral,
+            utxo(1, Value::from(10_000_000)),
+            utxo(2, with_token),
+            utxo(3, Value::from(10_000_000)),
+        ];
+        let required = BTreeMap::from([(AssetClass::Native, 8_000_000), (token(), 1_000)]);
+        let selected = |min_inputs| {
+            select_funding(utxos.clone(), excluded, &required, min_inputs)
+                .map(|us| us.into_iter().map(|u| u.input.index).collect::<Vec<_>>())
+        };
+        assert_eq!(selected(2), Some(vec![1, 2]));
+        assert_eq!(selected(3), Some(vec![1, 2, 3]));
+        assert_eq!(selected(4), None);
+        let required = BTreeMap::from([(AssetClass::Native, 30_000_000)]);
+        assert!(select_funding(utxos, excluded, &required, 1).is_none());
+    }
+}
@@ -18,9 +18,7 @@ use bloom_offchain::execution_engine::liquidity_book::types::{
 use bloom_offchain::execution_engine::liquidity_book::weight::Weighted;
 use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::ex_units::ExUnits;
-use spectrum_cardano_lib::plutus_data::{
-    ConstrPlutusDataExtension, DatumExtension, IntoPlutusData, PlutusDataExtension,
-};
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
 use spectrum_cardano_lib::value::ValueExtension;
@@ -34,7 +32,7 @@ use spectrum_offchain_cardano::deployment::{test_address, DeployedScriptInfo};
 use crate::relative_side::RelativeSide;
 
 /// Quote/Base price relative to order.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Into, From)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Into, From, serde::Serialize, serde::Deserialize)]
 pub struct GridPrice(Ratio<u128>);
 impl GridPrice {
     #[inline]
@@ -53,7 +51,7 @@ impl GridPrice {
 }
 
 /// Open Grid Order.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct GridOrder {
     pub beacon: PolicyId,
     pub base_asset: AssetClass,
@@ -339,7 +337,7 @@ impl Tradable for GridOrder {
 }
 
 #[derive(Debug)]
-struct DatumNative {
+pub struct DatumNative {
     beacon: PolicyId,
     token: AssetClass,
     buy_shift_factor: Ratio<u128>,
@@ -452,8 +450,8 @@ where
 {
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &C) -> Option<Self> {
         if test_address(repr.address(), ctx) {
-            let value = repr.value().clone();
-            let conf = DatumNative::try_from_pd(repr.datum()?.into_pd()?)?;
+            let value = repr.value();
+            let conf = DatumNative::try_from_pd(repr.inline_datum()?.clone())?;
             let base = conf.token;
             let total_lovelace = value.amount_of(AssetClass::Native)?;
             let total_base = value.amount_of(base).unwrap_or(0);
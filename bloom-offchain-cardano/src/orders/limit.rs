@@ -14,19 +14,18 @@ use bloom_offchain::execution_engine::liquidity_book::time::TimeBounds;
 use bloom_offchain::execution_engine::liquidity_book::types::{
     AbsolutePrice, FeeAsset, InputAsset, OutputAsset, RelativePrice,
 };
-use bloom_offchain::execution_engine::liquidity_book::weight::Weighted;
+use bloom_offchain::execution_engine::liquidity_book::weight::{Aging, OrderWeight};
+use bloom_offchain::execution_engine::types::Time;
 use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::ex_units::ExUnits;
-use spectrum_cardano_lib::plutus_data::{
-    ConstrPlutusDataExtension, DatumExtension, IntoPlutusData, PlutusDataExtension,
-};
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
 use spectrum_cardano_lib::value::ValueExtension;
 use spectrum_cardano_lib::{AssetClass, OutputRef};
 use spectrum_offchain::data::{Has, Stable, Tradable};
 use spectrum_offchain::ledger::TryFromLedger;
-use spectrum_offchain_cardano::creds::OperatorCred;
+use spectrum_offchain_cardano::creds::OperatorCredSet;
 use spectrum_offchain_cardano::data::pair::{side_of, PairId};
 use spectrum_offchain_cardano::deployment::ProtocolValidator::LimitOrderV1;
 use spectrum_offchain_cardano::deployment::{test_address, DeployedScriptInfo};
@@ -40,7 +39,7 @@ pub const EXEC_REDEEMER: PlutusData = PlutusData::ConstrPlutusData(ConstrPlutusD
 
 /// Composable limit order. Can be executed at a configured
 /// or better price as long as there is enough budget.
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct LimitOrder {
     /// Identifier of the order.
     pub beacon: PolicyId,
@@ -54,7 +53,8 @@ pub struct LimitOrder {
     pub output_amount: OutputAsset<u64>,
     /// Worst acceptable price (Output/Input).
     pub base_price: RelativePrice,
-    /// Currency used to pay for execution.
+    /// Currency used to pay for execution. Budget and fee are pegged to ADA
+    /// even when they are paid in the output asset.
     pub fee_asset: AssetClass,
     /// Remaining ADA to facilitate execution.
     pub execution_budget: FeeAsset<u64>,
@@ -74,6 +74,18 @@ pub struct LimitOrder {
     pub virgin: bool,
     /// How many execution units each order consumes.
     pub marginal_cost: ExUnits,
+    /// When the order was first observed (unix time in seconds).
+    pub arrived_at: u64,
+}
+
+/// Every minute of resting in the book weighs as much as 0.01 ADA of fee.
+pub type LimitOrderWeightFn = Aging<10_000, 60>;
+
+impl LimitOrder {
+    /// Weight of the order accounting for both fee and age.
+    pub fn prioritized_weight(&self) -> OrderWeight<ExUnits> {
+        OrderWeight::prioritized::<LimitOrderWeightFn>(self.fee, self.arrived_at, self.marginal_cost)
+    }
 }
 
 impl Display for LimitOrder {
@@ -112,7 +124,8 @@ impl Ord for LimitOrder {
             cmp_by_price
         };
         cmp_by_price
-            .then(self.weight().cmp(&other.weight()))
+            // Heavier orders go first.
+            .then(other.prioritized_weight().cmp(&self.prioritized_weight()))
             .then(self.stable_id().cmp(&other.stable_id()))
     }
 }
@@ -243,8 +256,8 @@ impl Tradable for LimitOrder {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct Datum {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Datum {
     pub beacon: PolicyId,
     pub input: AssetClass,
     pub tradable_input: InputAsset<u64>,
@@ -256,6 +269,8 @@ struct Datum {
     pub redeemer_address: PlutusAddress,
     pub cancellation_pkh: Ed25519KeyHash,
     pub permitted_executors: Vec<Ed25519KeyHash>,
+    /// Asset budget and fee are paid in, absent in datums of orders paying in ADA.
+    pub fee_asset: AssetClass,
 }
 
 struct DatumMapping {
@@ -270,6 +285,7 @@ struct DatumMapping {
     pub redeemer_address: usize,
     pub cancellation_pkh: usize,
     pub permitted_executors: usize,
+    pub fee_asset: usize,
 }
 
 const DATUM_MAPPING: DatumMapping = DatumMapping {
@@ -284,8 +300,37 @@ const DATUM_MAPPING: DatumMapping = DatumMapping {
     redeemer_address: 9,
     cancellation_pkh: 10,
     permitted_executors: 11,
+    fee_asset: 12,
 };
 
+impl IntoPlutusData for Datum {
+    fn into_pd(self) -> PlutusData {
+        let permitted_executors = self
+            .permitted_executors
+            .into_iter()
+            .map(|pkh| PlutusData::new_bytes(pkh.to_raw_bytes().to_vec()))
+            .collect();
+        let mut fields = vec![
+            PlutusData::new_bytes(vec![0]),
+            PlutusData::new_bytes(self.beacon.to_raw_bytes().to_vec()),
+            self.input.into_pd(),
+            self.tradable_input.into_pd(),
+            self.cost_per_ex_step.into_pd(),
+            self.min_marginal_output.into_pd(),
+            self.output.into_pd(),
+            self.base_price.into_pd(),
+            self.fee.into_pd(),
+            self.redeemer_address.into_pd(),
+            PlutusData::new_bytes(self.cancellation_pkh.to_raw_bytes().to_vec()),
+            PlutusData::new_list(permitted_executors),
+        ];
+        if self.fee_asset != AssetClass::Native {
+            fields.push(self.fee_asset.into_pd());
+        }
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, fields))
+    }
+}
+
 pub fn unsafe_update_datum(data: &mut PlutusData, tradable_input: InputAsset<u64>, fee: FeeAsset<u64>) {
     let cpd = data.get_constr_pd_mut().unwrap();
     cpd.set_field(DATUM_MAPPING.tradable_input, tradable_input.into_pd());
@@ -313,6 +358,10 @@ impl TryFromPData for Datum {
             .into_iter()
             .filter_map(|pd| Some(Ed25519KeyHash::from_raw_bytes(&*pd.into_bytes()?).ok()?))
             .collect();
+        let fee_asset = match cpd.take_field(DATUM_MAPPING.fee_asset) {
+            Some(fee_asset) => AssetClass::try_from_pd(fee_asset)?,
+            None => AssetClass::Native,
+        };
         Some(Datum {
             beacon,
             input,
@@ -325,30 +374,49 @@ impl TryFromPData for Datum {
             redeemer_address,
             cancellation_pkh,
             permitted_executors,
+            fee_asset,
         })
     }
 }
 
-fn beacon_from_oref(oref: OutputRef) -> PolicyId {
+/// Scheme used to derive order beacon from the consumed UTxO.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub enum BeaconScheme {
+    /// blake2b224(tx_hash ++ utf8(index))
+    V1,
+    /// blake2b224(tx_hash ++ be_bytes_u64(index))
+    V2,
+}
+
+impl BeaconScheme {
+    /// All schemes orders in the wild can be created with.
+    pub const SUPPORTED: [BeaconScheme; 2] = [BeaconScheme::V1, BeaconScheme::V2];
+}
+
+pub fn beacon_from_oref(oref: OutputRef, scheme: BeaconScheme) -> PolicyId {
     let mut bf = vec![];
     bf.append(&mut oref.tx_hash().to_raw_bytes().to_vec());
-    bf.append(&mut oref.index().to_string().as_bytes().to_vec());
+    match scheme {
+        BeaconScheme::V1 => bf.append(&mut oref.index().to_string().as_bytes().to_vec()),
+        BeaconScheme::V2 => bf.append(&mut oref.index().to_be_bytes().to_vec()),
+    }
     blake2b224(&*bf).into()
 }
 
-const MIN_LOVELACE: u64 = 1_500_000;
+pub(crate) const MIN_LOVELACE: u64 = 1_500_000;
 
 impl<C> TryFromLedger<BabbageTransactionOutput, C> for LimitOrder
 where
-    C: Has<OperatorCred>
+    C: Has<OperatorCredSet>
         + Has<ConsumedInputs>
         + Has<DeployedScriptInfo<{ LimitOrderV1 as u8 }>>
-        + Has<LimitOrderBounds>,
+        + Has<LimitOrderBounds>
+        + Has<Time>,
 {
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &C) -> Option<Self> {
         if test_address(repr.address(), ctx) {
-            let value = repr.value().clone();
-            let conf = Datum::try_from_pd(repr.datum()?.into_pd()?)?;
+            let value = repr.value();
+            let conf = Datum::try_from_pd(repr.inline_datum()?.clone())?;
             let total_input_asset_amount = value.amount_of(conf.input)?;
             let total_ada_input = value.amount_of(AssetClass::Native)?;
             let (reserved_lovelace, tradable_lovelace) = match (conf.input, conf.output) {
@@ -356,13 +424,24 @@ where
                 (_, AssetClass::Native) => (0, 0),
                 _ => (MIN_LOVELACE, 0),
             };
-            let execution_budget = total_ada_input
-                .checked_sub(reserved_lovelace)
-                .and_then(|lov| lov.checked_sub(conf.fee))
-                .and_then(|lov| lov.checked_sub(tradable_lovelace))?;
             if let Some(base_output) = linear_output_relative(conf.tradable_input, conf.base_price) {
                 let min_marginal_output = min(conf.min_marginal_output, base_output);
                 let max_execution_steps_possible = base_output.checked_div(min_marginal_output);
+                let execution_budget = match conf.fee_asset {
+                    AssetClass::Native => total_ada_input
+                        .checked_sub(reserved_lovelace)
+                        .and_then(|lov| lov.checked_sub(conf.fee))
+                        .and_then(|lov| lov.checked_sub(tradable_lovelace))?,
+                    // Budget paid out of the output is only bounded by the number of steps.
+                    fee_asset
+                        if pegged_to_ada(conf.input, conf.output, fee_asset)
+                            || babel_fee(conf.input, conf.output, fee_asset) =>
+                    {
+                        total_ada_input.checked_sub(reserved_lovelace + tradable_lovelace)?;
+                        max_execution_steps_possible?.checked_mul(conf.cost_per_ex_step)?
+                    }
+                    _ => return None,
+                };
                 let max_execution_steps_available = execution_budget.checked_div(conf.cost_per_ex_step);
                 if let (Some(max_execution_steps_possible), Some(max_execution_steps_available)) =
                     (max_execution_steps_possible, max_execution_steps_available)
@@ -371,19 +450,24 @@ where
                     let sufficient_execution_budget =
                         max_execution_steps_available >= max_execution_steps_possible;
                     let is_permissionless = conf.permitted_executors.is_empty();
+                    let operator_creds = ctx.select::<OperatorCredSet>();
                     let executable = is_permissionless
                         || conf
                             .permitted_executors
-                            .contains(&ctx.select::<OperatorCred>().into());
+                            .iter()
+                            .any(|pkh| operator_creds.contains(pkh));
                     if sufficient_input && sufficient_execution_budget && executable {
                         let bounds = ctx.select::<LimitOrderBounds>();
                         let valid_configuration = conf.cost_per_ex_step >= bounds.min_cost_per_ex_step
                             && execution_budget >= conf.cost_per_ex_step;
                         if valid_configuration {
-                            // Fresh beacon must be derived from one of consumed utxos.
-                            let valid_fresh_beacon = ctx
-                                .select::<ConsumedInputs>()
-                                .find(|o| beacon_from_oref(*o) == conf.beacon);
+                            // Fresh beacon must be derived from one of consumed utxos
+                            // under any of supported schemes.
+                            let valid_fresh_beacon = ctx.select::<ConsumedInputs>().find(|o| {
+                                BeaconScheme::SUPPORTED
+                                    .iter()
+                                    .any(|scheme| beacon_from_oref(*o, *scheme) == conf.beacon)
+                            });
                             let script_info = ctx.select::<DeployedScriptInfo<{ LimitOrderV1 as u8 }>>();
                             return Some(LimitOrder {
                                 beacon: conf.beacon,
@@ -393,7 +477,7 @@ where
                                 output_amount: value.amount_of(conf.output).unwrap_or(0),
                                 base_price: harden_price(conf.base_price, conf.tradable_input),
                                 execution_budget,
-                                fee_asset: AssetClass::Native,
+                                fee_asset: conf.fee_asset,
                                 fee: conf.fee,
                                 min_marginal_output,
                                 max_cost_per_ex_step: conf.cost_per_ex_step,
@@ -402,6 +486,7 @@ where
                                 requires_executor_sig: !is_permissionless,
                                 virgin: valid_fresh_beacon,
                                 marginal_cost: script_info.marginal_cost,
+                                arrived_at: ctx.select::<Time>().into(),
                             });
                         }
                     }
@@ -412,6 +497,18 @@ where
     }
 }
 
+/// Fees paid in a token are pegged to ADA through the price of the order,
+/// thus allowed only in the output of orders selling ADA.
+fn pegged_to_ada(input: AssetClass, output: AssetClass, fee_asset: AssetClass) -> bool {
+    input == AssetClass::Native && fee_asset == output && output != AssetClass::Native
+}
+
+/// Orders trading tokens for tokens may pay fees in the output,
+/// which is then converted at the rate of its ADA pool, see [crate::execution_engine::babel_fee::BabelFees].
+fn babel_fee(input: AssetClass, output: AssetClass, fee_asset: AssetClass) -> bool {
+    input != AssetClass::Native && output != AssetClass::Native && fee_asset == output
+}
+
 fn harden_price(p: RelativePrice, input: u64) -> RelativePrice {
     let min_output = (input as u128 * *p.numer()).div_ceil(*p.denom());
     RelativePrice::new(min_output, input as u128)
@@ -425,6 +522,8 @@ pub struct LimitOrderBounds {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeSet;
+
     use cml_chain::address::Address;
     use cml_chain::assets::AssetBundle;
     use cml_chain::plutus::PlutusData;
@@ -436,15 +535,21 @@ mod tests {
     use num_rational::Ratio;
     use type_equalities::IsEqual;
 
-    use bloom_offchain::execution_engine::liquidity_book::config::{ExecutionCap, ExecutionConfig};
+    use bloom_offchain::execution_engine::liquidity_book::config::{
+        ExecutionCap, ExecutionConfig, MakerSelection, MatchingMode, RecipeLimits, SettlementPolicy,
+        StashPolicy,
+    };
     use bloom_offchain::execution_engine::liquidity_book::market_taker::MarketTaker;
     use bloom_offchain::execution_engine::liquidity_book::{ExternalTLBEvents, TemporalLiquidityBook, TLB};
+    use bloom_offchain::execution_engine::types::Time;
+    use spectrum_cardano_lib::address::{PlutusAddress, PlutusCredential};
     use spectrum_cardano_lib::ex_units::ExUnits;
+    use spectrum_cardano_lib::plutus_data::{IntoPlutusData, PlutusDataExtension};
     use spectrum_cardano_lib::types::TryFromPData;
-    use spectrum_cardano_lib::{AssetName, OutputRef};
+    use spectrum_cardano_lib::{AssetClass, AssetName, OutputRef};
     use spectrum_offchain::data::Has;
     use spectrum_offchain::ledger::TryFromLedger;
-    use spectrum_offchain_cardano::creds::OperatorCred;
+    use spectrum_offchain_cardano::creds::{OperatorCred, OperatorCredSet};
     use spectrum_offchain_cardano::data::pool::AnyPool;
     use spectrum_offchain_cardano::deployment::ProtocolValidator::LimitOrderV1;
     use spectrum_offchain_cardano::deployment::{
@@ -452,7 +557,9 @@ mod tests {
     };
     use spectrum_offchain_cardano::utxo::ConsumedInputs;
 
-    use crate::orders::limit::{beacon_from_oref, unsafe_update_datum, Datum, LimitOrder, LimitOrderBounds};
+    use crate::orders::limit::{
+        beacon_from_oref, unsafe_update_datum, BeaconScheme, Datum, LimitOrder, LimitOrderBounds,
+    };
 
     struct Context {
         limit_order: DeployedScriptInfo<{ LimitOrderV1 as u8 }>,
@@ -460,6 +567,12 @@ mod tests {
         consumed_inputs: ConsumedInputs,
     }
 
+    impl Has<Time> for Context {
+        fn select<U: IsEqual<Time>>(&self) -> Time {
+            Time::from(0)
+        }
+    }
+
     impl Has<LimitOrderBounds> for Context {
         fn select<U: IsEqual<LimitOrderBounds>>(&self) -> LimitOrderBounds {
             LimitOrderBounds {
@@ -474,9 +587,9 @@ mod tests {
         }
     }
 
-    impl Has<OperatorCred> for Context {
-        fn select<U: IsEqual<OperatorCred>>(&self) -> OperatorCred {
-            self.cred
+    impl Has<OperatorCredSet> for Context {
+        fn select<U: IsEqual<OperatorCredSet>>(&self) -> OperatorCredSet {
+            OperatorCredSet::new(self.cred)
         }
     }
 
@@ -492,8 +605,12 @@ mod tests {
     fn beacon_derivation_eqv() {
         let oref = OutputRef::new(TransactionHash::from_hex(TX).unwrap(), IX);
         assert_eq!(
-            beacon_from_oref(oref).to_hex(),
+            beacon_from_oref(oref, BeaconScheme::V1).to_hex(),
             "eb9575d907ac66f8f0c75c44ad51189a4b41756e8543cd59e331bc02"
+        );
+        assert_eq!(
+            beacon_from_oref(oref, BeaconScheme::V2).to_hex(),
+            "c443bc376779dab21e4d98919c4ed99c3170fc031fba9aec16aed39a"
         )
     }
 
@@ -505,6 +622,50 @@ mod tests {
         dbg!(Ratio::new(3, 5).cmp(&Ratio::new(1, 6)));
     }
 
+    fn resting_order(arrived_at: u64, fee: u64) -> LimitOrder {
+        LimitOrder {
+            beacon: PolicyId::from([arrived_at as u8; 28]),
+            input_asset: AssetClass::Native,
+            input_amount: 1_000_000,
+            output_asset: AssetClass::Token((
+                PolicyId::from([1u8; 28]),
+                AssetName::try_from(b"T".to_vec()).unwrap(),
+            )),
+            output_amount: 0,
+            base_price: Ratio::new(1, 2),
+            fee_asset: AssetClass::Native,
+            execution_budget: 1_000_000,
+            fee,
+            max_cost_per_ex_step: 500_000,
+            min_marginal_output: 1,
+            redeemer_address: PlutusAddress {
+                payment_cred: PlutusCredential::PubKey(Ed25519KeyHash::from([0u8; 28])),
+                stake_cred: None,
+            },
+            cancellation_pkh: Ed25519KeyHash::from([0u8; 28]),
+            requires_executor_sig: false,
+            virgin: false,
+            marginal_cost: ExUnits { mem: 100, steps: 100 },
+            arrived_at,
+        }
+    }
+
+    #[test]
+    fn older_order_goes_first_at_same_price() {
+        let older = resting_order(0, 100_000);
+        let newer = resting_order(600, 100_000);
+        let book = BTreeSet::from([newer, older]);
+        assert_eq!(book.first(), Some(&older));
+    }
+
+    #[test]
+    fn substantially_higher_fee_outweighs_age() {
+        let older = resting_order(0, 100_000);
+        let newer = resting_order(60, 200_000);
+        let book = BTreeSet::from([older, newer]);
+        assert_eq!(book.first(), Some(&newer));
+    }
+
     #[test]
     fn update_order_datum() {
         let mut datum = PlutusData::from_cbor_bytes(&*hex::decode(DATA).unwrap()).unwrap();
@@ -523,6 +684,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn datum_roundtrip() {
+        let datum = PlutusData::from_cbor_bytes(&*hex::decode(DATUM).unwrap()).unwrap();
+        let conf = Datum::try_from_pd(datum).unwrap();
+        let encoded = conf.clone().into_pd();
+        assert_eq!(Datum::try_from_pd(encoded), Some(conf));
+    }
+
+    #[test]
+    fn fee_asset_is_encoded_only_when_not_ada() {
+        let datum = PlutusData::from_cbor_bytes(&*hex::decode(DATUM).unwrap()).unwrap();
+        let conf = Datum::try_from_pd(datum).unwrap();
+        assert_eq!(conf.fee_asset, AssetClass::Native);
+        assert_eq!(conf.clone().into_pd().into_constr_pd().unwrap().fields.len(), 12);
+        let paid_in_output = Datum {
+            fee_asset: conf.output,
+            ..conf
+        };
+        let encoded = paid_in_output.clone().into_pd();
+        assert_eq!(Datum::try_from_pd(encoded), Some(paid_in_output));
+    }
+
     const DATA: &str = "d8799f4100581c0896cb319806556fe598d40dcc625c74fa27d29e19a00188c8f830bdd8799f4040ff1a05f5e1001a0007a1201903e8d8799f581c40079b8ba147fb87a00da10deff7ddd13d64daf48802bb3f82530c3e4a53504c41534854657374ffd8799f011903e8ff1a0007a120d8799fd8799f581cab450d88aab97ff92b1614217e5e34b5710e201da0057d3aab684390ffd8799fd8799fd8799f581c1bc47eaccd81a6a13070fdf67304fc5dc9723d85cff31f0421c53101ffffffff581cab450d88aab97ff92b1614217e5e34b5710e201da0057d3aab68439080ff";
 
     #[test]
@@ -617,8 +800,16 @@ mod tests {
                         mem: 14000000,
                         steps: 10000000000,
                     },
+                    tx_size: None,
                 },
                 o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::default(),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::Continuous,
+                settlement_policy: SettlementPolicy::default(),
             },
         );
         vec![o0, o1]
@@ -4,24 +4,42 @@ use cml_multi_era::babbage::BabbageTransactionOutput;
 
 use crate::orders::grid::GridOrder;
 use crate::orders::limit::{LimitOrder, LimitOrderBounds};
+use crate::orders::rfq::RfqOrder;
 use bloom_derivation::{MarketTaker, Stable, Tradable};
 use bloom_offchain::execution_engine::liquidity_book::core::{Next, TerminalTake, Unit};
 use bloom_offchain::execution_engine::liquidity_book::market_taker::TakerBehaviour;
 use bloom_offchain::execution_engine::liquidity_book::types::{InputAsset, OutputAsset};
+use bloom_offchain::execution_engine::types::Time;
 use spectrum_offchain::data::Has;
 use spectrum_offchain::ledger::TryFromLedger;
-use spectrum_offchain_cardano::creds::OperatorCred;
+use spectrum_offchain_cardano::creds::OperatorCredSet;
 use spectrum_offchain_cardano::deployment::DeployedScriptInfo;
 use spectrum_offchain_cardano::deployment::ProtocolValidator::LimitOrderV1;
 use spectrum_offchain_cardano::utxo::ConsumedInputs;
 
 pub mod grid;
 pub mod limit;
+pub mod rfq;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, MarketTaker, Stable, Tradable)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    MarketTaker,
+    Stable,
+    Tradable,
+    serde::Serialize,
+    serde::Deserialize,
+)]
 pub enum AnyOrder {
     Limit(LimitOrder),
     Grid(GridOrder),
+    /// Off-chain quote of a market maker.
+    Rfq(RfqOrder),
 }
 
 impl Display for AnyOrder {
@@ -29,6 +47,7 @@ impl Display for AnyOrder {
         match self {
             AnyOrder::Limit(lo) => std::fmt::Display::fmt(&lo, f),
             AnyOrder::Grid(go) => std::fmt::Display::fmt(&go, f),
+            AnyOrder::Rfq(ro) => std::fmt::Display::fmt(&ro, f),
         }
     }
 }
@@ -38,6 +57,7 @@ impl TakerBehaviour for AnyOrder {
         match self {
             AnyOrder::Limit(o) => o.with_updated_time(time).map_succ(AnyOrder::Limit),
             AnyOrder::Grid(o) => o.with_updated_time(time).map_succ(AnyOrder::Grid),
+            AnyOrder::Rfq(o) => o.with_updated_time(time).map_succ(AnyOrder::Rfq),
         }
     }
 
@@ -53,6 +73,9 @@ impl TakerBehaviour for AnyOrder {
             AnyOrder::Grid(o) => o
                 .with_applied_trade(removed_input, added_output)
                 .map_succ(AnyOrder::Grid),
+            AnyOrder::Rfq(o) => o
+                .with_applied_trade(removed_input, added_output)
+                .map_succ(AnyOrder::Rfq),
         }
     }
 
@@ -66,6 +89,10 @@ impl TakerBehaviour for AnyOrder {
                 let (d, s) = o.with_budget_corrected(delta);
                 (d, AnyOrder::Grid(s))
             }
+            AnyOrder::Rfq(o) => {
+                let (d, s) = o.with_budget_corrected(delta);
+                (d, AnyOrder::Rfq(s))
+            }
         }
     }
 
@@ -73,6 +100,7 @@ impl TakerBehaviour for AnyOrder {
         match self {
             AnyOrder::Limit(o) => AnyOrder::Limit(o.with_fee_charged(fee)),
             AnyOrder::Grid(o) => AnyOrder::Grid(o.with_fee_charged(fee)),
+            AnyOrder::Rfq(o) => AnyOrder::Rfq(o.with_fee_charged(fee)),
         }
     }
 
@@ -80,6 +108,7 @@ impl TakerBehaviour for AnyOrder {
         match self {
             AnyOrder::Limit(o) => AnyOrder::Limit(o.with_output_added(added_output)),
             AnyOrder::Grid(o) => AnyOrder::Grid(o.with_output_added(added_output)),
+            AnyOrder::Rfq(o) => AnyOrder::Rfq(o.with_output_added(added_output)),
         }
     }
 
@@ -87,16 +116,18 @@ impl TakerBehaviour for AnyOrder {
         match self {
             AnyOrder::Limit(o) => o.try_terminate().map_succ(AnyOrder::Limit),
             AnyOrder::Grid(o) => o.try_terminate().map_succ(AnyOrder::Grid),
+            AnyOrder::Rfq(o) => o.try_terminate().map_succ(AnyOrder::Rfq),
         }
     }
 }
 
 impl<C> TryFromLedger<BabbageTransactionOutput, C> for AnyOrder
 where
-    C: Has<OperatorCred>
+    C: Has<OperatorCredSet>
         + Has<ConsumedInputs>
         + Has<DeployedScriptInfo<{ LimitOrderV1 as u8 }>>
-        + Has<LimitOrderBounds>,
+        + Has<LimitOrderBounds>
+        + Has<Time>,
 {
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &C) -> Option<Self> {
         LimitOrder::try_from_ledger(repr, ctx).map(AnyOrder::Limit)
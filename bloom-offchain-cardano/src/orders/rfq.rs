@@ -0,0 +1,399 @@
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use cml_chain::certs::StakeCredential;
+use cml_chain::transaction::TransactionOutput;
+use cml_chain::PolicyId;
+use cml_core::serialization::Deserialize;
+use cml_crypto::{
+    blake2b224, blake2b256, Ed25519KeyHash, Ed25519Signature, PublicKey, RawBytesEncoding, TransactionHash,
+};
+use derive_more::Display;
+use num_rational::Ratio;
+
+use bloom_offchain::execution_engine::liquidity_book::core::{Next, TerminalTake, Unit};
+use bloom_offchain::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use bloom_offchain::execution_engine::liquidity_book::side::Side;
+use bloom_offchain::execution_engine::liquidity_book::time::TimeBounds;
+use bloom_offchain::execution_engine::liquidity_book::types::{
+    AbsolutePrice, FeeAsset, InputAsset, OutputAsset, RelativePrice,
+};
+use spectrum_cardano_lib::ex_units::ExUnits;
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::value::ValueExtension;
+use spectrum_cardano_lib::{AssetClass, OutputRef};
+use spectrum_offchain::data::{Stable, Tradable};
+use spectrum_offchain_cardano::constants::MIN_SAFE_LOVELACE_VALUE;
+use spectrum_offchain_cardano::data::pair::{side_of, PairId};
+
+/// Firm quote streamed by a market maker.
+/// The maker commits to give up to `input_amount` of `input_asset` from the `funds` UTxO
+/// at `min_output / input_amount` or better until `expires_at` (unix time in seconds).
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RfqQuote {
+    /// Reference of the maker's UTxO backing the quote, e.g. `<tx_hash>#<index>`.
+    pub funds_ref: String,
+    /// Hex-encoded CBOR of the maker's UTxO backing the quote.
+    pub funds_output: String,
+    pub input_asset: AssetClass,
+    pub input_amount: u64,
+    pub output_asset: AssetClass,
+    pub min_output: u64,
+    pub expires_at: u64,
+    /// Hex-encoded Ed25519 public key of the maker.
+    pub maker_vkey: String,
+    /// Hex-encoded signature of [RfqQuote::digest].
+    pub signature: String,
+}
+
+/// Reasons to reject a quote.
+#[derive(Debug, Display, Clone, Eq, PartialEq)]
+pub enum RfqRejected {
+    #[display(fmt = "Malformed {}", _0)]
+    Malformed(&'static str),
+    #[display(fmt = "Bad signature")]
+    BadSignature,
+    #[display(fmt = "Quote expired")]
+    Expired,
+    #[display(fmt = "Quote TTL exceeds {}s", _0)]
+    TtlTooLong(u64),
+    #[display(fmt = "Funds are not owned by the maker")]
+    ForeignFunds,
+    #[display(fmt = "Funds do not cover the quote")]
+    InsufficientFunds,
+}
+
+impl RfqQuote {
+    /// Message the maker signs, `blake2b256` of
+    /// `<funds_ref>|<input_asset>|<input_amount>|<output_asset>|<min_output>|<expires_at>`
+    /// where assets are either `Native` or `<policy_id_hex>.<asset_name_hex>`.
+    pub fn digest(&self, funds_ref: OutputRef) -> [u8; 32] {
+        let msg = format!(
+            "{}|{}|{}|{}|{}|{}",
+            funds_ref,
+            asset_repr(self.input_asset),
+            self.input_amount,
+            asset_repr(self.output_asset),
+            self.min_output,
+            self.expires_at
+        );
+        blake2b256(msg.as_bytes())
+    }
+
+    /// Check the quote is signed by the owner of the funds and still valid at `now`.
+    pub fn verify(&self, now: u64, max_ttl_secs: u64) -> Result<(RfqOrder, FinalizedTxOut), RfqRejected> {
+        let funds_ref = parse_output_ref(&self.funds_ref).ok_or(RfqRejected::Malformed("funds reference"))?;
+        let funds_output = hex::decode(&self.funds_output)
+            .ok()
+            .and_then(|raw| TransactionOutput::from_cbor_bytes(&raw).ok())
+            .ok_or(RfqRejected::Malformed("funds output"))?;
+        let vkey = hex::decode(&self.maker_vkey)
+            .ok()
+            .and_then(|raw| PublicKey::from_raw_bytes(&raw).ok())
+            .ok_or(RfqRejected::Malformed("maker key"))?;
+        let signature = hex::decode(&self.signature)
+            .ok()
+            .and_then(|raw| Ed25519Signature::from_raw_bytes(&raw).ok())
+            .ok_or(RfqRejected::Malformed("signature"))?;
+        if self.input_amount == 0 || self.min_output == 0 || self.input_asset == self.output_asset {
+            return Err(RfqRejected::Malformed("terms"));
+        }
+        let digest = self.digest(funds_ref);
+        if !vkey.verify(&digest, &signature) {
+            return Err(RfqRejected::BadSignature);
+        }
+        if self.expires_at <= now {
+            return Err(RfqRejected::Expired);
+        }
+        if self.expires_at - now > max_ttl_secs {
+            return Err(RfqRejected::TtlTooLong(max_ttl_secs));
+        }
+        let maker = vkey.hash();
+        let owned_by_maker = matches!(
+            funds_output.address().payment_cred(),
+            Some(StakeCredential::PubKey { hash, .. }) if *hash == maker
+        );
+        if !owned_by_maker {
+            return Err(RfqRejected::ForeignFunds);
+        }
+        // Change must stay above min ADA after the quote is fully taken.
+        let reserved_lovelace = match self.input_asset {
+            AssetClass::Native => MIN_SAFE_LOVELACE_VALUE,
+            _ => 0,
+        };
+        let available = funds_output.value().amount_of(self.input_asset).unwrap_or(0);
+        if available < self.input_amount + reserved_lovelace {
+            return Err(RfqRejected::InsufficientFunds);
+        }
+        let order = RfqOrder {
+            id: blake2b224(&digest).into(),
+            maker,
+            input_asset: self.input_asset,
+            input_amount: self.input_amount,
+            output_asset: self.output_asset,
+            output_amount: 0,
+            base_price: Ratio::new(self.min_output as u128, self.input_amount as u128),
+            expires_at: self.expires_at,
+        };
+        Ok((order, FinalizedTxOut(funds_output, funds_ref)))
+    }
+}
+
+fn asset_repr(asset: AssetClass) -> String {
+    serde_json::to_value(asset)
+        .ok()
+        .and_then(|v| v.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+fn parse_output_ref(repr: &str) -> Option<OutputRef> {
+    let (tx_hash, index) = repr.split_once('#')?;
+    Some(OutputRef::new(
+        TransactionHash::from_hex(tx_hash).ok()?,
+        index.parse().ok()?,
+    ))
+}
+
+/// Off-chain quote of a market maker turned into a fragment.
+/// Backed by a UTxO of the maker, so spending it requires the maker's co-signature.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RfqOrder {
+    /// Identifier of the quote.
+    pub id: PolicyId,
+    /// Key the funds are locked with.
+    pub maker: Ed25519KeyHash,
+    /// What maker pays.
+    pub input_asset: AssetClass,
+    /// Remaining input maker is committed to give.
+    pub input_amount: InputAsset<u64>,
+    /// What maker receives.
+    pub output_asset: AssetClass,
+    /// Accumulated output.
+    pub output_amount: OutputAsset<u64>,
+    /// Worst acceptable price (Output/Input).
+    pub base_price: RelativePrice,
+    /// Quote is not firm after this time (unix time in seconds).
+    pub expires_at: u64,
+}
+
+impl Display for RfqOrder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(
+            format!(
+                "RfqOrder({}, {}, {}, p={}, in={} {}, out={} {}, expires_at={})",
+                self.id,
+                self.side(),
+                self.pair_id(),
+                self.price(),
+                self.input_amount,
+                self.input_asset,
+                self.output_amount,
+                self.output_asset,
+                self.expires_at
+            )
+            .as_str(),
+        )
+    }
+}
+
+impl PartialOrd for RfqOrder {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RfqOrder {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let cmp_by_price = self.price().cmp(&other.price());
+        let cmp_by_price = if matches!(self.side(), Side::Bid) {
+            cmp_by_price.reverse()
+        } else {
+            cmp_by_price
+        };
+        cmp_by_price.then(self.stable_id().cmp(&other.stable_id()))
+    }
+}
+
+impl TakerBehaviour for RfqOrder {
+    fn with_updated_time(self, time: u64) -> Next<Self, Unit> {
+        if time < self.expires_at {
+            Next::Succ(self)
+        } else {
+            Next::Term(Unit)
+        }
+    }
+
+    fn with_applied_trade(
+        mut self,
+        removed_input: InputAsset<u64>,
+        added_output: OutputAsset<u64>,
+    ) -> Next<Self, TerminalTake> {
+        self.input_amount -= removed_input;
+        self.output_amount += added_output;
+        if self.input_amount == 0 {
+            Next::Term(TerminalTake {
+                remaining_input: self.input_amount,
+                accumulated_output: self.output_amount,
+                remaining_fee: 0,
+                remaining_budget: 0,
+            })
+        } else {
+            Next::Succ(self)
+        }
+    }
+
+    /// Makers do not fund execution, TX fee is covered by the other side.
+    fn with_budget_corrected(self, _: i64) -> (i64, Self) {
+        (0, self)
+    }
+
+    fn with_fee_charged(self, _: u64) -> Self {
+        self
+    }
+
+    fn with_output_added(mut self, added_output: u64) -> Self {
+        self.output_amount += added_output;
+        self
+    }
+
+    fn try_terminate(self) -> Next<Self, TerminalTake> {
+        Next::Succ(self)
+    }
+}
+
+impl MarketTaker for RfqOrder {
+    type U = ExUnits;
+
+    fn side(&self) -> Side {
+        side_of(self.input_asset, self.output_asset)
+    }
+
+    fn input(&self) -> u64 {
+        self.input_amount
+    }
+
+    fn output(&self) -> OutputAsset<u64> {
+        self.output_amount
+    }
+
+    fn price(&self) -> AbsolutePrice {
+        AbsolutePrice::from_price(self.side(), self.base_price)
+    }
+
+    fn operator_fee(&self, _: InputAsset<u64>) -> FeeAsset<u64> {
+        0
+    }
+
+    fn fee(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    fn budget(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    fn consumable_budget(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    /// Funds are spent with a key witness, no scripts are run.
+    fn marginal_cost_hint(&self) -> ExUnits {
+        ExUnits { mem: 0, steps: 0 }
+    }
+
+    fn min_marginal_output(&self) -> OutputAsset<u64> {
+        1
+    }
+
+    fn time_bounds(&self) -> TimeBounds<u64> {
+        TimeBounds::Until(self.expires_at)
+    }
+}
+
+impl Stable for RfqOrder {
+    type StableId = PolicyId;
+    fn stable_id(&self) -> Self::StableId {
+        self.id
+    }
+    fn is_quasi_permanent(&self) -> bool {
+        false
+    }
+}
+
+impl Tradable for RfqOrder {
+    type PairId = PairId;
+
+    fn pair_id(&self) -> Self::PairId {
+        PairId::canonical(self.input_asset, self.output_asset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::TransactionOutput;
+    use cml_chain::Value;
+    use cml_core::serialization::Serialize;
+    use cml_crypto::{PrivateKey, RawBytesEncoding, TransactionHash};
+
+    use bloom_offchain::execution_engine::liquidity_book::core::Next;
+    use bloom_offchain::execution_engine::liquidity_book::market_taker::TakerBehaviour;
+    use spectrum_cardano_lib::{AssetClass, OutputRef};
+
+    use crate::orders::rfq::{RfqQuote, RfqRejected};
+
+    const TX: &str = "6c038a69587061acd5611507e68b1fd3a7e7d189367b7853f3bb5079a118b880";
+    const TOKEN: &str = "f6099832f9563e4cf59602b3351c3c5a8a7dda2d44575ef69b82cf8d.4144414f";
+
+    fn signed_quote(expires_at: u64) -> RfqQuote {
+        let sk = PrivateKey::from_normal_bytes(&[7u8; 32]).unwrap();
+        let vkey = sk.to_public();
+        let funds = TransactionOutput::new(
+            EnterpriseAddress::new(0, StakeCredential::new_pub_key(vkey.hash())).to_address(),
+            Value::from(10_000_000),
+            None,
+            None,
+        );
+        let mut quote = RfqQuote {
+            funds_ref: format!("{}#0", TX),
+            funds_output: hex::encode(funds.to_cbor_bytes()),
+            input_asset: AssetClass::Native,
+            input_amount: 5_000_000,
+            output_asset: AssetClass::try_from(TOKEN).unwrap(),
+            min_output: 1_000,
+            expires_at,
+            maker_vkey: hex::encode(vkey.to_raw_bytes()),
+            signature: String::new(),
+        };
+        let digest = quote.digest(OutputRef::new(TransactionHash::from_hex(TX).unwrap(), 0));
+        quote.signature = hex::encode(sk.sign(&digest).to_raw_bytes());
+        quote
+    }
+
+    #[test]
+    fn accepts_quote_signed_by_funds_owner() {
+        let (order, funds) = signed_quote(130).verify(100, 60).unwrap();
+        assert_eq!(funds.1.index(), 0);
+        assert_eq!(order.input_amount, 5_000_000);
+        assert!(matches!(order.with_updated_time(129), Next::Succ(_)));
+        assert!(matches!(order.with_updated_time(130), Next::Term(_)));
+    }
+
+    #[test]
+    fn rejects_tampered_or_stale_quotes() {
+        let mut tampered = signed_quote(130);
+        tampered.min_output = 1;
+        assert_eq!(tampered.verify(100, 60).err(), Some(RfqRejected::BadSignature));
+        assert_eq!(
+            signed_quote(130).verify(130, 60).err(),
+            Some(RfqRejected::Expired)
+        );
+        assert_eq!(
+            signed_quote(300).verify(100, 60).err(),
+            Some(RfqRejected::TtlTooLong(60))
+        );
+    }
+}
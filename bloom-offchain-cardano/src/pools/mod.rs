@@ -14,6 +14,7 @@ use spectrum_offchain_cardano::data::order::{ClassicalAMMOrder, RunClassicalAMMO
 
 use spectrum_offchain_cardano::data::pool::AnyPool;
 use spectrum_offchain_cardano::data::pool::AnyPool::{BalancedCFMM, PureCFMM, StableCFMM};
+use spectrum_offchain_cardano::data::refund::Refunds;
 use spectrum_offchain_cardano::data::stable_order::RunStableAMMOrderOverPool;
 use spectrum_offchain_cardano::deployment::DeployedValidator;
 use spectrum_offchain_cardano::deployment::ProtocolValidator::{
@@ -22,6 +23,7 @@ use spectrum_offchain_cardano::deployment::ProtocolValidator::{
     ConstFnPoolFeeSwitchBiDirFee, ConstFnPoolFeeSwitchV2, ConstFnPoolRedeem, ConstFnPoolSwap, ConstFnPoolV1,
     ConstFnPoolV2, StableFnPoolT2T, StableFnPoolT2TDeposit, StableFnPoolT2TRedeem,
 };
+use spectrum_offchain_cardano::fee_tuning::SharedFeeTuner;
 
 /// Magnet for local instances.
 #[repr(transparent)]
@@ -34,6 +36,8 @@ where
         + Has<NetworkId>
         + Has<Collateral>
         + Has<OperatorRewardAddress>
+        + Has<Refunds>
+        + Has<SharedFeeTuner>
         + Has<DeployedValidator<{ ConstFnPoolV1 as u8 }>>
         + Has<DeployedValidator<{ ConstFnPoolV2 as u8 }>>
         + Has<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>>
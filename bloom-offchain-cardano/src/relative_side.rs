@@ -4,7 +4,7 @@ use derive_more::{From, Into};
 use bloom_offchain::execution_engine::liquidity_book::side::Side;
 use spectrum_cardano_lib::plutus_data::IntoPlutusData;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Into, From)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Into, From, serde::Serialize, serde::Deserialize)]
 pub struct RelativeSide(Side);
 impl RelativeSide {
     pub fn value(self) -> Side {
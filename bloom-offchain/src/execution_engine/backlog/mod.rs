@@ -1,13 +1,16 @@
 use spectrum_offchain::data::Baked;
+use spectrum_offchain::executor::RunOrderError;
 
 use crate::execution_engine::bundled::Bundled;
 
 /// Interpreter for non-trade operations like AMM deposits/redeems.
 pub trait SpecializedInterpreter<Pl, Op, Ver, Txc, Bearer, Ctx> {
+    /// Returns the order back along with the reason if it cannot be executed,
+    /// non-fatal failures are retried later.
     fn try_run(
         &mut self,
         pool: Bundled<Pl, Bearer>,
         order: Bundled<Op, Bearer>,
         context: Ctx,
-    ) -> Option<(Txc, Bundled<Baked<Pl, Ver>, Bearer>, Bundled<Op, Bearer>)>;
+    ) -> Result<(Txc, Bundled<Baked<Pl, Ver>, Bearer>, Bundled<Op, Bearer>), RunOrderError<Bundled<Op, Bearer>>>;
 }
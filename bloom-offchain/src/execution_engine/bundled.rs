@@ -1,6 +1,8 @@
 use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 
+use serde::{Deserialize, Serialize};
+
 use spectrum_offchain::backlog;
 use spectrum_offchain::data::order::SpecializedOrder;
 use spectrum_offchain::data::{EntitySnapshot, Stable, Tradable};
@@ -9,7 +11,7 @@ use spectrum_offchain::ledger::TryFromLedger;
 use crate::execution_engine::liquidity_book;
 
 /// Entity bundled with its source.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Bundled<T, Bearer>(pub T, pub Bearer);
 
 impl<T: Display, B> Display for Bundled<T, B> {
@@ -87,6 +89,10 @@ where
     fn get_pool_ref(&self) -> Self::TPoolId {
         self.0.get_pool_ref()
     }
+
+    fn is_deposit(&self) -> bool {
+        self.0.is_deposit()
+    }
 }
 
 impl<T, Bearer> EntitySnapshot for Bundled<T, Bearer>
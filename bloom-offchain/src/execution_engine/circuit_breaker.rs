@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use either::Either;
+use log::{error, info};
+use num_rational::Ratio;
+
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, StateUpdate};
+
+use crate::execution_engine::bundled::Bundled;
+use crate::execution_engine::liquidity_book::market_maker::MarketMaker;
+use crate::execution_engine::notifier::WebhookNotifier;
+use crate::execution_engine::reserve_history::Volatility;
+use crate::execution_engine::Event;
+
+const BPS: f64 = 10_000.0;
+
+/// Price moves considered anomalous and how long matching pauses on them.
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    /// Pool spot price moving by more than this many basis points within a single TX trips the breaker.
+    pub max_spot_jump_bps: u32,
+    /// Pool spot price diverging from the index price by more than this many basis points trips the breaker.
+    pub max_index_divergence_bps: u32,
+    /// Realized volatility of the pair exceeding this many basis points trips the breaker,
+    /// not checked if not set.
+    #[serde(default)]
+    pub max_volatility_bps: Option<u32>,
+    /// Matching in a tripped pair is paused for this long.
+    pub cooldown: Duration,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TripReason {
+    /// Spot price of a pool jumped by the given number of basis points.
+    SpotJump(f64),
+    /// Spot price of a pool diverged from the index price by the given number of basis points.
+    IndexDivergence(f64),
+    /// Realized volatility of the pair reached the given number of basis points.
+    Volatility(f64),
+}
+
+impl Display for TripReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TripReason::SpotJump(bps) => write!(f, "spot price jumped by {:.0} bps", bps),
+            TripReason::IndexDivergence(bps) => write!(f, "spot price diverged from index by {:.0} bps", bps),
+            TripReason::Volatility(bps) => write!(f, "realized volatility reached {:.0} bps", bps),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Check a move of pool spot price from `prev` to `next` against the optional `index` price.
+    pub fn detect(
+        &self,
+        prev: Option<Ratio<u128>>,
+        next: Ratio<u128>,
+        index: Option<Ratio<u128>>,
+    ) -> Option<TripReason> {
+        if let Some(jump) = prev.and_then(|prev| deviation_bps(next, prev)) {
+            if jump > self.max_spot_jump_bps as f64 {
+                return Some(TripReason::SpotJump(jump));
+            }
+        }
+        if let Some(divergence) = index.and_then(|index| deviation_bps(next, index)) {
+            if divergence > self.max_index_divergence_bps as f64 {
+                return Some(TripReason::IndexDivergence(divergence));
+            }
+        }
+        None
+    }
+
+    /// Check realized volatility of a pair.
+    pub fn detect_volatility(&self, volatility: Volatility) -> Option<TripReason> {
+        self.max_volatility_bps
+            .filter(|max| volatility.bps > *max as f64)
+            .map(|_| TripReason::Volatility(volatility.bps))
+    }
+}
+
+/// Relative distance of `price` from `reference` in basis points.
+fn deviation_bps(price: Ratio<u128>, reference: Ratio<u128>) -> Option<f64> {
+    let to_f64 = |r: Ratio<u128>| *r.numer() as f64 / *r.denom() as f64;
+    let reference = to_f64(reference);
+    (reference > 0.0).then(|| (to_f64(price) - reference).abs() / reference * BPS)
+}
+
+struct BreakerState<Pair> {
+    index_prices: HashMap<Pair, Ratio<u128>>,
+    /// UNIX time (seconds) matching in the pair resumes at.
+    tripped_until: HashMap<Pair, u64>,
+}
+
+/// Pauses matching on pairs whose pools quote anomalous prices,
+/// protecting against oracle manipulation and fat-finger pools.
+/// Shared by all executor partitions, disabled unless configured.
+#[derive(Clone)]
+pub struct PairCircuitBreaker<Pair> {
+    conf: Option<CircuitBreakerConfig>,
+    webhook: Option<WebhookNotifier>,
+    state: Arc<Mutex<BreakerState<Pair>>>,
+}
+
+impl<Pair> PairCircuitBreaker<Pair>
+where
+    Pair: Copy + Eq + Hash + Display,
+{
+    pub fn new(conf: Option<CircuitBreakerConfig>, webhook: Option<WebhookNotifier>) -> Self {
+        Self {
+            conf,
+            webhook,
+            state: Arc::new(Mutex::new(BreakerState {
+                index_prices: HashMap::new(),
+                tripped_until: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Update index price of the pair, spot prices of its pools are checked against it.
+    pub fn observe_index(&self, pair: Pair, price: Ratio<u128>) {
+        self.state.lock().unwrap().index_prices.insert(pair, price);
+    }
+
+    /// Check confirmed transitions of pools in the pair for anomalous price moves.
+    pub fn observe<CO, SO, P, B, Ver>(&self, pair: Pair, event: &Event<CO, SO, P, B, Ver>, now: u64)
+    where
+        P: MarketMaker,
+    {
+        let Some(conf) = self.conf else {
+            return;
+        };
+        let Either::Left(Channel::Ledger(Confirmed(StateUpdate::Transition(tr)))) = event else {
+            return;
+        };
+        let (prev, next) = match tr {
+            Ior::Both(Bundled(Either::Right(prev), _), Bundled(Either::Right(next), _)) => {
+                (Some(prev.entity.static_price().unwrap()), next)
+            }
+            Ior::Right(Bundled(Either::Right(next), _)) => (None, next),
+            _ => return,
+        };
+        if !next.entity.is_active() {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+        let index = state.index_prices.get(&pair).copied();
+        if let Some(reason) = conf.detect(prev, next.entity.static_price().unwrap(), index) {
+            self.trip(&mut state, pair, reason, now + conf.cooldown.as_secs());
+        }
+    }
+
+    /// Check realized volatility of the pair estimated from its reserve history.
+    pub fn observe_volatility(&self, pair: Pair, volatility: Volatility, now: u64) {
+        let Some(conf) = self.conf else {
+            return;
+        };
+        if let Some(reason) = conf.detect_volatility(volatility) {
+            let mut state = self.state.lock().unwrap();
+            self.trip(&mut state, pair, reason, now + conf.cooldown.as_secs());
+        }
+    }
+
+    fn trip(&self, state: &mut BreakerState<Pair>, pair: Pair, reason: TripReason, until: u64) {
+        state.tripped_until.insert(pair, until);
+        error!(
+            target: "circuit_breaker",
+            "Matching in pair {} is paused until {}: {}",
+            pair,
+            until,
+            reason
+        );
+        if let Some(webhook) = &self.webhook {
+            webhook.post(serde_json::json!({
+                "event": "circuitBreaker",
+                "pair": pair.to_string(),
+                "reason": reason.to_string(),
+                "pausedUntil": until,
+            }));
+        }
+    }
+
+    /// Whether matching in the pair is paused at the moment.
+    pub fn is_tripped(&self, pair: &Pair, now: u64) -> bool {
+        self.tripped_until(pair, now).is_some()
+    }
+
+    /// UNIX time (seconds) matching in the pair resumes at if it is paused at the moment.
+    pub fn tripped_until(&self, pair: &Pair, now: u64) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        match state.tripped_until.get(pair) {
+            Some(until) if now < *until => Some(*until),
+            Some(_) => {
+                info!(target: "circuit_breaker", "Matching in pair {} is resumed", pair);
+                state.tripped_until.remove(pair);
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use num_rational::Ratio;
+
+    use crate::execution_engine::circuit_breaker::{CircuitBreakerConfig, PairCircuitBreaker, TripReason};
+    use crate::execution_engine::reserve_history::Volatility;
+
+    const CONF: CircuitBreakerConfig = CircuitBreakerConfig {
+        max_spot_jump_bps: 2_000,
+        max_index_divergence_bps: 500,
+        max_volatility_bps: Some(300),
+        cooldown: Duration::from_secs(600),
+    };
+
+    #[test]
+    fn anomalous_moves_are_detected() {
+        let p = |n, d| Ratio::new(n, d);
+        assert_eq!(CONF.detect(Some(p(100, 1)), p(110, 1), None), None);
+        assert_eq!(
+            CONF.detect(Some(p(100, 1)), p(50, 1), None),
+            Some(TripReason::SpotJump(5_000.0))
+        );
+        assert_eq!(CONF.detect(None, p(103, 1), Some(p(100, 1))), None);
+        assert_eq!(
+            CONF.detect(Some(p(100, 1)), p(110, 1), Some(p(100, 1))),
+            Some(TripReason::IndexDivergence(1_000.0))
+        );
+    }
+
+    #[test]
+    fn volatile_pair_is_paused() {
+        let breaker = PairCircuitBreaker::<u8>::new(Some(CONF), None);
+        let volatility = |bps| Volatility { bps, returns: 10 };
+        breaker.observe_volatility(1, volatility(250.0), 0);
+        assert!(!breaker.is_tripped(&1, 0));
+        breaker.observe_volatility(1, volatility(350.0), 0);
+        assert!(breaker.is_tripped(&1, 599));
+        let unchecked = CircuitBreakerConfig {
+            max_volatility_bps: None,
+            ..CONF
+        };
+        assert_eq!(unchecked.detect_volatility(volatility(350.0)), None);
+    }
+
+    #[test]
+    fn tripped_pair_resumes_after_cooldown() {
+        let breaker = PairCircuitBreaker::<u8>::new(Some(CONF), None);
+        breaker.state.lock().unwrap().tripped_until.insert(1, 600);
+        assert_eq!(breaker.tripped_until(&1, 599), Some(600));
+        assert!(!breaker.is_tripped(&2, 599));
+        assert!(!breaker.is_tripped(&1, 600));
+        assert!(breaker.state.lock().unwrap().tripped_until.is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+use derive_more::Display;
+
+/// Inconsistencies between index, cache and books the executor recovers from
+/// by skipping affected work.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Display)]
+pub enum ExecutorError<StableId, Pair> {
+    #[display(fmt = "State of {} is missing in index", _0)]
+    UnresolvedState(StableId),
+    #[display(fmt = "Recipe in pair {} targets {} which is missing in cache", _1, _0)]
+    UnlinkedRecipe(StableId, Pair),
+    #[display(fmt = "Maker {} turned into taker in pair {}", _0, _1)]
+    IncoherentTransition(StableId, Pair),
+}
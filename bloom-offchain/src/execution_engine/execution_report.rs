@@ -0,0 +1,234 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain::journal::{
+    compact_pruned, estimated_db_size, open_journal, open_journal_read_only, Journal,
+};
+use spectrum_offchain::rocks::{migrate, Migration, RocksConfig};
+
+use crate::execution_engine::liquidity_book::side::Side;
+use crate::execution_engine::liquidity_book::types::{FeeAsset, InputAsset, OutputAsset};
+use crate::execution_engine::notifier::{Fill, FillNotifier};
+
+/// What happened to an order in a particular TX.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionReport<TxHash> {
+    pub side: Side,
+    pub removed_input: InputAsset<u64>,
+    pub added_output: OutputAsset<u64>,
+    /// Effective price of the fill as `numer/denom` (Quote/Base).
+    pub price: Option<String>,
+    pub fee_charged: FeeAsset<u64>,
+    /// Whether the order was fully executed.
+    pub terminal: bool,
+    pub tx_hash: TxHash,
+    /// UNIX time (seconds) the TX was accepted by the network at.
+    pub executed_at: u64,
+}
+
+impl<TxHash> ExecutionReport<TxHash> {
+    pub fn new<OrderId>(fill: Fill<OrderId>, tx_hash: TxHash, executed_at: u64) -> Self {
+        Self {
+            side: fill.side,
+            removed_input: fill.removed_input,
+            added_output: fill.added_output,
+            price: fill.price().map(|p| format!("{}/{}", p.numer(), p.denom())),
+            fee_charged: fill.fee_charged,
+            terminal: fill.terminal,
+            tx_hash,
+            executed_at,
+        }
+    }
+}
+
+/// Lookup of execution history of orders.
+pub trait ExecutionReports<OrderId, TxHash> {
+    /// All reports on the given order, oldest first.
+    fn reports(&self, order_id: &OrderId) -> Vec<ExecutionReport<TxHash>>;
+}
+
+/// Migrations of the execution report store, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+/// Persists a report on every fill of an order.
+pub struct ExecutionReportsRocksDB<OrderId, TxHash> {
+    db: Arc<rocksdb::DB>,
+    /// Fills are stamped with time read from it.
+    clock: SharedClock,
+    pd: PhantomData<(OrderId, TxHash)>,
+}
+
+impl<OrderId, TxHash> ExecutionReportsRocksDB<OrderId, TxHash> {
+    pub fn new(conf: RocksConfig, clock: SharedClock) -> Self {
+        let db = open_journal(conf.db_path);
+        migrate(&db, "execution_reports", MIGRATIONS);
+        Self {
+            db: Arc::new(db),
+            clock,
+            pd: PhantomData,
+        }
+    }
+
+    pub fn read_only(conf: RocksConfig) -> Result<Self, rocksdb::Error> {
+        open_journal_read_only(conf.db_path).map(|db| Self {
+            db: Arc::new(db),
+            clock: SharedClock::system(),
+            pd: PhantomData,
+        })
+    }
+
+    /// Reports on all orders along with the key they are stored under.
+    fn all_reports(&self) -> impl Iterator<Item = (Box<[u8]>, Vec<ExecutionReport<TxHash>>)> + '_
+    where
+        TxHash: DeserializeOwned,
+    {
+        self.db.iterator(rocksdb::IteratorMode::Start).filter_map(|i| {
+            let (k, v) = i.unwrap();
+            bincode::deserialize(&v).ok().map(|reports| (k, reports))
+        })
+    }
+}
+
+/// Reports are stored per order, so history of an order is dropped as a whole
+/// once its latest report falls behind the cutoff.
+impl<OrderId, TxHash> Journal for ExecutionReportsRocksDB<OrderId, TxHash>
+where
+    OrderId: Clone + DeserializeOwned,
+    TxHash: DeserializeOwned,
+{
+    type Entry = (OrderId, ExecutionReport<TxHash>);
+
+    fn entries_between(&self, from: u64, to: u64) -> Vec<(OrderId, ExecutionReport<TxHash>)> {
+        let mut entries = vec![];
+        for (key, reports) in self.all_reports() {
+            let Ok(order_id) = bincode::deserialize::<OrderId>(&key) else {
+                continue;
+            };
+            for report in reports {
+                if report.executed_at >= from && report.executed_at < to {
+                    entries.push((order_id.clone(), report));
+                }
+            }
+        }
+        entries.sort_by_key(|(_, report)| report.executed_at);
+        entries
+    }
+
+    fn oldest_entry_at(&self) -> Option<u64> {
+        self.all_reports()
+            .filter_map(|(_, reports)| reports.iter().map(|r| r.executed_at).min())
+            .min()
+    }
+
+    fn prune_before(&self, time: u64) {
+        let stale = self
+            .all_reports()
+            .filter(|(_, reports)| reports.iter().all(|r| r.executed_at < time))
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>();
+        for key in stale {
+            self.db.delete(key).unwrap();
+        }
+        compact_pruned(&self.db, None, None);
+    }
+
+    fn estimated_size(&self) -> u64 {
+        estimated_db_size(&self.db)
+    }
+}
+
+impl<OrderId, TxHash> Clone for ExecutionReportsRocksDB<OrderId, TxHash> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+            clock: self.clock.clone(),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<OrderId, TxHash> ExecutionReports<OrderId, TxHash> for ExecutionReportsRocksDB<OrderId, TxHash>
+where
+    OrderId: Serialize,
+    TxHash: DeserializeOwned,
+{
+    fn reports(&self, order_id: &OrderId) -> Vec<ExecutionReport<TxHash>> {
+        self.db
+            .get(bincode::serialize(order_id).unwrap())
+            .unwrap()
+            .and_then(|v| bincode::deserialize(&v).ok())
+            .unwrap_or_default()
+    }
+}
+
+impl<OrderId, TxHash> FillNotifier<OrderId, TxHash> for ExecutionReportsRocksDB<OrderId, TxHash>
+where
+    OrderId: Serialize,
+    TxHash: Eq + Serialize + DeserializeOwned,
+{
+    fn on_fill(&self, fill: Fill<OrderId>, tx_hash: TxHash) {
+        let mut reports = self.reports(&fill.order_id);
+        // The same fill may be reported again after a restart.
+        if reports.iter().any(|r| r.tx_hash == tx_hash) {
+            return;
+        }
+        let executed_at = self.clock.unix_time_secs();
+        let key = bincode::serialize(&fill.order_id).unwrap();
+        reports.push(ExecutionReport::new(fill, tx_hash, executed_at));
+        self.db.put(key, bincode::serialize(&reports).unwrap()).unwrap();
+    }
+
+    fn on_removed(&self, _: OrderId) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use spectrum_offchain::clock::{ManualClock, SharedClock};
+    use spectrum_offchain::rocks::RocksConfig;
+
+    use crate::execution_engine::execution_report::{ExecutionReports, ExecutionReportsRocksDB};
+    use crate::execution_engine::liquidity_book::side::Side;
+    use crate::execution_engine::notifier::{Fill, FillNotifier};
+
+    #[test]
+    fn fills_are_stamped_with_clock_time() {
+        let clock = ManualClock::new(1_000);
+        let reports = ExecutionReportsRocksDB::<u8, u8>::new(
+            RocksConfig {
+                db_path: format!("./tmp/{}", rand::thread_rng().next_u32()),
+            },
+            SharedClock::new(clock.clone()),
+        );
+        let fill = Fill {
+            order_id: 1,
+            side: Side::Ask,
+            removed_input: 100,
+            added_output: 250,
+            fee_charged: 10,
+            terminal: false,
+        };
+        reports.on_fill(fill, 0);
+        clock.advance(60);
+        reports.on_fill(fill, 0);
+        reports.on_fill(
+            Fill {
+                terminal: true,
+                ..fill
+            },
+            1,
+        );
+        let executed_at = reports
+            .reports(&1)
+            .iter()
+            .map(|r| r.executed_at)
+            .collect::<Vec<_>>();
+        assert_eq!(executed_at, vec![1_000, 1_060]);
+    }
+}
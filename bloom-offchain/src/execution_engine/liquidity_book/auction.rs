@@ -0,0 +1,282 @@
+use std::cmp::{min, Reverse};
+use std::fmt::{Debug, Display};
+use std::ops::AddAssign;
+
+use algebra_core::monoid::Monoid;
+use log::trace;
+
+use spectrum_offchain::data::Stable;
+
+use crate::execution_engine::liquidity_book::config::ExecutionConfig;
+use crate::execution_engine::liquidity_book::core::{MatchmakingAttempt, MatchmakingRecipe};
+use crate::execution_engine::liquidity_book::market_maker::{MakerBehavior, MarketMaker};
+use crate::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use crate::execution_engine::liquidity_book::side::OnSide::Bid;
+use crate::execution_engine::liquidity_book::side::Side;
+use crate::execution_engine::liquidity_book::stashing_option::StashingOption;
+use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+use crate::execution_engine::liquidity_book::{
+    execute_with_taker, linear_output_unsafe, ExternalTLBEvents, TLBFeedback, TemporalLiquidityBook,
+    INSTRUCTIONS_PER_MATCH, TLB,
+};
+
+/// Liquidity book matching takers in discrete windows rather than continuously.
+///
+/// Takers arriving within a window are accumulated. Once it closes, all takers crossing
+/// the uniform clearing price are filled against each other at that price.
+/// What the auction leaves unmatched is then routed to makers as in continuous mode,
+/// after which the next window opens. A closed window is cleared once the pair is focused next time.
+#[derive(Clone)]
+pub struct BatchAuction<Taker, Maker: Stable, U> {
+    book: TLB<Taker, Maker, U>,
+    window_secs: u64,
+    time: u64,
+    /// Index of the window takers are currently accumulated in, unknown until clocks are advanced.
+    open_window: Option<u64>,
+    /// Whether the auction of the closed window has been held already.
+    auction_held: bool,
+}
+
+impl<Taker, Maker: Stable, U> BatchAuction<Taker, Maker, U> {
+    pub fn new(time: u64, conf: ExecutionConfig<U>, window_secs: u64) -> Self {
+        Self {
+            book: TLB::new(time, conf),
+            window_secs: window_secs.max(1),
+            time,
+            open_window: None,
+            auction_held: false,
+        }
+    }
+}
+
+impl<Taker, Maker, U> BatchAuction<Taker, Maker, U>
+where
+    Taker: Stable + MarketTaker<U = U> + TakerBehaviour + Ord + Copy + Display,
+    Maker: Stable + MarketMaker<U = U> + MakerBehavior + Copy + Display,
+    U: Monoid + AddAssign + PartialOrd + Copy,
+{
+    /// Fill takers crossing the clearing price of the closed window against each other at that price.
+    fn clear(&mut self) -> Option<MatchmakingRecipe<Taker, Maker>> {
+        let asks = self.book.state.active_takers(Side::Ask);
+        let bids = self.book.state.active_takers(Side::Bid);
+        let price = clearing_price(&asks, &bids)?;
+        trace!(target: "auction", "Clearing price is {}", price);
+        let conf = self.book.conf;
+        let mut batch: MatchmakingAttempt<Taker, Maker, U> = MatchmakingAttempt::empty();
+        while batch.execution_units_consumed() < conf.execution_cap.soft
+            && batch.num_instructions() + INSTRUCTIONS_PER_MATCH <= self.book.max_instructions()
+            && conf
+                .recipe_limits
+                .admits(batch.num_fills() + 2, batch.num_swaps())
+        {
+            let Some(ask) = self
+                .book
+                .state
+                .try_pick_taker(Side::Ask, |t| t.input() > 0 && t.price() <= price)
+            else {
+                break;
+            };
+            let Some(bid) = self
+                .book
+                .state
+                .try_pick_taker(Side::Bid, |t| t.input() > 0 && t.price() >= price)
+            else {
+                self.book.state.pre_add_taker(ask);
+                break;
+            };
+            let (take_ask, take_bid) = execute_with_taker(ask, bid, |_, _| price);
+            for take in [take_ask, take_bid] {
+                batch.add_take(take);
+                self.book.on_take(take.result);
+            }
+        }
+        match MatchmakingRecipe::try_from(batch, conf.recipe_limits.min_fills) {
+            Ok(recipe) => {
+                trace!(target: "auction", "Auction cleared with {}", recipe);
+                Some(recipe)
+            }
+            Err(None) => {
+                self.book.park_stashed();
+                self.book.state.rollback(StashingOption::Unstash);
+                None
+            }
+            Err(Some(unsatisfied_takers)) => {
+                self.book
+                    .state
+                    .rollback(StashingOption::Stash(unsatisfied_takers));
+                None
+            }
+        }
+    }
+}
+
+/// Price maximizing the volume (in base asset) exchanged among the given takers.
+/// Ties are resolved in favour of the smallest imbalance between supply and demand.
+fn clearing_price<Taker: MarketTaker>(asks: &[Taker], bids: &[Taker]) -> Option<AbsolutePrice> {
+    asks.iter()
+        .chain(bids)
+        .map(|t| t.price())
+        .filter_map(|price| {
+            let supply = asks
+                .iter()
+                .filter(|ask| ask.price() <= price)
+                .map(|ask| ask.input() as u128)
+                .sum::<u128>();
+            let demand = bids
+                .iter()
+                .filter(|bid| bid.price() >= price)
+                .map(|bid| linear_output_unsafe(bid.input(), Bid(price)) as u128)
+                .sum::<u128>();
+            let volume = min(supply, demand);
+            (volume > 0).then(|| (volume, Reverse(supply.abs_diff(demand)), price))
+        })
+        .max_by_key(|(volume, imbalance, _)| (*volume, *imbalance))
+        .map(|(_, _, price)| price)
+}
+
+impl<Taker, Maker, U> TemporalLiquidityBook<Taker, Maker> for BatchAuction<Taker, Maker, U>
+where
+    Taker: Stable + MarketTaker<U = U> + TakerBehaviour + Ord + Copy + Display,
+    Maker: Stable + MarketMaker<U = U> + MakerBehavior + Copy + Display,
+    U: Monoid + AddAssign + PartialOrd + Copy,
+{
+    fn attempt(&mut self) -> Option<MatchmakingRecipe<Taker, Maker>> {
+        let current_window = self.time / self.window_secs;
+        if self.open_window.map_or(true, |open| open >= current_window) {
+            return None;
+        }
+        if !self.auction_held {
+            self.auction_held = true;
+            if let Some(recipe) = self.clear() {
+                return Some(recipe);
+            }
+        }
+        let recipe = self.book.attempt();
+        if recipe.is_none() {
+            trace!(target: "auction", "Windows before #{} are cleared", current_window);
+            self.open_window = Some(current_window);
+            self.auction_held = false;
+        }
+        recipe
+    }
+}
+
+impl<Taker, Maker, U> ExternalTLBEvents<Taker, Maker> for BatchAuction<Taker, Maker, U>
+where
+    Taker: MarketTaker + TakerBehaviour + Ord + Copy + Display,
+    Maker: MarketMaker + Stable + Copy + Display + Debug,
+{
+    fn advance_clocks(&mut self, new_time: u64) {
+        self.time = new_time;
+        self.open_window.get_or_insert(new_time / self.window_secs);
+        self.book.advance_clocks(new_time)
+    }
+
+    fn update_taker(&mut self, fr: Taker) {
+        self.book.update_taker(fr)
+    }
+
+    fn remove_taker(&mut self, fr: Taker) {
+        self.book.remove_taker(fr)
+    }
+
+    fn update_maker(&mut self, pool: Maker) {
+        self.book.update_maker(pool)
+    }
+
+    fn remove_maker(&mut self, pool: Maker) {
+        self.book.remove_maker(pool)
+    }
+}
+
+impl<Taker, Maker, U> TLBFeedback<Taker, Maker> for BatchAuction<Taker, Maker, U>
+where
+    Taker: MarketTaker + Ord + Copy,
+    Maker: MarketMaker + Stable + Copy,
+{
+    fn on_recipe_succeeded(&mut self) {
+        self.book.on_recipe_succeeded()
+    }
+
+    fn on_recipe_failed(&mut self) {
+        self.book.on_recipe_failed()
+    }
+
+    fn on_recipe_oversized(&mut self, num_instructions: usize) {
+        self.book.on_recipe_oversized(num_instructions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::auction::{clearing_price, BatchAuction};
+    use crate::execution_engine::liquidity_book::config::{
+        ExecutionCap, ExecutionConfig, MakerSelection, MatchingMode, RecipeLimits, SettlementPolicy,
+        StashPolicy,
+    };
+    use crate::execution_engine::liquidity_book::side::Side::{Ask, Bid};
+    use crate::execution_engine::liquidity_book::state::tests::{SimpleCFMMPool, SimpleOrderPF};
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+    use crate::execution_engine::liquidity_book::{ExternalTLBEvents, TemporalLiquidityBook};
+
+    const WINDOW_SECS: u64 = 20;
+
+    fn auction() -> BatchAuction<SimpleOrderPF, SimpleCFMMPool, u64> {
+        BatchAuction::new(
+            0,
+            ExecutionConfig {
+                execution_cap: ExecutionCap {
+                    soft: 1000000,
+                    hard: 1600000,
+                    tx_size: None,
+                },
+                o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::default(),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::BatchAuction {
+                    window_secs: WINDOW_SECS,
+                },
+                settlement_policy: SettlementPolicy::default(),
+            },
+            WINDOW_SECS,
+        )
+    }
+
+    #[test]
+    fn clearing_price_maximizes_volume() {
+        let asks = vec![
+            SimpleOrderPF::new(Ask, 1000, AbsolutePrice::new_unsafe(1, 1), 0),
+            SimpleOrderPF::new(Ask, 1000, AbsolutePrice::new_unsafe(2, 1), 0),
+        ];
+        let bids = vec![
+            SimpleOrderPF::new(Bid, 3000, AbsolutePrice::new_unsafe(3, 1), 0),
+            SimpleOrderPF::new(Bid, 1500, AbsolutePrice::new_unsafe(3, 2), 0),
+        ];
+        // At 2 both asks are supplied while the first bid alone demands 1500 of base.
+        assert_eq!(
+            clearing_price(&asks, &bids),
+            Some(AbsolutePrice::new_unsafe(2, 1))
+        );
+        assert_eq!(clearing_price(&asks[1..], &bids[1..]), None);
+    }
+
+    #[test]
+    fn takers_are_matched_only_once_window_closes() {
+        let mut book = auction();
+        book.advance_clocks(WINDOW_SECS);
+        book.update_taker(SimpleOrderPF::new(Ask, 1000, AbsolutePrice::new_unsafe(1, 1), 0));
+        book.update_taker(SimpleOrderPF::new(Bid, 3000, AbsolutePrice::new_unsafe(3, 1), 0));
+        book.advance_clocks(2 * WINDOW_SECS - 1);
+        assert!(book.attempt().is_none());
+        book.advance_clocks(2 * WINDOW_SECS);
+        let recipe = book.attempt().unwrap();
+        let fills = recipe.fills();
+        assert_eq!(fills.len(), 2);
+        // Volume is the same anywhere within [1, 3], only at 3 supply and demand are balanced.
+        let ask_fill = fills.iter().find(|f| f.side == Ask).unwrap();
+        assert_eq!((ask_fill.removed_input, ask_fill.added_output), (1000, 3000));
+    }
+}
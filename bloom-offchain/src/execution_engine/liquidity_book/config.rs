@@ -1,12 +1,137 @@
+use num_rational::Ratio;
+
 #[derive(Debug, Copy, Clone)]
 pub struct ExecutionConfig<U> {
     pub execution_cap: ExecutionCap<U>,
     /// Order-order matchmaking allowed.
     pub o2o_allowed: bool,
+    /// Max relative move of a maker's spot price a single recipe may cause.
+    pub max_price_impact: Option<Ratio<u128>>,
+    pub maker_selection: MakerSelection,
+    pub stash_policy: StashPolicy,
+    pub recipe_limits: RecipeLimits,
+    /// Takers opposite to the flow already routed through a maker of the recipe are netted
+    /// against it in the same recipe, even past the soft execution cap.
+    pub net_opposite_flows: bool,
+    pub matching_mode: MatchingMode,
+    pub settlement_policy: SettlementPolicy,
+}
+
+/// How takers of a pair are matched.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum MatchingMode {
+    /// Takers are matched as soon as they arrive.
+    #[default]
+    Continuous,
+    /// Takers are accumulated for a window of the given length (in seconds)
+    /// and cleared in a single auction at a uniform price once it closes.
+    BatchAuction { window_secs: u64 },
+}
+
+/// How the price two overlapping takers are matched at is picked within the overlap.
+/// The pivot is the index price clamped into the overlap, or the middle of it if there is no index price.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SettlementPolicy {
+    /// Takers are settled at the pivot.
+    NoBias,
+    /// The pivot is shifted in favour of the taker paying the higher operator fee,
+    /// by up to `max_bias` of it in proportion to the imbalance of fees.
+    FeeBias { max_bias: Ratio<u128> },
+    /// The pivot is shifted by `max_bias` of it against the taker the match is made for,
+    /// in favour of the counter taker resting in the book.
+    TakerPays { max_bias: Ratio<u128> },
+}
+
+impl Default for SettlementPolicy {
+    fn default() -> Self {
+        Self::FeeBias {
+            max_bias: Ratio::new(3, 100),
+        }
+    }
+}
+
+/// Bounds on the number of instructions of each kind in a recipe,
+/// trading TX complexity for matching aggressiveness.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RecipeLimits {
+    /// Max number of orders filled by a single recipe, unbounded if not set.
+    pub max_fills: Option<usize>,
+    /// Max number of makers swapped against in a single recipe, unbounded if not set.
+    pub max_swaps: Option<usize>,
+    /// Recipes filling fewer orders are discarded.
+    pub min_fills: usize,
+}
+
+impl Default for RecipeLimits {
+    fn default() -> Self {
+        Self {
+            max_fills: None,
+            max_swaps: None,
+            min_fills: 1,
+        }
+    }
+}
+
+impl RecipeLimits {
+    /// Whether a recipe of the given number of fills and swaps is allowed.
+    pub fn admits(&self, num_fills: usize, num_swaps: usize) -> bool {
+        num_fills <= self.max_fills.unwrap_or(usize::MAX) && num_swaps <= self.max_swaps.unwrap_or(usize::MAX)
+    }
+}
+
+/// What happens to takers whose limits couldn't be satisfied in a matchmaking attempt.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum StashPolicy {
+    /// Takers are returned to matchmaking once the recipe they were stashed in settles.
+    #[default]
+    UntilSettled,
+    /// Takers sit out of matchmaking for the given number of attempts.
+    Attempts(u32),
+}
+
+/// How a maker is chosen when several of them can serve a taker.
+#[derive(Debug, Copy, Clone)]
+pub struct MakerSelection {
+    /// Makers quoting within this relative distance from the best real price are considered
+    /// equally good, the choice among them is made by recent failure rate, execution cost
+    /// and liquidity depth.
+    pub price_tolerance: Ratio<u128>,
+    /// Max share of a maker's reserves on the input side a single recipe may swap into it,
+    /// the remainder of the taker is routed to other makers.
+    pub max_reserves_share: Option<Ratio<u128>>,
+}
+
+impl Default for MakerSelection {
+    fn default() -> Self {
+        Self {
+            price_tolerance: Ratio::from_integer(0),
+            max_reserves_share: None,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub struct ExecutionCap<U> {
     pub soft: U,
     pub hard: U,
+    /// Recipes are not capped by TX size if not set.
+    pub tx_size: Option<TxSizeCap>,
+}
+
+/// Estimate of how many instructions fit into a TX of max size.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSizeCap {
+    /// Max size of a TX in bytes.
+    pub max_tx_size: u64,
+    /// Bytes taken regardless of the recipe, e.g. by funding IO, collateral and witness scripts.
+    pub base_size: u64,
+    /// Bytes taken by each instruction, i.e. an input along with its output and redeemer.
+    pub bytes_per_instruction: u64,
+}
+
+impl TxSizeCap {
+    pub fn max_instructions(&self) -> usize {
+        (self.max_tx_size.saturating_sub(self.base_size) / self.bytes_per_instruction.max(1)) as usize
+    }
 }
@@ -4,12 +4,13 @@ use crate::execution_engine::liquidity_book::market_maker::{AbsoluteReserves, Ma
 use crate::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
 use crate::execution_engine::liquidity_book::side::{OnSide, Side};
 use crate::execution_engine::liquidity_book::types::{FeeAsset, InputAsset, OutputAsset};
+use crate::execution_engine::notifier::Fill;
+use crate::execution_engine::pool_stats::PoolTrade;
 use algebra_core::monoid::Monoid;
 use algebra_core::semigroup::Semigroup;
 use derive_more::{Display, Into};
 use either::Either;
 use log::trace;
-use num_rational::Ratio;
 use spectrum_offchain::data::Stable;
 use std::cmp::{max, min};
 use std::collections::{HashMap, HashSet};
@@ -242,33 +243,6 @@ impl<T, B> Take<T, B> {
             .expect("Budget cannot increase")
     }
 
-    pub fn scale_consumed_budget(&mut self, scale: Ratio<u64>) -> i64
-    where
-        T: MarketTaker + TakerBehaviour + Copy,
-    {
-        let consumed_budget = self.consumed_budget();
-        match &mut self.result {
-            Next::Succ(ref mut next) => {
-                let old_val = consumed_budget;
-                let new_val = old_val * scale.numer() / scale.denom();
-                let delta_consumed_budget = new_val as i64 - old_val as i64;
-                let (delta_budget, updated) = next.with_budget_corrected(-delta_consumed_budget);
-                let _ = mem::replace(next, updated);
-                let delta_consumed_budget = -delta_budget;
-                delta_consumed_budget
-            }
-            Next::Term(ref mut term) => {
-                let old_val = consumed_budget;
-                let new_val = old_val * scale.numer() / scale.denom();
-                let delta_consumed_budget = new_val as i64 - old_val as i64;
-                let (delta_budget, updated) = term.with_budget_corrected(-delta_consumed_budget);
-                let _ = mem::replace(term, updated);
-                let delta_consumed_budget = -delta_budget;
-                delta_consumed_budget
-            }
-        }
-    }
-
     pub fn correct_consumed_budget(&mut self, delta: i64) -> i64
     where
         T: MarketTaker + TakerBehaviour + Copy,
@@ -599,8 +573,28 @@ impl<Taker: Stable, Maker: Stable, U> MatchmakingAttempt<Taker, Maker, U> {
         }
     }
 
-    pub fn is_complete(&self) -> bool {
-        self.takes.len() > 1 || self.takes.len() == 1 && self.makes.len() > 0
+    /// Whether the attempt fills at least `min_fills` orders and involves more than one party.
+    pub fn is_complete(&self, min_fills: usize) -> bool {
+        self.takes.len() >= min_fills
+            && (self.takes.len() > 1 || self.takes.len() == 1 && self.makes.len() > 0)
+    }
+
+    /// Number of distinct orders filled.
+    pub fn num_fills(&self) -> usize {
+        self.takes.len()
+    }
+
+    /// Number of distinct makers swapped against.
+    pub fn num_swaps(&self) -> usize {
+        self.makes.len()
+    }
+
+    pub fn fills(&self, taker: &Taker::StableId) -> bool {
+        self.takes.contains_key(taker)
+    }
+
+    pub fn swaps(&self, maker: &Maker::StableId) -> bool {
+        self.makes.contains_key(maker)
     }
 
     pub fn needs_rebalancing(&self) -> bool {
@@ -614,6 +608,11 @@ impl<Taker: Stable, Maker: Stable, U> MatchmakingAttempt<Taker, Maker, U> {
         self.execution_units_consumed
     }
 
+    /// Number of instructions the recipe would consist of.
+    pub fn num_instructions(&self) -> usize {
+        self.takes.len() + self.makes.len()
+    }
+
     pub fn next_offered_chunk(&self, taker: &Taker) -> OnSide<u64>
     where
         Taker: MarketTaker,
@@ -638,6 +637,28 @@ impl<Taker: Stable, Maker: Stable, U> MatchmakingAttempt<Taker, Maker, U> {
         taker.side().wrap(chunk)
     }
 
+    /// State of the maker as it was before this attempt touched it.
+    pub fn initial_maker_state<'a>(&'a self, maker: &'a Maker) -> &'a Maker {
+        self.makes
+            .get(&maker.stable_id())
+            .map(|tr| &tr.target)
+            .unwrap_or(maker)
+    }
+
+    /// IDs of makers swapped against in this attempt.
+    pub fn maker_ids(&self) -> Vec<Maker::StableId> {
+        self.makes.keys().copied().collect()
+    }
+
+    /// What the maker pays out across all swaps of this attempt,
+    /// on the side the swaps push it towards.
+    pub fn maker_payout(&self, maker: &Maker::StableId) -> Option<OnSide<u64>>
+    where
+        Maker: MarketMaker,
+    {
+        self.makes.get(maker)?.loss()
+    }
+
     pub fn add_take(&mut self, take: TakeInProgress<Taker>)
     where
         Taker: MarketTaker<U = U>,
@@ -689,10 +710,7 @@ impl<Taker: Stable, Maker: Stable, U> MatchmakingAttempt<Taker, Maker, U> {
         }
         let mut balanced_takes = vec![];
         for (id, take) in takes {
-            let excess = match take.target.side() {
-                Side::Bid => &mut excess_base,
-                Side::Ask => &mut excess_quote,
-            };
+            let excess = take.target.side().select(&mut excess_base, &mut excess_quote);
             balanced_takes.push((id, take.finalized(*excess)));
             *excess = 0;
         }
@@ -736,12 +754,74 @@ where
     Taker: Stable,
     Maker: Stable,
 {
-    pub fn try_from<U>(attempt: MatchmakingAttempt<Taker, Maker, U>) -> Result<Self, Option<Vec<Taker>>>
+    /// Fills of all takers involved in the recipe.
+    pub fn fills(&self) -> Vec<Fill<Taker::StableId>>
+    where
+        Taker: MarketTaker,
+    {
+        self.instructions
+            .iter()
+            .filter_map(|i| match i {
+                Either::Left(Trans { target, result }) => Some(match result {
+                    Next::Succ(next) => Fill {
+                        order_id: target.stable_id(),
+                        side: target.side(),
+                        removed_input: target.input().saturating_sub(next.input()),
+                        added_output: next.output().saturating_sub(target.output()),
+                        fee_charged: target.fee().saturating_sub(next.fee()),
+                        terminal: false,
+                    },
+                    Next::Term(term) => Fill {
+                        order_id: target.stable_id(),
+                        side: target.side(),
+                        removed_input: target.input().saturating_sub(term.remaining_input),
+                        added_output: term.accumulated_output,
+                        fee_charged: target.fee().saturating_sub(term.remaining_fee),
+                        terminal: true,
+                    },
+                }),
+                Either::Right(_) => None,
+            })
+            .collect()
+    }
+
+    /// Swaps routed through makers involved in the recipe.
+    pub fn pool_trades(&self) -> Vec<PoolTrade<Maker::StableId>>
+    where
+        Maker: MarketMaker,
+    {
+        self.instructions
+            .iter()
+            .filter_map(|i| {
+                let make = i.as_ref().right()?;
+                let input = make.gain()?;
+                Some(PoolTrade {
+                    pool: make.target.stable_id(),
+                    input,
+                    output: make.loss().map(|l| l.unwrap()).unwrap_or(0),
+                    lp_fee: make.target.lp_fee(input),
+                })
+            })
+            .collect()
+    }
+
+    /// IDs of all makers involved in the recipe.
+    pub fn maker_ids(&self) -> Vec<Maker::StableId> {
+        self.instructions
+            .iter()
+            .filter_map(|i| i.as_ref().right().map(|make| make.target.stable_id()))
+            .collect()
+    }
+
+    pub fn try_from<U>(
+        attempt: MatchmakingAttempt<Taker, Maker, U>,
+        min_fills: usize,
+    ) -> Result<Self, Option<Vec<Taker>>>
     where
         Maker: MarketMaker + MakerBehavior + Copy,
         Taker: MarketTaker + TakerBehaviour + Copy,
     {
-        if attempt.is_complete() {
+        if attempt.is_complete(min_fills) {
             if let Some(final_recipe) = attempt.finalized() {
                 let unsatisfied_fragments = final_recipe.unsatisfied_fragments();
                 return if unsatisfied_fragments.is_empty() {
@@ -770,10 +850,12 @@ pub type Execution<T, M, B> = Either<Take<T, B>, Make<M, B>>;
 pub struct ExecutionRecipe<Taker, Maker, B>(pub Vec<Execution<Taker, Maker, B>>);
 
 impl<T, M, B> ExecutionRecipe<T, M, B> {
+    /// Attach bearers to targets of the recipe.
+    /// Fails with ID of the first target [link] cannot resolve.
     pub fn link<I, F, V>(
         MatchmakingRecipe { instructions }: MatchmakingRecipe<T, M>,
         link: F,
-    ) -> Result<(Self, HashSet<V>), ()>
+    ) -> Result<(Self, HashSet<V>), I>
     where
         V: Hash + Eq,
         T: Stable<StableId = I>,
@@ -785,31 +867,80 @@ impl<T, M, B> ExecutionRecipe<T, M, B> {
         for i in instructions {
             match i {
                 Either::Left(Trans { target, result }) => {
-                    if let Some((ver, bearer)) = link(target.stable_id()) {
-                        consumed_versions.push(ver);
-                        translated_instructions.push(Either::Left(Trans {
-                            target: Bundled(target, bearer),
-                            result,
-                        }));
-                        continue;
-                    }
+                    let (ver, bearer) = link(target.stable_id()).ok_or_else(|| target.stable_id())?;
+                    consumed_versions.push(ver);
+                    translated_instructions.push(Either::Left(Trans {
+                        target: Bundled(target, bearer),
+                        result,
+                    }));
                 }
                 Either::Right(Trans { target, result }) => {
-                    if let Some((ver, bearer)) = link(target.stable_id()) {
-                        consumed_versions.push(ver);
-                        translated_instructions.push(Either::Right(Trans {
-                            target: Bundled(target, bearer),
-                            result,
-                        }));
-                        continue;
-                    }
+                    let (ver, bearer) = link(target.stable_id()).ok_or_else(|| target.stable_id())?;
+                    consumed_versions.push(ver);
+                    translated_instructions.push(Either::Right(Trans {
+                        target: Bundled(target, bearer),
+                        result,
+                    }));
                 }
             }
-            return Err(());
         }
         Ok((
             Self(translated_instructions),
             HashSet::from_iter(consumed_versions),
         ))
     }
+
+    /// Bearers consumed by the recipe.
+    pub fn bearers(&self) -> Vec<B>
+    where
+        B: Clone,
+    {
+        self.0
+            .iter()
+            .map(|i| match i {
+                Either::Left(Trans { target, .. }) => target.1.clone(),
+                Either::Right(Trans { target, .. }) => target.1.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use either::Either;
+
+    use crate::execution_engine::liquidity_book::core::{ExecutionRecipe, MatchmakingRecipe, Next, Trans};
+    use crate::execution_engine::liquidity_book::side::Side::Ask;
+    use crate::execution_engine::liquidity_book::state::tests::{SimpleCFMMPool, SimpleOrderPF};
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+    use crate::execution_engine::types::StableId;
+
+    #[test]
+    fn link_reports_unresolved_target() {
+        let order = SimpleOrderPF::new(Ask, 20000, AbsolutePrice::new_unsafe(36, 100), 1000);
+        let pool = SimpleCFMMPool {
+            pool_id: StableId::random(),
+            reserves_base: 1000000,
+            reserves_quote: 370000,
+            fee_num: 997,
+        };
+        let order_id = order.source;
+        let pool_id = pool.pool_id;
+        let recipe = MatchmakingRecipe::<SimpleOrderPF, SimpleCFMMPool> {
+            instructions: vec![
+                Either::Left(Trans {
+                    target: order,
+                    result: Next::Succ(order),
+                }),
+                Either::Right(Trans {
+                    target: pool,
+                    result: Next::Succ(pool),
+                }),
+            ],
+        };
+        let linked = ExecutionRecipe::link(recipe.clone(), |id| (id == order_id).then_some((id, ())));
+        assert_eq!(linked.err(), Some(pool_id));
+        let linked = ExecutionRecipe::link(recipe, |id| Some((id, ())));
+        assert!(linked.is_ok());
+    }
 }
@@ -0,0 +1,504 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use log::trace;
+use rocksdb::{Direction, IteratorMode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain::data::{Has, Stable};
+use spectrum_offchain::journal::{compact_pruned, estimated_db_size, open_journal, Journal};
+use spectrum_offchain::maker::Maker;
+use spectrum_offchain::rocks::{migrate, Migration};
+
+use crate::execution_engine::liquidity_book::core::MatchmakingRecipe;
+use crate::execution_engine::liquidity_book::{ExternalTLBEvents, TLBFeedback, TemporalLiquidityBook};
+
+/// External event applied to the book of a single pair.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TLBEvent<T, M> {
+    ClocksAdvanced(u64),
+    TakerUpdated(T),
+    TakerRemoved(T),
+    MakerUpdated(M),
+    MakerRemoved(M),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry<T, M> {
+    pub seq: u64,
+    /// UNIX time (seconds) the event was recorded at.
+    pub recorded_at: u64,
+    pub event: TLBEvent<T, M>,
+}
+
+/// State of the book materialized from a prefix of the log.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct BookSnapshot<T, M> {
+    /// Number of events folded into the snapshot.
+    pub next_seq: u64,
+    /// When the last folded event was recorded.
+    pub recorded_at: u64,
+    /// Book clocks.
+    pub time: u64,
+    pub takers: Vec<T>,
+    pub makers: Vec<M>,
+}
+
+impl<T, M> BookSnapshot<T, M> {
+    pub fn empty() -> Self {
+        Self {
+            next_seq: 0,
+            recorded_at: 0,
+            time: 0,
+            takers: vec![],
+            makers: vec![],
+        }
+    }
+}
+
+impl<T, M> BookSnapshot<T, M>
+where
+    T: Stable,
+    M: Stable,
+{
+    pub fn apply(&mut self, entry: LogEntry<T, M>) {
+        self.next_seq = entry.seq + 1;
+        self.recorded_at = entry.recorded_at;
+        match entry.event {
+            TLBEvent::ClocksAdvanced(time) => self.time = time,
+            TLBEvent::TakerUpdated(taker) => {
+                self.takers.retain(|t| t.stable_id() != taker.stable_id());
+                self.takers.push(taker);
+            }
+            TLBEvent::TakerRemoved(taker) => self.takers.retain(|t| t.stable_id() != taker.stable_id()),
+            TLBEvent::MakerUpdated(maker) => {
+                self.makers.retain(|m| m.stable_id() != maker.stable_id());
+                self.makers.push(maker);
+            }
+            TLBEvent::MakerRemoved(maker) => self.makers.retain(|m| m.stable_id() != maker.stable_id()),
+        }
+    }
+}
+
+/// Append-only log of external events of a single book.
+pub trait BookEventLog<T, M> {
+    fn append(&mut self, event: TLBEvent<T, M>);
+    /// Reconstruct the book as it was at the given UNIX time (seconds).
+    fn book_at(&self, time: u64) -> BookSnapshot<T, M>;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookEventLogConfig {
+    pub db_path: String,
+    /// Number of events between two consecutive snapshots.
+    pub compaction_interval: u64,
+}
+
+const EVENT: u8 = 0;
+const SNAPSHOT: u8 = 1;
+
+/// Migrations of log entries and snapshots.
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+/// Events are stored under `scope ++ EVENT ++ seq`, snapshots under `scope ++ SNAPSHOT ++ seq`,
+/// where `scope` identifies the pair. Events are never pruned, snapshots only shorten replays.
+pub struct BookEventLogRocksDB<T, M> {
+    db: Arc<rocksdb::DB>,
+    scope: Vec<u8>,
+    compaction_interval: u64,
+    next_seq: u64,
+    /// Events are recorded at time read from it.
+    clock: SharedClock,
+    pd: PhantomData<(T, M)>,
+}
+
+impl<T, M> Clone for BookEventLogRocksDB<T, M> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+            scope: self.scope.clone(),
+            compaction_interval: self.compaction_interval,
+            next_seq: self.next_seq,
+            clock: self.clock.clone(),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<T, M> BookEventLogRocksDB<T, M> {
+    pub fn new(conf: BookEventLogConfig, clock: SharedClock) -> Self {
+        let db = open_journal(conf.db_path);
+        migrate(&db, "book_event_log", MIGRATIONS);
+        Self {
+            db: Arc::new(db),
+            scope: vec![],
+            compaction_interval: conf.compaction_interval.max(1),
+            next_seq: 0,
+            clock,
+            pd: PhantomData,
+        }
+    }
+
+    /// Log of the given pair sharing the same underlying storage.
+    pub fn scoped<Pair: Serialize>(&self, pair: &Pair) -> Self {
+        let pair_bytes = bincode::serialize(pair).unwrap();
+        let mut scope = (pair_bytes.len() as u32).to_be_bytes().to_vec();
+        scope.extend(pair_bytes);
+        let mut log = Self {
+            scope,
+            ..self.clone()
+        };
+        log.next_seq = log
+            .iter_scoped(EVENT, u64::MAX, Direction::Reverse)
+            .next()
+            .map(|(seq, _)| seq + 1)
+            .unwrap_or(0);
+        log
+    }
+
+    fn key(&self, kind: u8, seq: u64) -> Vec<u8> {
+        let mut key = self.scope.clone();
+        key.push(kind);
+        key.extend(seq.to_be_bytes());
+        key
+    }
+
+    /// Records of the given kind starting from `seq` in the given direction.
+    fn iter_scoped(
+        &self,
+        kind: u8,
+        seq: u64,
+        direction: Direction,
+    ) -> impl Iterator<Item = (u64, Box<[u8]>)> + '_ {
+        let prefix_len = self.scope.len() + 1;
+        let from = self.key(kind, seq);
+        let prefix = from[..prefix_len].to_vec();
+        self.db
+            .iterator(IteratorMode::From(&from, direction))
+            .map(|item| item.unwrap())
+            .take_while(move |(key, _)| key.starts_with(&prefix))
+            .map(move |(key, value)| {
+                let seq = u64::from_be_bytes(key[prefix_len..].try_into().unwrap());
+                (seq, value)
+            })
+    }
+}
+
+impl<T, M> BookEventLogRocksDB<T, M>
+where
+    T: Stable + Serialize + DeserializeOwned,
+    M: Stable + Serialize + DeserializeOwned,
+{
+    fn append_at(&mut self, event: TLBEvent<T, M>, recorded_at: u64) {
+        let seq = self.next_seq;
+        let entry = LogEntry {
+            seq,
+            recorded_at,
+            event,
+        };
+        self.db
+            .put(self.key(EVENT, seq), bincode::serialize(&entry).unwrap())
+            .unwrap();
+        self.next_seq += 1;
+        if self.next_seq % self.compaction_interval == 0 {
+            self.compact();
+        }
+    }
+
+    /// Fold all events recorded so far into a snapshot.
+    fn compact(&mut self) {
+        let snapshot = self.book_at(u64::MAX);
+        trace!("Compacting book log at seq {}", snapshot.next_seq);
+        self.db
+            .put(
+                self.key(SNAPSHOT, snapshot.next_seq),
+                bincode::serialize(&snapshot).unwrap(),
+            )
+            .unwrap();
+    }
+}
+
+impl<T, M> BookEventLog<T, M> for BookEventLogRocksDB<T, M>
+where
+    T: Stable + Serialize + DeserializeOwned,
+    M: Stable + Serialize + DeserializeOwned,
+{
+    fn append(&mut self, event: TLBEvent<T, M>) {
+        let now = self.clock.unix_time_secs();
+        self.append_at(event, now)
+    }
+
+    fn book_at(&self, time: u64) -> BookSnapshot<T, M> {
+        let mut book = self
+            .iter_scoped(SNAPSHOT, u64::MAX, Direction::Reverse)
+            .filter_map(|(_, bytes)| bincode::deserialize::<BookSnapshot<T, M>>(&bytes).ok())
+            .find(|snapshot| snapshot.recorded_at <= time)
+            .unwrap_or_else(BookSnapshot::empty);
+        let entries = self
+            .iter_scoped(EVENT, book.next_seq, Direction::Forward)
+            .filter_map(|(_, bytes)| bincode::deserialize::<LogEntry<T, M>>(&bytes).ok())
+            .take_while(|entry| entry.recorded_at <= time)
+            .collect::<Vec<_>>();
+        for entry in entries {
+            book.apply(entry);
+        }
+        book
+    }
+}
+
+/// Pruning keeps the latest snapshot taken before the cutoff, so the book can still be
+/// reconstructed at any moment after it. Size is that of the storage shared by all pairs.
+impl<T, M> Journal for BookEventLogRocksDB<T, M>
+where
+    T: Stable + Serialize + DeserializeOwned,
+    M: Stable + Serialize + DeserializeOwned,
+{
+    type Entry = LogEntry<T, M>;
+
+    fn entries_between(&self, from: u64, to: u64) -> Vec<LogEntry<T, M>> {
+        self.iter_scoped(EVENT, 0, Direction::Forward)
+            .filter_map(|(_, bytes)| bincode::deserialize::<LogEntry<T, M>>(&bytes).ok())
+            .skip_while(|entry| entry.recorded_at < from)
+            .take_while(|entry| entry.recorded_at < to)
+            .collect()
+    }
+
+    fn oldest_entry_at(&self) -> Option<u64> {
+        self.iter_scoped(EVENT, 0, Direction::Forward)
+            .find_map(|(_, bytes)| bincode::deserialize::<LogEntry<T, M>>(&bytes).ok())
+            .map(|entry| entry.recorded_at)
+    }
+
+    fn prune_before(&self, time: u64) {
+        let Some(snapshot) = self
+            .iter_scoped(SNAPSHOT, u64::MAX, Direction::Reverse)
+            .filter_map(|(_, bytes)| bincode::deserialize::<BookSnapshot<T, M>>(&bytes).ok())
+            .find(|snapshot| snapshot.recorded_at < time)
+        else {
+            return;
+        };
+        trace!("Pruning book log up to seq {}", snapshot.next_seq);
+        for kind in [EVENT, SNAPSHOT] {
+            let from = self.key(kind, 0);
+            let to = self.key(kind, snapshot.next_seq);
+            self.db.delete_range(&from, &to).unwrap();
+            compact_pruned(&self.db, Some(&from), Some(&to));
+        }
+    }
+
+    fn estimated_size(&self) -> u64 {
+        estimated_db_size(&self.db)
+    }
+}
+
+/// Book which records every external event into the log before applying it.
+#[derive(Debug, Clone)]
+pub struct Recorded<Book, Log> {
+    book: Book,
+    log: Log,
+}
+
+impl<Book, Log> Recorded<Book, Log> {
+    pub fn new(book: Book, log: Log) -> Self {
+        Self { book, log }
+    }
+}
+
+impl<T, M, Book, Log> ExternalTLBEvents<T, M> for Recorded<Book, Log>
+where
+    T: Clone,
+    M: Clone,
+    Book: ExternalTLBEvents<T, M>,
+    Log: BookEventLog<T, M>,
+{
+    fn advance_clocks(&mut self, new_time: u64) {
+        self.log.append(TLBEvent::ClocksAdvanced(new_time));
+        self.book.advance_clocks(new_time)
+    }
+
+    fn update_taker(&mut self, fr: T) {
+        self.log.append(TLBEvent::TakerUpdated(fr.clone()));
+        self.book.update_taker(fr)
+    }
+
+    fn remove_taker(&mut self, fr: T) {
+        self.log.append(TLBEvent::TakerRemoved(fr.clone()));
+        self.book.remove_taker(fr)
+    }
+
+    fn update_maker(&mut self, pool: M) {
+        self.log.append(TLBEvent::MakerUpdated(pool.clone()));
+        self.book.update_maker(pool)
+    }
+
+    fn remove_maker(&mut self, pool: M) {
+        self.log.append(TLBEvent::MakerRemoved(pool.clone()));
+        self.book.remove_maker(pool)
+    }
+}
+
+impl<T, M, Book, Log> TemporalLiquidityBook<T, M> for Recorded<Book, Log>
+where
+    Book: TemporalLiquidityBook<T, M>,
+{
+    fn attempt(&mut self) -> Option<MatchmakingRecipe<T, M>> {
+        self.book.attempt()
+    }
+}
+
+impl<T, M, Book, Log> TLBFeedback<T, M> for Recorded<Book, Log>
+where
+    Book: TLBFeedback<T, M>,
+{
+    fn on_recipe_succeeded(&mut self) {
+        self.book.on_recipe_succeeded()
+    }
+
+    fn on_recipe_failed(&mut self) {
+        self.book.on_recipe_failed()
+    }
+
+    fn on_recipe_oversized(&mut self, num_instructions: usize) {
+        self.book.on_recipe_oversized(num_instructions)
+    }
+}
+
+/// Context is expected to provide the log already scoped to the pair of the book.
+impl<Book, Log, Ctx> Maker<Ctx> for Recorded<Book, Log>
+where
+    Book: Maker<Ctx>,
+    Ctx: Has<Log>,
+{
+    fn make(ctx: &Ctx) -> Self {
+        Self::new(Book::make(ctx), ctx.select::<Log>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+    use serde::{Deserialize, Serialize};
+
+    use spectrum_offchain::clock::{ManualClock, SharedClock};
+    use spectrum_offchain::data::Stable;
+    use spectrum_offchain::journal::Journal;
+
+    use crate::execution_engine::liquidity_book::event_log::{
+        BookEventLog, BookEventLogConfig, BookEventLogRocksDB, TLBEvent,
+    };
+
+    #[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+    struct Entity {
+        id: u8,
+        amount: u64,
+    }
+
+    impl Stable for Entity {
+        type StableId = u8;
+        fn stable_id(&self) -> u8 {
+            self.id
+        }
+        fn is_quasi_permanent(&self) -> bool {
+            false
+        }
+    }
+
+    fn e(id: u8, amount: u64) -> Entity {
+        Entity { id, amount }
+    }
+
+    #[test]
+    fn book_is_reconstructed_at_any_moment() {
+        let rnd = rand::thread_rng().next_u32();
+        let root = BookEventLogRocksDB::<Entity, Entity>::new(
+            BookEventLogConfig {
+                db_path: format!("./tmp/{}", rnd),
+                compaction_interval: 3,
+            },
+            SharedClock::default(),
+        );
+        let mut log = root.scoped(&"A/B");
+        let mut other_log = root.scoped(&"A/C");
+        log.append_at(TLBEvent::TakerUpdated(e(1, 100)), 10);
+        log.append_at(TLBEvent::MakerUpdated(e(2, 1000)), 10);
+        other_log.append_at(TLBEvent::TakerUpdated(e(9, 1)), 11);
+        log.append_at(TLBEvent::ClocksAdvanced(42), 20);
+        log.append_at(TLBEvent::TakerUpdated(e(1, 50)), 30);
+        log.append_at(TLBEvent::MakerUpdated(e(2, 1050)), 30);
+        log.append_at(TLBEvent::TakerRemoved(e(1, 50)), 40);
+        log.append_at(TLBEvent::MakerRemoved(e(2, 1050)), 50);
+
+        let at_15 = log.book_at(15);
+        assert_eq!(
+            (at_15.takers, at_15.makers, at_15.time),
+            (vec![e(1, 100)], vec![e(2, 1000)], 0)
+        );
+        let at_30 = log.book_at(30);
+        assert_eq!(
+            (at_30.takers, at_30.makers, at_30.time),
+            (vec![e(1, 50)], vec![e(2, 1050)], 42)
+        );
+        let at_45 = log.book_at(45);
+        assert_eq!((at_45.takers, at_45.makers), (vec![], vec![e(2, 1050)]));
+        assert_eq!(log.book_at(60).next_seq, 7);
+        assert_eq!(other_log.book_at(60).takers, vec![e(9, 1)]);
+        // Reopened log continues the sequence.
+        assert_eq!(root.scoped(&"A/B").next_seq, 7);
+    }
+
+    #[test]
+    fn pruned_book_is_reconstructed_after_cutoff() {
+        let rnd = rand::thread_rng().next_u32();
+        let root = BookEventLogRocksDB::<Entity, Entity>::new(
+            BookEventLogConfig {
+                db_path: format!("./tmp/{}", rnd),
+                compaction_interval: 3,
+            },
+            SharedClock::default(),
+        );
+        let mut log = root.scoped(&"A/B");
+        let mut other_log = root.scoped(&"A/C");
+        other_log.append_at(TLBEvent::TakerUpdated(e(9, 1)), 5);
+        log.append_at(TLBEvent::TakerUpdated(e(1, 100)), 10);
+        log.append_at(TLBEvent::MakerUpdated(e(2, 1000)), 10);
+        log.append_at(TLBEvent::ClocksAdvanced(42), 20);
+        log.append_at(TLBEvent::TakerUpdated(e(1, 50)), 30);
+        log.append_at(TLBEvent::MakerUpdated(e(2, 1050)), 30);
+        log.append_at(TLBEvent::TakerUpdated(e(3, 10)), 30);
+        log.append_at(TLBEvent::TakerRemoved(e(1, 50)), 40);
+
+        let before = log.book_at(45);
+        log.prune_before(35);
+        assert_eq!(log.oldest_entry_at(), Some(40));
+        assert_eq!(log.entries_between(0, u64::MAX).len(), 1);
+        assert_eq!(log.book_at(45), before);
+        assert_eq!(other_log.oldest_entry_at(), Some(5));
+    }
+
+    #[test]
+    fn events_are_recorded_at_clock_time() {
+        let rnd = rand::thread_rng().next_u32();
+        let clock = ManualClock::new(100);
+        let root = BookEventLogRocksDB::<Entity, Entity>::new(
+            BookEventLogConfig {
+                db_path: format!("./tmp/{}", rnd),
+                compaction_interval: 3,
+            },
+            SharedClock::new(clock.clone()),
+        );
+        let mut log = root.scoped(&"A/B");
+        log.append(TLBEvent::TakerUpdated(e(1, 100)));
+        clock.advance(50);
+        log.append(TLBEvent::TakerUpdated(e(1, 50)));
+        let recorded_at = log
+            .entries_between(0, u64::MAX)
+            .iter()
+            .map(|entry| entry.recorded_at)
+            .collect::<Vec<_>>();
+        assert_eq!(recorded_at, vec![100, 150]);
+        assert_eq!(log.book_at(120).takers, vec![e(1, 100)]);
+    }
+}
@@ -0,0 +1,424 @@
+//! Scenarios running full [TLB] matchmaking against any market maker.
+//! Makers are plugged in through [HarnessMaker], so that production pools go through the same checks
+//! as the toy pools used across this crate and mismatches between the two surface early.
+
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::AddAssign;
+
+use algebra_core::monoid::Monoid;
+
+use spectrum_offchain::data::Stable;
+
+use crate::execution_engine::liquidity_book::config::{
+    ExecutionCap, ExecutionConfig, MakerSelection, MatchingMode, RecipeLimits, SettlementPolicy, StashPolicy,
+};
+use crate::execution_engine::liquidity_book::core::{Next, TerminalTake, Unit};
+use crate::execution_engine::liquidity_book::market_maker::{MakerBehavior, MarketMaker};
+use crate::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use crate::execution_engine::liquidity_book::side::{OnSide, Side};
+use crate::execution_engine::liquidity_book::time::TimeBounds;
+use crate::execution_engine::liquidity_book::types::{AbsolutePrice, FeeAsset, InputAsset, OutputAsset};
+use crate::execution_engine::liquidity_book::{ExternalTLBEvents, TemporalLiquidityBook, TLB};
+use crate::execution_engine::notifier::Fill;
+use crate::execution_engine::pool_stats::PoolTrade;
+use crate::execution_engine::types::StableId;
+
+/// Base reserves of makers spawned by scenarios, quote reserves are derived from the desired spot price.
+const RESERVES_BASE: u64 = 1_000_000_000;
+
+/// Market maker the harness is able to spawn in an arbitrary state.
+pub trait HarnessMaker: MarketMaker + MakerBehavior + Stable + Copy + Display + Debug {
+    /// Copy of this maker under a fresh identity, holding the given reserves.
+    fn with_reserves(self, base: u64, quote: u64) -> Self;
+}
+
+/// Plain limit order charging neither operator fee nor execution budget.
+#[derive(Copy, Clone, Debug)]
+pub struct HarnessTaker<U> {
+    source: StableId,
+    side: Side,
+    input: u64,
+    output: u64,
+    price: AbsolutePrice,
+    cost_hint: U,
+}
+
+impl<U> HarnessTaker<U> {
+    pub fn new(side: Side, input: u64, price: AbsolutePrice, cost_hint: U) -> Self {
+        Self {
+            source: StableId::random(),
+            side,
+            input,
+            output: 0,
+            price,
+            cost_hint,
+        }
+    }
+}
+
+impl<U> Stable for HarnessTaker<U> {
+    type StableId = StableId;
+    fn stable_id(&self) -> Self::StableId {
+        self.source
+    }
+    fn is_quasi_permanent(&self) -> bool {
+        true
+    }
+}
+
+impl<U> Display for HarnessTaker<U> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&*format!(
+            "Ord(input={}, price={}, side={})",
+            self.input, self.price, self.side
+        ))
+    }
+}
+
+impl<U> PartialEq for HarnessTaker<U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl<U> Eq for HarnessTaker<U> {}
+
+impl<U> PartialOrd for HarnessTaker<U> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<U> Ord for HarnessTaker<U> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.price.cmp(&other.price).then(self.source.cmp(&other.source))
+    }
+}
+
+impl<U: Copy> MarketTaker for HarnessTaker<U> {
+    type U = U;
+
+    fn side(&self) -> Side {
+        self.side
+    }
+
+    fn input(&self) -> InputAsset<u64> {
+        self.input
+    }
+
+    fn output(&self) -> OutputAsset<u64> {
+        self.output
+    }
+
+    fn price(&self) -> AbsolutePrice {
+        self.price
+    }
+
+    fn operator_fee(&self, _: InputAsset<u64>) -> FeeAsset<u64> {
+        0
+    }
+
+    fn fee(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    fn budget(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    fn consumable_budget(&self) -> FeeAsset<u64> {
+        0
+    }
+
+    fn marginal_cost_hint(&self) -> U {
+        self.cost_hint
+    }
+
+    fn min_marginal_output(&self) -> OutputAsset<u64> {
+        0
+    }
+
+    fn time_bounds(&self) -> TimeBounds<u64> {
+        TimeBounds::None
+    }
+}
+
+impl<U: Copy> TakerBehaviour for HarnessTaker<U> {
+    fn with_updated_time(self, _: u64) -> Next<Self, Unit> {
+        Next::Succ(self)
+    }
+
+    fn with_applied_trade(
+        mut self,
+        removed_input: InputAsset<u64>,
+        added_output: OutputAsset<u64>,
+    ) -> Next<Self, TerminalTake> {
+        self.input -= removed_input;
+        self.output += added_output;
+        self.try_terminate()
+    }
+
+    fn with_budget_corrected(self, _: i64) -> (i64, Self) {
+        (0, self)
+    }
+
+    fn with_fee_charged(self, _: u64) -> Self {
+        self
+    }
+
+    fn with_output_added(mut self, added_output: u64) -> Self {
+        self.output += added_output;
+        self
+    }
+
+    fn try_terminate(self) -> Next<Self, TerminalTake> {
+        if self.input > 0 {
+            Next::Succ(self)
+        } else {
+            Next::Term(TerminalTake {
+                remaining_input: self.input,
+                accumulated_output: self.output,
+                remaining_budget: 0,
+                remaining_fee: 0,
+            })
+        }
+    }
+}
+
+/// Runs matchmaking scenarios against makers spawned off a template.
+pub struct Harness<M: MarketMaker> {
+    template: M,
+    conf: ExecutionConfig<M::U>,
+    taker_cost: M::U,
+}
+
+impl<M> Harness<M>
+where
+    M: HarnessMaker,
+    M::U: Monoid + AddAssign + PartialOrd + Copy,
+{
+    /// Harness spawning makers off `template` and takers costing `taker_cost` each.
+    pub fn new(template: M, execution_cap: ExecutionCap<M::U>, taker_cost: M::U) -> Self {
+        Self {
+            template,
+            conf: ExecutionConfig {
+                execution_cap,
+                o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::default(),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::Continuous,
+                settlement_policy: SettlementPolicy::default(),
+            },
+            taker_cost,
+        }
+    }
+
+    pub fn run_all(&self) {
+        self.taker_is_filled_at_maker_quote(Side::Ask);
+        self.taker_is_filled_at_maker_quote(Side::Bid);
+        self.taker_beyond_maker_price_is_left_alone();
+        self.best_maker_is_picked();
+        self.opposite_takers_are_settled_in_balance();
+    }
+
+    /// What the maker quotes for routing is exactly what the taker is paid.
+    pub fn taker_is_filled_at_maker_quote(&self, side: Side) {
+        let maker = self.maker(2, 1);
+        let (input, limit) = side.select(
+            (20_000_000, AbsolutePrice::new_unsafe(3, 1)),
+            (10_000_000, AbsolutePrice::new_unsafe(1, 1)),
+        );
+        let mut book = self.book(&[maker], &[self.taker(side, input, limit)]);
+        let recipe = book
+            .attempt()
+            .expect("Taker crossing maker price must be matched");
+        let fills = recipe.fills();
+        let trades = recipe.pool_trades();
+        assert_eq!((fills.len(), trades.len()), (1, 1));
+        let (fill, trade) = (&fills[0], &trades[0]);
+        assert!(fill.terminal);
+        assert_eq!(fill.removed_input, input);
+        assert_eq!(trade.input, side.wrap(input));
+        assert_eq!(fill.added_output, trade.output);
+        assert_eq!(fill.price(), maker.real_price(side.wrap(input)));
+        assert!(within_limit(fill, limit));
+    }
+
+    /// Takers the maker can't honour the limit of, in full, are not matched at all.
+    pub fn taker_beyond_maker_price_is_left_alone(&self) {
+        let maker = self.maker(2, 1);
+        let takers = [
+            self.taker(Side::Ask, 10_000_000, AbsolutePrice::new_unsafe(3, 1)),
+            self.taker(Side::Bid, 20_000_000, AbsolutePrice::new_unsafe(1, 1)),
+            // Selling half of the base reserves moves the price far below the limit.
+            self.taker(Side::Ask, RESERVES_BASE / 2, AbsolutePrice::new_unsafe(19, 10)),
+        ];
+        for taker in takers {
+            assert!(
+                self.book(&[maker], &[taker]).attempt().is_none(),
+                "{} must not be matched",
+                taker
+            );
+        }
+    }
+
+    /// Takers are routed to the maker quoting the best price for their side.
+    pub fn best_maker_is_picked(&self) {
+        let cheap = self.maker(2, 1);
+        let dear = self.maker(22, 10);
+        let cases = [
+            (Side::Ask, 10_000_000, AbsolutePrice::new_unsafe(1, 1), dear),
+            (Side::Bid, 20_000_000, AbsolutePrice::new_unsafe(3, 1), cheap),
+        ];
+        for (side, input, limit, best_maker) in cases {
+            let mut book = self.book(&[cheap, dear], &[self.taker(side, input, limit)]);
+            let recipe = book
+                .attempt()
+                .expect("Taker crossing maker price must be matched");
+            let trades = recipe.pool_trades();
+            assert_eq!(trades.len(), 1);
+            assert!(
+                trades[0].pool == best_maker.stable_id(),
+                "{} taker routed to {} instead of {}",
+                side,
+                trades[0].pool,
+                best_maker.stable_id()
+            );
+        }
+    }
+
+    /// Opposite takers matched against each other and the maker are never paid more than
+    /// the recipe brings in, neither in base nor in quote.
+    pub fn opposite_takers_are_settled_in_balance(&self) {
+        let maker = self.maker(2, 1);
+        let (ask_limit, bid_limit) = (AbsolutePrice::new_unsafe(3, 2), AbsolutePrice::new_unsafe(5, 2));
+        let takers = [
+            self.taker(Side::Ask, 30_000_000, ask_limit),
+            self.taker(Side::Bid, 80_000_000, bid_limit),
+        ];
+        let mut book = self.book(&[maker], &takers);
+        let recipe = book.attempt().expect("Crossing takers must be matched");
+        let fills = recipe.fills();
+        assert_eq!(fills.len(), 2);
+        for fill in &fills {
+            assert!(within_limit(fill, fill.side.select(bid_limit, ask_limit)));
+        }
+        let [base_in, base_out, quote_in, quote_out] = balance(&fills, &recipe.pool_trades());
+        assert!(base_in >= base_out, "{} base paid out of {}", base_out, base_in);
+        assert!(
+            quote_in >= quote_out,
+            "{} quote paid out of {}",
+            quote_out,
+            quote_in
+        );
+    }
+
+    /// Maker quoting `price_num / price_denom` of quote per unit of base.
+    fn maker(&self, price_num: u64, price_denom: u64) -> M {
+        self.template
+            .with_reserves(RESERVES_BASE, RESERVES_BASE * price_num / price_denom)
+    }
+
+    fn taker(&self, side: Side, input: u64, price: AbsolutePrice) -> HarnessTaker<M::U> {
+        HarnessTaker::new(side, input, price, self.taker_cost)
+    }
+
+    fn book(&self, makers: &[M], takers: &[HarnessTaker<M::U>]) -> TLB<HarnessTaker<M::U>, M, M::U> {
+        let mut book = TLB::new(0, self.conf);
+        makers.iter().for_each(|m| book.update_maker(*m));
+        takers.iter().for_each(|t| book.update_taker(*t));
+        book
+    }
+}
+
+fn within_limit<Id>(fill: &Fill<Id>, limit: AbsolutePrice) -> bool {
+    fill.price()
+        .map_or(false, |price| fill.side.select(price <= limit, price >= limit))
+}
+
+/// Base and quote entering the recipe vs. leaving it: `[base_in, base_out, quote_in, quote_out]`.
+fn balance<Id, PoolId>(fills: &[Fill<Id>], trades: &[PoolTrade<PoolId>]) -> [u64; 4] {
+    let mut acc = [0u64; 4];
+    for fill in fills {
+        let (input, output) = fill.side.select((2, 1), (0, 3));
+        acc[input] += fill.removed_input;
+        acc[output] += fill.added_output;
+    }
+    for trade in trades {
+        match trade.input {
+            OnSide::Bid(quote_input) => {
+                acc[0] += trade.output;
+                acc[3] += quote_input;
+            }
+            OnSide::Ask(base_input) => {
+                acc[1] += base_input;
+                acc[2] += trade.output;
+            }
+        }
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::config::ExecutionCap;
+    use crate::execution_engine::liquidity_book::harness::{Harness, HarnessMaker};
+    use crate::execution_engine::liquidity_book::side::Side;
+    use crate::execution_engine::liquidity_book::state::tests::SimpleCFMMPool;
+    use crate::execution_engine::types::StableId;
+
+    impl HarnessMaker for SimpleCFMMPool {
+        fn with_reserves(self, base: u64, quote: u64) -> Self {
+            Self {
+                pool_id: StableId::random(),
+                reserves_base: base,
+                reserves_quote: quote,
+                ..self
+            }
+        }
+    }
+
+    fn harness() -> Harness<SimpleCFMMPool> {
+        let template = SimpleCFMMPool {
+            pool_id: StableId::random(),
+            reserves_base: 0,
+            reserves_quote: 0,
+            fee_num: 997,
+        };
+        let cap = ExecutionCap {
+            soft: 1000000,
+            hard: 1600000,
+            tx_size: None,
+        };
+        Harness::new(template, cap, 10)
+    }
+
+    #[test]
+    fn ask_is_filled_at_maker_quote() {
+        harness().taker_is_filled_at_maker_quote(Side::Ask);
+    }
+
+    #[test]
+    fn bid_is_filled_at_maker_quote() {
+        harness().taker_is_filled_at_maker_quote(Side::Bid);
+    }
+
+    #[test]
+    fn taker_beyond_maker_price_is_left_alone() {
+        harness().taker_beyond_maker_price_is_left_alone();
+    }
+
+    #[test]
+    fn best_maker_is_picked() {
+        harness().best_maker_is_picked();
+    }
+
+    #[test]
+    fn opposite_takers_are_settled_in_balance() {
+        harness().opposite_takers_are_settled_in_balance();
+    }
+}
@@ -18,6 +18,15 @@ pub struct ExecutionResult<Fr, Pl, V, Bearer, Txc> {
     pub funding_io: FundingIO<Bearer, Bearer>,
 }
 
+/// Why a recipe was not turned into a transaction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum RecipeRejected {
+    /// Recipe cannot be turned into a consistent transaction.
+    Inconsistent,
+    /// Transaction would exceed max size, the recipe has to be split.
+    Oversized,
+}
+
 pub trait RecipeInterpreter<Fr, Pl, Ctx, V, Bearer, Txc> {
     /// Interpret recipe [ExecutionRecipe] into a transaction candidate [Txc] and
     /// a set of new sources resulted from execution.
@@ -26,5 +35,5 @@ pub trait RecipeInterpreter<Fr, Pl, Ctx, V, Bearer, Txc> {
         recipe: ExecutionRecipe<Fr, Pl, Bearer>,
         funding: Bearer,
         ctx: Ctx,
-    ) -> ExecutionResult<Fr, Pl, V, Bearer, Txc>;
+    ) -> Result<ExecutionResult<Fr, Pl, V, Bearer, Txc>, RecipeRejected>;
 }
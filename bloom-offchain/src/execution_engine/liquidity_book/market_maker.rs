@@ -16,6 +16,18 @@ impl SpotPrice {
     }
 }
 
+/// Stage of a maker's life.
+/// A new pool is `Bootstrapping` until initial deposits bring its liquidity
+/// above the threshold, from then on it is `Active` unless its reserves fall
+/// below operational bounds, in which case it is `Paused`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Display)]
+pub enum PoolLifecycle {
+    /// Zero or dust liquidity, only deposits are accepted.
+    Bootstrapping,
+    Active,
+    Paused,
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct AbsoluteReserves {
     pub base: u64,
@@ -35,8 +47,14 @@ pub trait MarketMaker {
     fn marginal_cost_hint(&self) -> Self::U;
     /// How much base and quote asset is available.
     fn liquidity(&self) -> AbsoluteReserves;
-    /// Is this MM active at the moment or not.
-    fn is_active(&self) -> bool;
+    /// Stage of the MM's life.
+    fn lifecycle(&self) -> PoolLifecycle;
+    /// Is this MM available for matchmaking at the moment or not.
+    fn is_active(&self) -> bool {
+        self.lifecycle() == PoolLifecycle::Active
+    }
+    /// Part of the given input (in units of the input asset) LPs retain as a fee.
+    fn lp_fee(&self, input: OnSide<u64>) -> u64;
 }
 
 /// Pooled liquidity.
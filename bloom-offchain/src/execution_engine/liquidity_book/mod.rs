@@ -1,12 +1,17 @@
 use algebra_core::monoid::Monoid;
 use log::trace;
 use num_rational::Ratio;
-use primitive_types::U256;
+use primitive_types::U512;
+use std::cmp::{max, min};
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::ops::AddAssign;
 
 use crate::display::{display_option, display_tuple};
-use crate::execution_engine::liquidity_book::config::ExecutionConfig;
+use crate::execution_engine::liquidity_book::auction::BatchAuction;
+use crate::execution_engine::liquidity_book::config::{
+    ExecutionConfig, MatchingMode, SettlementPolicy, StashPolicy,
+};
 use crate::execution_engine::liquidity_book::core::{
     MakeInProgress, MatchmakingAttempt, MatchmakingRecipe, Next, TakeInProgress, Trans,
 };
@@ -17,16 +22,21 @@ use crate::execution_engine::liquidity_book::side::{OnSide, Side};
 use crate::execution_engine::liquidity_book::stashing_option::StashingOption;
 use crate::execution_engine::liquidity_book::state::queries::{max_by_distance_to_spot, max_by_volume};
 use crate::execution_engine::liquidity_book::state::{IdleState, TLBState};
-use crate::execution_engine::liquidity_book::types::{AbsolutePrice, RelativePrice};
+use crate::execution_engine::liquidity_book::types::{mul_div, AbsolutePrice, RelativePrice, Rounding};
 use crate::execution_engine::types::Time;
 use spectrum_offchain::data::{Has, Stable};
 use spectrum_offchain::maker::Maker;
 
+pub mod auction;
 pub mod config;
 pub mod core;
+pub mod event_log;
+#[cfg(any(test, feature = "harness"))]
+pub mod harness;
 pub mod interpreter;
 pub mod market_maker;
 pub mod market_taker;
+pub mod projection;
 pub mod side;
 pub mod stashing_option;
 mod state;
@@ -60,12 +70,54 @@ pub trait TLBFeedback<T, M> {
     fn on_recipe_succeeded(&mut self);
     /// Recipe failed.
     fn on_recipe_failed(&mut self);
+    /// Recipe of the given number of instructions doesn't fit into a single TX.
+    /// Its takers and makers return to the book to be matched in smaller recipes.
+    fn on_recipe_oversized(&mut self, num_instructions: usize);
 }
 
 #[derive(Clone)]
 pub struct TLB<Taker, Maker: Stable, U> {
     state: TLBState<Taker, Maker>,
     conf: ExecutionConfig<U>,
+    /// Recent failure rates of recipes involving particular makers.
+    failure_rates: HashMap<Maker::StableId, FailureRate>,
+    /// Makers involved in the recipe awaiting feedback.
+    makers_in_flight: Vec<Maker::StableId>,
+    /// Takers sitting out of matchmaking along with the number of attempts left until release.
+    stashed: Vec<(u32, Taker)>,
+    stash_stats: StashStats,
+    /// Cap on the number of instructions learned from recipes which turned out oversized.
+    learned_max_instructions: Option<usize>,
+}
+
+/// Counters of takers stashed according to [StashPolicy::Attempts].
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct StashStats {
+    /// Takers currently sitting out of matchmaking.
+    pub stashed: usize,
+    /// Takers stashed since start.
+    pub stashed_total: u64,
+    /// Takers returned to matchmaking since start.
+    pub released_total: u64,
+}
+
+/// Exponential moving average of failures, in per mille.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd)]
+struct FailureRate(u64);
+
+const FAILURE_RATE_SCALE: u64 = 1000;
+/// Weight of the latest outcome is 1/FAILURE_RATE_SMOOTHING.
+const FAILURE_RATE_SMOOTHING: u64 = 8;
+
+impl FailureRate {
+    fn record(self, failed: bool) -> Self {
+        let decayed = self.0 - self.0 / FAILURE_RATE_SMOOTHING;
+        if failed {
+            Self(decayed + FAILURE_RATE_SCALE / FAILURE_RATE_SMOOTHING)
+        } else {
+            Self(decayed)
+        }
+    }
 }
 
 impl<Taker, Maker, U> TLBFeedback<Taker, Maker> for TLB<Taker, Maker, U>
@@ -74,14 +126,33 @@ where
     Maker: MarketMaker + Stable + Copy,
 {
     fn on_recipe_succeeded(&mut self) {
+        self.park_stashed();
         self.state.commit();
+        self.record_outcome(false);
+        // Probe for larger recipes again.
+        self.learned_max_instructions = self.learned_max_instructions.map(|n| n + 1);
     }
 
     fn on_recipe_failed(&mut self) {
+        self.park_stashed();
+        self.state.rollback(StashingOption::Unstash);
+        self.record_outcome(true);
+    }
+
+    fn on_recipe_oversized(&mut self, num_instructions: usize) {
+        self.park_stashed();
         self.state.rollback(StashingOption::Unstash);
+        // Makers are not to blame for the size of the recipe.
+        self.makers_in_flight.clear();
+        let cap = max(num_instructions / 2, INSTRUCTIONS_PER_MATCH);
+        trace!(target: "tlb", "Recipe of {} instructions is oversized, capping at {}", num_instructions, cap);
+        self.learned_max_instructions = Some(cap);
     }
 }
 
+/// A single match adds at most this many instructions to the recipe.
+const INSTRUCTIONS_PER_MATCH: usize = 2;
+
 impl<Taker, Maker, U> TLB<Taker, Maker, U>
 where
     Maker: Stable,
@@ -90,9 +161,39 @@ where
         Self {
             state: TLBState::new(time),
             conf,
+            failure_rates: HashMap::new(),
+            makers_in_flight: Vec::new(),
+            stashed: Vec::new(),
+            stash_stats: StashStats::default(),
+            learned_max_instructions: None,
+        }
+    }
+
+    pub fn stash_stats(&self) -> StashStats {
+        StashStats {
+            stashed: self.stashed.len(),
+            ..self.stash_stats
+        }
+    }
+
+    fn record_outcome(&mut self, failed: bool) {
+        for maker_id in self.makers_in_flight.drain(..) {
+            let rate = self.failure_rates.entry(maker_id).or_default();
+            *rate = rate.record(failed);
         }
     }
 
+    /// Max number of instructions a recipe may consist of to fit into a single TX.
+    fn max_instructions(&self) -> usize {
+        let estimated = self
+            .conf
+            .execution_cap
+            .tx_size
+            .map_or(usize::MAX, |cap| cap.max_instructions());
+        self.learned_max_instructions
+            .map_or(estimated, |learned| min(learned, estimated))
+    }
+
     fn spot_price(&self) -> Option<SpotPrice>
     where
         Taker: MarketTaker,
@@ -102,6 +203,54 @@ where
     }
 }
 
+impl<Taker, Maker, U> TLB<Taker, Maker, U>
+where
+    Taker: MarketTaker + Ord + Copy,
+    Maker: Stable + Copy,
+{
+    /// Move takers stashed in the current state out of the state so that they
+    /// sit out of matchmaking for the number of attempts set by the policy.
+    fn park_stashed(&mut self) {
+        if let StashPolicy::Attempts(ttl) = self.conf.stash_policy {
+            for taker in self.state.take_stash() {
+                self.stash_stats.stashed_total += 1;
+                self.stashed.push((ttl, taker));
+            }
+        }
+    }
+}
+
+impl<Taker, Maker, U> TLB<Taker, Maker, U>
+where
+    Taker: MarketTaker + TakerBehaviour + Ord + Copy + Display,
+    Maker: Stable,
+{
+    /// Return takers whose stash TTL expired to matchmaking.
+    fn sweep_stashed(&mut self) {
+        if self.stashed.is_empty() {
+            return;
+        }
+        let TLBState::Idle(ref mut st) = self.state else {
+            return;
+        };
+        let mut released = 0;
+        self.stashed.retain_mut(|(ttl, taker)| {
+            if *ttl == 0 {
+                st.add_fragment(*taker);
+                released += 1;
+                false
+            } else {
+                *ttl -= 1;
+                true
+            }
+        });
+        if released > 0 {
+            self.stash_stats.released_total += released;
+            trace!(target: "tlb", "Released {} stashed takers, {} remain", released, self.stashed.len());
+        }
+    }
+}
+
 impl<Taker, Maker, U> TLB<Taker, Maker, U>
 where
     Taker: MarketTaker<U = U> + Ord + Copy + Display,
@@ -128,10 +277,13 @@ where
     U: Monoid + AddAssign + PartialOrd + Copy,
 {
     fn attempt(&mut self) -> Option<MatchmakingRecipe<Taker, Maker>> {
+        self.sweep_stashed();
         loop {
             trace!("Attempting to matchmake");
             let mut batch: MatchmakingAttempt<Taker, Maker, U> = MatchmakingAttempt::empty();
-            while batch.execution_units_consumed() < self.conf.execution_cap.soft {
+            while batch.execution_units_consumed() < self.conf.execution_cap.soft
+                && batch.num_instructions() + INSTRUCTIONS_PER_MATCH <= self.max_instructions()
+            {
                 let spot_price = self.spot_price();
                 let price_range = self.state.allowed_price_range();
                 trace!("Spot price is: {}", display_option(spot_price));
@@ -146,24 +298,54 @@ where
                     let target_price = target_side.wrap(target_taker.price());
                     let maybe_price_counter_taker = self.state.best_taker_price(!target_side);
                     let chunk_offered = batch.next_offered_chunk(&target_taker);
-                    let maybe_price_maker = self.state.preselect_market_maker(chunk_offered);
+                    let max_reserves_share = self.conf.maker_selection.max_reserves_share;
+                    let failure_rates = &self.failure_rates;
+                    let maybe_price_maker = self.state.preselect_market_maker(
+                        chunk_offered,
+                        self.conf.maker_selection.price_tolerance,
+                        |maker| {
+                            let headroom = max_reserves_share
+                                .map(|share| {
+                                    reserves_headroom(
+                                        batch.initial_maker_state(maker),
+                                        maker,
+                                        chunk_offered,
+                                        share,
+                                    )
+                                })
+                                .unwrap_or(u64::MAX);
+                            (headroom > 0)
+                                .then(|| failure_rates.get(&maker.stable_id()).map(|r| r.0).unwrap_or(0))
+                        },
+                    );
                     trace!(
                         "P_target: {}, P_counter: {}, P_amm: {}",
                         target_price.unwrap(),
                         display_option(maybe_price_counter_taker),
                         display_option(maybe_price_maker.map(display_tuple))
                     );
+                    let limits = self.conf.recipe_limits;
+                    let num_fills = batch.num_fills() + !batch.fills(&target_taker.stable_id()) as usize;
+                    if !limits.admits(num_fills, batch.num_swaps()) {
+                        trace!("Recipe reached max number of fills");
+                        self.state.pre_add_taker(target_taker);
+                        break;
+                    }
                     match (maybe_price_counter_taker, maybe_price_maker) {
                         (Some(price_counter_taker), maybe_price_maker)
                             if self.conf.o2o_allowed
+                                // Counter taker is assumed to be a new fill.
+                                && limits.admits(num_fills + 1, batch.num_swaps())
                                 && target_price.overlaps(price_counter_taker.unwrap())
                                 && maybe_price_maker
                                     .map(|(_, p)| price_counter_taker.better_than(p))
                                     .unwrap_or(true) =>
                         {
                             if let Some(counter_taker) = self.state.try_pick_taker(!target_side, ok) {
-                                let make_match =
-                                    |ask: &Taker, bid: &Taker| settle_price(ask, bid, spot_price);
+                                let policy = self.conf.settlement_policy;
+                                let make_match = |ask: &Taker, bid: &Taker| {
+                                    settle_price(ask, bid, spot_price, policy, target_side)
+                                };
                                 let (take_a, take_b) =
                                     execute_with_taker(target_taker, counter_taker, make_match);
                                 trace!("Taker {} matched with {}", target_taker, counter_taker);
@@ -175,14 +357,44 @@ where
                             }
                         }
                         (_, Some((maker_sid, price_maker))) if target_price.overlaps(price_maker) => {
-                            if let Some(maker) = self.state.pick_maker_by_id(&maker_sid) {
-                                trace!("Taker {} matched with {}", target_taker, maker);
-                                let (take, make) = execute_with_maker(target_taker, maker, chunk_offered);
-                                batch.add_make(make);
-                                batch.add_take(take);
-                                self.on_take(take.result);
-                                self.on_make(make.result);
-                                continue;
+                            let num_swaps = batch.num_swaps() + !batch.swaps(&maker_sid) as usize;
+                            if !limits.admits(num_fills, num_swaps) {
+                                trace!("Recipe reached max number of swaps");
+                                self.state.pre_add_taker(target_taker);
+                            } else if let Some(maker) = self.state.pick_maker_by_id(&maker_sid) {
+                                // Route what exceeds the maker's share to other makers.
+                                let chunk_offered = match max_reserves_share {
+                                    Some(share) => chunk_offered.map(|c| {
+                                        c.min(reserves_headroom(
+                                            batch.initial_maker_state(&maker),
+                                            &maker,
+                                            chunk_offered,
+                                            share,
+                                        ))
+                                    }),
+                                    None => chunk_offered,
+                                };
+                                let chunk = match self.conf.max_price_impact {
+                                    Some(max_impact) => fit_price_impact(
+                                        batch.initial_maker_state(&maker),
+                                        maker,
+                                        chunk_offered,
+                                        max_impact,
+                                    ),
+                                    None => Some(chunk_offered),
+                                };
+                                if let Some(chunk) = chunk {
+                                    trace!("Taker {} matched with {}", target_taker, maker);
+                                    let (take, make) = execute_with_maker(target_taker, maker, chunk);
+                                    batch.add_make(make);
+                                    batch.add_take(take);
+                                    self.on_take(take.result);
+                                    self.on_make(make.result);
+                                    continue;
+                                }
+                                trace!("Taker {} would move price in {} too far", target_taker, maker);
+                                self.state.pre_add_taker(target_taker);
+                                self.state.pre_add_maker(maker);
                             }
                         }
                         _ => {}
@@ -190,14 +402,19 @@ where
                 }
                 break;
             }
+            if self.conf.net_opposite_flows {
+                self.net_opposite_flows(&mut batch);
+            }
             trace!("Raw batch: {}", batch);
-            match MatchmakingRecipe::try_from(batch) {
+            match MatchmakingRecipe::try_from(batch, self.conf.recipe_limits.min_fills) {
                 Ok(ex_recipe) => {
                     trace!("Successfully formed a batch {}", ex_recipe);
+                    self.makers_in_flight = ex_recipe.maker_ids();
                     return Some(ex_recipe);
                 }
                 Err(None) => {
                     trace!("Matchmaking attempt failed");
+                    self.park_stashed();
                     self.state.rollback(StashingOption::Unstash);
                 }
                 Err(Some(unsatisfied_takers)) => {
@@ -211,6 +428,122 @@ where
     }
 }
 
+impl<Taker, Maker, U> TLB<Taker, Maker, U>
+where
+    Taker: Stable + MarketTaker<U = U> + TakerBehaviour + Ord + Copy + Display,
+    Maker: Stable + MarketMaker<U = U> + MakerBehavior + Copy + Display,
+    U: Monoid + AddAssign + PartialOrd + Copy,
+{
+    /// Match takers opposite to the flow routed through each maker of the batch against that maker.
+    /// Such a match adds a single take while shrinking the aggregated swap, so it saves the fees and
+    /// price impact a separate recipe pushing the maker back would incur. Hence it is admitted past
+    /// the soft execution cap, as long as the hard cap and TX size allow.
+    /// Takers never offer more than the maker pays out, so the swap shrinks but is never reversed.
+    fn net_opposite_flows(&mut self, batch: &mut MatchmakingAttempt<Taker, Maker, U>) {
+        let hard_cap = self.conf.execution_cap.hard;
+        for maker_sid in batch.maker_ids() {
+            while batch.num_instructions() < self.max_instructions() {
+                let Some(payout) = batch.maker_payout(&maker_sid).filter(|p| p.unwrap() > 0) else {
+                    break;
+                };
+                let counter_side = !payout.marker();
+                let Some(maker) = self.state.pick_maker_by_id(&maker_sid) else {
+                    break;
+                };
+                let limits = self.conf.recipe_limits;
+                let units_consumed = batch.execution_units_consumed();
+                // Only fresh takers are netted, so that every match adds an instruction.
+                let counter_taker = self.state.try_pick_taker(counter_side, |taker| {
+                    let mut units = units_consumed;
+                    units += taker.marginal_cost_hint();
+                    let chunk = batch.next_offered_chunk(taker).map(|c| min(c, payout.unwrap()));
+                    !batch.fills(&taker.stable_id())
+                        && limits.admits(batch.num_fills() + 1, batch.num_swaps())
+                        && units <= hard_cap
+                        && maker
+                            .real_price(chunk)
+                            .map_or(false, |p| counter_side.wrap(taker.price()).overlaps(p))
+                });
+                match counter_taker {
+                    Some(taker) => {
+                        let chunk = batch.next_offered_chunk(&taker).map(|c| min(c, payout.unwrap()));
+                        trace!("Taker {} netted against flow through {}", taker, maker);
+                        let (take, make) = execute_with_maker(taker, maker, chunk);
+                        batch.add_make(make);
+                        batch.add_take(take);
+                        self.on_take(take.result);
+                        self.on_make(make.result);
+                    }
+                    None => {
+                        self.state.pre_add_maker(maker);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Shrink the chunk until the spot price of the maker moves no further than by
+/// [max_impact] relatively to its state [initial] before the recipe.
+fn fit_price_impact<Maker>(
+    initial: &Maker,
+    maker: Maker,
+    chunk: OnSide<u64>,
+    max_impact: Ratio<u128>,
+) -> Option<OnSide<u64>>
+where
+    Maker: MarketMaker + MakerBehavior + Copy,
+{
+    let initial_price = initial.static_price();
+    let mut chunk = chunk;
+    while chunk.unwrap() > 0 {
+        if let Next::Succ(next_maker) = maker.swap(chunk) {
+            if price_impact_within(initial_price, next_maker.static_price(), max_impact) {
+                return Some(chunk);
+            }
+        }
+        chunk = chunk.map(|c| c / 2);
+    }
+    None
+}
+
+/// How much more input the maker may take within this recipe so that the total amount swapped
+/// into it stays within [max_share] of its reserves before the recipe.
+fn reserves_headroom<Maker: MarketMaker>(
+    initial: &Maker,
+    maker: &Maker,
+    chunk: OnSide<u64>,
+    max_share: Ratio<u128>,
+) -> u64 {
+    let (initial_reserves, reserves) = (initial.liquidity(), maker.liquidity());
+    let (initial_in, current_in) = match chunk {
+        Ask(_) => (initial_reserves.base, reserves.base),
+        Bid(_) => (initial_reserves.quote, reserves.quote),
+    };
+    let cap = (max_share * initial_in as u128)
+        .to_integer()
+        .min(u64::MAX as u128) as u64;
+    cap.saturating_sub(current_in.saturating_sub(initial_in))
+}
+
+fn price_impact_within(p0: SpotPrice, p1: SpotPrice, max_impact: Ratio<u128>) -> bool {
+    relative_distance_within(p0.unwrap(), p1.unwrap(), max_impact)
+}
+
+/// Checks |p1 - p0| / p0 <= max_distance without risking an overflow.
+fn relative_distance_within(p0: Ratio<u128>, p1: Ratio<u128>, max_distance: Ratio<u128>) -> bool {
+    let (n0, d0) = (U512::from(*p0.numer()), U512::from(*p0.denom()));
+    let (n1, d1) = (U512::from(*p1.numer()), U512::from(*p1.denom()));
+    let (max_n, max_d) = (
+        U512::from(*max_distance.numer()),
+        U512::from(*max_distance.denom()),
+    );
+    let (x, y) = (n1 * d0, n0 * d1);
+    let shift = if x > y { x - y } else { y - x };
+    shift * max_d <= max_n * d1 * n0
+}
+
 fn execute_with_maker<Taker, Maker>(
     target_taker: Taker,
     maker: Maker,
@@ -237,10 +570,9 @@ where
     Taker: MarketTaker + TakerBehaviour + Copy,
     F: FnOnce(&Taker, &Taker) -> AbsolutePrice,
 {
-    let (ask, bid) = match target_taker.side() {
-        Side::Ask => (target_taker, counter_taker),
-        Side::Bid => (counter_taker, target_taker),
-    };
+    let (ask, bid) = target_taker
+        .side()
+        .select((counter_taker, target_taker), (target_taker, counter_taker));
     let price = matchmaker(&ask, &bid);
     let quote_input = bid.input();
     let demand_base = linear_output_unsafe(quote_input, Bid(price));
@@ -272,6 +604,112 @@ where
     }
 }
 
+/// Liquidity book of a pair matching takers in the configured [MatchingMode].
+#[derive(Clone)]
+pub enum AnyBook<Taker, Maker: Stable, U> {
+    Continuous(TLB<Taker, Maker, U>),
+    BatchAuction(BatchAuction<Taker, Maker, U>),
+}
+
+impl<Fr, Pl, Ctx, U> Maker<Ctx> for AnyBook<Fr, Pl, U>
+where
+    Pl: Stable,
+    Ctx: Has<Time> + Has<ExecutionConfig<U>>,
+{
+    fn make(ctx: &Ctx) -> Self {
+        let time = ctx.select::<Time>().into();
+        let conf = ctx.select::<ExecutionConfig<U>>();
+        match conf.matching_mode {
+            MatchingMode::Continuous => AnyBook::Continuous(TLB::new(time, conf)),
+            MatchingMode::BatchAuction { window_secs } => {
+                AnyBook::BatchAuction(BatchAuction::new(time, conf, window_secs))
+            }
+        }
+    }
+}
+
+impl<Taker, Maker, U> TemporalLiquidityBook<Taker, Maker> for AnyBook<Taker, Maker, U>
+where
+    Taker: Stable + MarketTaker<U = U> + TakerBehaviour + Ord + Copy + Display,
+    Maker: Stable + MarketMaker<U = U> + MakerBehavior + Copy + Display,
+    U: Monoid + AddAssign + PartialOrd + Copy,
+{
+    fn attempt(&mut self) -> Option<MatchmakingRecipe<Taker, Maker>> {
+        match self {
+            AnyBook::Continuous(book) => book.attempt(),
+            AnyBook::BatchAuction(book) => book.attempt(),
+        }
+    }
+}
+
+impl<Fr, Pl, U> ExternalTLBEvents<Fr, Pl> for AnyBook<Fr, Pl, U>
+where
+    Fr: MarketTaker + TakerBehaviour + Ord + Copy + Display,
+    Pl: MarketMaker + Stable + Copy + Display + Debug,
+{
+    fn advance_clocks(&mut self, new_time: u64) {
+        match self {
+            AnyBook::Continuous(book) => book.advance_clocks(new_time),
+            AnyBook::BatchAuction(book) => book.advance_clocks(new_time),
+        }
+    }
+
+    fn update_taker(&mut self, fr: Fr) {
+        match self {
+            AnyBook::Continuous(book) => book.update_taker(fr),
+            AnyBook::BatchAuction(book) => book.update_taker(fr),
+        }
+    }
+
+    fn remove_taker(&mut self, fr: Fr) {
+        match self {
+            AnyBook::Continuous(book) => book.remove_taker(fr),
+            AnyBook::BatchAuction(book) => book.remove_taker(fr),
+        }
+    }
+
+    fn update_maker(&mut self, pool: Pl) {
+        match self {
+            AnyBook::Continuous(book) => book.update_maker(pool),
+            AnyBook::BatchAuction(book) => book.update_maker(pool),
+        }
+    }
+
+    fn remove_maker(&mut self, pool: Pl) {
+        match self {
+            AnyBook::Continuous(book) => book.remove_maker(pool),
+            AnyBook::BatchAuction(book) => book.remove_maker(pool),
+        }
+    }
+}
+
+impl<Taker, Maker, U> TLBFeedback<Taker, Maker> for AnyBook<Taker, Maker, U>
+where
+    Taker: MarketTaker + Ord + Copy,
+    Maker: MarketMaker + Stable + Copy,
+{
+    fn on_recipe_succeeded(&mut self) {
+        match self {
+            AnyBook::Continuous(book) => book.on_recipe_succeeded(),
+            AnyBook::BatchAuction(book) => book.on_recipe_succeeded(),
+        }
+    }
+
+    fn on_recipe_failed(&mut self) {
+        match self {
+            AnyBook::Continuous(book) => book.on_recipe_failed(),
+            AnyBook::BatchAuction(book) => book.on_recipe_failed(),
+        }
+    }
+
+    fn on_recipe_oversized(&mut self, num_instructions: usize) {
+        match self {
+            AnyBook::Continuous(book) => book.on_recipe_oversized(num_instructions),
+            AnyBook::BatchAuction(book) => book.on_recipe_oversized(num_instructions),
+        }
+    }
+}
+
 fn requiring_settled_state<Fr, Pl, U, F>(book: &mut TLB<Fr, Pl, U>, f: F)
 where
     Pl: Stable,
@@ -293,7 +731,11 @@ where
     Pl: MarketMaker + Stable + Copy + Display + Debug,
 {
     fn advance_clocks(&mut self, new_time: u64) {
-        requiring_settled_state(self, |st| st.advance_clocks(new_time))
+        // Unlike other events time does not invalidate a recipe in flight,
+        // so the clocks are simply advanced once it is settled.
+        if let TLBState::Idle(st) = &mut self.state {
+            st.advance_clocks(new_time)
+        }
     }
 
     fn update_taker(&mut self, fr: Fr) {
@@ -301,6 +743,7 @@ where
     }
 
     fn remove_taker(&mut self, fr: Fr) {
+        self.stashed.retain(|(_, taker)| *taker != fr);
         requiring_settled_state(self, |st| st.remove_fragment(fr))
     }
 
@@ -313,35 +756,46 @@ where
     }
 }
 
-const MAX_BIAS_PERCENT: u128 = 3;
-
 //                 P_settled
 //                     |
 // p: >.... P_x ......(.)...... P_index .... P_y.... >
 //           |         |           |          |
-//          ask     |bias|<=3%...pivot       bid
+//          ask     |bias|<=max..pivot       bid
 /// Settle execution price for two interleaving fragments.
-fn settle_price<Fr: MarketTaker>(ask: &Fr, bid: &Fr, index_price: Option<SpotPrice>) -> AbsolutePrice {
-    let price_ask = ask.price();
-    let price_bid = bid.price();
-    let price_ask_rat = price_ask.unwrap();
-    let price_bid_rat = price_bid.unwrap();
+/// `aggressor` is the side of the taker the match is made for.
+fn settle_price<Fr: MarketTaker>(
+    ask: &Fr,
+    bid: &Fr,
+    index_price: Option<SpotPrice>,
+    policy: SettlementPolicy,
+    aggressor: Side,
+) -> AbsolutePrice {
+    let price_ask_rat = ask.price().unwrap();
+    let price_bid_rat = bid.price().unwrap();
     let d = price_bid_rat - price_ask_rat;
     let pivotal_price = if let Some(index_price) = index_price {
         clamp(index_price.unwrap(), price_ask_rat, price_bid_rat)
     } else {
         price_ask_rat + d / 2
     };
-    let fee_ask = ask.fee() as i128;
-    let fee_bid = bid.fee() as i128;
-    let bias_percent = if fee_ask < fee_bid {
-        (-fee_ask * 100).checked_div(fee_bid).unwrap_or(0)
-    } else {
-        (fee_bid * 100).checked_div(fee_ask).unwrap_or(0)
+    // Share of the pivot the price moves by, towards the limit of the given side.
+    let bias = match policy {
+        SettlementPolicy::NoBias => None,
+        SettlementPolicy::FeeBias { max_bias } => {
+            let fee_ask = ask.fee() as u128;
+            let fee_bid = bid.fee() as u128;
+            // Moving towards the bid limit favours the ask and vice versa.
+            let towards = if fee_ask > fee_bid { Side::Bid } else { Side::Ask };
+            (fee_ask + fee_bid > 0)
+                .then(|| towards.wrap(max_bias * Ratio::new(fee_ask.abs_diff(fee_bid), fee_ask + fee_bid)))
+        }
+        SettlementPolicy::TakerPays { max_bias } => Some(aggressor.wrap(max_bias)),
+    };
+    let corrected_price = match bias {
+        Some(OnSide::Bid(bias)) => pivotal_price + pivotal_price * bias,
+        Some(OnSide::Ask(bias)) => pivotal_price - pivotal_price * min(bias, Ratio::from_integer(1)),
+        None => pivotal_price,
     };
-    let max_deviation = pivotal_price * Ratio::new(MAX_BIAS_PERCENT, 100);
-    let deviation = to_signed(max_deviation) * Ratio::new(bias_percent, 100);
-    let corrected_price = to_unsigned(to_signed(pivotal_price) + deviation);
     AbsolutePrice::from(clamp(corrected_price, price_ask_rat, price_bid_rat))
 }
 
@@ -355,30 +809,26 @@ fn clamp<I: PartialOrd>(value: I, low: I, high: I) -> I {
     }
 }
 
-fn to_signed(r: Ratio<u128>) -> Ratio<i128> {
-    Ratio::new(*r.numer() as i128, *r.denom() as i128)
-}
-
-fn to_unsigned(r: Ratio<i128>) -> Ratio<u128> {
-    Ratio::new(*r.numer() as u128, *r.denom() as u128)
-}
-
 pub fn linear_output_relative(input: u64, price: RelativePrice) -> Option<u64> {
-    u64::try_from((U256::from(input) * U256::from(*price.numer())).checked_div(U256::from(*price.denom()))?)
-        .ok()
+    mul_div(input, *price.numer(), *price.denom(), Rounding::Floor)
 }
 
+/// Output of trading [input] at [price], rounded down.
+/// Saturates when the output does not fit into `u64` (including zero Bid price).
 pub fn linear_output_unsafe(input: u64, price: OnSide<AbsolutePrice>) -> u64 {
-    match price {
-        Bid(price) => (U256::from(input) * U256::from(*price.denom()) / U256::from(*price.numer())).as_u64(),
-        Ask(price) => (U256::from(input) * U256::from(*price.numer()) / U256::from(*price.denom())).as_u64(),
-    }
+    price.checked_output(input).unwrap_or(u64::MAX)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::execution_engine::liquidity_book::config::{ExecutionCap, ExecutionConfig};
-    use crate::execution_engine::liquidity_book::market_maker::MarketMaker;
+    use num_rational::Ratio;
+
+    use crate::execution_engine::liquidity_book::config::{
+        ExecutionCap, ExecutionConfig, MakerSelection, MatchingMode, RecipeLimits, SettlementPolicy,
+        StashPolicy,
+    };
+    use crate::execution_engine::liquidity_book::core::Next;
+    use crate::execution_engine::liquidity_book::market_maker::{MakerBehavior, MarketMaker};
     use crate::execution_engine::liquidity_book::market_taker::MarketTaker;
     use crate::execution_engine::liquidity_book::side::Side::{Ask, Bid};
     use crate::execution_engine::liquidity_book::side::{OnSide, Side};
@@ -386,7 +836,8 @@ mod tests {
     use crate::execution_engine::liquidity_book::time::TimeBounds;
     use crate::execution_engine::liquidity_book::types::AbsolutePrice;
     use crate::execution_engine::liquidity_book::{
-        execute_with_maker, execute_with_taker, settle_price, ExternalTLBEvents, TemporalLiquidityBook, TLB,
+        execute_with_maker, execute_with_taker, fit_price_impact, price_impact_within, reserves_headroom,
+        settle_price, ExternalTLBEvents, FailureRate, StashStats, TemporalLiquidityBook, TLB,
     };
     use crate::execution_engine::types::StableId;
 
@@ -415,8 +866,16 @@ mod tests {
                 execution_cap: ExecutionCap {
                     soft: 1000000,
                     hard: 1600000,
+                    tx_size: None,
                 },
                 o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::default(),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::Continuous,
+                settlement_policy: SettlementPolicy::default(),
             },
         );
         vec![o1, o2].into_iter().for_each(|o| book.update_taker(o));
@@ -447,8 +906,16 @@ mod tests {
                 execution_cap: ExecutionCap {
                     soft: 1000000,
                     hard: 1600000,
+                    tx_size: None,
                 },
                 o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::default(),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::Continuous,
+                settlement_policy: SettlementPolicy::default(),
             },
         );
         book.update_taker(o1);
@@ -459,6 +926,154 @@ mod tests {
         dbg!(recipe);
     }
 
+    #[test]
+    fn recipe_limits_are_respected() {
+        let book_with = |recipe_limits| {
+            let mut book = TLB::new(
+                0,
+                ExecutionConfig {
+                    execution_cap: ExecutionCap {
+                        soft: 1000000,
+                        hard: 1600000,
+                        tx_size: None,
+                    },
+                    o2o_allowed: true,
+                    max_price_impact: None,
+                    maker_selection: MakerSelection::default(),
+                    stash_policy: StashPolicy::default(),
+                    recipe_limits,
+                    net_opposite_flows: false,
+                    matching_mode: MatchingMode::Continuous,
+                    settlement_policy: SettlementPolicy::default(),
+                },
+            );
+            book.update_taker(SimpleOrderPF::new(
+                Ask,
+                20000,
+                AbsolutePrice::new_unsafe(36, 100),
+                1000,
+            ));
+            book.update_maker(SimpleCFMMPool {
+                pool_id: StableId::random(),
+                reserves_base: 1000000,
+                reserves_quote: 370000,
+                fee_num: 997,
+            });
+            book
+        };
+        assert!(book_with(RecipeLimits::default()).attempt().is_some());
+        let no_swaps = RecipeLimits {
+            max_swaps: Some(0),
+            ..RecipeLimits::default()
+        };
+        assert!(book_with(no_swaps).attempt().is_none());
+        let two_fills_min = RecipeLimits {
+            min_fills: 2,
+            ..RecipeLimits::default()
+        };
+        assert!(book_with(two_fills_min).attempt().is_none());
+    }
+
+    #[test]
+    fn opposite_flows_are_netted_past_soft_cap() {
+        let book_with = |net_opposite_flows| {
+            let mut book = TLB::new(
+                0,
+                ExecutionConfig {
+                    execution_cap: ExecutionCap {
+                        // Exhausted by a single match.
+                        soft: 15,
+                        hard: 100,
+                        tx_size: None,
+                    },
+                    o2o_allowed: false,
+                    max_price_impact: None,
+                    maker_selection: MakerSelection::default(),
+                    stash_policy: StashPolicy::default(),
+                    recipe_limits: RecipeLimits::default(),
+                    net_opposite_flows,
+                    matching_mode: MatchingMode::Continuous,
+                    settlement_policy: SettlementPolicy::default(),
+                },
+            );
+            book.update_taker(SimpleOrderPF::new(
+                Ask,
+                20000,
+                AbsolutePrice::new_unsafe(36, 100),
+                1000,
+            ));
+            book.update_taker(SimpleOrderPF::new(
+                Bid,
+                3000,
+                AbsolutePrice::new_unsafe(38, 100),
+                1000,
+            ));
+            book.update_maker(SimpleCFMMPool {
+                pool_id: StableId::random(),
+                reserves_base: 1000000,
+                reserves_quote: 370000,
+                fee_num: 997,
+            });
+            book
+        };
+        let separate = book_with(false).attempt().unwrap();
+        assert_eq!(separate.fills().len(), 1);
+        let netted = book_with(true).attempt().unwrap();
+        assert_eq!(netted.fills().len(), 2);
+        assert_eq!(netted.maker_ids().len(), 1);
+    }
+
+    #[test]
+    fn reserves_headroom_shrinks_as_maker_takes_input() {
+        let pool = SimpleCFMMPool {
+            pool_id: StableId::random(),
+            reserves_base: 1000000,
+            reserves_quote: 370000,
+            fee_num: 997,
+        };
+        let share = Ratio::new(1, 10);
+        assert_eq!(reserves_headroom(&pool, &pool, OnSide::Ask(0), share), 100000);
+        if let Next::Succ(next_pool) = pool.swap(OnSide::Ask(40000)) {
+            let taken = next_pool.liquidity().base - pool.liquidity().base;
+            assert_eq!(
+                reserves_headroom(&pool, &next_pool, OnSide::Ask(0), share),
+                100000 - taken
+            );
+        }
+    }
+
+    #[test]
+    fn failure_rate_favours_recent_outcomes() {
+        let failed = FailureRate::default().record(true);
+        assert_eq!(failed, FailureRate(125));
+        assert_eq!(failed.record(false), FailureRate(110));
+        assert!(failed.record(true) > failed.record(false).record(true));
+    }
+
+    #[test]
+    fn chunk_is_shrunk_to_fit_price_impact() {
+        let pool = SimpleCFMMPool {
+            pool_id: StableId::random(),
+            reserves_base: 1000000,
+            reserves_quote: 370000,
+            fee_num: 997,
+        };
+        let max_impact = Ratio::new(2, 100);
+        let chunk = fit_price_impact(&pool, pool, OnSide::Ask(100000), max_impact).unwrap();
+        assert!(chunk.unwrap() < 100000);
+        if let Next::Succ(next_pool) = pool.swap(chunk) {
+            assert!(price_impact_within(
+                pool.static_price(),
+                next_pool.static_price(),
+                max_impact
+            ));
+        }
+        assert_eq!(
+            fit_price_impact(&pool, pool, OnSide::Ask(1000), max_impact),
+            Some(OnSide::Ask(1000))
+        );
+    }
+
     #[test]
     fn match_taker_with_taker() {
         // Assuming pair ADA/USDT @ 0.37
@@ -487,7 +1102,13 @@ mod tests {
             bounds: TimeBounds::None,
         };
         let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| {
-            settle_price(x, y, Some(AbsolutePrice::new_unsafe(37, 100).into()))
+            settle_price(
+                x,
+                y,
+                Some(AbsolutePrice::new_unsafe(37, 100).into()),
+                SettlementPolicy::default(),
+                Ask,
+            )
         };
         let (t1, t2) = execute_with_taker(fr1, fr2, make_match);
         assert_eq!(t1.added_output(), fr2.input);
@@ -522,7 +1143,9 @@ mod tests {
             cost_hint: 100,
             bounds: TimeBounds::None,
         };
-        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| settle_price(x, y, Some(p.into()));
+        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| {
+            settle_price(x, y, Some(p.into()), SettlementPolicy::default(), Ask)
+        };
         let (t1, t2) = execute_with_taker(fr1, fr2, make_match);
         assert_eq!(
             t2.added_output(),
@@ -613,7 +1236,9 @@ mod tests {
             cost_hint: 100,
             bounds: TimeBounds::None,
         };
-        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| settle_price(x, y, Some(index_price.into()));
+        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| {
+            settle_price(x, y, Some(index_price.into()), SettlementPolicy::default(), Ask)
+        };
         let final_price = make_match(&ask_fr, &bid_fr);
         assert!(final_price.unwrap() - ask_price.unwrap() > bid_price.unwrap() - final_price.unwrap());
     }
@@ -647,7 +1272,9 @@ mod tests {
             cost_hint: 100,
             bounds: TimeBounds::None,
         };
-        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| settle_price(x, y, Some(index_price.into()));
+        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| {
+            settle_price(x, y, Some(index_price.into()), SettlementPolicy::default(), Ask)
+        };
         let final_price = make_match(&ask_fr, &bid_fr);
         assert!(final_price.unwrap() - ask_price.unwrap() > bid_price.unwrap() - final_price.unwrap());
     }
@@ -681,11 +1308,108 @@ mod tests {
             cost_hint: 100,
             bounds: TimeBounds::None,
         };
-        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| settle_price(x, y, Some(index_price.into()));
+        let make_match = |x: &SimpleOrderPF, y: &SimpleOrderPF| {
+            settle_price(x, y, Some(index_price.into()), SettlementPolicy::default(), Ask)
+        };
         let final_price = make_match(&ask_fr, &bid_fr);
         assert_eq!(final_price, bid_price)
     }
 
+    fn settle_at_index(
+        fee_ask: u64,
+        fee_bid: u64,
+        policy: SettlementPolicy,
+        aggressor: Side,
+    ) -> AbsolutePrice {
+        let ask = SimpleOrderPF::new(Ask, 1000, AbsolutePrice::new_unsafe(30, 100), fee_ask);
+        let bid = SimpleOrderPF::new(Bid, 400, AbsolutePrice::new_unsafe(50, 100), fee_bid);
+        let index_price = AbsolutePrice::new_unsafe(40, 100);
+        settle_price(&ask, &bid, Some(index_price.into()), policy, aggressor)
+    }
+
+    const FEE_BIAS: SettlementPolicy = SettlementPolicy::FeeBias {
+        max_bias: Ratio::new_raw(3, 100),
+    };
+
+    #[test]
+    fn fee_bias_is_proportional_to_fee_imbalance() {
+        // Ask pays 3/4 of fees, so the price moves up by a half of the max bias.
+        let price = settle_at_index(3000, 1000, FEE_BIAS, Ask);
+        assert_eq!(price, AbsolutePrice::new_unsafe(406, 1000));
+        let price = settle_at_index(1000, 3000, FEE_BIAS, Ask);
+        assert_eq!(price, AbsolutePrice::new_unsafe(394, 1000));
+    }
+
+    #[test]
+    fn fee_bias_is_neutral_for_equal_fees() {
+        for fee in [0, 2000] {
+            let price = settle_at_index(fee, fee, FEE_BIAS, Ask);
+            assert_eq!(price, AbsolutePrice::new_unsafe(40, 100));
+        }
+    }
+
+    #[test]
+    fn fee_bias_is_full_when_single_side_pays() {
+        let price = settle_at_index(0, 1000, FEE_BIAS, Bid);
+        assert_eq!(price, AbsolutePrice::new_unsafe(388, 1000));
+        let price = settle_at_index(1000, 0, FEE_BIAS, Bid);
+        assert_eq!(price, AbsolutePrice::new_unsafe(412, 1000));
+    }
+
+    #[test]
+    fn no_bias_settles_at_pivot() {
+        let price = settle_at_index(3000, 1000, SettlementPolicy::NoBias, Ask);
+        assert_eq!(price, AbsolutePrice::new_unsafe(40, 100));
+    }
+
+    #[test]
+    fn aggressor_pays_bias_to_resting_taker() {
+        let policy = SettlementPolicy::TakerPays {
+            max_bias: Ratio::new(3, 100),
+        };
+        // Fees don't matter under this policy.
+        let price = settle_at_index(3000, 1000, policy, Ask);
+        assert_eq!(price, AbsolutePrice::new_unsafe(388, 1000));
+        let price = settle_at_index(1000, 3000, policy, Bid);
+        assert_eq!(price, AbsolutePrice::new_unsafe(412, 1000));
+    }
+
+    #[test]
+    fn stashed_taker_returns_once_ttl_expires() {
+        let taker = SimpleOrderPF::new(Ask, 1000, AbsolutePrice::new_unsafe(1, 1), 0);
+        let mut book = TLB::<_, SimpleCFMMPool, _>::new(
+            0,
+            ExecutionConfig {
+                execution_cap: ExecutionCap {
+                    soft: 1000000,
+                    hard: 1600000,
+                    tx_size: None,
+                },
+                o2o_allowed: true,
+                max_price_impact: None,
+                maker_selection: MakerSelection::default(),
+                stash_policy: StashPolicy::Attempts(1),
+                recipe_limits: RecipeLimits::default(),
+                net_opposite_flows: false,
+                matching_mode: MatchingMode::Continuous,
+                settlement_policy: SettlementPolicy::default(),
+            },
+        );
+        book.stashed.push((1, taker));
+        assert!(book.attempt().is_none());
+        assert!(book.state.best_taker_price(Ask).is_none());
+        assert!(book.attempt().is_none());
+        assert!(book.state.best_taker_price(Ask).is_some());
+        assert_eq!(
+            book.stash_stats(),
+            StashStats {
+                stashed: 0,
+                stashed_total: 0,
+                released_total: 1,
+            }
+        );
+    }
+
     #[test]
     fn price_overlap() {
         let rem_side = Bid;
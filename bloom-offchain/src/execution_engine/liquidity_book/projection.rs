@@ -0,0 +1,231 @@
+use std::fmt::{Display, Formatter};
+
+use spectrum_offchain::data::Stable;
+
+use crate::execution_engine::liquidity_book::core::{Next, Unit};
+use crate::execution_engine::liquidity_book::market_maker::{
+    AbsoluteReserves, MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
+};
+use crate::execution_engine::liquidity_book::side::OnSide;
+use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+
+/// Pooled liquidity of arbitrary number of assets, any two of which can be swapped.
+pub trait MultiAssetMaker: Sized {
+    type Asset: Copy + Eq;
+    type U;
+    fn assets(&self) -> Vec<Self::Asset>;
+    fn reserves(&self, asset: Self::Asset) -> u64;
+    /// Price of a theoretical 0-swap of `base` to `quote`.
+    fn static_price(&self, base: Self::Asset, quote: Self::Asset) -> SpotPrice;
+    /// Amount of `output_asset` received for `input` of `input_asset` along with the resulting state.
+    fn swap_assets(
+        self,
+        input_asset: Self::Asset,
+        output_asset: Self::Asset,
+        input: u64,
+    ) -> Option<(u64, Self)>;
+    fn quality(&self) -> PoolQuality;
+    fn marginal_cost_hint(&self) -> Self::U;
+    fn lifecycle(&self) -> PoolLifecycle;
+    /// Part of `input` of `input_asset` swapped into the maker LPs retain as a fee.
+    fn lp_fee(&self, input_asset: Self::Asset, input: u64) -> u64;
+}
+
+/// Projection of a [MultiAssetMaker] onto a single pair of its assets.
+/// Views of one maker share its on-chain state, so each of them is meant to live in
+/// the TLB of its own pair.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PairView<M: MultiAssetMaker> {
+    pub maker: M,
+    pub base: M::Asset,
+    pub quote: M::Asset,
+}
+
+impl<M: MultiAssetMaker> PairView<M> {
+    pub fn new(maker: M, base: M::Asset, quote: M::Asset) -> Self {
+        Self { maker, base, quote }
+    }
+}
+
+/// All pairwise views of the given maker, `orient` decides which asset of a pair is the base one.
+pub fn pair_views<M, F>(maker: M, orient: F) -> Vec<PairView<M>>
+where
+    M: MultiAssetMaker + Copy,
+    F: Fn(M::Asset, M::Asset) -> (M::Asset, M::Asset),
+{
+    let assets = maker.assets();
+    let mut views = vec![];
+    for (i, x) in assets.iter().enumerate() {
+        for y in &assets[i + 1..] {
+            let (base, quote) = orient(*x, *y);
+            views.push(PairView::new(maker, base, quote));
+        }
+    }
+    views
+}
+
+impl<M> Stable for PairView<M>
+where
+    M: MultiAssetMaker + Stable,
+{
+    type StableId = M::StableId;
+    fn stable_id(&self) -> Self::StableId {
+        self.maker.stable_id()
+    }
+    fn is_quasi_permanent(&self) -> bool {
+        self.maker.is_quasi_permanent()
+    }
+}
+
+impl<M> MakerBehavior for PairView<M>
+where
+    M: MultiAssetMaker + Copy,
+{
+    fn swap(self, input: OnSide<u64>) -> Next<Self, Unit> {
+        let (input_asset, output_asset) = input
+            .marker()
+            .select((self.quote, self.base), (self.base, self.quote));
+        match self.maker.swap_assets(input_asset, output_asset, input.unwrap()) {
+            Some((_, maker)) => Next::Succ(Self { maker, ..self }),
+            None => Next::Term(Unit),
+        }
+    }
+}
+
+impl<M> MarketMaker for PairView<M>
+where
+    M: MultiAssetMaker + Copy,
+{
+    type U = M::U;
+
+    fn static_price(&self) -> SpotPrice {
+        self.maker.static_price(self.base, self.quote)
+    }
+
+    fn real_price(&self, input: OnSide<u64>) -> Option<AbsolutePrice> {
+        match input {
+            OnSide::Bid(quote_input) => {
+                let (base_output, _) = self.maker.swap_assets(self.quote, self.base, quote_input)?;
+                AbsolutePrice::new(quote_input, base_output)
+            }
+            OnSide::Ask(base_input) => {
+                let (quote_output, _) = self.maker.swap_assets(self.base, self.quote, base_input)?;
+                AbsolutePrice::new(quote_output, base_input)
+            }
+        }
+    }
+
+    fn quality(&self) -> PoolQuality {
+        self.maker.quality()
+    }
+
+    fn marginal_cost_hint(&self) -> Self::U {
+        self.maker.marginal_cost_hint()
+    }
+
+    fn liquidity(&self) -> AbsoluteReserves {
+        AbsoluteReserves {
+            base: self.maker.reserves(self.base),
+            quote: self.maker.reserves(self.quote),
+        }
+    }
+
+    fn lifecycle(&self) -> PoolLifecycle {
+        self.maker.lifecycle()
+    }
+
+    fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+        match input {
+            OnSide::Bid(quote_input) => self.maker.lp_fee(self.quote, quote_input),
+            OnSide::Ask(base_input) => self.maker.lp_fee(self.base, base_input),
+        }
+    }
+}
+
+impl<M> Display for PairView<M>
+where
+    M: MultiAssetMaker + Display,
+    M::Asset: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PairView({}/{} of {})", self.base, self.quote, self.maker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::core::Next;
+    use crate::execution_engine::liquidity_book::market_maker::{
+        MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
+    };
+    use crate::execution_engine::liquidity_book::projection::{pair_views, MultiAssetMaker};
+    use crate::execution_engine::liquidity_book::side::OnSide;
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+
+    /// Constant-sum pool of three assets.
+    #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    struct TriPool {
+        reserves: [u64; 3],
+    }
+
+    impl MultiAssetMaker for TriPool {
+        type Asset = usize;
+        type U = u64;
+
+        fn assets(&self) -> Vec<usize> {
+            vec![0, 1, 2]
+        }
+
+        fn reserves(&self, asset: usize) -> u64 {
+            self.reserves[asset]
+        }
+
+        fn static_price(&self, _base: usize, _quote: usize) -> SpotPrice {
+            AbsolutePrice::new_unsafe(1, 1).into()
+        }
+
+        fn swap_assets(mut self, input_asset: usize, output_asset: usize, input: u64) -> Option<(u64, Self)> {
+            let output = input.min(self.reserves[output_asset]);
+            if output == 0 {
+                return None;
+            }
+            self.reserves[input_asset] += input;
+            self.reserves[output_asset] -= output;
+            Some((output, self))
+        }
+
+        fn quality(&self) -> PoolQuality {
+            PoolQuality::from(0u64)
+        }
+
+        fn marginal_cost_hint(&self) -> u64 {
+            10
+        }
+
+        fn lifecycle(&self) -> PoolLifecycle {
+            PoolLifecycle::Active
+        }
+
+        fn lp_fee(&self, _input_asset: usize, _input: u64) -> u64 {
+            0
+        }
+    }
+
+    #[test]
+    fn views_cover_every_pair_and_share_state() {
+        let pool = TriPool {
+            reserves: [100, 200, 300],
+        };
+        let views = pair_views(pool, |x, y| (x.max(y), x.min(y)));
+        let pairs = views.iter().map(|v| (v.base, v.quote)).collect::<Vec<_>>();
+        assert_eq!(pairs, vec![(1, 0), (2, 0), (2, 1)]);
+        let view = views[1];
+        assert_eq!(view.liquidity().base, 300);
+        assert_eq!(view.liquidity().quote, 100);
+        assert_eq!(view.real_price(OnSide::Ask(50)), AbsolutePrice::new(50, 50));
+        match view.swap(OnSide::Ask(50)) {
+            Next::Succ(next) => assert_eq!(next.maker.reserves, [50, 200, 350]),
+            Next::Term(_) => panic!(),
+        }
+    }
+}
@@ -1,31 +1,44 @@
 use std::fmt::{Display, Formatter};
 use std::ops::Not;
 
-use derive_more::{Display, From, Into};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
 
 /// Side marker.
-#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Side {
     Bid,
     Ask,
 }
 
 impl Side {
+    /// Attach a value to this side.
     pub fn wrap<T>(self, value: T) -> OnSide<T> {
         match self {
             Side::Bid => OnSide::Bid(value),
             Side::Ask => OnSide::Ask(value),
         }
     }
+    /// Opposite side.
+    pub fn flip(self) -> Side {
+        match self {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+    /// Pick one of two values depending on the side.
+    pub fn select<T>(self, on_bid: T, on_ask: T) -> T {
+        match self {
+            Side::Bid => on_bid,
+            Side::Ask => on_ask,
+        }
+    }
 }
 
 impl Not for Side {
     type Output = Side;
     fn not(self) -> Self::Output {
-        match self {
-            Side::Bid => Side::Ask,
-            Side::Ask => Side::Bid,
-        }
+        self.flip()
     }
 }
 
@@ -63,6 +76,23 @@ impl<T> OnSide<T> {
             OnSide::Ask(_) => Side::Ask,
         }
     }
+    /// Split into side marker and the value.
+    pub fn split(self) -> (Side, T) {
+        (self.marker(), self.unwrap())
+    }
+    /// Same value on the opposite side.
+    pub fn flip(self) -> OnSide<T> {
+        match self {
+            OnSide::Bid(t) => OnSide::Ask(t),
+            OnSide::Ask(t) => OnSide::Bid(t),
+        }
+    }
+    pub fn as_ref(&self) -> OnSide<&T> {
+        match self {
+            OnSide::Bid(t) => OnSide::Bid(t),
+            OnSide::Ask(t) => OnSide::Ask(t),
+        }
+    }
     pub fn map<R, F>(self, f: F) -> OnSide<R>
     where
         F: FnOnce(T) -> R,
@@ -73,3 +103,34 @@ impl<T> OnSide<T> {
         }
     }
 }
+
+impl<T> From<OnSide<T>> for Side {
+    fn from(value: OnSide<T>) -> Self {
+        value.marker()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::side::{OnSide, Side};
+
+    #[test]
+    fn wrap_split_roundtrip() {
+        for side in [Side::Bid, Side::Ask] {
+            assert_eq!(side.wrap(7).split(), (side, 7));
+        }
+    }
+
+    #[test]
+    fn flip_preserves_value() {
+        assert_eq!(OnSide::Bid(1).flip(), OnSide::Ask(1));
+        assert_eq!(OnSide::Ask(1).flip().flip(), OnSide::Ask(1));
+        assert_eq!(OnSide::Bid(1).flip().marker(), !Side::Bid);
+    }
+
+    #[test]
+    fn select_follows_side() {
+        assert_eq!(Side::Bid.select("bid", "ask"), "bid");
+        assert_eq!(Side::Ask.select("bid", "ask"), "ask");
+    }
+}
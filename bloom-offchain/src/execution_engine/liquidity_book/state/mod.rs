@@ -1,4 +1,4 @@
-use std::collections::hash_map::Entry;
+use std::cmp::Ordering;
 use std::collections::{btree_map, BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Debug, Display, Formatter};
 use std::mem;
@@ -6,12 +6,14 @@ use std::ops::Add;
 
 use either::{Either, Left, Right};
 use log::trace;
+use num_rational::Ratio;
 
 use spectrum_offchain::data::Stable;
 
 use crate::execution_engine::liquidity_book::core::Next;
 use crate::execution_engine::liquidity_book::market_maker::{MarketMaker, PoolQuality, SpotPrice};
 use crate::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
+use crate::execution_engine::liquidity_book::relative_distance_within;
 use crate::execution_engine::liquidity_book::side::{OnSide, Side};
 use crate::execution_engine::liquidity_book::stashing_option::StashingOption;
 use crate::execution_engine::liquidity_book::state::price_range::AllowedPriceRange;
@@ -40,7 +42,7 @@ impl<T, M: Stable> IdleState<T, M> {
 impl<T, M> IdleState<T, M>
 where
     T: MarketTaker + TakerBehaviour + Ord + Copy + Display,
-    M: MarketMaker + Stable + Copy + Display + Debug,
+    M: Stable,
 {
     pub fn advance_clocks(&mut self, new_time: u64) {
         self.takers.advance_clocks(new_time)
@@ -55,7 +57,12 @@ where
         trace!("Removing {} from active frontier", fr);
         self.takers.remove_fragment(fr);
     }
+}
 
+impl<T, M> IdleState<T, M>
+where
+    M: MarketMaker + Stable + Copy + Display + Debug,
+{
     pub fn update_pool(&mut self, maker: M) {
         trace!("Updating {} in active frontier", maker);
         self.makers.update_pool(maker);
@@ -99,6 +106,7 @@ where
     fn commit(&mut self) -> IdleState<T, M> {
         trace!(target: "state", "PartialPreviewState::commit");
         let mut fresh_settled_st = IdleState::new(0);
+        self.unstash();
         mem::swap(&mut fresh_settled_st.takers, &mut self.takers_preview);
         mem::swap(&mut fresh_settled_st.makers, &mut self.makers_preview);
         fresh_settled_st
@@ -324,6 +332,15 @@ where
             TLBState::Preview(st) => &st.active_takers_preview,
         }
     }
+
+    /// Active takers on the given side, best first.
+    pub fn active_takers(&self, side: Side) -> Vec<T> {
+        let active_fragments = self.active_fragments();
+        side.select(&active_fragments.bids, &active_fragments.asks)
+            .iter()
+            .copied()
+            .collect()
+    }
 }
 
 impl<T, M> TLBState<T, M>
@@ -367,6 +384,16 @@ where
         }
     }
 
+    /// Take stashed takers out of the state so that they don't return to matchmaking
+    /// once the state is settled.
+    pub fn take_stash(&mut self) -> Vec<T> {
+        match self {
+            TLBState::PartialPreview(st) => mem::take(&mut st.stashed_active_takers),
+            TLBState::Preview(st) => mem::take(&mut st.stashed_active_takers),
+            TLBState::Idle(_) => vec![],
+        }
+    }
+
     fn move_into_partial_preview(&mut self, target: &mut PartialPreviewState<T, M>) {
         match self {
             // Transit into PartialPreview if state is untouched yet
@@ -469,10 +496,7 @@ where
 
     pub fn best_taker_price(&self, side: Side) -> Option<OnSide<AbsolutePrice>> {
         let active_fragments = self.active_fragments();
-        let side_store = match side {
-            Side::Bid => &active_fragments.bids,
-            Side::Ask => &active_fragments.asks,
-        };
+        let side_store = side.select(&active_fragments.bids, &active_fragments.asks);
         side_store.first().map(|fr| side.wrap(fr.price()))
     }
 
@@ -598,24 +622,46 @@ impl<T, M> TLBState<T, M>
 where
     M: Stable + Copy,
 {
-    pub fn preselect_market_maker(
+    /// Select maker to serve the given chunk. Makers quoting within [price_tolerance]
+    /// from the best real price are ranked by [penalty], then by execution cost and quality.
+    /// Makers for which [penalty] returns `None` are not considered.
+    pub fn preselect_market_maker<F>(
         &self,
         offered_amount: OnSide<InputAsset<u64>>,
+        price_tolerance: Ratio<u128>,
+        penalty: F,
     ) -> Option<(M::StableId, AbsolutePrice)>
     where
         M: MarketMaker,
+        M::U: PartialOrd,
+        F: Fn(&M) -> Option<u64>,
     {
         let pools = self
             .pools()
             .values
             .values()
             .filter(|pool| pool.is_active())
-            .filter_map(|p| p.real_price(offered_amount).map(|rp| (p.stable_id(), rp)))
+            .filter_map(|p| penalty(p).map(|pn| (p, pn)))
+            .filter_map(|(p, pn)| p.real_price(offered_amount).map(|rp| (p, pn, rp)))
             .collect::<Vec<_>>();
-        match offered_amount {
-            OnSide::Bid(_) => pools.into_iter().min_by_key(|(_, rp)| *rp),
-            OnSide::Ask(_) => pools.into_iter().max_by_key(|(_, rp)| *rp),
-        }
+        let best_price = match offered_amount {
+            OnSide::Bid(_) => pools.iter().map(|(_, _, rp)| *rp).min(),
+            OnSide::Ask(_) => pools.iter().map(|(_, _, rp)| *rp).max(),
+        }?;
+        pools
+            .into_iter()
+            .filter(|(_, _, rp)| relative_distance_within(best_price.unwrap(), rp.unwrap(), price_tolerance))
+            .min_by(|(p1, pn1, rp1), (p2, pn2, rp2)| {
+                pn1.cmp(pn2)
+                    .then_with(|| {
+                        p1.marginal_cost_hint()
+                            .partial_cmp(&p2.marginal_cost_hint())
+                            .unwrap_or(Ordering::Equal)
+                    })
+                    .then_with(|| p1.quality().cmp(&p2.quality()))
+                    .then_with(|| offered_amount.marker().select(rp1.cmp(rp2), rp2.cmp(rp1)))
+            })
+            .map(|(p, _, rp)| (p.stable_id(), rp))
     }
 
     pub fn try_select_pool(&self, trade_hint: OnSide<u64>) -> Option<(AbsolutePrice, SpotPrice, M::StableId)>
@@ -641,24 +687,21 @@ where
     pub fn try_pick_pool<F>(&mut self, test: F) -> Option<M>
     where
         T: MarketTaker + Ord + Copy,
+        M: MarketMaker,
         F: Fn(&M) -> bool,
     {
         self.pick_maker(|pools| {
-            for id in pools.quality_index.values() {
-                match pools.values.entry(*id) {
-                    Entry::Occupied(pl) if test(pl.get()) => return Some(pl.remove()),
-                    _ => {}
-                }
-            }
-            None
+            let pool_id = pools.by_quality().find(|id| test(&pools.values[*id])).copied()?;
+            pools.take(&pool_id)
         })
     }
 
     pub fn pick_maker_by_id(&mut self, pid: &M::StableId) -> Option<M>
     where
         T: MarketTaker + Ord + Copy,
+        M: MarketMaker,
     {
-        self.pick_maker(|pools| pools.values.remove(pid))
+        self.pick_maker(|pools| pools.take(pid))
     }
 
     /// Pick pool ensuring TLB is in proper state.
@@ -733,10 +776,7 @@ where
     T: MarketTaker + Copy + Ord,
     F: FnOnce(&T) -> bool,
 {
-    let side = match side {
-        Side::Bid => &mut active_frontier.bids,
-        Side::Ask => &mut active_frontier.asks,
-    };
+    let side = side.select(&mut active_frontier.bids, &mut active_frontier.asks);
     if let Some(best_fr) = side.pop_first() {
         if test(&best_fr) {
             return Some(best_fr);
@@ -886,7 +926,8 @@ where
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct MarketMakers<M: Stable> {
     values: HashMap<M::StableId, M>,
-    quality_index: BTreeMap<PoolQuality, M::StableId>,
+    /// Makers of equal quality are kept in order of arrival.
+    quality_index: BTreeMap<PoolQuality, Vec<M::StableId>>,
 }
 
 impl<M: Stable> MarketMakers<M> {
@@ -907,6 +948,11 @@ impl<M: Stable> MarketMakers<M> {
             .map(|(k, v)| format!("{} -> {}", k, v))
             .fold("".to_string(), |acc, x| acc.add(format!("{}, ", x).as_str()))
     }
+
+    /// IDs of all makers from the best quality to the worst one.
+    fn by_quality(&self) -> impl Iterator<Item = &M::StableId> {
+        self.quality_index.values().flatten()
+    }
 }
 
 impl<M> MarketMakers<M>
@@ -914,16 +960,38 @@ where
     M: MarketMaker + Stable + Copy,
 {
     pub fn update_pool(&mut self, pool: M) {
-        if let Some(old_pool) = self.values.insert(pool.stable_id(), pool) {
+        let pool_id = pool.stable_id();
+        if let Some(old_pool) = self.values.insert(pool_id, pool) {
             trace!(target: "state", "removing old pool {}", old_pool.stable_id());
-            self.quality_index.remove(&old_pool.quality());
+            self.unindex(&old_pool);
         }
-        trace!(target: "state", "adding new pool id: {}, quality: {:?}", pool.stable_id(), pool.quality());
-        self.quality_index.insert(pool.quality(), pool.stable_id());
+        trace!(target: "state", "adding new pool id: {}, quality: {:?}", pool_id, pool.quality());
+        self.quality_index
+            .entry(pool.quality())
+            .or_default()
+            .push(pool_id);
     }
+
     pub fn remove_pool(&mut self, pool: M) {
-        self.values.remove(&pool.stable_id());
-        self.quality_index.remove(&pool.quality());
+        self.take(&pool.stable_id());
+    }
+
+    /// Remove maker by ID.
+    pub fn take(&mut self, pool_id: &M::StableId) -> Option<M> {
+        let pool = self.values.remove(pool_id)?;
+        self.unindex(&pool);
+        Some(pool)
+    }
+
+    /// Remove the given state of the maker from the quality index.
+    fn unindex(&mut self, pool: &M) {
+        let quality = pool.quality();
+        if let btree_map::Entry::Occupied(mut ids) = self.quality_index.entry(quality) {
+            ids.get_mut().retain(|id| *id != pool.stable_id());
+            if ids.get().is_empty() {
+                ids.remove();
+            }
+        }
     }
 }
 
@@ -938,7 +1006,7 @@ pub mod tests {
 
     use crate::execution_engine::liquidity_book::core::{Next, TerminalTake, Trans, Unit};
     use crate::execution_engine::liquidity_book::market_maker::{
-        AbsoluteReserves, MakerBehavior, MarketMaker, SpotPrice,
+        AbsoluteReserves, MakerBehavior, MarketMaker, PoolLifecycle, SpotPrice,
     };
     use crate::execution_engine::liquidity_book::market_taker::{MarketTaker, TakerBehaviour};
     use crate::execution_engine::liquidity_book::side::{OnSide, Side};
@@ -1242,6 +1310,66 @@ pub mod tests {
         assert_eq!(state.pools().values.get(&p0.pool_id).copied(), Some(p0));
     }
 
+    #[test]
+    fn makers_of_equal_quality_do_not_collide() {
+        let pools = (0..10)
+            .map(|i| SimpleCFMMPool {
+                pool_id: StableId::random(),
+                reserves_base: 1000 + i,
+                reserves_quote: 1000,
+                fee_num: 997,
+            })
+            .collect::<Vec<_>>();
+        let mut makers = MarketMakers::new();
+        for pool in &pools {
+            makers.update_pool(*pool);
+        }
+        assert_eq!(makers.by_quality().count(), pools.len());
+        // New state of the same pool replaces the old one.
+        makers.update_pool(SimpleCFMMPool {
+            reserves_base: 1,
+            ..pools[0]
+        });
+        assert_eq!(makers.by_quality().count(), pools.len());
+        assert_eq!(makers.by_quality().next(), Some(&pools[1].pool_id));
+        for pool in &pools[..3] {
+            makers.remove_pool(*pool);
+        }
+        assert_eq!(
+            makers.by_quality().copied().collect::<Vec<_>>(),
+            pools[3..].iter().map(|p| p.pool_id).collect::<Vec<_>>()
+        );
+        for pool in &pools[3..] {
+            assert_eq!(makers.take(&pool.pool_id), Some(*pool));
+        }
+        assert!(makers.quality_index.is_empty());
+    }
+
+    #[test]
+    fn pick_maker_among_equal_quality() {
+        let pools = (0..5)
+            .map(|i| SimpleCFMMPool {
+                pool_id: StableId::random(),
+                reserves_base: 1000 * (i + 1),
+                reserves_quote: 1000,
+                fee_num: 997,
+            })
+            .collect::<Vec<_>>();
+        let mut s0 = IdleState::<SimpleOrderPF, SimpleCFMMPool>::new(0);
+        for pool in &pools {
+            s0.makers.update_pool(*pool);
+        }
+        let mut state = TLBState::Idle(s0);
+        let target = pools[3];
+        assert_eq!(
+            state.try_pick_pool(|p| p.reserves_base == target.reserves_base),
+            Some(target)
+        );
+        assert_eq!(state.pools().by_quality().count(), pools.len() - 1);
+        assert_eq!(state.pick_maker_by_id(&pools[0].pool_id), Some(pools[0]));
+        assert_eq!(state.pools().by_quality().count(), pools.len() - 2);
+    }
+
     /// Order that supports partial filling.
     #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
     pub struct SimpleOrderPF {
@@ -1508,19 +1636,11 @@ pub mod tests {
         }
 
         fn real_price(&self, input: OnSide<u64>) -> Option<AbsolutePrice> {
+            let trans = Trans::new(*self, self.swap(input));
+            let output = trans.loss().map(|r| r.unwrap()).unwrap_or(0);
             match input {
-                OnSide::Bid(quote_input) => {
-                    let result_pool = self.swap(OnSide::Bid(quote_input));
-                    let trans = Trans::new(*self, result_pool);
-                    let base_output = trans.loss().map(|r| r.unwrap()).unwrap_or(0);
-                    AbsolutePrice::new(quote_input, base_output)
-                }
-                OnSide::Ask(base_input) => {
-                    let result_pool = self.swap(OnSide::Ask(base_input));
-                    let trans = Trans::new(*self, result_pool);
-                    let quote_output = trans.loss().map(|r| r.unwrap()).unwrap_or(0);
-                    AbsolutePrice::new(quote_output, base_input)
-                }
+                OnSide::Bid(quote_input) => AbsolutePrice::new(quote_input, output),
+                OnSide::Ask(base_input) => AbsolutePrice::new(output, base_input),
             }
         }
 
@@ -1539,9 +1659,13 @@ pub mod tests {
             10
         }
 
-        fn is_active(&self) -> bool {
+        fn lifecycle(&self) -> PoolLifecycle {
             // SimpleCFMMPool used only for tests
-            true
+            PoolLifecycle::Active
+        }
+
+        fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+            input.unwrap() * (1000 - self.fee_num) / 1000
         }
     }
 }
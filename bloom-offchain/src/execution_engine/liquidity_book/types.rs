@@ -5,6 +5,7 @@ use std::str::FromStr;
 use bignumber::BigNumber;
 use derive_more::{Add, Div, From, Into, Mul, Sub};
 use num_rational::Ratio;
+use primitive_types::U256;
 
 use crate::execution_engine::liquidity_book::side::{OnSide, Side};
 
@@ -88,7 +89,57 @@ impl AbsolutePrice {
     }
 }
 
+/// Rounding direction of price arithmetic.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Rounding {
+    /// Towards zero, used for amounts paid out.
+    Floor,
+    /// Away from zero, used for amounts required in.
+    Ceil,
+}
+
+/// Divides `numer` by `denom` rounding in the given direction.
+/// Returns `None` if `denom` is zero or the result does not fit into `u64`.
+pub fn div_rounded(numer: U256, denom: U256, rounding: Rounding) -> Option<u64> {
+    if denom.is_zero() {
+        return None;
+    }
+    let (quot, rem) = numer.div_mod(denom);
+    let result = match rounding {
+        Rounding::Ceil if !rem.is_zero() => quot + 1,
+        _ => quot,
+    };
+    (result <= U256::from(u64::MAX)).then(|| result.as_u64())
+}
+
+/// Computes `amount * numer / denom` in 256 bits.
+/// Returns `None` if `denom` is zero or the result does not fit into `u64`.
+pub fn mul_div(amount: u64, numer: u128, denom: u128, rounding: Rounding) -> Option<u64> {
+    div_rounded(
+        U256::from(amount) * U256::from(numer),
+        U256::from(denom),
+        rounding,
+    )
+}
+
 impl OnSide<AbsolutePrice> {
+    /// Amount received for `input` traded at this price, i.e. quote for base on Ask
+    /// and base for quote on Bid. Rounded down.
+    pub fn checked_output(self, input: InputAsset<u64>) -> Option<OutputAsset<u64>> {
+        match self {
+            OnSide::Bid(price) => mul_div(input, *price.denom(), *price.numer(), Rounding::Floor),
+            OnSide::Ask(price) => mul_div(input, *price.numer(), *price.denom(), Rounding::Floor),
+        }
+    }
+
+    /// Amount to be traded at this price in order to receive `output`. Rounded up.
+    pub fn checked_required_input(self, output: OutputAsset<u64>) -> Option<InputAsset<u64>> {
+        match self {
+            OnSide::Bid(price) => mul_div(output, *price.numer(), *price.denom(), Rounding::Ceil),
+            OnSide::Ask(price) => mul_div(output, *price.denom(), *price.numer(), Rounding::Ceil),
+        }
+    }
+
     /// Compare prices on opposite sides.
     pub fn overlaps(self, that: AbsolutePrice) -> bool {
         match self {
@@ -109,3 +160,47 @@ impl OnSide<AbsolutePrice> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::side::OnSide;
+    use crate::execution_engine::liquidity_book::types::{mul_div, AbsolutePrice, Rounding};
+
+    #[test]
+    fn mul_div_rounds_as_requested() {
+        assert_eq!(mul_div(10, 1, 3, Rounding::Floor), Some(3));
+        assert_eq!(mul_div(10, 1, 3, Rounding::Ceil), Some(4));
+        assert_eq!(mul_div(9, 1, 3, Rounding::Ceil), Some(3));
+        assert_eq!(mul_div(10, 1, 0, Rounding::Floor), None);
+    }
+
+    #[test]
+    fn extreme_prices_neither_overflow_nor_panic() {
+        // Memecoin worth a tiny fraction of the quote unit.
+        let tiny = AbsolutePrice::new_unsafe(1, u64::MAX);
+        assert_eq!(OnSide::Ask(tiny).checked_output(u64::MAX), Some(1));
+        assert_eq!(OnSide::Ask(tiny).checked_output(u64::MAX - 1), Some(0));
+        assert_eq!(OnSide::Bid(tiny).checked_output(2), None);
+        assert_eq!(OnSide::Bid(tiny).checked_output(1), Some(u64::MAX));
+        // Huge price.
+        let huge = AbsolutePrice::new_unsafe(u64::MAX, 1);
+        assert_eq!(OnSide::Ask(huge).checked_output(u64::MAX), None);
+        assert_eq!(OnSide::Bid(huge).checked_output(u64::MAX), Some(1));
+        // Zero price.
+        let zero = AbsolutePrice::zero();
+        assert_eq!(OnSide::Bid(zero).checked_output(1), None);
+        assert_eq!(OnSide::Ask(zero).checked_output(u64::MAX), Some(0));
+    }
+
+    #[test]
+    fn required_input_covers_output() {
+        let price = AbsolutePrice::new_unsafe(37, 100);
+        for output in [1, 36, 37, 1000, u32::MAX as u64] {
+            for side in [OnSide::Bid(price), OnSide::Ask(price)] {
+                let input = side.checked_required_input(output).unwrap();
+                assert!(side.checked_output(input).unwrap() >= output);
+                assert!(side.checked_output(input - 1).unwrap() < output);
+            }
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::execution_engine::liquidity_book::market_taker::MarketTaker;
 use crate::execution_engine::liquidity_book::types::{ExCostUnits, FeeAsset};
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-pub struct OrderWeight<CostUnits>(u64, CostUnits);
+pub struct OrderWeight<CostUnits>(u128, CostUnits);
 
 impl<U: PartialOrd> PartialOrd for OrderWeight<U> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -28,7 +28,43 @@ impl<U: Ord> Ord for OrderWeight<U> {
 
 impl<U> OrderWeight<U> {
     pub fn new(fee: FeeAsset<u64>, cost: U) -> Self {
-        Self(fee, cost)
+        Self(fee as u128, cost)
+    }
+
+    /// Weight of a fragment which arrived at [arrived_at] as prioritized by [W].
+    pub fn prioritized<W: WeightFn>(fee: FeeAsset<u64>, arrived_at: u64, cost: U) -> Self {
+        Self(W::priority(fee, arrived_at), cost)
+    }
+}
+
+/// Maps fee and arrival time of a fragment into its priority.
+pub trait WeightFn {
+    fn priority(fee: FeeAsset<u64>, arrived_at: u64) -> u128;
+}
+
+/// Priority is driven by fee alone.
+#[derive(Debug, Copy, Clone)]
+pub struct FeeDriven;
+
+impl WeightFn for FeeDriven {
+    fn priority(fee: FeeAsset<u64>, _: u64) -> u128 {
+        fee as u128
+    }
+}
+
+/// Each full [PERIOD] a fragment has been resting adds [BONUS] to its fee,
+/// so that among equally priced fragments the older ones are preferred.
+#[derive(Debug, Copy, Clone)]
+pub struct Aging<const BONUS: u64, const PERIOD: u64>;
+
+impl<const BONUS: u64, const PERIOD: u64> WeightFn for Aging<BONUS, PERIOD> {
+    fn priority(fee: FeeAsset<u64>, arrived_at: u64) -> u128 {
+        // Age is measured back from the end of time, which keeps relative
+        // priorities of fragments independent of the current time.
+        let periods_aged = (u64::MAX - arrived_at) / PERIOD;
+        (BONUS as u128)
+            .saturating_mul(periods_aged as u128)
+            .saturating_add(fee as u128)
     }
 }
 
@@ -41,13 +77,18 @@ where
     T: MarketTaker<U = U>,
 {
     fn weight(&self) -> OrderWeight<U> {
-        OrderWeight(self.fee(), self.marginal_cost_hint())
+        OrderWeight::new(self.fee(), self.marginal_cost_hint())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::execution_engine::liquidity_book::weight::OrderWeight;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::execution_engine::liquidity_book::weight::{Aging, FeeDriven, OrderWeight, WeightFn};
+
+    type TestAging = Aging<1_000, 60>;
 
     #[test]
     fn order_with_lower_cost_is_preferred() {
@@ -55,4 +96,55 @@ mod tests {
         let w2 = OrderWeight::new(100, 1001);
         assert!(w1 > w2);
     }
+
+    #[test]
+    fn older_order_is_preferred_given_same_fee() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let fee = rng.gen_range(0..u64::MAX / 2);
+            let cost = rng.gen::<u64>();
+            let older = rng.gen_range(0..u64::MAX - 60);
+            let newer = rng.gen_range(older + 60..=u64::MAX);
+            assert!(
+                OrderWeight::prioritized::<TestAging>(fee, older, cost)
+                    > OrderWeight::prioritized::<TestAging>(fee, newer, cost)
+            );
+        }
+    }
+
+    #[test]
+    fn priority_is_monotone_in_fee_and_age() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let fee = rng.gen_range(0..u64::MAX / 2);
+            let arrived_at = rng.gen_range(1..u64::MAX);
+            let higher_fee = rng.gen_range(fee..=u64::MAX);
+            let earlier = rng.gen_range(0..arrived_at);
+            assert!(TestAging::priority(higher_fee, arrived_at) >= TestAging::priority(fee, arrived_at));
+            assert!(TestAging::priority(fee, earlier) >= TestAging::priority(fee, arrived_at));
+        }
+    }
+
+    #[test]
+    fn aging_does_not_outweigh_fee_within_one_period() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let fee = rng.gen_range(0..u64::MAX / 2);
+            let older = rng.gen_range(0..u64::MAX / 2);
+            let newer = rng.gen_range(older..older + 60);
+            assert!(TestAging::priority(fee + 1_001, newer) > TestAging::priority(fee, older));
+        }
+    }
+
+    #[test]
+    fn fee_driven_ignores_arrival() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..1000 {
+            let fee = rng.gen::<u64>();
+            assert_eq!(
+                FeeDriven::priority(fee, rng.gen()),
+                FeeDriven::priority(fee, rng.gen())
+            );
+        }
+    }
 }
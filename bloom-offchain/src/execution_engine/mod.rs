@@ -1,42 +1,56 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::marker::PhantomData;
 use std::mem;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use either::Either;
 use futures::channel::mpsc;
 use futures::stream::FusedStream;
 use futures::{FutureExt, Stream};
 use futures::{SinkExt, StreamExt};
-use log::{trace, warn};
+use futures_timer::Delay;
 use tokio::sync::broadcast;
+use tracing::{debug_span, error, info, info_span, trace, trace_span, warn, Instrument};
 
 use liquidity_book::interpreter::RecipeInterpreter;
 use liquidity_book::stashing_option::StashingOption;
 use spectrum_offchain::backlog::HotBacklog;
 use spectrum_offchain::circular_filter::CircularFilter;
+use spectrum_offchain::clock::{Clock, SharedClock};
 use spectrum_offchain::combinators::Ior;
 use spectrum_offchain::data::event::{Channel, Confirmed, Predicted, StateUpdate, Unconfirmed};
 use spectrum_offchain::data::order::{OrderUpdate, SpecializedOrder};
 use spectrum_offchain::data::{Baked, EntitySnapshot, Has, Stable};
-use spectrum_offchain::maker::Maker;
-use spectrum_offchain::network::Network;
+use spectrum_offchain::executor::RunOrderError;
+use spectrum_offchain::maker::{Maker, Specialize};
+use spectrum_offchain::network::{FailedScripts, Network};
+use spectrum_offchain::quarantine::Quarantine;
+use spectrum_offchain::sync_progress::SyncLagGuard;
 use spectrum_offchain::tx_hash::CanonicalHash;
+use spectrum_offchain::tx_journal::{PendingTx, TxJournal};
 use spectrum_offchain::tx_prover::TxProver;
+use spectrum_offchain::tx_validator::TxValidator;
 
 use crate::execution_engine::backlog::SpecializedInterpreter;
 use crate::execution_engine::bundled::Bundled;
+use crate::execution_engine::circuit_breaker::PairCircuitBreaker;
+use crate::execution_engine::error::ExecutorError;
 use crate::execution_engine::execution_effect::ExecutionEff;
 use crate::execution_engine::focus_set::FocusSet;
 use crate::execution_engine::funding_effect::FundingEvent;
 use crate::execution_engine::liquidity_book::core::ExecutionRecipe;
-use crate::execution_engine::liquidity_book::interpreter::ExecutionResult;
+use crate::execution_engine::liquidity_book::interpreter::{ExecutionResult, RecipeRejected};
+use crate::execution_engine::liquidity_book::market_maker::{MarketMaker, PoolLifecycle};
 use crate::execution_engine::liquidity_book::market_taker::MarketTaker;
 use crate::execution_engine::liquidity_book::{ExternalTLBEvents, TLBFeedback, TemporalLiquidityBook};
 use crate::execution_engine::multi_pair::MultiPair;
+use crate::execution_engine::notifier::{Fill, FillNotifier};
+use crate::execution_engine::pool_stats::{PoolStatsRegistry, PoolTrade};
+use crate::execution_engine::quarantine::{MakerQuarantine, QuarantinePolicy};
 use crate::execution_engine::resolver::resolve_source_state;
 use crate::execution_engine::storage::kv_store::KvStore;
 use crate::execution_engine::storage::StateIndex;
@@ -44,16 +58,26 @@ use crate::execution_engine::storage::StateIndex;
 pub mod backlog;
 pub mod batch_exec;
 pub mod bundled;
+pub mod circuit_breaker;
+pub mod error;
 pub mod execution_effect;
+pub mod execution_report;
 mod focus_set;
 pub mod funding_effect;
 pub mod liquidity_book;
 pub mod multi_pair;
+pub mod notifier;
 pub mod partial_fill;
+pub mod pool_stats;
+pub mod quarantine;
+pub mod reserve_history;
 pub mod resolver;
 pub mod storage;
 pub mod types;
 
+/// Specialized orders which failed for a transient reason are returned to the backlog after this delay.
+const DEFERRED_ORDER_RETRY_DELAY: Duration = Duration::from_secs(60);
+
 /// Class of entities that evolve upon execution.
 type EvolvingEntity<CO, P, V, B> = Bundled<Either<Baked<CO, V>, Baked<P, V>>, B>;
 
@@ -109,7 +133,11 @@ pub fn execution_part_stream<
     Backlog,
     RecInterpreter,
     SpecInterpreter,
+    Validator,
     Prover,
+    Journal,
+    QuarantineStore,
+    Notifier,
     Net,
     Err,
 >(
@@ -120,27 +148,39 @@ pub fn execution_part_stream<
     context: Ctx,
     rec_interpreter: RecInterpreter,
     spec_interpreter: SpecInterpreter,
+    validator: Validator,
     prover: Prover,
+    journal: Journal,
+    pending_tx_ttl: Duration,
+    unconfirmed_ttl_slots: u64,
+    quarantine_policy: QuarantinePolicy,
+    quarantine: QuarantineStore,
+    notifier: Notifier,
+    pool_stats: PoolStatsRegistry<StableId>,
+    circuit_breaker: PairCircuitBreaker<Pair>,
     upstream: Upstream,
     funding: Funding,
     network: Net,
     mut tip_reached_signal: broadcast::Receiver<bool>,
+    lag_guard: SyncLagGuard,
+    // Books are kept in sync, but never acted upon.
+    read_only: bool,
 ) -> impl Stream<Item = ()> + 'a
 where
     Upstream: Stream<Item = (Pair, Event<CompOrd, SpecOrd, Pool, Bearer, Ver>)> + Unpin + 'a,
     Funding: Stream<Item = FundingEvent<Bearer>> + Unpin + 'a,
     Pair: Copy + Eq + Ord + Hash + Display + Unpin + 'a,
-    StableId: Copy + Eq + Hash + Debug + Display + Unpin + 'a,
+    StableId: Copy + Eq + Ord + Hash + Debug + Display + Unpin + 'a,
     Ver: Copy + Eq + Hash + Display + Unpin + 'a,
-    Pool: Stable<StableId = StableId> + Copy + Debug + Unpin + Display + 'a,
+    Pool: Stable<StableId = StableId> + MarketMaker + Copy + Debug + Unpin + Display + 'a,
     CompOrd: Stable<StableId = StableId> + MarketTaker<U = ExUnits> + Copy + Debug + Unpin + Display + 'a,
     SpecOrd: SpecializedOrder<TPoolId = StableId, TOrderId = Ver> + Debug + Unpin + 'a,
     Bearer: Has<Ver> + Eq + Ord + Clone + Debug + Unpin + 'a,
     TxCandidate: Unpin + 'a,
     Tx: CanonicalHash<Hash = TxHash> + Unpin + 'a,
-    TxHash: Display + Unpin + 'a,
-    Ctx: Clone + Unpin + 'a,
-    MakerCtx: Clone + Unpin + 'a,
+    TxHash: Eq + Hash + Clone + Display + Unpin + 'a,
+    Ctx: Has<SharedClock> + Clone + Unpin + 'a,
+    MakerCtx: Specialize<Pair> + Clone + Unpin + 'a,
     Index: StateIndex<EvolvingEntity<CompOrd, Pool, Ver, Bearer>> + Unpin + 'a,
     Cache: KvStore<StableId, EvolvingEntity<CompOrd, Pool, Ver, Bearer>> + Unpin + 'a,
     Book: TemporalLiquidityBook<CompOrd, Pool>
@@ -152,7 +192,11 @@ where
     Backlog: HotBacklog<Bundled<SpecOrd, Bearer>> + Maker<MakerCtx> + Unpin + 'a,
     RecInterpreter: RecipeInterpreter<CompOrd, Pool, Ctx, Ver, Bearer, TxCandidate> + Unpin + 'a,
     SpecInterpreter: SpecializedInterpreter<Pool, SpecOrd, Ver, TxCandidate, Bearer, Ctx> + Unpin + 'a,
+    Validator: TxValidator<TxCandidate, Bearer> + Unpin + 'a,
     Prover: TxProver<TxCandidate, Tx> + Unpin + 'a,
+    Journal: TxJournal<Pair, TxHash, Ver> + Unpin + 'a,
+    QuarantineStore: Quarantine<Pair, StableId> + Unpin + 'a,
+    Notifier: FillNotifier<StableId, TxHash> + Unpin + 'a,
     Net: Network<Tx, Err> + Clone + 'a,
     Err: TryInto<HashSet<Ver>> + Clone + Unpin + Debug + Display + 'a,
 {
@@ -165,10 +209,21 @@ where
         context,
         rec_interpreter,
         spec_interpreter,
+        validator,
         prover,
+        journal,
+        pending_tx_ttl,
+        unconfirmed_ttl_slots,
+        quarantine_policy,
+        quarantine,
+        notifier,
+        pool_stats,
+        circuit_breaker,
         upstream,
         funding,
         feedback_in,
+        lag_guard,
+        read_only,
     );
     let wait_signal = async move {
         let _ = tip_reached_signal.recv().await;
@@ -178,10 +233,15 @@ where
             executor.then(move |tx| {
                 let mut network = network.clone();
                 let mut feedback = feedback_out.clone();
+                let tx_hash = tx.canonical_hash();
+                let span = info_span!("submit_tx", tx = %tx_hash);
                 async move {
                     let result = network.submit_tx(tx).await;
-                    feedback.send(result).await.expect("Filed to propagate feedback.");
+                    if feedback.send((tx_hash, result)).await.is_err() {
+                        warn!("Failed to propagate feedback");
+                    }
                 }
+                .instrument(span)
             })
         })
         .flatten_stream()
@@ -208,7 +268,11 @@ pub struct Executor<
     Backlog,
     TradeInterpreter,
     SpecInterpreter,
+    Validator,
     Prover,
+    Journal,
+    QuarantineStore,
+    Notifier,
     Err,
 > {
     /// Storage for all on-chain states.
@@ -222,23 +286,116 @@ pub struct Executor<
     context: Ctx,
     trade_interpreter: TradeInterpreter,
     spec_interpreter: SpecInterpreter,
+    /// Dry-run checks of TX candidates before they are proved.
+    validator: Validator,
     prover: Prover,
+    /// Persists in-flight TXs so that their effects can be reconciled after restart.
+    journal: Journal,
+    /// TXs left in-flight by the previous run, their pairs are not matched until these are settled.
+    recovering: Vec<PendingTx<Pair, TxHash, Ver>>,
+    pending_tx_ttl: Duration,
+    /// Unconfirmed and predicted states not confirmed within this number of slots are dropped.
+    unconfirmed_ttl_slots: u64,
+    /// Pair each evolving entity belongs to.
+    entity_pairs: HashMap<StableId, Pair>,
+    /// Pools awaiting initial liquidity by pair, deposits into them take precedence over matchmaking.
+    bootstrapping_pools: HashMap<Pair, HashSet<StableId>>,
+    /// Makers excluded from matchmaking after repeated deterministic failures.
+    quarantine: MakerQuarantine<Pair, StableId, QuarantineStore>,
+    /// Specialized orders by ID which may succeed later along with their pair and UNIX time (seconds)
+    /// they are returned to the backlog at.
+    deferred_orders: HashMap<Ver, (Pair, u64, Bundled<SpecOrd, Bearer>)>,
+    /// Pairs taken out of focus while paused by the circuit breaker, refocused once it resumes them.
+    paused_pairs: HashSet<Pair>,
+    /// Wakes the executor up once deferred work is due, along with UNIX time (seconds) it fires at.
+    wakeup: Option<(u64, Delay)>,
+    /// Keeps order owners informed about fills and removals of their orders.
+    notifier: Notifier,
     upstream: Upstream,
     funding_events: Funding,
     funding_pool: BTreeSet<Bearer>,
     /// Feedback channel is used to signal the status of transaction submitted earlier by the executor.
-    feedback: mpsc::Receiver<Result<(), Err>>,
-    /// Pending effects resulted from execution of a batch trade in a certain [Pair].
-    pending_effects: Vec<Effects<Pair, TxHash, CompOrd, SpecOrd, Pool, Ver, Bearer>>,
+    feedback: mpsc::Receiver<(TxHash, Result<(), Err>)>,
+    /// Pending effects of submitted transactions by TX hash.
+    pending_effects: HashMap<TxHash, Vec<Effects<Pair, TxHash, CompOrd, SpecOrd, Pool, Ver, Bearer>>>,
+    /// Fills are reported only once the network accepts the TX.
+    pending_fills: HashMap<TxHash, Vec<Fill<StableId>>>,
+    /// Makers involved in submitted recipes by TX hash.
+    pending_makers: HashMap<TxHash, Vec<StableId>>,
+    /// Swaps through pools by TX hash, accounted in stats once the network accepts the TX.
+    pending_pool_trades: HashMap<TxHash, Vec<PoolTrade<StableId>>>,
+    pool_stats: PoolStatsRegistry<StableId>,
+    /// Pairs with anomalous prices are not matched until the breaker cools down.
+    circuit_breaker: PairCircuitBreaker<Pair>,
     /// Which pair should we process in the first place.
     focus_set: FocusSet<Pair>,
     /// Temporarily memoize entities that came from unconfirmed updates.
     skip_filter: CircularFilter<256, Ver>,
+    /// Matchmaking is paused while chain sync lags behind.
+    lag_guard: SyncLagGuard,
+    /// Books are kept in sync, but no recipes are ever attempted.
+    read_only: bool,
+    /// Source of wall-clock time.
+    clock: SharedClock,
     pd: PhantomData<(StableId, Ver, TxCandidate, Tx, Err)>,
 }
 
-impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E>
-    Executor<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E>
+impl<
+        S,
+        F,
+        PR,
+        SID,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    >
+    Executor<
+        S,
+        F,
+        PR,
+        SID,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    >
 {
     fn new(
         index: IX,
@@ -248,11 +405,39 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         context: C,
         trade_interpreter: RIR,
         spec_interpreter: SIR,
+        validator: VAL,
         prover: PRV,
+        mut journal: JRN,
+        pending_tx_ttl: Duration,
+        unconfirmed_ttl_slots: u64,
+        quarantine_policy: QuarantinePolicy,
+        quarantine: QRN,
+        notifier: NTF,
+        pool_stats: PoolStatsRegistry<SID>,
+        circuit_breaker: PairCircuitBreaker<PR>,
         upstream: S,
         funding_events: F,
-        feedback: mpsc::Receiver<Result<(), E>>,
-    ) -> Self {
+        feedback: mpsc::Receiver<(TH, Result<(), E>)>,
+        lag_guard: SyncLagGuard,
+        read_only: bool,
+    ) -> Self
+    where
+        PR: Copy + Display,
+        SID: Copy + Eq + Hash + Display,
+        JRN: TxJournal<PR, TH, V>,
+        QRN: Quarantine<PR, SID>,
+        C: Has<SharedClock>,
+    {
+        let clock = context.select::<SharedClock>();
+        let now = clock.unix_time_secs();
+        let mut recovering = vec![];
+        for tx in journal.pending() {
+            if now.saturating_sub(tx.submitted_at) < pending_tx_ttl.as_secs() {
+                recovering.push(tx);
+            } else {
+                journal.resolve(&tx.tx_hash);
+            }
+        }
         Self {
             index,
             cache,
@@ -261,25 +446,113 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
             context,
             trade_interpreter,
             spec_interpreter,
+            validator,
             prover,
+            journal,
+            recovering,
+            pending_tx_ttl,
+            unconfirmed_ttl_slots,
+            entity_pairs: HashMap::new(),
+            bootstrapping_pools: HashMap::new(),
+            quarantine: MakerQuarantine::new(quarantine_policy, quarantine),
+            deferred_orders: HashMap::new(),
+            paused_pairs: HashSet::new(),
+            wakeup: None,
+            notifier,
             upstream,
             funding_events,
             funding_pool: BTreeSet::new(),
             feedback,
-            pending_effects: Vec::new(),
+            pending_effects: HashMap::new(),
+            pending_fills: HashMap::new(),
+            pending_makers: HashMap::new(),
+            pending_pool_trades: HashMap::new(),
+            pool_stats,
+            circuit_breaker,
             focus_set: FocusSet::new(),
             skip_filter: CircularFilter::new(),
+            lag_guard,
+            read_only,
+            clock,
             pd: Default::default(),
         }
     }
 
+    /// Check TX candidate before it gets proved.
+    fn dry_run(&self, txc: &TC, consumed_bearers: &[B]) -> Result<(), VAL::Error>
+    where
+        VAL: TxValidator<TC, B>,
+    {
+        self.validator.validate(txc, consumed_bearers)
+    }
+
+    fn on_tx_submitted(&mut self, pair: PR, tx_hash: TH, consumed_versions: &HashSet<V>)
+    where
+        V: Copy,
+        JRN: TxJournal<PR, TH, V>,
+    {
+        self.journal.record(PendingTx {
+            pair,
+            tx_hash,
+            consumed_versions: consumed_versions.iter().copied().collect(),
+            submitted_at: self.clock.unix_time_secs(),
+        });
+    }
+
+    fn is_recovering(&self, pair: &PR) -> bool
+    where
+        PR: Eq,
+    {
+        self.recovering.iter().any(|tx| tx.pair == *pair)
+    }
+
+    /// Taker whose consumption is confirmed by the ledger while it was not executed by us,
+    /// i.e. the order was cancelled or executed by someone else.
+    fn removed_elsewhere(&self, event: &Event<CO, SO, P, B, V>) -> Option<SID>
+    where
+        V: Copy + Eq + Hash,
+        CO: Stable<StableId = SID>,
+    {
+        match event {
+            Either::Left(Channel::Ledger(Confirmed(StateUpdate::Transition(Ior::Left(Bundled(
+                Either::Left(order),
+                _,
+            ))))))
+                if !self.skip_filter.contains(&order.version) =>
+            {
+                Some(order.entity.stable_id())
+            }
+            _ => None,
+        }
+    }
+
+    /// TX left by the previous run is settled either once the ledger confirms
+    /// consumption of any of its inputs or when it expires.
+    fn settle_recovering<Pred>(&mut self, is_settled: Pred)
+    where
+        PR: Copy + Display,
+        TH: Display,
+        JRN: TxJournal<PR, TH, V>,
+        Pred: Fn(&PendingTx<PR, TH, V>) -> bool,
+    {
+        let (settled, recovering) = mem::take(&mut self.recovering)
+            .into_iter()
+            .partition::<Vec<_>, _>(|tx| is_settled(tx));
+        self.recovering = recovering;
+        for tx in settled {
+            trace!("TX {} left by previous run is settled", tx.tx_hash);
+            self.journal.resolve(&tx.tx_hash);
+            self.focus_set.push_back(tx.pair);
+        }
+    }
+
     fn sync_backlog(&mut self, pair: &PR, update: Channel<OrderUpdate<Bundled<SO, B>, SO>>)
     where
         PR: Copy + Eq + Hash + Display,
         V: Copy + Eq + Hash + Display,
         SO: SpecializedOrder<TOrderId = V>,
         L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
     {
         let is_confirmed = matches!(update, Channel::Ledger(_));
         let (Channel::Ledger(Confirmed(upd))
@@ -294,6 +567,7 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
             }
             OrderUpdate::Eliminated(elim_order) => {
                 let elim_order_id = elim_order.get_self_ref();
+                self.deferred_orders.remove(&elim_order_id);
                 if is_confirmed {
                     self.multi_backlog.get_mut(pair).remove(elim_order_id);
                 } else {
@@ -312,34 +586,298 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         SID: Copy + Eq + Hash + Display + Debug,
         V: Copy + Eq + Hash + Display,
         B: Clone,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
         CO: Stable<StableId = SID> + Clone,
-        P: Stable<StableId = SID> + Clone,
+        P: Stable<StableId = SID> + MarketMaker + Clone,
         IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
         CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
         TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
+    {
+        trace!(
+            target: "executor",
+            "syncing book pair: {}, transition: {}",
+            pair,
+            transition.as_ref().bimap(entity_id, entity_id).summary()
+        );
+        match &transition {
+            Ior::Left(Either::Right(maker)) => {
+                self.track_bootstrapping(*pair, maker.entity.stable_id(), false)
+            }
+            Ior::Both(_, Either::Right(maker)) | Ior::Right(Either::Right(maker)) => {
+                let bootstrapping = maker.entity.lifecycle() == PoolLifecycle::Bootstrapping;
+                self.track_bootstrapping(*pair, maker.entity.stable_id(), bootstrapping)
+            }
+            _ => {}
+        }
+        if let Ior::Both(_, Either::Right(maker)) | Ior::Right(Either::Right(maker)) = &transition {
+            if self.quarantine.is_quarantined(&maker.entity.stable_id()) {
+                trace!(target: "executor", "maker {} is quarantined", maker.entity.stable_id());
+                return;
+            }
+        }
+        match book_update(transition) {
+            BookUpdate::RemoveTaker(taker) => self.multi_book.get_mut(pair).remove_taker(taker),
+            BookUpdate::RemoveMaker(maker) => self.multi_book.get_mut(pair).remove_maker(maker),
+            BookUpdate::ReplaceTaker(old, new) => {
+                self.multi_book.get_mut(pair).remove_taker(old);
+                self.multi_book.get_mut(pair).update_taker(new);
+            }
+            BookUpdate::UpdateTaker(taker) => self.multi_book.get_mut(pair).update_taker(taker),
+            BookUpdate::UpdateMaker(maker) => self.multi_book.get_mut(pair).update_maker(maker),
+            BookUpdate::Incoherent(taker) => {
+                self.report(ExecutorError::IncoherentTransition(taker.stable_id(), *pair));
+            }
+        }
+    }
+
+    fn track_bootstrapping(&mut self, pair: PR, maker: SID, bootstrapping: bool)
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+    {
+        let pools = self.bootstrapping_pools.entry(pair).or_default();
+        if bootstrapping {
+            if pools.insert(maker) {
+                trace!(target: "executor", "pool {} in pair {} is bootstrapping", maker, pair);
+            }
+        } else if pools.remove(&maker) {
+            trace!(target: "executor", "pool {} in pair {} is done bootstrapping", maker, pair);
+        }
+        if pools.is_empty() {
+            self.bootstrapping_pools.remove(&pair);
+        }
+    }
+
+    /// Alert on inconsistent state. Affected work is skipped by the caller.
+    fn report(&self, err: ExecutorError<SID, PR>)
+    where
+        PR: Display,
+        SID: Display,
+    {
+        error!(target: "executor", "{}", err);
+    }
+
+    /// Exclude makers which keep breaking recipes in the given pair from matchmaking.
+    fn on_makers_failed(&mut self, pair: PR, makers: Vec<SID>)
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+        MC: Specialize<PR> + Clone,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
+    {
+        for maker in self
+            .quarantine
+            .on_failure(pair, makers, self.clock.unix_time_secs())
+        {
+            if let Some(Bundled(Either::Right(pool), _)) = self.cache.get(maker) {
+                self.multi_book.get_mut(&pair).remove_maker(pool.entity);
+            }
+        }
+    }
+
+    /// Return quarantined makers due for a retest to matchmaking.
+    fn retest_quarantined(&mut self)
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+        MC: Specialize<PR> + Clone,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
+    {
+        for (pair, maker) in self.quarantine.release_for_retest(self.clock.unix_time_secs()) {
+            if let Some(Bundled(Either::Right(pool), _)) = self.cache.get(maker) {
+                self.multi_book.get_mut(&pair).update_maker(pool.entity);
+                self.focus_set.push_back(pair);
+            }
+        }
+    }
+
+    /// Make sure the executor is woken up at the given UNIX time (seconds).
+    fn schedule_wakeup(&mut self, at: u64) {
+        if self
+            .wakeup
+            .as_ref()
+            .map_or(true, |(scheduled, _)| at < *scheduled)
+        {
+            let delay = at.saturating_sub(self.clock.unix_time_secs());
+            self.wakeup = Some((at, Delay::new(Duration::from_secs(delay))));
+        }
+    }
+
+    /// Retry the specialized order later, e.g. once the pool or fee estimates change.
+    fn defer_order(&mut self, pair: PR, order: Bundled<SO, B>)
+    where
+        V: Copy + Eq + Hash,
+        SO: SpecializedOrder<TOrderId = V>,
+    {
+        let retry_at = self.clock.unix_time_secs() + DEFERRED_ORDER_RETRY_DELAY.as_secs();
+        self.deferred_orders
+            .insert(order.get_self_ref(), (pair, retry_at, order));
+        self.schedule_wakeup(retry_at);
+    }
+
+    /// Return deferred specialized orders which are due for a retry to their backlogs.
+    fn release_deferred_orders(&mut self)
+    where
+        PR: Copy + Eq + Hash + Display,
+        V: Copy + Eq + Hash,
+        MC: Specialize<PR> + Clone,
+        L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+    {
+        let now = self.clock.unix_time_secs();
+        let due = self
+            .deferred_orders
+            .iter()
+            .filter(|(_, (_, retry_at, _))| *retry_at <= now)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in due {
+            if let Some((pair, _, order)) = self.deferred_orders.remove(&id) {
+                self.multi_backlog.get_mut(&pair).put(order);
+                self.focus_set.push_back(pair);
+            }
+        }
+        if let Some(next_retry) = self.deferred_orders.values().map(|(_, at, _)| *at).min() {
+            self.schedule_wakeup(next_retry);
+        }
+    }
+
+    /// Return pairs resumed by the circuit breaker to the focus set.
+    fn resume_paused_pairs(&mut self)
+    where
+        PR: Copy + Eq + Hash + Display,
+    {
+        let now = self.clock.unix_time_secs();
+        for pair in self.paused_pairs.clone() {
+            if let Some(until) = self.circuit_breaker.tripped_until(&pair, now) {
+                self.schedule_wakeup(until);
+            } else {
+                self.paused_pairs.remove(&pair);
+                self.focus_set.push_back(pair);
+            }
+        }
+    }
+
+    /// Try to execute the best specialized order in the given pair.
+    fn attempt_backlog(&mut self, focus_pair: PR) -> Option<TX>
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+        V: Copy + Eq + Hash + Display,
+        B: Clone,
+        MC: Specialize<PR> + Clone,
+        C: Clone,
+        SO: SpecializedOrder<TPoolId = SID, TOrderId = V>,
+        TX: CanonicalHash<Hash = TH>,
+        TH: Clone,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+        SIR: SpecializedInterpreter<P, SO, V, TC, B, C>,
+        VAL: TxValidator<TC, B>,
+        PRV: TxProver<TC, TX>,
+        JRN: TxJournal<PR, TH, V>,
+    {
+        let _span = debug_span!("backlog_attempt", pair = %focus_pair).entered();
+        let next_order = self.multi_backlog.get_mut(&focus_pair).try_pop()?;
+        self.execute_backlog_order(focus_pair, next_order)
+    }
+
+    /// Try to execute the best deposit into a bootstrapping pool in the given pair.
+    fn attempt_bootstrap_deposit(&mut self, focus_pair: PR) -> Option<TX>
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+        V: Copy + Eq + Hash + Display,
+        B: Clone,
+        MC: Specialize<PR> + Clone,
+        C: Clone,
+        SO: SpecializedOrder<TPoolId = SID, TOrderId = V>,
+        TX: CanonicalHash<Hash = TH>,
+        TH: Clone,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+        SIR: SpecializedInterpreter<P, SO, V, TC, B, C>,
+        VAL: TxValidator<TC, B>,
+        PRV: TxProver<TC, TX>,
+        JRN: TxJournal<PR, TH, V>,
+    {
+        let _span = debug_span!("bootstrap_deposit_attempt", pair = %focus_pair).entered();
+        let bootstrapping_pools = self.bootstrapping_pools.get(&focus_pair)?;
+        let next_order = self
+            .multi_backlog
+            .get_mut(&focus_pair)
+            .try_pop_where(|ord| ord.is_deposit() && bootstrapping_pools.contains(&ord.get_pool_ref()))?;
+        self.execute_backlog_order(focus_pair, next_order)
+    }
+
+    fn execute_backlog_order(&mut self, focus_pair: PR, next_order: Bundled<SO, B>) -> Option<TX>
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Display,
+        V: Copy + Eq + Hash + Display,
+        B: Clone,
+        MC: Specialize<PR> + Clone,
+        C: Clone,
+        SO: SpecializedOrder<TPoolId = SID, TOrderId = V>,
+        TX: CanonicalHash<Hash = TH>,
+        TH: Clone,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+        SIR: SpecializedInterpreter<P, SO, V, TC, B, C>,
+        VAL: TxValidator<TC, B>,
+        PRV: TxProver<TC, TX>,
+        JRN: TxJournal<PR, TH, V>,
     {
-        trace!(target: "executor", "syncing book pair: {}", pair);
-        match transition {
-            Ior::Left(e) => match e {
-                Either::Left(o) => self.multi_book.get_mut(pair).remove_taker(o.entity),
-                Either::Right(p) => self.multi_book.get_mut(pair).remove_maker(p.entity),
-            },
-            Ior::Both(old, new) => match (old, new) {
-                (Either::Left(old), Either::Left(new)) => {
-                    self.multi_book.get_mut(pair).remove_taker(old.entity);
-                    self.multi_book.get_mut(pair).update_taker(new.entity);
+        if let Some(Bundled(Either::Right(pool), pool_bearer)) = self.cache.get(next_order.0.get_pool_ref()) {
+            let ctx = self.context.clone();
+            let consumed_bearers = vec![pool_bearer.clone(), next_order.1.clone()];
+            let result = self
+                .spec_interpreter
+                .try_run(Bundled(pool.entity, pool_bearer), next_order, ctx);
+            let (txc, updated_pool, consumed_ord) = match result {
+                Ok(res) => res,
+                Err(RunOrderError::NonFatal(reason, order)) => {
+                    info!("Order {} is deferred: {}", order.get_self_ref(), reason);
+                    self.defer_order(focus_pair, order);
+                    return None;
                 }
-                (_, Either::Right(new)) => {
-                    self.multi_book.get_mut(pair).update_maker(new.entity);
+                Err(RunOrderError::Fatal(reason, order)) => {
+                    info!("Order {} dropped due to error: {}", order.get_self_ref(), reason);
+                    return None;
                 }
-                _ => unreachable!(),
-            },
-            Ior::Right(new) => match new {
-                Either::Left(new) => self.multi_book.get_mut(pair).update_taker(new.entity),
-                Either::Right(new) => self.multi_book.get_mut(pair).update_maker(new.entity),
-            },
+            };
+            if let Err(err) = self.dry_run(&txc, &consumed_bearers) {
+                // Pool may have changed since the candidate was built, so the order is retried later.
+                warn!(
+                    "Order {} is deferred, TX candidate rejected by dry run: {}",
+                    consumed_ord.get_self_ref(),
+                    err
+                );
+                self.defer_order(focus_pair, consumed_ord);
+                return None;
+            }
+            let tx = self.prover.prove(txc);
+            let tx_hash = tx.canonical_hash();
+            let consumed_versions = HashSet::from_iter(vec![pool.version, consumed_ord.get_self_ref()]);
+            self.on_tx_submitted(focus_pair, tx_hash.clone(), &consumed_versions);
+            self.pending_effects.insert(
+                tx_hash.clone(),
+                vec![Effects::Pair(ExecutionEffectsByPair {
+                    pair: focus_pair,
+                    tx_hash,
+                    consumed_versions,
+                    pending_effects: ExecutionEffects::FromBacklog(updated_pool, consumed_ord),
+                })],
+            );
+            // Return pair to focus set to make sure corresponding TLB will be exhausted.
+            self.focus_set.push_back(focus_pair);
+            return Some(tx);
         }
+        None
     }
 
     fn cache<T>(&mut self, new_entity_state: Bundled<T, B>) -> Option<Ior<T, T>>
@@ -366,33 +904,107 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         SID: Copy + Eq + Hash + Debug + Display,
         V: Copy + Eq + Hash + Display,
         B: Clone + Debug,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
         CO: Stable<StableId = SID> + Clone + Display,
-        P: Stable<StableId = SID> + Clone,
+        P: Stable<StableId = SID> + MarketMaker + Clone,
         IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
         CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
         TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
     {
         for ver in versions {
+            self.index.begin_batch();
             if let Some(stable_id) = self.index.invalidate_version(ver) {
                 trace!("Invalidating snapshot {} of {}", ver, stable_id);
-                let maybe_transition = match resolve_source_state(stable_id, &self.index) {
-                    None => self
-                        .cache
-                        .remove(stable_id)
-                        .map(|Bundled(elim_state, _)| Ior::Left(elim_state)),
-                    Some(latest_state) => self.cache(latest_state),
-                };
-                if let Some(tr) = maybe_transition {
-                    trace!("Resulting transition is {}", tr);
-                    self.sync_book(pair, tr);
-                }
+                self.resync_entity(pair, stable_id);
             }
+            self.index.commit_batch();
         }
     }
 
+    /// Drop unconfirmed states which stayed so for too long.
+    fn expire_unconfirmed(&mut self)
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Debug + Display,
+        V: Copy + Eq + Hash + Display,
+        B: Clone + Debug,
+        MC: Specialize<PR> + Clone,
+        CO: Stable<StableId = SID> + Clone + Display,
+        P: Stable<StableId = SID> + MarketMaker + Clone,
+        IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
+    {
+        self.index.begin_batch();
+        let expired = self
+            .index
+            .expire_unconfirmed(self.lag_guard.current_slot(), self.unconfirmed_ttl_slots);
+        if !expired.is_empty() {
+            warn!(
+                "Unconfirmed states of {} entities expired, {} states expired since start",
+                expired.len(),
+                self.index.expired_total()
+            );
+        }
+        for stable_id in expired {
+            if let Some(pair) = self.entity_pairs.get(&stable_id).copied() {
+                self.resync_entity(&pair, stable_id);
+                self.focus_set.push_back(pair);
+            }
+        }
+        self.index.commit_batch();
+    }
+
+    /// Bring cache and book in line with the latest state of the entity in the index.
+    fn resync_entity(&mut self, pair: &PR, stable_id: SID)
+    where
+        PR: Copy + Eq + Hash + Display,
+        SID: Copy + Eq + Hash + Debug + Display,
+        V: Copy + Eq + Hash + Display,
+        B: Clone + Debug,
+        MC: Specialize<PR> + Clone,
+        CO: Stable<StableId = SID> + Clone + Display,
+        P: Stable<StableId = SID> + MarketMaker + Clone,
+        IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
+        CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
+        TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
+    {
+        let maybe_transition = match resolve_source_state(stable_id, &self.index) {
+            None => self
+                .cache
+                .remove(stable_id)
+                .map(|Bundled(elim_state, _)| Ior::Left(elim_state)),
+            Some(latest_state) => self.cache(latest_state),
+        };
+        if let Some(tr) = maybe_transition {
+            trace!("Resulting transition is {}", tr);
+            self.sync_book(pair, tr);
+        }
+    }
+
+    /// Writes to index and cache caused by the update are committed in a single batch.
     fn update_state<T>(&mut self, update: Channel<StateUpdate<Bundled<T, B>>>) -> Option<Ior<T, T>>
     where
+        PR: Display,
+        SID: Copy + Eq + Hash + Display,
+        V: Copy + Eq + Hash + Display,
+        T: EntitySnapshot<StableId = SID, Version = V> + Clone,
+        B: Clone,
+        IX: StateIndex<Bundled<T, B>>,
+        CH: KvStore<SID, Bundled<T, B>>,
+    {
+        self.index.begin_batch();
+        let res = self.apply_state_update(update);
+        self.index.commit_batch();
+        res
+    }
+
+    fn apply_state_update<T>(&mut self, update: Channel<StateUpdate<Bundled<T, B>>>) -> Option<Ior<T, T>>
+    where
+        PR: Display,
         SID: Copy + Eq + Hash + Display,
         V: Copy + Eq + Hash + Display,
         T: EntitySnapshot<StableId = SID, Version = V> + Clone,
@@ -434,7 +1046,10 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
                 }
                 match resolve_source_state(id, &self.index) {
                     Some(latest_state) => self.cache(latest_state),
-                    None => unreachable!(),
+                    None => {
+                        self.report(ExecutorError::UnresolvedState(id));
+                        None
+                    }
                 }
             }
             StateUpdate::Transition(Ior::Left(st)) | StateUpdate::TransitionRollback(Ior::Left(st)) => {
@@ -456,21 +1071,25 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         SID: Eq + Hash + Copy + Display + Debug,
         V: Eq + Hash + Copy + Display,
         B: Clone + Debug,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
         PR: Eq + Hash + Copy + Display,
         SO: SpecializedOrder<TOrderId = V>,
         CO: Stable<StableId = SID> + Copy + Debug,
-        P: Stable<StableId = SID> + Copy,
+        P: Stable<StableId = SID> + MarketMaker + Copy,
         CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
-        TH: Display,
+        TH: Eq + Hash + Display,
         IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
         TLB: ExternalTLBEvents<CO, P> + TLBFeedback<CO, P> + Maker<MC>,
         L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
     {
         trace!("TX {} succeeded", tx_hash);
         match pending_effects {
             ExecutionEffects::FromLiquidityBook(mut pending_effects) => {
                 self.multi_book.get_mut(&pair).on_recipe_succeeded();
+                if let Some(makers) = self.pending_makers.remove(&tx_hash) {
+                    self.quarantine.on_success(makers);
+                }
                 while let Some(effect) = pending_effects.pop() {
                     let tr = match effect {
                         ExecutionEff::Updated(elim, upd) => {
@@ -514,19 +1133,22 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         SID: Eq + Hash + Copy + Display + Debug,
         V: Eq + Hash + Copy + Display,
         B: Clone + Debug,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
         PR: Eq + Hash + Copy + Display,
         SO: SpecializedOrder<TOrderId = V>,
         CO: Stable<StableId = SID> + Copy + Display,
-        P: Stable<StableId = SID> + Copy,
+        P: Stable<StableId = SID> + MarketMaker + Copy,
         CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
-        TH: Display,
+        TH: Eq + Hash + Display,
         IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
         TLB: ExternalTLBEvents<CO, P> + TLBFeedback<CO, P> + Maker<MC>,
         L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
-        E: TryInto<HashSet<V>> + Unpin + Debug + Display,
+        E: TryInto<HashSet<V>> + FailedScripts<V> + Unpin + Debug + Display,
+        QRN: Quarantine<PR, SID>,
     {
         warn!("TX {} failed {:?}", tx_hash, err);
+        let makers = self.pending_makers.remove(&tx_hash).unwrap_or_default();
+        let failed_inputs = err.failed_script_inputs();
         if let Ok(missing_bearers) = err.try_into() {
             match pending_effects {
                 ExecutionEffects::FromLiquidityBook(_) => {
@@ -552,6 +1174,17 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
             match pending_effects {
                 ExecutionEffects::FromLiquidityBook(_) => {
                     self.multi_book.get_mut(&pair).on_recipe_failed();
+                    // Only makers whose own scripts rejected the TX are blamed,
+                    // other failures may be caused by the rest of the recipe or be transient.
+                    let failed_makers = makers
+                        .into_iter()
+                        .filter(|maker| {
+                            self.cache.get(*maker).map_or(false, |Bundled(entity, _)| {
+                                failed_inputs.contains(&entity.either(|t| t.version, |m| m.version))
+                            })
+                        })
+                        .collect();
+                    self.on_makers_failed(pair, failed_makers);
                 }
                 ExecutionEffects::FromBacklog(_, order) => {
                     self.multi_backlog.get_mut(&pair).put(order);
@@ -606,19 +1239,23 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
         SID: Eq + Hash + Copy + Display + Debug,
         V: Eq + Hash + Copy + Display,
         B: Clone + Debug,
-        MC: Clone,
+        MC: Specialize<PR> + Clone,
         PR: Eq + Hash + Copy + Display,
         SO: SpecializedOrder<TOrderId = V>,
         CO: Stable<StableId = SID> + Copy + Debug,
-        P: Stable<StableId = SID> + Copy,
+        P: Stable<StableId = SID> + MarketMaker + Copy,
         CH: KvStore<SID, EvolvingEntity<CO, P, V, B>>,
         IX: StateIndex<EvolvingEntity<CO, P, V, B>>,
         TLB: ExternalTLBEvents<CO, P> + Maker<MC>,
         L: HotBacklog<Bundled<SO, B>> + Maker<MC>,
+        QRN: Quarantine<PR, SID>,
     {
         match event {
             Either::Left(evolving_entity) => {
+                self.entity_pairs
+                    .insert(updated_entity_id(&evolving_entity), pair);
                 if let Some(upd) = self.update_state(evolving_entity) {
+                    let _span = trace_span!("book_sync", %pair).entered();
                     self.sync_book(&pair, upd)
                 }
             }
@@ -642,69 +1279,156 @@ impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR
     }
 }
 
-impl<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, U, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E> Stream
-    for Executor<S, F, PR, SID, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E>
+impl<
+        S,
+        F,
+        PR,
+        SID,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        U,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    > Stream
+    for Executor<
+        S,
+        F,
+        PR,
+        SID,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    >
 where
     S: Stream<Item = (PR, Event<CO, SO, P, B, V>)> + Unpin,
     F: Stream<Item = FundingEvent<B>> + Unpin,
     PR: Copy + Eq + Ord + Hash + Display + Unpin,
-    SID: Copy + Eq + Hash + Debug + Display + Unpin,
+    SID: Copy + Eq + Ord + Hash + Debug + Display + Unpin,
     V: Copy + Eq + Hash + Display + Unpin,
-    P: Stable<StableId = SID> + Copy + Debug + Unpin + Display,
+    P: Stable<StableId = SID> + MarketMaker + Copy + Debug + Unpin + Display,
     CO: Stable<StableId = SID> + MarketTaker<U = U> + Copy + Debug + Unpin + Display,
     SO: SpecializedOrder<TPoolId = SID, TOrderId = V> + Unpin,
     B: Has<V> + Eq + Ord + Clone + Debug + Unpin,
     TC: Unpin,
     TX: CanonicalHash<Hash = TH> + Unpin,
-    TH: Display + Unpin,
+    TH: Eq + Hash + Clone + Display + Unpin,
     C: Clone + Unpin,
-    MC: Clone + Unpin,
+    MC: Specialize<PR> + Clone + Unpin,
     IX: StateIndex<EvolvingEntity<CO, P, V, B>> + Unpin,
     CH: KvStore<SID, EvolvingEntity<CO, P, V, B>> + Unpin,
     TLB: TemporalLiquidityBook<CO, P> + ExternalTLBEvents<CO, P> + TLBFeedback<CO, P> + Maker<MC> + Unpin,
     L: HotBacklog<Bundled<SO, B>> + Maker<MC> + Unpin,
     RIR: RecipeInterpreter<CO, P, C, V, B, TC> + Unpin,
     SIR: SpecializedInterpreter<P, SO, V, TC, B, C> + Unpin,
+    VAL: TxValidator<TC, B> + Unpin,
+    JRN: TxJournal<PR, TH, V> + Unpin,
+    QRN: Quarantine<PR, SID> + Unpin,
+    NTF: FillNotifier<SID, TH> + Unpin,
     PRV: TxProver<TC, TX> + Unpin,
-    E: TryInto<HashSet<V>> + Clone + Unpin + Debug + Display,
+    E: TryInto<HashSet<V>> + FailedScripts<V> + Clone + Unpin + Debug + Display,
 {
     type Item = TX;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let _span = trace_span!("executor_poll").entered();
         loop {
-            // Wait for the feedback from the last pending job.
-            if !self.pending_effects.is_empty() {
-                if let Poll::Ready(Some(result)) = Stream::poll_next(Pin::new(&mut self.feedback), cx) {
-                    match result {
-                        Ok(_) => {
-                            while let Some(effect) = self.pending_effects.pop() {
-                                match effect {
-                                    Effects::Pair(execution_effects) => {
-                                        self.on_execution_effects_success(execution_effects)
-                                    }
-                                    Effects::Funding(funding_effects) => {
-                                        self.on_funding_effects_success(funding_effects)
-                                    }
+            // Apply feedback on submitted transactions, correlated by TX hash.
+            while !self.pending_effects.is_empty() {
+                let Poll::Ready(Some((tx_hash, result))) =
+                    Stream::poll_next(Pin::new(&mut self.feedback), cx)
+                else {
+                    break;
+                };
+                // TX is resolved in the journal atomically with the state it produced.
+                self.index.begin_batch();
+                self.journal.resolve(&tx_hash);
+                let fills = self.pending_fills.remove(&tx_hash).unwrap_or_default();
+                let pool_trades = self.pending_pool_trades.remove(&tx_hash).unwrap_or_default();
+                let Some(mut effects) = self.pending_effects.remove(&tx_hash) else {
+                    self.index.commit_batch();
+                    warn!("Got feedback on unknown TX {}", tx_hash);
+                    continue;
+                };
+                match result {
+                    Ok(_) => {
+                        for fill in fills {
+                            self.notifier.on_fill(fill, tx_hash.clone());
+                        }
+                        self.pool_stats.record(self.lag_guard.current_slot(), pool_trades);
+                        while let Some(effect) = effects.pop() {
+                            match effect {
+                                Effects::Pair(execution_effects) => {
+                                    self.on_execution_effects_success(execution_effects)
+                                }
+                                Effects::Funding(funding_effects) => {
+                                    self.on_funding_effects_success(funding_effects)
                                 }
                             }
                         }
-                        Err(err) => {
-                            while let Some(effect) = self.pending_effects.pop() {
-                                match effect {
-                                    Effects::Pair(execution_effects) => {
-                                        self.on_execution_effects_failure(err.clone(), execution_effects)
-                                    }
-                                    Effects::Funding(funding_effects) => {
-                                        self.on_funding_effects_failure(err.clone(), funding_effects)
-                                    }
+                    }
+                    Err(err) => {
+                        while let Some(effect) = effects.pop() {
+                            match effect {
+                                Effects::Pair(execution_effects) => {
+                                    self.on_execution_effects_failure(err.clone(), execution_effects)
+                                }
+                                Effects::Funding(funding_effects) => {
+                                    self.on_funding_effects_failure(err.clone(), funding_effects)
                                 }
                             }
                         }
                     }
                 }
+                self.index.commit_batch();
             }
             // Process all upstream events before matchmaking.
             if let Poll::Ready(Some((pair, event))) = Stream::poll_next(Pin::new(&mut self.upstream), cx) {
+                let _span = trace_span!("upstream_event", %pair).entered();
+                if let Some(spent_ver) = confirmed_spent_version(&event) {
+                    if !self.recovering.is_empty() {
+                        self.settle_recovering(|tx| tx.consumed_versions.contains(&spent_ver));
+                    }
+                }
+                if let Some(order_id) = self.removed_elsewhere(&event) {
+                    self.notifier.on_removed(order_id);
+                }
                 self.on_pair_event(pair, event);
                 continue;
             }
@@ -715,69 +1439,150 @@ where
                 self.on_funding_event(funding_event);
                 continue;
             }
+            // Don't act upon stale books. Sync progress is driven by upstream events, which wake us up.
+            if self.lag_guard.is_lagging() {
+                trace!("Chain sync lags behind, matchmaking is paused");
+                return Poll::Pending;
+            }
+            if !self.recovering.is_empty() {
+                let expired_before = self
+                    .clock
+                    .unix_time_secs()
+                    .saturating_sub(self.pending_tx_ttl.as_secs());
+                self.settle_recovering(|tx| tx.submitted_at <= expired_before);
+            }
+            self.retest_quarantined();
+            self.release_deferred_orders();
+            self.resume_paused_pairs();
+            self.expire_unconfirmed();
+            if self.read_only {
+                return Poll::Pending;
+            }
             // Finally attempt to matchmake.
             while let Some(focus_pair) = self.focus_set.pop_front() {
+                if self.is_recovering(&focus_pair) {
+                    trace!(
+                        "Pair {} awaits settlement of TXs left by previous run",
+                        focus_pair
+                    );
+                    continue;
+                }
+                if let Some(until) = self
+                    .circuit_breaker
+                    .tripped_until(&focus_pair, self.clock.unix_time_secs())
+                {
+                    trace!("Matching in pair {} is paused by circuit breaker", focus_pair);
+                    self.paused_pairs.insert(focus_pair);
+                    self.schedule_wakeup(until);
+                    continue;
+                }
+                // Initial deposits into bootstrapping pools go first:
+                if let Some(tx) = self.attempt_bootstrap_deposit(focus_pair) {
+                    return Poll::Ready(Some(tx));
+                }
                 // Try TLB:
-                if let Some(recipe) = self.multi_book.get_mut(&focus_pair).attempt() {
-                    let (linked_recipe, consumed_versions) = ExecutionRecipe::link(recipe, |id| {
+                let book = self.multi_book.get_mut(&focus_pair);
+                // Drop takers whose time bounds have passed, e.g. expired RFQ quotes.
+                book.advance_clocks(self.clock.unix_time_secs());
+                let attempted = debug_span!("recipe_attempt", pair = %focus_pair).in_scope(|| book.attempt());
+                if let Some(recipe) = attempted {
+                    let fills = recipe.fills();
+                    let makers = recipe.maker_ids();
+                    let pool_trades = recipe.pool_trades();
+                    let linked = ExecutionRecipe::link(recipe, |id| {
                         self.cache
                             .get(id)
                             .map(|Bundled(t, bearer)| (t.either(|b| b.version, |b| b.version), bearer))
-                    })
-                    .expect("State is inconsistent");
+                    });
+                    let (linked_recipe, consumed_versions) = match linked {
+                        Ok(linked) => linked,
+                        Err(missing) => {
+                            self.report(ExecutorError::UnlinkedRecipe(missing, focus_pair));
+                            self.multi_book.get_mut(&focus_pair).on_recipe_failed();
+                            continue;
+                        }
+                    };
                     let ctx = self.context.clone();
                     if let Some(funding) = self.funding_pool.pop_first() {
-                        let ExecutionResult {
-                            txc,
-                            matchmaking_effects,
-                            funding_io,
-                        } = self.trade_interpreter.run(linked_recipe, funding, ctx);
-                        let tx = self.prover.prove(txc);
-                        let tx_hash = tx.canonical_hash();
-                        self.pending_effects.push(Effects::Pair(ExecutionEffectsByPair {
-                            pair: focus_pair,
-                            tx_hash,
-                            consumed_versions,
-                            pending_effects: ExecutionEffects::FromLiquidityBook(matchmaking_effects),
-                        }));
-                        let (maybe_unused_funding, funding_effects) = funding_io.into_effects();
-                        if let Some(unused_funding) = maybe_unused_funding {
-                            self.funding_pool.insert(unused_funding);
+                        let mut consumed_bearers = linked_recipe.bearers();
+                        consumed_bearers.push(funding.clone());
+                        let num_instructions = linked_recipe.0.len();
+                        let result =
+                            debug_span!("interpret", pair = %focus_pair, num_instructions).in_scope(|| {
+                                self.trade_interpreter
+                                    .run(linked_recipe, funding.clone(), ctx)
+                                    .and_then(|res| match self.dry_run(&res.txc, &consumed_bearers) {
+                                        Ok(()) => Ok(res),
+                                        Err(err) => {
+                                            warn!("TX candidate rejected by dry run: {}", err);
+                                            Err(RecipeRejected::Inconsistent)
+                                        }
+                                    })
+                            });
+                        match result {
+                            Ok(ExecutionResult {
+                                txc,
+                                matchmaking_effects,
+                                funding_io,
+                            }) => {
+                                let tx = debug_span!("prove", pair = %focus_pair)
+                                    .in_scope(|| self.prover.prove(txc));
+                                let tx_hash = tx.canonical_hash();
+                                self.on_tx_submitted(focus_pair, tx_hash.clone(), &consumed_versions);
+                                self.pending_fills.insert(tx_hash.clone(), fills);
+                                self.pending_makers.insert(tx_hash.clone(), makers);
+                                self.pending_pool_trades.insert(tx_hash.clone(), pool_trades);
+                                let (maybe_unused_funding, funding_effects) = funding_io.into_effects();
+                                if let Some(unused_funding) = maybe_unused_funding {
+                                    self.funding_pool.insert(unused_funding);
+                                }
+                                self.pending_effects.insert(
+                                    tx_hash.clone(),
+                                    vec![
+                                        Effects::Pair(ExecutionEffectsByPair {
+                                            pair: focus_pair,
+                                            tx_hash,
+                                            consumed_versions,
+                                            pending_effects: ExecutionEffects::FromLiquidityBook(
+                                                matchmaking_effects,
+                                            ),
+                                        }),
+                                        Effects::Funding(funding_effects),
+                                    ],
+                                );
+                                // Return pair to focus set to make sure corresponding TLB will be exhausted.
+                                self.focus_set.push_back(focus_pair);
+                                return Poll::Ready(Some(tx));
+                            }
+                            Err(RecipeRejected::Oversized) => {
+                                self.funding_pool.insert(funding);
+                                self.multi_book
+                                    .get_mut(&focus_pair)
+                                    .on_recipe_oversized(num_instructions);
+                                // Retry with smaller recipes.
+                                self.focus_set.push_back(focus_pair);
+                            }
+                            Err(RecipeRejected::Inconsistent) => {
+                                self.funding_pool.insert(funding);
+                                // Rejection doesn't tell which inputs are at fault, so no maker is blamed.
+                                self.multi_book.get_mut(&focus_pair).on_recipe_failed();
+                            }
                         }
-                        self.pending_effects.push(Effects::Funding(funding_effects));
-                        // Return pair to focus set to make sure corresponding TLB will be exhausted.
-                        self.focus_set.push_back(focus_pair);
-                        return Poll::Ready(Some(tx));
                     } else {
                         warn!("Cannot matchmake without funding box");
                         self.multi_book.get_mut(&focus_pair).on_recipe_failed();
                     }
                 }
                 // Try Backlog:
-                if let Some(next_order) = self.multi_backlog.get_mut(&focus_pair).try_pop() {
-                    if let Some(Bundled(Either::Right(pool), pool_bearer)) =
-                        self.cache.get(next_order.0.get_pool_ref())
-                    {
-                        let ctx = self.context.clone();
-                        if let Some((txc, updated_pool, consumed_ord)) =
-                            self.spec_interpreter
-                                .try_run(Bundled(pool.entity, pool_bearer), next_order, ctx)
-                        {
-                            let tx = self.prover.prove(txc);
-                            let tx_hash = tx.canonical_hash();
-                            let consumed_versions =
-                                HashSet::from_iter(vec![pool.version, consumed_ord.get_self_ref()]);
-                            self.pending_effects.push(Effects::Pair(ExecutionEffectsByPair {
-                                pair: focus_pair,
-                                tx_hash,
-                                consumed_versions,
-                                pending_effects: ExecutionEffects::FromBacklog(updated_pool, consumed_ord),
-                            }));
-                            // Return pair to focus set to make sure corresponding TLB will be exhausted.
-                            self.focus_set.push_back(focus_pair);
-                            return Poll::Ready(Some(tx));
-                        }
-                    }
+                if let Some(tx) = self.attempt_backlog(focus_pair) {
+                    return Poll::Ready(Some(tx));
+                }
+            }
+            // Deferred work is not driven by upstream events, so the timer has to wake us up.
+            if let Some((_, wakeup)) = self.wakeup.as_mut() {
+                if wakeup.poll_unpin(cx).is_ready() {
+                    self.wakeup = None;
+                    continue;
                 }
             }
             return Poll::Pending;
@@ -785,8 +1590,63 @@ where
     }
 }
 
-impl<S, F, PR, ST, V, CO, SO, P, B, TC, TX, TH, U, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E> FusedStream
-    for Executor<S, F, PR, ST, V, CO, SO, P, B, TC, TX, TH, C, MC, IX, CH, TLB, L, RIR, SIR, PRV, E>
+impl<
+        S,
+        F,
+        PR,
+        ST,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        U,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    > FusedStream
+    for Executor<
+        S,
+        F,
+        PR,
+        ST,
+        V,
+        CO,
+        SO,
+        P,
+        B,
+        TC,
+        TX,
+        TH,
+        C,
+        MC,
+        IX,
+        CH,
+        TLB,
+        L,
+        RIR,
+        SIR,
+        VAL,
+        PRV,
+        JRN,
+        QRN,
+        NTF,
+        E,
+    >
 where
     S: Stream<Item = (PR, Event<CO, SO, P, B, V>)> + Unpin,
     F: Stream<Item = FundingEvent<B>> + Unpin,
@@ -799,19 +1659,144 @@ where
     B: Has<V> + Eq + Ord + Clone + Debug + Unpin,
     TC: Unpin,
     TX: CanonicalHash<Hash = TH> + Unpin,
-    TH: Display + Unpin,
+    TH: Eq + Hash + Clone + Display + Unpin,
     C: Clone + Unpin,
-    MC: Clone + Unpin,
+    MC: Specialize<PR> + Clone + Unpin,
     IX: StateIndex<EvolvingEntity<CO, P, V, B>> + Unpin,
     CH: KvStore<ST, EvolvingEntity<CO, P, V, B>> + Unpin,
     TLB: TemporalLiquidityBook<CO, P> + ExternalTLBEvents<CO, P> + TLBFeedback<CO, P> + Maker<MC> + Unpin,
     L: HotBacklog<Bundled<SO, B>> + Maker<MC> + Unpin,
     RIR: RecipeInterpreter<CO, P, C, V, B, TC> + Unpin,
     SIR: SpecializedInterpreter<P, SO, V, TC, B, C> + Unpin,
+    VAL: TxValidator<TC, B> + Unpin,
+    JRN: TxJournal<PR, TH, V> + Unpin,
+    QRN: Quarantine<PR, ST> + Unpin,
+    NTF: FillNotifier<ST, TH> + Unpin,
     PRV: TxProver<TC, TX> + Unpin,
-    E: TryInto<HashSet<V>> + Clone + Unpin + Debug + Display,
+    E: TryInto<HashSet<V>> + FailedScripts<V> + Clone + Unpin + Debug + Display,
 {
     fn is_terminated(&self) -> bool {
         false
     }
 }
+
+/// ID of the entity affected by the update.
+fn updated_entity_id<T: Stable>(update: &Channel<StateUpdate<T>>) -> T::StableId {
+    let (Channel::Ledger(Confirmed(upd))
+    | Channel::Mempool(Unconfirmed(upd))
+    | Channel::LocalTxSubmit(Predicted(upd))) = update;
+    let (StateUpdate::Transition(tr) | StateUpdate::TransitionRollback(tr)) = upd;
+    match tr {
+        Ior::Left(st) | Ior::Right(st) | Ior::Both(_, st) => st.stable_id(),
+    }
+}
+
+/// Version of the entity whose consumption is confirmed by the ledger.
+fn confirmed_spent_version<SID, V, CO, SO, P, B>(event: &Event<CO, SO, P, B, V>) -> Option<V>
+where
+    SID: Copy + Eq + Hash + Debug + Display,
+    V: Copy + Eq + Hash + Display,
+    CO: Stable<StableId = SID>,
+    P: Stable<StableId = SID>,
+    SO: SpecializedOrder<TOrderId = V>,
+{
+    match event {
+        Either::Left(Channel::Ledger(Confirmed(StateUpdate::Transition(
+            Ior::Left(spent) | Ior::Both(spent, _),
+        )))) => Some(spent.version()),
+        Either::Right(Channel::Ledger(Confirmed(OrderUpdate::Eliminated(spent)))) => {
+            Some(spent.get_self_ref())
+        }
+        _ => None,
+    }
+}
+
+/// ID of an entity on either side of the book.
+fn entity_id<SID, V, CO, P>(entity: &Either<Baked<CO, V>, Baked<P, V>>) -> SID
+where
+    CO: Stable<StableId = SID>,
+    P: Stable<StableId = SID>,
+{
+    match entity {
+        Either::Left(taker) => taker.stable_id(),
+        Either::Right(maker) => maker.stable_id(),
+    }
+}
+
+/// Change of the liquidity book caused by a transition of an entity.
+#[derive(Debug, Eq, PartialEq)]
+enum BookUpdate<Taker, Maker> {
+    RemoveTaker(Taker),
+    RemoveMaker(Maker),
+    ReplaceTaker(Taker, Taker),
+    UpdateTaker(Taker),
+    UpdateMaker(Maker),
+    /// Maker turned into a taker which is not supported.
+    Incoherent(Taker),
+}
+
+fn book_update<CO, P, V>(
+    transition: Ior<Either<Baked<CO, V>, Baked<P, V>>, Either<Baked<CO, V>, Baked<P, V>>>,
+) -> BookUpdate<CO, P> {
+    match transition {
+        Ior::Left(Either::Left(taker)) => BookUpdate::RemoveTaker(taker.entity),
+        Ior::Left(Either::Right(maker)) => BookUpdate::RemoveMaker(maker.entity),
+        Ior::Both(Either::Left(old), Either::Left(new)) => BookUpdate::ReplaceTaker(old.entity, new.entity),
+        Ior::Both(_, Either::Right(new)) | Ior::Right(Either::Right(new)) => {
+            BookUpdate::UpdateMaker(new.entity)
+        }
+        Ior::Both(Either::Right(_), Either::Left(new)) => BookUpdate::Incoherent(new.entity),
+        Ior::Right(Either::Left(new)) => BookUpdate::UpdateTaker(new.entity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use either::Either;
+
+    use spectrum_offchain::combinators::Ior;
+    use spectrum_offchain::data::Baked;
+
+    use crate::execution_engine::{book_update, BookUpdate};
+
+    type Entity = Either<Baked<&'static str, u32>, Baked<char, u32>>;
+
+    fn taker(t: &'static str) -> Entity {
+        Either::Left(Baked::new(t, 0))
+    }
+
+    fn maker(m: char) -> Entity {
+        Either::Right(Baked::new(m, 0))
+    }
+
+    #[test]
+    fn maker_downgraded_to_taker_is_incoherent() {
+        assert_eq!(
+            book_update(Ior::Both(maker('p'), taker("o"))),
+            BookUpdate::Incoherent("o")
+        );
+    }
+
+    #[test]
+    fn taker_upgraded_to_maker_updates_maker() {
+        assert_eq!(
+            book_update(Ior::Both(taker("o"), maker('p'))),
+            BookUpdate::UpdateMaker('p')
+        );
+    }
+
+    #[test]
+    fn regular_transitions() {
+        assert_eq!(book_update(Ior::Left(taker("o"))), BookUpdate::RemoveTaker("o"));
+        assert_eq!(book_update(Ior::Left(maker('p'))), BookUpdate::RemoveMaker('p'));
+        assert_eq!(
+            book_update(Ior::Both(taker("o"), taker("o1"))),
+            BookUpdate::ReplaceTaker("o", "o1")
+        );
+        assert_eq!(book_update(Ior::Right(taker("o"))), BookUpdate::UpdateTaker("o"));
+        assert_eq!(
+            book_update(Ior::Both(maker('p'), maker('q'))),
+            BookUpdate::UpdateMaker('q')
+        );
+    }
+}
@@ -4,7 +4,7 @@ use std::hash::Hash;
 use log::trace;
 use type_equalities::IsEqual;
 
-use spectrum_offchain::maker::Maker;
+use spectrum_offchain::maker::{Maker, Specialize};
 
 #[derive(Debug, Clone)]
 pub struct MultiPair<PairId, R, Ctx>(HashMap<PairId, R>, Ctx, &'static str);
@@ -19,7 +19,7 @@ impl<PairId, R, Ctx> MultiPair<PairId, R, Ctx>
 where
     PairId: Copy + Eq + Hash + std::fmt::Display,
     R: Maker<Ctx>,
-    Ctx: Clone,
+    Ctx: Specialize<PairId> + Clone,
 {
     pub fn with_resource_mut<F, T>(&mut self, pair: &PairId, f: F) -> T
     where
@@ -33,7 +33,7 @@ where
             self.0.get_mut(pair).unwrap()
         } else {
             trace!(target: "offchain", "MultiPair[{}]: new pair: {}", self.2, pair);
-            self.0.insert(*pair, Maker::make(&self.1));
+            self.0.insert(*pair, Maker::make(&self.1.specialize(pair)));
             self.get_mut(pair)
         }
     }
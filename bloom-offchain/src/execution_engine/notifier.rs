@@ -0,0 +1,156 @@
+use std::fmt::Display;
+
+use isahc::{Request, RequestExt};
+use log::{trace, warn};
+
+use crate::execution_engine::liquidity_book::side::Side;
+use crate::execution_engine::liquidity_book::types::{AbsolutePrice, FeeAsset, InputAsset, OutputAsset};
+
+/// Fill of a taker within a particular TX.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Fill<OrderId> {
+    pub order_id: OrderId,
+    pub side: Side,
+    pub removed_input: InputAsset<u64>,
+    pub added_output: OutputAsset<u64>,
+    /// Operator fee charged from the order for this fill.
+    pub fee_charged: FeeAsset<u64>,
+    /// Whether the order is fully executed.
+    pub terminal: bool,
+}
+
+impl<OrderId> Fill<OrderId> {
+    /// Actual price of the fill.
+    pub fn price(&self) -> Option<AbsolutePrice> {
+        match self.side {
+            Side::Ask => AbsolutePrice::new(self.added_output, self.removed_input),
+            Side::Bid => AbsolutePrice::new(self.removed_input, self.added_output),
+        }
+    }
+}
+
+/// Notifies order owners about what happens to their orders.
+pub trait FillNotifier<OrderId, TxHash> {
+    /// Order was (partially) filled in a TX accepted by the network.
+    fn on_fill(&self, fill: Fill<OrderId>, tx_hash: TxHash);
+    /// Order left the book without being executed by this agent,
+    /// i.e. it was cancelled or executed by someone else.
+    fn on_removed(&self, order_id: OrderId);
+}
+
+impl<OrderId, TxHash, N> FillNotifier<OrderId, TxHash> for Option<N>
+where
+    N: FillNotifier<OrderId, TxHash>,
+{
+    fn on_fill(&self, fill: Fill<OrderId>, tx_hash: TxHash) {
+        if let Some(notifier) = self {
+            notifier.on_fill(fill, tx_hash)
+        }
+    }
+
+    fn on_removed(&self, order_id: OrderId) {
+        if let Some(notifier) = self {
+            notifier.on_removed(order_id)
+        }
+    }
+}
+
+impl<OrderId, TxHash, N1, N2> FillNotifier<OrderId, TxHash> for (N1, N2)
+where
+    OrderId: Copy,
+    TxHash: Clone,
+    N1: FillNotifier<OrderId, TxHash>,
+    N2: FillNotifier<OrderId, TxHash>,
+{
+    fn on_fill(&self, fill: Fill<OrderId>, tx_hash: TxHash) {
+        self.0.on_fill(fill, tx_hash.clone());
+        self.1.on_fill(fill, tx_hash)
+    }
+
+    fn on_removed(&self, order_id: OrderId) {
+        self.0.on_removed(order_id);
+        self.1.on_removed(order_id)
+    }
+}
+
+/// Posts notifications as JSON to a configured endpoint. Delivery is best-effort.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    endpoint: String,
+}
+
+impl WebhookNotifier {
+    pub fn new(endpoint: String) -> Self {
+        Self { endpoint }
+    }
+
+    pub fn post(&self, body: serde_json::Value) {
+        let endpoint = self.endpoint.clone();
+        tokio::spawn(async move {
+            let request = Request::post(&endpoint)
+                .header("Content-Type", "application/json")
+                .body(body.to_string());
+            match request {
+                Ok(req) => match req.send_async().await {
+                    Ok(resp) => trace!("Webhook responded with {}", resp.status()),
+                    Err(err) => warn!("Webhook delivery failed: {}", err),
+                },
+                Err(err) => warn!("Cannot build webhook request: {}", err),
+            }
+        });
+    }
+}
+
+impl<OrderId, TxHash> FillNotifier<OrderId, TxHash> for WebhookNotifier
+where
+    OrderId: Display,
+    TxHash: Display,
+{
+    fn on_fill(&self, fill: Fill<OrderId>, tx_hash: TxHash) {
+        let price = fill.price().map(|p| format!("{}/{}", p.numer(), p.denom()));
+        self.post(serde_json::json!({
+            "event": "fill",
+            "orderId": fill.order_id.to_string(),
+            "removedInput": fill.removed_input,
+            "addedOutput": fill.added_output,
+            "price": price,
+            "feeCharged": fill.fee_charged,
+            "terminal": fill.terminal,
+            "txHash": tx_hash.to_string(),
+        }))
+    }
+
+    fn on_removed(&self, order_id: OrderId) {
+        self.post(serde_json::json!({
+            "event": "removed",
+            "orderId": order_id.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::side::Side;
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+    use crate::execution_engine::notifier::Fill;
+
+    #[test]
+    fn fill_price_is_quote_per_base() {
+        let ask = Fill {
+            order_id: 0,
+            side: Side::Ask,
+            removed_input: 100,
+            added_output: 250,
+            fee_charged: 10,
+            terminal: false,
+        };
+        assert_eq!(ask.price(), AbsolutePrice::new(250, 100));
+        let bid = Fill {
+            side: Side::Bid,
+            removed_input: 250,
+            added_output: 100,
+            ..ask
+        };
+        assert_eq!(bid.price(), AbsolutePrice::new(250, 100));
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+
+use crate::execution_engine::liquidity_book::side::OnSide;
+
+/// Swap routed through a pool within a particular TX.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PoolTrade<PoolId> {
+    pub pool: PoolId,
+    /// Input the pool received, tagged with the side of the taker.
+    pub input: OnSide<u64>,
+    pub output: u64,
+    /// Part of the input retained by LPs.
+    pub lp_fee: u64,
+}
+
+/// Maps slots to epochs.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochSchedule {
+    /// First slot of `origin_epoch`.
+    pub origin_slot: u64,
+    pub origin_epoch: u64,
+    pub epoch_length_slots: u64,
+}
+
+impl EpochSchedule {
+    pub fn epoch_of(&self, slot: u64) -> u64 {
+        self.origin_epoch + slot.saturating_sub(self.origin_slot) / self.epoch_length_slots.max(1)
+    }
+}
+
+/// Activity of a pool over an epoch.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStats {
+    pub swaps: u64,
+    pub volume_base: u64,
+    pub volume_quote: u64,
+    pub lp_fees_base: u64,
+    pub lp_fees_quote: u64,
+}
+
+impl PoolStats {
+    fn add(&mut self, trade: OnSide<u64>, output: u64, lp_fee: u64) {
+        self.swaps += 1;
+        match trade {
+            OnSide::Bid(quote_input) => {
+                self.volume_quote += quote_input;
+                self.volume_base += output;
+                self.lp_fees_quote += lp_fee;
+            }
+            OnSide::Ask(base_input) => {
+                self.volume_base += base_input;
+                self.volume_quote += output;
+                self.lp_fees_base += lp_fee;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolStatsEntry<PoolId> {
+    pub pool: PoolId,
+    pub epoch: u64,
+    #[serde(flatten)]
+    pub stats: PoolStats,
+    pub avg_trade_base: u64,
+    pub avg_trade_quote: u64,
+}
+
+/// Number of epochs stats are kept for.
+const RETAINED_EPOCHS: u64 = 8;
+
+/// Per-pool, per-epoch stats of swaps executed by this agent. Shared between executors.
+#[derive(Debug, Clone)]
+pub struct PoolStatsRegistry<PoolId> {
+    schedule: EpochSchedule,
+    stats: Arc<Mutex<BTreeMap<(u64, PoolId), PoolStats>>>,
+}
+
+impl<PoolId: Copy + Ord> PoolStatsRegistry<PoolId> {
+    pub fn new(schedule: EpochSchedule) -> Self {
+        Self {
+            schedule,
+            stats: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    /// Account trades confirmed to go through at the given slot.
+    pub fn record(&self, slot: u64, trades: Vec<PoolTrade<PoolId>>) {
+        let epoch = self.schedule.epoch_of(slot);
+        let mut stats = self.stats.lock().unwrap();
+        for PoolTrade {
+            pool,
+            input,
+            output,
+            lp_fee,
+        } in trades
+        {
+            stats.entry((epoch, pool)).or_default().add(input, output, lp_fee);
+        }
+        let oldest_retained = epoch.saturating_sub(RETAINED_EPOCHS - 1);
+        stats.retain(|(e, _), _| *e >= oldest_retained);
+    }
+
+    /// Stats of all pools, of the given epoch only if specified.
+    pub fn snapshot(&self, epoch: Option<u64>) -> Vec<PoolStatsEntry<PoolId>> {
+        self.stats
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((e, _), _)| epoch.map_or(true, |epoch| *e == epoch))
+            .map(|((epoch, pool), stats)| PoolStatsEntry {
+                pool: *pool,
+                epoch: *epoch,
+                stats: *stats,
+                avg_trade_base: stats.volume_base / stats.swaps.max(1),
+                avg_trade_quote: stats.volume_quote / stats.swaps.max(1),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::side::OnSide;
+    use crate::execution_engine::pool_stats::{EpochSchedule, PoolStatsRegistry, PoolTrade};
+
+    const SCHEDULE: EpochSchedule = EpochSchedule {
+        origin_slot: 100,
+        origin_epoch: 10,
+        epoch_length_slots: 50,
+    };
+
+    #[test]
+    fn stats_are_aggregated_by_pool_and_epoch() {
+        let registry = PoolStatsRegistry::new(SCHEDULE);
+        let ask = PoolTrade {
+            pool: 1,
+            input: OnSide::Ask(100),
+            output: 200,
+            lp_fee: 3,
+        };
+        let bid = PoolTrade {
+            input: OnSide::Bid(400),
+            output: 190,
+            lp_fee: 12,
+            ..ask
+        };
+        registry.record(120, vec![ask, bid]);
+        registry.record(160, vec![ask]);
+        let epoch_10 = registry.snapshot(Some(10));
+        assert_eq!(epoch_10.len(), 1);
+        let entry = &epoch_10[0];
+        assert_eq!(entry.stats.swaps, 2);
+        assert_eq!(entry.stats.volume_base, 290);
+        assert_eq!(entry.stats.volume_quote, 600);
+        assert_eq!(entry.stats.lp_fees_base, 3);
+        assert_eq!(entry.stats.lp_fees_quote, 12);
+        assert_eq!(entry.avg_trade_quote, 300);
+        assert_eq!(registry.snapshot(None).len(), 2);
+    }
+}
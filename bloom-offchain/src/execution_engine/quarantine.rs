@@ -0,0 +1,178 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::hash::Hash;
+use std::time::Duration;
+
+use log::{info, warn};
+
+use spectrum_offchain::quarantine::{Quarantine, QuarantinedMaker};
+
+/// When makers get quarantined and how long they stay there.
+#[derive(Debug, Copy, Clone)]
+pub struct QuarantinePolicy {
+    /// Consecutive deterministic failures of recipes involving a maker after which it is quarantined.
+    pub max_consecutive_failures: u32,
+    /// Quarantined maker is returned to matchmaking for a retest once this much time passes.
+    pub retest_after: Duration,
+}
+
+/// Keeps makers which repeatedly break recipes out of matchmaking,
+/// so that a single toxic pool doesn't stall its whole pair.
+pub(crate) struct MakerQuarantine<Pair, StableId, Store> {
+    policy: QuarantinePolicy,
+    store: Store,
+    /// Consecutive deterministic failures by maker.
+    failures: HashMap<StableId, u32>,
+    quarantined: HashMap<StableId, QuarantinedMaker<Pair, StableId>>,
+    /// Makers under retest, a single failure sends them back to quarantine.
+    on_retest: HashSet<StableId>,
+}
+
+impl<Pair, StableId, Store> MakerQuarantine<Pair, StableId, Store>
+where
+    Pair: Copy + Display,
+    StableId: Copy + Eq + Hash + Display,
+    Store: Quarantine<Pair, StableId>,
+{
+    pub fn new(policy: QuarantinePolicy, store: Store) -> Self {
+        let quarantined = store.entries().into_iter().map(|e| (e.maker, e)).collect();
+        Self {
+            policy,
+            store,
+            failures: HashMap::new(),
+            quarantined,
+            on_retest: HashSet::new(),
+        }
+    }
+
+    pub fn is_quarantined(&self, maker: &StableId) -> bool {
+        self.quarantined.contains_key(maker)
+    }
+
+    /// Record deterministic failure of a recipe involving the given makers.
+    /// Returns makers which got quarantined in result.
+    pub fn on_failure(&mut self, pair: Pair, makers: Vec<StableId>, now: u64) -> Vec<StableId> {
+        let mut newly_quarantined = vec![];
+        for maker in makers {
+            if self.is_quarantined(&maker) {
+                continue;
+            }
+            let failures = self.failures.entry(maker).or_default();
+            *failures += 1;
+            let failures = *failures;
+            let failed_retest = self.on_retest.remove(&maker);
+            if failed_retest || failures >= self.policy.max_consecutive_failures {
+                warn!(
+                    target: "quarantine",
+                    "Maker {} in pair {} is quarantined after {} consecutive failures",
+                    maker,
+                    pair,
+                    failures
+                );
+                self.failures.remove(&maker);
+                let entry = QuarantinedMaker {
+                    pair,
+                    maker,
+                    since: now,
+                };
+                self.store.put(entry.clone());
+                self.quarantined.insert(maker, entry);
+                newly_quarantined.push(maker);
+            }
+        }
+        newly_quarantined
+    }
+
+    /// Record successful execution of a recipe involving the given makers.
+    pub fn on_success(&mut self, makers: Vec<StableId>) {
+        for maker in makers {
+            self.failures.remove(&maker);
+            if self.on_retest.remove(&maker) {
+                info!(target: "quarantine", "Maker {} passed retest and is released", maker);
+                self.store.release(&maker);
+            }
+        }
+    }
+
+    /// Makers due for a retest. They are no longer excluded from matchmaking.
+    pub fn release_for_retest(&mut self, now: u64) -> Vec<(Pair, StableId)> {
+        let retest_after = self.policy.retest_after.as_secs();
+        let due = self
+            .quarantined
+            .values()
+            .filter(|e| now.saturating_sub(e.since) >= retest_after)
+            .map(|e| (e.pair, e.maker))
+            .collect::<Vec<_>>();
+        for (pair, maker) in &due {
+            info!(target: "quarantine", "Retesting maker {} in pair {}", maker, pair);
+            self.quarantined.remove(maker);
+            self.on_retest.insert(*maker);
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use spectrum_offchain::quarantine::{Quarantine, QuarantinedMaker};
+
+    use crate::execution_engine::quarantine::{MakerQuarantine, QuarantinePolicy};
+
+    #[derive(Default)]
+    struct InMemoryQuarantine(HashMap<u64, QuarantinedMaker<u8, u64>>);
+
+    impl Quarantine<u8, u64> for InMemoryQuarantine {
+        fn put(&mut self, entry: QuarantinedMaker<u8, u64>) {
+            self.0.insert(entry.maker, entry);
+        }
+
+        fn release(&mut self, maker: &u64) {
+            self.0.remove(maker);
+        }
+
+        fn entries(&self) -> Vec<QuarantinedMaker<u8, u64>> {
+            self.0.values().cloned().collect()
+        }
+    }
+
+    const POLICY: QuarantinePolicy = QuarantinePolicy {
+        max_consecutive_failures: 3,
+        retest_after: Duration::from_secs(60),
+    };
+
+    #[test]
+    fn maker_is_quarantined_after_consecutive_failures_only() {
+        let mut q = MakerQuarantine::new(POLICY, InMemoryQuarantine::default());
+        assert!(q.on_failure(0, vec![1, 2], 0).is_empty());
+        assert!(q.on_failure(0, vec![1, 2], 0).is_empty());
+        q.on_success(vec![2]);
+        assert_eq!(q.on_failure(0, vec![1, 2], 0), vec![1]);
+        assert!(q.is_quarantined(&1));
+        assert!(!q.is_quarantined(&2));
+        assert_eq!(q.store.entries().len(), 1);
+    }
+
+    #[test]
+    fn retest_failure_quarantines_again_success_releases() {
+        let mut q = MakerQuarantine::new(POLICY, InMemoryQuarantine::default());
+        for _ in 0..3 {
+            q.on_failure(0, vec![1], 0);
+        }
+        assert!(q.release_for_retest(59).is_empty());
+        assert_eq!(q.release_for_retest(60), vec![(0, 1)]);
+        assert!(!q.is_quarantined(&1));
+        assert_eq!(q.on_failure(0, vec![1], 60), vec![1]);
+        assert_eq!(q.release_for_retest(120), vec![(0, 1)]);
+        q.on_success(vec![1]);
+        assert!(q.store.entries().is_empty());
+        // Entries survive restart.
+        q.on_failure(0, vec![3], 0);
+        q.on_failure(0, vec![3], 0);
+        q.on_failure(0, vec![3], 0);
+        let restored = MakerQuarantine::new(POLICY, q.store);
+        assert!(restored.is_quarantined(&3));
+    }
+}
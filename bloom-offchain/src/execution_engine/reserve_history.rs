@@ -0,0 +1,277 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use either::Either;
+use serde::{Deserialize, Serialize};
+
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, StateUpdate};
+use spectrum_offchain::data::Stable;
+
+use crate::execution_engine::bundled::Bundled;
+use crate::execution_engine::liquidity_book::market_maker::{AbsoluteReserves, MarketMaker};
+use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+use crate::execution_engine::Event;
+
+const BPS: f64 = 10_000.0;
+
+/// Estimates from fewer returns are too noisy to act upon.
+const MIN_RETURNS: usize = 3;
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveHistoryConfig {
+    /// Number of most recent blocks reserves of each pool are kept for.
+    pub retained_blocks: usize,
+    /// Volatility is estimated from snapshots taken within this many slots back from the latest one.
+    pub volatility_window_slots: u64,
+}
+
+/// Reserves of a pool as of the end of a block.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReserveSnapshot {
+    pub slot: u64,
+    pub base: u64,
+    pub quote: u64,
+}
+
+impl ReserveSnapshot {
+    fn price(&self) -> Option<f64> {
+        (self.base > 0 && self.quote > 0).then(|| self.quote as f64 / self.base as f64)
+    }
+}
+
+/// Realized volatility of a pair.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volatility {
+    /// Root mean square of log returns of spot price between consecutive snapshots, in basis points.
+    pub bps: f64,
+    /// Number of returns the estimate is based on.
+    pub returns: usize,
+}
+
+impl Volatility {
+    /// Estimate from snapshots of a single pool, oldest first.
+    fn estimate(snapshots: &[ReserveSnapshot]) -> Option<Self> {
+        let prices = snapshots.iter().filter_map(|s| s.price()).collect::<Vec<_>>();
+        let returns = prices.windows(2).map(|w| (w[1] / w[0]).ln()).collect::<Vec<_>>();
+        (returns.len() >= MIN_RETURNS).then(|| Self {
+            bps: (returns.iter().map(|r| r * r).sum::<f64>() / returns.len() as f64).sqrt() * BPS,
+            returns: returns.len(),
+        })
+    }
+}
+
+struct PoolSeries<Pair> {
+    pair: Pair,
+    snapshots: VecDeque<ReserveSnapshot>,
+}
+
+struct HistoryState<Pair, PoolId> {
+    pools: HashMap<PoolId, PoolSeries<Pair>>,
+    /// Slot of the most recent snapshot across all pools.
+    latest_slot: u64,
+}
+
+/// Per-block reserve snapshots of pools, recent volatility of pairs is estimated from them.
+/// Shared between upstream observers and consumers, disabled unless configured.
+#[derive(Clone)]
+pub struct ReserveHistory<Pair, PoolId> {
+    conf: Option<ReserveHistoryConfig>,
+    state: Arc<Mutex<HistoryState<Pair, PoolId>>>,
+}
+
+impl<Pair, PoolId> ReserveHistory<Pair, PoolId>
+where
+    Pair: Copy + Eq,
+    PoolId: Copy + Eq + Hash,
+{
+    pub fn new(conf: Option<ReserveHistoryConfig>) -> Self {
+        Self {
+            conf,
+            state: Arc::new(Mutex::new(HistoryState {
+                pools: HashMap::new(),
+                latest_slot: 0,
+            })),
+        }
+    }
+
+    /// Record reserves of the pool at the given slot.
+    /// Supersedes the snapshot taken earlier within the same block.
+    pub fn record(&self, pair: Pair, pool: PoolId, slot: u64, reserves: AbsoluteReserves) {
+        let Some(conf) = self.conf else {
+            return;
+        };
+        let snapshot = ReserveSnapshot {
+            slot,
+            base: reserves.base,
+            quote: reserves.quote,
+        };
+        let mut state = self.state.lock().unwrap();
+        state.latest_slot = state.latest_slot.max(slot);
+        let series = state.pools.entry(pool).or_insert_with(|| PoolSeries {
+            pair,
+            snapshots: VecDeque::new(),
+        });
+        match series.snapshots.back_mut() {
+            Some(last) if last.slot >= slot => *last = snapshot,
+            _ => series.snapshots.push_back(snapshot),
+        }
+        while series.snapshots.len() > conf.retained_blocks {
+            series.snapshots.pop_front();
+        }
+    }
+
+    /// Record confirmed transitions of pools in the pair observed at the given slot.
+    /// Returns updated volatility of the pair if reserves of one of its pools moved.
+    pub fn observe<CO, SO, P, B, Ver>(
+        &self,
+        pair: Pair,
+        event: &Event<CO, SO, P, B, Ver>,
+        slot: u64,
+    ) -> Option<Volatility>
+    where
+        P: MarketMaker + Stable<StableId = PoolId>,
+    {
+        let Either::Left(Channel::Ledger(Confirmed(StateUpdate::Transition(tr)))) = event else {
+            return None;
+        };
+        match tr {
+            Ior::Both(_, Bundled(Either::Right(next), _)) | Ior::Right(Bundled(Either::Right(next), _))
+                if next.entity.is_active() =>
+            {
+                self.record(pair, next.entity.stable_id(), slot, next.entity.liquidity());
+                self.volatility(&pair)
+            }
+            Ior::Left(Bundled(Either::Right(prev), _)) => {
+                self.state.lock().unwrap().pools.remove(&prev.entity.stable_id());
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Snapshots of the pool retained at the moment, oldest first.
+    pub fn series(&self, pool: &PoolId) -> Vec<ReserveSnapshot> {
+        self.state
+            .lock()
+            .unwrap()
+            .pools
+            .get(pool)
+            .map_or(vec![], |series| series.snapshots.iter().copied().collect())
+    }
+
+    /// Latest spot price (quote per base) of the pair as quoted by its deepest pool.
+    pub fn spot_price(&self, pair: &Pair) -> Option<AbsolutePrice> {
+        let state = self.state.lock().unwrap();
+        let latest = deepest(&state, pair)?.snapshots.back()?;
+        AbsolutePrice::new(latest.quote, latest.base)
+    }
+
+    /// Volatility of the pair over the configured window, estimated from its deepest pool.
+    pub fn volatility(&self, pair: &Pair) -> Option<Volatility> {
+        let conf = self.conf?;
+        let state = self.state.lock().unwrap();
+        let since = state.latest_slot.saturating_sub(conf.volatility_window_slots);
+        let deepest = deepest(&state, pair)?;
+        let window = deepest
+            .snapshots
+            .iter()
+            .filter(|s| s.slot >= since)
+            .copied()
+            .collect::<Vec<_>>();
+        Volatility::estimate(&window)
+    }
+}
+
+fn deepest<'a, Pair: Eq, PoolId>(
+    state: &'a HistoryState<Pair, PoolId>,
+    pair: &Pair,
+) -> Option<&'a PoolSeries<Pair>> {
+    state
+        .pools
+        .values()
+        .filter(|series| series.pair == *pair)
+        .max_by_key(|series| series.snapshots.back().map_or(0, |s| s.quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::execution_engine::liquidity_book::market_maker::AbsoluteReserves;
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+    use crate::execution_engine::reserve_history::{ReserveHistory, ReserveHistoryConfig, ReserveSnapshot};
+
+    const CONF: ReserveHistoryConfig = ReserveHistoryConfig {
+        retained_blocks: 4,
+        volatility_window_slots: 100,
+    };
+
+    fn reserves(base: u64, quote: u64) -> AbsoluteReserves {
+        AbsoluteReserves { base, quote }
+    }
+
+    #[test]
+    fn snapshots_are_taken_per_block_and_bounded() {
+        let history = ReserveHistory::<u8, u8>::new(Some(CONF));
+        history.record(0, 1, 10, reserves(100, 100));
+        history.record(0, 1, 10, reserves(100, 110));
+        for slot in 11..15 {
+            history.record(0, 1, slot, reserves(100, 100 + slot));
+        }
+        let series = history.series(&1);
+        assert_eq!(series.len(), CONF.retained_blocks);
+        assert_eq!(
+            series[0],
+            ReserveSnapshot {
+                slot: 11,
+                base: 100,
+                quote: 111
+            }
+        );
+        assert!(history.series(&2).is_empty());
+    }
+
+    #[test]
+    fn volatility_is_estimated_from_deepest_pool() {
+        let history = ReserveHistory::<u8, u8>::new(Some(CONF));
+        for (slot, quote) in [(10, 1_000), (20, 1_100), (30, 1_000), (40, 1_100)] {
+            history.record(0, 1, slot, reserves(1_000, quote));
+            history.record(0, 2, slot, reserves(10, 10));
+        }
+        let volatility = history.volatility(&0).unwrap();
+        assert_eq!(volatility.returns, 3);
+        assert!((volatility.bps - (1.1f64).ln() * 10_000.0).abs() < 1e-6);
+        assert_eq!(history.volatility(&1), None);
+    }
+
+    #[test]
+    fn spot_price_is_quoted_by_deepest_pool() {
+        let history = ReserveHistory::<u8, u8>::new(Some(CONF));
+        history.record(0, 1, 10, reserves(1_000, 2_000));
+        history.record(0, 2, 10, reserves(10, 30));
+        history.record(0, 1, 20, reserves(1_000, 2_500));
+        assert_eq!(history.spot_price(&0), AbsolutePrice::new(2_500, 1_000));
+        assert_eq!(history.spot_price(&1), None);
+    }
+
+    #[test]
+    fn short_history_yields_no_volatility() {
+        let history = ReserveHistory::<u8, u8>::new(Some(CONF));
+        history.record(0, 1, 10, reserves(100, 100));
+        history.record(0, 1, 20, reserves(100, 200));
+        assert_eq!(history.volatility(&0), None);
+        history.record(0, 1, 200, reserves(100, 100));
+        history.record(0, 1, 210, reserves(100, 100));
+        assert_eq!(history.volatility(&0), None);
+    }
+
+    #[test]
+    fn disabled_history_records_nothing() {
+        let history = ReserveHistory::<u8, u8>::new(None);
+        history.record(0, 1, 10, reserves(100, 100));
+        assert!(history.series(&1).is_empty());
+    }
+}
@@ -1,5 +1,11 @@
 use std::collections::HashMap;
 use std::hash::Hash;
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use spectrum_offchain::kv_backend::KvBackend;
 
 pub trait KvStore<K, V> {
     fn insert(&mut self, key: K, value: V) -> Option<V>;
@@ -33,3 +39,60 @@ where
         self.0.remove(&key)
     }
 }
+
+/// [KvStore] persisted in a [KvBackend].
+/// Keys are prefixed, so the backend can be shared with a persistent state index.
+pub struct PersistentKvStore<B, K, V> {
+    backend: B,
+    pd: PhantomData<(K, V)>,
+}
+
+impl<B, K, V> PersistentKvStore<B, K, V> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<B: Clone, K, V> Clone for PersistentKvStore<B, K, V> {
+    fn clone(&self) -> Self {
+        Self::new(self.backend.clone())
+    }
+}
+
+const KV_PREFIX: u8 = 8u8;
+
+fn prefixed<K: Serialize>(key: &K) -> Vec<u8> {
+    let mut raw = vec![KV_PREFIX];
+    raw.extend(bincode::serialize(key).unwrap());
+    raw
+}
+
+impl<B, K, V> KvStore<K, V> for PersistentKvStore<B, K, V>
+where
+    B: KvBackend,
+    K: Serialize,
+    V: Serialize + DeserializeOwned,
+{
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let key = prefixed(&key);
+        let prev = self.backend.get(&key);
+        self.backend.put(&key, &bincode::serialize(&value).unwrap());
+        prev.and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn get(&self, key: K) -> Option<V> {
+        self.backend
+            .get(&prefixed(&key))
+            .and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn remove(&mut self, key: K) -> Option<V> {
+        let key = prefixed(&key);
+        let prev = self.backend.get(&key);
+        self.backend.delete(&key);
+        prev.and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+}
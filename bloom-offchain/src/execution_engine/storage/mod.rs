@@ -1,11 +1,15 @@
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter, Write};
+use std::marker::PhantomData;
 
 use log::trace;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
 use spectrum_offchain::data::event::{Confirmed, Predicted, Unconfirmed};
 use spectrum_offchain::data::{EntitySnapshot, Stable};
+use spectrum_offchain::kv_backend::KvBackend;
 
 pub mod kv_store;
 
@@ -26,6 +30,18 @@ pub trait StateIndex<T: EntitySnapshot> {
     fn eliminate<'a>(&mut self, sid: T::StableId);
     fn exists<'a>(&self, sid: &T::Version) -> bool;
     fn get_state<'a>(&self, sid: T::Version) -> Option<T>;
+    /// Drop unconfirmed and predicted states which weren't confirmed within `ttl_slots`,
+    /// so that affected entities revert to their last confirmed states.
+    /// States are stamped with the last `current_slot` passed here when put.
+    /// Returns IDs of affected entities.
+    fn expire_unconfirmed(&mut self, current_slot: u64, ttl_slots: u64) -> Vec<T::StableId>;
+    /// Number of states expired since start.
+    fn expired_total(&self) -> u64;
+    /// Buffer subsequent writes until [StateIndex::commit_batch] persists them atomically,
+    /// along with writes of other stores sharing the backend. No-op for volatile indexes.
+    /// Batches may nest, writes are persisted once the outermost one is committed.
+    fn begin_batch(&mut self) {}
+    fn commit_batch(&mut self) {}
 }
 
 #[derive(Clone)]
@@ -120,6 +136,33 @@ where
         trace!("state_index::get_state({}) -> {}", sid, Displayed(&res));
         res
     }
+
+    fn expire_unconfirmed(&mut self, current_slot: u64, ttl_slots: u64) -> Vec<T::StableId> {
+        let res = self.0.expire_unconfirmed(current_slot, ttl_slots);
+        if !res.is_empty() {
+            trace!(
+                "state_index::expire_unconfirmed({}, {}) -> {} entities",
+                current_slot,
+                ttl_slots,
+                res.len()
+            );
+        }
+        res
+    }
+
+    fn expired_total(&self) -> u64 {
+        self.0.expired_total()
+    }
+
+    fn begin_batch(&mut self) {
+        trace!("state_index::begin_batch()");
+        self.0.begin_batch()
+    }
+
+    fn commit_batch(&mut self) {
+        trace!("state_index::commit_batch()");
+        self.0.commit_batch()
+    }
 }
 
 const MAX_ROLLBACK_DEPTH: usize = 32;
@@ -128,6 +171,10 @@ const MAX_ROLLBACK_DEPTH: usize = 32;
 pub struct InMemoryStateIndex<T: EntitySnapshot> {
     store: HashMap<T::Version, T>,
     index: HashMap<InMemoryIndexKey, T::Version>,
+    /// Slots unconfirmed and predicted states were put at.
+    unconfirmed_since: HashMap<T::Version, u64>,
+    current_slot: u64,
+    expired_total: u64,
 }
 
 impl<T: EntitySnapshot> InMemoryStateIndex<T> {
@@ -135,12 +182,16 @@ impl<T: EntitySnapshot> InMemoryStateIndex<T> {
         Self {
             store: HashMap::new(),
             index: HashMap::new(),
+            unconfirmed_since: HashMap::new(),
+            current_slot: 0,
+            expired_total: 0,
         }
     }
 
     fn put(&mut self, index_key: InMemoryIndexKey, value: T) {
         if let Some(old_ver) = self.index.get(&index_key) {
             self.store.remove(old_ver);
+            self.unconfirmed_since.remove(old_ver);
         }
         let new_ver = value.version();
         self.index.insert(index_key, new_ver);
@@ -186,6 +237,7 @@ where
 
     fn put_confirmed(&mut self, Confirmed(entity): Confirmed<T>) {
         let sid = entity.stable_id();
+        self.unconfirmed_since.remove(&entity.version());
         let index_key = index_key(LAST_CONFIRMED_PREFIX, sid);
         self.put(index_key, entity);
     }
@@ -193,16 +245,19 @@ where
     fn put_unconfirmed(&mut self, Unconfirmed(entity): Unconfirmed<T>) {
         let sid = entity.stable_id();
         let index_key = index_key(LAST_UNCONFIRMED_PREFIX, sid);
+        self.unconfirmed_since.insert(entity.version(), self.current_slot);
         self.put(index_key, entity);
     }
 
     fn put_predicted(&mut self, Predicted(entity): Predicted<T>) {
         let sid = entity.stable_id();
         let index_key = index_key(LAST_PREDICTED_PREFIX, sid);
+        self.unconfirmed_since.insert(entity.version(), self.current_slot);
         self.put(index_key, entity);
     }
 
     fn invalidate_version(&mut self, ver: T::Version) -> Option<T::StableId> {
+        self.unconfirmed_since.remove(&ver);
         if let Some(entity) = self.store.remove(&ver) {
             let sid = entity.stable_id();
             let indexes = vec![
@@ -228,9 +283,11 @@ where
         let confirmed_ver = self.index.remove(&index_key(LAST_PREDICTED_PREFIX, sid));
         if let Some(ver) = predicted_ver {
             self.store.remove(&ver);
+            self.unconfirmed_since.remove(&ver);
         }
         if let Some(ver) = unconfirmed_ver {
             self.store.remove(&ver);
+            self.unconfirmed_since.remove(&ver);
         }
         if let Some(ver) = confirmed_ver {
             self.store.remove(&ver);
@@ -244,6 +301,226 @@ where
     fn get_state(&self, sid: T::Version) -> Option<T> {
         self.store.get(&sid).map(|e| e.clone())
     }
+
+    fn expire_unconfirmed(&mut self, current_slot: u64, ttl_slots: u64) -> Vec<T::StableId> {
+        self.current_slot = current_slot;
+        let expired_versions = self
+            .unconfirmed_since
+            .iter()
+            .filter(|(_, since)| current_slot.saturating_sub(**since) >= ttl_slots)
+            .map(|(ver, _)| *ver)
+            .collect::<Vec<_>>();
+        let mut affected = vec![];
+        for ver in expired_versions {
+            if let Some(sid) = self.invalidate_version(ver) {
+                self.expired_total += 1;
+                if !affected.contains(&sid) {
+                    affected.push(sid);
+                }
+            }
+        }
+        affected
+    }
+
+    fn expired_total(&self) -> u64 {
+        self.expired_total
+    }
+}
+
+/// [StateIndex] persisted in a [KvBackend].
+/// The backend may be shared with a [kv_store::PersistentKvStore], wrap it into
+/// [spectrum_offchain::kv_backend::Batched] to update both atomically.
+/// Clones share the underlying storage when the backend does.
+#[derive(Clone)]
+pub struct PersistentStateIndex<B, T> {
+    backend: B,
+    current_slot: u64,
+    expired_total: u64,
+    pd: PhantomData<T>,
+}
+
+impl<B, T> PersistentStateIndex<B, T> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            current_slot: 0,
+            expired_total: 0,
+            pd: PhantomData,
+        }
+    }
+}
+
+const STATE_PREFIX: u8 = 6u8;
+const UNCONFIRMED_SINCE_PREFIX: u8 = 7u8;
+
+fn version_key<V: Serialize>(prefix: u8, ver: &V) -> Vec<u8> {
+    let mut key = vec![prefix];
+    key.extend(bincode::serialize(ver).unwrap());
+    key
+}
+
+impl<B, T> PersistentStateIndex<B, T>
+where
+    B: KvBackend,
+    T: EntitySnapshot + Serialize + DeserializeOwned,
+    T::Version: Serialize + DeserializeOwned,
+{
+    fn get_indexed(&self, index_key: InMemoryIndexKey) -> Option<T> {
+        self.backend
+            .get(&index_key)
+            .and_then(|raw| bincode::deserialize::<T::Version>(&raw).ok())
+            .and_then(|ver| self.get_state_by_version(&ver))
+    }
+
+    fn get_state_by_version(&self, ver: &T::Version) -> Option<T> {
+        self.backend
+            .get(&version_key(STATE_PREFIX, ver))
+            .and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn indexed_version(&self, index_key: &InMemoryIndexKey) -> Option<T::Version> {
+        self.backend
+            .get(index_key)
+            .and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn put(&mut self, index_key: InMemoryIndexKey, value: T) {
+        if let Some(old_ver) = self.indexed_version(&index_key) {
+            self.backend.delete(&version_key(STATE_PREFIX, &old_ver));
+            self.backend.delete(&version_key(UNCONFIRMED_SINCE_PREFIX, &old_ver));
+        }
+        let new_ver = value.version();
+        self.backend
+            .put(&index_key, &bincode::serialize(&new_ver).unwrap());
+        self.backend.put(
+            &version_key(STATE_PREFIX, &new_ver),
+            &bincode::serialize(&value).unwrap(),
+        );
+    }
+
+    fn mark_unconfirmed(&self, ver: &T::Version) {
+        self.backend.put(
+            &version_key(UNCONFIRMED_SINCE_PREFIX, ver),
+            &bincode::serialize(&self.current_slot).unwrap(),
+        );
+    }
+}
+
+impl<B, T> StateIndex<T> for PersistentStateIndex<B, T>
+where
+    B: KvBackend,
+    T: EntitySnapshot + Serialize + DeserializeOwned,
+    T::Version: Serialize + DeserializeOwned,
+    <T as Stable>::StableId: Into<[u8; 28]>,
+{
+    fn get_last_confirmed(&self, id: T::StableId) -> Option<Confirmed<T>> {
+        self.get_indexed(index_key(LAST_CONFIRMED_PREFIX, id)).map(Confirmed)
+    }
+
+    fn get_last_unconfirmed(&self, id: T::StableId) -> Option<Unconfirmed<T>> {
+        self.get_indexed(index_key(LAST_UNCONFIRMED_PREFIX, id))
+            .map(Unconfirmed)
+    }
+
+    fn get_last_predicted(&self, id: T::StableId) -> Option<Predicted<T>> {
+        self.get_indexed(index_key(LAST_PREDICTED_PREFIX, id)).map(Predicted)
+    }
+
+    fn put_confirmed(&mut self, Confirmed(entity): Confirmed<T>) {
+        let sid = entity.stable_id();
+        self.backend
+            .delete(&version_key(UNCONFIRMED_SINCE_PREFIX, &entity.version()));
+        self.put(index_key(LAST_CONFIRMED_PREFIX, sid), entity);
+    }
+
+    fn put_unconfirmed(&mut self, Unconfirmed(entity): Unconfirmed<T>) {
+        let sid = entity.stable_id();
+        self.mark_unconfirmed(&entity.version());
+        self.put(index_key(LAST_UNCONFIRMED_PREFIX, sid), entity);
+    }
+
+    fn put_predicted(&mut self, Predicted(entity): Predicted<T>) {
+        let sid = entity.stable_id();
+        self.mark_unconfirmed(&entity.version());
+        self.put(index_key(LAST_PREDICTED_PREFIX, sid), entity);
+    }
+
+    fn invalidate_version(&mut self, ver: T::Version) -> Option<T::StableId> {
+        self.backend.delete(&version_key(UNCONFIRMED_SINCE_PREFIX, &ver));
+        let entity = self.get_state_by_version(&ver)?;
+        self.backend.delete(&version_key(STATE_PREFIX, &ver));
+        let sid = entity.stable_id();
+        for prefix in [
+            LAST_PREDICTED_PREFIX,
+            LAST_UNCONFIRMED_PREFIX,
+            LAST_CONFIRMED_PREFIX,
+        ] {
+            let key = index_key(prefix, sid);
+            if self.indexed_version(&key) == Some(ver) {
+                self.backend.delete(&key);
+            }
+        }
+        Some(sid)
+    }
+
+    fn eliminate(&mut self, sid: T::StableId) {
+        for prefix in [
+            LAST_PREDICTED_PREFIX,
+            LAST_UNCONFIRMED_PREFIX,
+            LAST_CONFIRMED_PREFIX,
+        ] {
+            let key = index_key(prefix, sid);
+            if let Some(ver) = self.indexed_version(&key) {
+                self.backend.delete(&key);
+                self.backend.delete(&version_key(STATE_PREFIX, &ver));
+                self.backend.delete(&version_key(UNCONFIRMED_SINCE_PREFIX, &ver));
+            }
+        }
+    }
+
+    fn exists(&self, sid: &T::Version) -> bool {
+        self.backend.get(&version_key(STATE_PREFIX, sid)).is_some()
+    }
+
+    fn get_state(&self, sid: T::Version) -> Option<T> {
+        self.get_state_by_version(&sid)
+    }
+
+    fn expire_unconfirmed(&mut self, current_slot: u64, ttl_slots: u64) -> Vec<T::StableId> {
+        self.current_slot = current_slot;
+        let expired_versions = self
+            .backend
+            .scan_prefix(&[UNCONFIRMED_SINCE_PREFIX])
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let ver = bincode::deserialize::<T::Version>(&key[1..]).ok()?;
+                let since = bincode::deserialize::<u64>(&value).ok()?;
+                (current_slot.saturating_sub(since) >= ttl_slots).then_some(ver)
+            })
+            .collect::<Vec<_>>();
+        let mut affected = vec![];
+        for ver in expired_versions {
+            if let Some(sid) = self.invalidate_version(ver) {
+                self.expired_total += 1;
+                if !affected.contains(&sid) {
+                    affected.push(sid);
+                }
+            }
+        }
+        affected
+    }
+
+    fn expired_total(&self) -> u64 {
+        self.expired_total
+    }
+
+    fn begin_batch(&mut self) {
+        self.backend.begin()
+    }
+
+    fn commit_batch(&mut self) {
+        self.backend.commit()
+    }
 }
 
 pub fn index_key<T: Into<[u8; 28]>>(prefix: u8, id: T) -> InMemoryIndexKey {
@@ -254,3 +531,135 @@ pub fn index_key<T: Into<[u8; 28]>>(prefix: u8, id: T) -> InMemoryIndexKey {
     }
     arr
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::{Display, Formatter};
+    use std::sync::Arc;
+
+    use rand::RngCore;
+
+    use spectrum_offchain::data::event::{Confirmed, Unconfirmed};
+    use spectrum_offchain::data::{EntitySnapshot, Stable};
+    use spectrum_offchain::kv_backend::{open_backend, Batched, KvBackend, KvBackendConfig};
+
+    use crate::execution_engine::resolver::resolve_source_state;
+    use crate::execution_engine::storage::kv_store::{KvStore, PersistentKvStore};
+    use crate::execution_engine::storage::{InMemoryStateIndex, PersistentStateIndex, StateIndex};
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+    struct Id(u8);
+
+    impl Display for Id {
+        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+            write!(f, "Id({})", self.0)
+        }
+    }
+
+    impl From<Id> for [u8; 28] {
+        fn from(id: Id) -> Self {
+            [id.0; 28]
+        }
+    }
+
+    #[derive(Copy, Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Entity(Id, u64);
+
+    impl Stable for Entity {
+        type StableId = Id;
+        fn stable_id(&self) -> Self::StableId {
+            self.0
+        }
+        fn is_quasi_permanent(&self) -> bool {
+            false
+        }
+    }
+
+    impl EntitySnapshot for Entity {
+        type Version = u64;
+        fn version(&self) -> Self::Version {
+            self.1
+        }
+    }
+
+    fn unconfirmed_state_expires_to_last_confirmed<I: StateIndex<Entity>>(mut index: I) {
+        let (v1, v2, v3) = (Entity(Id(0), 1), Entity(Id(0), 2), Entity(Id(0), 3));
+        index.put_confirmed(Confirmed(v1));
+        assert!(index.expire_unconfirmed(100, 10).is_empty());
+        index.put_unconfirmed(Unconfirmed(v2));
+        index.put_unconfirmed(Unconfirmed(Entity(Id(1), 4)));
+        assert!(index.expire_unconfirmed(105, 10).is_empty());
+        // Confirmed in time.
+        index.put_confirmed(Confirmed(Entity(Id(1), 4)));
+        assert_eq!(index.expire_unconfirmed(110, 10), vec![Id(0)]);
+        assert_eq!(resolve_source_state(Id(0), &index), Some(v1));
+        assert_eq!(resolve_source_state(Id(1), &index), Some(Entity(Id(1), 4)));
+        assert_eq!(index.expired_total(), 1);
+        index.put_unconfirmed(Unconfirmed(v3));
+        assert!(index.expire_unconfirmed(119, 10).is_empty());
+    }
+
+    fn rolled_back_state_is_unresolved<I: StateIndex<Entity>>(mut index: I) {
+        index.put_unconfirmed(Unconfirmed(Entity(Id(0), 1)));
+        assert_eq!(index.invalidate_version(1), Some(Id(0)));
+        assert_eq!(resolve_source_state::<Entity, _>(Id(0), &index), None);
+    }
+
+    /// Scenarios every [StateIndex] implementation must pass.
+    fn conformance<I: StateIndex<Entity>>(new_index: impl Fn() -> I) {
+        unconfirmed_state_expires_to_last_confirmed(new_index());
+        rolled_back_state_is_unresolved(new_index());
+    }
+
+    fn persistent_index(
+        conf: impl Fn(String) -> KvBackendConfig,
+    ) -> impl Fn() -> PersistentStateIndex<Arc<dyn KvBackend + Send + Sync>, Entity> {
+        move || {
+            let db_path = format!("./tmp/{}", rand::thread_rng().next_u32());
+            PersistentStateIndex::new(open_backend(&conf(db_path)))
+        }
+    }
+
+    #[test]
+    fn in_memory_index_conforms() {
+        conformance(InMemoryStateIndex::new);
+    }
+
+    #[test]
+    fn rocksdb_index_conforms() {
+        conformance(persistent_index(|db_path| KvBackendConfig::RocksDB { db_path }));
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn sled_index_conforms() {
+        conformance(persistent_index(|db_path| KvBackendConfig::Sled { db_path }));
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn redb_index_conforms() {
+        conformance(persistent_index(|db_path| KvBackendConfig::Redb { db_path }));
+    }
+
+    #[test]
+    fn index_and_cache_are_committed_together() {
+        let backend = open_backend(&KvBackendConfig::RocksDB {
+            db_path: format!("./tmp/{}", rand::thread_rng().next_u32()),
+        });
+        let batched = Batched::new(backend.clone());
+        let mut index = PersistentStateIndex::<_, Entity>::new(batched.clone());
+        let mut cache = PersistentKvStore::<_, Id, Entity>::new(batched);
+        let entity = Entity(Id(0), 1);
+        index.begin_batch();
+        index.put_confirmed(Confirmed(entity));
+        cache.insert(Id(0), entity);
+        assert_eq!(cache.get(Id(0)), Some(entity));
+        assert!(backend.scan_prefix(&[]).is_empty());
+        index.commit_batch();
+        let index = PersistentStateIndex::<_, Entity>::new(backend.clone());
+        let cache = PersistentKvStore::<_, Id, Entity>::new(backend);
+        assert_eq!(resolve_source_state(Id(0), &index), Some(entity));
+        assert_eq!(cache.get(Id(0)), Some(entity));
+    }
+}
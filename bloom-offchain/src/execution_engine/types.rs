@@ -10,7 +10,7 @@ pub struct Time(u64);
 pub struct StableId([u8; 32]);
 
 impl StableId {
-    #[cfg(test)]
+    #[cfg(any(test, feature = "harness"))]
     pub fn random() -> StableId {
         let mut bf = [0u8; 32];
         rand::thread_rng().fill_bytes(&mut bf);
@@ -1,3 +1,6 @@
 mod display;
 pub mod execution_engine;
+pub mod market_making;
+pub mod pair_registry;
 pub mod partitioning;
+pub mod quote;
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use num_rational::Ratio;
+
+use crate::execution_engine::liquidity_book::side::Side;
+use crate::execution_engine::liquidity_book::types::{mul_div, AbsolutePrice, Rounding};
+use crate::execution_engine::notifier::{Fill, FillNotifier};
+use crate::execution_engine::reserve_history::Volatility;
+
+const BPS: i128 = 10_000;
+
+/// How we quote our own inventory around an index price.
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotingConfig {
+    /// Distance of each quote from the mid price in basis points.
+    pub half_spread_bps: u32,
+    /// Base asset bought or sold by each quote.
+    pub quote_size: u64,
+    /// Position (in base asset) beyond which we stop adding to it.
+    pub max_position: u64,
+    /// Mid price moves against the position by this many basis points per `quote_size` held.
+    pub skew_bps: u32,
+    /// Half spread is widened by this percentage of realized volatility of the pair.
+    #[serde(default)]
+    pub volatility_spread_percent: u32,
+}
+
+impl QuotingConfig {
+    /// Config to quote with given recent volatility of the pair, as is if it is unknown.
+    pub fn widened_by(self, volatility: Option<Volatility>) -> Self {
+        let widening = volatility.map_or(0.0, |v| v.bps * self.volatility_spread_percent as f64 / 100.0);
+        Self {
+            half_spread_bps: self.half_spread_bps.saturating_add(widening.ceil() as u32),
+            ..self
+        }
+    }
+}
+
+/// Holdings of the maker in a pair.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct Inventory {
+    pub base: u64,
+    pub quote: u64,
+    /// Net base asset bought (positive) or sold (negative) through fills of our quotes.
+    pub position: i128,
+}
+
+impl Inventory {
+    pub fn on_fill(&mut self, side: Side, base: u64, quote: u64) {
+        match side {
+            Side::Bid => {
+                self.base += base;
+                self.quote = self.quote.saturating_sub(quote);
+                self.position += base as i128;
+            }
+            Side::Ask => {
+                self.base = self.base.saturating_sub(base);
+                self.quote += quote;
+                self.position -= base as i128;
+            }
+        }
+    }
+}
+
+/// Quote to keep resting in the book.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TargetQuote {
+    pub side: Side,
+    pub price: AbsolutePrice,
+    /// Base asset bought (bid) or sold (ask).
+    pub base_amount: u64,
+    /// Quote asset paid (bid) or asked for (ask).
+    pub quote_amount: u64,
+}
+
+/// Quotes backed by the given inventory.
+/// Mid price is skewed against the current position so that fills tend to flatten it,
+/// the side growing the position is withdrawn once it reaches the limit.
+pub fn target_quotes(
+    index_price: AbsolutePrice,
+    inventory: Inventory,
+    conf: QuotingConfig,
+) -> Vec<TargetQuote> {
+    let skew = inventory.position * conf.skew_bps as i128 / conf.quote_size.max(1) as i128;
+    let half_spread = conf.half_spread_bps as i128;
+    let mut quotes = vec![];
+    if inventory.position < conf.max_position as i128 {
+        if let Some(bid) = quote_at(Side::Bid, index_price, -skew - half_spread, conf.quote_size) {
+            if bid.quote_amount <= inventory.quote {
+                quotes.push(bid);
+            }
+        }
+    }
+    if inventory.position > -(conf.max_position as i128) && conf.quote_size <= inventory.base {
+        if let Some(ask) = quote_at(Side::Ask, index_price, -skew + half_spread, conf.quote_size) {
+            quotes.push(ask);
+        }
+    }
+    quotes
+}
+
+fn quote_at(
+    side: Side,
+    index_price: AbsolutePrice,
+    offset_bps: i128,
+    base_amount: u64,
+) -> Option<TargetQuote> {
+    let factor = BPS + offset_bps;
+    if factor <= 0 {
+        return None;
+    }
+    let price = AbsolutePrice::from(Ratio::new(
+        index_price.numer().checked_mul(factor as u128)?,
+        index_price.denom().checked_mul(BPS as u128)?,
+    ));
+    // We never pay more than the price on bid and never ask for less on ask.
+    let rounding = side.select(Rounding::Floor, Rounding::Ceil);
+    let quote_amount = mul_div(base_amount, *price.numer(), *price.denom(), rounding)?;
+    (quote_amount > 0).then(|| TargetQuote {
+        side,
+        price,
+        base_amount,
+        quote_amount,
+    })
+}
+
+struct QuotingState<Pair, OrderId> {
+    inventories: HashMap<Pair, Inventory>,
+    /// Our quotes resting in the book along with pairs they quote.
+    resting: HashMap<OrderId, Pair>,
+}
+
+/// Inventories of pairs we quote in along with our quotes resting in the book.
+/// Shared between the quoting routine and fill notifications.
+#[derive(Clone)]
+pub struct QuotingBook<Pair, OrderId>(Arc<Mutex<QuotingState<Pair, OrderId>>>);
+
+impl<Pair, OrderId> QuotingBook<Pair, OrderId>
+where
+    Pair: Copy + Eq + Hash,
+    OrderId: Eq + Hash,
+{
+    pub fn new(inventories: HashMap<Pair, Inventory>) -> Self {
+        Self(Arc::new(Mutex::new(QuotingState {
+            inventories,
+            resting: HashMap::new(),
+        })))
+    }
+
+    /// Pairs none of our quotes rest in at the moment, along with our inventories in them.
+    pub fn idle(&self) -> Vec<(Pair, Inventory)> {
+        let state = self.0.lock().unwrap();
+        state
+            .inventories
+            .iter()
+            .filter(|(pair, _)| !state.resting.values().any(|p| p == *pair))
+            .map(|(pair, inventory)| (*pair, *inventory))
+            .collect()
+    }
+
+    /// Register quotes placed in the pair.
+    pub fn placed(&self, pair: Pair, orders: impl IntoIterator<Item = OrderId>) {
+        let mut state = self.0.lock().unwrap();
+        for order in orders {
+            state.resting.insert(order, pair);
+        }
+    }
+
+    pub fn inventory(&self, pair: &Pair) -> Option<Inventory> {
+        self.0.lock().unwrap().inventories.get(pair).copied()
+    }
+}
+
+impl<Pair, OrderId, TxHash> FillNotifier<OrderId, TxHash> for QuotingBook<Pair, OrderId>
+where
+    Pair: Copy + Eq + Hash,
+    OrderId: Eq + Hash,
+{
+    fn on_fill(&self, fill: Fill<OrderId>, _: TxHash) {
+        let mut state = self.0.lock().unwrap();
+        let Some(pair) = state.resting.get(&fill.order_id).copied() else {
+            return;
+        };
+        let (base, quote) = match fill.side {
+            Side::Ask => (fill.removed_input, fill.added_output),
+            Side::Bid => (fill.added_output, fill.removed_input),
+        };
+        if let Some(inventory) = state.inventories.get_mut(&pair) {
+            inventory.on_fill(fill.side, base, quote);
+        }
+        if fill.terminal {
+            state.resting.remove(&fill.order_id);
+        }
+    }
+
+    fn on_removed(&self, order_id: OrderId) {
+        self.0.lock().unwrap().resting.remove(&order_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::execution_engine::liquidity_book::side::Side;
+    use crate::execution_engine::liquidity_book::types::AbsolutePrice;
+    use crate::execution_engine::notifier::{Fill, FillNotifier};
+    use crate::execution_engine::reserve_history::Volatility;
+    use crate::market_making::{target_quotes, Inventory, QuotingBook, QuotingConfig};
+
+    const CONF: QuotingConfig = QuotingConfig {
+        half_spread_bps: 100,
+        quote_size: 1_000,
+        max_position: 2_000,
+        skew_bps: 50,
+        volatility_spread_percent: 50,
+    };
+
+    fn inventory(position: i128) -> Inventory {
+        Inventory {
+            base: 10_000,
+            quote: 10_000,
+            position,
+        }
+    }
+
+    #[test]
+    fn flat_position_quotes_symmetric_spread() {
+        let quotes = target_quotes(AbsolutePrice::new_unsafe(2, 1), inventory(0), CONF);
+        assert_eq!(quotes.len(), 2);
+        assert_eq!((quotes[0].side, quotes[0].quote_amount), (Side::Bid, 1_980));
+        assert_eq!((quotes[1].side, quotes[1].quote_amount), (Side::Ask, 2_020));
+    }
+
+    #[test]
+    fn long_position_lowers_quotes() {
+        let quotes = target_quotes(AbsolutePrice::new_unsafe(2, 1), inventory(1_000), CONF);
+        assert_eq!(quotes[0].quote_amount, 1_970);
+        assert_eq!(quotes[1].quote_amount, 2_010);
+    }
+
+    #[test]
+    fn volatility_widens_spread() {
+        let volatility = Volatility {
+            bps: 100.0,
+            returns: 10,
+        };
+        assert_eq!(CONF.widened_by(None).half_spread_bps, 100);
+        let quotes = target_quotes(
+            AbsolutePrice::new_unsafe(2, 1),
+            inventory(0),
+            CONF.widened_by(Some(volatility)),
+        );
+        assert_eq!(quotes[0].quote_amount, 1_970);
+        assert_eq!(quotes[1].quote_amount, 2_030);
+    }
+
+    #[test]
+    fn position_limit_withdraws_growing_side() {
+        let long = target_quotes(AbsolutePrice::new_unsafe(2, 1), inventory(2_000), CONF);
+        assert_eq!(long.iter().map(|q| q.side).collect::<Vec<_>>(), vec![Side::Ask]);
+        let short = target_quotes(AbsolutePrice::new_unsafe(2, 1), inventory(-2_000), CONF);
+        assert_eq!(short.iter().map(|q| q.side).collect::<Vec<_>>(), vec![Side::Bid]);
+    }
+
+    #[test]
+    fn quotes_are_backed_by_inventory() {
+        let broke = Inventory {
+            base: 999,
+            quote: 1_000,
+            position: 0,
+        };
+        assert!(target_quotes(AbsolutePrice::new_unsafe(2, 1), broke, CONF).is_empty());
+    }
+
+    #[test]
+    fn fills_move_position() {
+        let mut inv = inventory(0);
+        inv.on_fill(Side::Bid, 1_000, 1_980);
+        assert_eq!(
+            inv,
+            Inventory {
+                base: 11_000,
+                quote: 8_020,
+                position: 1_000
+            }
+        );
+        inv.on_fill(Side::Ask, 500, 1_010);
+        assert_eq!(
+            inv,
+            Inventory {
+                base: 10_500,
+                quote: 9_030,
+                position: 500
+            }
+        );
+    }
+
+    #[test]
+    fn fills_of_own_quotes_update_inventory() {
+        let book = QuotingBook::<u8, u8>::new(HashMap::from([(0, inventory(0))]));
+        book.placed(0, [1, 2]);
+        assert!(book.idle().is_empty());
+        let fill = Fill {
+            order_id: 1,
+            side: Side::Bid,
+            removed_input: 1_980,
+            added_output: 1_000,
+            fee_charged: 0,
+            terminal: true,
+        };
+        book.on_fill(fill, ());
+        book.on_fill(Fill { order_id: 3, ..fill }, ());
+        assert_eq!(
+            book.inventory(&0),
+            Some(Inventory {
+                base: 11_000,
+                quote: 8_020,
+                position: 1_000
+            })
+        );
+        assert!(book.idle().is_empty());
+        book.on_removed(2);
+        assert_eq!(book.idle(), vec![(0, book.inventory(&0).unwrap())]);
+    }
+}
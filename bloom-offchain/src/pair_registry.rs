@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use either::Either;
+use isahc::{AsyncReadResponseExt, Request, RequestExt};
+use log::{trace, warn};
+use serde::Serialize;
+
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, StateUpdate};
+use spectrum_offchain::data::Stable;
+
+use crate::execution_engine::bundled::Bundled;
+use crate::execution_engine::liquidity_book::market_maker::MarketMaker;
+use crate::execution_engine::Event;
+
+/// Confirmed state of a pool as seen by the registry.
+#[derive(Debug, Copy, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolListing<PoolId, Ver> {
+    pub pool_id: PoolId,
+    pub version: Ver,
+    pub reserves_base: u64,
+    pub reserves_quote: u64,
+}
+
+/// Tradable pair along with all pools serving it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairListing<Pair, PoolId, Ver> {
+    pub pair: Pair,
+    pub pools: Vec<PoolListing<PoolId, Ver>>,
+}
+
+struct RegistryState<Pair, PoolId, Ver> {
+    pools: HashMap<PoolId, (Pair, PoolListing<PoolId, Ver>)>,
+    /// Whether anything changed since the last snapshot was taken.
+    dirty: bool,
+}
+
+/// Authoritative set of tradable pairs discovered from ledger-confirmed pool states.
+#[derive(Clone)]
+pub struct PairRegistry<Pair, PoolId, Ver> {
+    state: Arc<Mutex<RegistryState<Pair, PoolId, Ver>>>,
+}
+
+impl<Pair, PoolId, Ver> PairRegistry<Pair, PoolId, Ver> {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(RegistryState {
+                pools: HashMap::new(),
+                dirty: false,
+            })),
+        }
+    }
+}
+
+impl<Pair, PoolId, Ver> Default for PairRegistry<Pair, PoolId, Ver> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Pair, PoolId, Ver> PairRegistry<Pair, PoolId, Ver>
+where
+    Pair: Copy + Ord,
+    PoolId: Copy + Ord + Hash,
+    Ver: Copy,
+{
+    /// Track pools in the given event. Unconfirmed updates are ignored.
+    pub fn observe<CO, SO, P, B>(&self, pair: Pair, event: &Event<CO, SO, P, B, Ver>)
+    where
+        P: Stable<StableId = PoolId> + MarketMaker,
+    {
+        let Either::Left(Channel::Ledger(Confirmed(
+            StateUpdate::Transition(tr) | StateUpdate::TransitionRollback(tr),
+        ))) = event
+        else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        match tr {
+            Ior::Left(Bundled(Either::Right(pool), _)) => {
+                state.pools.remove(&pool.entity.stable_id());
+            }
+            Ior::Right(Bundled(Either::Right(pool), _)) | Ior::Both(_, Bundled(Either::Right(pool), _)) => {
+                let reserves = pool.entity.liquidity();
+                let listing = PoolListing {
+                    pool_id: pool.entity.stable_id(),
+                    version: pool.version,
+                    reserves_base: reserves.base,
+                    reserves_quote: reserves.quote,
+                };
+                state.pools.insert(listing.pool_id, (pair, listing));
+            }
+            _ => return,
+        }
+        state.dirty = true;
+    }
+
+    /// Current listing ordered by pair, if it changed since the previous call.
+    pub fn take_snapshot(&self) -> Option<Vec<PairListing<Pair, PoolId, Ver>>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.dirty {
+            return None;
+        }
+        state.dirty = false;
+        let mut pools = state.pools.values().copied().collect::<Vec<_>>();
+        pools.sort_by_key(|(pair, pool)| (*pair, pool.pool_id));
+        let mut listings: Vec<PairListing<Pair, PoolId, Ver>> = vec![];
+        for (pair, pool) in pools {
+            match listings.last_mut() {
+                Some(listing) if listing.pair == pair => listing.pools.push(pool),
+                _ => listings.push(PairListing {
+                    pair,
+                    pools: vec![pool],
+                }),
+            }
+        }
+        Some(listings)
+    }
+}
+
+/// Where the listing is published.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ListingTarget {
+    /// JSON file, replaced atomically on each update.
+    File(String),
+    /// HTTP endpoint the JSON listing is PUT to.
+    Http(String),
+}
+
+#[async_trait::async_trait]
+pub trait ListingPublisher<T> {
+    async fn publish(&self, listing: &T) -> Result<(), String>;
+}
+
+#[async_trait::async_trait]
+impl<T> ListingPublisher<T> for ListingTarget
+where
+    T: Serialize + Sync,
+{
+    async fn publish(&self, listing: &T) -> Result<(), String> {
+        let body = serde_json::to_vec_pretty(listing).map_err(|err| err.to_string())?;
+        match self {
+            ListingTarget::File(path) => {
+                let tmp_path = format!("{}.tmp", path);
+                tokio::fs::write(&tmp_path, body)
+                    .await
+                    .map_err(|err| err.to_string())?;
+                tokio::fs::rename(&tmp_path, path)
+                    .await
+                    .map_err(|err| err.to_string())
+            }
+            ListingTarget::Http(endpoint) => {
+                let mut resp = Request::put(endpoint)
+                    .header("Content-Type", "application/json")
+                    .body(body)
+                    .map_err(|err| err.to_string())?
+                    .send_async()
+                    .await
+                    .map_err(|err| err.to_string())?;
+                if resp.status().is_success() {
+                    Ok(())
+                } else {
+                    let reason = resp.text().await.unwrap_or_default();
+                    Err(format!("{}: {}", resp.status(), reason))
+                }
+            }
+        }
+    }
+}
+
+/// Publish the listing each time it changes, checking for changes every `interval`.
+pub async fn publish_listing<Pair, PoolId, Ver, Pub>(
+    registry: PairRegistry<Pair, PoolId, Ver>,
+    publisher: Pub,
+    interval: Duration,
+) where
+    Pair: Copy + Ord + Serialize + Send + Sync,
+    PoolId: Copy + Ord + Hash + Serialize + Send + Sync,
+    Ver: Copy + Serialize + Send + Sync,
+    Pub: ListingPublisher<Vec<PairListing<Pair, PoolId, Ver>>> + Send + Sync,
+{
+    loop {
+        if let Some(listing) = registry.take_snapshot() {
+            match publisher.publish(&listing).await {
+                Ok(_) => trace!("Published listing of {} pairs", listing.len()),
+                Err(err) => {
+                    warn!("Failed to publish pair listing: {}", err);
+                    // Make sure the listing is retried on the next tick.
+                    registry.state.lock().unwrap().dirty = true;
+                }
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::pair_registry::{PairRegistry, PoolListing};
+
+    #[test]
+    fn snapshot_groups_pools_by_pair() {
+        let registry = PairRegistry::<u8, u8, u8>::new();
+        {
+            let mut state = registry.state.lock().unwrap();
+            state.pools = HashMap::from([(3, (1, listing(3))), (1, (2, listing(1))), (2, (1, listing(2)))]);
+            state.dirty = true;
+        }
+        let snapshot = registry.take_snapshot().unwrap();
+        let grouped = snapshot
+            .iter()
+            .map(|l| (l.pair, l.pools.iter().map(|p| p.pool_id).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        assert_eq!(grouped, vec![(1, vec![2, 3]), (2, vec![1])]);
+        assert!(registry.take_snapshot().is_none());
+    }
+
+    fn listing(pool_id: u8) -> PoolListing<u8, u8> {
+        PoolListing {
+            pool_id,
+            version: 0,
+            reserves_base: 0,
+            reserves_quote: 0,
+        }
+    }
+}
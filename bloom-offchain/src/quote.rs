@@ -0,0 +1,183 @@
+use std::fmt::Display;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use either::Either;
+use serde::Serialize;
+
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, Predicted, StateUpdate, Unconfirmed};
+use spectrum_offchain::data::Stable;
+use spectrum_offchain::maker::{Maker, Specialize};
+
+use crate::execution_engine::bundled::Bundled;
+use crate::execution_engine::liquidity_book::market_taker::MarketTaker;
+use crate::execution_engine::liquidity_book::{ExternalTLBEvents, TLBFeedback, TemporalLiquidityBook};
+use crate::execution_engine::multi_pair::MultiPair;
+use crate::execution_engine::Event;
+
+/// Max number of matchmaking rounds a single quote may take.
+const MAX_QUOTE_ROUNDS: usize = 16;
+/// Max number of sizes a ladder is evaluated at.
+const MAX_LADDER_RUNGS: usize = 32;
+
+/// Expected outcome of a trade.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    /// Part of the requested input that can be executed.
+    pub input: u64,
+    pub output: u64,
+    /// Number of makers the trade is routed through.
+    pub makers_used: usize,
+    /// Number of other takers the trade is matched with.
+    pub takers_used: usize,
+}
+
+/// Point of a price ladder.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LadderRung {
+    /// Cumulative executable input.
+    pub input: u64,
+    /// Cumulative output.
+    pub output: u64,
+    /// Price (Output/Input) of the input added since the previous rung.
+    pub marginal_price: f64,
+}
+
+/// Replica of the books of all pairs which is only used to preview trades.
+#[derive(Clone)]
+pub struct QuoteBooks<Pair, Book, Ctx> {
+    books: Arc<Mutex<MultiPair<Pair, Book, Ctx>>>,
+}
+
+impl<Pair, Book, Ctx> QuoteBooks<Pair, Book, Ctx> {
+    pub fn new(context: Ctx) -> Self {
+        Self {
+            books: Arc::new(Mutex::new(MultiPair::new::<Book>(context, "Quote"))),
+        }
+    }
+}
+
+impl<Pair, Book, Ctx> QuoteBooks<Pair, Book, Ctx>
+where
+    Pair: Copy + Eq + Hash + Display,
+    Book: Maker<Ctx>,
+    Ctx: Specialize<Pair> + Clone,
+{
+    /// Mirror the given event in the book of the pair.
+    pub fn observe<CO, SO, P, B, Ver>(&self, pair: Pair, event: &Event<CO, SO, P, B, Ver>)
+    where
+        CO: Clone,
+        P: Clone,
+        Book: ExternalTLBEvents<CO, P>,
+    {
+        let Either::Left(
+            Channel::Ledger(Confirmed(upd))
+            | Channel::Mempool(Unconfirmed(upd))
+            | Channel::LocalTxSubmit(Predicted(upd)),
+        ) = event
+        else {
+            return;
+        };
+        let (StateUpdate::Transition(tr) | StateUpdate::TransitionRollback(tr)) = upd;
+        let mut books = self.books.lock().unwrap();
+        let book = books.get_mut(&pair);
+        match tr {
+            Ior::Left(Bundled(Either::Left(taker), _)) => book.remove_taker(taker.entity.clone()),
+            Ior::Left(Bundled(Either::Right(maker), _)) => book.remove_maker(maker.entity.clone()),
+            Ior::Both(Bundled(Either::Left(old), _), Bundled(Either::Left(new), _)) => {
+                book.remove_taker(old.entity.clone());
+                book.update_taker(new.entity.clone());
+            }
+            Ior::Both(_, Bundled(Either::Right(maker), _)) | Ior::Right(Bundled(Either::Right(maker), _)) => {
+                book.update_maker(maker.entity.clone())
+            }
+            Ior::Both(_, Bundled(Either::Left(taker), _)) | Ior::Right(Bundled(Either::Left(taker), _)) => {
+                book.update_taker(taker.entity.clone())
+            }
+        }
+    }
+
+    /// Preview execution of the given `taker` against a copy of the current book of the pair.
+    pub fn quote<T, M>(&self, pair: &Pair, taker: T, now: u64) -> Quote
+    where
+        T: Stable + MarketTaker,
+        M: Stable,
+        Book: Clone + ExternalTLBEvents<T, M> + TemporalLiquidityBook<T, M> + TLBFeedback<T, M>,
+    {
+        let book = self.books.lock().unwrap().get_mut(pair).clone();
+        preview(book, taker, now)
+    }
+
+    /// Quote `amount` split into `steps` equal increments, aggregating all liquidity sources
+    /// of the pair. The ladder ends early once the book can't absorb more input.
+    pub fn ladder<T, M, F>(
+        &self,
+        pair: &Pair,
+        amount: u64,
+        steps: usize,
+        now: u64,
+        taker_of: F,
+    ) -> Vec<LadderRung>
+    where
+        T: Stable + MarketTaker,
+        M: Stable,
+        Book: Clone + ExternalTLBEvents<T, M> + TemporalLiquidityBook<T, M> + TLBFeedback<T, M>,
+        F: Fn(u64) -> T,
+    {
+        let book = self.books.lock().unwrap().get_mut(pair).clone();
+        let steps = steps.clamp(1, MAX_LADDER_RUNGS);
+        let mut rungs: Vec<LadderRung> = Vec::with_capacity(steps);
+        for i in 1..=steps {
+            let size = (amount as u128 * i as u128 / steps as u128) as u64;
+            let quote = preview(book.clone(), taker_of(size), now);
+            let (prev_input, prev_output) = rungs.last().map(|r| (r.input, r.output)).unwrap_or((0, 0));
+            let added_input = quote.input.saturating_sub(prev_input);
+            if added_input == 0 {
+                break;
+            }
+            rungs.push(LadderRung {
+                input: quote.input,
+                output: quote.output,
+                marginal_price: quote.output.saturating_sub(prev_output) as f64 / added_input as f64,
+            });
+            if quote.input < size {
+                break;
+            }
+        }
+        rungs
+    }
+}
+
+fn preview<T, M, Book>(mut book: Book, taker: T, now: u64) -> Quote
+where
+    T: Stable + MarketTaker,
+    M: Stable,
+    Book: ExternalTLBEvents<T, M> + TemporalLiquidityBook<T, M> + TLBFeedback<T, M>,
+{
+    let taker_id = taker.stable_id();
+    book.advance_clocks(now);
+    book.update_taker(taker);
+    let mut quote = Quote::default();
+    for _ in 0..MAX_QUOTE_ROUNDS {
+        let Some(recipe) = book.attempt() else {
+            break;
+        };
+        let fills = recipe.fills();
+        let (own_fills, other_fills): (Vec<_>, Vec<_>) =
+            fills.into_iter().partition(|fill| fill.order_id == taker_id);
+        book.on_recipe_succeeded();
+        if let Some(fill) = own_fills.first() {
+            quote.input += fill.removed_input;
+            quote.output += fill.added_output;
+            quote.makers_used += recipe.maker_ids().len();
+            quote.takers_used += other_fills.len();
+            if fill.terminal {
+                break;
+            }
+        }
+    }
+    quote
+}
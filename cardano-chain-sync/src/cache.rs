@@ -12,6 +12,7 @@ use serde::Serialize;
 use tokio::task::spawn_blocking;
 
 use crate::client::Point;
+use spectrum_offchain::rocks::{migrate, Migration};
 
 #[derive(serde::Serialize, serde::Deserialize)]
 pub struct LinkedBlock(/*block bytes*/ pub Vec<u8>, /*prev point*/ pub Point);
@@ -27,15 +28,18 @@ pub trait LedgerCache {
     fn replay<'a>(&self, from_point: Inclusive<Point>) -> impl Stream<Item = LinkedBlock> + Send + 'a;
 }
 
+/// Migrations of cached blocks and tip.
+const MIGRATIONS: &[Migration<rocksdb::OptimisticTransactionDB>] = &[];
+
 pub struct LedgerCacheRocksDB {
     pub db: Arc<rocksdb::OptimisticTransactionDB>,
 }
 
 impl LedgerCacheRocksDB {
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        Self {
-            db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(path).unwrap()),
-        }
+        let db = rocksdb::OptimisticTransactionDB::open_default(path).unwrap();
+        migrate(&db, "ledger_cache", MIGRATIONS);
+        Self { db: Arc::new(db) }
     }
 }
 
@@ -6,7 +6,7 @@ use cml_core::serialization::Deserialize;
 use cml_core::Slot;
 use cml_crypto::BlockHeaderHash;
 use log::debug;
-use pallas_network::miniprotocols::chainsync::{BlockContent, NextResponse, State};
+use pallas_network::miniprotocols::chainsync::{BlockContent, NextResponse, State, Tip};
 use pallas_network::miniprotocols::handshake::RefuseReason;
 use pallas_network::miniprotocols::{chainsync, handshake, PROTOCOL_N2C_CHAIN_SYNC, PROTOCOL_N2C_HANDSHAKE};
 use pallas_network::multiplexer;
@@ -19,6 +19,8 @@ use crate::data::ChainUpgrade;
 pub struct ChainSyncClient<Block> {
     plexer: RunningPlexer,
     chain_sync: chainsync::N2CClient,
+    /// Latest tip reported by the node.
+    tip: Option<Point>,
     block: PhantomData<Block>,
 }
 
@@ -71,6 +73,7 @@ impl<Block> ChainSyncClient<Block> {
         Ok(Self {
             plexer,
             chain_sync: cs_client,
+            tip: None,
             block: PhantomData::default(),
         })
     }
@@ -84,7 +87,8 @@ impl<Block> ChainSyncClient<Block> {
             _ => self.chain_sync.request_next().await,
         };
         match response {
-            Ok(NextResponse::RollForward(BlockContent(raw), _)) => {
+            Ok(NextResponse::RollForward(BlockContent(raw), Tip(tip, _))) => {
+                self.tip = Some(tip.into());
                 let original_bytes = raw[BLK_START..].to_vec();
                 match Block::from_cbor_bytes(&original_bytes) {
                     Ok(blk) => Some(ChainUpgrade::RollForward {
@@ -99,11 +103,18 @@ impl<Block> ChainSyncClient<Block> {
                     ),
                 }
             }
-            Ok(NextResponse::RollBackward(pt, _)) => Some(ChainUpgrade::RollBackward(pt.into())),
+            Ok(NextResponse::RollBackward(pt, Tip(tip, _))) => {
+                self.tip = Some(tip.into());
+                Some(ChainUpgrade::RollBackward(pt.into()))
+            }
             _ => None,
         }
     }
 
+    pub fn tip(&self) -> Option<Point> {
+        self.tip
+    }
+
     pub async fn close(self) {
         self.plexer.abort().await
     }
@@ -12,6 +12,18 @@ pub enum LedgerTxEvent<Tx> {
     TxUnapplied(Tx),
 }
 
+impl<Tx> LedgerTxEvent<Tx> {
+    pub fn map<Tx1, F>(self, f: F) -> LedgerTxEvent<Tx1>
+    where
+        F: FnOnce(Tx) -> Tx1,
+    {
+        match self {
+            LedgerTxEvent::TxApplied { tx, slot } => LedgerTxEvent::TxApplied { tx: f(tx), slot },
+            LedgerTxEvent::TxUnapplied(tx) => LedgerTxEvent::TxUnapplied(f(tx)),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub enum ChainUpgrade<Block> {
     /// Deserialized block and it's serialized representation.
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_stream::stream;
@@ -8,6 +9,8 @@ use futures_timer::Delay;
 use log::trace;
 use tokio::sync::broadcast;
 
+use spectrum_offchain::sync_progress::SyncProgress;
+
 use crate::client::ChainSyncClient;
 use crate::data::ChainUpgrade;
 
@@ -19,6 +22,7 @@ pub mod event_source;
 pub fn chain_sync_stream<'a, Block>(
     mut chain_sync: ChainSyncClient<Block>,
     tip_reached_signal: broadcast::Sender<bool>,
+    sync_progress: Arc<SyncProgress>,
 ) -> impl Stream<Item = ChainUpgrade<Block>> + 'a
 where
     Block: Deserialize + 'a,
@@ -30,10 +34,21 @@ where
             if let Some(delay) = delay {
                 delay.await;
             }
-            if let Some(upgr) = chain_sync.try_pull_next().await {
+            let next = chain_sync.try_pull_next().await;
+            let tip_slot = chain_sync.tip().map(|tip| tip.get_slot());
+            if let Some(tip_slot) = tip_slot {
+                sync_progress.on_tip(tip_slot);
+            }
+            if let Some(upgr) = next {
+                if let ChainUpgrade::RollBackward(point) = &upgr {
+                    sync_progress.on_point(point.get_slot());
+                }
                 yield upgr;
             } else {
                 trace!(target: "chain_sync", "Tip reached, waiting for new blocks ..");
+                if let Some(tip_slot) = tip_slot {
+                    sync_progress.on_point(tip_slot);
+                }
                 *delay_mux.lock().await = Some(Delay::new(Duration::from_secs(THROTTLE_SECS)));
                 let _ = tip_reached_signal.send(true);
             }
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use cml_chain::address::Address;
+use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+use cml_chain::plutus::{PlutusData, PlutusV1Script, PlutusV2Script, PlutusV3Script};
+use cml_chain::transaction::{
+    ConwayFormatTxOut, DatumOption, NativeScript, ScriptRef, TransactionInput, TransactionOutput,
+};
+use cml_chain::{PolicyId, Value};
+use cml_core::serialization::Deserialize;
+use cml_crypto::{BlockHeaderHash, DatumHash, ScriptHash, TransactionHash};
+use log::warn;
+use serde::de::DeserializeOwned;
+
+use cardano_chain_sync::client::Point;
+use spectrum_cardano_lib::value::ValueExtension;
+use spectrum_cardano_lib::AssetClass::Token;
+use spectrum_cardano_lib::{AssetName, OutputRef, PaymentCredential};
+
+use crate::CardanoNetwork;
+
+/// Client of a [Kupo](https://cardanosolutions.github.io/kupo) instance.
+/// Kupo serves all matches of a pattern at once, so `offset` and `limit` are applied locally.
+#[derive(Clone)]
+pub struct Kupo {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl Kupo {
+    pub fn new(url: String) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Most recent point the instance is synchronized to.
+    pub async fn tip(&self) -> Option<Point> {
+        latest_point(
+            self.get::<Vec<KupoCheckpoint>>(format!("{}/checkpoints", self.url))
+                .await?,
+        )
+    }
+
+    async fn unspent_matches(&self, pattern: &str) -> Vec<TransactionUnspentOutput> {
+        let matches = self
+            .get::<Vec<KupoMatch>>(format!("{}/matches/{}?unspent", self.url, pattern))
+            .await
+            .unwrap_or_default();
+        let mut utxos = vec![];
+        for m in matches {
+            let oref = format!("{}#{}", m.transaction_id, m.output_index);
+            match self.resolve(m).await {
+                Some(utxo) => utxos.push(utxo),
+                None => warn!("Kupo match {} could not be resolved", oref),
+            }
+        }
+        utxos
+    }
+
+    /// Fetch datum and reference script of the match and assemble the UTxO.
+    async fn resolve(&self, m: KupoMatch) -> Option<TransactionUnspentOutput> {
+        let datum_option = match (m.datum_hash, m.datum_type) {
+            (Some(hash), Some(KupoDatumType::Inline)) => {
+                let KupoDatum { datum } = self
+                    .get::<Option<KupoDatum>>(format!("{}/datums/{}", self.url, hash))
+                    .await??;
+                Some(DatumOption::new_datum(
+                    PlutusData::from_cbor_bytes(&*hex::decode(datum).ok()?).ok()?,
+                ))
+            }
+            (Some(hash), _) => Some(DatumOption::new_hash(DatumHash::from_hex(hash.as_str()).ok()?)),
+            (None, _) => None,
+        };
+        let script_reference = match m.script_hash {
+            Some(hash) => Some(
+                self.get::<Option<KupoScript>>(format!("{}/scripts/{}", self.url, hash))
+                    .await??
+                    .try_into_cml()?,
+            ),
+            None => None,
+        };
+        let input = TransactionInput::new(
+            TransactionHash::from_hex(m.transaction_id.as_str()).ok()?,
+            m.output_index,
+        );
+        let output = TransactionOutput::ConwayFormatTxOut(ConwayFormatTxOut {
+            address: Address::from_bech32(m.address.as_str()).ok()?,
+            amount: m.value.try_into_cml()?,
+            datum_option,
+            script_reference,
+            encodings: None,
+        });
+        Some(TransactionUnspentOutput::new(input, output))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, url: String) -> Option<T> {
+        match self.client.get(url.as_str()).send().await {
+            Ok(resp) => resp.json::<T>().await.ok(),
+            Err(err) => {
+                warn!("Kupo request {} failed: {}", url, err);
+                None
+            }
+        }
+    }
+}
+
+fn latest_point(checkpoints: Vec<KupoCheckpoint>) -> Option<Point> {
+    let latest = checkpoints.into_iter().max_by_key(|c| c.slot_no)?;
+    Some(Point::Specific(
+        latest.slot_no,
+        BlockHeaderHash::from_hex(latest.header_hash.as_str()).ok()?,
+    ))
+}
+
+fn page(utxos: Vec<TransactionUnspentOutput>, offset: u32, limit: u16) -> Vec<TransactionUnspentOutput> {
+    utxos
+        .into_iter()
+        .skip(offset as usize)
+        .take(limit as usize)
+        .collect()
+}
+
+/// Kupo expects credentials in hex.
+fn credential_pattern(payment_credential: PaymentCredential) -> Option<String> {
+    let cred = String::from(payment_credential);
+    ScriptHash::from_bech32(cred.as_str())
+        .ok()
+        .map(|hash| format!("{}/*", hash.to_hex()))
+}
+
+impl CardanoNetwork for Kupo {
+    async fn utxo_by_ref(&self, oref: OutputRef) -> Option<TransactionUnspentOutput> {
+        self.unspent_matches(format!("{}@{}", oref.index(), oref.tx_hash().to_hex()).as_str())
+            .await
+            .pop()
+    }
+
+    async fn utxos_by_pay_cred(
+        &self,
+        payment_credential: PaymentCredential,
+        offset: u32,
+        limit: u16,
+    ) -> Vec<TransactionUnspentOutput> {
+        page(
+            self.all_utxos_by_pay_cred(payment_credential).await,
+            offset,
+            limit,
+        )
+    }
+
+    async fn utxos_by_address(
+        &self,
+        address: Address,
+        offset: u32,
+        limit: u16,
+    ) -> Vec<TransactionUnspentOutput> {
+        let utxos = self
+            .unspent_matches(address.to_bech32(None).unwrap().as_str())
+            .await;
+        page(utxos, offset, limit)
+    }
+
+    async fn all_utxos_by_pay_cred(
+        &self,
+        payment_credential: PaymentCredential,
+    ) -> Vec<TransactionUnspentOutput> {
+        match credential_pattern(payment_credential) {
+            Some(pattern) => self.unspent_matches(pattern.as_str()).await,
+            None => vec![],
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct KupoCheckpoint {
+    slot_no: u64,
+    header_hash: String,
+}
+
+#[derive(serde::Deserialize)]
+struct KupoMatch {
+    transaction_id: String,
+    output_index: u64,
+    address: String,
+    value: KupoValue,
+    datum_hash: Option<String>,
+    #[serde(default)]
+    datum_type: Option<KupoDatumType>,
+    script_hash: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum KupoDatumType {
+    Hash,
+    Inline,
+}
+
+#[derive(serde::Deserialize)]
+struct KupoValue {
+    coins: u64,
+    /// Quantities keyed by `<policy_id>.<asset_name>`, or just `<policy_id>` for empty names.
+    #[serde(default)]
+    assets: HashMap<String, u64>,
+}
+
+impl KupoValue {
+    fn try_into_cml(self) -> Option<Value> {
+        let mut value = Value::from(self.coins);
+        for (unit, amount) in self.assets {
+            let (policy, name) = unit.split_once('.').unwrap_or((unit.as_str(), ""));
+            let policy_id = PolicyId::from_hex(policy).ok()?;
+            let token_name = AssetName::try_from_hex(name)?;
+            value.add_unsafe(Token((policy_id, token_name)), amount);
+        }
+        Some(value)
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct KupoDatum {
+    datum: String,
+}
+
+#[derive(serde::Deserialize)]
+struct KupoScript {
+    language: String,
+    script: String,
+}
+
+impl KupoScript {
+    fn try_into_cml(self) -> Option<ScriptRef> {
+        let raw = hex::decode(self.script).ok()?;
+        match self.language.as_str() {
+            "native" => Some(ScriptRef::new_native(NativeScript::from_cbor_bytes(&*raw).ok()?)),
+            "plutus:v1" => Some(ScriptRef::new_plutus_v1(
+                PlutusV1Script::from_cbor_bytes(&*raw).ok()?,
+            )),
+            "plutus:v2" => Some(ScriptRef::new_plutus_v2(
+                PlutusV2Script::from_cbor_bytes(&*raw).ok()?,
+            )),
+            "plutus:v3" => Some(ScriptRef::new_plutus_v3(
+                PlutusV3Script::from_cbor_bytes(&*raw).ok()?,
+            )),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cardano_chain_sync::client::Point;
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+    use cml_chain::certs::Credential;
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::{PolicyId, Value};
+    use cml_crypto::{BlockHeaderHash, ScriptHash, TransactionHash};
+
+    use spectrum_cardano_lib::value::ValueExtension;
+    use spectrum_cardano_lib::{AssetClass, AssetName, PaymentCredential};
+
+    use crate::kupo::{credential_pattern, latest_point, page, KupoCheckpoint, KupoValue};
+
+    #[test]
+    fn value_is_read_from_match() {
+        let policy = PolicyId::from([1u8; 28]);
+        let value = serde_json::from_str::<KupoValue>(
+            format!(
+                r#"{{"coins": 2000000, "assets": {{"{0}.6e6674": 5, "{0}": 7}}}}"#,
+                policy.to_hex()
+            )
+            .as_str(),
+        )
+        .unwrap()
+        .try_into_cml()
+        .unwrap();
+        let token = |name: &str| AssetClass::Token((policy, AssetName::try_from_hex(name).unwrap()));
+        assert_eq!(value.amount_of(AssetClass::Native), Some(2_000_000));
+        assert_eq!(value.amount_of(token("6e6674")), Some(5));
+        assert_eq!(value.amount_of(token("")), Some(7));
+    }
+
+    #[test]
+    fn script_credentials_are_matched_by_hash() {
+        let hash = ScriptHash::from([2u8; 28]);
+        assert_eq!(
+            credential_pattern(PaymentCredential::from(hash)),
+            Some(format!("{}/*", hash.to_hex()))
+        );
+        assert_eq!(
+            credential_pattern(PaymentCredential::from("script1".to_string())),
+            None
+        );
+    }
+
+    #[test]
+    fn tip_is_the_latest_checkpoint() {
+        let hash = |b: u8| hex::encode([b; 32]);
+        let checkpoints = vec![
+            KupoCheckpoint {
+                slot_no: 10,
+                header_hash: hash(1),
+            },
+            KupoCheckpoint {
+                slot_no: 20,
+                header_hash: hash(2),
+            },
+        ];
+        assert_eq!(
+            latest_point(checkpoints),
+            Some(Point::Specific(20, BlockHeaderHash::from([2u8; 32])))
+        );
+        assert_eq!(latest_point(vec![]), None);
+    }
+
+    #[test]
+    fn matches_are_paged_locally() {
+        let address =
+            EnterpriseAddress::new(0, Credential::new_script(ScriptHash::from([2u8; 28]))).to_address();
+        let utxos = (0..3)
+            .map(|ix| {
+                TransactionUnspentOutput::new(
+                    TransactionInput::new(TransactionHash::from([0u8; 32]), ix),
+                    TransactionOutput::new(address.clone(), Value::from(2_000_000), None, None),
+                )
+            })
+            .collect::<Vec<_>>();
+        let indices = |offset, limit| {
+            page(utxos.clone(), offset, limit)
+                .into_iter()
+                .map(|u| u.input.index)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(indices(1, 1), vec![1]);
+        assert_eq!(indices(2, 10), vec![2]);
+    }
+}
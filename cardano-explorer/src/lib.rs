@@ -20,6 +20,7 @@ pub mod client;
 
 pub mod constants;
 pub mod data;
+pub mod kupo;
 
 #[derive(serde::Deserialize)]
 pub enum Network {
@@ -59,8 +60,30 @@ pub trait CardanoNetwork {
         offset: u32,
         limit: u16,
     ) -> Vec<TransactionUnspentOutput>;
+    /// All UTxOs locked by the given credential, fetched page by page.
+    async fn all_utxos_by_pay_cred(
+        &self,
+        payment_credential: PaymentCredential,
+    ) -> Vec<TransactionUnspentOutput> {
+        let mut utxos = vec![];
+        let mut offset = 0u32;
+        loop {
+            let page = self
+                .utxos_by_pay_cred(payment_credential.clone(), offset, UTXOS_PAGE_SIZE)
+                .await;
+            let num_fetched = page.len();
+            utxos.extend(page);
+            if num_fetched < UTXOS_PAGE_SIZE as usize {
+                break;
+            }
+            offset += UTXOS_PAGE_SIZE as u32;
+        }
+        utxos
+    }
 }
 
+const UTXOS_PAGE_SIZE: u16 = 100;
+
 pub struct Maestro(maestro::Maestro);
 
 impl Maestro {
@@ -2,11 +2,13 @@ use std::collections::{HashSet, VecDeque};
 use std::marker::PhantomData;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_stream::stream;
 use cml_core::serialization::Deserialize;
 use cml_crypto::blake2b224;
 use futures::Stream;
+use futures_timer::Delay;
 use pallas_network::miniprotocols::{handshake, txmonitor, PROTOCOL_N2C_HANDSHAKE};
 use pallas_network::multiplexer;
 use pallas_network::multiplexer::{Bearer, RunningPlexer};
@@ -76,6 +78,10 @@ impl<Tx> LocalTxMonitorClient<Tx> {
                             break;
                         }
                     }
+                } else {
+                    // Don't hammer the node while it refuses to give out a snapshot.
+                    drop(tx_monitor);
+                    Delay::new(ACQUIRE_RETRY_DELAY).await;
                 }
             }
         }
@@ -88,6 +94,7 @@ impl<Tx> LocalTxMonitorClient<Tx> {
 
 const PROTOCOL_N2C_TX_MONITOR: u16 = 9;
 const FILTER_CAP: usize = 4096;
+const ACQUIRE_RETRY_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash)]
 struct RawTxHash([u8; 28]);
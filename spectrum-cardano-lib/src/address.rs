@@ -1,12 +1,12 @@
-use crate::plutus_data::{ConstrPlutusDataExtension, PlutusDataExtension};
+use crate::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
 use crate::types::TryFromPData;
 use crate::NetworkId;
-use cml_chain::address::{Address, BaseAddress, EnterpriseAddress};
+use cml_chain::address::{Address, BaseAddress, EnterpriseAddress, Pointer, PointerAddress};
 use cml_chain::certs::{Credential, StakeCredential};
-use cml_chain::plutus::PlutusData;
+use cml_chain::plutus::{ConstrPlutusData, PlutusData};
 use cml_crypto::{Ed25519KeyHash, RawBytesEncoding, ScriptHash};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum PlutusCredential {
     PubKey(Ed25519KeyHash),
     Script(ScriptHash),
@@ -26,6 +26,25 @@ impl TryFromPData for PlutusCredential {
     }
 }
 
+impl IntoPlutusData for PlutusCredential {
+    fn into_pd(self) -> PlutusData {
+        let (alt, hash) = match self {
+            PlutusCredential::PubKey(hash) => (0, hash.to_raw_bytes().to_vec()),
+            PlutusCredential::Script(hash) => (1, hash.to_raw_bytes().to_vec()),
+        };
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(alt, vec![PlutusData::new_bytes(hash)]))
+    }
+}
+
+impl PlutusCredential {
+    pub fn from_credential(cred: &Credential) -> Self {
+        match cred {
+            Credential::PubKey { hash, .. } => PlutusCredential::PubKey(*hash),
+            Credential::Script { hash, .. } => PlutusCredential::Script(*hash),
+        }
+    }
+}
+
 impl From<PlutusCredential> for Credential {
     fn from(value: PlutusCredential) -> Self {
         match value {
@@ -35,38 +54,126 @@ impl From<PlutusCredential> for Credential {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct InlineCredential(PlutusCredential);
-impl TryFromPData for InlineCredential {
+/// Plutus `StakingCredential`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum StakingCredential {
+    Inline(PlutusCredential),
+    /// Location of the stake registration certificate.
+    Pointer {
+        slot: u64,
+        tx_index: u64,
+        cert_index: u64,
+    },
+}
+
+impl TryFromPData for StakingCredential {
     fn try_from_pd(data: PlutusData) -> Option<Self> {
         let mut cpd = data.into_constr_pd()?;
         match cpd.alternative {
-            0 => Some(InlineCredential(PlutusCredential::try_from_pd(
+            0 => Some(StakingCredential::Inline(PlutusCredential::try_from_pd(
                 cpd.take_field(0)?,
             )?)),
+            1 => Some(StakingCredential::Pointer {
+                slot: cpd.take_field(0)?.into_u64()?,
+                tx_index: cpd.take_field(1)?.into_u64()?,
+                cert_index: cpd.take_field(2)?.into_u64()?,
+            }),
             _ => None,
         }
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+impl From<PlutusCredential> for StakingCredential {
+    fn from(cred: PlutusCredential) -> Self {
+        Self::Inline(cred)
+    }
+}
+
+impl IntoPlutusData for StakingCredential {
+    fn into_pd(self) -> PlutusData {
+        match self {
+            StakingCredential::Inline(cred) => {
+                PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, vec![cred.into_pd()]))
+            }
+            StakingCredential::Pointer {
+                slot,
+                tx_index,
+                cert_index,
+            } => PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+                1,
+                vec![slot.into_pd(), tx_index.into_pd(), cert_index.into_pd()],
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct PlutusAddress {
     pub payment_cred: PlutusCredential,
-    pub stake_cred: Option<InlineCredential>,
+    pub stake_cred: Option<StakingCredential>,
 }
 
 impl PlutusAddress {
+    /// Address of a user identified by key hashes, as recorded in datums of classical orders.
+    pub fn pub_key(payment_pkh: Ed25519KeyHash, stake_pkh: Option<Ed25519KeyHash>) -> Self {
+        Self {
+            payment_cred: PlutusCredential::PubKey(payment_pkh),
+            stake_cred: stake_pkh.map(|pkh| StakingCredential::Inline(PlutusCredential::PubKey(pkh))),
+        }
+    }
+
+    /// Key hash which must sign to spend from this address, if any.
+    pub fn payment_pkh(&self) -> Option<Ed25519KeyHash> {
+        match self.payment_cred {
+            PlutusCredential::PubKey(pkh) => Some(pkh),
+            PlutusCredential::Script(_) => None,
+        }
+    }
+
+    pub fn from_address(address: &Address) -> Option<Self> {
+        match address {
+            Address::Base(addr) => Some(Self {
+                payment_cred: PlutusCredential::from_credential(&addr.payment),
+                stake_cred: Some(StakingCredential::Inline(PlutusCredential::from_credential(
+                    &addr.stake,
+                ))),
+            }),
+            Address::Enterprise(addr) => Some(Self {
+                payment_cred: PlutusCredential::from_credential(&addr.payment),
+                stake_cred: None,
+            }),
+            Address::Ptr(addr) => Some(Self {
+                payment_cred: PlutusCredential::from_credential(&addr.payment),
+                stake_cred: Some(StakingCredential::Pointer {
+                    slot: addr.stake.slot(),
+                    tx_index: addr.stake.tx_index(),
+                    cert_index: addr.stake.cert_index(),
+                }),
+            }),
+            _ => None,
+        }
+    }
+
     pub fn to_address(self, network_id: NetworkId) -> Address {
         let PlutusAddress {
             payment_cred,
             stake_cred,
         } = self;
         match stake_cred {
-            Some(InlineCredential(stake_cred)) => Address::Base(BaseAddress::new(
+            Some(StakingCredential::Inline(stake_cred)) => Address::Base(BaseAddress::new(
                 network_id.into(),
                 payment_cred.into(),
                 stake_cred.into(),
             )),
+            Some(StakingCredential::Pointer {
+                slot,
+                tx_index,
+                cert_index,
+            }) => Address::Ptr(PointerAddress::new(
+                network_id.into(),
+                payment_cred.into(),
+                Pointer::new(slot, tx_index, cert_index),
+            )),
             None => Address::Enterprise(EnterpriseAddress::new(network_id.into(), payment_cred.into())),
         }
     }
@@ -82,6 +189,15 @@ impl TryFromPData for PlutusAddress {
     }
 }
 
+impl IntoPlutusData for PlutusAddress {
+    fn into_pd(self) -> PlutusData {
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![self.payment_cred.into_pd(), self.stake_cred.into_pd()],
+        ))
+    }
+}
+
 pub trait AddressExtension {
     fn script_hash(&self) -> Option<ScriptHash>;
     fn update_payment_cred(&mut self, cred: Credential);
@@ -115,10 +231,13 @@ impl AddressExtension for Address {
 
 #[cfg(test)]
 mod test {
-    use crate::address::PlutusAddress;
+    use crate::address::{PlutusAddress, PlutusCredential, StakingCredential};
+    use crate::plutus_data::IntoPlutusData;
     use crate::types::TryFromPData;
+    use crate::NetworkId;
     use cml_chain::plutus::PlutusData;
     use cml_core::serialization::Deserialize;
+    use cml_crypto::ScriptHash;
 
     const RAW_ADDR: &str = "D8799FD8799F581C4BE4FA25F029D14C0D723AF4A1E6FA7133FC3A610F880336AD685CBAFFD8799FD8799FD8799F581C5BDA73043D43AD8DF5CE75639CF48E1F2B4545403BE92F0113E37537FFFFFFFF";
 
@@ -129,4 +248,20 @@ mod test {
                 .unwrap();
         dbg!(addr);
     }
+
+    #[test]
+    fn script_owner_with_pointer_roundtrip() {
+        let addr = PlutusAddress {
+            payment_cred: PlutusCredential::Script(ScriptHash::from([7u8; 28])),
+            stake_cred: Some(StakingCredential::Pointer {
+                slot: 2498243,
+                tx_index: 27,
+                cert_index: 3,
+            }),
+        };
+        assert_eq!(PlutusAddress::try_from_pd(addr.into_pd()), Some(addr));
+        let ledger_addr = addr.to_address(NetworkId::from(1));
+        assert_eq!(PlutusAddress::from_address(&ledger_addr), Some(addr));
+        assert_eq!(addr.payment_pkh(), None);
+    }
 }
@@ -0,0 +1,236 @@
+use std::fmt::{Display, Formatter};
+
+use cml_chain::address::Address;
+use cml_chain::transaction::{
+    AlonzoFormatTxOut, ConwayFormatTxOut, DatumOption, ScriptRef, TransactionOutput,
+};
+use cml_chain::Value;
+use cml_multi_era::babbage::{BabbageFormatTxOut, BabbageScriptRef, BabbageTransactionOutput};
+
+use crate::transaction::BabbageScriptRefExtension;
+
+/// Layout of an output on the wire.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Legacy array layout, carries datum hash at most.
+    Alonzo,
+    /// Map layout introduced in Babbage.
+    PostAlonzo,
+}
+
+/// Era-agnostic transaction output.
+/// Converting an output of any era into [AnyEraOutput] and back preserves its content and layout,
+/// original CBOR encodings are not preserved though.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AnyEraOutput {
+    pub address: Address,
+    pub value: Value,
+    pub datum: Option<DatumOption>,
+    pub script_ref: Option<ScriptRef>,
+    pub format: OutputFormat,
+}
+
+impl AnyEraOutput {
+    /// Whether the output can be represented in the legacy layout.
+    fn fits_alonzo_format(&self) -> bool {
+        self.script_ref.is_none() && !matches!(self.datum, Some(DatumOption::Datum { .. }))
+    }
+
+    fn datum_hash(&self) -> Option<cml_crypto::DatumHash> {
+        match &self.datum {
+            Some(DatumOption::Hash { datum_hash, .. }) => Some(*datum_hash),
+            _ => None,
+        }
+    }
+}
+
+/// Part of an output that cannot be represented in the target era.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LossyConversion {
+    /// Reference script of a language unknown to the target era.
+    ScriptRef(ScriptRef),
+}
+
+impl Display for LossyConversion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LossyConversion::ScriptRef(_) => write!(f, "Reference script is not supported by the target era"),
+        }
+    }
+}
+
+impl From<BabbageTransactionOutput> for AnyEraOutput {
+    fn from(out: BabbageTransactionOutput) -> Self {
+        match out {
+            BabbageTransactionOutput::AlonzoFormatTxOut(out) => alonzo_format(out),
+            BabbageTransactionOutput::BabbageFormatTxOut(out) => Self {
+                address: out.address,
+                value: out.amount,
+                datum: out.datum_option,
+                script_ref: out.script_reference.map(BabbageScriptRef::upcast),
+                format: OutputFormat::PostAlonzo,
+            },
+        }
+    }
+}
+
+impl From<TransactionOutput> for AnyEraOutput {
+    fn from(out: TransactionOutput) -> Self {
+        match out {
+            TransactionOutput::AlonzoFormatTxOut(out) => alonzo_format(out),
+            TransactionOutput::ConwayFormatTxOut(out) => Self {
+                address: out.address,
+                value: out.amount,
+                datum: out.datum_option,
+                script_ref: out.script_reference,
+                format: OutputFormat::PostAlonzo,
+            },
+        }
+    }
+}
+
+fn alonzo_format(out: AlonzoFormatTxOut) -> AnyEraOutput {
+    AnyEraOutput {
+        address: out.address,
+        value: out.amount,
+        datum: out.datum_hash.map(DatumOption::new_hash),
+        script_ref: None,
+        format: OutputFormat::Alonzo,
+    }
+}
+
+/// Conway is a superset of previous eras, so the conversion is never lossy.
+impl From<AnyEraOutput> for TransactionOutput {
+    fn from(out: AnyEraOutput) -> Self {
+        if out.format == OutputFormat::Alonzo && out.fits_alonzo_format() {
+            let mut legacy = AlonzoFormatTxOut::new(out.address.clone(), out.value.clone());
+            legacy.datum_hash = out.datum_hash();
+            TransactionOutput::AlonzoFormatTxOut(legacy)
+        } else {
+            TransactionOutput::ConwayFormatTxOut(ConwayFormatTxOut {
+                address: out.address,
+                amount: out.value,
+                datum_option: out.datum,
+                script_reference: out.script_ref,
+                encodings: None,
+            })
+        }
+    }
+}
+
+impl TryFrom<AnyEraOutput> for BabbageTransactionOutput {
+    type Error = LossyConversion;
+    fn try_from(out: AnyEraOutput) -> Result<Self, Self::Error> {
+        if out.format == OutputFormat::Alonzo && out.fits_alonzo_format() {
+            let mut legacy = AlonzoFormatTxOut::new(out.address.clone(), out.value.clone());
+            legacy.datum_hash = out.datum_hash();
+            return Ok(BabbageTransactionOutput::AlonzoFormatTxOut(legacy));
+        }
+        let script_reference = match out.script_ref {
+            None => None,
+            Some(script_ref) => Some(downcast_script_ref(script_ref)?),
+        };
+        Ok(BabbageTransactionOutput::BabbageFormatTxOut(BabbageFormatTxOut {
+            address: out.address,
+            amount: out.value,
+            datum_option: out.datum,
+            script_reference,
+            encodings: None,
+        }))
+    }
+}
+
+fn downcast_script_ref(script_ref: ScriptRef) -> Result<BabbageScriptRef, LossyConversion> {
+    match script_ref {
+        ScriptRef::Native {
+            script,
+            len_encoding,
+            tag_encoding,
+        } => Ok(BabbageScriptRef::Native {
+            script,
+            len_encoding,
+            tag_encoding,
+        }),
+        ScriptRef::PlutusV1 {
+            script,
+            len_encoding,
+            tag_encoding,
+        } => Ok(BabbageScriptRef::PlutusV1 {
+            script,
+            len_encoding,
+            tag_encoding,
+        }),
+        ScriptRef::PlutusV2 {
+            script,
+            len_encoding,
+            tag_encoding,
+        } => Ok(BabbageScriptRef::PlutusV2 {
+            script,
+            len_encoding,
+            tag_encoding,
+        }),
+        unsupported => Err(LossyConversion::ScriptRef(unsupported)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::plutus::{PlutusData, PlutusV2Script, PlutusV3Script};
+    use cml_chain::transaction::{AlonzoFormatTxOut, DatumOption, ScriptRef, TransactionOutput};
+    use cml_chain::Value;
+    use cml_crypto::{DatumHash, Ed25519KeyHash};
+    use cml_multi_era::babbage::{BabbageFormatTxOut, BabbageScriptRef, BabbageTransactionOutput};
+
+    use crate::era::{AnyEraOutput, LossyConversion};
+
+    fn address() -> cml_chain::address::Address {
+        EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([1u8; 28]))).to_address()
+    }
+
+    fn babbage_round_trip(out: BabbageTransactionOutput) {
+        let unified = AnyEraOutput::from(out.clone());
+        assert_eq!(BabbageTransactionOutput::try_from(unified.clone()), Ok(out));
+        let conway = TransactionOutput::from(unified.clone());
+        assert_eq!(AnyEraOutput::from(conway), unified);
+    }
+
+    #[test]
+    fn legacy_output_round_trip() {
+        let mut out = AlonzoFormatTxOut::new(address(), Value::from(2_000_000));
+        out.datum_hash = Some(DatumHash::from([2u8; 32]));
+        babbage_round_trip(BabbageTransactionOutput::AlonzoFormatTxOut(out));
+    }
+
+    #[test]
+    fn babbage_output_round_trip() {
+        babbage_round_trip(BabbageTransactionOutput::BabbageFormatTxOut(
+            BabbageFormatTxOut::new(
+                address(),
+                Value::from(2_000_000),
+                Some(DatumOption::new_datum(PlutusData::new_list(vec![]))),
+                Some(BabbageScriptRef::new_plutus_v2(PlutusV2Script::new(vec![
+                    3u8;
+                    16
+                ]))),
+            ),
+        ));
+        babbage_round_trip(BabbageTransactionOutput::BabbageFormatTxOut(
+            BabbageFormatTxOut::new(address(), Value::from(2_000_000), None, None),
+        ));
+    }
+
+    #[test]
+    fn loss_is_detected() {
+        let script_ref = ScriptRef::new_plutus_v3(PlutusV3Script::new(vec![4u8; 16]));
+        let conway =
+            TransactionOutput::new(address(), Value::from(2_000_000), None, Some(script_ref.clone()));
+        let unified = AnyEraOutput::from(conway.clone());
+        assert_eq!(TransactionOutput::from(unified.clone()), conway);
+        assert_eq!(
+            BabbageTransactionOutput::try_from(unified),
+            Err(LossyConversion::ScriptRef(script_ref))
+        );
+    }
+}
@@ -6,22 +6,23 @@ use std::str::FromStr;
 
 use cml_chain::assets::MultiAsset;
 use cml_chain::certs::Credential;
-use cml_chain::plutus::PlutusData;
+use cml_chain::plutus::{ConstrPlutusData, PlutusData};
 use cml_chain::transaction::TransactionInput;
 use cml_chain::{PolicyId, Value};
-use cml_crypto::{RawBytesEncoding, TransactionHash};
+use cml_crypto::{RawBytesEncoding, ScriptHash, TransactionHash};
 use derivative::Derivative;
 use derive_more::{From, Into};
 use num::{CheckedAdd, CheckedSub};
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::plutus_data::{ConstrPlutusDataExtension, PlutusDataExtension};
+use crate::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
 use crate::types::TryFromPData;
 
 pub mod address;
 pub mod collateral;
 pub mod constants;
 pub mod credential;
+pub mod era;
 pub mod ex_units;
 pub mod funding;
 pub mod hash;
@@ -33,7 +34,9 @@ pub mod types;
 pub mod value;
 
 /// Asset name bytes padded to 32-byte fixed array and tupled with the len of the original asset name.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, derive_more::From)]
+#[derive(
+    Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize, derive_more::From,
+)]
 pub struct AssetName(u8, [u8; 32]);
 
 impl AssetName {
@@ -113,8 +116,8 @@ impl TryFrom<Vec<u8>> for AssetName {
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize)]
-#[serde(try_from = "String")]
+/// Reference to a transaction output, formatted as `<tx_hash_hex>#<index>`.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct OutputRef(TransactionHash, u64);
 
 impl OutputRef {
@@ -129,6 +132,30 @@ impl OutputRef {
     }
 }
 
+/// Human-readable formats get `<tx_hash_hex>#<index>`, binary ones get a `(tx_hash_bytes, index)` tuple.
+impl Serialize for OutputRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            (self.0.to_raw_bytes(), self.1).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let raw = String::deserialize(deserializer)?;
+            OutputRef::from_str(&raw).map_err(serde::de::Error::custom)
+        } else {
+            let (raw_hash, ix) = <(Vec<u8>, u64)>::deserialize(deserializer)?;
+            let hash = TransactionHash::from_raw_bytes(&raw_hash).map_err(serde::de::Error::custom)?;
+            Ok(OutputRef(hash, ix))
+        }
+    }
+}
+
 impl Debug for OutputRef {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         Display::fmt(self, f)
@@ -162,26 +189,32 @@ impl From<OutputRef> for TransactionInput {
 impl TryFrom<String> for OutputRef {
     type Error = &'static str;
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        OutputRef::try_from(&*value)
+        OutputRef::from_str(&value)
     }
 }
 
 impl TryFrom<&str> for OutputRef {
     type Error = &'static str;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        if let Some((raw_tx_id, str_idx)) = value.split_once("#") {
-            return Ok(OutputRef(
-                TransactionHash::from_hex(raw_tx_id).unwrap(),
-                u64::from_str(str_idx).unwrap(),
-            ));
-        }
-        Err("Invalid OutputRef")
+        OutputRef::from_str(value)
+    }
+}
+
+/// Parses `<tx_hash_hex>#<index>`.
+impl FromStr for OutputRef {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (raw_tx_id, str_idx) = s.split_once('#').ok_or("Invalid OutputRef: missing '#'")?;
+        let tx_hash = TransactionHash::from_hex(raw_tx_id).map_err(|_| "Invalid OutputRef: bad tx hash")?;
+        let index = u64::from_str(str_idx).map_err(|_| "Invalid OutputRef: bad index")?;
+        Ok(OutputRef(tx_hash, index))
     }
 }
 
 pub type Token = (PolicyId, AssetName);
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Deserialize)]
+#[serde(try_from = "String")]
 pub enum AssetClass {
     Native,
     Token(Token),
@@ -220,12 +253,70 @@ impl Display for AssetClass {
     }
 }
 
+/// Serialized in the form accepted by [AssetClass::try_from].
+impl Serialize for AssetClass {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            AssetClass::Native => serializer.serialize_str("Native"),
+            AssetClass::Token((policy, AssetName(orig_len, raw_name))) => serializer.serialize_str(
+                format!(
+                    "{}.{}",
+                    policy.to_hex(),
+                    hex::encode(&raw_name[0..*orig_len as usize])
+                )
+                .as_str(),
+            ),
+        }
+    }
+}
+
+impl TryFrom<String> for AssetClass {
+    type Error = &'static str;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        AssetClass::try_from(&*value)
+    }
+}
+
+/// Parses either `Native` or `<policy_id_hex>.<asset_name_hex>`.
+impl TryFrom<&str> for AssetClass {
+    type Error = &'static str;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if value == "Native" {
+            return Ok(AssetClass::Native);
+        }
+        if let Some((raw_policy, raw_name)) = value.split_once(".") {
+            let policy = PolicyId::from_hex(raw_policy).map_err(|_| "Invalid PolicyId")?;
+            let name = hex::decode(raw_name)
+                .ok()
+                .and_then(|name| AssetName::try_from(name).ok())
+                .ok_or("Invalid AssetName")?;
+            return Ok(AssetClass::Token((policy, name)));
+        }
+        Err("Invalid AssetClass")
+    }
+}
+
 impl<T> From<TaggedAssetClass<T>> for AssetClass {
     fn from(value: TaggedAssetClass<T>) -> Self {
         value.0
     }
 }
 
+impl IntoPlutusData for AssetClass {
+    fn into_pd(self) -> PlutusData {
+        let (policy, name) = match self {
+            AssetClass::Native => (vec![], vec![]),
+            AssetClass::Token((policy, AssetName(len, bytes))) => {
+                (policy.to_raw_bytes().to_vec(), bytes[0..len as usize].to_vec())
+            }
+        };
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![PlutusData::new_bytes(policy), PlutusData::new_bytes(name)],
+        ))
+    }
+}
+
 impl TryFromPData for AssetClass {
     fn try_from_pd(data: PlutusData) -> Option<Self> {
         let mut cpd = data.into_constr_pd()?;
@@ -252,6 +343,8 @@ impl TryFromPData for AssetClass {
     PartialOrd(bound = ""),
     Hash(bound = "")
 )]
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct TaggedAssetClass<T>(AssetClass, PhantomData<T>);
 
 impl<T> TaggedAssetClass<T> {
@@ -266,6 +359,12 @@ impl<T> TaggedAssetClass<T> {
     }
 }
 
+impl<T> IntoPlutusData for TaggedAssetClass<T> {
+    fn into_pd(self) -> PlutusData {
+        self.0.into_pd()
+    }
+}
+
 impl<T> TryFromPData for TaggedAssetClass<T> {
     fn try_from_pd(data: PlutusData) -> Option<Self> {
         Some(Self(AssetClass::try_from_pd(data)?, PhantomData::default()))
@@ -275,6 +374,8 @@ impl<T> TryFromPData for TaggedAssetClass<T> {
 #[repr(transparent)]
 #[derive(Derivative)]
 #[derivative(Debug(bound = ""), Copy(bound = ""), Clone(bound = ""), Eq(bound = ""))]
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
 pub struct TaggedAmount<T>(u64, PhantomData<T>);
 
 impl<T> Display for TaggedAmount<T> {
@@ -374,9 +475,24 @@ pub struct NetworkId(u8);
 #[derive(serde::Deserialize, Debug, Clone, From, Into)]
 pub struct PaymentCredential(String);
 
+impl From<ScriptHash> for PaymentCredential {
+    fn from(hash: ScriptHash) -> Self {
+        Self(hash.to_bech32(SCRIPT_CRED_PREFIX).unwrap())
+    }
+}
+
+const SCRIPT_CRED_PREFIX: &str = "script";
+
 #[cfg(test)]
 mod tests {
-    use crate::AssetName;
+    use std::str::FromStr;
+
+    use cml_chain::PolicyId;
+    use cml_crypto::RawBytesEncoding;
+
+    use crate::plutus_data::IntoPlutusData;
+    use crate::types::TryFromPData;
+    use crate::{AssetClass, AssetName, OutputRef};
 
     #[test]
     fn asset_name_is_isomorphic_to_cml() {
@@ -386,4 +502,60 @@ mod tests {
         let cml_an_reconstructed = cml_chain::assets::AssetName::from(spectrum_an);
         assert_eq!(cml_an, cml_an_reconstructed);
     }
+
+    #[test]
+    fn asset_class_parses_from_str() {
+        let policy = "fd4b7d5a35c3e5e0b9c3d5bf0e2d0d8e0c2d1ea1f9dc7e4c5e0e3b3a";
+        let token = AssetClass::try_from(format!("{}.{}", policy, hex::encode("SNEK"))).unwrap();
+        assert_eq!(
+            token,
+            AssetClass::Token((
+                PolicyId::from_hex(policy).unwrap(),
+                AssetName::try_from(b"SNEK".to_vec()).unwrap()
+            ))
+        );
+        assert_eq!(AssetClass::try_from("Native"), Ok(AssetClass::Native));
+        assert!(AssetClass::try_from("SNEK").is_err());
+    }
+
+    #[test]
+    fn asset_class_pd_roundtrip() {
+        let policy = "fd4b7d5a35c3e5e0b9c3d5bf0e2d0d8e0c2d1ea1f9dc7e4c5e0e3b3a";
+        for asset in [
+            AssetClass::Native,
+            AssetClass::try_from(format!("{}.{}", policy, hex::encode("SNEK"))).unwrap(),
+        ] {
+            assert_eq!(AssetClass::try_from_pd(asset.into_pd()), Some(asset));
+        }
+    }
+
+    #[test]
+    fn output_ref_parses_from_str() {
+        let raw = "72d665ecf4b724d85b8b62aaee800ef13b21b436b4632e4fff0ac0b645977ae6#1";
+        let oref = OutputRef::from_str(raw).unwrap();
+        assert_eq!(oref.index(), 1);
+        assert_eq!(oref.to_string(), raw);
+        assert!(OutputRef::from_str("72d665ec#1").is_err());
+        assert!(
+            OutputRef::from_str("72d665ecf4b724d85b8b62aaee800ef13b21b436b4632e4fff0ac0b645977ae6").is_err()
+        );
+        assert!(
+            OutputRef::from_str("72d665ecf4b724d85b8b62aaee800ef13b21b436b4632e4fff0ac0b645977ae6#x")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn output_ref_serde_roundtrip() {
+        let oref = OutputRef::from_str("72d665ecf4b724d85b8b62aaee800ef13b21b436b4632e4fff0ac0b645977ae6#3")
+            .unwrap();
+        let json = serde_json::to_string(&oref).unwrap();
+        assert_eq!(
+            json,
+            "\"72d665ecf4b724d85b8b62aaee800ef13b21b436b4632e4fff0ac0b645977ae6#3\""
+        );
+        assert_eq!(serde_json::from_str::<OutputRef>(&json).unwrap(), oref);
+        let bin = bincode::serialize(&oref).unwrap();
+        assert_eq!(bincode::deserialize::<OutputRef>(&bin).unwrap(), oref);
+    }
 }
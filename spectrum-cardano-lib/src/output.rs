@@ -1,7 +1,9 @@
 use crate::transaction::BabbageTransactionOutputExtension;
 use crate::OutputRef;
 use cml_chain::transaction::TransactionOutput;
+use cml_core::serialization::{Deserialize as _, Serialize as _};
 use cml_multi_era::babbage::BabbageTransactionOutput;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use spectrum_offchain::data::Has;
 use std::cmp::Ordering;
 use type_equalities::IsEqual;
@@ -33,8 +35,50 @@ impl FinalizedTxOut {
     }
 }
 
+/// Output is serialized as CBOR bytes, so that its original encoding is preserved.
+impl Serialize for FinalizedTxOut {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.0.to_cbor_bytes(), self.1).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FinalizedTxOut {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (raw_output, output_ref) = <(Vec<u8>, OutputRef)>::deserialize(deserializer)?;
+        let output = TransactionOutput::from_cbor_bytes(&raw_output).map_err(serde::de::Error::custom)?;
+        Ok(FinalizedTxOut(output, output_ref))
+    }
+}
+
 impl FinalizedTxOut {
     pub fn new(out: BabbageTransactionOutput, out_ref: OutputRef) -> Self {
         Self(out.upcast(), out_ref)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::TransactionOutput;
+    use cml_chain::Value;
+    use cml_core::serialization::Serialize;
+    use cml_crypto::{Ed25519KeyHash, TransactionHash};
+
+    use crate::output::FinalizedTxOut;
+    use crate::OutputRef;
+
+    #[test]
+    fn finalized_output_survives_binary_roundtrip() {
+        let addr = EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([0u8; 28])))
+            .to_address();
+        let out = FinalizedTxOut(
+            TransactionOutput::new(addr, Value::from(1_000_000), None, None),
+            OutputRef::new(TransactionHash::from([1u8; 32]), 2),
+        );
+        let bin = bincode::serialize(&out).unwrap();
+        let FinalizedTxOut(output, output_ref) = bincode::deserialize(&bin).unwrap();
+        assert_eq!(output.to_cbor_bytes(), out.0.to_cbor_bytes());
+        assert_eq!(output_ref, out.1);
+    }
+}
@@ -5,10 +5,13 @@ use std::str::FromStr;
 use cml_chain::plutus::{ConstrPlutusData, PlutusData};
 use cml_chain::transaction::DatumOption;
 use cml_chain::utils::BigInteger;
-use cml_core::serialization::LenEncoding;
+use cml_core::serialization::{LenEncoding, Serialize};
+use derive_more::Display;
 use num_rational::Ratio;
 use primitive_types::U512;
 
+use crate::types::TryFromPData;
+
 pub trait IntoPlutusData {
     fn into_pd(self) -> PlutusData;
 }
@@ -46,6 +49,16 @@ impl<T: IntoPlutusData + Copy> IntoPlutusData for Ratio<T> {
     }
 }
 
+/// Encoded as Plutus `Maybe`.
+impl<T: IntoPlutusData> IntoPlutusData for Option<T> {
+    fn into_pd(self) -> PlutusData {
+        match self {
+            Some(value) => PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, vec![value.into_pd()])),
+            None => PlutusData::ConstrPlutusData(ConstrPlutusData::new(1, vec![])),
+        }
+    }
+}
+
 pub trait PlutusDataExtension {
     fn into_constr_pd(self) -> Option<ConstrPlutusData>;
     fn get_constr_pd_mut(&mut self) -> Option<&mut ConstrPlutusData>;
@@ -172,3 +185,108 @@ impl DatumExtension for DatumOption {
         }
     }
 }
+
+/// Reasons why a datum can't be updated in place.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Display)]
+pub enum DatumUpdateError {
+    #[display(fmt = "Datum is not a constructor")]
+    NotConstr,
+    #[display(fmt = "Datum has no field #{}", _0)]
+    MissingField(usize),
+    #[display(fmt = "Field #{} is of unexpected type", _0)]
+    FieldTypeMismatch(usize),
+    #[display(fmt = "Datum doesn't parse into the expected structure")]
+    Malformed,
+    #[display(fmt = "Field #{} changed unexpectedly", _0)]
+    UnexpectedMutation(usize),
+}
+
+/// Replace the given fields of a constructor datum.
+/// Each replaced field must keep its type and the rest of fields must encode exactly as before.
+/// `data` is left intact if the update fails.
+pub fn update_constr_fields<I>(data: &mut PlutusData, updates: I) -> Result<(), DatumUpdateError>
+where
+    I: IntoIterator<Item = (usize, PlutusData)>,
+{
+    let original = data.clone().into_constr_pd().ok_or(DatumUpdateError::NotConstr)?;
+    let mut updated = original.clone();
+    let mut touched = vec![];
+    for (ix, value) in updates {
+        let field = updated
+            .fields
+            .get_mut(ix)
+            .ok_or(DatumUpdateError::MissingField(ix))?;
+        if mem::discriminant(field) != mem::discriminant(&value) {
+            return Err(DatumUpdateError::FieldTypeMismatch(ix));
+        }
+        *field = value;
+        touched.push(ix);
+    }
+    for (ix, (before, after)) in original.fields.iter().zip(updated.fields.iter()).enumerate() {
+        if !touched.contains(&ix) && before.to_cbor_bytes() != after.to_cbor_bytes() {
+            return Err(DatumUpdateError::UnexpectedMutation(ix));
+        }
+    }
+    *data.get_constr_pd_mut().ok_or(DatumUpdateError::NotConstr)? = updated;
+    Ok(())
+}
+
+/// Same as [update_constr_fields], but also requires the datum to parse into `T`
+/// both before and after the update.
+pub fn update_typed_datum<T, I>(data: &mut PlutusData, updates: I) -> Result<(), DatumUpdateError>
+where
+    T: TryFromPData,
+    I: IntoIterator<Item = (usize, PlutusData)>,
+{
+    T::try_from_pd(data.clone()).ok_or(DatumUpdateError::Malformed)?;
+    let mut updated = data.clone();
+    update_constr_fields(&mut updated, updates)?;
+    T::try_from_pd(updated.clone()).ok_or(DatumUpdateError::Malformed)?;
+    *data = updated;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::plutus::{ConstrPlutusData, PlutusData};
+    use cml_core::serialization::Serialize;
+
+    use crate::plutus_data::{update_constr_fields, DatumUpdateError, IntoPlutusData};
+
+    fn datum() -> PlutusData {
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![1u64.into_pd(), PlutusData::new_bytes(vec![0xab]), 2u64.into_pd()],
+        ))
+    }
+
+    #[test]
+    fn updates_only_given_fields() {
+        let mut data = datum();
+        update_constr_fields(&mut data, [(2, 7u64.into_pd())]).unwrap();
+        let expected = PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![1u64.into_pd(), PlutusData::new_bytes(vec![0xab]), 7u64.into_pd()],
+        ));
+        assert_eq!(data.to_cbor_bytes(), expected.to_cbor_bytes());
+    }
+
+    #[test]
+    fn rejects_unexpected_shape() {
+        let mut data = datum();
+        assert_eq!(
+            update_constr_fields(&mut data, [(3, 7u64.into_pd())]),
+            Err(DatumUpdateError::MissingField(3))
+        );
+        assert_eq!(
+            update_constr_fields(&mut data, [(1, 7u64.into_pd())]),
+            Err(DatumUpdateError::FieldTypeMismatch(1))
+        );
+        let mut int = 5u64.into_pd();
+        assert_eq!(
+            update_constr_fields(&mut int, [(0, 7u64.into_pd())]),
+            Err(DatumUpdateError::NotConstr)
+        );
+        assert_eq!(data.to_cbor_bytes(), datum().to_cbor_bytes());
+    }
+}
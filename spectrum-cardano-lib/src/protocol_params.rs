@@ -1,13 +1,28 @@
 use cml_chain::builders::tx_builder::{TransactionBuilder, TransactionBuilderConfigBuilder};
 use cml_chain::fees::LinearFee;
+use cml_chain::min_ada::min_ada_required;
 use cml_chain::plutus::{CostModels, ExUnitPrices};
+use cml_chain::transaction::TransactionOutput;
 use cml_chain::SubCoin;
 use cml_core::Int;
 
-const MAX_TX_SIZE: u32 = 16384;
+use crate::ex_units::ExUnits;
+
+pub const MAX_TX_SIZE: u32 = 16384;
 const MAX_VALUE_SIZE: u32 = 5000;
 
-const COINS_PER_UTXO_BYTE: u64 = 4310;
+pub const COINS_PER_UTXO_BYTE: u64 = 4310;
+
+/// Max execution units a single TX may consume.
+pub const MAX_TX_EX_UNITS: ExUnits = ExUnits {
+    mem: 14_000_000,
+    steps: 10_000_000_000,
+};
+
+/// Min lovelace the given output must hold according to its actual serialized size.
+pub fn min_utxo_lovelace(output: &TransactionOutput) -> u64 {
+    min_ada_required(output, COINS_PER_UTXO_BYTE).unwrap_or(u64::MAX)
+}
 
 pub fn constant_tx_builder() -> TransactionBuilder {
     create_tx_builder_full(
@@ -36,6 +36,9 @@ pub trait TransactionOutputExtension {
     fn value(&self) -> &Value;
     fn value_mut(&mut self) -> &mut Value;
     fn datum(&self) -> Option<DatumOption>;
+    /// Borrowed view of the inline datum, if any. Preferred over [Self::datum] when parsing,
+    /// as it doesn't copy the datum of every inspected output.
+    fn inline_datum(&self) -> Option<&PlutusData>;
     fn data_mut(&mut self) -> Option<&mut PlutusData>;
     fn null_datum(&mut self);
     fn into_datum(self) -> Option<DatumOption>;
@@ -79,6 +82,15 @@ impl TransactionOutputExtension for BabbageTransactionOutput {
             Self::BabbageFormatTxOut(tx_out) => tx_out.datum_option.clone(),
         }
     }
+    fn inline_datum(&self) -> Option<&PlutusData> {
+        match self {
+            Self::BabbageFormatTxOut(BabbageFormatTxOut {
+                datum_option: Some(DatumOption::Datum { datum, .. }),
+                ..
+            }) => Some(datum),
+            _ => None,
+        }
+    }
     fn data_mut(&mut self) -> Option<&mut PlutusData> {
         match self {
             Self::BabbageFormatTxOut(BabbageFormatTxOut {
@@ -165,6 +177,15 @@ impl TransactionOutputExtension for TransactionOutput {
             Self::ConwayFormatTxOut(tx_out) => tx_out.datum_option.clone(),
         }
     }
+    fn inline_datum(&self) -> Option<&PlutusData> {
+        match self {
+            Self::ConwayFormatTxOut(ConwayFormatTxOut {
+                datum_option: Some(DatumOption::Datum { datum, .. }),
+                ..
+            }) => Some(datum),
+            _ => None,
+        }
+    }
     fn data_mut(&mut self) -> Option<&mut PlutusData> {
         match self {
             Self::ConwayFormatTxOut(ConwayFormatTxOut {
@@ -0,0 +1,333 @@
+use clap::{Args, Parser, ValueEnum};
+use cml_chain::address::{BaseAddress, EnterpriseAddress};
+use cml_chain::certs::Credential;
+use cml_chain::plutus::{PlutusData, PlutusV2Script};
+use cml_chain::transaction::Transaction;
+use cml_core::serialization::{Deserialize, Serialize};
+use cml_crypto::ScriptHash;
+use num_rational::Ratio;
+use pallas_network::miniprotocols::localtxsubmission::Response;
+
+use cardano_explorer::{CardanoNetwork, Maestro};
+use cardano_submit_api::client::LocalTxSubmissionClient;
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::constants::BABBAGE_ERA_ID;
+use spectrum_cardano_lib::ex_units::ExUnits;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::{AssetClass, AssetName, NetworkId, OutputRef};
+use spectrum_offchain::tx_prover::TxProver;
+use spectrum_offchain_cardano::creds::operator_creds;
+use spectrum_offchain_cardano::data::cfmm_pool::ConstFnPoolVer;
+use spectrum_offchain_cardano::data::pool::PoolBounds;
+use spectrum_offchain_cardano::deployment::DeployedValidators;
+use spectrum_offchain_cardano::pool_creation::{build_pool_creation_tx, PoolCreationParams, PoolTokenPolicy};
+use spectrum_offchain_cardano::prover::operator::OperatorProver;
+
+/// Builds and submits a TX creating a constant-product pool on behalf of the given key.
+#[derive(Parser)]
+#[command(name = "splash-create-pool")]
+struct AppArgs {
+    /// Path to the deployment JSON configuration file.
+    #[arg(long, short)]
+    deployment_path: String,
+    /// Path to the file holding Maestro API key.
+    #[arg(long)]
+    maestro_key_path: String,
+    /// Network ID: 0 for testnets, 1 for mainnet.
+    #[arg(long)]
+    network_id: u8,
+    /// Path to the node socket.
+    #[arg(long)]
+    node_path: String,
+    /// Network magic of the node.
+    #[arg(long)]
+    node_magic: u64,
+    /// Bech32-encoded BIP32 private key. Pool is funded from its enterprise address,
+    /// LQ and change return there.
+    #[arg(long, short)]
+    key: String,
+    /// Print the signed TX instead of submitting it.
+    #[arg(long)]
+    dry_run: bool,
+    /// UTxO to fund the pool from, `<tx_hash_hex>#<index>`. May be repeated.
+    /// One-shot policies of pool tokens must be parametrized by one of them.
+    #[arg(long = "utxo", required = true)]
+    utxos: Vec<OutputRef>,
+    /// Pure ADA UTxO at the own address to use as collateral.
+    #[arg(long)]
+    collateral: OutputRef,
+    #[arg(long, value_enum)]
+    version: PoolVersion,
+    /// Asset X, `Native` or `<policy_id_hex>.<asset_name_hex>`.
+    #[arg(long)]
+    x: String,
+    /// Asset Y, `Native` or `<policy_id_hex>.<asset_name_hex>`.
+    #[arg(long)]
+    y: String,
+    #[arg(long)]
+    reserves_x: u64,
+    #[arg(long)]
+    reserves_y: u64,
+    /// Share of input left for the swap, in units of 1/100000.
+    #[arg(long, default_value_t = 99700)]
+    lp_fee_num: u64,
+    /// Share of Y input left for the swap if it differs from the one of X.
+    #[arg(long)]
+    lp_fee_num_y: Option<u64>,
+    #[arg(long, default_value_t = 0)]
+    treasury_fee_num: u64,
+    #[arg(long, default_value_t = 0)]
+    lq_lower_bound: u64,
+    /// Stake admin policy of legacy pools, DAO policy of fee-switch pools, hex. May be repeated.
+    #[arg(long = "admin_policy")]
+    admin_policies: Vec<String>,
+    /// Script treasury of fee-switch pools is withdrawn to, hex.
+    #[arg(long)]
+    treasury_script: Option<String>,
+    /// Lowest acceptable initial price of X in Y, `num/denom`.
+    #[arg(long)]
+    min_price: Option<String>,
+    /// Highest acceptable initial price of X in Y, `num/denom`.
+    #[arg(long)]
+    max_price: Option<String>,
+    /// Script hash staking the pool address, hex.
+    #[arg(long)]
+    pool_stake_script: Option<String>,
+    #[arg(long, default_value_t = 10_000_000)]
+    min_n2t_lovelace: u64,
+    #[arg(long, default_value_t = 10_000_000)]
+    min_t2t_lovelace: u64,
+    #[command(flatten)]
+    nft: NftPolicyArgs,
+    #[command(flatten)]
+    lq: LqPolicyArgs,
+}
+
+#[derive(Args)]
+struct NftPolicyArgs {
+    /// Path to the file holding CBOR hex of the applied NFT minting policy.
+    #[arg(long)]
+    nft_policy_path: String,
+    /// NFT name, hex.
+    #[arg(long)]
+    nft_name: String,
+    /// CBOR hex of the NFT minting redeemer.
+    #[arg(long, default_value = "d87980")]
+    nft_redeemer: String,
+    #[arg(long, default_value_t = 500_000)]
+    nft_mem: u64,
+    #[arg(long, default_value_t = 200_000_000)]
+    nft_steps: u64,
+}
+
+#[derive(Args)]
+struct LqPolicyArgs {
+    /// Path to the file holding CBOR hex of the applied LQ minting policy.
+    #[arg(long)]
+    lq_policy_path: String,
+    /// LQ name, hex.
+    #[arg(long)]
+    lq_name: String,
+    /// CBOR hex of the LQ minting redeemer.
+    #[arg(long, default_value = "d87980")]
+    lq_redeemer: String,
+    #[arg(long, default_value_t = 500_000)]
+    lq_mem: u64,
+    #[arg(long, default_value_t = 200_000_000)]
+    lq_steps: u64,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum PoolVersion {
+    V1,
+    V2,
+    FeeSwitch,
+    FeeSwitchV2,
+    FeeSwitchBidirFee,
+}
+
+impl PoolVersion {
+    fn ver(self) -> ConstFnPoolVer {
+        match self {
+            PoolVersion::V1 => ConstFnPoolVer::V1,
+            PoolVersion::V2 => ConstFnPoolVer::V2,
+            PoolVersion::FeeSwitch => ConstFnPoolVer::FeeSwitch,
+            PoolVersion::FeeSwitchV2 => ConstFnPoolVer::FeeSwitchV2,
+            PoolVersion::FeeSwitchBidirFee => ConstFnPoolVer::FeeSwitchBiDirFee,
+        }
+    }
+
+    fn pool_script(self, deployment: &DeployedValidators) -> ScriptHash {
+        match self {
+            PoolVersion::V1 => deployment.const_fn_pool_v1.hash,
+            PoolVersion::V2 => deployment.const_fn_pool_v2.hash,
+            PoolVersion::FeeSwitch => deployment.const_fn_pool_fee_switch.hash,
+            PoolVersion::FeeSwitchV2 => deployment.const_fn_pool_fee_switch_v2.hash,
+            PoolVersion::FeeSwitchBidirFee => deployment.const_fn_pool_fee_switch_bidir_fee.hash,
+        }
+    }
+}
+
+const UTXO_LOOKUP_LIMIT: u16 = 50;
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run(AppArgs::parse()).await {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: AppArgs) -> Result<(), String> {
+    let raw_deployment = std::fs::read_to_string(&args.deployment_path).map_err(|err| err.to_string())?;
+    let deployment: DeployedValidators =
+        serde_json::from_str(&raw_deployment).map_err(|err| err.to_string())?;
+    let network_id = NetworkId::from(args.network_id);
+    let (sk, _, own_address, _) = operator_creds(&args.key, network_id);
+    let own_address = own_address.address();
+    let explorer = Maestro::new(&args.maestro_key_path, network_id.into())
+        .await
+        .map_err(|err| err.to_string())?;
+    let utxos = explorer
+        .utxos_by_address(own_address.clone(), 0, UTXO_LOOKUP_LIMIT)
+        .await;
+    let lookup = |oref: &OutputRef| {
+        utxos
+            .iter()
+            .find(|utxo| OutputRef::from(utxo.input.clone()) == *oref)
+            .cloned()
+            .ok_or(format!("UTxO {} not found at the own address", oref))
+    };
+    let funding = args.utxos.iter().map(lookup).collect::<Result<Vec<_>, _>>()?;
+    let collateral = Collateral::from(lookup(&args.collateral)?);
+
+    let price_bounds = match (args.min_price, args.max_price) {
+        (None, None) => None,
+        (min_price, max_price) => Some((
+            min_price.map_or(Ok(Ratio::new(0, 1)), |p| parse_price(&p))?,
+            max_price.map_or(Ok(Ratio::new(u128::MAX, 1)), |p| parse_price(&p))?,
+        )),
+    };
+    let params = PoolCreationParams {
+        ver: args.version.ver(),
+        asset_x: parse_asset(&args.x)?,
+        asset_y: parse_asset(&args.y)?,
+        reserves_x: args.reserves_x,
+        reserves_y: args.reserves_y,
+        lp_fee_num_x: args.lp_fee_num,
+        lp_fee_num_y: args.lp_fee_num_y.unwrap_or(args.lp_fee_num),
+        treasury_fee_num: args.treasury_fee_num,
+        lq_lower_bound: args.lq_lower_bound,
+        admin_policies: args
+            .admin_policies
+            .iter()
+            .map(|raw| parse_script_hash(raw))
+            .collect::<Result<Vec<_>, _>>()?,
+        treasury_script: args.treasury_script.map(|raw| parse_script_hash(&raw)).transpose()?,
+        price_bounds,
+    };
+    let nft = token_policy(
+        &args.nft.nft_policy_path,
+        &args.nft.nft_name,
+        &args.nft.nft_redeemer,
+        ExUnits {
+            mem: args.nft.nft_mem,
+            steps: args.nft.nft_steps,
+        },
+    )?;
+    let lq = token_policy(
+        &args.lq.lq_policy_path,
+        &args.lq.lq_name,
+        &args.lq.lq_redeemer,
+        ExUnits {
+            mem: args.lq.lq_mem,
+            steps: args.lq.lq_steps,
+        },
+    )?;
+    let pool_cred = Credential::new_script(args.version.pool_script(&deployment));
+    let pool_address = match args.pool_stake_script {
+        Some(stake_script) => BaseAddress::new(
+            args.network_id,
+            pool_cred,
+            Credential::new_script(parse_script_hash(&stake_script)?),
+        )
+        .to_address(),
+        None => EnterpriseAddress::new(args.network_id, pool_cred).to_address(),
+    };
+    let bounds = PoolBounds {
+        min_n2t_lovelace: args.min_n2t_lovelace,
+        min_t2t_lovelace: args.min_t2t_lovelace,
+        bootstrap_lovelace: 0,
+        bootstrap_liquidity: 0,
+        swap_deposit_surplus: false,
+    };
+    let pool_nft = nft.token();
+    let (signed_tx_builder, liquidity) = build_pool_creation_tx(
+        params,
+        nft,
+        lq,
+        pool_address,
+        funding,
+        collateral,
+        &bounds,
+        &own_address,
+    )
+    .map_err(|err| err.to_string())?;
+    let tx: Transaction = (*OperatorProver::new(&sk).prove(signed_tx_builder)).clone();
+    let tx_hash = hash_transaction_canonical(&tx.body);
+    println!(
+        "Pool {} with {} LQ emitted to the creator",
+        AssetClass::Token(pool_nft),
+        liquidity
+    );
+
+    if args.dry_run {
+        println!("{}", hex::encode(tx.to_cbor_bytes()));
+        return Ok(());
+    }
+    let mut client =
+        LocalTxSubmissionClient::<BABBAGE_ERA_ID, Transaction>::init(args.node_path, args.node_magic)
+            .await
+            .map_err(|err| err.to_string())?;
+    match client.submit_tx(tx).await.map_err(|err| err.to_string())? {
+        Response::Accepted => println!("Submitted {}", tx_hash.to_hex()),
+        Response::Rejected(errors) => return Err(format!("TX {} rejected: {:?}", tx_hash.to_hex(), errors)),
+    }
+    client.close().await;
+    Ok(())
+}
+
+fn token_policy(
+    script_path: &str,
+    name: &str,
+    redeemer: &str,
+    ex_units: ExUnits,
+) -> Result<PoolTokenPolicy, String> {
+    let raw_script = std::fs::read_to_string(script_path).map_err(|err| err.to_string())?;
+    let script_bytes = hex::decode(raw_script.trim()).map_err(|err| err.to_string())?;
+    let redeemer_bytes = hex::decode(redeemer).map_err(|err| err.to_string())?;
+    Ok(PoolTokenPolicy {
+        script: PlutusV2Script::from_cbor_bytes(&*script_bytes).map_err(|err| err.to_string())?,
+        name: AssetName::try_from_hex(name).ok_or(format!("Invalid asset name: {}", name))?,
+        redeemer: PlutusData::from_cbor_bytes(&*redeemer_bytes).map_err(|err| err.to_string())?,
+        ex_units,
+    })
+}
+
+fn parse_asset(raw: &str) -> Result<AssetClass, String> {
+    AssetClass::try_from(raw).map_err(|err| format!("{}: {}", err, raw))
+}
+
+fn parse_script_hash(raw: &str) -> Result<ScriptHash, String> {
+    ScriptHash::from_hex(raw).map_err(|err| format!("{}: {}", err, raw))
+}
+
+fn parse_price(raw: &str) -> Result<Ratio<u128>, String> {
+    let (numer, denom) = raw.split_once('/').unwrap_or((raw, "1"));
+    let numer = numer.trim().parse::<u128>().map_err(|err| err.to_string())?;
+    let denom = denom.trim().parse::<u128>().map_err(|err| err.to_string())?;
+    if denom == 0 {
+        return Err("Price denominator must be positive".to_string());
+    }
+    Ok(Ratio::new(numer, denom))
+}
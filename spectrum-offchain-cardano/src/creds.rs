@@ -1,10 +1,16 @@
+use std::io::Read;
+
+use age::armor::ArmoredReader;
+use age::secrecy::Secret;
 use cml_chain::address::{Address, BaseAddress, EnterpriseAddress};
 use cml_chain::certs::Credential;
 use cml_crypto::{Bip32PrivateKey, Ed25519KeyHash, PrivateKey};
-use derive_more::{From, Into};
+use derive_more::{Display, From, Into};
+use log::warn;
 
 use crate::funding::FundingAddresses;
 use spectrum_cardano_lib::NetworkId;
+use spectrum_offchain::sensitive::Sensitive;
 
 #[derive(serde::Deserialize, Debug, Clone, Into, From)]
 pub struct OperatorRewardAddress(pub Address);
@@ -27,6 +33,38 @@ impl CollateralAddress {
 #[derive(serde::Deserialize, Debug, Copy, Clone, Into, From)]
 pub struct OperatorCred(pub Ed25519KeyHash);
 
+/// Operator credentials orders may be bound to. During a key rotation the retiring
+/// credential is kept next to the current one until no orders bound to it remain.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct OperatorCredSet {
+    pub current: OperatorCred,
+    pub retiring: Option<OperatorCred>,
+}
+
+impl OperatorCredSet {
+    pub fn new(current: OperatorCred) -> Self {
+        Self {
+            current,
+            retiring: None,
+        }
+    }
+
+    pub fn with_retiring(self, retiring: OperatorCred) -> Self {
+        Self {
+            retiring: Some(retiring),
+            ..self
+        }
+    }
+
+    pub fn contains(&self, pkh: &Ed25519KeyHash) -> bool {
+        self.iter().any(|cred| cred.0 == *pkh)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = OperatorCred> {
+        std::iter::once(self.current).chain(self.retiring)
+    }
+}
+
 impl From<OperatorCred> for Credential {
     fn from(value: OperatorCred) -> Self {
         Credential::PubKey {
@@ -38,6 +76,82 @@ impl From<OperatorCred> for Credential {
     }
 }
 
+/// Where the operator signing key (bech32 BIP32 private key) is taken from.
+/// Credentials are derived from the key on every start, so rotating the key only requires
+/// replacing the secret (and funding the new addresses) before restarting the agent.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum OperatorKeySource {
+    /// Plaintext key inline in the config. Only meant for testing.
+    Inline(Sensitive<String>),
+    /// Environment variable holding the key, e.g. injected from a secret store.
+    Env { env: String },
+    /// File encrypted with a passphrase by `age -p`, binary or armored.
+    /// The passphrase is prompted for unless given via `passphraseEnv`.
+    EncryptedFile {
+        #[serde(rename = "encryptedFile")]
+        path: String,
+        #[serde(rename = "passphraseEnv", default)]
+        passphrase_env: Option<String>,
+    },
+}
+
+#[derive(Debug, Display)]
+pub enum OperatorKeyError {
+    #[display(fmt = "Environment variable {} is not set", _0)]
+    MissingEnv(String),
+    #[display(fmt = "Cannot read key file {}: {}", _0, _1)]
+    Read(String, std::io::Error),
+    #[display(fmt = "Cannot decrypt key file {}: {}", _0, _1)]
+    Decrypt(String, String),
+    #[display(fmt = "Malformed operator key")]
+    Malformed,
+}
+
+impl OperatorKeySource {
+    /// Bech32 encoded operator key.
+    pub fn load(&self) -> Result<Sensitive<String>, OperatorKeyError> {
+        let key = match self {
+            OperatorKeySource::Inline(key) => {
+                warn!("Operator key is stored in plaintext, consider using an encrypted file");
+                key.expose().clone()
+            }
+            OperatorKeySource::Env { env } => {
+                std::env::var(env).map_err(|_| OperatorKeyError::MissingEnv(env.clone()))?
+            }
+            OperatorKeySource::EncryptedFile { path, passphrase_env } => {
+                let passphrase = match passphrase_env {
+                    Some(env) => std::env::var(env).map_err(|_| OperatorKeyError::MissingEnv(env.clone()))?,
+                    None => rpassword::prompt_password(format!("Passphrase for {}: ", path))
+                        .map_err(|err| OperatorKeyError::Read(path.clone(), err))?,
+                };
+                decrypt_key_file(path, passphrase)?
+            }
+        };
+        let key = key.trim().to_string();
+        Bip32PrivateKey::from_bech32(&key).map_err(|_| OperatorKeyError::Malformed)?;
+        Ok(Sensitive::new(key))
+    }
+}
+
+fn decrypt_key_file(path: &str, passphrase: String) -> Result<String, OperatorKeyError> {
+    let decrypt_err = |err: String| OperatorKeyError::Decrypt(path.to_string(), err);
+    let encrypted = std::fs::read(path).map_err(|err| OperatorKeyError::Read(path.to_string(), err))?;
+    let decryptor = match age::Decryptor::new(ArmoredReader::new(&encrypted[..]))
+        .map_err(|err| decrypt_err(err.to_string()))?
+    {
+        age::Decryptor::Passphrase(decryptor) => decryptor,
+        age::Decryptor::Recipients(_) => return Err(decrypt_err("not encrypted with a passphrase".into())),
+    };
+    let mut key = String::new();
+    decryptor
+        .decrypt(&Secret::new(passphrase), None)
+        .map_err(|err| decrypt_err(err.to_string()))?
+        .read_to_string(&mut key)
+        .map_err(|err| OperatorKeyError::Read(path.to_string(), err))?;
+    Ok(key)
+}
+
 pub fn operator_creds(
     operator_sk_raw: &str,
     network_id: NetworkId,
@@ -98,7 +212,21 @@ mod tests {
     use cml_chain::address::{Address, BaseAddress, EnterpriseAddress};
     use cml_chain::certs::{Credential, StakeCredential};
     use cml_chain::genesis::network_info::NetworkInfo;
-    use cml_crypto::Bip32PrivateKey;
+    use cml_crypto::{Bip32PrivateKey, Ed25519KeyHash};
+
+    use crate::creds::{OperatorCred, OperatorCredSet};
+
+    #[test]
+    fn cred_set_accepts_retiring_cred() {
+        let current = OperatorCred(Ed25519KeyHash::from([1u8; 28]));
+        let retiring = OperatorCred(Ed25519KeyHash::from([2u8; 28]));
+        let set = OperatorCredSet::new(current);
+        assert!(set.contains(&current.0));
+        assert!(!set.contains(&retiring.0));
+        let rotating = set.with_retiring(retiring);
+        assert!(rotating.contains(&retiring.0));
+        assert_eq!(rotating.iter().collect::<Vec<_>>().len(), 2);
+    }
 
     #[test]
     fn gen_operator_creds() {
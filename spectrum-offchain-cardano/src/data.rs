@@ -16,7 +16,9 @@ pub mod limit_swap;
 pub mod operation_output;
 pub mod order;
 pub mod pool;
+pub mod pool_datum_diff;
 pub mod redeem;
+pub mod refund;
 
 pub mod ref_scripts;
 
@@ -58,7 +60,20 @@ impl OnChainOrderId {
 }
 
 #[repr(transparent)]
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, derive_more::From, derive_more::Into)]
+#[derive(
+    Debug,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    derive_more::From,
+    derive_more::Into,
+    serde::Serialize,
+    serde::Deserialize,
+)]
 pub struct PoolId(Token);
 
 impl PoolId {
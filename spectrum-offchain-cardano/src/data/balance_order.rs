@@ -6,6 +6,7 @@ use crate::data::deposit::ClassicalOnChainDeposit;
 use crate::data::order::ClassicalAMMOrder;
 use crate::data::pool::try_run_order_against_pool;
 use crate::data::redeem::ClassicalOnChainRedeem;
+use crate::data::refund::Refunds;
 use crate::deployment::ProtocolValidator::{
     BalanceFnPoolDeposit, BalanceFnPoolRedeem, BalanceFnPoolV1, BalanceFnPoolV2, ConstFnFeeSwitchPoolDeposit,
     ConstFnFeeSwitchPoolRedeem, ConstFnFeeSwitchPoolSwap, ConstFnPoolDeposit, ConstFnPoolRedeem,
@@ -13,6 +14,7 @@ use crate::deployment::ProtocolValidator::{
     StableFnPoolT2TRedeem,
 };
 use crate::deployment::{DeployedScriptInfo, DeployedValidator};
+use crate::fee_tuning::SharedFeeTuner;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use cml_chain::builders::tx_builder::SignedTxBuilder;
 use cml_crypto::ScriptHash;
@@ -38,6 +40,8 @@ where
         + Has<Collateral>
         + Has<NetworkId>
         + Has<OperatorRewardAddress>
+        + Has<Refunds>
+        + Has<SharedFeeTuner>
         + Has<DeployedValidator<{ BalanceFnPoolV1 as u8 }>>
         + Has<DeployedValidator<{ BalanceFnPoolV2 as u8 }>>
         + Has<DeployedValidator<{ BalanceFnPoolDeposit as u8 }>>
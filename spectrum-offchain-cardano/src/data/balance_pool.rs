@@ -4,10 +4,10 @@ use std::ops::Mul;
 use bignumber::BigNumber;
 use bloom_offchain::execution_engine::liquidity_book::core::{Next, Unit};
 use bloom_offchain::execution_engine::liquidity_book::market_maker::{
-    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolQuality, SpotPrice,
+    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
 };
 use bloom_offchain::execution_engine::liquidity_book::side::{OnSide, Side};
-use bloom_offchain::execution_engine::liquidity_book::types::AbsolutePrice;
+use bloom_offchain::execution_engine::liquidity_book::types::{mul_div, AbsolutePrice, Rounding};
 use cml_chain::address::Address;
 use cml_chain::assets::MultiAsset;
 use cml_chain::certs::StakeCredential;
@@ -21,8 +21,9 @@ use cml_multi_era::babbage::BabbageTransactionOutput;
 use num_rational::Ratio;
 use num_traits::{CheckedAdd, CheckedSub};
 use primitive_types::U512;
+use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::ex_units::ExUnits;
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, DatumExtension};
+use spectrum_cardano_lib::plutus_data::{update_typed_datum, ConstrPlutusDataExtension, DatumUpdateError};
 use spectrum_cardano_lib::plutus_data::{IntoPlutusData, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
@@ -30,7 +31,7 @@ use spectrum_cardano_lib::value::ValueExtension;
 use spectrum_cardano_lib::AssetClass::Native;
 use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use spectrum_offchain::data::{Has, Stable};
-use spectrum_offchain::ledger::{IntoLedger, TryFromLedger};
+use spectrum_offchain::ledger::{TryFromLedger, TryIntoLedger};
 use void::Void;
 
 use crate::constants::{ADA_WEIGHT, FEE_DEN, MAX_LQ_CAP, TOKEN_WEIGHT, WEIGHT_FEE_DEN};
@@ -77,7 +78,7 @@ impl TryFromPData for BalancePoolConfig {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum BalancePoolVer {
     V1,
     V2,
@@ -112,7 +113,7 @@ impl BalancePoolVer {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct BalancePool {
     pub id: PoolId,
     pub reserves_x: TaggedAmount<Rx>,
@@ -132,6 +133,10 @@ pub struct BalancePool {
     /// How many execution units pool invokation costs.
     pub marginal_cost: ExUnits,
     pub min_pool_lovelace: u64,
+    /// Pool is bootstrapping until its lovelace reserves reach this amount.
+    pub bootstrap_lovelace: u64,
+    /// Pool is bootstrapping until this amount of LP tokens is in circulation.
+    pub bootstrap_liquidity: u64,
 }
 
 impl BalancePool {
@@ -173,28 +178,17 @@ impl BalancePool {
         let x = self.asset_x.untag();
         let y = self.asset_y.untag();
         let [base, _] = order_canonical(x, y);
-        if base == x {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-            }
-        } else {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-            }
+        // Sides are relative to the canonical pair, so they flip when `x` is the quote asset.
+        let side = if base == x { side } else { side.flip() };
+        match side {
+            Side::Bid => PoolAssetMapping {
+                asset_to_deduct_from: x,
+                asset_to_add_to: y,
+            },
+            Side::Ask => PoolAssetMapping {
+                asset_to_deduct_from: y,
+                asset_to_add_to: x,
+            },
         }
     }
 
@@ -233,8 +227,7 @@ where
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &Ctx) -> Option<Self> {
         if let Some(pool_ver) = BalancePoolVer::try_from_address(repr.address(), ctx) {
             let value = repr.value();
-            let pd = repr.datum().clone()?.into_pd()?;
-            let conf = BalancePoolConfig::try_from_pd(pd.clone())?;
+            let conf = BalancePoolConfig::try_from_pd(repr.inline_datum()?.clone())?;
             let liquidity_neg = value.amount_of(conf.asset_lq.into())?;
             let bounds = ctx.select::<PoolBounds>();
             let lov = value.amount_of(Native)?;
@@ -259,6 +252,8 @@ where
                         .select::<DeployedScriptInfo<{ BalanceFnPoolV1 as u8 }>>()
                         .marginal_cost,
                     min_pool_lovelace: bounds.min_n2t_lovelace,
+                    bootstrap_lovelace: bounds.bootstrap_lovelace,
+                    bootstrap_liquidity: bounds.bootstrap_liquidity,
                 });
             }
         }
@@ -266,8 +261,13 @@ where
     }
 }
 
-impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for BalancePool {
-    fn into_ledger(self, mut immut_pool: ImmutablePoolUtxo) -> TransactionOutput {
+impl TryIntoLedger<TransactionOutput, ImmutablePoolUtxo> for BalancePool {
+    type Error = DatumUpdateError;
+
+    fn try_into_ledger(
+        self,
+        mut immut_pool: ImmutablePoolUtxo,
+    ) -> Result<TransactionOutput, DatumUpdateError> {
         let mut ma = MultiAsset::new();
         let coins = if self.asset_x.is_native() {
             let (policy, name) = self.asset_y.untag().into_token().unwrap();
@@ -290,23 +290,25 @@ impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for BalancePool {
         ma.set(nft_lq, name_nft.into(), 1);
 
         if let Some(DatumOption::Datum { datum, .. }) = &mut immut_pool.datum_option {
-            unsafe_update_datum(datum, self.treasury_x.untag(), self.treasury_y.untag());
+            update_treasury(datum, self.treasury_x.untag(), self.treasury_y.untag())?;
         }
 
-        TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
+        Ok(TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
             address: immut_pool.address,
             amount: Value::new(coins, ma),
             datum_option: immut_pool.datum_option,
             script_reference: immut_pool.script_reference,
             encodings: None,
-        })
+        }))
     }
 }
 
-pub fn unsafe_update_datum(data: &mut PlutusData, treasury_x: u64, treasury_y: u64) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(6, treasury_x.into_pd());
-    cpd.set_field(7, treasury_y.into_pd());
+pub fn update_treasury(
+    data: &mut PlutusData,
+    treasury_x: u64,
+    treasury_y: u64,
+) -> Result<(), DatumUpdateError> {
+    update_typed_datum::<BalancePoolConfig, _>(data, [(6, treasury_x.into_pd()), (7, treasury_y.into_pd())])
 }
 
 impl Stable for BalancePool {
@@ -383,17 +385,24 @@ impl AMMOps for BalancePool {
             in_y_amount,
         )?;
 
-        let x_to_deposit = (unlocked_lq.untag() as u128
-            * (self.reserves_x.untag() - self.treasury_x.untag()) as u128)
-            / (self.liquidity.untag() as u128);
-        let y_to_deposit_bn = (unlocked_lq.untag() as u128
-            * (self.reserves_y.untag() - self.treasury_y.untag()) as u128)
-            / (self.liquidity.untag() as u128);
+        // Amounts the pool takes in are rounded up.
+        let x_to_deposit = mul_div(
+            unlocked_lq.untag(),
+            (self.reserves_x.untag() - self.treasury_x.untag()) as u128,
+            self.liquidity.untag() as u128,
+            Rounding::Ceil,
+        )?;
+        let y_to_deposit = mul_div(
+            unlocked_lq.untag(),
+            (self.reserves_y.untag() - self.treasury_y.untag()) as u128,
+            self.liquidity.untag() as u128,
+            Rounding::Ceil,
+        )?;
 
         Some((
             unlocked_lq,
-            TaggedAmount::new(in_x_amount - x_to_deposit as u64),
-            TaggedAmount::new(in_y_amount - y_to_deposit_bn as u64),
+            TaggedAmount::new(in_x_amount.checked_sub(x_to_deposit)?),
+            TaggedAmount::new(in_y_amount.checked_sub(y_to_deposit)?),
         ))
     }
 
@@ -497,6 +506,19 @@ impl MarketMaker for BalancePool {
         AbsolutePrice::new(quote, base)
     }
 
+    fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+        let x = self.asset_x.untag();
+        let y = self.asset_y.untag();
+        let [base, _] = order_canonical(x, y);
+        let (input_is_x, input) = match input {
+            OnSide::Bid(quote_input) => (x != base, quote_input),
+            OnSide::Ask(base_input) => (x == base, base_input),
+        };
+        // Fee multipliers are shares of input left for the swap, LPs keep the rest.
+        let fee = if input_is_x { self.lp_fee_x } else { self.lp_fee_y };
+        ((input as u128 * (fee.denom() - fee.numer()) as u128) / *fee.denom() as u128) as u64
+    }
+
     fn quality(&self) -> PoolQuality {
         PoolQuality::from(0u128)
     }
@@ -505,13 +527,24 @@ impl MarketMaker for BalancePool {
         self.marginal_cost
     }
 
-    fn is_active(&self) -> bool {
-        if self.asset_x.is_native() {
-            self.reserves_x.untag() >= self.min_pool_lovelace
+    fn lifecycle(&self) -> PoolLifecycle {
+        let native_reserves = if self.asset_x.is_native() {
+            Some(self.reserves_x.untag())
         } else if self.asset_y.is_native() {
-            self.reserves_y.untag() >= self.min_pool_lovelace
+            Some(self.reserves_y.untag())
+        } else {
+            None
+        };
+        if self.reserves_x.untag() == 0
+            || self.reserves_y.untag() == 0
+            || self.liquidity.untag() < self.bootstrap_liquidity
+            || native_reserves.map_or(false, |r| r < self.bootstrap_lovelace)
+        {
+            PoolLifecycle::Bootstrapping
+        } else if native_reserves.map_or(true, |r| r >= self.min_pool_lovelace) {
+            PoolLifecycle::Active
         } else {
-            true
+            PoolLifecycle::Paused
         }
     }
 
@@ -569,17 +602,17 @@ impl ApplyOrder<ClassicalOnChainDeposit> for BalancePool {
                     .reserves_x
                     .checked_add(&TaggedAmount::new(net_x))
                     .and_then(|result| result.checked_sub(&change_x))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_add(&TaggedAmount::new(net_y))
                     .and_then(|result| result.checked_sub(&change_y))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
 
                 self.liquidity = self
                     .liquidity
                     .checked_add(&unlocked_lq)
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
 
                 let deposit_output = DepositOutput {
                     token_x_asset: order.token_x,
@@ -589,13 +622,12 @@ impl ApplyOrder<ClassicalOnChainDeposit> for BalancePool {
                     token_lq_asset: order.token_lq,
                     token_lq_amount: unlocked_lq,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, deposit_output))
             }
-            None => Err(ApplyOrderError::incompatible(deposit)),
+            None => Err(ApplyOrderError::invariant_violation(deposit)),
         }
     }
 }
@@ -613,15 +645,15 @@ impl ApplyOrder<ClassicalOnChainRedeem> for BalancePool {
                 self.reserves_x = self
                     .reserves_x
                     .checked_sub(&x_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_sub(&y_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.liquidity = self
                     .liquidity
                     .checked_sub(&order.token_lq_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
 
                 let redeem_output = RedeemOutput {
                     token_x_asset: order.token_x,
@@ -629,13 +661,12 @@ impl ApplyOrder<ClassicalOnChainRedeem> for BalancePool {
                     token_y_asset: order.token_y,
                     token_y_amount: y_amount,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, redeem_output))
             }
-            None => Err(ApplyOrderError::incompatible(redeem)),
+            None => Err(ApplyOrderError::invariant_violation(redeem)),
         }
     }
 }
@@ -652,7 +683,9 @@ mod tests {
 
     use algebra_core::semigroup::Semigroup;
     use bloom_offchain::execution_engine::liquidity_book::core::{Next, Trans, Unit};
-    use bloom_offchain::execution_engine::liquidity_book::market_maker::MakerBehavior;
+    use bloom_offchain::execution_engine::liquidity_book::market_maker::{
+        MakerBehavior, MarketMaker, PoolLifecycle,
+    };
     use bloom_offchain::execution_engine::liquidity_book::side::OnSide;
     use bloom_offchain::execution_engine::liquidity_book::side::OnSide::{Ask, Bid};
     use spectrum_cardano_lib::ex_units::ExUnits;
@@ -665,6 +698,7 @@ mod tests {
     use crate::data::pool::{ApplyOrder, CFMMPoolAction};
     use crate::data::redeem::{ClassicalOnChainRedeem, Redeem};
     use crate::data::{OnChainOrderId, PoolId};
+    use spectrum_offchain::executor::RunOrderError;
 
     const DATUM_SAMPLE: &str = "d8799fd8799f581c5df8fe3f9f0e10855f930e0ea6c227e3bba0aba54d39f9d55b95e21c436e6674ffd8799f4040ff01d8799f581c4b3459fd18a1dbabe207cd19c9951a9fac9f5c0f9c384e3d97efba26457465737443ff04d8799f581c0df79145b95580c14ef4baf8d022d7f0cbb08f3bed43bf97a2ddd8cb426c71ff1a000186820a00009fd8799fd87a9f581cb046b660db0eaf9be4f4300180ccf277e4209dada77c48fbd37ba81dffffff581c8d4be10d934b60a22f267699ea3f7ebdade1f8e535d1bd0ef7ce18b61a0501bced08ff";
 
@@ -735,6 +769,8 @@ mod tests {
                 steps: 100000000000,
             },
             min_pool_lovelace: 10000000000,
+            bootstrap_lovelace: 0,
+            bootstrap_liquidity: 0,
         };
     }
 
@@ -873,4 +909,45 @@ mod tests {
 
         assert_eq!(1, 1)
     }
+
+    fn redeem_of(pool: &BalancePool, token_lq_amount: u64) -> ClassicalOnChainRedeem {
+        ClassicalOrder {
+            id: OnChainOrderId(OutputRef::new(TransactionHash::from([1u8; 32]), 0)),
+            pool_id: pool.id,
+            order: Redeem {
+                pool_nft: pool.id,
+                token_x: pool.asset_x,
+                token_y: pool.asset_y,
+                token_lq: pool.asset_lq,
+                token_lq_amount: TaggedAmount::new(token_lq_amount),
+                ex_fee: 1500000,
+                reward_pkh: Ed25519KeyHash::from([0u8; 28]),
+                reward_stake_pkh: None,
+                collateral_ada: 3000000,
+                order_type: BalanceFn,
+            },
+        }
+    }
+
+    #[test]
+    fn paused_pool_accepts_redeem() {
+        let pool = gen_ada_token_pool(1_000_000_000, 1_000_000, 1_000_000, 99000, 99000, 100, 0, 0);
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Paused);
+        let redeem = redeem_of(&pool, 1_000);
+        let (pool, _) = pool
+            .apply_order(redeem)
+            .expect("LPs must be able to withdraw from paused pools");
+        assert_eq!(pool.liquidity.untag(), 999_000);
+    }
+
+    #[test]
+    fn failed_share_math_is_not_fatal() {
+        let pool = gen_ada_token_pool(20_000_000_000, 1_000_000, 0, 99000, 99000, 100, 0, 0);
+        let redeem = redeem_of(&pool, 1_000);
+        let Err(err) = pool.apply_order(redeem) else {
+            panic!("Shares of empty liquidity cannot be computed")
+        };
+        assert!(!err.is_permanent());
+        assert!(matches!(RunOrderError::from(err), RunOrderError::NonFatal(..)));
+    }
 }
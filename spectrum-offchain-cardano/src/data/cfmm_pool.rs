@@ -2,7 +2,7 @@ use std::fmt::Debug;
 
 use bloom_offchain::execution_engine::liquidity_book::core::{Next, Unit};
 use bloom_offchain::execution_engine::liquidity_book::market_maker::{
-    AbsoluteReserves, MakerBehavior, MarketMaker, PoolQuality, SpotPrice,
+    AbsoluteReserves, MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
 };
 use bloom_offchain::execution_engine::liquidity_book::side::{OnSide, Side};
 use bloom_offchain::execution_engine::liquidity_book::types::AbsolutePrice;
@@ -16,9 +16,10 @@ use cml_chain::Value;
 use cml_multi_era::babbage::BabbageTransactionOutput;
 use num_rational::Ratio;
 use num_traits::{CheckedAdd, CheckedSub};
+use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::ex_units::ExUnits;
 use spectrum_cardano_lib::plutus_data::{
-    ConstrPlutusDataExtension, DatumExtension, IntoPlutusData, PlutusDataExtension,
+    update_typed_datum, ConstrPlutusDataExtension, DatumUpdateError, IntoPlutusData, PlutusDataExtension,
 };
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
@@ -26,7 +27,7 @@ use spectrum_cardano_lib::value::ValueExtension;
 use spectrum_cardano_lib::AssetClass::Native;
 use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use spectrum_offchain::data::{Has, Stable};
-use spectrum_offchain::ledger::{IntoLedger, TryFromLedger};
+use spectrum_offchain::ledger::{TryFromLedger, TryIntoLedger};
 use type_equalities::IsEqual;
 use void::Void;
 
@@ -50,8 +51,10 @@ use crate::deployment::{DeployedScriptInfo, DeployedValidator, DeployedValidator
 use crate::fees::FeeExtension;
 use crate::pool_math::cfmm_math::{
     classic_cfmm_output_amount, classic_cfmm_reward_lp, classic_cfmm_shares_amount,
+    classic_cfmm_surplus_swap_amount,
 };
 
+#[derive(Debug)]
 pub struct LegacyCFMMPoolConfig {
     pub pool_nft: TaggedAssetClass<PoolNft>,
     pub asset_x: TaggedAssetClass<Rx>,
@@ -81,7 +84,7 @@ impl TryFromPData for LegacyCFMMPoolConfig {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum ConstFnPoolVer {
     V1,
     V2,
@@ -140,7 +143,7 @@ impl ConstFnPoolVer {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ConstFnPool {
     pub id: PoolId,
     pub reserves_x: TaggedAmount<Rx>,
@@ -165,28 +168,17 @@ impl ConstFnPool {
         let x = self.asset_x.untag();
         let y = self.asset_y.untag();
         let [base, _] = order_canonical(x, y);
-        if base == x {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-            }
-        } else {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-            }
+        // Sides are relative to the canonical pair, so they flip when `x` is the quote asset.
+        let side = if base == x { side } else { side.flip() };
+        match side {
+            Side::Bid => PoolAssetMapping {
+                asset_to_deduct_from: x,
+                asset_to_add_to: y,
+            },
+            Side::Ask => PoolAssetMapping {
+                asset_to_deduct_from: y,
+                asset_to_add_to: x,
+            },
         }
     }
 }
@@ -376,6 +368,19 @@ impl MarketMaker for ConstFnPool {
         AbsolutePrice::new(quote, base)
     }
 
+    fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+        let x = self.asset_x.untag();
+        let y = self.asset_y.untag();
+        let [base, _] = order_canonical(x, y);
+        let (input_is_x, input) = match input {
+            OnSide::Bid(quote_input) => (x != base, quote_input),
+            OnSide::Ask(base_input) => (x == base, base_input),
+        };
+        // Fee multipliers are shares of input left for the swap, LPs keep the rest.
+        let fee = if input_is_x { self.lp_fee_x } else { self.lp_fee_y };
+        ((input as u128 * (fee.denom() - fee.numer()) as u128) / *fee.denom() as u128) as u64
+    }
+
     fn quality(&self) -> PoolQuality {
         PoolQuality::from(0u128)
     }
@@ -384,16 +389,27 @@ impl MarketMaker for ConstFnPool {
         self.marginal_cost
     }
 
-    fn is_active(&self) -> bool {
-        let lq_bound = (self.reserves_x.untag() * 2) >= self.lq_lower_bound.untag();
-        let native_bound = if self.asset_x.is_native() {
-            self.reserves_x.untag() >= self.bounds.min_n2t_lovelace
+    fn lifecycle(&self) -> PoolLifecycle {
+        let native_reserves = if self.asset_x.is_native() {
+            Some(self.reserves_x.untag())
         } else if self.asset_y.is_native() {
-            self.reserves_y.untag() >= self.bounds.min_n2t_lovelace
+            Some(self.reserves_y.untag())
         } else {
-            true
+            None
         };
-        lq_bound && native_bound
+        let lq_bound = (self.reserves_x.untag() * 2) >= self.lq_lower_bound.untag();
+        let native_bound = native_reserves.map_or(true, |r| r >= self.bounds.min_n2t_lovelace);
+        if self.reserves_x.untag() == 0
+            || self.reserves_y.untag() == 0
+            || self.liquidity.untag() < self.bounds.bootstrap_liquidity
+            || native_reserves.map_or(false, |r| r < self.bounds.bootstrap_lovelace)
+        {
+            PoolLifecycle::Bootstrapping
+        } else if lq_bound && native_bound {
+            PoolLifecycle::Active
+        } else {
+            PoolLifecycle::Paused
+        }
     }
 
     fn liquidity(&self) -> AbsoluteReserves {
@@ -442,7 +458,7 @@ where
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &Ctx) -> Option<Self> {
         if let Some(pool_ver) = ConstFnPoolVer::try_from_address(repr.address(), ctx) {
             let value = repr.value();
-            let pd = repr.datum().clone()?.into_pd()?;
+            let pd = repr.inline_datum()?.clone();
             let bounds = ctx.select::<PoolBounds>();
             let marginal_cost = match pool_ver {
                 ConstFnPoolVer::V1 => {
@@ -468,7 +484,7 @@ where
             };
             match pool_ver {
                 ConstFnPoolVer::V1 | ConstFnPoolVer::V2 => {
-                    let conf = LegacyCFMMPoolConfig::try_from_pd(pd.clone())?;
+                    let conf = LegacyCFMMPoolConfig::try_from_pd(pd)?;
                     let liquidity_neg = value.amount_of(conf.asset_lq.into())?;
                     return Some(ConstFnPool {
                         id: PoolId::try_from(conf.pool_nft).ok()?,
@@ -492,7 +508,7 @@ where
                     });
                 }
                 ConstFnPoolVer::FeeSwitch | ConstFnPoolVer::FeeSwitchV2 => {
-                    let conf = FeeSwitchPoolConfig::try_from_pd(pd.clone())?;
+                    let conf = FeeSwitchPoolConfig::try_from_pd(pd)?;
                     let liquidity_neg = value.amount_of(conf.asset_lq.into())?;
                     let lov = value.amount_of(Native)?;
                     let reserves_x = value.amount_of(conf.asset_x.into())?;
@@ -525,7 +541,7 @@ where
                     }
                 }
                 ConstFnPoolVer::FeeSwitchBiDirFee => {
-                    let conf = FeeSwitchBidirectionalPoolConfig::try_from_pd(pd.clone())?;
+                    let conf = FeeSwitchBidirectionalPoolConfig::try_from_pd(pd)?;
                     let liquidity_neg = value.amount_of(conf.asset_lq.into())?;
                     let lov = value.amount_of(Native)?;
                     let reserves_x = value.amount_of(conf.asset_x.into())?;
@@ -563,8 +579,13 @@ where
     }
 }
 
-impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for ConstFnPool {
-    fn into_ledger(self, mut immut_pool: ImmutablePoolUtxo) -> TransactionOutput {
+impl TryIntoLedger<TransactionOutput, ImmutablePoolUtxo> for ConstFnPool {
+    type Error = DatumUpdateError;
+
+    fn try_into_ledger(
+        self,
+        mut immut_pool: ImmutablePoolUtxo,
+    ) -> Result<TransactionOutput, DatumUpdateError> {
         let mut ma = MultiAsset::new();
         let coins = if self.asset_x.is_native() {
             let (policy, name) = self.asset_y.untag().into_token().unwrap();
@@ -588,24 +609,27 @@ impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for ConstFnPool {
 
         if self.ver == ConstFnPoolVer::FeeSwitch || self.ver == ConstFnPoolVer::FeeSwitchV2 {
             if let Some(DatumOption::Datum { datum, .. }) = &mut immut_pool.datum_option {
-                unsafe_update_pd(datum, self.treasury_x.untag(), self.treasury_y.untag());
+                update_treasury(datum, self.treasury_x.untag(), self.treasury_y.untag())?;
             }
         }
 
-        TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
+        Ok(TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
             address: immut_pool.address,
             amount: Value::new(coins, ma),
             datum_option: immut_pool.datum_option,
             script_reference: immut_pool.script_reference,
             encodings: None,
-        })
+        }))
     }
 }
 
-pub fn unsafe_update_pd(data: &mut PlutusData, treasury_x: u64, treasury_y: u64) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(6, treasury_x.into_pd());
-    cpd.set_field(7, treasury_y.into_pd());
+/// Set treasury in the datum of a fee-switch pool.
+pub fn update_treasury(
+    data: &mut PlutusData,
+    treasury_x: u64,
+    treasury_y: u64,
+) -> Result<(), DatumUpdateError> {
+    update_typed_datum::<FeeSwitchPoolConfig, _>(data, [(6, treasury_x.into_pd()), (7, treasury_y.into_pd())])
 }
 
 impl ApplyOrder<ClassicalOnChainLimitSwap> for ConstFnPool {
@@ -615,6 +639,13 @@ impl ApplyOrder<ClassicalOnChainLimitSwap> for ConstFnPool {
         mut self,
         ClassicalOrder { id, pool_id, order }: ClassicalOnChainLimitSwap,
     ) -> Result<(Self, SwapOutput), ApplyOrderError<ClassicalOnChainLimitSwap>> {
+        if !self.is_active() {
+            return Err(ApplyOrderError::pool_paused(ClassicalOrder {
+                id,
+                pool_id,
+                order,
+            }));
+        }
         let quote_amount = self.output_amount(order.base_asset, order.base_amount);
         if quote_amount < order.min_expected_quote_amount {
             return Err(ApplyOrderError::slippage(
@@ -661,14 +692,75 @@ impl ApplyOrder<ClassicalOnChainLimitSwap> for ConstFnPool {
             quote_asset: order.quote_asset,
             quote_amount,
             ada_residue,
-            redeemer_pkh: order.redeemer_pkh,
-            redeemer_stake_pkh: order.redeemer_stake_pkh,
+            redeemer: PlutusAddress::pub_key(order.redeemer_pkh, order.redeemer_stake_pkh),
         };
         // Prepare batcher fee.
         Ok((self, swap_output))
     }
 }
 
+impl ConstFnPool {
+    /// Swap a part of the deposit change into the pool and deposit the rest of it together with
+    /// the swap output. Returns the resulting pool, additional LQ and the change left.
+    fn deposit_surplus(
+        &self,
+        change_x: TaggedAmount<Rx>,
+        change_y: TaggedAmount<Ry>,
+    ) -> Option<(Self, TaggedAmount<Lq>, TaggedAmount<Rx>, TaggedAmount<Ry>)> {
+        let mut pool = *self;
+        let surplus_in_x = change_x.untag() > 0;
+        let (base_asset, surplus, reserves_in, fee) = if surplus_in_x {
+            (
+                pool.asset_x.untag(),
+                change_x.untag(),
+                (pool.reserves_x - pool.treasury_x).untag(),
+                pool.lp_fee_x - pool.treasury_fee,
+            )
+        } else {
+            (
+                pool.asset_y.untag(),
+                change_y.untag(),
+                (pool.reserves_y - pool.treasury_y).untag(),
+                pool.lp_fee_y - pool.treasury_fee,
+            )
+        };
+        let swap_in = classic_cfmm_surplus_swap_amount(reserves_in, surplus, fee)?;
+        let swap_out = pool
+            .output_amount(TaggedAssetClass::new(base_asset), TaggedAmount::new(swap_in))
+            .untag();
+        if swap_out == 0 {
+            return None;
+        }
+        let additional_treasury = ((swap_in as u128 * *pool.treasury_fee.numer() as u128)
+            / *pool.treasury_fee.denom() as u128) as u64;
+        let (in_x_amount, in_y_amount) = if surplus_in_x {
+            pool.reserves_x = pool.reserves_x + TaggedAmount::new(swap_in);
+            pool.treasury_x = pool.treasury_x + TaggedAmount::new(additional_treasury);
+            pool.reserves_y = pool.reserves_y.checked_sub(&TaggedAmount::new(swap_out))?;
+            (surplus - swap_in, swap_out)
+        } else {
+            pool.reserves_y = pool.reserves_y + TaggedAmount::new(swap_in);
+            pool.treasury_y = pool.treasury_y + TaggedAmount::new(additional_treasury);
+            pool.reserves_x = pool.reserves_x.checked_sub(&TaggedAmount::new(swap_out))?;
+            (swap_out, surplus - swap_in)
+        };
+        let (extra_lq, change_x, change_y) = pool.reward_lp(in_x_amount, in_y_amount)?;
+        if extra_lq.untag() == 0 {
+            return None;
+        }
+        pool.reserves_x = pool
+            .reserves_x
+            .checked_add(&TaggedAmount::new(in_x_amount))?
+            .checked_sub(&change_x)?;
+        pool.reserves_y = pool
+            .reserves_y
+            .checked_add(&TaggedAmount::new(in_y_amount))?
+            .checked_sub(&change_y)?;
+        pool.liquidity = pool.liquidity.checked_add(&extra_lq)?;
+        Some((pool, extra_lq, change_x, change_y))
+    }
+}
+
 impl ApplyOrder<ClassicalOnChainDeposit> for ConstFnPool {
     type Result = DepositOutput;
 
@@ -705,16 +797,29 @@ impl ApplyOrder<ClassicalOnChainDeposit> for ConstFnPool {
                     .reserves_x
                     .checked_add(&TaggedAmount::new(net_x))
                     .and_then(|result| result.checked_sub(&change_x))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_add(&TaggedAmount::new(net_y))
                     .and_then(|result| result.checked_sub(&change_y))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
                 self.liquidity = self
                     .liquidity
                     .checked_add(&unlocked_lq)
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
+
+                let (unlocked_lq, change_x, change_y) = match self
+                    .bounds
+                    .swap_deposit_surplus
+                    .then(|| self.deposit_surplus(change_x, change_y))
+                    .flatten()
+                {
+                    Some((pool, extra_lq, change_x, change_y)) => {
+                        self = pool;
+                        (unlocked_lq + extra_lq, change_x, change_y)
+                    }
+                    None => (unlocked_lq, change_x, change_y),
+                };
 
                 let deposit_output = DepositOutput {
                     token_x_asset: order.token_x,
@@ -724,13 +829,12 @@ impl ApplyOrder<ClassicalOnChainDeposit> for ConstFnPool {
                     token_lq_asset: order.token_lq,
                     token_lq_amount: unlocked_lq,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, deposit_output))
             }
-            None => Err(ApplyOrderError::incompatible(deposit)),
+            None => Err(ApplyOrderError::invariant_violation(deposit)),
         }
     }
 }
@@ -748,15 +852,15 @@ impl ApplyOrder<ClassicalOnChainRedeem> for ConstFnPool {
                 self.reserves_x = self
                     .reserves_x
                     .checked_sub(&x_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_sub(&y_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.liquidity = self
                     .liquidity
                     .checked_sub(&order.token_lq_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem))?;
 
                 let redeem_output = RedeemOutput {
                     token_x_asset: order.token_x,
@@ -764,13 +868,12 @@ impl ApplyOrder<ClassicalOnChainRedeem> for ConstFnPool {
                     token_y_asset: order.token_y,
                     token_y_amount: y_amount,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, redeem_output))
             }
-            None => Err(ApplyOrderError::incompatible(redeem)),
+            None => Err(ApplyOrderError::invariant_violation(redeem)),
         }
     }
 }
@@ -778,17 +881,22 @@ impl ApplyOrder<ClassicalOnChainRedeem> for ConstFnPool {
 #[cfg(test)]
 mod tests {
     use crate::data::cfmm_pool::{ConstFnPool, ConstFnPoolVer};
-    use crate::data::pool::PoolBounds;
+    use crate::data::pair::order_canonical;
+    use crate::data::pool::{AnyPool, PoolBounds};
     use crate::data::PoolId;
     use crate::deployment::ProtocolValidator::{
         ConstFnPoolFeeSwitch, ConstFnPoolFeeSwitchBiDirFee, ConstFnPoolFeeSwitchV2, ConstFnPoolV1,
         ConstFnPoolV2,
     };
     use crate::deployment::{DeployedScriptInfo, DeployedValidators, ProtocolScriptHashes};
+    use bloom_offchain::execution_engine::liquidity_book::config::ExecutionCap;
     use bloom_offchain::execution_engine::liquidity_book::core::{
         Excess, Final, MakeInProgress, Next, Trans,
     };
-    use bloom_offchain::execution_engine::liquidity_book::market_maker::MakerBehavior;
+    use bloom_offchain::execution_engine::liquidity_book::harness::{Harness, HarnessMaker};
+    use bloom_offchain::execution_engine::liquidity_book::market_maker::{
+        MakerBehavior, MarketMaker, PoolLifecycle,
+    };
     use bloom_offchain::execution_engine::liquidity_book::side::OnSide::Ask;
     use bloom_offchain::execution_engine::liquidity_book::side::{OnSide, Side};
     use cml_core::serialization::Deserialize;
@@ -867,6 +975,9 @@ mod tests {
             bounds: PoolBounds {
                 min_n2t_lovelace: 10000000,
                 min_t2t_lovelace: 10000000,
+                bootstrap_lovelace: 0,
+                bootstrap_liquidity: 0,
+                swap_deposit_surplus: false,
             },
         };
     }
@@ -920,6 +1031,65 @@ mod tests {
         );
     }
 
+    impl HarnessMaker for AnyPool {
+        fn with_reserves(self, base: u64, quote: u64) -> Self {
+            match self {
+                AnyPool::PureCFMM(pool) => {
+                    let x = pool.asset_x.untag();
+                    let [canonical_base, _] = order_canonical(x, pool.asset_y.untag());
+                    let (reserves_x, reserves_y) = if x == canonical_base {
+                        (base, quote)
+                    } else {
+                        (quote, base)
+                    };
+                    AnyPool::PureCFMM(ConstFnPool {
+                        id: PoolId::random(),
+                        reserves_x: TaggedAmount::new(reserves_x),
+                        reserves_y: TaggedAmount::new(reserves_y),
+                        treasury_x: TaggedAmount::new(0),
+                        treasury_y: TaggedAmount::new(0),
+                        ..pool
+                    })
+                }
+                _ => panic!("Only CFMM pools are spawned by the harness"),
+            }
+        }
+    }
+
+    fn harness(template: ConstFnPool) -> Harness<AnyPool> {
+        let cap = ExecutionCap {
+            soft: ExUnits {
+                mem: 5_000_000,
+                steps: 2_000_000_000,
+            },
+            hard: ExUnits {
+                mem: 14_000_000,
+                steps: 10_000_000_000,
+            },
+            tx_size: None,
+        };
+        let taker_cost = ExUnits {
+            mem: 100_000,
+            steps: 50_000_000,
+        };
+        Harness::new(AnyPool::PureCFMM(template), cap, taker_cost)
+    }
+
+    #[test]
+    fn const_fn_pool_matches_in_tlb() {
+        let pool = ConstFnPool {
+            ver: ConstFnPoolVer::V1,
+            ..gen_ada_token_pool(0, 0, 0, 99700, 99700, 0, 0, 0)
+        };
+        harness(pool).run_all();
+    }
+
+    #[test]
+    fn fee_switch_pool_matches_in_tlb() {
+        let pool = gen_ada_token_pool(0, 0, 0, 99700, 99700, 100, 0, 0);
+        harness(pool).run_all();
+    }
+
     #[test]
     fn treasury_x_test() {
         let pool = gen_ada_token_pool(1632109645, 1472074052, 0, 99970, 99970, 10, 11500, 2909);
@@ -937,6 +1107,23 @@ mod tests {
         assert_eq!(new_pool.treasury_x.untag(), correct_x_treasury)
     }
 
+    #[test]
+    fn pool_lifecycle() {
+        let pool = gen_ada_token_pool(0, 0, 0, 99970, 99970, 10, 0, 0);
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Bootstrapping);
+        let mut pool = gen_ada_token_pool(15_000_000, 1_000_000, 0, 99970, 99970, 10, 0, 0);
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Active);
+        pool.bounds.bootstrap_lovelace = 20_000_000;
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Bootstrapping);
+        let mut pool = gen_ada_token_pool(15_000_000, 1_000_000, 100_000, 99970, 99970, 10, 0, 0);
+        pool.bounds.bootstrap_liquidity = 1_000_000;
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Bootstrapping);
+        pool.bounds.bootstrap_liquidity = 100_000;
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Active);
+        let pool = gen_ada_token_pool(5_000_000, 1_000_000, 0, 99970, 99970, 10, 0, 0);
+        assert_eq!(pool.lifecycle(), PoolLifecycle::Paused);
+    }
+
     struct Ctx {
         bounds: PoolBounds,
         scripts: ProtocolScriptHashes,
@@ -999,6 +1186,9 @@ mod tests {
             bounds: PoolBounds {
                 min_n2t_lovelace: 150_000_000,
                 min_t2t_lovelace: 10_000_000,
+                bootstrap_lovelace: 0,
+                bootstrap_liquidity: 0,
+                swap_deposit_surplus: false,
             },
         };
         let bearer = BabbageTransactionOutput::from_cbor_bytes(&*hex::decode(POOL_UTXO).unwrap()).unwrap();
@@ -1,8 +1,8 @@
-use cml_chain::plutus::PlutusData;
-use cml_crypto::Ed25519KeyHash;
+use cml_chain::plutus::{ConstrPlutusData, PlutusData};
+use cml_crypto::{Ed25519KeyHash, RawBytesEncoding};
 use cml_multi_era::babbage::BabbageTransactionOutput;
 
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, DatumExtension, PlutusDataExtension};
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
 use spectrum_cardano_lib::value::ValueExtension;
@@ -118,8 +118,8 @@ where
             } else {
                 OrderType::StableFn
             };
-            let value = repr.value().clone();
-            let conf = OnChainDepositConfig::try_from_pd(repr.clone().into_datum()?.into_pd()?)?;
+            let value = repr.value();
+            let conf = OnChainDepositConfig::try_from_pd(repr.inline_datum()?.clone())?;
             let token_x_amount = TaggedAmount::new(value.amount_of(conf.token_x.untag()).unwrap_or(0));
             let token_y_amount = TaggedAmount::new(value.amount_of(conf.token_y.untag()).unwrap_or(0));
             let deposit = Deposit {
@@ -150,15 +150,37 @@ where
     }
 }
 
-struct OnChainDepositConfig {
-    pool_nft: TaggedAssetClass<PoolNft>,
-    token_x: TaggedAssetClass<Rx>,
-    token_y: TaggedAssetClass<Ry>,
-    token_lq: TaggedAssetClass<Lq>,
-    ex_fee: u64,
-    reward_pkh: Ed25519KeyHash,
-    reward_stake_pkh: Option<Ed25519KeyHash>,
-    collateral_ada: u64,
+pub struct OnChainDepositConfig {
+    pub pool_nft: TaggedAssetClass<PoolNft>,
+    pub token_x: TaggedAssetClass<Rx>,
+    pub token_y: TaggedAssetClass<Ry>,
+    pub token_lq: TaggedAssetClass<Lq>,
+    pub ex_fee: u64,
+    pub reward_pkh: Ed25519KeyHash,
+    pub reward_stake_pkh: Option<Ed25519KeyHash>,
+    pub collateral_ada: u64,
+}
+
+impl IntoPlutusData for OnChainDepositConfig {
+    fn into_pd(self) -> PlutusData {
+        let reward_stake_pkh = match self.reward_stake_pkh {
+            Some(pkh) => ConstrPlutusData::new(0, vec![PlutusData::new_bytes(pkh.to_raw_bytes().to_vec())]),
+            None => ConstrPlutusData::new(1, vec![]),
+        };
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![
+                self.pool_nft.into_pd(),
+                self.token_x.into_pd(),
+                self.token_y.into_pd(),
+                self.token_lq.into_pd(),
+                self.ex_fee.into_pd(),
+                PlutusData::new_bytes(self.reward_pkh.to_raw_bytes().to_vec()),
+                PlutusData::ConstrPlutusData(reward_stake_pkh),
+                self.collateral_ada.into_pd(),
+            ],
+        ))
+    }
 }
 
 impl TryFromPData for OnChainDepositConfig {
@@ -7,6 +7,7 @@ use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use crate::data::order::PoolNft;
 use crate::data::pool::{Lq, Rx, Ry};
 
+#[derive(Debug)]
 pub struct FeeSwitchBidirectionalPoolConfig {
     pub pool_nft: TaggedAssetClass<PoolNft>,
     pub asset_x: TaggedAssetClass<Rx>,
@@ -1,5 +1,6 @@
 use cml_chain::plutus::PlutusData;
 
+use spectrum_cardano_lib::address::StakingCredential;
 use spectrum_cardano_lib::plutus_data::ConstrPlutusDataExtension;
 use spectrum_cardano_lib::plutus_data::PlutusDataExtension;
 use spectrum_cardano_lib::types::TryFromPData;
@@ -8,6 +9,7 @@ use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use crate::data::order::PoolNft;
 use crate::data::pool::{Lq, Rx, Ry};
 
+#[derive(Debug)]
 pub struct FeeSwitchPoolConfig {
     pub pool_nft: TaggedAssetClass<PoolNft>,
     pub asset_x: TaggedAssetClass<Rx>,
@@ -17,6 +19,8 @@ pub struct FeeSwitchPoolConfig {
     pub treasury_fee_num: u64,
     pub treasury_x: u64,
     pub treasury_y: u64,
+    /// Staking scripts governance actions over the pool are delegated to.
+    pub dao_policy: Vec<StakingCredential>,
     pub lq_lower_bound: TaggedAmount<Rx>,
 }
 
@@ -32,6 +36,10 @@ impl TryFromPData for FeeSwitchPoolConfig {
             treasury_fee_num: cpd.take_field(5)?.into_u64()?,
             treasury_x: cpd.take_field(6)?.into_u64()?,
             treasury_y: cpd.take_field(7)?.into_u64()?,
+            dao_policy: cpd
+                .take_field(8)
+                .and_then(|pd| pd.into_vec_pd(StakingCredential::try_from_pd))
+                .unwrap_or_default(),
             lq_lower_bound: TaggedAmount::new(cpd.take_field(9).and_then(|pd| pd.into_u64()).unwrap_or(0)),
         })
     }
@@ -51,6 +59,6 @@ mod tests {
     fn parse_fee_switch_datum_mainnet() {
         let pd = PlutusData::from_cbor_bytes(&*hex::decode(DATUM_SAMPLE).unwrap()).unwrap();
         let maybe_conf = FeeSwitchPoolConfig::try_from_pd(pd);
-        assert!(maybe_conf.is_some())
+        assert!(maybe_conf.is_some_and(|conf| conf.dao_policy.is_empty()))
     }
 }
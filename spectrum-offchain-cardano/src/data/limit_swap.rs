@@ -4,7 +4,7 @@ use cml_crypto::Ed25519KeyHash;
 use cml_multi_era::babbage::BabbageTransactionOutput;
 use num_rational::Ratio;
 
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, DatumExtension, PlutusDataExtension};
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
 use spectrum_cardano_lib::value::ValueExtension;
@@ -13,7 +13,6 @@ use spectrum_offchain::data::order::UniqueOrder;
 use spectrum_offchain::data::Has;
 use spectrum_offchain::ledger::TryFromLedger;
 
-use crate::constants::MIN_SAFE_LOVELACE_VALUE;
 use crate::data::order::{Base, ClassicalOrder, OrderType, PoolNft, Quote};
 use crate::data::pool::CFMMPoolAction;
 use crate::data::pool::CFMMPoolAction::Swap;
@@ -65,8 +64,8 @@ where
 {
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &Ctx) -> Option<Self> {
         if test_address(repr.address(), ctx) {
-            let value = repr.value().clone();
-            let conf = OnChainLimitSwapConfig::try_from_pd(repr.datum()?.into_pd()?)?;
+            let value = repr.value();
+            let conf = OnChainLimitSwapConfig::try_from_pd(repr.inline_datum()?.clone())?;
             let real_base_input = value.amount_of(conf.base.untag()).unwrap_or(0);
             let (min_base, ada_deposit) = if conf.base.is_native() {
                 let min = conf.base_amount.untag()
@@ -77,7 +76,8 @@ where
             } else {
                 (conf.base_amount.untag(), value.coin)
             };
-            if real_base_input < min_base || ada_deposit < MIN_SAFE_LOVELACE_VALUE {
+            // Whether the deposit covers min-UTxO of the output is checked against its actual size on execution.
+            if real_base_input < min_base {
                 return None;
             }
             let swap = LimitSwap {
@@ -1,10 +1,8 @@
-use cml_chain::address::{BaseAddress, EnterpriseAddress};
 use cml_chain::assets::MultiAsset;
-use cml_chain::certs::StakeCredential;
 use cml_chain::transaction::{ConwayFormatTxOut, TransactionOutput};
 use cml_chain::{Coin, Value};
-use cml_crypto::Ed25519KeyHash;
 
+use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::{NetworkId, TaggedAmount, TaggedAssetClass};
 use spectrum_offchain::data::Has;
 use spectrum_offchain::ledger::IntoLedger;
@@ -17,8 +15,8 @@ pub struct SwapOutput {
     pub quote_asset: TaggedAssetClass<Quote>,
     pub quote_amount: TaggedAmount<Quote>,
     pub ada_residue: Coin,
-    pub redeemer_pkh: Ed25519KeyHash,
-    pub redeemer_stake_pkh: Option<Ed25519KeyHash>,
+    /// Where proceeds go, owners holding script credentials (e.g. smart wallets) included.
+    pub redeemer: PlutusAddress,
 }
 
 impl<Ctx> IntoLedger<TransactionOutput, Ctx> for SwapOutput
@@ -26,17 +24,7 @@ where
     Ctx: Has<NetworkId>,
 {
     fn into_ledger(self, ctx: Ctx) -> TransactionOutput {
-        let addr = if let Some(stake_pkh) = self.redeemer_stake_pkh {
-            BaseAddress::new(
-                ctx.get().into(),
-                StakeCredential::new_pub_key(self.redeemer_pkh),
-                StakeCredential::new_pub_key(stake_pkh),
-            )
-            .to_address()
-        } else {
-            EnterpriseAddress::new(ctx.get().into(), StakeCredential::new_pub_key(self.redeemer_pkh))
-                .to_address()
-        };
+        let addr = self.redeemer.to_address(ctx.get());
 
         let mut ma = MultiAsset::new();
 
@@ -69,8 +57,8 @@ pub struct DepositOutput {
     pub token_lq_asset: TaggedAssetClass<Lq>,
     pub token_lq_amount: TaggedAmount<Lq>,
     pub ada_residue: Coin,
-    pub redeemer_pkh: Ed25519KeyHash,
-    pub redeemer_stake_pkh: Option<Ed25519KeyHash>,
+    /// Where proceeds go, owners holding script credentials (e.g. smart wallets) included.
+    pub redeemer: PlutusAddress,
 }
 
 impl<Ctx> IntoLedger<TransactionOutput, Ctx> for DepositOutput
@@ -78,17 +66,7 @@ where
     Ctx: Has<NetworkId>,
 {
     fn into_ledger(self, ctx: Ctx) -> TransactionOutput {
-        let addr = if let Some(stake_pkh) = self.redeemer_stake_pkh {
-            BaseAddress::new(
-                ctx.get().into(),
-                StakeCredential::new_pub_key(self.redeemer_pkh),
-                StakeCredential::new_pub_key(stake_pkh),
-            )
-            .to_address()
-        } else {
-            EnterpriseAddress::new(ctx.get().into(), StakeCredential::new_pub_key(self.redeemer_pkh))
-                .to_address()
-        };
+        let addr = self.redeemer.to_address(ctx.get());
 
         let mut ma = MultiAsset::new();
 
@@ -137,8 +115,8 @@ pub struct RedeemOutput {
     pub token_y_asset: TaggedAssetClass<Ry>,
     pub token_y_amount: TaggedAmount<Ry>,
     pub ada_residue: Coin,
-    pub redeemer_pkh: Ed25519KeyHash,
-    pub redeemer_stake_pkh: Option<Ed25519KeyHash>,
+    /// Where proceeds go, owners holding script credentials (e.g. smart wallets) included.
+    pub redeemer: PlutusAddress,
 }
 
 impl<Ctx> IntoLedger<TransactionOutput, Ctx> for RedeemOutput
@@ -146,17 +124,7 @@ where
     Ctx: Has<NetworkId>,
 {
     fn into_ledger(self, ctx: Ctx) -> TransactionOutput {
-        let addr = if let Some(stake_pkh) = self.redeemer_stake_pkh {
-            BaseAddress::new(
-                ctx.get().into(),
-                StakeCredential::new_pub_key(self.redeemer_pkh),
-                StakeCredential::new_pub_key(stake_pkh),
-            )
-            .to_address()
-        } else {
-            EnterpriseAddress::new(ctx.get().into(), StakeCredential::new_pub_key(self.redeemer_pkh))
-                .to_address()
-        };
+        let addr = self.redeemer.to_address(ctx.get());
 
         let mut ma = MultiAsset::new();
 
@@ -26,6 +26,7 @@ use crate::data::deposit::{ClassicalOnChainDeposit, DepositOrderBounds};
 use crate::data::limit_swap::ClassicalOnChainLimitSwap;
 use crate::data::pool::try_run_order_against_pool;
 use crate::data::redeem::{ClassicalOnChainRedeem, RedeemOrderBounds};
+use crate::data::refund::Refunds;
 use crate::data::PoolId;
 use crate::deployment::ProtocolValidator::{
     BalanceFnPoolDeposit, BalanceFnPoolRedeem, BalanceFnPoolV1, BalanceFnPoolV2, ConstFnFeeSwitchPoolDeposit,
@@ -34,6 +35,7 @@ use crate::deployment::ProtocolValidator::{
     ConstFnPoolV2, StableFnPoolT2T, StableFnPoolT2TDeposit, StableFnPoolT2TRedeem,
 };
 use crate::deployment::{DeployedScriptInfo, DeployedValidator};
+use crate::fee_tuning::SharedFeeTuner;
 use spectrum_cardano_lib::{NetworkId, OutputRef};
 use spectrum_offchain::executor::RunOrderError::Fatal;
 
@@ -70,12 +72,14 @@ impl<Id: Clone, Ord> Has<Id> for ClassicalOrder<Id, Ord> {
 
 pub enum ClassicalOrderAction {
     Apply,
+    Refund,
 }
 
 impl ClassicalOrderAction {
     pub fn to_plutus_data(self) -> PlutusData {
         match self {
             ClassicalOrderAction::Apply => PlutusData::Integer(BigInteger::from(0)),
+            ClassicalOrderAction::Refund => PlutusData::Integer(BigInteger::from(1)),
         }
     }
 }
@@ -156,6 +160,10 @@ impl SpecializedOrder for ClassicalAMMOrder {
             ClassicalAMMOrder::Redeem(red) => red.pool_id.0 .0,
         }
     }
+
+    fn is_deposit(&self) -> bool {
+        matches!(self, ClassicalAMMOrder::Deposit(_))
+    }
 }
 
 impl<Ctx> TryFromLedger<BabbageTransactionOutput, Ctx> for ClassicalAMMOrder
@@ -197,6 +205,8 @@ where
         + Has<Collateral>
         + Has<NetworkId>
         + Has<OperatorRewardAddress>
+        + Has<Refunds>
+        + Has<SharedFeeTuner>
         + Has<DeployedValidator<{ ConstFnPoolV1 as u8 }>>
         + Has<DeployedValidator<{ ConstFnPoolV2 as u8 }>>
         + Has<DeployedValidator<{ ConstFnFeeSwitchPoolSwap as u8 }>>
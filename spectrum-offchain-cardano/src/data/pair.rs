@@ -3,7 +3,7 @@ use std::fmt::{Display, Formatter, Write};
 use bloom_offchain::execution_engine::liquidity_book::side::Side;
 use spectrum_cardano_lib::AssetClass;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, serde::Serialize, serde::Deserialize)]
 pub struct PairId(AssetClass, AssetClass);
 
 impl PairId {
@@ -12,6 +12,11 @@ impl PairId {
         let xs = order_canonical(x, y);
         Self(xs[0], xs[1])
     }
+
+    /// Base and quote assets of the pair.
+    pub fn assets(&self) -> [AssetClass; 2] {
+        [self.0, self.1]
+    }
 }
 
 /// Determine side of a trade relatively to canonical pair.
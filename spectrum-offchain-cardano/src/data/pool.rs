@@ -1,14 +1,11 @@
 use std::fmt::{Debug, Display, Formatter};
 
 use cml_chain::address::Address;
-use cml_chain::builders::input_builder::SingleInputBuilder;
 use cml_chain::builders::output_builder::SingleOutputBuilderResult;
-use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
 use cml_chain::builders::tx_builder::{
     ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
 };
-use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
-use cml_chain::plutus::{PlutusData, RedeemerTag};
+use cml_chain::plutus::PlutusData;
 use cml_chain::transaction::{DatumOption, ScriptRef, TransactionOutput};
 use cml_chain::utils::BigInteger;
 use cml_chain::{Coin, PolicyId};
@@ -17,7 +14,7 @@ use cml_core::serialization::Serialize;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use bloom_offchain::execution_engine::liquidity_book::core::{Next, Unit};
 use bloom_offchain::execution_engine::liquidity_book::market_maker::{
-    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolQuality, SpotPrice,
+    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
 };
 use bloom_offchain::execution_engine::liquidity_book::side::OnSide;
 use bloom_offchain::execution_engine::liquidity_book::types::AbsolutePrice;
@@ -27,12 +24,13 @@ use spectrum_cardano_lib::collateral::Collateral;
 use spectrum_cardano_lib::ex_units::ExUnits;
 use spectrum_cardano_lib::hash::hash_transaction_canonical;
 use spectrum_cardano_lib::output::FinalizedTxOut;
-use spectrum_cardano_lib::protocol_params::constant_tx_builder;
-use spectrum_cardano_lib::{AssetClass, OutputRef, TaggedAmount, Token};
+use spectrum_cardano_lib::plutus_data::DatumUpdateError;
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, min_utxo_lovelace};
+use spectrum_cardano_lib::{AssetClass, NetworkId, OutputRef, TaggedAmount, Token};
 use spectrum_offchain::data::event::Predicted;
 use spectrum_offchain::data::{Has, Stable, Tradable};
 use spectrum_offchain::executor::RunOrderError;
-use spectrum_offchain::ledger::{IntoLedger, TryFromLedger};
+use spectrum_offchain::ledger::{IntoLedger, TryFromLedger, TryIntoLedger};
 use void::Void;
 
 use crate::creds::OperatorRewardAddress;
@@ -41,6 +39,7 @@ use crate::data::cfmm_pool::{CFMMPoolRedeemer, ConstFnPool};
 use crate::data::order::{ClassicalOrderAction, ClassicalOrderRedeemer, Quote};
 use crate::data::pair::PairId;
 use crate::data::pool::AnyPool::{BalancedCFMM, PureCFMM, StableCFMM};
+use crate::data::refund::{HasOwner, Refunds};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::value::ValueExtension;
 
@@ -51,6 +50,8 @@ use crate::deployment::ProtocolValidator::{
     ConstFnPoolFeeSwitchV2, ConstFnPoolV1, ConstFnPoolV2, StableFnPoolT2T,
 };
 use crate::deployment::{DeployedScriptInfo, RequiresValidator};
+use crate::fee_tuning::{OffersBatcherFee, SharedFeeTuner};
+use crate::script::{delayed_redeemer, ScriptInput, TxInputs};
 
 pub struct Rx;
 
@@ -58,10 +59,52 @@ pub struct Ry;
 
 pub struct Lq;
 
+/// Reasons why an order cannot be applied to a pool.
 pub enum ApplyOrderError<Order> {
     Slippage(Slippage<Order>),
     LowBatcherFee(LowerBatcherFee<Order>),
     Incompatible(Incompatible<Order>),
+    PoolPaused(PoolPaused<Order>),
+    InvariantViolation(InvariantViolation<Order>),
+}
+
+/// Machine-readable reason of order rejection.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyOrderErrorReason {
+    Slippage,
+    LowBatcherFee,
+    Incompatible,
+    PoolPaused,
+    InvariantViolation,
+}
+
+impl ApplyOrderErrorReason {
+    /// Permanently invalid orders will never be executed and should be refunded.
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            ApplyOrderErrorReason::Slippage
+            | ApplyOrderErrorReason::PoolPaused
+            | ApplyOrderErrorReason::InvariantViolation => false,
+            ApplyOrderErrorReason::LowBatcherFee | ApplyOrderErrorReason::Incompatible => true,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApplyOrderErrorReason::Slippage => "slippage",
+            ApplyOrderErrorReason::LowBatcherFee => "low_batcher_fee",
+            ApplyOrderErrorReason::Incompatible => "incompatible",
+            ApplyOrderErrorReason::PoolPaused => "pool_paused",
+            ApplyOrderErrorReason::InvariantViolation => "invariant_violation",
+        }
+    }
+}
+
+impl Display for ApplyOrderErrorReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.code())
+    }
 }
 
 impl<Order> ApplyOrderError<Order> {
@@ -69,6 +112,38 @@ impl<Order> ApplyOrderError<Order> {
         Self::Incompatible(Incompatible { order })
     }
 
+    pub fn pool_paused(order: Order) -> Self {
+        Self::PoolPaused(PoolPaused { order })
+    }
+
+    pub fn invariant_violation(order: Order) -> Self {
+        Self::InvariantViolation(InvariantViolation { order })
+    }
+
+    pub fn reason(&self) -> ApplyOrderErrorReason {
+        match self {
+            ApplyOrderError::Slippage(_) => ApplyOrderErrorReason::Slippage,
+            ApplyOrderError::LowBatcherFee(_) => ApplyOrderErrorReason::LowBatcherFee,
+            ApplyOrderError::Incompatible(_) => ApplyOrderErrorReason::Incompatible,
+            ApplyOrderError::PoolPaused(_) => ApplyOrderErrorReason::PoolPaused,
+            ApplyOrderError::InvariantViolation(_) => ApplyOrderErrorReason::InvariantViolation,
+        }
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        self.reason().is_permanent()
+    }
+
+    pub fn into_order(self) -> Order {
+        match self {
+            ApplyOrderError::Slippage(Slippage { order, .. })
+            | ApplyOrderError::LowBatcherFee(LowerBatcherFee { order, .. })
+            | ApplyOrderError::Incompatible(Incompatible { order })
+            | ApplyOrderError::PoolPaused(PoolPaused { order })
+            | ApplyOrderError::InvariantViolation(InvariantViolation { order }) => order,
+        }
+    }
+
     pub fn map<F, T1>(self, f: F) -> ApplyOrderError<T1>
     where
         F: FnOnce(Order) -> T1,
@@ -79,6 +154,10 @@ impl<Order> ApplyOrderError<Order> {
                 ApplyOrderError::LowBatcherFee(low_batcher_fee.map(f))
             }
             ApplyOrderError::Incompatible(math_error) => ApplyOrderError::Incompatible(math_error.map(f)),
+            ApplyOrderError::PoolPaused(paused) => ApplyOrderError::PoolPaused(paused.map(f)),
+            ApplyOrderError::InvariantViolation(violation) => {
+                ApplyOrderError::InvariantViolation(violation.map(f))
+            }
         }
     }
 
@@ -109,6 +188,8 @@ impl<Order> From<ApplyOrderError<Order>> for RunOrderError<Order> {
             ApplyOrderError::Slippage(slippage) => slippage.into(),
             ApplyOrderError::LowBatcherFee(low_batcher_fee) => low_batcher_fee.into(),
             ApplyOrderError::Incompatible(math_error) => math_error.into(),
+            ApplyOrderError::PoolPaused(paused) => paused.into(),
+            ApplyOrderError::InvariantViolation(violation) => violation.into(),
         }
     }
 }
@@ -135,15 +216,23 @@ impl<T> Slippage<T> {
 
 impl<Order> From<Slippage<Order>> for RunOrderError<Order> {
     fn from(value: Slippage<Order>) -> Self {
-        RunOrderError::NonFatal("Price slippage".to_string(), value.order)
+        RunOrderError::NonFatal(
+            format!(
+                "[{}] Price slippage. Quote amount {}. Expected amount {}",
+                ApplyOrderErrorReason::Slippage,
+                value.quote_amount,
+                value.expected_amount
+            ),
+            value.order,
+        )
     }
 }
 
 #[derive(Debug)]
 pub struct LowerBatcherFee<Order> {
-    order: Order,
-    batcher_fee: u64,
-    ada_deposit: Coin,
+    pub order: Order,
+    pub batcher_fee: u64,
+    pub ada_deposit: Coin,
 }
 
 impl<T> LowerBatcherFee<T> {
@@ -161,10 +250,12 @@ impl<T> LowerBatcherFee<T> {
 
 impl<Order> From<LowerBatcherFee<Order>> for RunOrderError<Order> {
     fn from(value: LowerBatcherFee<Order>) -> Self {
-        RunOrderError::NonFatal(
+        RunOrderError::Fatal(
             format!(
-                "Lower batcher fee. Batcher fee {}. Ada deposit {}",
-                value.batcher_fee, value.ada_deposit
+                "[{}] Lower batcher fee. Batcher fee {}. Ada deposit {}",
+                ApplyOrderErrorReason::LowBatcherFee,
+                value.batcher_fee,
+                value.ada_deposit
             ),
             value.order,
         )
@@ -187,7 +278,59 @@ impl<T> Incompatible<T> {
 
 impl<Order> From<Incompatible<Order>> for RunOrderError<Order> {
     fn from(value: Incompatible<Order>) -> Self {
-        RunOrderError::NonFatal("Math error".to_string(), value.order)
+        RunOrderError::Fatal(
+            format!("[{}] Math error", ApplyOrderErrorReason::Incompatible),
+            value.order,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct PoolPaused<Order> {
+    pub order: Order,
+}
+
+impl<T> PoolPaused<T> {
+    pub fn map<F, T1>(self, f: F) -> PoolPaused<T1>
+    where
+        F: FnOnce(T) -> T1,
+    {
+        PoolPaused { order: f(self.order) }
+    }
+}
+
+impl<Order> From<PoolPaused<Order>> for RunOrderError<Order> {
+    fn from(value: PoolPaused<Order>) -> Self {
+        RunOrderError::NonFatal(
+            format!("[{}] Pool is not active", ApplyOrderErrorReason::PoolPaused),
+            value.order,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct InvariantViolation<Order> {
+    pub order: Order,
+}
+
+impl<T> InvariantViolation<T> {
+    pub fn map<F, T1>(self, f: F) -> InvariantViolation<T1>
+    where
+        F: FnOnce(T) -> T1,
+    {
+        InvariantViolation { order: f(self.order) }
+    }
+}
+
+impl<Order> From<InvariantViolation<Order>> for RunOrderError<Order> {
+    fn from(value: InvariantViolation<Order>) -> Self {
+        RunOrderError::NonFatal(
+            format!(
+                "[{}] Pool invariant would be violated",
+                ApplyOrderErrorReason::InvariantViolation
+            ),
+            value.order,
+        )
     }
 }
 
@@ -196,6 +339,8 @@ pub enum CFMMPoolAction {
     Deposit,
     Redeem,
     Destroy,
+    /// Governance action authorized by the DAO, e.g. withdrawal of accumulated treasury.
+    DAOAction,
 }
 
 impl CFMMPoolAction {
@@ -205,18 +350,31 @@ impl CFMMPoolAction {
             CFMMPoolAction::Deposit => PlutusData::Integer(BigInteger::from(0)),
             CFMMPoolAction::Redeem => PlutusData::Integer(BigInteger::from(1)),
             CFMMPoolAction::Destroy => PlutusData::Integer(BigInteger::from(3)),
+            CFMMPoolAction::DAOAction => PlutusData::Integer(BigInteger::from(4)),
         }
     }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, serde::Deserialize)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PoolBounds {
     pub min_n2t_lovelace: u64,
     pub min_t2t_lovelace: u64,
-}
-
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+    /// Pools with less lovelace in reserves are considered bootstrapping: they accept
+    /// initial deposits, but are held back from matchmaking.
+    #[serde(default)]
+    pub bootstrap_lovelace: u64,
+    /// Pools with less LP tokens in circulation are considered bootstrapping as well.
+    /// Unlike [PoolBounds::bootstrap_lovelace] applies to T2T pools too.
+    #[serde(default)]
+    pub bootstrap_liquidity: u64,
+    /// Swap the surplus of a deposit with mismatched ratio within the pool instead of
+    /// returning it as change. Only valid for pool validators accepting such deposits.
+    #[serde(default)]
+    pub swap_deposit_surplus: bool,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum AnyPool {
     PureCFMM(ConstFnPool),
     BalancedCFMM(BalancePool),
@@ -298,6 +456,14 @@ impl MarketMaker for AnyPool {
         }
     }
 
+    fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+        match self {
+            PureCFMM(p) => p.lp_fee(input),
+            BalancedCFMM(p) => p.lp_fee(input),
+            StableCFMM(p) => p.lp_fee(input),
+        }
+    }
+
     fn marginal_cost_hint(&self) -> Self::U {
         match self {
             PureCFMM(p) => p.marginal_cost_hint(),
@@ -314,11 +480,11 @@ impl MarketMaker for AnyPool {
         }
     }
 
-    fn is_active(&self) -> bool {
+    fn lifecycle(&self) -> PoolLifecycle {
         match self {
-            PureCFMM(p) => p.is_active(),
-            BalancedCFMM(p) => p.is_active(),
-            StableCFMM(p) => p.is_active(),
+            PureCFMM(p) => p.lifecycle(),
+            BalancedCFMM(p) => p.lifecycle(),
+            StableCFMM(p) => p.lifecycle(),
         }
     }
 }
@@ -455,63 +621,112 @@ pub fn try_run_order_against_pool<Order, Pool, Ctx>(
 where
     Pool: ApplyOrder<Order>
         + RequiresValidator<Ctx>
-        + IntoLedger<TransactionOutput, ImmutablePoolUtxo>
+        + TryIntoLedger<TransactionOutput, ImmutablePoolUtxo, Error = DatumUpdateError>
         + RequiresRedeemer<CFMMPoolAction>
-        + Clone,
+        + Clone
+        + 'static,
     <Pool as ApplyOrder<Order>>::Result: IntoLedger<TransactionOutput, Ctx>,
-    Order: Has<OnChainOrderId> + RequiresValidator<Ctx> + Clone + Debug,
+    Order: Has<OnChainOrderId> + HasOwner + OffersBatcherFee + RequiresValidator<Ctx> + Clone + Debug,
     Order: Into<CFMMPoolAction>,
-    Ctx: Clone + Has<Collateral> + Has<OperatorRewardAddress>,
+    Ctx: Clone
+        + Has<Collateral>
+        + Has<NetworkId>
+        + Has<OperatorRewardAddress>
+        + Has<Refunds>
+        + Has<SharedFeeTuner>,
 {
     let Bundled(pool, FinalizedTxOut(pool_utxo, pool_ref)) = pool_bundle.clone();
     let Bundled(order, FinalizedTxOut(order_utxo, order_ref)) = order_bundle.clone();
 
     info!(target: "offchain", "Running order {} against pool {}", order_ref, pool_ref);
 
-    let mut sorted_inputs = [pool_ref, order_ref];
-    sorted_inputs.sort();
-
-    let (pool_in_idx, order_in_idx) = match sorted_inputs {
-        [lh, _] if lh == pool_ref => (0u64, 1u64),
-        _ => (1u64, 0u64),
-    };
+    let fee_tuner = ctx.select::<SharedFeeTuner>();
+    let fee_target = order.fee_target();
+    if let Some(min_fee) = fee_tuner
+        .min_acceptable_fee(fee_target)
+        .filter(|min_fee| order.offered_fee() < *min_fee)
+    {
+        return Err(RunOrderError::NonFatal(
+            format!(
+                "Batcher fee {} is below acceptable {}",
+                order.offered_fee(),
+                min_fee
+            ),
+            order_bundle,
+        ));
+    }
 
     let immut_pool = ImmutablePoolUtxo::from(&pool_utxo);
-    let order_redeemer = ClassicalOrderRedeemer {
-        pool_input_index: pool_in_idx,
-        order_input_index: order_in_idx,
-        output_index: 1,
-        action: ClassicalOrderAction::Apply,
-    };
-    let order_validator = order.get_validator(&ctx);
-    let order_script = PartialPlutusWitness::new(
-        PlutusScriptWitness::Ref(order_validator.hash),
-        order_redeemer.to_plutus_data(),
-    );
-    let order_in = SingleInputBuilder::new(order_ref.into(), order_utxo.clone())
-        .plutus_script_inline_datum(order_script, Vec::new())
-        .unwrap();
     let (next_pool, user_out) = match pool.clone().apply_order(order.clone()) {
         Ok(res) => res,
         Err(order_error) => {
+            if order_error.is_permanent() {
+                ctx.select::<Refunds>().on_rejected(
+                    Bundled(order, FinalizedTxOut(order_utxo.clone(), order_ref)),
+                    order_error.reason(),
+                    ctx.clone(),
+                );
+            }
             return Err(order_error
                 .map(|value| Bundled(value, FinalizedTxOut(order_utxo, order_ref)))
                 .into());
         }
     };
-    let pool_out = next_pool.clone().into_ledger(immut_pool);
+    // Order isn't at fault if the pool datum can't be updated, so it is retried later.
+    let pool_out = match next_pool.clone().try_into_ledger(immut_pool) {
+        Ok(pool_out) => pool_out,
+        Err(err) => {
+            return Err(RunOrderError::NonFatal(
+                format!("Cannot update datum of pool {}: {}", pool_ref, err),
+                order_bundle,
+            ))
+        }
+    };
 
-    let pool_validator = pool.get_validator(&ctx);
-    let pool_script = PartialPlutusWitness::new(
-        PlutusScriptWitness::Ref(pool_validator.hash),
-        next_pool
-            .clone()
-            .redeemer(pool.clone(), pool_in_idx, order.clone().into()),
-    );
+    // Size of the user output depends on the assets it carries, so min-UTxO is checked against the actual one.
+    let user_out = user_out.into_ledger(ctx.clone());
+    let min_user_lovelace = min_utxo_lovelace(&user_out);
+    if user_out.value().coin < min_user_lovelace {
+        return Err(RunOrderError::Fatal(
+            format!(
+                "User output holds {} lovelace while {} is required",
+                user_out.value().coin,
+                min_user_lovelace
+            ),
+            order_bundle,
+        ));
+    }
 
-    let pool_in = SingleInputBuilder::new(pool_ref.into(), pool_utxo.clone())
-        .plutus_script_inline_datum(pool_script, Vec::new())
-        .unwrap();
+    let order_validator = order.get_validator(&ctx);
+    let pool_validator = pool.get_validator(&ctx);
+    let ex_units = order_validator.ex_budget + pool_validator.ex_budget;
+    let pool_action: CFMMPoolAction = order.clone().into();
+    let mut inputs = TxInputs::new();
+    inputs.add_script_input(ScriptInput {
+        utxo: TransactionUnspentOutput::new(pool_ref.into(), pool_utxo.clone()),
+        script: pool_validator.hash,
+        redeemer: delayed_redeemer({
+            let (next_pool, pool) = (next_pool.clone(), pool.clone());
+            move |ordering| next_pool.redeemer(pool, ordering.index_of(&pool_ref) as u64, pool_action)
+        }),
+        ex_units: pool_validator.ex_budget.into(),
+        required_signers: Vec::new(),
+    });
+    inputs.add_script_input(ScriptInput {
+        utxo: TransactionUnspentOutput::new(order_ref.into(), order_utxo.clone()),
+        script: order_validator.hash,
+        redeemer: delayed_redeemer(move |ordering| {
+            ClassicalOrderRedeemer {
+                pool_input_index: ordering.index_of(&pool_ref) as u64,
+                order_input_index: ordering.index_of(&order_ref) as u64,
+                output_index: 1,
+                action: ClassicalOrderAction::Apply,
+            }
+            .to_plutus_data()
+        }),
+        ex_units: order_validator.ex_budget.into(),
+        required_signers: Vec::new(),
+    });
 
     let mut tx_builder = constant_tx_builder();
 
@@ -522,28 +737,16 @@ where
     tx_builder.add_reference_input(order_validator.reference_utxo);
     tx_builder.add_reference_input(pool_validator.reference_utxo);
 
-    tx_builder
-        .add_input(pool_in)
-        .map_err(|err| RunOrderError::from_cml_error(err, order_bundle.clone()))?;
-    tx_builder
-        .add_input(order_in)
+    inputs
+        .project_onto(&mut tx_builder)
         .map_err(|err| RunOrderError::from_cml_error(err, order_bundle.clone()))?;
 
-    tx_builder.set_exunits(
-        RedeemerWitnessKey::new(RedeemerTag::Spend, pool_in_idx.clone().into()),
-        pool_validator.ex_budget.into(),
-    );
-    tx_builder.set_exunits(
-        RedeemerWitnessKey::new(RedeemerTag::Spend, order_in_idx.clone().into()),
-        order_validator.ex_budget.into(),
-    );
-
     tx_builder
         .add_output(SingleOutputBuilderResult::new(pool_out.clone()))
         .map_err(|err| RunOrderError::from_cml_error(err, order_bundle.clone()))?;
 
     tx_builder
-        .add_output(SingleOutputBuilderResult::new(user_out.into_ledger(ctx.clone())))
+        .add_output(SingleOutputBuilderResult::new(user_out))
         .map_err(|err| RunOrderError::from_cml_error(err, order_bundle.clone()))?;
 
     let tx = wrap_cml_action(
@@ -556,6 +759,8 @@ where
 
     let tx_hash = hash_transaction_canonical(&tx.body());
 
+    fee_tuner.on_tx_built(tx_hash, fee_target, tx.body().fee, ex_units);
+
     let next_pool_ref = OutputRef::new(tx_hash, 0);
     let predicted_pool = Predicted(Bundled(next_pool, FinalizedTxOut(pool_out, next_pool_ref)));
 
@@ -0,0 +1,149 @@
+use cml_chain::plutus::PlutusData;
+use cml_core::serialization::Serialize;
+
+use spectrum_cardano_lib::plutus_data::PlutusDataExtension;
+
+/// Positions of the fields of a pool datum which may change in result of execution.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PoolDatumLayout {
+    pub lp_fee_num: usize,
+    pub treasury_fee_num: usize,
+    pub treasury_x: usize,
+    pub treasury_y: usize,
+}
+
+/// Layout shared by fee-switch CFMM pools and balance pools.
+pub const FEE_SWITCH_POOL_LAYOUT: PoolDatumLayout = PoolDatumLayout {
+    lp_fee_num: 4,
+    treasury_fee_num: 5,
+    treasury_x: 6,
+    treasury_y: 7,
+};
+
+pub const STABLE_POOL_T2T_LAYOUT: PoolDatumLayout = PoolDatumLayout {
+    lp_fee_num: 9,
+    treasury_fee_num: 10,
+    treasury_x: 13,
+    treasury_y: 14,
+};
+
+/// Structured difference between two versions of a pool datum.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PoolDatumDiff {
+    /// Treasury in X before and after.
+    pub treasury_x: (u64, u64),
+    /// Treasury in Y before and after.
+    pub treasury_y: (u64, u64),
+    /// LP fee numerator before and after, if it changed.
+    pub lp_fee_num: Option<(u64, u64)>,
+    /// Treasury fee numerator before and after, if it changed.
+    pub treasury_fee_num: Option<(u64, u64)>,
+    /// Positions of all other fields that changed.
+    pub other_fields: Vec<usize>,
+}
+
+impl PoolDatumDiff {
+    /// Diff `old` and `new` datums laid out according to `layout`.
+    /// Returns `None` if they aren't constructors of the same shape.
+    pub fn of(old: &PlutusData, new: &PlutusData, layout: PoolDatumLayout) -> Option<Self> {
+        let old = old.clone().into_constr_pd()?;
+        let new = new.clone().into_constr_pd()?;
+        if old.alternative != new.alternative || old.fields.len() != new.fields.len() {
+            return None;
+        }
+        let u64_field = |pd: &PlutusData| pd.clone().into_u64();
+        let before_after =
+            |ix: usize| Some((u64_field(old.fields.get(ix)?)?, u64_field(new.fields.get(ix)?)?));
+        let changed =
+            |ix: usize| before_after(ix).map(|(before, after)| (before != after).then_some((before, after)));
+        let tracked = [
+            layout.lp_fee_num,
+            layout.treasury_fee_num,
+            layout.treasury_x,
+            layout.treasury_y,
+        ];
+        let other_fields = old
+            .fields
+            .iter()
+            .zip(new.fields.iter())
+            .enumerate()
+            .filter(|(ix, (before, after))| {
+                !tracked.contains(ix) && before.to_cbor_bytes() != after.to_cbor_bytes()
+            })
+            .map(|(ix, _)| ix)
+            .collect();
+        Some(Self {
+            treasury_x: before_after(layout.treasury_x)?,
+            treasury_y: before_after(layout.treasury_y)?,
+            lp_fee_num: changed(layout.lp_fee_num)?,
+            treasury_fee_num: changed(layout.treasury_fee_num)?,
+            other_fields,
+        })
+    }
+
+    /// Change of treasury in X and Y.
+    pub fn treasury_delta(&self) -> (i128, i128) {
+        (
+            self.treasury_x.1 as i128 - self.treasury_x.0 as i128,
+            self.treasury_y.1 as i128 - self.treasury_y.0 as i128,
+        )
+    }
+
+    /// Whether treasury was set to the given values while the rest of the datum stayed intact.
+    pub fn is_treasury_update(&self, treasury_x: u64, treasury_y: u64) -> bool {
+        self.treasury_x.1 == treasury_x
+            && self.treasury_y.1 == treasury_y
+            && self.lp_fee_num.is_none()
+            && self.treasury_fee_num.is_none()
+            && self.other_fields.is_empty()
+    }
+}
+
+/// Check that `new` datum differs from `old` one only in treasury, which is set to the given values.
+pub fn is_treasury_update(
+    old: &PlutusData,
+    new: &PlutusData,
+    layout: PoolDatumLayout,
+    treasury_x: u64,
+    treasury_y: u64,
+) -> bool {
+    PoolDatumDiff::of(old, new, layout).map_or(false, |diff| diff.is_treasury_update(treasury_x, treasury_y))
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::plutus::PlutusData;
+    use cml_chain::Deserialize;
+
+    use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
+
+    use crate::data::cfmm_pool::update_treasury;
+    use crate::data::pool_datum_diff::{PoolDatumDiff, FEE_SWITCH_POOL_LAYOUT};
+
+    const DATUM_SAMPLE: &str =
+        "d8799fd8799f581c6aaa652b39f5723afc85bba38401a4cbfd5b2f7aa3771504257ac8a74d74657374425f4144415f4e4654ffd8799f4040ffd8799f581c4b3459fd18a1dbabe207cd19c9951a9fac9f5c0f9c384e3d97efba26457465737442ffd8799f581c635f44ae5df86be9e80fd0c57a5ec699a146d9d9034516ffd72febef4c74657374425f4144415f4c51ff19270b010000801b00000002540be400581c2618e94cdb06792f05ae9b1ec78b0231f4b7f4215b1b4cf52e6342deff";
+
+    #[test]
+    fn treasury_update_touches_nothing_else() {
+        let old = PlutusData::from_cbor_bytes(&*hex::decode(DATUM_SAMPLE).unwrap()).unwrap();
+        let mut new = old.clone();
+        update_treasury(&mut new, 100, 200).unwrap();
+        let diff = PoolDatumDiff::of(&old, &new, FEE_SWITCH_POOL_LAYOUT).unwrap();
+        assert_eq!(diff.treasury_delta(), (100, 200));
+        assert!(diff.is_treasury_update(100, 200));
+        assert!(!diff.is_treasury_update(100, 201));
+    }
+
+    #[test]
+    fn detects_fee_and_foreign_changes() {
+        let old = PlutusData::from_cbor_bytes(&*hex::decode(DATUM_SAMPLE).unwrap()).unwrap();
+        let mut new = old.clone();
+        let cpd = new.get_constr_pd_mut().unwrap();
+        cpd.set_field(4, 9000u64.into_pd());
+        cpd.set_field(8, 1u64.into_pd());
+        let diff = PoolDatumDiff::of(&old, &new, FEE_SWITCH_POOL_LAYOUT).unwrap();
+        assert_eq!(diff.lp_fee_num, Some((9995, 9000)));
+        assert_eq!(diff.other_fields, vec![8]);
+        assert!(!diff.is_treasury_update(0, 0));
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_core::serialization::Serialize;
+use cml_crypto::Ed25519KeyHash;
+use log::info;
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use spectrum_cardano_lib::address::PlutusAddress;
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::protocol_params::constant_tx_builder;
+use spectrum_cardano_lib::{NetworkId, OutputRef};
+use spectrum_offchain::data::event::{Channel, Confirmed};
+use spectrum_offchain::data::order::{OrderUpdate, SpecializedOrder};
+use spectrum_offchain::data::Has;
+use spectrum_offchain::kv_backend::KvBackend;
+
+use crate::data::deposit::ClassicalOnChainDeposit;
+use crate::data::limit_swap::ClassicalOnChainLimitSwap;
+use crate::data::order::{ClassicalAMMOrder, ClassicalOrderAction, ClassicalOrderRedeemer};
+use crate::data::pool::ApplyOrderErrorReason;
+use crate::data::redeem::ClassicalOnChainRedeem;
+use crate::deployment::ProtocolValidator::{
+    BalanceFnPoolDeposit, BalanceFnPoolRedeem, ConstFnFeeSwitchPoolDeposit, ConstFnFeeSwitchPoolRedeem,
+    ConstFnFeeSwitchPoolSwap, ConstFnPoolDeposit, ConstFnPoolRedeem, StableFnPoolT2TDeposit,
+    StableFnPoolT2TRedeem,
+};
+use crate::deployment::{DeployedValidator, DeployedValidatorErased, RequiresValidator};
+use crate::script::{delayed_redeemer, ScriptInput, TxInputs};
+
+/// Address of the user who owns an order.
+pub trait HasOwner {
+    fn owner(&self) -> PlutusAddress;
+}
+
+impl HasOwner for ClassicalOnChainLimitSwap {
+    fn owner(&self) -> PlutusAddress {
+        PlutusAddress::pub_key(self.order.redeemer_pkh, self.order.redeemer_stake_pkh)
+    }
+}
+
+impl HasOwner for ClassicalOnChainDeposit {
+    fn owner(&self) -> PlutusAddress {
+        PlutusAddress::pub_key(self.order.reward_pkh, self.order.reward_stake_pkh)
+    }
+}
+
+impl HasOwner for ClassicalOnChainRedeem {
+    fn owner(&self) -> PlutusAddress {
+        PlutusAddress::pub_key(self.order.reward_pkh, self.order.reward_stake_pkh)
+    }
+}
+
+impl HasOwner for ClassicalAMMOrder {
+    fn owner(&self) -> PlutusAddress {
+        match self {
+            ClassicalAMMOrder::Swap(swap) => swap.owner(),
+            ClassicalAMMOrder::Deposit(deposit) => deposit.owner(),
+            ClassicalAMMOrder::Redeem(redeem) => redeem.owner(),
+        }
+    }
+}
+
+impl<Ctx> RequiresValidator<Ctx> for ClassicalAMMOrder
+where
+    Ctx: Has<DeployedValidator<{ ConstFnFeeSwitchPoolSwap as u8 }>>
+        + Has<DeployedValidator<{ ConstFnFeeSwitchPoolDeposit as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolDeposit as u8 }>>
+        + Has<DeployedValidator<{ BalanceFnPoolDeposit as u8 }>>
+        + Has<DeployedValidator<{ StableFnPoolT2TDeposit as u8 }>>
+        + Has<DeployedValidator<{ ConstFnFeeSwitchPoolRedeem as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolRedeem as u8 }>>
+        + Has<DeployedValidator<{ BalanceFnPoolRedeem as u8 }>>
+        + Has<DeployedValidator<{ StableFnPoolT2TRedeem as u8 }>>,
+{
+    fn get_validator(&self, ctx: &Ctx) -> DeployedValidatorErased {
+        match self {
+            ClassicalAMMOrder::Swap(swap) => swap.get_validator(ctx),
+            ClassicalAMMOrder::Deposit(deposit) => deposit.get_validator(ctx),
+            ClassicalAMMOrder::Redeem(redeem) => redeem.get_validator(ctx),
+        }
+    }
+}
+
+/// Build a transaction returning all funds locked in a permanently invalid order back to its owner.
+/// Network fee is deducted from the order value.
+/// Note: order validators authorize refunds by the owner's signature,
+/// so the resulting TX must be co-signed by the owner before submission.
+/// Owners holding script credentials cannot sign, their script must authorize the refund instead.
+pub fn build_refund_tx<Order, Ctx>(
+    Bundled(order, FinalizedTxOut(order_utxo, order_ref)): Bundled<Order, FinalizedTxOut>,
+    ctx: Ctx,
+) -> Result<SignedTxBuilder, TxBuilderError>
+where
+    Order: HasOwner + RequiresValidator<Ctx>,
+    Ctx: Has<Collateral> + Has<NetworkId>,
+{
+    let owner = order.owner();
+    let owner_pkh = owner.payment_pkh();
+    info!(target: "offchain", "Building refund of order {} to {:?}", order_ref, owner.payment_cred);
+
+    let order_validator = order.get_validator(&ctx);
+    let mut inputs = TxInputs::new();
+    inputs.add_script_input(ScriptInput {
+        utxo: TransactionUnspentOutput::new(order_ref.into(), order_utxo),
+        script: order_validator.hash,
+        redeemer: delayed_redeemer(move |ordering| {
+            ClassicalOrderRedeemer {
+                pool_input_index: 0,
+                order_input_index: ordering.index_of(&order_ref) as u64,
+                output_index: 0,
+                action: ClassicalOrderAction::Refund,
+            }
+            .to_plutus_data()
+        }),
+        ex_units: order_validator.ex_budget.into(),
+        required_signers: owner_pkh.into_iter().collect(),
+    });
+
+    let mut tx_builder = constant_tx_builder();
+
+    tx_builder.add_collateral(ctx.select::<Collateral>().into())?;
+    tx_builder.add_reference_input(order_validator.reference_utxo);
+    inputs.project_onto(&mut tx_builder)?;
+    if let Some(pkh) = owner_pkh {
+        tx_builder.add_required_signer(pkh);
+    }
+
+    // Whole order value goes back to the owner as change.
+    tx_builder.build(
+        ChangeSelectionAlgo::Default,
+        &owner.to_address(ctx.select::<NetworkId>()),
+    )
+}
+
+/// Refund of a permanently invalid order awaiting the signature of its owner.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingRefund {
+    pub order: OutputRef,
+    pub reason: ApplyOrderErrorReason,
+    /// Unsigned refund TX, hex-encoded CBOR.
+    pub tx: String,
+}
+
+/// Key prefix refunds are persisted under, the backend may be shared with the rest of the agent state.
+const REFUND_PREFIX: u8 = 10u8;
+
+/// At most this many refunds are kept, the oldest ones are forgotten first.
+const MAX_PENDING_REFUNDS: usize = 10_000;
+
+fn refund_key(order: &OutputRef) -> Vec<u8> {
+    let mut key = vec![REFUND_PREFIX];
+    key.extend(bincode::serialize(order).unwrap());
+    key
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+struct RegisteredRefund {
+    /// Refunds are forgotten in the order they were registered in.
+    seq: u64,
+    owner: Ed25519KeyHash,
+    refund: PendingRefund,
+}
+
+#[derive(Default)]
+struct RefundsState {
+    by_order: HashMap<OutputRef, RegisteredRefund>,
+    /// Orders by registration sequence number, oldest first.
+    registration_order: BTreeMap<u64, OutputRef>,
+    next_seq: u64,
+}
+
+impl RefundsState {
+    fn insert(&mut self, refund: RegisteredRefund) -> Option<RegisteredRefund> {
+        let order = refund.refund.order;
+        self.next_seq = self.next_seq.max(refund.seq + 1);
+        self.registration_order.insert(refund.seq, order);
+        let prev = self.by_order.insert(order, refund);
+        if let Some(prev) = &prev {
+            self.registration_order.remove(&prev.seq);
+        }
+        prev
+    }
+
+    fn remove(&mut self, order: &OutputRef) -> Option<RegisteredRefund> {
+        let refund = self.by_order.remove(order)?;
+        self.registration_order.remove(&refund.seq);
+        Some(refund)
+    }
+
+    fn pop_oldest(&mut self) -> Option<RegisteredRefund> {
+        let (_, order) = self.registration_order.pop_first()?;
+        self.by_order.remove(&order)
+    }
+}
+
+/// Refunds of permanently invalid orders by their owners.
+/// A refund is kept until the order it spends is eliminated or newer refunds push it out.
+/// Refunds survive restarts if a backend is given.
+#[derive(Clone, Default)]
+pub struct Refunds {
+    state: Arc<RwLock<RefundsState>>,
+    backend: Option<Arc<dyn KvBackend + Send + Sync>>,
+}
+
+impl Debug for Refunds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Refunds")
+            .field("pending", &self.state.read().unwrap().by_order.len())
+            .finish()
+    }
+}
+
+impl Refunds {
+    /// Refunds persisted in the given backend, the ones registered before are restored.
+    pub fn persistent(backend: Arc<dyn KvBackend + Send + Sync>) -> Self {
+        let mut state = RefundsState::default();
+        for (_, value) in backend.scan_prefix(&[REFUND_PREFIX]) {
+            if let Ok(refund) = bincode::deserialize::<RegisteredRefund>(&value) {
+                state.insert(refund);
+            }
+        }
+        Self {
+            state: Arc::new(RwLock::new(state)),
+            backend: Some(backend),
+        }
+    }
+
+    pub fn register(&self, owner: Ed25519KeyHash, refund: PendingRefund) {
+        info!(
+            target: "offchain",
+            "Refund of order {} ({}) awaits signature of {}",
+            refund.order,
+            refund.reason,
+            owner.to_hex()
+        );
+        let mut state = self.state.write().unwrap();
+        let registered = RegisteredRefund {
+            seq: state.next_seq,
+            owner,
+            refund,
+        };
+        if let Some(backend) = &self.backend {
+            backend.put(
+                &refund_key(&registered.refund.order),
+                &bincode::serialize(&registered).unwrap(),
+            );
+        }
+        state.insert(registered);
+        while state.by_order.len() > MAX_PENDING_REFUNDS {
+            if let Some(oldest) = state.pop_oldest() {
+                self.forget(&oldest.refund.order);
+            }
+        }
+    }
+
+    fn forget(&self, order: &OutputRef) {
+        if let Some(backend) = &self.backend {
+            backend.delete(&refund_key(order));
+        }
+    }
+
+    /// Build and register refund of the given order if it is permanently invalid for the given reason.
+    pub fn on_rejected<Order, Ctx>(
+        &self,
+        Bundled(order, bearer): Bundled<Order, FinalizedTxOut>,
+        reason: ApplyOrderErrorReason,
+        ctx: Ctx,
+    ) where
+        Order: HasOwner + RequiresValidator<Ctx>,
+        Ctx: Has<Collateral> + Has<NetworkId>,
+    {
+        if !reason.is_permanent() {
+            return;
+        }
+        let order_ref = bearer.1;
+        let Some(owner) = order.owner().payment_pkh() else {
+            return;
+        };
+        match build_refund_tx(Bundled(order, bearer), ctx) {
+            Ok(tx) => self.register(
+                owner,
+                PendingRefund {
+                    order: order_ref,
+                    reason,
+                    tx: hex::encode(tx.build_unchecked().to_cbor_bytes()),
+                },
+            ),
+            Err(err) => info!(target: "offchain", "Cannot build refund of order {}: {}", order_ref, err),
+        }
+    }
+
+    /// Refunds awaiting signature of the given owner.
+    pub fn of_owner(&self, owner: &Ed25519KeyHash) -> Vec<PendingRefund> {
+        self.state
+            .read()
+            .unwrap()
+            .by_order
+            .values()
+            .filter(|r| r.owner == *owner)
+            .map(|r| r.refund.clone())
+            .collect()
+    }
+
+    /// Forget refunds of orders once their elimination is confirmed.
+    pub fn observe<New, Elim>(&self, update: &Channel<OrderUpdate<New, Elim>>)
+    where
+        Elim: SpecializedOrder<TOrderId = OutputRef>,
+    {
+        if let Channel::Ledger(Confirmed(OrderUpdate::Eliminated(order))) = update {
+            let order_ref = order.get_self_ref();
+            if self.state.write().unwrap().remove(&order_ref).is_some() {
+                self.forget(&order_ref);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cml_crypto::{Ed25519KeyHash, ScriptHash, TransactionHash};
+    use rand::RngCore;
+
+    use spectrum_cardano_lib::{AssetClass, AssetName, OutputRef, TaggedAmount, TaggedAssetClass};
+    use spectrum_offchain::data::event::{Channel, Confirmed};
+    use spectrum_offchain::data::order::OrderUpdate;
+    use spectrum_offchain::kv_backend::{open_backend, KvBackendConfig};
+
+    use crate::data::order::{ClassicalAMMOrder, ClassicalOrder, OrderType};
+    use crate::data::pool::ApplyOrderErrorReason;
+    use crate::data::redeem::Redeem;
+    use crate::data::refund::{PendingRefund, Refunds, MAX_PENDING_REFUNDS};
+    use crate::data::{OnChainOrderId, PoolId};
+
+    fn order_ref(seed: u8) -> OutputRef {
+        OutputRef::new(TransactionHash::from([seed; 32]), 0)
+    }
+
+    fn pending(seed: u8) -> PendingRefund {
+        PendingRefund {
+            order: order_ref(seed),
+            reason: ApplyOrderErrorReason::LowBatcherFee,
+            tx: String::new(),
+        }
+    }
+
+    fn redeem(seed: u8) -> ClassicalAMMOrder {
+        let pool_id = PoolId::from((ScriptHash::from([0u8; 28]), AssetName::from((0, [0u8; 32]))));
+        ClassicalAMMOrder::Redeem(ClassicalOrder {
+            id: OnChainOrderId(order_ref(seed)),
+            pool_id,
+            order: Redeem {
+                pool_nft: pool_id,
+                token_x: TaggedAssetClass::new(AssetClass::Native),
+                token_y: TaggedAssetClass::new(AssetClass::Native),
+                token_lq: TaggedAssetClass::new(AssetClass::Native),
+                token_lq_amount: TaggedAmount::new(1),
+                ex_fee: 0,
+                reward_pkh: Ed25519KeyHash::from([seed; 28]),
+                reward_stake_pkh: None,
+                collateral_ada: 0,
+                order_type: OrderType::ConstFn,
+            },
+        })
+    }
+
+    #[test]
+    fn refunds_are_kept_until_order_is_eliminated() {
+        let refunds = Refunds::default();
+        let owner = Ed25519KeyHash::from([1u8; 28]);
+        refunds.register(owner, pending(1));
+        refunds.register(owner, pending(2));
+        refunds.register(Ed25519KeyHash::from([3u8; 28]), pending(3));
+        assert_eq!(refunds.of_owner(&owner).len(), 2);
+        refunds
+            .observe::<ClassicalAMMOrder, _>(&Channel::Ledger(Confirmed(OrderUpdate::Eliminated(redeem(1)))));
+        assert_eq!(refunds.of_owner(&owner), vec![pending(2)]);
+    }
+
+    #[test]
+    fn refunds_survive_restart() {
+        let backend = open_backend(&KvBackendConfig::RocksDB {
+            db_path: format!("./tmp/{}", rand::thread_rng().next_u32()),
+        });
+        let owner = Ed25519KeyHash::from([1u8; 28]);
+        let refunds = Refunds::persistent(Arc::clone(&backend));
+        refunds.register(owner, pending(1));
+        refunds.register(owner, pending(2));
+        refunds
+            .observe::<ClassicalAMMOrder, _>(&Channel::Ledger(Confirmed(OrderUpdate::Eliminated(redeem(1)))));
+        let restored = Refunds::persistent(backend);
+        assert_eq!(restored.of_owner(&owner), vec![pending(2)]);
+    }
+
+    #[test]
+    fn oldest_refunds_are_forgotten_first() {
+        let refunds = Refunds::default();
+        let owner = Ed25519KeyHash::from([1u8; 28]);
+        for ix in 0..=MAX_PENDING_REFUNDS as u64 {
+            refunds.register(
+                owner,
+                PendingRefund {
+                    order: OutputRef::new(TransactionHash::from([0u8; 32]), ix),
+                    reason: ApplyOrderErrorReason::LowBatcherFee,
+                    tx: String::new(),
+                },
+            );
+        }
+        let pending = refunds.of_owner(&owner);
+        assert_eq!(pending.len(), MAX_PENDING_REFUNDS);
+        assert!(pending.iter().all(|r| r.order.index() != 0));
+    }
+}
@@ -1,6 +1,7 @@
 use crate::creds::OperatorRewardAddress;
 use crate::data::order::ClassicalAMMOrder;
 use crate::data::pool::try_run_order_against_pool;
+use crate::data::refund::Refunds;
 use crate::data::stable_pool_t2t::StablePoolT2T;
 use crate::deployment::DeployedValidator;
 use crate::deployment::ProtocolValidator::{
@@ -9,6 +10,7 @@ use crate::deployment::ProtocolValidator::{
     ConstFnPoolSwap, ConstFnPoolV1, ConstFnPoolV2, StableFnPoolT2T, StableFnPoolT2TDeposit,
     StableFnPoolT2TRedeem,
 };
+use crate::fee_tuning::SharedFeeTuner;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use cml_chain::builders::tx_builder::SignedTxBuilder;
 use spectrum_cardano_lib::collateral::Collateral;
@@ -27,6 +29,8 @@ where
         + Has<Collateral>
         + Has<NetworkId>
         + Has<OperatorRewardAddress>
+        + Has<Refunds>
+        + Has<SharedFeeTuner>
         + Has<DeployedValidator<{ BalanceFnPoolV1 as u8 }>>
         + Has<DeployedValidator<{ BalanceFnPoolDeposit as u8 }>>
         + Has<DeployedValidator<{ BalanceFnPoolRedeem as u8 }>>
@@ -3,7 +3,7 @@ use std::ops::Mul;
 
 use bloom_offchain::execution_engine::liquidity_book::core::{Next, Unit};
 use bloom_offchain::execution_engine::liquidity_book::market_maker::{
-    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolQuality, SpotPrice,
+    AbsoluteReserves, Excess, MakerBehavior, MarketMaker, PoolLifecycle, PoolQuality, SpotPrice,
 };
 use bloom_offchain::execution_engine::liquidity_book::side::{OnSide, Side};
 use bloom_offchain::execution_engine::liquidity_book::types::AbsolutePrice;
@@ -18,8 +18,9 @@ use cml_multi_era::babbage::BabbageTransactionOutput;
 use num_rational::Ratio;
 use num_traits::{CheckedAdd, CheckedSub, Pow, ToPrimitive};
 use primitive_types::U512;
+use spectrum_cardano_lib::address::PlutusAddress;
 use spectrum_cardano_lib::ex_units::ExUnits;
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, DatumExtension};
+use spectrum_cardano_lib::plutus_data::{update_typed_datum, ConstrPlutusDataExtension, DatumUpdateError};
 use spectrum_cardano_lib::plutus_data::{IntoPlutusData, PlutusDataExtension};
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::types::TryFromPData;
@@ -27,7 +28,7 @@ use spectrum_cardano_lib::value::ValueExtension;
 use spectrum_cardano_lib::AssetClass::Native;
 use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use spectrum_offchain::data::{Has, Stable};
-use spectrum_offchain::ledger::{IntoLedger, TryFromLedger};
+use spectrum_offchain::ledger::{TryFromLedger, TryIntoLedger};
 use void::Void;
 
 use crate::constants::{FEE_DEN, MAX_LQ_CAP};
@@ -114,7 +115,7 @@ impl TryFromPData for StablePoolT2TConfig {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub enum StablePoolT2TVer {
     V1,
 }
@@ -141,7 +142,7 @@ impl StablePoolT2TVer {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct StablePoolT2T {
     pub id: PoolId,
     pub an2n: u64,
@@ -161,6 +162,8 @@ pub struct StablePoolT2T {
     pub ver: StablePoolT2TVer,
     /// How many execution units pool invokation costs.
     pub marginal_cost: ExUnits,
+    /// Pool is bootstrapping until this amount of LP tokens is in circulation.
+    pub bootstrap_liquidity: u64,
 }
 
 impl StablePoolT2T {
@@ -168,28 +171,17 @@ impl StablePoolT2T {
         let x = self.asset_x.untag();
         let y = self.asset_y.untag();
         let [base, _] = order_canonical(x, y);
-        if base == x {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-            }
-        } else {
-            match side {
-                Side::Bid => PoolAssetMapping {
-                    asset_to_deduct_from: y,
-                    asset_to_add_to: x,
-                },
-                Side::Ask => PoolAssetMapping {
-                    asset_to_deduct_from: x,
-                    asset_to_add_to: y,
-                },
-            }
+        // Sides are relative to the canonical pair, so they flip when `x` is the quote asset.
+        let side = if base == x { side } else { side.flip() };
+        match side {
+            Side::Bid => PoolAssetMapping {
+                asset_to_deduct_from: x,
+                asset_to_add_to: y,
+            },
+            Side::Ask => PoolAssetMapping {
+                asset_to_deduct_from: y,
+                asset_to_add_to: x,
+            },
         }
     }
 
@@ -236,8 +228,7 @@ where
     fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &Ctx) -> Option<Self> {
         if let Some(pool_ver) = StablePoolT2TVer::try_from_address(repr.address(), ctx) {
             let value = repr.value();
-            let pd = repr.datum().clone()?.into_pd()?;
-            let conf = StablePoolT2TConfig::try_from_pd(pd.clone())?;
+            let conf = StablePoolT2TConfig::try_from_pd(repr.inline_datum()?.clone())?;
             let liquidity_neg = value.amount_of(conf.asset_lq.into())?;
             let bounds = ctx.select::<PoolBounds>();
             let lov = value.amount_of(Native)?;
@@ -267,6 +258,7 @@ where
                     marginal_cost: ctx
                         .select::<DeployedScriptInfo<{ StableFnPoolT2T as u8 }>>()
                         .marginal_cost,
+                    bootstrap_liquidity: bounds.bootstrap_liquidity,
                 });
             }
         }
@@ -274,8 +266,13 @@ where
     }
 }
 
-impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for StablePoolT2T {
-    fn into_ledger(self, mut immut_pool: ImmutablePoolUtxo) -> TransactionOutput {
+impl TryIntoLedger<TransactionOutput, ImmutablePoolUtxo> for StablePoolT2T {
+    type Error = DatumUpdateError;
+
+    fn try_into_ledger(
+        self,
+        mut immut_pool: ImmutablePoolUtxo,
+    ) -> Result<TransactionOutput, DatumUpdateError> {
         let mut ma = MultiAsset::new();
         let coins = if self.asset_x.is_native() {
             let (policy, name) = self.asset_y.untag().into_token().unwrap();
@@ -298,23 +295,31 @@ impl IntoLedger<TransactionOutput, ImmutablePoolUtxo> for StablePoolT2T {
         ma.set(nft_lq, name_nft.into(), 1);
 
         if let Some(DatumOption::Datum { datum, .. }) = &mut immut_pool.datum_option {
-            unsafe_update_datum(datum, self.treasury_x.untag(), self.treasury_y.untag());
+            update_treasury(datum, self.treasury_x.untag(), self.treasury_y.untag())?;
         }
 
-        TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
+        Ok(TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
             address: immut_pool.address,
             amount: Value::new(coins, ma),
             datum_option: immut_pool.datum_option,
             script_reference: immut_pool.script_reference,
             encodings: None,
-        })
+        }))
     }
 }
 
-pub fn unsafe_update_datum(data: &mut PlutusData, treasury_x: u64, treasury_y: u64) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(DATUM_MAPPING.treasury_x, treasury_x.into_pd());
-    cpd.set_field(DATUM_MAPPING.treasury_y, treasury_y.into_pd());
+pub fn update_treasury(
+    data: &mut PlutusData,
+    treasury_x: u64,
+    treasury_y: u64,
+) -> Result<(), DatumUpdateError> {
+    update_typed_datum::<StablePoolT2TConfig, _>(
+        data,
+        [
+            (DATUM_MAPPING.treasury_x, treasury_x.into_pd()),
+            (DATUM_MAPPING.treasury_y, treasury_y.into_pd()),
+        ],
+    )
 }
 
 impl Stable for StablePoolT2T {
@@ -523,6 +528,17 @@ impl MarketMaker for StablePoolT2T {
         AbsolutePrice::new(quote, base)
     }
 
+    fn lp_fee(&self, input: OnSide<u64>) -> u64 {
+        let [base, _] = order_canonical(self.asset_x.untag(), self.asset_y.untag());
+        // Fee is charged from the output, its share of the input is the same.
+        let fee = if self.asset_x.untag() == base {
+            self.lp_fee_y
+        } else {
+            self.lp_fee_x
+        };
+        ((input.unwrap() as u128 * *fee.numer() as u128) / *fee.denom() as u128) as u64
+    }
+
     fn quality(&self) -> PoolQuality {
         PoolQuality::from(0u128)
     }
@@ -548,10 +564,17 @@ impl MarketMaker for StablePoolT2T {
         }
     }
 
-    fn is_active(&self) -> bool {
-        // balance pools do not support lq bound, so
-        // swaps allowed all time
-        true
+    fn lifecycle(&self) -> PoolLifecycle {
+        // stable pools do not support lq bound, so
+        // swaps allowed all time once liquidity is bootstrapped
+        if self.reserves_x.untag() == 0
+            || self.reserves_y.untag() == 0
+            || self.liquidity.untag() < self.bootstrap_liquidity
+        {
+            PoolLifecycle::Bootstrapping
+        } else {
+            PoolLifecycle::Active
+        }
     }
 }
 
@@ -591,17 +614,17 @@ impl ApplyOrder<ClassicalOnChainDeposit> for StablePoolT2T {
                     .reserves_x
                     .checked_add(&TaggedAmount::new(net_x))
                     .and_then(|result| result.checked_sub(&change_x))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_add(&TaggedAmount::new(net_y))
                     .and_then(|result| result.checked_sub(&change_y))
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
 
                 self.liquidity = self
                     .liquidity
                     .checked_add(&unlocked_lq)
-                    .ok_or(ApplyOrderError::incompatible(deposit.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(deposit.clone()))?;
 
                 let deposit_output = DepositOutput {
                     token_x_asset: order.token_x,
@@ -611,13 +634,12 @@ impl ApplyOrder<ClassicalOnChainDeposit> for StablePoolT2T {
                     token_lq_asset: order.token_lq,
                     token_lq_amount: unlocked_lq,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, deposit_output))
             }
-            None => Err(ApplyOrderError::incompatible(deposit)),
+            None => Err(ApplyOrderError::invariant_violation(deposit)),
         }
     }
 }
@@ -635,15 +657,15 @@ impl ApplyOrder<ClassicalOnChainRedeem> for StablePoolT2T {
                 self.reserves_x = self
                     .reserves_x
                     .checked_sub(&x_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.reserves_y = self
                     .reserves_y
                     .checked_sub(&y_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
                 self.liquidity = self
                     .liquidity
                     .checked_sub(&order.token_lq_amount)
-                    .ok_or(ApplyOrderError::incompatible(redeem.clone()))?;
+                    .ok_or(ApplyOrderError::invariant_violation(redeem.clone()))?;
 
                 let redeem_output = RedeemOutput {
                     token_x_asset: order.token_x,
@@ -651,13 +673,12 @@ impl ApplyOrder<ClassicalOnChainRedeem> for StablePoolT2T {
                     token_y_asset: order.token_y,
                     token_y_amount: y_amount,
                     ada_residue: order.collateral_ada,
-                    redeemer_pkh: order.reward_pkh,
-                    redeemer_stake_pkh: order.reward_stake_pkh,
+                    redeemer: PlutusAddress::pub_key(order.reward_pkh, order.reward_stake_pkh),
                 };
 
                 Ok((self, redeem_output))
             }
-            None => Err(ApplyOrderError::incompatible(redeem)),
+            None => Err(ApplyOrderError::invariant_violation(redeem)),
         }
     }
 }
@@ -774,6 +795,7 @@ mod tests {
                 mem: 120000000,
                 steps: 100000000000,
             },
+            bootstrap_liquidity: 0,
         };
     }
 
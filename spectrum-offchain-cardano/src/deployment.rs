@@ -60,30 +60,37 @@ impl TryFrom<Script> for cml_chain::Script {
     }
 }
 
-#[derive(Copy, Clone, serde::Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct ReferenceUTxO {
-    pub tx_hash: TransactionHash,
-    pub output_index: u64,
-}
-
-impl Display for ReferenceUTxO {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(format!("{}:{}", self.tx_hash, self.output_index).as_str())
-    }
+/// Reference UTxO either in the standard `<tx_hash>#<index>` form
+/// or in the legacy `{"txHash": .., "outputIndex": ..}` one.
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum ReferenceUTxO {
+    Standard(OutputRef),
+    #[serde(rename_all = "camelCase")]
+    Legacy {
+        tx_hash: TransactionHash,
+        output_index: u64,
+    },
 }
 
-impl From<ReferenceUTxO> for OutputRef {
-    fn from(value: ReferenceUTxO) -> Self {
-        Self::new(value.tx_hash, value.output_index)
-    }
+fn reference_utxo<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<OutputRef, D::Error> {
+    Ok(
+        match <ReferenceUTxO as serde::Deserialize>::deserialize(deserializer)? {
+            ReferenceUTxO::Standard(oref) => oref,
+            ReferenceUTxO::Legacy {
+                tx_hash,
+                output_index,
+            } => OutputRef::new(tx_hash, output_index),
+        },
+    )
 }
 
 #[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeployedValidatorRef {
     pub hash: ScriptHash,
-    pub reference_utxo: ReferenceUTxO,
+    #[serde(deserialize_with = "reference_utxo")]
+    pub reference_utxo: OutputRef,
     /// Cost per contract invokation.
     pub cost: ExUnits,
     /// Cost per each subsequent contract invokation.
@@ -242,9 +249,9 @@ impl PartialEq for DeployedValidatorErased {
 impl Eq for DeployedValidatorErased {}
 
 impl<const TYP: u8> DeployedValidator<TYP> {
-    async fn unsafe_pull<Net: CardanoNetwork>(v: DeployedValidatorRef, explorer: &Net) -> Self {
+    pub async fn unsafe_pull<Net: CardanoNetwork>(v: DeployedValidatorRef, explorer: &Net) -> Self {
         let ref_output = explorer
-            .utxo_by_ref(v.reference_utxo.into())
+            .utxo_by_ref(v.reference_utxo)
             .await
             .expect(format!("Reference UTxO {} from config not found", v.reference_utxo).as_str());
         Self {
@@ -281,6 +288,10 @@ pub enum ProtocolValidator {
     StableFnPoolT2T,
     StableFnPoolT2TDeposit,
     StableFnPoolT2TRedeem,
+    /// Team/treasury vesting, deployed separately from the DEX validators.
+    Vesting,
+    /// Staking script authorizing governance actions over fee-switch pools on behalf of the DAO.
+    DaoAuthority,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -0,0 +1,353 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+use cml_chain::Coin;
+use cml_crypto::TransactionHash;
+use num_rational::Ratio;
+
+use spectrum_cardano_lib::ex_units::ExUnits;
+
+use crate::data::deposit::ClassicalOnChainDeposit;
+use crate::data::limit_swap::ClassicalOnChainLimitSwap;
+use crate::data::order::ClassicalAMMOrder;
+use crate::data::redeem::ClassicalOnChainRedeem;
+use crate::fees::{FeeExtension, FeePerOutput};
+
+/// Kind of classical order the batcher fee is collected for.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FeeTarget {
+    Swap,
+    Deposit,
+    Redeem,
+}
+
+impl From<&ClassicalAMMOrder> for FeeTarget {
+    fn from(order: &ClassicalAMMOrder) -> Self {
+        match order {
+            ClassicalAMMOrder::Swap(_) => FeeTarget::Swap,
+            ClassicalAMMOrder::Deposit(_) => FeeTarget::Deposit,
+            ClassicalAMMOrder::Redeem(_) => FeeTarget::Redeem,
+        }
+    }
+}
+
+/// Batcher fee an order pays upon execution.
+pub trait OffersBatcherFee {
+    fn fee_target(&self) -> FeeTarget;
+    /// Least fee the order pays if executed.
+    fn offered_fee(&self) -> Coin;
+}
+
+impl OffersBatcherFee for ClassicalOnChainLimitSwap {
+    fn fee_target(&self) -> FeeTarget {
+        FeeTarget::Swap
+    }
+
+    /// Fee is linear in the output, which is at least the minimal expected one.
+    fn offered_fee(&self) -> Coin {
+        self.order
+            .fee
+            .value()
+            .linear_fee(self.order.min_expected_quote_amount.untag())
+    }
+}
+
+impl OffersBatcherFee for ClassicalOnChainDeposit {
+    fn fee_target(&self) -> FeeTarget {
+        FeeTarget::Deposit
+    }
+
+    fn offered_fee(&self) -> Coin {
+        self.order.ex_fee
+    }
+}
+
+impl OffersBatcherFee for ClassicalOnChainRedeem {
+    fn fee_target(&self) -> FeeTarget {
+        FeeTarget::Redeem
+    }
+
+    fn offered_fee(&self) -> Coin {
+        self.order.ex_fee
+    }
+}
+
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeTuningConfig {
+    /// How many recent executions are taken into account.
+    pub window_size: usize,
+    /// Margin over the observed average cost the operator requires, in percents.
+    pub margin_percent: u64,
+    /// Static lower bound of the batcher fee.
+    pub min_fee_floor: Coin,
+    pub mem_price: ExUnitPrice,
+    pub steps_price: ExUnitPrice,
+}
+
+/// Price of a single ex-unit in lovelace.
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(try_from = "RawExUnitPrice")]
+pub struct ExUnitPrice {
+    num: u64,
+    denom: u64,
+}
+
+impl ExUnitPrice {
+    pub fn new(num: u64, denom: u64) -> Option<Self> {
+        (denom > 0).then_some(Self { num, denom })
+    }
+
+    fn cost_of(&self, units: u64) -> Coin {
+        ((units as u128 * self.num as u128) / self.denom as u128) as Coin
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct RawExUnitPrice {
+    num: u64,
+    denom: u64,
+}
+
+impl TryFrom<RawExUnitPrice> for ExUnitPrice {
+    type Error = String;
+    fn try_from(raw: RawExUnitPrice) -> Result<Self, Self::Error> {
+        ExUnitPrice::new(raw.num, raw.denom)
+            .ok_or_else(|| "Ex-unit price denominator must be positive".into())
+    }
+}
+
+/// Actual costs of execution of a single order.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ExecutionCost {
+    /// Size-dependent part of the TX fee attributed to the order.
+    pub tx_fee: Coin,
+    /// Ex-units consumed by the scripts involved in execution of the order.
+    pub ex_units: ExUnits,
+}
+
+/// Tracks recent execution costs and derives the minimal batcher fee
+/// the operator is willing to accept for each kind of order.
+#[derive(Debug, Clone)]
+pub struct BatcherFeeTuner<K> {
+    conf: FeeTuningConfig,
+    observations: HashMap<K, VecDeque<ExecutionCost>>,
+}
+
+impl<K> BatcherFeeTuner<K>
+where
+    K: Copy + Eq + Hash,
+{
+    pub fn new(conf: FeeTuningConfig) -> Self {
+        Self {
+            conf,
+            observations: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, target: K, cost: ExecutionCost) {
+        let window = self.observations.entry(target).or_insert_with(VecDeque::new);
+        window.push_back(cost);
+        while window.len() > self.conf.window_size {
+            window.pop_front();
+        }
+    }
+
+    /// Record execution of a single order by a TX charging `total_fee`.
+    /// The part of the fee not explained by the ex-units consumed is attributed to the size of the TX.
+    pub fn observe_tx(&mut self, target: K, total_fee: Coin, ex_units: ExUnits) {
+        let tx_fee = total_fee.saturating_sub(self.ex_units_cost(ex_units));
+        self.observe(target, ExecutionCost { tx_fee, ex_units });
+    }
+
+    /// Average cost of execution of an order of the given kind, if known.
+    pub fn average_cost(&self, target: K) -> Option<Coin> {
+        let window = self.observations.get(&target).filter(|w| !w.is_empty())?;
+        let total = window
+            .iter()
+            .map(|cost| (cost.tx_fee + self.ex_units_cost(cost.ex_units)) as u128)
+            .sum::<u128>();
+        Some((total / window.len() as u128) as Coin)
+    }
+
+    /// Minimal batcher fee acceptable for an order of the given kind.
+    pub fn min_acceptable_fee(&self, target: K) -> Coin {
+        self.average_cost(target)
+            .map(|avg| ((avg as u128 * (100 + self.conf.margin_percent) as u128) / 100) as Coin)
+            .unwrap_or(0)
+            .max(self.conf.min_fee_floor)
+    }
+
+    /// Whether the given fee offered by the order covers the costs of its execution.
+    pub fn admits(&self, target: K, offered_fee: Coin) -> bool {
+        offered_fee >= self.min_acceptable_fee(target)
+    }
+
+    /// Minimal linear fee per unit of output given the expected output amount.
+    pub fn min_fee_per_output(&self, target: K, expected_output: u64) -> FeePerOutput {
+        let min_fee = self.min_acceptable_fee(target) as u128;
+        Ratio::new(min_fee, expected_output.max(1) as u128)
+    }
+
+    fn ex_units_cost(&self, units: ExUnits) -> Coin {
+        self.conf.mem_price.cost_of(units.mem) + self.conf.steps_price.cost_of(units.steps)
+    }
+}
+
+/// At most this many executions awaiting confirmation are tracked, the oldest ones are forgotten first.
+const MAX_UNCONFIRMED_EXECUTIONS: usize = 1024;
+
+/// Execution of a single order by a TX which is not confirmed yet.
+#[derive(Debug, Copy, Clone)]
+struct UnconfirmedExecution {
+    target: FeeTarget,
+    total_fee: Coin,
+    ex_units: ExUnits,
+}
+
+#[derive(Debug)]
+struct TunerState {
+    tuner: BatcherFeeTuner<FeeTarget>,
+    unconfirmed: HashMap<TransactionHash, UnconfirmedExecution>,
+    /// Hashes of unconfirmed TXs, oldest first.
+    unconfirmed_order: VecDeque<TransactionHash>,
+}
+
+/// [BatcherFeeTuner] shared between executors, admits any fee unless configured.
+/// Only executions confirmed on-chain are taken into account,
+/// so that TXs which are rejected or never make it into a block don't skew the estimates.
+#[derive(Debug, Clone, Default)]
+pub struct SharedFeeTuner(Option<Arc<Mutex<TunerState>>>);
+
+impl SharedFeeTuner {
+    pub fn new(conf: Option<FeeTuningConfig>) -> Self {
+        Self(conf.map(|conf| {
+            Arc::new(Mutex::new(TunerState {
+                tuner: BatcherFeeTuner::new(conf),
+                unconfirmed: HashMap::new(),
+                unconfirmed_order: VecDeque::new(),
+            }))
+        }))
+    }
+
+    /// Remember execution of a single order by the TX, it is observed once the TX is confirmed.
+    pub fn on_tx_built(
+        &self,
+        tx_hash: TransactionHash,
+        target: FeeTarget,
+        total_fee: Coin,
+        ex_units: ExUnits,
+    ) {
+        if let Some(state) = &self.0 {
+            let mut state = state.lock().unwrap();
+            let execution = UnconfirmedExecution {
+                target,
+                total_fee,
+                ex_units,
+            };
+            if state.unconfirmed.insert(tx_hash, execution).is_none() {
+                state.unconfirmed_order.push_back(tx_hash);
+            }
+            while state.unconfirmed_order.len() > MAX_UNCONFIRMED_EXECUTIONS {
+                if let Some(oldest) = state.unconfirmed_order.pop_front() {
+                    state.unconfirmed.remove(&oldest);
+                }
+            }
+        }
+    }
+
+    /// Observe execution by the given TX if it was built by us.
+    pub fn on_tx_confirmed(&self, tx_hash: &TransactionHash) {
+        if let Some(state) = &self.0 {
+            let mut state = state.lock().unwrap();
+            if let Some(execution) = state.unconfirmed.remove(tx_hash) {
+                state.unconfirmed_order.retain(|h| h != tx_hash);
+                state
+                    .tuner
+                    .observe_tx(execution.target, execution.total_fee, execution.ex_units);
+            }
+        }
+    }
+
+    /// Minimal batcher fee acceptable for an order of the given kind, if tuning is enabled.
+    pub fn min_acceptable_fee(&self, target: FeeTarget) -> Option<Coin> {
+        self.0
+            .as_ref()
+            .map(|state| state.lock().unwrap().tuner.min_acceptable_fee(target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_crypto::TransactionHash;
+
+    use spectrum_cardano_lib::ex_units::ExUnits;
+
+    use crate::fee_tuning::{
+        BatcherFeeTuner, ExUnitPrice, ExecutionCost, FeeTarget, FeeTuningConfig, SharedFeeTuner,
+    };
+
+    fn conf() -> FeeTuningConfig {
+        FeeTuningConfig {
+            window_size: 2,
+            margin_percent: 10,
+            min_fee_floor: 100_000,
+            mem_price: ExUnitPrice::new(577, 10000).unwrap(),
+            steps_price: ExUnitPrice::new(721, 10000000).unwrap(),
+        }
+    }
+
+    fn cost(tx_fee: u64) -> ExecutionCost {
+        ExecutionCost {
+            tx_fee,
+            ex_units: ExUnits { mem: 0, steps: 0 },
+        }
+    }
+
+    #[test]
+    fn falls_back_to_floor_without_observations() {
+        let tuner = BatcherFeeTuner::<FeeTarget>::new(conf());
+        assert_eq!(tuner.min_acceptable_fee(FeeTarget::Swap), 100_000);
+    }
+
+    #[test]
+    fn tracks_sliding_window() {
+        let mut tuner = BatcherFeeTuner::new(conf());
+        tuner.observe(FeeTarget::Swap, cost(1_000_000));
+        tuner.observe(FeeTarget::Swap, cost(200_000));
+        tuner.observe(FeeTarget::Swap, cost(400_000));
+        assert_eq!(tuner.average_cost(FeeTarget::Swap), Some(300_000));
+        assert_eq!(tuner.min_acceptable_fee(FeeTarget::Swap), 330_000);
+        assert!(tuner.admits(FeeTarget::Swap, 330_000));
+        assert!(!tuner.admits(FeeTarget::Swap, 329_999));
+        assert_eq!(tuner.min_acceptable_fee(FeeTarget::Deposit), 100_000);
+    }
+
+    #[test]
+    fn zero_denominated_price_is_rejected() {
+        let price = serde_json::from_str::<ExUnitPrice>(r#"{"num": 577, "denom": 0}"#);
+        assert!(price.is_err());
+        let price = serde_json::from_str::<ExUnitPrice>(r#"{"num": 577, "denom": 10}"#).unwrap();
+        assert_eq!(price.cost_of(10), 577);
+    }
+
+    #[test]
+    fn attributes_unexplained_fee_to_tx_size() {
+        let tuner = SharedFeeTuner::new(Some(conf()));
+        let ex_units = ExUnits {
+            mem: 1_000_000,
+            steps: 1_000_000_000,
+        };
+        let tx_hash = TransactionHash::from([1u8; 32]);
+        tuner.on_tx_built(tx_hash, FeeTarget::Redeem, 400_000, ex_units);
+        assert_eq!(tuner.min_acceptable_fee(FeeTarget::Redeem), Some(100_000));
+        tuner.on_tx_confirmed(&tx_hash);
+        assert_eq!(tuner.min_acceptable_fee(FeeTarget::Redeem), Some(440_000));
+        assert_eq!(
+            SharedFeeTuner::new(None).min_acceptable_fee(FeeTarget::Redeem),
+            None
+        );
+    }
+}
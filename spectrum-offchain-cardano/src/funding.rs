@@ -2,6 +2,7 @@ use std::fmt::{Debug, Display, Formatter};
 use std::ops::Index;
 
 use cml_chain::address::Address;
+use cml_chain::certs::StakeCredential;
 use derive_more::From;
 
 #[derive(Clone, From)]
@@ -28,4 +29,8 @@ impl<const N: usize> FundingAddresses<N> {
     pub fn partition_by_address(&self, address: &Address) -> Option<usize> {
         self.0.iter().position(|e| e == address)
     }
+
+    pub fn payment_creds(&self) -> impl Iterator<Item = &StakeCredential> {
+        self.0.iter().filter_map(|addr| addr.payment_cred())
+    }
 }
@@ -4,12 +4,19 @@ pub mod creds;
 pub mod data;
 pub mod deployment;
 pub mod event_sink;
+pub mod fee_tuning;
 mod fees;
 pub mod funding;
 pub mod node;
 pub mod parametrized_validators;
+pub mod pnl;
+pub mod pool_creation;
 pub mod pool_math;
 pub mod prover;
 pub mod script;
+pub mod sweep;
+pub mod treasury;
 pub mod tx_submission;
+pub mod tx_validator;
 pub mod utxo;
+pub mod vesting;
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use spectrum_cardano_lib::AssetClass;
+use spectrum_offchain::journal::{
+    compact_pruned, estimated_db_size, open_journal, open_journal_read_only, Journal,
+};
+use spectrum_offchain::rocks::{migrate, Migration, RocksConfig};
+
+/// What caused a movement of operator funds.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum PnlEntryKind {
+    /// Residuals of execution consolidated into the main funding wallet.
+    ResidualSweep,
+}
+
+/// Movement of operator funds made outside of order execution.
+#[derive(Debug, Clone, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PnlEntry {
+    pub kind: PnlEntryKind,
+    /// Hex-encoded hash of the TX which moved the funds.
+    pub tx_hash: String,
+    /// Assets moved.
+    pub assets: Vec<(AssetClass, u64)>,
+    /// Lovelace paid as TX fee.
+    pub tx_fee: u64,
+    /// Unix time (in seconds) the TX was submitted at.
+    pub submitted_at: u64,
+}
+
+/// Migrations of the journal, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+/// Durable journal of operator PnL entries ordered by submission time.
+#[derive(Clone)]
+pub struct PnlJournalRocksDB {
+    db: Arc<rocksdb::DB>,
+}
+
+impl PnlJournalRocksDB {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = open_journal(conf.db_path);
+        migrate(&db, "pnl_journal", MIGRATIONS);
+        Self { db: Arc::new(db) }
+    }
+
+    pub fn read_only(conf: RocksConfig) -> Result<Self, rocksdb::Error> {
+        open_journal_read_only(conf.db_path).map(|db| Self { db: Arc::new(db) })
+    }
+
+    pub fn record(&self, entry: PnlEntry) {
+        let mut key = entry.submitted_at.to_be_bytes().to_vec();
+        key.extend_from_slice(entry.tx_hash.as_bytes());
+        self.db.put(key, bincode::serialize(&entry).unwrap()).unwrap();
+    }
+
+    /// Entries submitted at or after the given unix time.
+    pub fn entries_since(&self, unix_time_secs: u64) -> Vec<PnlEntry> {
+        self.entries_between(unix_time_secs, u64::MAX)
+    }
+}
+
+impl Journal for PnlJournalRocksDB {
+    type Entry = PnlEntry;
+
+    fn entries_between(&self, from: u64, to: u64) -> Vec<PnlEntry> {
+        let from = from.to_be_bytes();
+        self.db
+            .iterator(rocksdb::IteratorMode::From(&from, rocksdb::Direction::Forward))
+            .filter_map(|i| {
+                let (_, v) = i.unwrap();
+                bincode::deserialize::<PnlEntry>(&v).ok()
+            })
+            .take_while(|entry| entry.submitted_at < to)
+            .collect()
+    }
+
+    fn oldest_entry_at(&self) -> Option<u64> {
+        self.db
+            .iterator(rocksdb::IteratorMode::Start)
+            .find_map(|i| {
+                let (_, v) = i.unwrap();
+                bincode::deserialize::<PnlEntry>(&v).ok()
+            })
+            .map(|entry| entry.submitted_at)
+    }
+
+    fn prune_before(&self, time: u64) {
+        let to = time.to_be_bytes();
+        let from = 0u64.to_be_bytes();
+        self.db.delete_range(from, to).unwrap();
+        compact_pruned(&self.db, Some(&from), Some(&to));
+    }
+
+    fn estimated_size(&self) -> u64 {
+        estimated_db_size(&self.db)
+    }
+}
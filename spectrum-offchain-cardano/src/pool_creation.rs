@@ -0,0 +1,469 @@
+use cml_chain::address::Address;
+use cml_chain::assets::MultiAsset;
+use cml_chain::builders::input_builder::SingleInputBuilder;
+use cml_chain::builders::mint_builder::SingleMintBuilder;
+use cml_chain::builders::output_builder::SingleOutputBuilderResult;
+use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
+use cml_chain::plutus::{ConstrPlutusData, PlutusData, PlutusScript, PlutusV2Script, RedeemerTag};
+use cml_chain::transaction::{ConwayFormatTxOut, DatumOption, TransactionOutput};
+use cml_chain::{PolicyId, Value};
+use cml_crypto::{RawBytesEncoding, ScriptHash};
+use derive_more::Display;
+use num_integer::Roots;
+use num_rational::Ratio;
+
+use spectrum_cardano_lib::address::{PlutusCredential, StakingCredential};
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::ex_units::ExUnits;
+use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, min_utxo_lovelace};
+use spectrum_cardano_lib::{AssetClass, AssetName, Token};
+
+use crate::constants::{FEE_DEN, LEGACY_FEE_NUM_MULTIPLIER, MAX_LQ_CAP};
+use crate::data::cfmm_pool::ConstFnPoolVer;
+use crate::data::pool::PoolBounds;
+
+/// Parameters of a constant-product pool to create.
+#[derive(Debug, Clone)]
+pub struct PoolCreationParams {
+    pub ver: ConstFnPoolVer,
+    pub asset_x: AssetClass,
+    pub asset_y: AssetClass,
+    pub reserves_x: u64,
+    pub reserves_y: u64,
+    /// Share of X input left for the swap, in units of [FEE_DEN].
+    pub lp_fee_num_x: u64,
+    /// Share of Y input left for the swap, in units of [FEE_DEN].
+    /// Only fee-switch pools with bidirectional fees allow it to differ from `lp_fee_num_x`.
+    pub lp_fee_num_y: u64,
+    /// Share of input routed to the treasury, in units of [FEE_DEN]. Legacy pools have none.
+    pub treasury_fee_num: u64,
+    /// Pools with less than half of this amount in X reserves are paused. Not part of V1 datum.
+    pub lq_lower_bound: u64,
+    /// Stake admin policies of legacy pools, DAO policies of fee-switch pools.
+    pub admin_policies: Vec<ScriptHash>,
+    /// Script treasury of fee-switch pools is withdrawn to.
+    pub treasury_script: Option<ScriptHash>,
+    /// Acceptable range of the initial price of X in Y, `reserves_y / reserves_x`.
+    pub price_bounds: Option<(Ratio<u128>, Ratio<u128>)>,
+}
+
+/// One-shot minting policy of a pool token, already applied to its parameters.
+#[derive(Debug, Clone)]
+pub struct PoolTokenPolicy {
+    pub script: PlutusV2Script,
+    pub name: AssetName,
+    pub redeemer: PlutusData,
+    pub ex_units: ExUnits,
+}
+
+impl PoolTokenPolicy {
+    pub fn policy_id(&self) -> PolicyId {
+        self.script.hash()
+    }
+
+    pub fn token(&self) -> Token {
+        (self.policy_id(), self.name)
+    }
+}
+
+#[derive(Debug, Display)]
+pub enum PoolCreationError {
+    #[display(fmt = "Pool assets must differ")]
+    IdenticalAssets,
+    #[display(fmt = "Pool NFT and LQ must be minted by different policies")]
+    SharedTokenPolicy,
+    #[display(fmt = "Both reserves must be positive")]
+    EmptyReserves,
+    #[display(fmt = "Fee {}/{} is invalid for pool {:?}", _0, FEE_DEN, _1)]
+    InvalidFee(u64, ConstFnPoolVer),
+    #[display(fmt = "Pool {:?} requires a treasury script", _0)]
+    MissingTreasuryScript(ConstFnPoolVer),
+    #[display(fmt = "Initial price {} of X in Y is out of bounds", _0)]
+    PriceOutOfBounds(Ratio<u128>),
+    #[display(fmt = "Pool would be paused: X reserves {} are below half of LQ lower bound {}", _0, _1)]
+    BelowLqLowerBound(u64, u64),
+    #[display(fmt = "Pool holds {} lovelace while {} is required", _0, _1)]
+    InsufficientLovelace(u64, u64),
+    #[display(fmt = "Failed to build pool creation TX: {}", _0)]
+    TxBuilder(TxBuilderError),
+}
+
+impl From<TxBuilderError> for PoolCreationError {
+    fn from(err: TxBuilderError) -> Self {
+        PoolCreationError::TxBuilder(err)
+    }
+}
+
+/// Checks the given pool can be created and starts out active.
+/// Returns the amount of LQ the creator receives.
+pub fn validate_pool_creation(params: &PoolCreationParams) -> Result<u64, PoolCreationError> {
+    if params.asset_x == params.asset_y {
+        return Err(PoolCreationError::IdenticalAssets);
+    }
+    if params.reserves_x == 0 || params.reserves_y == 0 {
+        return Err(PoolCreationError::EmptyReserves);
+    }
+    for fee in [params.lp_fee_num_x, params.lp_fee_num_y] {
+        if fee == 0 || fee > FEE_DEN || fee < params.treasury_fee_num {
+            return Err(PoolCreationError::InvalidFee(fee, params.ver));
+        }
+    }
+    match params.ver {
+        ConstFnPoolVer::V1 | ConstFnPoolVer::V2 => {
+            // Legacy fees are expressed in units of 1/1000.
+            if params.lp_fee_num_x != params.lp_fee_num_y
+                || params.lp_fee_num_x % LEGACY_FEE_NUM_MULTIPLIER != 0
+            {
+                return Err(PoolCreationError::InvalidFee(params.lp_fee_num_y, params.ver));
+            }
+            if params.treasury_fee_num != 0 {
+                return Err(PoolCreationError::InvalidFee(params.treasury_fee_num, params.ver));
+            }
+        }
+        ConstFnPoolVer::FeeSwitch | ConstFnPoolVer::FeeSwitchV2 => {
+            if params.lp_fee_num_x != params.lp_fee_num_y {
+                return Err(PoolCreationError::InvalidFee(params.lp_fee_num_y, params.ver));
+            }
+        }
+        ConstFnPoolVer::FeeSwitchBiDirFee => {}
+    }
+    if params.ver != ConstFnPoolVer::V1
+        && params.ver != ConstFnPoolVer::V2
+        && params.treasury_script.is_none()
+    {
+        return Err(PoolCreationError::MissingTreasuryScript(params.ver));
+    }
+    let price = Ratio::new(params.reserves_y as u128, params.reserves_x as u128);
+    if let Some((min_price, max_price)) = params.price_bounds {
+        if price < min_price || price > max_price {
+            return Err(PoolCreationError::PriceOutOfBounds(price));
+        }
+    }
+    if (params.reserves_x as u128) * 2 < params.lq_lower_bound as u128 {
+        return Err(PoolCreationError::BelowLqLowerBound(
+            params.reserves_x,
+            params.lq_lower_bound,
+        ));
+    }
+    Ok(initial_liquidity(params.reserves_x, params.reserves_y))
+}
+
+/// LQ emitted to the creator of a pool, `sqrt(reserves_x * reserves_y)`.
+pub fn initial_liquidity(reserves_x: u64, reserves_y: u64) -> u64 {
+    (reserves_x as u128 * reserves_y as u128).sqrt() as u64
+}
+
+/// Datum of a fresh pool in the layout expected by its validator.
+pub fn pool_datum(params: &PoolCreationParams, pool_nft: Token, asset_lq: Token) -> PlutusData {
+    let mut fields = vec![
+        AssetClass::Token(pool_nft).into_pd(),
+        params.asset_x.into_pd(),
+        params.asset_y.into_pd(),
+        AssetClass::Token(asset_lq).into_pd(),
+    ];
+    match params.ver {
+        ConstFnPoolVer::V1 | ConstFnPoolVer::V2 => {
+            fields.push((params.lp_fee_num_x / LEGACY_FEE_NUM_MULTIPLIER).into_pd());
+            fields.push(PlutusData::new_list(
+                params
+                    .admin_policies
+                    .iter()
+                    .map(|policy| PlutusData::new_bytes(policy.to_raw_bytes().to_vec()))
+                    .collect(),
+            ));
+            if params.ver == ConstFnPoolVer::V2 {
+                fields.push(params.lq_lower_bound.into_pd());
+            }
+        }
+        ConstFnPoolVer::FeeSwitch | ConstFnPoolVer::FeeSwitchV2 | ConstFnPoolVer::FeeSwitchBiDirFee => {
+            fields.push(params.lp_fee_num_x.into_pd());
+            if params.ver == ConstFnPoolVer::FeeSwitchBiDirFee {
+                fields.push(params.lp_fee_num_y.into_pd());
+            }
+            fields.push(params.treasury_fee_num.into_pd());
+            // Fresh pools have empty treasury.
+            fields.push(0u64.into_pd());
+            fields.push(0u64.into_pd());
+            fields.push(PlutusData::new_list(
+                params
+                    .admin_policies
+                    .iter()
+                    .map(|policy| StakingCredential::Inline(PlutusCredential::Script(*policy)).into_pd())
+                    .collect(),
+            ));
+            fields.push(params.lq_lower_bound.into_pd());
+            let treasury_script = params.treasury_script.expect("Treasury script is validated");
+            fields.push(PlutusData::new_bytes(treasury_script.to_raw_bytes().to_vec()));
+        }
+    }
+    PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, fields))
+}
+
+/// Output of a fresh pool. Lovelace of T2T pools is set to the maximum of
+/// [PoolBounds::min_t2t_lovelace] and the ledger minimum.
+pub fn pool_output(
+    params: &PoolCreationParams,
+    pool_nft: Token,
+    asset_lq: Token,
+    liquidity: u64,
+    pool_address: Address,
+    bounds: &PoolBounds,
+) -> Result<TransactionOutput, PoolCreationError> {
+    let mut coins = 0;
+    let mut ma = MultiAsset::new();
+    for (asset, amount) in [
+        (params.asset_x, params.reserves_x),
+        (params.asset_y, params.reserves_y),
+        (AssetClass::Token(pool_nft), 1),
+        (AssetClass::Token(asset_lq), MAX_LQ_CAP - liquidity),
+    ] {
+        match asset.into_token() {
+            Some((policy, name)) => {
+                ma.set(policy, name.into(), amount);
+            }
+            None => coins = amount,
+        }
+    }
+    let n2t = params.asset_x.is_native() || params.asset_y.is_native();
+    if n2t && coins < bounds.min_n2t_lovelace {
+        return Err(PoolCreationError::InsufficientLovelace(
+            coins,
+            bounds.min_n2t_lovelace,
+        ));
+    }
+    let mut output = TransactionOutput::new_conway_format_tx_out(ConwayFormatTxOut {
+        address: pool_address,
+        amount: Value::new(coins, ma.clone()),
+        datum_option: Some(DatumOption::new_datum(pool_datum(params, pool_nft, asset_lq))),
+        script_reference: None,
+        encodings: None,
+    });
+    let min_required = min_utxo_lovelace(&output);
+    if n2t {
+        if coins < min_required {
+            return Err(PoolCreationError::InsufficientLovelace(coins, min_required));
+        }
+    } else if let TransactionOutput::ConwayFormatTxOut(out) = &mut output {
+        out.amount = Value::new(bounds.min_t2t_lovelace.max(min_required), ma);
+    }
+    Ok(output)
+}
+
+/// Builds a TX creating a pool at `pool_address`: mints pool NFT and the whole LQ emission,
+/// locks reserves along with the NFT and the unclaimed LQ in the pool.
+/// Reserves and TX fee are covered by `funding`, the one-shot policies are expected to be
+/// parametrized by one of its UTxOs. LQ of the creator goes to `change_address` along with change.
+pub fn build_pool_creation_tx(
+    params: PoolCreationParams,
+    pool_nft: PoolTokenPolicy,
+    pool_lq: PoolTokenPolicy,
+    pool_address: Address,
+    funding: Vec<TransactionUnspentOutput>,
+    collateral: Collateral,
+    bounds: &PoolBounds,
+    change_address: &Address,
+) -> Result<(SignedTxBuilder, u64), PoolCreationError> {
+    if pool_nft.policy_id() == pool_lq.policy_id() {
+        return Err(PoolCreationError::SharedTokenPolicy);
+    }
+    let liquidity = validate_pool_creation(&params)?;
+    let pool_out = pool_output(
+        &params,
+        pool_nft.token(),
+        pool_lq.token(),
+        liquidity,
+        pool_address,
+        bounds,
+    )?;
+
+    let mut tx_builder = constant_tx_builder();
+    tx_builder.add_collateral(collateral.into())?;
+    for utxo in funding {
+        tx_builder.add_input(SingleInputBuilder::new(utxo.input, utxo.output).payment_key()?)?;
+    }
+    // Mint redeemers are indexed in the order of policy IDs.
+    let mut policies = [(pool_nft, 1), (pool_lq, MAX_LQ_CAP)];
+    policies.sort_by_key(|(policy, _)| policy.policy_id());
+    for (ix, (policy, amount)) in policies.into_iter().enumerate() {
+        let witness = PartialPlutusWitness::new(
+            PlutusScriptWitness::Script(PlutusScript::PlutusV2(policy.script)),
+            policy.redeemer,
+        );
+        tx_builder.add_mint(
+            SingleMintBuilder::new_single_asset(policy.name.into(), amount as i64)
+                .plutus_script(witness, vec![]),
+        )?;
+        tx_builder.set_exunits(
+            RedeemerWitnessKey::new(RedeemerTag::Mint, ix as u64),
+            policy.ex_units.into(),
+        );
+    }
+    tx_builder.add_output(SingleOutputBuilderResult::new(pool_out))?;
+    let tx = tx_builder.build(ChangeSelectionAlgo::Default, change_address)?;
+    Ok((tx, liquidity))
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::Credential;
+    use cml_chain::PolicyId;
+    use cml_crypto::ScriptHash;
+    use num_rational::Ratio;
+
+    use spectrum_cardano_lib::types::TryFromPData;
+    use spectrum_cardano_lib::{AssetClass, AssetName};
+
+    use crate::data::cfmm_pool::{ConstFnPoolVer, LegacyCFMMPoolConfig};
+    use crate::data::fee_switch_bidirectional_fee::FeeSwitchBidirectionalPoolConfig;
+    use crate::data::fee_switch_pool::FeeSwitchPoolConfig;
+    use crate::data::pool::PoolBounds;
+    use crate::pool_creation::{
+        initial_liquidity, pool_datum, pool_output, validate_pool_creation, PoolCreationError,
+        PoolCreationParams,
+    };
+
+    const BOUNDS: PoolBounds = PoolBounds {
+        min_n2t_lovelace: 10_000_000,
+        min_t2t_lovelace: 10_000_000,
+        bootstrap_lovelace: 0,
+        bootstrap_liquidity: 0,
+        swap_deposit_surplus: false,
+    };
+
+    fn token(tag: u8, name: &str) -> (PolicyId, AssetName) {
+        (PolicyId::from([tag; 28]), AssetName::utf8_unsafe(name.to_string()))
+    }
+
+    fn params(ver: ConstFnPoolVer) -> PoolCreationParams {
+        PoolCreationParams {
+            ver,
+            asset_x: AssetClass::Native,
+            asset_y: AssetClass::Token(token(1, "y")),
+            reserves_x: 100_000_000,
+            reserves_y: 400_000_000,
+            lp_fee_num_x: 99700,
+            lp_fee_num_y: 99700,
+            treasury_fee_num: 0,
+            lq_lower_bound: 0,
+            admin_policies: vec![ScriptHash::from([2u8; 28])],
+            treasury_script: Some(ScriptHash::from([3u8; 28])),
+            price_bounds: None,
+        }
+    }
+
+    #[test]
+    fn legacy_datum_is_parsed_back() {
+        let mut params = params(ConstFnPoolVer::V2);
+        params.lq_lower_bound = 1000;
+        let datum = pool_datum(&params, token(4, "nft"), token(5, "lq"));
+        let conf = LegacyCFMMPoolConfig::try_from_pd(datum).unwrap();
+        assert_eq!(conf.asset_y.untag(), params.asset_y);
+        assert_eq!(conf.lp_fee_num, 997);
+        assert_eq!(conf.lq_lower_bound.untag(), 1000);
+    }
+
+    #[test]
+    fn fee_switch_datum_is_parsed_back() {
+        let mut params = params(ConstFnPoolVer::FeeSwitchV2);
+        params.treasury_fee_num = 100;
+        let datum = pool_datum(&params, token(4, "nft"), token(5, "lq"));
+        let conf = FeeSwitchPoolConfig::try_from_pd(datum).unwrap();
+        assert_eq!(conf.lp_fee_num, 99700);
+        assert_eq!(conf.treasury_fee_num, 100);
+        assert_eq!((conf.treasury_x, conf.treasury_y), (0, 0));
+    }
+
+    #[test]
+    fn bidir_fee_datum_is_parsed_back() {
+        let mut params = params(ConstFnPoolVer::FeeSwitchBiDirFee);
+        params.lp_fee_num_y = 99000;
+        let datum = pool_datum(&params, token(4, "nft"), token(5, "lq"));
+        let conf = FeeSwitchBidirectionalPoolConfig::try_from_pd(datum).unwrap();
+        assert_eq!((conf.lp_fee_num_x, conf.lp_fee_num_y), (99700, 99000));
+    }
+
+    #[test]
+    fn creator_receives_geometric_mean_of_reserves() {
+        assert_eq!(
+            validate_pool_creation(&params(ConstFnPoolVer::V1)).unwrap(),
+            200_000_000
+        );
+        assert_eq!(initial_liquidity(3, 3), 3);
+    }
+
+    #[test]
+    fn invalid_fees_are_rejected() {
+        let mut legacy = params(ConstFnPoolVer::V2);
+        legacy.lp_fee_num_x = 99750;
+        legacy.lp_fee_num_y = 99750;
+        assert!(matches!(
+            validate_pool_creation(&legacy),
+            Err(PoolCreationError::InvalidFee(..))
+        ));
+        let mut fee_switch = params(ConstFnPoolVer::FeeSwitch);
+        fee_switch.lp_fee_num_y = 99000;
+        assert!(matches!(
+            validate_pool_creation(&fee_switch),
+            Err(PoolCreationError::InvalidFee(..))
+        ));
+        let mut excessive_treasury_fee = params(ConstFnPoolVer::FeeSwitchBiDirFee);
+        excessive_treasury_fee.treasury_fee_num = 99800;
+        assert!(matches!(
+            validate_pool_creation(&excessive_treasury_fee),
+            Err(PoolCreationError::InvalidFee(..))
+        ));
+    }
+
+    #[test]
+    fn fee_switch_pool_requires_treasury_script() {
+        let mut params = params(ConstFnPoolVer::FeeSwitch);
+        params.treasury_script = None;
+        assert!(matches!(
+            validate_pool_creation(&params),
+            Err(PoolCreationError::MissingTreasuryScript(_))
+        ));
+    }
+
+    #[test]
+    fn price_out_of_bounds_is_rejected() {
+        let mut params = params(ConstFnPoolVer::V2);
+        params.price_bounds = Some((Ratio::new(1, 1), Ratio::new(3, 1)));
+        assert!(matches!(
+            validate_pool_creation(&params),
+            Err(PoolCreationError::PriceOutOfBounds(_))
+        ));
+        params.price_bounds = Some((Ratio::new(3, 1), Ratio::new(5, 1)));
+        assert!(validate_pool_creation(&params).is_ok());
+    }
+
+    #[test]
+    fn pool_paused_from_the_start_is_rejected() {
+        let mut params = params(ConstFnPoolVer::V2);
+        params.lq_lower_bound = 300_000_000;
+        assert!(matches!(
+            validate_pool_creation(&params),
+            Err(PoolCreationError::BelowLqLowerBound(..))
+        ));
+    }
+
+    #[test]
+    fn lovelace_bounds_are_respected() {
+        let address = EnterpriseAddress::new(0, Credential::new_script(ScriptHash::from([6u8; 28]))).to_address();
+        let mut n2t = params(ConstFnPoolVer::V2);
+        n2t.reserves_x = 5_000_000;
+        assert!(matches!(
+            pool_output(&n2t, token(4, "nft"), token(5, "lq"), 1, address.clone(), &BOUNDS),
+            Err(PoolCreationError::InsufficientLovelace(..))
+        ));
+        let mut t2t = params(ConstFnPoolVer::V2);
+        t2t.asset_x = AssetClass::Token(token(7, "x"));
+        let out = pool_output(&t2t, token(4, "nft"), token(5, "lq"), 1, address, &BOUNDS).unwrap();
+        assert_eq!(out.amount().coin, BOUNDS.min_t2t_lovelace);
+    }
+}
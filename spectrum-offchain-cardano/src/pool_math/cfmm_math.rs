@@ -1,11 +1,15 @@
 use crate::data::order::{Base, Quote};
 use crate::data::pool::{Lq, Rx, Ry};
 
+use bloom_offchain::execution_engine::liquidity_book::types::{div_rounded, mul_div, Rounding};
 use log::info;
 use num_rational::Ratio;
+use primitive_types::U256;
 use spectrum_cardano_lib::{TaggedAmount, TaggedAssetClass};
 use std::cmp::min;
 
+/// Quote output of a swap through constant product pool.
+/// Rounded down so that the pool validator never sees more than `y * in * fee / (x + in * fee)` taken.
 pub fn classic_cfmm_output_amount<X, Y>(
     asset_x: TaggedAssetClass<X>,
     reserves_x: TaggedAmount<X>,
@@ -15,18 +19,22 @@ pub fn classic_cfmm_output_amount<X, Y>(
     pool_fee_x: Ratio<u64>,
     pool_fee_y: Ratio<u64>,
 ) -> TaggedAmount<Quote> {
-    let quote_amount = if base_asset.untag() == asset_x.untag() {
-        (reserves_y.untag() as u128) * (base_amount.untag() as u128) * (*pool_fee_x.numer() as u128)
-            / ((reserves_x.untag() as u128) * (*pool_fee_x.denom() as u128)
-                + (base_amount.untag() as u128) * (*pool_fee_x.numer() as u128))
+    let (reserves_in, reserves_out, fee) = if base_asset.untag() == asset_x.untag() {
+        (reserves_x.untag(), reserves_y.untag(), pool_fee_x)
     } else {
-        (reserves_x.untag() as u128) * (base_amount.untag() as u128) * (*pool_fee_y.numer() as u128)
-            / ((reserves_y.untag() as u128) * (*pool_fee_y.denom() as u128)
-                + (base_amount.untag() as u128) * (*pool_fee_y.numer() as u128))
+        (reserves_y.untag(), reserves_x.untag(), pool_fee_y)
     };
-    TaggedAmount::new(quote_amount as u64)
+    let base_amount = U256::from(base_amount.untag());
+    let quote_amount = div_rounded(
+        U256::from(reserves_out) * base_amount * U256::from(*fee.numer()),
+        U256::from(reserves_in) * U256::from(*fee.denom()) + base_amount * U256::from(*fee.numer()),
+        Rounding::Floor,
+    );
+    TaggedAmount::new(quote_amount.unwrap_or(0))
 }
 
+/// LQ minted for the given deposit along with the change returned to the depositor.
+/// Both LQ and change are rounded down, which keeps the deposit on the pool's side.
 pub fn classic_cfmm_reward_lp(
     reserves_x: TaggedAmount<Rx>,
     reserves_y: TaggedAmount<Ry>,
@@ -34,28 +42,42 @@ pub fn classic_cfmm_reward_lp(
     in_x_amount: u64,
     in_y_amount: u64,
 ) -> Option<(TaggedAmount<Lq>, TaggedAmount<Rx>, TaggedAmount<Ry>)> {
-    let min_by_x =
-        ((in_x_amount as u128) * (liquidity.untag() as u128)).checked_div(reserves_x.untag() as u128)?;
-    let min_by_y =
-        ((in_y_amount as u128) * (liquidity.untag() as u128)).checked_div(reserves_y.untag() as u128)?;
-    let (change_by_x, change_by_y): (u64, u64) = if min_by_x == min_by_y {
+    let min_by_x = mul_div(
+        in_x_amount,
+        liquidity.untag() as u128,
+        reserves_x.untag() as u128,
+        Rounding::Floor,
+    )?;
+    let min_by_y = mul_div(
+        in_y_amount,
+        liquidity.untag() as u128,
+        reserves_y.untag() as u128,
+        Rounding::Floor,
+    )?;
+    let (change_by_x, change_by_y) = if min_by_x == min_by_y {
         (0, 0)
+    } else if min_by_x < min_by_y {
+        (
+            0,
+            mul_div(
+                min_by_y - min_by_x,
+                reserves_y.untag() as u128,
+                liquidity.untag() as u128,
+                Rounding::Floor,
+            )?,
+        )
     } else {
-        if min_by_x < min_by_y {
-            (
-                0,
-                (((min_by_y - min_by_x) * (reserves_y.untag() as u128))
-                    .checked_div(liquidity.untag() as u128))? as u64,
-            )
-        } else {
-            (
-                (((min_by_x - min_by_y) * (reserves_x.untag() as u128))
-                    .checked_div(liquidity.untag() as u128))? as u64,
-                0,
-            )
-        }
+        (
+            mul_div(
+                min_by_x - min_by_y,
+                reserves_x.untag() as u128,
+                liquidity.untag() as u128,
+                Rounding::Floor,
+            )?,
+            0,
+        )
     };
-    let unlocked_lq = min(min_by_x, min_by_y) as u64;
+    let unlocked_lq = min(min_by_x, min_by_y);
     Some((
         TaggedAmount::new(unlocked_lq),
         TaggedAmount::new(change_by_x),
@@ -63,19 +85,165 @@ pub fn classic_cfmm_reward_lp(
     ))
 }
 
+/// Part of the deposit `surplus` to swap into the pool so that the rest of it and the swap output
+/// match the ratio of reserves after the swap.
+/// Solves `g*s^2 + (g + d)*X*s - d*X*a = 0` for `s`, where `g/d` is the pool fee, `X` are reserves
+/// of the surplus asset and `a` is the surplus. Rounded down.
+pub fn classic_cfmm_surplus_swap_amount(reserves_in: u64, surplus: u64, fee: Ratio<u64>) -> Option<u64> {
+    let g = U256::from(*fee.numer());
+    let d = U256::from(*fee.denom());
+    if g.is_zero() {
+        return None;
+    }
+    let x = U256::from(reserves_in);
+    let a = U256::from(surplus);
+    let b = x * (g + d);
+    let discriminant = b * b + U256::from(4) * g * d * x * a;
+    let swap_amount = discriminant.integer_sqrt().checked_sub(b)? / (U256::from(2) * g);
+    (swap_amount <= a).then(|| swap_amount.as_u64())
+}
+
+/// Reserves released in exchange for `burned_lq`, rounded down.
 pub fn classic_cfmm_shares_amount(
     reserves_x: TaggedAmount<Rx>,
     reserves_y: TaggedAmount<Ry>,
     liquidity: TaggedAmount<Lq>,
     burned_lq: TaggedAmount<Lq>,
 ) -> Option<(TaggedAmount<Rx>, TaggedAmount<Ry>)> {
-    let x_amount =
-        ((burned_lq.untag() as u128) * (reserves_x.untag() as u128)).checked_div(liquidity.untag() as u128)?;
-    let y_amount =
-        ((burned_lq.untag() as u128) * (reserves_y.untag() as u128)).checked_div(liquidity.untag() as u128)?;
+    let x_amount = mul_div(
+        burned_lq.untag(),
+        reserves_x.untag() as u128,
+        liquidity.untag() as u128,
+        Rounding::Floor,
+    )?;
+    let y_amount = mul_div(
+        burned_lq.untag(),
+        reserves_y.untag() as u128,
+        liquidity.untag() as u128,
+        Rounding::Floor,
+    )?;
+    Some((TaggedAmount::new(x_amount), TaggedAmount::new(y_amount)))
+}
 
-    Some((
-        TaggedAmount::new(x_amount as u64),
-        TaggedAmount::new(y_amount as u64),
-    ))
+#[cfg(test)]
+mod tests {
+    use num_rational::Ratio;
+    use primitive_types::U256;
+    use spectrum_cardano_lib::{AssetClass, TaggedAmount, TaggedAssetClass};
+
+    use crate::data::pool::Rx;
+    use crate::pool_math::cfmm_math::{
+        classic_cfmm_output_amount, classic_cfmm_reward_lp, classic_cfmm_shares_amount,
+        classic_cfmm_surplus_swap_amount,
+    };
+
+    const RESERVES: [u64; 4] = [1, 997, 1_000_000_007, u64::MAX / 3];
+    const AMOUNTS: [u64; 4] = [1, 13, 999_999_937, u32::MAX as u64];
+
+    /// Swap condition checked by the pool validator.
+    fn swap_is_valid(rx: u64, ry: u64, base_in: u64, quote_out: u64, fee: Ratio<u64>) -> bool {
+        let (rx, ry, base_in, quote_out) = (
+            U256::from(rx),
+            U256::from(ry),
+            U256::from(base_in),
+            U256::from(quote_out),
+        );
+        let (fee_num, fee_den) = (U256::from(*fee.numer()), U256::from(*fee.denom()));
+        ry * base_in * fee_num >= quote_out * (rx * fee_den + base_in * fee_num)
+    }
+
+    #[test]
+    fn swap_output_is_max_accepted_by_validator() {
+        let fee = Ratio::new(997, 1000);
+        let x = TaggedAssetClass::<Rx>::new(AssetClass::Native);
+        for rx in RESERVES {
+            for ry in RESERVES {
+                for base_in in AMOUNTS {
+                    let out = classic_cfmm_output_amount(
+                        x,
+                        TaggedAmount::new(rx),
+                        TaggedAmount::new(ry),
+                        TaggedAssetClass::new(AssetClass::Native),
+                        TaggedAmount::new(base_in),
+                        fee,
+                        fee,
+                    )
+                    .untag();
+                    assert!(swap_is_valid(rx, ry, base_in, out, fee));
+                    assert!(!swap_is_valid(rx, ry, base_in, out + 1, fee));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn redeem_never_releases_more_than_share() {
+        for rx in RESERVES {
+            for liquidity in RESERVES {
+                for burned in AMOUNTS.into_iter().filter(|lq| *lq <= liquidity) {
+                    let (out_x, _) = classic_cfmm_shares_amount(
+                        TaggedAmount::new(rx),
+                        TaggedAmount::new(rx),
+                        TaggedAmount::new(liquidity),
+                        TaggedAmount::new(burned),
+                    )
+                    .unwrap();
+                    let share = U256::from(burned) * U256::from(rx);
+                    assert!(U256::from(out_x.untag()) * U256::from(liquidity) <= share);
+                    assert!(U256::from(out_x.untag() + 1) * U256::from(liquidity) > share);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn deposit_never_mints_more_than_share() {
+        for (rx, ry) in RESERVES.into_iter().zip(RESERVES.into_iter().rev()) {
+            for liquidity in RESERVES {
+                for (in_x, in_y) in AMOUNTS.into_iter().zip(AMOUNTS.into_iter().rev()) {
+                    let Some((lq, change_x, change_y)) = classic_cfmm_reward_lp(
+                        TaggedAmount::new(rx),
+                        TaggedAmount::new(ry),
+                        TaggedAmount::new(liquidity),
+                        in_x,
+                        in_y,
+                    ) else {
+                        // LQ to be minted overflows.
+                        continue;
+                    };
+                    let lq = U256::from(lq.untag());
+                    let deposited_x = U256::from(in_x - change_x.untag());
+                    let deposited_y = U256::from(in_y - change_y.untag());
+                    assert!(lq * U256::from(rx) <= deposited_x * U256::from(liquidity));
+                    assert!(lq * U256::from(ry) <= deposited_y * U256::from(liquidity));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn surplus_swap_amount_is_max_root() {
+        let fee = Ratio::new(997, 1000);
+        // g*s^2 + (g + d)*X*s - d*X*a
+        let lhs = |x: u64, a: u64, s: u64| {
+            let (g, d, x, a, s) = (
+                U256::from(997),
+                U256::from(1000),
+                U256::from(x),
+                U256::from(a),
+                U256::from(s),
+            );
+            (g * s * s + (g + d) * x * s, d * x * a)
+        };
+        for rx in RESERVES {
+            for surplus in AMOUNTS {
+                let s = classic_cfmm_surplus_swap_amount(rx, surplus, fee).unwrap();
+                assert!(s <= surplus);
+                let (at_s, rhs) = lhs(rx, surplus, s);
+                let (at_next, _) = lhs(rx, surplus, s + 1);
+                assert!(at_s <= rhs);
+                assert!(at_next > rhs);
+            }
+        }
+    }
 }
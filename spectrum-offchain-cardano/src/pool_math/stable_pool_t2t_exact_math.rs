@@ -383,6 +383,7 @@ mod test {
                 mem: 120000000,
                 steps: 100000000000,
             },
+            bootstrap_liquidity: 0,
         };
     }
 
@@ -9,19 +9,26 @@ use spectrum_offchain::tx_prover::TxProver;
 
 /// Signs transactions on behalf of operator.
 #[derive(Copy, Clone)]
-pub struct OperatorProver<'a>(&'a PrivateKey);
+pub struct OperatorProver<'a>(&'a [PrivateKey]);
 
 impl<'a> OperatorProver<'a> {
     pub fn new(sk: &'a PrivateKey) -> Self {
-        Self(sk)
+        Self(std::slice::from_ref(sk))
+    }
+
+    /// Sign with each of the given keys, e.g. both the current and the retiring one.
+    pub fn with_keys(sks: &'a [PrivateKey]) -> Self {
+        Self(sks)
     }
 }
 
 impl<'a> TxProver<SignedTxBuilder, OutboundTransaction<Transaction>> for OperatorProver<'a> {
     fn prove(&self, mut candidate: SignedTxBuilder) -> OutboundTransaction<Transaction> {
         let body = candidate.body();
-        let signature = make_vkey_witness(&hash_transaction_canonical(&body), self.0);
-        candidate.add_vkey(signature);
+        let tx_hash = hash_transaction_canonical(&body);
+        for sk in self.0 {
+            candidate.add_vkey(make_vkey_witness(&tx_hash, sk));
+        }
         candidate.build_unchecked().into()
     }
 }
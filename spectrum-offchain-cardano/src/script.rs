@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 
-use cml_chain::plutus::PlutusData;
+use cml_chain::builders::input_builder::SingleInputBuilder;
+use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
+use cml_chain::builders::tx_builder::{TransactionBuilder, TransactionUnspentOutput, TxBuilderError};
+use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
+use cml_chain::plutus::{PlutusData, RedeemerTag};
+use cml_chain::transaction::RequiredSigners;
 use cml_crypto::ScriptHash;
 
 use spectrum_cardano_lib::ex_units::ExUnits;
@@ -73,3 +78,130 @@ pub fn ready_redeemer(r: PlutusData) -> DelayedRedeemer {
 pub fn delayed_redeemer(f: impl FnOnce(&TxInputsOrdering) -> PlutusData + 'static) -> DelayedRedeemer {
     DelayedRedeemer::Delayed(Box::new(f))
 }
+
+/// Script UTxO spent by a TX along with everything needed to witness it.
+pub struct ScriptInput {
+    pub utxo: TransactionUnspentOutput,
+    pub script: ScriptHash,
+    /// Redeemer referring to positions of inputs must be [DelayedRedeemer::Delayed],
+    /// so that indices are only ever taken from the final ordering.
+    pub redeemer: DelayedRedeemer,
+    pub ex_units: cml_chain::plutus::ExUnits,
+    pub required_signers: RequiredSigners,
+}
+
+/// Inputs of a TX assembled at once.
+/// The ledger orders inputs canonically (lexicographically by output ref) regardless of
+/// the order they were added in, so redeemers are computed only after the whole set is known.
+#[derive(Default)]
+pub struct TxInputs {
+    script_inputs: Vec<ScriptInput>,
+    key_inputs: Vec<TransactionUnspentOutput>,
+}
+
+impl TxInputs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_script_input(&mut self, input: ScriptInput) {
+        self.script_inputs.push(input);
+    }
+
+    pub fn add_key_input(&mut self, utxo: TransactionUnspentOutput) {
+        self.key_inputs.push(utxo);
+    }
+
+    /// Canonical ordering of all inputs added so far.
+    pub fn ordering(&self) -> TxInputsOrdering {
+        let mut refs = self
+            .script_inputs
+            .iter()
+            .map(|i| OutputRef::from(i.utxo.input.clone()))
+            .chain(self.key_inputs.iter().map(|i| OutputRef::from(i.input.clone())))
+            .collect::<Vec<_>>();
+        refs.sort();
+        TxInputsOrdering::new(refs.into_iter().enumerate().map(|(ix, r)| (r, ix)).collect())
+    }
+
+    /// Add all inputs to the builder computing redeemers and assigning ex units against the final ordering.
+    /// The ordering is returned for other redeemers (e.g. minting ones) to refer to inputs.
+    pub fn project_onto(self, txb: &mut TransactionBuilder) -> Result<TxInputsOrdering, TxBuilderError> {
+        let ordering = self.ordering();
+        for ScriptInput {
+            utxo,
+            script,
+            redeemer,
+            ex_units,
+            required_signers,
+        } in self.script_inputs
+        {
+            let ix = ordering.index_of(&OutputRef::from(utxo.input.clone()));
+            let witness =
+                PartialPlutusWitness::new(PlutusScriptWitness::Ref(script), redeemer.compute(&ordering));
+            let input = SingleInputBuilder::new(utxo.input, utxo.output)
+                .plutus_script_inline_datum(witness, required_signers)?;
+            txb.add_input(input)?;
+            txb.set_exunits(RedeemerWitnessKey::new(RedeemerTag::Spend, ix as u64), ex_units);
+        }
+        for utxo in self.key_inputs {
+            txb.add_input(SingleInputBuilder::new(utxo.input, utxo.output).payment_key()?)?;
+        }
+        Ok(ordering)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::plutus::{ExUnits, PlutusData};
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::utils::BigInteger;
+    use cml_chain::Value;
+    use cml_crypto::{Ed25519KeyHash, ScriptHash, TransactionHash};
+
+    use spectrum_cardano_lib::OutputRef;
+
+    use crate::script::{delayed_redeemer, ScriptInput, TxInputs};
+
+    fn utxo(tx_hash: u8, ix: u64) -> TransactionUnspentOutput {
+        let addr = EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([0u8; 28])))
+            .to_address();
+        TransactionUnspentOutput::new(
+            TransactionInput::new(TransactionHash::from([tx_hash; 32]), ix),
+            TransactionOutput::new(addr, Value::from(1_000_000), None, None),
+        )
+    }
+
+    #[test]
+    fn inputs_are_ordered_canonically() {
+        let pool = utxo(2, 0);
+        let order = utxo(1, 1);
+        let funding = utxo(1, 0);
+        let pool_ref = OutputRef::from(pool.input.clone());
+        let mut inputs = TxInputs::new();
+        inputs.add_script_input(ScriptInput {
+            utxo: pool,
+            script: ScriptHash::from([3u8; 28]),
+            redeemer: delayed_redeemer(move |ordering| {
+                PlutusData::Integer(BigInteger::from(ordering.index_of(&pool_ref) as u64))
+            }),
+            ex_units: ExUnits::new(0, 0),
+            required_signers: vec![],
+        });
+        inputs.add_script_input(ScriptInput {
+            utxo: order.clone(),
+            script: ScriptHash::from([4u8; 28]),
+            redeemer: delayed_redeemer(|_| PlutusData::new_list(vec![])),
+            ex_units: ExUnits::new(0, 0),
+            required_signers: vec![],
+        });
+        inputs.add_key_input(funding.clone());
+        let ordering = inputs.ordering();
+        assert_eq!(ordering.index_of(&OutputRef::from(funding.input)), 0);
+        assert_eq!(ordering.index_of(&OutputRef::from(order.input)), 1);
+        assert_eq!(ordering.index_of(&pool_ref), 2);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use cml_chain::address::Address;
+use cml_chain::builders::input_builder::SingleInputBuilder;
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::Coin;
+use cml_crypto::RawBytesEncoding;
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::{info, trace, warn};
+
+use cardano_explorer::CardanoNetwork;
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::protocol_params::constant_tx_builder;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::{AssetClass, OutputRef};
+use spectrum_offchain::clock::{Clock, SharedClock};
+use spectrum_offchain::data::Has;
+use spectrum_offchain::network::Network;
+use spectrum_offchain::tx_prover::TxProver;
+
+use crate::pnl::{PnlEntry, PnlEntryKind, PnlJournalRocksDB};
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ResidualSweepConfig {
+    pub poll_interval_secs: u64,
+    /// UTxOs holding less lovelace than this, as well as ones holding any tokens, are residuals.
+    pub dust_lovelace: Coin,
+    /// Minimal lovelace held by residuals in total which justifies a sweep.
+    pub min_sweep_lovelace: Coin,
+    /// Maximal number of UTxOs consumed by one sweep.
+    pub max_inputs: usize,
+}
+
+/// Residuals worth sweeping, if any.
+/// The `excluded` UTxO (e.g. collateral) is never selected.
+pub fn select_residuals(
+    utxos: Vec<TransactionUnspentOutput>,
+    excluded: OutputRef,
+    conf: &ResidualSweepConfig,
+) -> Vec<TransactionUnspentOutput> {
+    let residuals = utxos
+        .into_iter()
+        .filter(|u| {
+            OutputRef::from(u.input.clone()) != excluded
+                && (u.output.amount().has_multiassets() || u.output.value().coin < conf.dust_lovelace)
+        })
+        .take(conf.max_inputs)
+        .collect::<Vec<_>>();
+    let total_lovelace = residuals.iter().map(|u| u.output.value().coin).sum::<u64>();
+    if total_lovelace >= conf.min_sweep_lovelace {
+        residuals
+    } else {
+        vec![]
+    }
+}
+
+/// Builds a TX consolidating all given residuals into a single UTxO at `destination`.
+/// Returns the TX along with the assets it moves.
+pub fn build_sweep_tx(
+    residuals: Vec<TransactionUnspentOutput>,
+    destination: Address,
+) -> Result<(SignedTxBuilder, Vec<(AssetClass, u64)>), TxBuilderError> {
+    let mut swept = BTreeMap::<AssetClass, u64>::new();
+    let mut tx_builder = constant_tx_builder();
+    for utxo in residuals {
+        let value = utxo.output.value();
+        *swept.entry(AssetClass::Native).or_default() += value.coin;
+        for (policy, assets) in value.multiasset.iter() {
+            for (an, amount) in assets.iter() {
+                *swept
+                    .entry(AssetClass::Token((*policy, an.clone().into())))
+                    .or_default() += *amount;
+            }
+        }
+        tx_builder.add_input(SingleInputBuilder::new(utxo.input, utxo.output).payment_key()?)?;
+    }
+    // Everything but TX fee goes to the destination as change.
+    let tx = tx_builder.build(ChangeSelectionAlgo::Default, &destination)?;
+    Ok((tx, swept.into_iter().collect()))
+}
+
+const RESIDUALS_LOOKUP_LIMIT: u16 = 100;
+
+/// Periodically consolidates residuals accumulated at `sources` into `destination`.
+/// Each sweep is recorded in the PnL journal.
+pub fn residual_sweep_stream<'a, Net, Explorer, Prover, Tx, Err, Ctx>(
+    sources: Vec<Address>,
+    destination: Address,
+    explorer: Explorer,
+    network: Net,
+    prover: Prover,
+    journal: PnlJournalRocksDB,
+    conf: ResidualSweepConfig,
+    ctx: Ctx,
+) -> impl Stream<Item = ()> + 'a
+where
+    Explorer: CardanoNetwork + 'a,
+    Net: Network<Tx, Err> + 'a,
+    Prover: TxProver<SignedTxBuilder, Tx> + 'a,
+    Err: std::fmt::Debug,
+    Ctx: Has<Collateral> + Has<SharedClock> + 'a,
+{
+    let interval = Duration::from_secs(conf.poll_interval_secs);
+    stream::unfold(
+        (explorer, network, prover, journal, ctx),
+        move |(explorer, mut network, prover, journal, ctx)| {
+            let sources = sources.clone();
+            let destination = destination.clone();
+            let conf = conf.clone();
+            async move {
+                Delay::new(interval).await;
+                let mut utxos = vec![];
+                for address in sources {
+                    utxos.extend(
+                        explorer
+                            .utxos_by_address(address, 0, RESIDUALS_LOOKUP_LIMIT)
+                            .await,
+                    );
+                }
+                let residuals = select_residuals(utxos, ctx.select::<Collateral>().reference(), &conf);
+                if residuals.is_empty() {
+                    trace!("No residuals worth sweeping");
+                } else {
+                    let num_residuals = residuals.len();
+                    match build_sweep_tx(residuals, destination) {
+                        Ok((tx_candidate, assets)) => {
+                            let tx_hash = hash_transaction_canonical(&tx_candidate.body());
+                            let tx_fee = tx_candidate.body().fee;
+                            let tx = prover.prove(tx_candidate);
+                            match network.submit_tx(tx).await {
+                                Ok(()) => {
+                                    info!("Swept {} residual UTxOs in TX {}", num_residuals, tx_hash);
+                                    journal.record(PnlEntry {
+                                        kind: PnlEntryKind::ResidualSweep,
+                                        tx_hash: tx_hash.to_hex(),
+                                        assets,
+                                        tx_fee,
+                                        submitted_at: ctx.select::<SharedClock>().unix_time_secs(),
+                                    });
+                                }
+                                Err(err) => warn!("Failed to submit residual sweep TX: {:?}", err),
+                            }
+                        }
+                        Err(err) => warn!("Failed to build residual sweep TX: {}", err),
+                    }
+                }
+                Some(((), (explorer, network, prover, journal, ctx)))
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::assets::MultiAsset;
+    use cml_chain::builders::tx_builder::TransactionUnspentOutput;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::{TransactionInput, TransactionOutput};
+    use cml_chain::{PolicyId, Value};
+    use cml_crypto::{Ed25519KeyHash, TransactionHash};
+
+    use spectrum_cardano_lib::{AssetName, OutputRef};
+
+    use crate::sweep::{select_residuals, ResidualSweepConfig};
+
+    const CONF: ResidualSweepConfig = ResidualSweepConfig {
+        poll_interval_secs: 60,
+        dust_lovelace: 2_000_000,
+        min_sweep_lovelace: 3_000_000,
+        max_inputs: 10,
+    };
+
+    fn utxo(ix: u64, value: Value) -> TransactionUnspentOutput {
+        let address =
+            EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([1u8; 28])))
+                .to_address();
+        TransactionUnspentOutput::new(
+            TransactionInput::new(TransactionHash::from([0xaa; 32]), ix),
+            TransactionOutput::new(address, value, None, None),
+        )
+    }
+
+    fn with_token(lovelace: u64) -> Value {
+        let mut ma = MultiAsset::new();
+        ma.set(
+            PolicyId::from([2u8; 28]),
+            AssetName::from((4, [1u8; 32])).into(),
+            10,
+        );
+        Value::new(lovelace, ma)
+    }
+
+    #[test]
+    fn dust_and_tokens_are_swept_once_above_threshold() {
+        let collateral = utxo(0, Value::from(1_500_000));
+        let excluded = OutputRef::from(collateral.input.clone());
+        let utxos = vec![
+            collateral,
+            utxo(1, Value::from(50_000_000)),
+            utxo(2, Value::from(1_200_000)),
+            utxo(3, with_token(1_900_000)),
+        ];
+        let swept = select_residuals(utxos.clone(), excluded, &CONF)
+            .into_iter()
+            .map(|u| u.input.index)
+            .collect::<Vec<_>>();
+        assert_eq!(swept, vec![2, 3]);
+        assert!(select_residuals(utxos[..3].to_vec(), excluded, &CONF).is_empty());
+    }
+}
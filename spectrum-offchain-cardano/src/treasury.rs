@@ -0,0 +1,533 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use cml_chain::address::{Address, RewardAddress};
+use cml_chain::assets::MultiAsset;
+use cml_chain::builders::input_builder::{InputBuilderError, SingleInputBuilder};
+use cml_chain::builders::output_builder::{
+    OutputBuilderError, SingleOutputBuilderResult, TransactionOutputBuilder,
+};
+use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::builders::withdrawal_builder::{SingleWithdrawalBuilder, WithdrawalBuilderError};
+use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
+use cml_chain::certs::Credential;
+use cml_chain::plutus::{ConstrPlutusData, PlutusData, RedeemerTag};
+use cml_chain::transaction::TransactionOutput;
+use cml_chain::{Coin, Value};
+use cml_crypto::ScriptHash;
+use either::Either;
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::{info, trace, warn};
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use bloom_offchain::execution_engine::Event;
+use cardano_explorer::CardanoNetwork;
+use spectrum_cardano_lib::address::{PlutusCredential, StakingCredential};
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::plutus_data::DatumUpdateError;
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, COINS_PER_UTXO_BYTE};
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::types::TryFromPData;
+use spectrum_cardano_lib::{NetworkId, OutputRef, TaggedAmount};
+use spectrum_offchain::combinators::Ior;
+use spectrum_offchain::data::event::{Channel, Confirmed, Predicted, StateUpdate};
+use spectrum_offchain::data::Has;
+use spectrum_offchain::ledger::TryIntoLedger;
+use spectrum_offchain::network::Network;
+use spectrum_offchain::tx_prover::TxProver;
+
+use crate::constants::MIN_SAFE_LOVELACE_VALUE;
+use crate::creds::OperatorRewardAddress;
+use crate::data::cfmm_pool::{ConstFnPool, ConstFnPoolVer};
+use crate::data::fee_switch_pool::FeeSwitchPoolConfig;
+use crate::data::pool::{AnyPool, CFMMPoolAction, ImmutablePoolUtxo, RequiresRedeemer};
+use crate::data::PoolId;
+use crate::deployment::ProtocolValidator::{
+    ConstFnPoolFeeSwitch, ConstFnPoolFeeSwitchBiDirFee, ConstFnPoolFeeSwitchV2, ConstFnPoolV1, ConstFnPoolV2,
+    DaoAuthority,
+};
+use crate::deployment::{DeployedValidator, RequiresValidator};
+
+/// Address accumulated protocol fees are routed to.
+#[derive(serde::Deserialize, Debug, Clone, derive_more::Into, derive_more::From)]
+pub struct TreasuryAddress(pub Address);
+
+/// Minimal accumulated amounts of each pool asset which justify a withdrawal.
+#[derive(serde::Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TreasuryThreshold {
+    pub x: u64,
+    pub y: u64,
+}
+
+#[derive(serde::Deserialize, Debug, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TreasuryWithdrawalConfig {
+    pub poll_interval_secs: u64,
+    /// Minimal lovelace amount of an operator UTxO to fund withdrawal TXs with.
+    pub min_funding_lovelace: Coin,
+    pub threshold: TreasuryThreshold,
+}
+
+#[derive(Debug, derive_more::From)]
+pub enum TreasuryWithdrawalError {
+    /// Pool doesn't delegate governance actions to the configured DAO authority.
+    NotAuthorized,
+    TxBuilder(TxBuilderError),
+    InputBuilder(InputBuilderError),
+    OutputBuilder(OutputBuilderError),
+    WithdrawalBuilder(WithdrawalBuilderError),
+    /// Pool datum cannot be updated to reflect withdrawn treasury.
+    Datum(DatumUpdateError),
+}
+
+/// Only pools whose datum layout is known to [ConstFnPool::try_into_ledger] are eligible.
+fn supports_withdrawal(pool: &ConstFnPool) -> bool {
+    matches!(pool.ver, ConstFnPoolVer::FeeSwitch | ConstFnPoolVer::FeeSwitchV2)
+}
+
+/// Whether the treasury accumulated in the given pool is worth withdrawing.
+pub fn withdrawal_due(pool: &ConstFnPool, threshold: TreasuryThreshold) -> bool {
+    let tx = pool.treasury_x.untag();
+    let ty = pool.treasury_y.untag();
+    supports_withdrawal(pool) && (tx > 0 || ty > 0) && (tx >= threshold.x || ty >= threshold.y)
+}
+
+/// Whether the given DAO authority is among DAO policies listed in the datum of the pool.
+pub fn authorized_by(pool_utxo: &TransactionOutput, authority: ScriptHash) -> bool {
+    pool_utxo
+        .inline_datum()
+        .and_then(|pd| FeeSwitchPoolConfig::try_from_pd(pd.clone()))
+        .is_some_and(|conf| {
+            conf.dao_policy
+                .contains(&StakingCredential::Inline(PlutusCredential::Script(authority)))
+        })
+}
+
+/// Latest confirmed states of fee-switch pools.
+/// Shared between upstream observers and the withdrawal routine.
+#[derive(Debug, Clone, Default)]
+pub struct TreasuryWatch(Arc<Mutex<HashMap<PoolId, Bundled<ConstFnPool, FinalizedTxOut>>>>);
+
+impl TreasuryWatch {
+    pub fn observe<CO, SO>(&self, event: &Event<CO, SO, AnyPool, FinalizedTxOut, OutputRef>) {
+        let Either::Left(Channel::Ledger(Confirmed(
+            StateUpdate::Transition(tr) | StateUpdate::TransitionRollback(tr),
+        ))) = event
+        else {
+            return;
+        };
+        let mut pools = self.0.lock().unwrap();
+        match tr {
+            Ior::Both(_, Bundled(Either::Right(next), bearer))
+            | Ior::Right(Bundled(Either::Right(next), bearer)) => {
+                if let AnyPool::PureCFMM(pool) = &next.entity {
+                    if supports_withdrawal(pool) {
+                        pools.insert(pool.id, Bundled(pool.clone(), bearer.clone()));
+                    }
+                }
+            }
+            Ior::Left(Bundled(Either::Right(prev), _)) => {
+                if let AnyPool::PureCFMM(pool) = &prev.entity {
+                    pools.remove(&pool.id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Pools whose treasury is worth withdrawing.
+    pub fn due(&self, threshold: TreasuryThreshold) -> Vec<Bundled<ConstFnPool, FinalizedTxOut>> {
+        self.0
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|Bundled(pool, _)| withdrawal_due(pool, threshold))
+            .cloned()
+            .collect()
+    }
+
+    /// Stop tracking the pool until its next confirmed state is observed.
+    pub fn forget(&self, pool: &PoolId) {
+        self.0.lock().unwrap().remove(pool);
+    }
+}
+
+/// Builds a TX withdrawing the whole treasury of the given pool to [TreasuryAddress].
+/// TX fee and min-ADA of the treasury output are covered by the `funding` UTxO,
+/// change goes back to [OperatorRewardAddress].
+/// Pool validator delegates [CFMMPoolAction::DAOAction] to the DAO policies listed in its datum,
+/// so the TX invokes the configured DAO authority via a zero withdrawal from its reward account.
+/// Pools not governed by that authority are rejected.
+pub fn build_treasury_withdrawal_tx<Ctx>(
+    Bundled(pool, FinalizedTxOut(pool_utxo, pool_ref)): Bundled<ConstFnPool, FinalizedTxOut>,
+    funding: TransactionUnspentOutput,
+    ctx: Ctx,
+) -> Result<(SignedTxBuilder, Predicted<Bundled<ConstFnPool, FinalizedTxOut>>), TreasuryWithdrawalError>
+where
+    ConstFnPool: RequiresValidator<Ctx>,
+    Ctx: Has<Collateral>
+        + Has<OperatorRewardAddress>
+        + Has<TreasuryAddress>
+        + Has<NetworkId>
+        + Has<DeployedValidator<{ DaoAuthority as u8 }>>,
+{
+    let authority = ctx.select::<DeployedValidator<{ DaoAuthority as u8 }>>();
+    if !authorized_by(&pool_utxo, authority.hash) {
+        return Err(TreasuryWithdrawalError::NotAuthorized);
+    }
+    let funding_ref = OutputRef::from(funding.input.clone());
+    info!(target: "offchain", "Withdrawing treasury of pool {}", pool.id);
+
+    let mut sorted_inputs = [pool_ref, funding_ref];
+    sorted_inputs.sort();
+    let pool_in_idx = if sorted_inputs[0] == pool_ref { 0u64 } else { 1u64 };
+
+    let mut next_pool = pool.clone();
+    next_pool.reserves_x = next_pool.reserves_x - pool.treasury_x;
+    next_pool.reserves_y = next_pool.reserves_y - pool.treasury_y;
+    next_pool.treasury_x = TaggedAmount::new(0);
+    next_pool.treasury_y = TaggedAmount::new(0);
+
+    let pool_out = next_pool
+        .clone()
+        .try_into_ledger(ImmutablePoolUtxo::from(&pool_utxo))?;
+    let treasury_out = treasury_output(&pool, ctx.select::<TreasuryAddress>().into())?;
+
+    let pool_validator = pool.get_validator(&ctx);
+    let pool_script = PartialPlutusWitness::new(
+        PlutusScriptWitness::Ref(pool_validator.hash),
+        next_pool
+            .clone()
+            .redeemer(pool.clone(), pool_in_idx, CFMMPoolAction::DAOAction),
+    );
+    let pool_in = SingleInputBuilder::new(pool_ref.into(), pool_utxo)
+        .plutus_script_inline_datum(pool_script, Vec::new())?;
+    let funding_in = SingleInputBuilder::new(funding.input, funding.output).payment_key()?;
+
+    let authority_address = RewardAddress::new(
+        ctx.select::<NetworkId>().into(),
+        Credential::new_script(authority.hash),
+    );
+    // Authority checks the action against the state of the DAO on its own, so it takes no arguments.
+    let authority_script = PartialPlutusWitness::new(
+        PlutusScriptWitness::Ref(authority.hash),
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, vec![])),
+    );
+    let authorization =
+        SingleWithdrawalBuilder::new(authority_address, 0).plutus_script(authority_script, Vec::new())?;
+
+    let mut tx_builder = constant_tx_builder();
+    tx_builder.add_collateral(ctx.select::<Collateral>().into())?;
+    tx_builder.add_reference_input(pool_validator.reference_utxo);
+    tx_builder.add_reference_input(authority.reference_utxo);
+    tx_builder.add_input(pool_in)?;
+    tx_builder.add_input(funding_in)?;
+    tx_builder.add_withdrawal(authorization);
+    tx_builder.set_exunits(
+        RedeemerWitnessKey::new(RedeemerTag::Spend, pool_in_idx.into()),
+        pool_validator.ex_budget.into(),
+    );
+    tx_builder.set_exunits(
+        RedeemerWitnessKey::new(RedeemerTag::Reward, 0),
+        authority.cost.into(),
+    );
+    tx_builder.add_output(SingleOutputBuilderResult::new(pool_out.clone()))?;
+    tx_builder.add_output(SingleOutputBuilderResult::new(treasury_out))?;
+
+    let tx = tx_builder.build(
+        ChangeSelectionAlgo::Default,
+        &ctx.select::<OperatorRewardAddress>().into(),
+    )?;
+    let next_pool_ref = OutputRef::new(hash_transaction_canonical(&tx.body()), 0);
+
+    Ok((
+        tx,
+        Predicted(Bundled(next_pool, FinalizedTxOut(pool_out, next_pool_ref))),
+    ))
+}
+
+fn treasury_output(pool: &ConstFnPool, address: Address) -> Result<TransactionOutput, OutputBuilderError> {
+    let mut native = 0;
+    let mut ma = MultiAsset::new();
+    for (asset, amount) in [
+        (pool.asset_x.untag(), pool.treasury_x.untag()),
+        (pool.asset_y.untag(), pool.treasury_y.untag()),
+    ] {
+        match asset.into_token() {
+            Some((policy, name)) if amount > 0 => {
+                ma.set(policy, name.into(), amount);
+            }
+            Some(_) => {}
+            None => native += amount,
+        }
+    }
+    let min_required = TransactionOutputBuilder::new()
+        .with_address(address.clone())
+        .next()?
+        .with_asset_and_min_required_coin(ma.clone(), COINS_PER_UTXO_BYTE)?
+        .build()?
+        .output
+        .value()
+        .coin;
+    Ok(TransactionOutputBuilder::new()
+        .with_address(address)
+        .next()?
+        .with_value(Value::new(native.max(min_required), ma))
+        .build()?
+        .output)
+}
+
+const FUNDING_LOOKUP_LIMIT: u16 = 50;
+
+async fn pull_funding<Net: CardanoNetwork>(
+    address: Address,
+    min_lovelace: Coin,
+    explorer: &Net,
+) -> Option<TransactionUnspentOutput> {
+    explorer
+        .utxos_by_address(address, 0, FUNDING_LOOKUP_LIMIT)
+        .await
+        .into_iter()
+        .find(|u| !u.output.amount().has_multiassets() && u.output.value().coin >= min_lovelace)
+}
+
+/// Periodically checks treasuries of fee-switch pools tracked by the [TreasuryWatch]
+/// and withdraws the ones exceeding the threshold.
+pub fn treasury_withdrawal_stream<'a, Net, Explorer, Prover, Tx, Err, Ctx>(
+    watch: TreasuryWatch,
+    explorer: Explorer,
+    network: Net,
+    prover: Prover,
+    conf: TreasuryWithdrawalConfig,
+    ctx: Ctx,
+) -> impl Stream<Item = ()> + 'a
+where
+    Explorer: CardanoNetwork + 'a,
+    Net: Network<Tx, Err> + 'a,
+    Prover: TxProver<SignedTxBuilder, Tx> + 'a,
+    Err: std::fmt::Debug,
+    Ctx: Has<Collateral>
+        + Has<OperatorRewardAddress>
+        + Has<TreasuryAddress>
+        + Has<NetworkId>
+        + Has<DeployedValidator<{ DaoAuthority as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolV1 as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolV2 as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolFeeSwitch as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolFeeSwitchV2 as u8 }>>
+        + Has<DeployedValidator<{ ConstFnPoolFeeSwitchBiDirFee as u8 }>>
+        + Clone
+        + 'a,
+{
+    let interval = Duration::from_secs(conf.poll_interval_secs);
+    let min_funding = conf.min_funding_lovelace.max(MIN_SAFE_LOVELACE_VALUE);
+    stream::unfold(
+        (watch, explorer, network, prover, ctx),
+        move |(watch, explorer, mut network, prover, ctx)| async move {
+            Delay::new(interval).await;
+            for pool in watch.due(conf.threshold) {
+                let pool_id = pool.0.id;
+                let operator_addr: Address = ctx.select::<OperatorRewardAddress>().into();
+                let Some(funding) = pull_funding(operator_addr, min_funding, &explorer).await else {
+                    warn!("No funding available for treasury withdrawal");
+                    break;
+                };
+                match build_treasury_withdrawal_tx(pool, funding, ctx.clone()) {
+                    Ok((tx_candidate, _)) => {
+                        let tx = prover.prove(tx_candidate);
+                        if let Err(err) = network.submit_tx(tx).await {
+                            warn!(
+                                "Failed to submit treasury withdrawal TX for pool {}: {:?}",
+                                pool_id, err
+                            );
+                        } else {
+                            // Pool is spent by the withdrawal, its next state is awaited from the ledger.
+                            watch.forget(&pool_id);
+                            // Change of the funding UTxO is not visible until the TX is settled.
+                            break;
+                        }
+                    }
+                    Err(TreasuryWithdrawalError::NotAuthorized) => {
+                        trace!("Pool {} is not governed by the DAO authority", pool_id);
+                        watch.forget(&pool_id);
+                    }
+                    Err(err) => warn!(
+                        "Failed to build treasury withdrawal TX for pool {}: {:?}",
+                        pool_id, err
+                    ),
+                }
+            }
+            Some(((), (watch, explorer, network, prover, ctx)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::Credential;
+    use cml_chain::transaction::TransactionOutput;
+    use cml_chain::PolicyId;
+    use cml_crypto::{ScriptHash, TransactionHash};
+    use either::Either;
+    use num_rational::Ratio;
+
+    use bloom_offchain::execution_engine::bundled::Bundled;
+    use bloom_offchain::execution_engine::Event;
+    use spectrum_cardano_lib::ex_units::ExUnits;
+    use spectrum_cardano_lib::output::FinalizedTxOut;
+    use spectrum_cardano_lib::{AssetClass, AssetName, OutputRef, TaggedAmount, TaggedAssetClass};
+    use spectrum_offchain::combinators::Ior;
+    use spectrum_offchain::data::event::{Channel, Confirmed, StateUpdate};
+    use spectrum_offchain::data::Baked;
+
+    use crate::data::cfmm_pool::{ConstFnPool, ConstFnPoolVer};
+    use crate::data::pool::{AnyPool, PoolBounds};
+    use crate::data::PoolId;
+    use crate::pool_creation::{pool_output, PoolCreationParams};
+    use crate::treasury::{authorized_by, withdrawal_due, TreasuryThreshold, TreasuryWatch};
+
+    const BOUNDS: PoolBounds = PoolBounds {
+        min_n2t_lovelace: 10_000_000,
+        min_t2t_lovelace: 10_000_000,
+        bootstrap_lovelace: 0,
+        bootstrap_liquidity: 0,
+        swap_deposit_surplus: false,
+    };
+
+    const THRESHOLD: TreasuryThreshold = TreasuryThreshold { x: 1_000, y: 1_000 };
+
+    fn dao() -> ScriptHash {
+        ScriptHash::from([2u8; 28])
+    }
+
+    fn token(tag: u8, name: &str) -> (PolicyId, AssetName) {
+        (
+            PolicyId::from([tag; 28]),
+            AssetName::utf8_unsafe(name.to_string()),
+        )
+    }
+
+    fn pool(ver: ConstFnPoolVer, treasury_x: u64, treasury_y: u64) -> ConstFnPool {
+        ConstFnPool {
+            id: PoolId::from(token(4, "nft")),
+            reserves_x: TaggedAmount::new(100_000_000),
+            reserves_y: TaggedAmount::new(400_000_000),
+            liquidity: TaggedAmount::new(200_000_000),
+            asset_x: TaggedAssetClass::new(AssetClass::Native),
+            asset_y: TaggedAssetClass::new(AssetClass::Token(token(1, "y"))),
+            asset_lq: TaggedAssetClass::new(AssetClass::Token(token(5, "lq"))),
+            lp_fee_x: Ratio::new_raw(99700, 100000),
+            lp_fee_y: Ratio::new_raw(99700, 100000),
+            treasury_fee: Ratio::new_raw(100, 100000),
+            treasury_x: TaggedAmount::new(treasury_x),
+            treasury_y: TaggedAmount::new(treasury_y),
+            lq_lower_bound: TaggedAmount::new(0),
+            ver,
+            marginal_cost: ExUnits { mem: 100, steps: 100 },
+            bounds: BOUNDS,
+        }
+    }
+
+    fn pool_utxo(ver: ConstFnPoolVer, dao_policies: Vec<ScriptHash>) -> TransactionOutput {
+        let params = PoolCreationParams {
+            ver,
+            asset_x: AssetClass::Native,
+            asset_y: AssetClass::Token(token(1, "y")),
+            reserves_x: 100_000_000,
+            reserves_y: 400_000_000,
+            lp_fee_num_x: 99700,
+            lp_fee_num_y: 99700,
+            treasury_fee_num: 100,
+            lq_lower_bound: 0,
+            admin_policies: dao_policies,
+            treasury_script: Some(ScriptHash::from([3u8; 28])),
+            price_bounds: None,
+        };
+        let address =
+            EnterpriseAddress::new(0, Credential::new_script(ScriptHash::from([6u8; 28]))).to_address();
+        pool_output(&params, token(4, "nft"), token(5, "lq"), 1, address, &BOUNDS).unwrap()
+    }
+
+    fn confirmed(tr: Ior<AnyPool, AnyPool>) -> Event<(), (), AnyPool, FinalizedTxOut, OutputRef> {
+        let bearer = FinalizedTxOut(
+            pool_utxo(ConstFnPoolVer::FeeSwitch, vec![dao()]),
+            OutputRef::new(TransactionHash::from([1u8; 32]), 0),
+        );
+        let bundle = |p| Bundled(Either::Right(Baked::new(p, bearer.1)), bearer.clone());
+        let tr = match tr {
+            Ior::Left(prev) => Ior::Left(bundle(prev)),
+            Ior::Right(next) => Ior::Right(bundle(next)),
+            Ior::Both(prev, next) => Ior::Both(bundle(prev), bundle(next)),
+        };
+        Either::Left(Channel::Ledger(Confirmed(StateUpdate::Transition(tr))))
+    }
+
+    #[test]
+    fn withdrawal_is_due_above_threshold_only() {
+        assert!(withdrawal_due(
+            &pool(ConstFnPoolVer::FeeSwitch, 1_000, 0),
+            THRESHOLD
+        ));
+        assert!(withdrawal_due(
+            &pool(ConstFnPoolVer::FeeSwitchV2, 0, 5_000),
+            THRESHOLD
+        ));
+        assert!(!withdrawal_due(
+            &pool(ConstFnPoolVer::FeeSwitch, 999, 999),
+            THRESHOLD
+        ));
+        assert!(!withdrawal_due(
+            &pool(ConstFnPoolVer::FeeSwitch, 0, 0),
+            TreasuryThreshold { x: 0, y: 0 }
+        ));
+        assert!(!withdrawal_due(
+            &pool(ConstFnPoolVer::FeeSwitchBiDirFee, 5_000, 5_000),
+            THRESHOLD
+        ));
+    }
+
+    #[test]
+    fn authorization_is_read_from_pool_datum() {
+        assert!(authorized_by(
+            &pool_utxo(ConstFnPoolVer::FeeSwitch, vec![dao()]),
+            dao()
+        ));
+        assert!(!authorized_by(
+            &pool_utxo(ConstFnPoolVer::FeeSwitch, vec![ScriptHash::from([7u8; 28])]),
+            dao()
+        ));
+        assert!(!authorized_by(&pool_utxo(ConstFnPoolVer::V2, vec![dao()]), dao()));
+    }
+
+    #[test]
+    fn watch_tracks_latest_confirmed_fee_switch_pools() {
+        let watch = TreasuryWatch::default();
+        let legacy = pool(ConstFnPoolVer::V2, 5_000, 5_000);
+        watch.observe(&confirmed(Ior::Right(AnyPool::PureCFMM(legacy))));
+        assert!(watch.due(THRESHOLD).is_empty());
+
+        let below = pool(ConstFnPoolVer::FeeSwitch, 10, 10);
+        let above = pool(ConstFnPoolVer::FeeSwitch, 5_000, 10);
+        watch.observe(&confirmed(Ior::Right(AnyPool::PureCFMM(below.clone()))));
+        assert!(watch.due(THRESHOLD).is_empty());
+        watch.observe(&confirmed(Ior::Both(
+            AnyPool::PureCFMM(below),
+            AnyPool::PureCFMM(above.clone()),
+        )));
+        assert_eq!(watch.due(THRESHOLD).len(), 1);
+
+        watch.forget(&above.id);
+        assert!(watch.due(THRESHOLD).is_empty());
+        watch.observe(&confirmed(Ior::Right(AnyPool::PureCFMM(above.clone()))));
+        watch.observe(&confirmed(Ior::Left(AnyPool::PureCFMM(above))));
+        assert!(watch.due(THRESHOLD).is_empty());
+    }
+}
@@ -1,23 +1,28 @@
 use std::collections::HashSet;
 use std::fmt::Display;
 use std::ops::Deref;
+use std::sync::Arc;
 
 use async_stream::stream;
-use cml_core::serialization::Serialize;
+use cml_chain::plutus::PlutusData;
+use cml_core::serialization::{Deserialize, Serialize};
+use cml_crypto::{RawBytesEncoding, TransactionHash};
 use futures::channel::{mpsc, oneshot};
 use futures::{SinkExt, Stream, StreamExt};
 use log::{trace, warn};
 use pallas_network::miniprotocols::localtxsubmission;
 use pallas_network::miniprotocols::localtxsubmission::cardano_node_errors::{
-    AlonzoUtxoPredFailure, ApplyTxError, BabbageUtxoPredFailure, BabbageUtxowPredFailure,
-    ShelleyLedgerPredFailure, TxInput,
+    AlonzoUtxoPredFailure, ApplyTxError, BabbageUtxoPredFailure, BabbageUtxowPredFailure, FailureDescription,
+    ShelleyLedgerPredFailure, TagMismatchDescription, TxInput, UtxosPredFailure,
 };
 use pallas_network::miniprotocols::localtxsubmission::Response;
 use pallas_network::multiplexer;
 
 use cardano_submit_api::client::{Error, LocalTxSubmissionClient};
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, PlutusDataExtension};
 use spectrum_cardano_lib::OutputRef;
-use spectrum_offchain::network::Network;
+use spectrum_offchain::network::{ErrorClass, FailedScripts, Network, RetryPolicy, SubmissionMetrics};
+use spectrum_offchain::streaming::retry_with_backoff;
 use spectrum_offchain::tx_hash::CanonicalHash;
 
 use crate::node::NodeConfig;
@@ -26,19 +31,28 @@ pub struct TxSubmissionAgent<'a, const ERA: u16, TxAdapter, Tx> {
     client: LocalTxSubmissionClient<'a, ERA, Tx>,
     mailbox: mpsc::Receiver<SubmitTx<TxAdapter>>,
     node_config: NodeConfig<'a>,
+    retry_policy: RetryPolicy,
+    metrics: Arc<SubmissionMetrics>,
 }
 
 impl<'a, const ERA: u16, TxAdapter, Tx> TxSubmissionAgent<'a, ERA, TxAdapter, Tx> {
     pub async fn new(
         node_config: NodeConfig<'a>,
         buffer_size: usize,
+        retry_policy: RetryPolicy,
+        metrics: Arc<SubmissionMetrics>,
     ) -> Result<(Self, TxSubmissionChannel<ERA, TxAdapter>), Error> {
-        let tx_submission_client = LocalTxSubmissionClient::init(node_config.path, node_config.magic).await?;
+        let tx_submission_client = retry_with_backoff(retry_policy, || {
+            LocalTxSubmissionClient::init(node_config.path, node_config.magic)
+        })
+        .await?;
         let (snd, recv) = mpsc::channel(buffer_size);
         let agent = Self {
             client: tx_submission_client,
             mailbox: recv,
             node_config,
+            retry_policy,
+            metrics,
         };
         Ok((agent, TxSubmissionChannel(snd)))
     }
@@ -52,14 +66,20 @@ impl<'a, const ERA: u16, TxAdapter, Tx> TxSubmissionAgent<'a, ERA, TxAdapter, Tx
             client,
             mailbox,
             node_config,
+            retry_policy,
+            metrics,
         } = self;
         client.close().await;
-        let new_tx_submission_client =
-            LocalTxSubmissionClient::init(node_config.path, node_config.magic).await?;
+        let new_tx_submission_client = retry_with_backoff(retry_policy, || {
+            LocalTxSubmissionClient::init(node_config.path, node_config.magic)
+        })
+        .await?;
         Ok(Self {
             client: new_tx_submission_client,
             mailbox,
             node_config,
+            retry_policy,
+            metrics,
         })
     }
 }
@@ -84,7 +104,20 @@ impl From<SubmissionResult> for Result<(), RejectReasons> {
     }
 }
 
-const MAX_SUBMIT_ATTEMPTS: usize = 3;
+/// Submitter may have stopped waiting, e.g. when the TX was already accepted by another endpoint.
+fn respond(on_resp: oneshot::Sender<SubmissionResult>, result: SubmissionResult) {
+    if on_resp.send(result).is_err() {
+        trace!("Submission result is no longer awaited");
+    }
+}
+
+fn classify(err: &localtxsubmission::Error) -> ErrorClass {
+    match err {
+        // Node response could not be decoded, most likely it is a rejection we cannot parse.
+        localtxsubmission::Error::ChannelError(multiplexer::Error::Decoding(_)) => ErrorClass::Deterministic,
+        _ => ErrorClass::Transient,
+    }
+}
 
 pub fn tx_submission_agent_stream<'a, const ERA: u16, TxAdapter, Tx>(
     mut agent: TxSubmissionAgent<'a, ERA, TxAdapter, Tx>,
@@ -101,27 +134,37 @@ where
             let tx_hash = tx.canonical_hash();
             loop {
                 match agent.client.submit_tx((*tx).clone()).await {
-                    Ok(Response::Accepted) => on_resp.send(SubmissionResult::Ok).expect("Responder was dropped"),
+                    Ok(Response::Accepted) => {
+                        agent.metrics.on_accepted();
+                        respond(on_resp, SubmissionResult::Ok);
+                    }
                     Ok(Response::Rejected(errors)) => {
                         trace!("TX {} was rejected due to error: {:?}", tx_hash, errors);
-                        on_resp.send(SubmissionResult::TxRejected{errors:  RejectReasons(errors)}).expect("Responder was dropped");
+                        agent.metrics.on_failure(ErrorClass::Deterministic);
+                        respond(on_resp, SubmissionResult::TxRejected{errors: RejectReasons(errors)});
                     },
                     Err(Error::TxSubmissionProtocol(err)) => {
                         trace!("Failed to submit TX {}: {}", tx_hash, hex::encode(tx.to_cbor_bytes()));
-                        match err {
-                            localtxsubmission::Error::ChannelError(multiplexer::Error::Decoding(_)) => {
+                        let class = classify(&err);
+                        agent.metrics.on_failure(class);
+                        match class {
+                            ErrorClass::Deterministic => {
                                 warn!("TX {} was likely rejected, reason unknown. Trying to recover.", tx_hash);
                                 agent.recover();
-                                on_resp.send(SubmissionResult::TxRejected{errors: vec![].into()}).expect("Responder was dropped");
+                                respond(on_resp, SubmissionResult::TxRejected{errors: vec![].into()});
                             }
-                            retryable_err => {
-                                trace!("Failed to submit TX {}: protocol returned error: {}", tx_hash, retryable_err);
-                                if attempts_done < MAX_SUBMIT_ATTEMPTS {
-                                    trace!("Retrying");
+                            ErrorClass::Transient => {
+                                trace!("Failed to submit TX {}: protocol returned error: {}", tx_hash, err);
+                                if agent.retry_policy.should_retry(class, attempts_done) {
+                                    let delay = agent.retry_policy.backoff(attempts_done);
+                                    trace!("Retrying in {:?}", delay);
+                                    tokio::time::sleep(delay).await;
+                                    agent.metrics.on_retry();
                                     attempts_done += 1;
                                 } else {
                                     trace!("Restarting TxSubmissionProtocol");
                                     agent = agent.restarted().await.expect("Failed to restart TxSubmissionProtocol");
+                                    attempts_done = 0;
                                 }
                                 continue;
                             },
@@ -164,6 +207,71 @@ impl TryFrom<RejectReasons> for HashSet<OutputRef> {
     }
 }
 
+impl FailedScripts<OutputRef> for RejectReasons {
+    fn failed_script_inputs(&self) -> HashSet<OutputRef> {
+        let mut failed_inputs = HashSet::new();
+        for ApplyTxError { node_errors } in &self.0 {
+            for error in node_errors {
+                if let ShelleyLedgerPredFailure::UtxowFailure(BabbageUtxowPredFailure::UtxoFailure(
+                    BabbageUtxoPredFailure::AlonzoInBabbageUtxoPredFailure(
+                        AlonzoUtxoPredFailure::UtxosFailure(UtxosPredFailure::ValidationTagMismatch(
+                            _,
+                            TagMismatchDescription::FailedUnexpectedly(failures),
+                        )),
+                    ),
+                )) = error
+                {
+                    failed_inputs.extend(
+                        failures
+                            .iter()
+                            .filter_map(|FailureDescription::PlutusFailure(_, debug)| failed_spend(debug)),
+                    );
+                }
+            }
+        }
+        failed_inputs
+    }
+}
+
+/// Input spent by the script which failed phase-2 validation.
+/// Debug info is encoded as `[language, cost_model, ex_units, script, args, protocol_version]`,
+/// script context comes last in `args`. Scripts run for other purposes yield nothing.
+fn failed_spend(debug: &[u8]) -> Option<OutputRef> {
+    let mut decoder = minicbor::Decoder::new(debug);
+    decoder.array().ok()?;
+    for _ in 0..4 {
+        decoder.skip().ok()?;
+    }
+    let num_args = decoder.array().ok()??;
+    for _ in 1..num_args {
+        decoder.skip().ok()?;
+    }
+    let context_start = decoder.position();
+    decoder.skip().ok()?;
+    let context = PlutusData::from_cbor_bytes(&debug[context_start..decoder.position()]).ok()?;
+    spent_input(context)
+}
+
+/// Input spent under the given script context, i.e. `Spending TxOutRef` purpose.
+fn spent_input(context: PlutusData) -> Option<OutputRef> {
+    const SPENDING: u64 = 1;
+    let mut purpose = context.into_constr_pd()?.take_field(1)?.into_constr_pd()?;
+    if purpose.alternative != SPENDING {
+        return None;
+    }
+    let mut out_ref = purpose.take_field(0)?.into_constr_pd()?;
+    let tx_id = out_ref
+        .take_field(0)?
+        .into_constr_pd()?
+        .take_field(0)?
+        .into_bytes()?;
+    let index = out_ref.take_field(1)?.into_u64()?;
+    Some(OutputRef::new(
+        TransactionHash::from_raw_bytes(&tx_id).ok()?,
+        index,
+    ))
+}
+
 #[async_trait::async_trait]
 impl<const ERA: u16, Tx> Network<Tx, RejectReasons> for TxSubmissionChannel<ERA, Tx>
 where
@@ -179,3 +287,63 @@ where
 #[derive(Debug, Clone, derive_more::Display, derive_more::From)]
 #[display(fmt = "RejectReasons: {:?}", "_0")]
 pub struct RejectReasons(pub Vec<ApplyTxError>);
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::plutus::{ConstrPlutusData, PlutusData};
+    use cml_core::serialization::Serialize;
+    use cml_crypto::TransactionHash;
+
+    use spectrum_cardano_lib::OutputRef;
+
+    use crate::tx_submission::failed_spend;
+
+    fn context(purpose: PlutusData) -> PlutusData {
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![PlutusData::new_list(vec![]), purpose],
+        ))
+    }
+
+    fn debug_info(context: PlutusData) -> Vec<u8> {
+        let mut encoder = minicbor::Encoder::new(vec![]);
+        encoder.array(6).unwrap();
+        encoder.u8(1).unwrap();
+        encoder.array(0).unwrap();
+        encoder.array(2).unwrap().u64(1000).unwrap().u64(1000).unwrap();
+        encoder.bytes(&[0u8; 4]).unwrap();
+        encoder.array(3).unwrap();
+        encoder
+            .writer_mut()
+            .extend(PlutusData::new_integer(0u64.into()).to_cbor_bytes());
+        encoder
+            .writer_mut()
+            .extend(PlutusData::new_integer(1u64.into()).to_cbor_bytes());
+        encoder.writer_mut().extend(context.to_cbor_bytes());
+        encoder.array(2).unwrap().u8(8).unwrap().u8(0).unwrap();
+        encoder.into_writer()
+    }
+
+    #[test]
+    fn failed_spend_is_read_from_script_context() {
+        let out_ref = OutputRef::new(TransactionHash::from([7u8; 32]), 2);
+        let tx_out_ref = PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![
+                PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+                    0,
+                    vec![PlutusData::new_bytes(vec![7u8; 32])],
+                )),
+                PlutusData::new_integer(2u64.into()),
+            ],
+        ));
+        let spending = PlutusData::ConstrPlutusData(ConstrPlutusData::new(1, vec![tx_out_ref]));
+        assert_eq!(failed_spend(&debug_info(context(spending))), Some(out_ref));
+        let minting = PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![PlutusData::new_bytes(vec![0u8; 28])],
+        ));
+        assert_eq!(failed_spend(&debug_info(context(minting))), None);
+        assert_eq!(failed_spend(&[0xff]), None);
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Display, Formatter};
+
+use cml_chain::builders::tx_builder::SignedTxBuilder;
+use cml_chain::certs::StakeCredential;
+use cml_chain::min_ada::min_ada_required;
+use cml_chain::plutus::RedeemerTag;
+use cml_chain::transaction::TransactionOutput;
+use cml_chain::Value;
+
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::protocol_params::COINS_PER_UTXO_BYTE;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::{AssetClass, OutputRef};
+use spectrum_offchain::tx_validator::TxValidator;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TxValidationError {
+    /// Input is not among the outputs known to be consumed.
+    UnresolvedInput(OutputRef),
+    /// Consumed and produced value differ by the given amounts (consumed minus produced).
+    ValueNotConserved(Vec<(AssetClass, i128)>),
+    InsufficientMinAda {
+        output_ix: usize,
+        required: u64,
+        provided: u64,
+    },
+    /// Script input has no spending redeemer.
+    MissingRedeemer(OutputRef),
+    /// Spending redeemer points to an input not guarded by a script.
+    DanglingRedeemer(u64),
+}
+
+impl Display for TxValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TxValidationError::UnresolvedInput(input) => write!(f, "Input {} is unresolved", input),
+            TxValidationError::ValueNotConserved(imbalance) => {
+                f.write_str("Value is not conserved:")?;
+                for (ac, delta) in imbalance {
+                    write!(f, " {}: {}", ac, delta)?;
+                }
+                Ok(())
+            }
+            TxValidationError::InsufficientMinAda {
+                output_ix,
+                required,
+                provided,
+            } => write!(
+                f,
+                "Output #{} holds {} lovelace while {} is required",
+                output_ix, provided, required
+            ),
+            TxValidationError::MissingRedeemer(input) => write!(f, "Script input {} has no redeemer", input),
+            TxValidationError::DanglingRedeemer(ix) => {
+                write!(f, "Spending redeemer #{} points to a non-script input", ix)
+            }
+        }
+    }
+}
+
+/// Catches TXs the node would reject: unbalanced value, outputs below min-ADA,
+/// and script inputs inconsistent with redeemers.
+/// Operator signature is not checked as it is added by the prover.
+#[derive(Debug, Copy, Clone)]
+pub struct DryRunValidator {
+    coins_per_utxo_byte: u64,
+}
+
+impl DryRunValidator {
+    pub fn new() -> Self {
+        Self {
+            coins_per_utxo_byte: COINS_PER_UTXO_BYTE,
+        }
+    }
+}
+
+impl Default for DryRunValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TxValidator<SignedTxBuilder, FinalizedTxOut> for DryRunValidator {
+    type Error = TxValidationError;
+
+    fn validate(&self, candidate: &SignedTxBuilder, consumed: &[FinalizedTxOut]) -> Result<(), Self::Error> {
+        let body = candidate.body();
+        let resolved = consumed
+            .iter()
+            .map(|FinalizedTxOut(out, out_ref)| (*out_ref, out))
+            .collect::<HashMap<_, _>>();
+        // Redeemers refer to inputs in their canonical order.
+        let mut inputs = vec![];
+        for i in &body.inputs {
+            let input = OutputRef::from(i.clone());
+            let out = resolved
+                .get(&input)
+                .ok_or(TxValidationError::UnresolvedInput(input))?;
+            inputs.push((input, *out));
+        }
+        inputs.sort_by_key(|(input, _)| *input);
+
+        let mut balance = BTreeMap::<AssetClass, i128>::new();
+        for (_, out) in &inputs {
+            add_value(&mut balance, out.value(), 1);
+        }
+        if let Some(mint) = &body.mint {
+            for (policy, assets) in mint.iter() {
+                for (an, amount) in assets.iter() {
+                    *balance
+                        .entry(AssetClass::Token((*policy, an.clone().into())))
+                        .or_default() += *amount as i128;
+                }
+            }
+        }
+        if let Some(withdrawals) = &body.withdrawals {
+            for (_, amount) in withdrawals.iter() {
+                *balance.entry(AssetClass::Native).or_default() += *amount as i128;
+            }
+        }
+        for (output_ix, out) in body.outputs.iter().enumerate() {
+            check_min_ada(output_ix, out, self.coins_per_utxo_byte)?;
+            add_value(&mut balance, out.value(), -1);
+        }
+        *balance.entry(AssetClass::Native).or_default() -= body.fee as i128;
+        let imbalance = balance
+            .into_iter()
+            .filter(|(_, delta)| *delta != 0)
+            .collect::<Vec<_>>();
+        if !imbalance.is_empty() {
+            return Err(TxValidationError::ValueNotConserved(imbalance));
+        }
+
+        let mut spent_by_redeemer = candidate
+            .witness_set()
+            .build()
+            .redeemers
+            .map(|rs| rs.to_flat_format())
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|r| r.tag == RedeemerTag::Spend)
+            .map(|r| r.index)
+            .collect::<Vec<_>>();
+        spent_by_redeemer.sort();
+        for (ix, (input, out)) in inputs.iter().enumerate() {
+            let is_script_input =
+                matches!(out.address().payment_cred(), Some(StakeCredential::Script { .. }));
+            let has_redeemer = spent_by_redeemer.binary_search(&(ix as u64)).is_ok();
+            if is_script_input && !has_redeemer {
+                return Err(TxValidationError::MissingRedeemer(*input));
+            }
+            if !is_script_input && has_redeemer {
+                return Err(TxValidationError::DanglingRedeemer(ix as u64));
+            }
+        }
+        if let Some(ix) = spent_by_redeemer.iter().find(|ix| **ix as usize >= inputs.len()) {
+            return Err(TxValidationError::DanglingRedeemer(*ix));
+        }
+        Ok(())
+    }
+}
+
+fn add_value(balance: &mut BTreeMap<AssetClass, i128>, value: &Value, sign: i128) {
+    *balance.entry(AssetClass::Native).or_default() += sign * value.coin as i128;
+    for (policy, assets) in value.multiasset.iter() {
+        for (an, amount) in assets.iter() {
+            *balance
+                .entry(AssetClass::Token((*policy, an.clone().into())))
+                .or_default() += sign * *amount as i128;
+        }
+    }
+}
+
+fn check_min_ada(
+    output_ix: usize,
+    out: &TransactionOutput,
+    coins_per_utxo_byte: u64,
+) -> Result<(), TxValidationError> {
+    let provided = out.value().coin;
+    let required = min_ada_required(out, coins_per_utxo_byte).unwrap_or(u64::MAX);
+    if provided < required {
+        return Err(TxValidationError::InsufficientMinAda {
+            output_ix,
+            required,
+            provided,
+        });
+    }
+    Ok(())
+}
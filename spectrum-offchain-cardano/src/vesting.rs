@@ -0,0 +1,331 @@
+use std::time::Duration;
+
+use cml_chain::address::Address;
+use cml_chain::builders::output_builder::{
+    OutputBuilderError, SingleOutputBuilderResult, TransactionOutputBuilder,
+};
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::plutus::{ConstrPlutusData, PlutusData};
+use cml_chain::{Coin, Value};
+use cml_multi_era::babbage::BabbageTransactionOutput;
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::{info, trace, warn};
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use cardano_explorer::CardanoNetwork;
+use spectrum_cardano_lib::address::PlutusAddress;
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::era::AnyEraOutput;
+use spectrum_cardano_lib::output::FinalizedTxOut;
+use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
+use spectrum_cardano_lib::protocol_params::constant_tx_builder;
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::types::TryFromPData;
+use spectrum_cardano_lib::{NetworkId, OutputRef, PaymentCredential};
+use spectrum_offchain::clock::Clock;
+use spectrum_offchain::data::Has;
+use spectrum_offchain::ledger::TryFromLedger;
+use spectrum_offchain::network::Network;
+use spectrum_offchain::tx_prover::TxProver;
+
+use crate::constants::MIN_SAFE_LOVELACE_VALUE;
+use crate::creds::OperatorRewardAddress;
+use crate::deployment::ProtocolValidator::Vesting;
+use crate::deployment::{
+    test_address, DeployedScriptInfo, DeployedValidator, DeployedValidatorErased, RequiresValidator,
+};
+use crate::script::{ready_redeemer, ScriptInput, TxInputs};
+
+/// Datum of the vesting validator.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VestingDatum {
+    /// Address locked funds are released to.
+    pub beneficiary: PlutusAddress,
+    /// POSIX time (milliseconds) after which the lock can be claimed.
+    pub unlock_after: u64,
+}
+
+impl TryFromPData for VestingDatum {
+    fn try_from_pd(data: PlutusData) -> Option<Self> {
+        let mut cpd = data.into_constr_pd()?;
+        Some(Self {
+            beneficiary: PlutusAddress::try_from_pd(cpd.take_field(0)?)?,
+            unlock_after: cpd.take_field(1)?.into_u64()?,
+        })
+    }
+}
+
+impl IntoPlutusData for VestingDatum {
+    fn into_pd(self) -> PlutusData {
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+            0,
+            vec![self.beneficiary.into_pd(), self.unlock_after.into_pd()],
+        ))
+    }
+}
+
+/// Funds (e.g. team or treasury allocation) locked at the vesting validator until the unlock time.
+/// Anyone may claim an unlocked lock, the validator only checks that the whole value goes to the beneficiary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VestingLock {
+    pub beneficiary: PlutusAddress,
+    /// POSIX time (milliseconds) after which the lock can be claimed.
+    pub unlock_after: u64,
+    pub value: Value,
+}
+
+impl VestingLock {
+    pub fn is_unlocked(&self, now_millis: u64) -> bool {
+        now_millis > self.unlock_after
+    }
+}
+
+impl<Ctx> RequiresValidator<Ctx> for VestingLock
+where
+    Ctx: Has<DeployedValidator<{ Vesting as u8 }>>,
+{
+    fn get_validator(&self, ctx: &Ctx) -> DeployedValidatorErased {
+        ctx.get().erased()
+    }
+}
+
+impl<Ctx> TryFromLedger<BabbageTransactionOutput, Ctx> for VestingLock
+where
+    Ctx: Has<DeployedScriptInfo<{ Vesting as u8 }>>,
+{
+    fn try_from_ledger(repr: &BabbageTransactionOutput, ctx: &Ctx) -> Option<Self> {
+        if test_address(repr.address(), ctx) {
+            let datum = VestingDatum::try_from_pd(repr.inline_datum()?.clone())?;
+            return Some(Self {
+                beneficiary: datum.beneficiary,
+                unlock_after: datum.unlock_after,
+                value: repr.value().clone(),
+            });
+        }
+        None
+    }
+}
+
+/// Reference point mapping POSIX time to slots, which are one second long since Shelley.
+#[derive(serde::Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SlotOrigin {
+    pub slot: u64,
+    pub posix_time_millis: u64,
+}
+
+impl SlotOrigin {
+    /// First slot starting strictly after the given POSIX time.
+    pub fn first_slot_after(&self, posix_time_millis: u64) -> u64 {
+        self.slot + posix_time_millis.saturating_sub(self.posix_time_millis) / 1000 + 1
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VestingClaimConfig {
+    pub poll_interval_secs: u64,
+    /// Minimal lovelace amount of an operator UTxO to fund claim TXs with.
+    pub min_funding_lovelace: Coin,
+    pub slot_origin: SlotOrigin,
+}
+
+#[derive(Debug, derive_more::From)]
+pub enum VestingClaimError {
+    TxBuilder(TxBuilderError),
+    OutputBuilder(OutputBuilderError),
+}
+
+fn claim_redeemer() -> PlutusData {
+    PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, vec![]))
+}
+
+/// Builds a TX releasing the whole value of the given lock to its beneficiary.
+/// TX fee is covered by the `funding` UTxO, change goes back to [OperatorRewardAddress].
+/// The TX is only valid from `valid_from` slot on, which must start after the unlock time.
+pub fn build_vesting_claim_tx<Ctx>(
+    Bundled(lock, FinalizedTxOut(lock_utxo, lock_ref)): Bundled<VestingLock, FinalizedTxOut>,
+    funding: TransactionUnspentOutput,
+    valid_from: u64,
+    ctx: Ctx,
+) -> Result<SignedTxBuilder, VestingClaimError>
+where
+    VestingLock: RequiresValidator<Ctx>,
+    Ctx: Has<Collateral> + Has<OperatorRewardAddress> + Has<NetworkId>,
+{
+    info!(
+        target: "offchain",
+        "Claiming vesting lock {} for {:?}",
+        lock_ref,
+        lock.beneficiary.payment_cred
+    );
+    let validator = lock.get_validator(&ctx);
+    let mut inputs = TxInputs::new();
+    inputs.add_script_input(ScriptInput {
+        utxo: TransactionUnspentOutput::new(lock_ref.into(), lock_utxo),
+        script: validator.hash,
+        redeemer: ready_redeemer(claim_redeemer()),
+        ex_units: validator.ex_budget.into(),
+        required_signers: std::iter::empty().collect(),
+    });
+    inputs.add_key_input(funding);
+
+    let beneficiary_out = TransactionOutputBuilder::new()
+        .with_address(lock.beneficiary.to_address(ctx.select::<NetworkId>()))
+        .next()?
+        .with_value(lock.value)
+        .build()?
+        .output;
+
+    let mut tx_builder = constant_tx_builder();
+    tx_builder.add_collateral(ctx.select::<Collateral>().into())?;
+    tx_builder.add_reference_input(validator.reference_utxo);
+    inputs.project_onto(&mut tx_builder)?;
+    tx_builder.add_output(SingleOutputBuilderResult::new(beneficiary_out))?;
+    tx_builder.set_validity_start_interval(valid_from);
+
+    Ok(tx_builder.build(
+        ChangeSelectionAlgo::Default,
+        &ctx.select::<OperatorRewardAddress>().into(),
+    )?)
+}
+
+const FUNDING_LOOKUP_LIMIT: u16 = 50;
+
+async fn pull_funding<Net: CardanoNetwork>(
+    address: Address,
+    min_lovelace: Coin,
+    explorer: &Net,
+) -> Option<TransactionUnspentOutput> {
+    explorer
+        .utxos_by_address(address, 0, FUNDING_LOOKUP_LIMIT)
+        .await
+        .into_iter()
+        .find(|u| !u.output.amount().has_multiassets() && u.output.value().coin >= min_lovelace)
+}
+
+/// Locks currently sitting at the vesting validator.
+async fn pull_locks<Net, Ctx>(explorer: &Net, ctx: &Ctx) -> Vec<Bundled<VestingLock, FinalizedTxOut>>
+where
+    Net: CardanoNetwork,
+    Ctx: Has<DeployedScriptInfo<{ Vesting as u8 }>>,
+{
+    let script = ctx.select::<DeployedScriptInfo<{ Vesting as u8 }>>().script_hash;
+    let cred = PaymentCredential::from(script);
+    explorer
+        .all_utxos_by_pay_cred(cred)
+        .await
+        .into_iter()
+        .filter_map(|utxo| {
+            let output = BabbageTransactionOutput::try_from(AnyEraOutput::from(utxo.output.clone())).ok()?;
+            let lock = VestingLock::try_from_ledger(&output, ctx)?;
+            Some(Bundled(
+                lock,
+                FinalizedTxOut(utxo.output, OutputRef::from(utxo.input)),
+            ))
+        })
+        .collect()
+}
+
+/// Periodically claims vesting locks whose unlock time has passed on behalf of their beneficiaries.
+pub fn vesting_claim_stream<'a, Net, Explorer, Prover, Clk, Tx, Err, Ctx>(
+    explorer: Explorer,
+    network: Net,
+    prover: Prover,
+    clock: Clk,
+    conf: VestingClaimConfig,
+    ctx: Ctx,
+) -> impl Stream<Item = ()> + 'a
+where
+    Explorer: CardanoNetwork + 'a,
+    Net: Network<Tx, Err> + 'a,
+    Prover: TxProver<SignedTxBuilder, Tx> + 'a,
+    Clk: Clock + 'a,
+    Err: std::fmt::Debug,
+    Ctx: Has<Collateral>
+        + Has<OperatorRewardAddress>
+        + Has<NetworkId>
+        + Has<DeployedValidator<{ Vesting as u8 }>>
+        + Has<DeployedScriptInfo<{ Vesting as u8 }>>
+        + Clone
+        + 'a,
+{
+    let interval = Duration::from_secs(conf.poll_interval_secs);
+    let min_funding = conf.min_funding_lovelace.max(MIN_SAFE_LOVELACE_VALUE);
+    stream::unfold(
+        (explorer, network, prover, clock, ctx),
+        move |(explorer, mut network, prover, clock, ctx)| async move {
+            let now_millis = clock.unix_time_secs() * 1000;
+            for Bundled(lock, bearer) in pull_locks(&explorer, &ctx).await {
+                let lock_ref = bearer.1;
+                if !lock.is_unlocked(now_millis) {
+                    trace!("Vesting lock {} unlocks at {}", lock_ref, lock.unlock_after);
+                    continue;
+                }
+                let operator_addr = ctx.select::<OperatorRewardAddress>().into();
+                let Some(funding) = pull_funding(operator_addr, min_funding, &explorer).await else {
+                    warn!("No funding available for vesting claims");
+                    break;
+                };
+                let valid_from = conf.slot_origin.first_slot_after(lock.unlock_after);
+                match build_vesting_claim_tx(Bundled(lock, bearer), funding, valid_from, ctx.clone()) {
+                    Ok(tx_candidate) => {
+                        let tx = prover.prove(tx_candidate);
+                        if let Err(err) = network.submit_tx(tx).await {
+                            warn!(
+                                "Failed to submit claim TX for vesting lock {}: {:?}",
+                                lock_ref, err
+                            );
+                        } else {
+                            // Change of the funding UTxO is not visible until the TX is settled.
+                            break;
+                        }
+                    }
+                    Err(err) => warn!(
+                        "Failed to build claim TX for vesting lock {}: {:?}",
+                        lock_ref, err
+                    ),
+                }
+            }
+            Delay::new(interval).await;
+            Some(((), (explorer, network, prover, clock, ctx)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use cml_crypto::Ed25519KeyHash;
+
+    use spectrum_cardano_lib::address::PlutusAddress;
+    use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+    use spectrum_cardano_lib::types::TryFromPData;
+
+    use crate::vesting::{SlotOrigin, VestingDatum};
+
+    #[test]
+    fn vesting_datum_roundtrip() {
+        let datum = VestingDatum {
+            beneficiary: PlutusAddress::pub_key(
+                Ed25519KeyHash::from([1u8; 28]),
+                Some(Ed25519KeyHash::from([2u8; 28])),
+            ),
+            unlock_after: 1_700_000_000_000,
+        };
+        assert_eq!(VestingDatum::try_from_pd(datum.into_pd()), Some(datum));
+    }
+
+    #[test]
+    fn claim_is_valid_strictly_after_unlock() {
+        let origin = SlotOrigin {
+            slot: 4_492_800,
+            posix_time_millis: 1_596_059_091_000,
+        };
+        assert_eq!(origin.first_slot_after(1_596_059_091_000), 4_492_801);
+        assert_eq!(origin.first_slot_after(1_596_059_092_500), 4_492_802);
+        assert_eq!(origin.first_slot_after(0), 4_492_801);
+    }
+}
@@ -36,6 +36,10 @@ where
         TOrd: 'a;
     /// Pop best order.
     fn try_pop(&mut self) -> Option<TOrd>;
+    /// Pop best order satisfying the given predicate, other orders stay in backlog.
+    fn try_pop_where<F>(&mut self, pred: F) -> Option<TOrd>
+    where
+        F: Fn(&TOrd) -> bool;
     /// Check if order with the given id exists already in backlog.
     fn exists<'a>(&self, ord_id: TOrd::TOrderId) -> bool
     where
@@ -110,6 +114,28 @@ where
         None
     }
 
+    fn try_pop_where<F>(&mut self, pred: F) -> Option<TOrd>
+    where
+        F: Fn(&TOrd) -> bool,
+    {
+        let mut skipped = vec![];
+        let mut found = None;
+        while let Some((oid, wt)) = self.queue.pop() {
+            if let Some(ord) = self.store.get(&oid) {
+                if pred(ord) {
+                    found = self.store.remove(&oid);
+                    self.capacity += 1;
+                    break;
+                }
+                skipped.push((oid, wt));
+            }
+        }
+        for (oid, wt) in skipped {
+            self.queue.push(oid, wt);
+        }
+        found
+    }
+
     fn exists<'a>(&self, ord_id: TOrd::TOrderId) -> bool
     where
         TOrd::TOrderId: 'a,
@@ -573,7 +599,10 @@ mod tests {
 
     use crate::backlog::data::{BacklogOrder, OrderWeight, Weighted};
     use crate::backlog::persistence::{BacklogStore, BacklogStoreRocksDB};
-    use crate::backlog::{BacklogConfig, PersistentPriorityBacklog, ResilientBacklog};
+    use crate::backlog::{
+        BacklogCapacity, BacklogConfig, HotBacklog, HotPriorityBacklog, PersistentPriorityBacklog,
+        ResilientBacklog,
+    };
     use crate::data::order::{PendingOrder, ProgressingOrder, SuspendedOrder, UniqueOrder};
 
     #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Clone, Copy, Serialize, Deserialize)]
@@ -753,6 +782,22 @@ mod tests {
         assert_eq!(res, Some(ord3.order))
     }
 
+    #[test]
+    fn should_pop_best_order_matching_predicate() {
+        let mut backlog = HotPriorityBacklog::new(BacklogCapacity::from(10));
+        let ord1 = make_order(1, 1).order;
+        let ord2 = make_order(2, 2).order;
+        let ord3 = make_order(3, 3).order;
+        backlog.put(ord1.clone());
+        backlog.put(ord2.clone());
+        backlog.put(ord3.clone());
+
+        let res = backlog.try_pop_where(|ord| ord.order_id.0 < 3);
+        assert_eq!(res, Some(ord2));
+        assert_eq!(backlog.try_pop(), Some(ord3));
+        assert_eq!(backlog.try_pop(), Some(ord1));
+    }
+
     #[tokio::test]
     async fn should_always_pop_suspended_order_when_pa_100() {
         let mut backlog = setup_backlog(10, 5, 100).await;
@@ -7,7 +7,7 @@ use serde::Serialize;
 
 use crate::backlog::data::BacklogOrder;
 use crate::data::order::UniqueOrder;
-use crate::rocks::RocksConfig;
+use crate::rocks::{migrate, Migration, RocksConfig};
 
 #[async_trait]
 pub trait BacklogStore<TOrd>
@@ -23,15 +23,18 @@ where
         F: Fn(&TOrd) -> bool + Send + 'static;
 }
 
+/// Steps upgrading stored [BacklogOrder]s.
+const MIGRATIONS: &[Migration<rocksdb::OptimisticTransactionDB>] = &[];
+
 pub struct BacklogStoreRocksDB {
     pub db: Arc<rocksdb::OptimisticTransactionDB>,
 }
 
 impl BacklogStoreRocksDB {
     pub fn new(conf: RocksConfig) -> Self {
-        Self {
-            db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap()),
-        }
+        let db = rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap();
+        migrate(&db, "backlog", MIGRATIONS);
+        Self { db: Arc::new(db) }
     }
 }
 
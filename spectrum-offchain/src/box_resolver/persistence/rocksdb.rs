@@ -12,7 +12,10 @@ use crate::box_resolver::persistence::EntityRepo;
 use crate::box_resolver::{Predicted, Traced};
 use crate::data::event::{Confirmed, Unconfirmed};
 use crate::data::{EntitySnapshot, Stable};
-use crate::rocks::RocksConfig;
+use crate::rocks::{migrate, Migration, RocksConfig};
+
+/// Schema migrations of the entity repo.
+const MIGRATIONS: &[Migration<rocksdb::OptimisticTransactionDB>] = &[];
 
 pub struct EntityRepoRocksDB {
     pub db: Arc<rocksdb::OptimisticTransactionDB>,
@@ -20,9 +23,9 @@ pub struct EntityRepoRocksDB {
 
 impl EntityRepoRocksDB {
     pub fn new(conf: RocksConfig) -> Self {
-        Self {
-            db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap()),
-        }
+        let db = rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap();
+        migrate(&db, "entity_repo", MIGRATIONS);
+        Self { db: Arc::new(db) }
     }
 }
 
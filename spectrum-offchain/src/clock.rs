@@ -0,0 +1,95 @@
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Source of wall-clock time.
+pub trait Clock {
+    /// Seconds since UNIX epoch.
+    fn unix_time_secs(&self) -> u64;
+}
+
+/// Clock of the host system.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn unix_time_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+/// Clock moved by hand, all copies observe the same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock(Arc<AtomicU64>);
+
+impl ManualClock {
+    pub fn new(unix_time_secs: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(unix_time_secs)))
+    }
+
+    pub fn set(&self, unix_time_secs: u64) {
+        self.0.store(unix_time_secs, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.0.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn unix_time_secs(&self) -> u64 {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Clock shared by components via context.
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock + Send + Sync>);
+
+impl SharedClock {
+    pub fn new<C: Clock + Send + Sync + 'static>(clock: C) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    pub fn system() -> Self {
+        Self::new(SystemClock)
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl Debug for SharedClock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SharedClock({})", self.unix_time_secs())
+    }
+}
+
+impl Clock for SharedClock {
+    fn unix_time_secs(&self) -> u64 {
+        self.0.unix_time_secs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::clock::{Clock, ManualClock, SharedClock};
+
+    #[test]
+    fn shared_manual_clock_is_observed_by_all_holders() {
+        let manual = ManualClock::new(1_000);
+        let shared = SharedClock::new(manual.clone());
+        let other_holder = shared.clone();
+        manual.advance(30);
+        assert_eq!(shared.unix_time_secs(), 1_030);
+        manual.set(5);
+        assert_eq!(other_holder.unix_time_secs(), 5);
+    }
+}
@@ -38,6 +38,73 @@ impl<O1, O2> Ior<O1, O2> {
             Ior::Both(lh, rh) => Ior::Both(lhf(lh), rhf(rh)),
         }
     }
+
+    pub fn map_left<A, F>(self, f: F) -> Ior<A, O2>
+    where
+        F: FnOnce(O1) -> A,
+    {
+        self.bimap(f, |rh| rh)
+    }
+
+    pub fn map_right<B, F>(self, f: F) -> Ior<O1, B>
+    where
+        F: FnOnce(O2) -> B,
+    {
+        self.bimap(|lh| lh, f)
+    }
+
+    pub fn as_ref(&self) -> Ior<&O1, &O2> {
+        match self {
+            Ior::Left(lh) => Ior::Left(lh),
+            Ior::Right(rh) => Ior::Right(rh),
+            Ior::Both(lh, rh) => Ior::Both(lh, rh),
+        }
+    }
+
+    pub fn left(self) -> Option<O1> {
+        match self {
+            Ior::Left(lh) | Ior::Both(lh, _) => Some(lh),
+            Ior::Right(_) => None,
+        }
+    }
+
+    pub fn right(self) -> Option<O2> {
+        match self {
+            Ior::Right(rh) | Ior::Both(_, rh) => Some(rh),
+            Ior::Left(_) => None,
+        }
+    }
+
+    /// Display both sides of the transition, e.g. `a -> b`.
+    pub fn summary(&self) -> IorSummary<'_, O1, O2> {
+        IorSummary(self)
+    }
+}
+
+impl<T> Ior<T, T> {
+    /// Collapse into a single value, `f` combines both sides if present.
+    pub fn merge<F>(self, f: F) -> T
+    where
+        F: FnOnce(T, T) -> T,
+    {
+        match self {
+            Ior::Left(x) | Ior::Right(x) => x,
+            Ior::Both(lh, rh) => f(lh, rh),
+        }
+    }
+}
+
+/// Shows a transition as `-a` (eliminated), `+b` (created) or `a -> b` (updated).
+pub struct IorSummary<'a, O1, O2>(&'a Ior<O1, O2>);
+
+impl<'a, O1: Display, O2: Display> Display for IorSummary<'a, O1, O2> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Ior::Left(lh) => write!(f, "-{}", lh),
+            Ior::Right(rh) => write!(f, "+{}", rh),
+            Ior::Both(lh, rh) => write!(f, "{} -> {}", lh, rh),
+        }
+    }
 }
 
 impl<O1, O2> TryFrom<(Option<O1>, Option<O2>)> for Ior<O1, O2> {
@@ -51,3 +118,39 @@ impl<O1, O2> TryFrom<(Option<O1>, Option<O2>)> for Ior<O1, O2> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::combinators::Ior;
+
+    #[test]
+    fn map_sides_independently() {
+        let ior: Ior<u8, &str> = Ior::Both(1, "a");
+        assert!(matches!(ior.clone().map_left(|x| x + 1), Ior::Both(2, "a")));
+        assert!(matches!(ior.map_right(str::len), Ior::Both(1, 1)));
+        let left_only: Ior<u8, &str> = Ior::Left(1);
+        assert!(matches!(left_only.map_right(str::len), Ior::Left(1)));
+    }
+
+    #[test]
+    fn project_sides() {
+        assert_eq!(Ior::<u8, u8>::Both(1, 2).left(), Some(1));
+        assert_eq!(Ior::<u8, u8>::Both(1, 2).right(), Some(2));
+        assert_eq!(Ior::<u8, u8>::Left(1).right(), None);
+        assert_eq!(Ior::<u8, u8>::Right(2).left(), None);
+    }
+
+    #[test]
+    fn merge_sides() {
+        assert_eq!(Ior::Both(1, 2).merge(|a, b| a + b), 3);
+        assert_eq!(Ior::Left(1).merge(|a, b| a + b), 1);
+        assert_eq!(Ior::Right(2).merge(|a, b| a + b), 2);
+    }
+
+    #[test]
+    fn summarize_transitions() {
+        assert_eq!(Ior::<u8, u8>::Left(1).summary().to_string(), "-1");
+        assert_eq!(Ior::<u8, u8>::Right(2).summary().to_string(), "+2");
+        assert_eq!(Ior::<u8, u8>::Both(1, 2).summary().to_string(), "1 -> 2");
+    }
+}
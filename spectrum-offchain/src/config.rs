@@ -0,0 +1,125 @@
+use derive_more::Display;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// Separates levels of nesting in names of override variables,
+/// e.g. `BLOOM_NODE__MAGIC` overrides `node.magic`.
+const PATH_SEPARATOR: &str = "__";
+
+/// Failure to load configuration of an agent.
+#[derive(Debug, Display)]
+pub enum ConfigError {
+    #[display(fmt = "Cannot read {}: {}", _0, _1)]
+    Read(String, std::io::Error),
+    #[display(fmt = "Malformed {}: {}", _0, _1)]
+    Parse(String, serde_json::Error),
+    #[display(fmt = "Cannot apply override {}: {}", _0, _1)]
+    Override(String, String),
+}
+
+/// Read JSON configuration at `path`. If `env_prefix` is given, environment variables
+/// starting with it override respective fields, e.g. with prefix `BLOOM_`
+/// `BLOOM_TX_JOURNAL_DB_PATH=/data/journal` replaces `txJournalDbPath`.
+pub fn read_json_source(path: &str, env_prefix: Option<&str>) -> Result<String, ConfigError> {
+    let raw = std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.to_string(), err))?;
+    match env_prefix {
+        Some(prefix) => {
+            let mut value: Value =
+                serde_json::from_str(&raw).map_err(|err| ConfigError::Parse(path.to_string(), err))?;
+            apply_overrides(&mut value, prefix, std::env::vars())?;
+            Ok(value.to_string())
+        }
+        None => Ok(raw),
+    }
+}
+
+/// Deserialize configuration read from `path`.
+pub fn parse<'a, T: Deserialize<'a>>(path: &str, source: &'a str) -> Result<T, ConfigError> {
+    serde_json::from_str(source).map_err(|err| ConfigError::Parse(path.to_string(), err))
+}
+
+/// Values of overrides are taken as JSON if they parse, as plain strings otherwise.
+pub fn apply_overrides<I>(config: &mut Value, prefix: &str, vars: I) -> Result<(), ConfigError>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    for (var, raw_value) in vars {
+        if let Some(path) = var.strip_prefix(prefix) {
+            let value = serde_json::from_str(&raw_value).unwrap_or(Value::String(raw_value));
+            let mut node = &mut *config;
+            for key in path.split(PATH_SEPARATOR).map(camel_case) {
+                if node.is_null() {
+                    *node = Value::Object(Map::new());
+                }
+                match node {
+                    Value::Object(fields) => node = fields.entry(key).or_insert(Value::Null),
+                    other => {
+                        return Err(ConfigError::Override(
+                            var.clone(),
+                            format!("{} is not an object", other),
+                        ))
+                    }
+                }
+            }
+            *node = value;
+        }
+    }
+    Ok(())
+}
+
+fn camel_case(screaming_snake: &str) -> String {
+    let mut words = screaming_snake.split('_').filter(|w| !w.is_empty());
+    let mut key = words.next().unwrap_or_default().to_lowercase();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(head) = chars.next() {
+            key.push(head.to_ascii_uppercase());
+            key.push_str(&chars.as_str().to_lowercase());
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::config::apply_overrides;
+
+    fn vars(vs: &[(&str, &str)]) -> Vec<(String, String)> {
+        vs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn overrides_nested_fields_by_env() {
+        let mut conf = json!({
+            "txJournalDbPath": "tx_journal",
+            "node": {"path": "/ipc/node.socket", "magic": 1},
+        });
+        apply_overrides(
+            &mut conf,
+            "BLOOM_",
+            vars(&[
+                ("BLOOM_TX_JOURNAL_DB_PATH", "/data/tx_journal"),
+                ("BLOOM_NODE__MAGIC", "764824073"),
+                ("BLOOM_QUOTE_API_ADDR", "0.0.0.0:8081"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            conf,
+            json!({
+                "txJournalDbPath": "/data/tx_journal",
+                "node": {"path": "/ipc/node.socket", "magic": 764824073},
+                "quoteApiAddr": "0.0.0.0:8081",
+            })
+        );
+    }
+
+    #[test]
+    fn override_of_scalar_subfield_is_rejected() {
+        let mut conf = json!({"backlogCapacity": 10});
+        assert!(apply_overrides(&mut conf, "BLOOM_", vars(&[("BLOOM_BACKLOG_CAPACITY__X", "1")])).is_err());
+    }
+}
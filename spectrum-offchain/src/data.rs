@@ -2,6 +2,7 @@ use std::fmt::{Debug, Display};
 use std::hash::Hash;
 
 use either::Either;
+use serde::{Deserialize, Serialize};
 use type_equalities::IsEqual;
 
 use crate::ledger::TryFromLedger;
@@ -99,7 +100,7 @@ where
 
 /// A baked entity [T] paired with a computed version [V],
 /// i.e. [T] can no longer be modified.
-#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Serialize, Deserialize)]
 pub struct Baked<T, V> {
     pub entity: T,
     pub version: V,
@@ -33,6 +33,10 @@ pub trait SpecializedOrder {
 
     fn get_self_ref(&self) -> Self::TOrderId;
     fn get_pool_ref(&self) -> Self::TPoolId;
+    /// Whether the order adds liquidity to the pool.
+    fn is_deposit(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Hash, Clone, Eq, PartialEq)]
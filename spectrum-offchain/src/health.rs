@@ -0,0 +1,186 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use log::{trace, warn};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::network::SubmissionMetrics;
+use crate::sync_progress::SyncProgress;
+
+/// Readiness conditions of an agent, updated by its components as they come up.
+#[derive(Debug, Default)]
+pub struct HealthState {
+    synced: AtomicBool,
+    db_open: AtomicBool,
+    network_reachable: AtomicBool,
+    sync_progress: Option<Arc<SyncProgress>>,
+    submission_metrics: Option<Arc<SubmissionMetrics>>,
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report chain sync progress along with readiness.
+    pub fn with_sync_progress(self, sync_progress: Arc<SyncProgress>) -> Self {
+        Self {
+            sync_progress: Some(sync_progress),
+            ..self
+        }
+    }
+
+    /// Report outcomes of TX submissions along with readiness.
+    pub fn with_submission_metrics(self, submission_metrics: Arc<SubmissionMetrics>) -> Self {
+        Self {
+            submission_metrics: Some(submission_metrics),
+            ..self
+        }
+    }
+
+    pub fn set_synced(&self, value: bool) {
+        self.synced.store(value, Ordering::Relaxed);
+    }
+
+    pub fn set_db_open(&self, value: bool) {
+        self.db_open.store(value, Ordering::Relaxed);
+    }
+
+    pub fn set_network_reachable(&self, value: bool) {
+        self.network_reachable.store(value, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.synced.load(Ordering::Relaxed)
+            && self.db_open.load(Ordering::Relaxed)
+            && self.network_reachable.load(Ordering::Relaxed)
+    }
+
+    fn report(&self) -> String {
+        let sync_progress = self
+            .sync_progress
+            .as_ref()
+            .map(|progress| {
+                format!(
+                    ",\"currentSlot\":{},\"tipSlot\":{},\"lagSlots\":{},\"lagSecs\":{}",
+                    progress.current_slot(),
+                    progress.tip_slot(),
+                    progress.lag_slots(),
+                    progress.lag().as_secs()
+                )
+            })
+            .unwrap_or_default();
+        let submission_metrics = self
+            .submission_metrics
+            .as_ref()
+            .map(|metrics| {
+                format!(
+                    ",\"txAccepted\":{},\"txRejected\":{},\"txTransientFailures\":{},\"txRetries\":{}",
+                    metrics.accepted(),
+                    metrics.rejected(),
+                    metrics.transient_failures(),
+                    metrics.retries()
+                )
+            })
+            .unwrap_or_default();
+        format!(
+            "{{\"synced\":{},\"dbOpen\":{},\"networkReachable\":{}{}{}}}",
+            self.synced.load(Ordering::Relaxed),
+            self.db_open.load(Ordering::Relaxed),
+            self.network_reachable.load(Ordering::Relaxed),
+            sync_progress,
+            submission_metrics
+        )
+    }
+}
+
+/// Serves `/healthz` (liveness), `/readyz` (readiness) and `/version` over plain HTTP.
+pub async fn serve_health_checks(addr: SocketAddr, state: Arc<HealthState>, version: &'static str) {
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            warn!("Cannot bind health check endpoint to {}: {}", addr, err);
+            return;
+        }
+    };
+    loop {
+        if let Ok((stream, _)) = listener.accept().await {
+            let state = Arc::clone(&state);
+            tokio::spawn(async move {
+                if let Err(err) = respond(stream, &state, version).await {
+                    trace!("Health check connection failed: {}", err);
+                }
+            });
+        }
+    }
+}
+
+const MAX_REQUEST_LEN: usize = 1024;
+
+async fn respond(mut stream: TcpStream, state: &HealthState, version: &str) -> std::io::Result<()> {
+    let mut buf = [0u8; MAX_REQUEST_LEN];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.lines().next().and_then(|line| {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("GET") => parts.next(),
+            _ => None,
+        }
+    });
+    let (status, body) = match path {
+        Some("/healthz") => ("200 OK", String::from("ok")),
+        Some("/readyz") if state.is_ready() => ("200 OK", state.report()),
+        Some("/readyz") => ("503 Service Unavailable", state.report()),
+        Some("/version") => ("200 OK", String::from(version)),
+        Some(_) => ("404 Not Found", String::new()),
+        None => ("400 Bad Request", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    use crate::health::{serve_health_checks, HealthState};
+
+    async fn get(addr: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn readiness_follows_state() {
+        let addr: SocketAddr = "127.0.0.1:38291".parse().unwrap();
+        let state = Arc::new(HealthState::new());
+        tokio::spawn(serve_health_checks(addr, Arc::clone(&state), "1.0.0"));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(get(addr, "/healthz").await.starts_with("HTTP/1.1 200"));
+        assert!(get(addr, "/readyz").await.starts_with("HTTP/1.1 503"));
+        state.set_synced(true);
+        state.set_db_open(true);
+        state.set_network_reachable(true);
+        assert!(get(addr, "/readyz").await.starts_with("HTTP/1.1 200"));
+        assert!(get(addr, "/version").await.ends_with("1.0.0"));
+    }
+}
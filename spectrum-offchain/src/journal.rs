@@ -0,0 +1,221 @@
+use std::cmp::max;
+use std::path::Path;
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use futures_timer::Delay;
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{Clock, SharedClock};
+
+/// Journal of entries ordered by the time they were recorded at.
+pub trait Journal {
+    type Entry;
+    /// Entries recorded within `[from, to)` (UNIX time, seconds), oldest first.
+    fn entries_between(&self, from: u64, to: u64) -> Vec<Self::Entry>;
+    /// UNIX time (seconds) the oldest entry was recorded at.
+    fn oldest_entry_at(&self) -> Option<u64>;
+    /// Drop entries recorded before the given UNIX time (seconds).
+    fn prune_before(&self, time: u64);
+    /// Estimated size of the journal on disk in bytes.
+    fn estimated_size(&self) -> u64;
+}
+
+/// How long journal entries are kept.
+#[derive(Serialize, Deserialize, Copy, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalRetention {
+    /// Entries older than this are dropped, kept forever if not set.
+    #[serde(default)]
+    pub max_age: Option<Duration>,
+    /// Once the journal grows beyond this size (in bytes) the oldest entries are rotated out.
+    #[serde(default)]
+    pub max_size_bytes: Option<u64>,
+    pub check_interval: Duration,
+}
+
+/// Share of the period covered by an oversized journal rotated out at once.
+const ROTATION_FRACTION: u64 = 10;
+
+impl JournalRetention {
+    /// UNIX time (seconds) entries recorded before are to be dropped at the given moment.
+    pub fn cutoff<J: Journal>(&self, journal: &J, now: u64) -> Option<u64> {
+        let by_age = self.max_age.map(|age| now.saturating_sub(age.as_secs()));
+        let by_size = self
+            .max_size_bytes
+            .filter(|max_size| journal.estimated_size() > *max_size)
+            .and_then(|_| journal.oldest_entry_at())
+            .map(|oldest| oldest + now.saturating_sub(oldest) / ROTATION_FRACTION + 1);
+        match (by_age, by_size) {
+            (Some(x), Some(y)) => Some(max(x, y)),
+            (x, y) => x.or(y),
+        }
+    }
+}
+
+/// Periodically apply the retention policy to the journal.
+pub fn retention_stream<'a, J>(
+    journal: J,
+    name: &'a str,
+    policy: JournalRetention,
+    clock: SharedClock,
+) -> impl Stream<Item = ()> + 'a
+where
+    J: Journal + 'a,
+{
+    stream::unfold(journal, move |journal| {
+        let clock = clock.clone();
+        async move {
+            Delay::new(policy.check_interval).await;
+            let now = clock.unix_time_secs();
+            if let Some(cutoff) = policy.cutoff(&journal, now) {
+                info!("Pruning {} journal entries recorded before {}", name, cutoff);
+                journal.prune_before(cutoff);
+            }
+            Some(((), journal))
+        }
+    })
+}
+
+/// Max size of a single RocksDB info log file before it is rotated.
+const INFO_LOG_FILE_SIZE: usize = 16 * 1024 * 1024;
+/// Number of rotated RocksDB info log files kept.
+const INFO_LOG_FILES_KEPT: usize = 4;
+
+/// Options of DBs backing journals: blocks are compressed with zstd, info logs are rotated.
+pub fn journal_options() -> rocksdb::Options {
+    let mut opts = rocksdb::Options::default();
+    opts.create_if_missing(true);
+    opts.set_compression_type(rocksdb::DBCompressionType::Zstd);
+    opts.set_bottommost_compression_type(rocksdb::DBCompressionType::Zstd);
+    opts.set_max_log_file_size(INFO_LOG_FILE_SIZE);
+    opts.set_keep_log_file_num(INFO_LOG_FILES_KEPT);
+    opts
+}
+
+pub fn open_journal<P: AsRef<Path>>(path: P) -> rocksdb::DB {
+    rocksdb::DB::open(&journal_options(), path).unwrap()
+}
+
+/// Open a journal alongside the agent writing into it, e.g. to export entries.
+pub fn open_journal_read_only<P: AsRef<Path>>(path: P) -> Result<rocksdb::DB, rocksdb::Error> {
+    rocksdb::DB::open_for_read_only(&journal_options(), path, false)
+}
+
+/// Estimated size of the DB on disk in bytes.
+pub fn estimated_db_size(db: &rocksdb::DB) -> u64 {
+    ["rocksdb.total-sst-files-size", "rocksdb.cur-size-all-mem-tables"]
+        .into_iter()
+        .filter_map(|property| db.property_int_value(property).ok().flatten())
+        .sum()
+}
+
+/// Reclaim space taken by entries deleted from the given key range.
+pub fn compact_pruned(db: &rocksdb::DB, from: Option<&[u8]>, to: Option<&[u8]>) {
+    db.compact_range(from, to);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    use futures::executor::block_on;
+    use futures::StreamExt;
+
+    use crate::clock::{ManualClock, SharedClock};
+    use crate::journal::{retention_stream, Journal, JournalRetention};
+
+    struct VecJournal {
+        entries: RefCell<Vec<u64>>,
+        size_per_entry: u64,
+    }
+
+    impl Journal for VecJournal {
+        type Entry = u64;
+        fn entries_between(&self, from: u64, to: u64) -> Vec<u64> {
+            self.entries
+                .borrow()
+                .iter()
+                .copied()
+                .filter(|t| *t >= from && *t < to)
+                .collect()
+        }
+        fn oldest_entry_at(&self) -> Option<u64> {
+            self.entries.borrow().first().copied()
+        }
+        fn prune_before(&self, time: u64) {
+            self.entries.borrow_mut().retain(|t| *t >= time)
+        }
+        fn estimated_size(&self) -> u64 {
+            self.entries.borrow().len() as u64 * self.size_per_entry
+        }
+    }
+
+    impl Journal for &VecJournal {
+        type Entry = u64;
+        fn entries_between(&self, from: u64, to: u64) -> Vec<u64> {
+            (*self).entries_between(from, to)
+        }
+        fn oldest_entry_at(&self) -> Option<u64> {
+            (*self).oldest_entry_at()
+        }
+        fn prune_before(&self, time: u64) {
+            (*self).prune_before(time)
+        }
+        fn estimated_size(&self) -> u64 {
+            (*self).estimated_size()
+        }
+    }
+
+    #[test]
+    fn journal_is_pruned_by_age_and_rotated_by_size() {
+        let journal = VecJournal {
+            entries: RefCell::new((0..100).map(|t| t * 10).collect()),
+            size_per_entry: 10,
+        };
+        let by_age = JournalRetention {
+            max_age: Some(Duration::from_secs(500)),
+            max_size_bytes: None,
+            check_interval: Duration::from_secs(60),
+        };
+        assert_eq!(by_age.cutoff(&journal, 1000), Some(500));
+        let by_size = JournalRetention {
+            max_age: None,
+            max_size_bytes: Some(1000),
+            check_interval: Duration::from_secs(60),
+        };
+        assert_eq!(by_size.cutoff(&journal, 1000), None);
+        journal.entries.borrow_mut().push(1000);
+        let cutoff = by_size.cutoff(&journal, 1000).unwrap();
+        journal.prune_before(cutoff);
+        assert_eq!(journal.oldest_entry_at(), Some(110));
+        assert_eq!(journal.entries_between(0, 200).len(), 9);
+    }
+
+    #[test]
+    fn retention_is_applied_at_clock_time() {
+        let journal = VecJournal {
+            entries: RefCell::new((0..100).map(|t| t * 10).collect()),
+            size_per_entry: 10,
+        };
+        let clock = ManualClock::new(700);
+        let policy = JournalRetention {
+            max_age: Some(Duration::from_secs(500)),
+            max_size_bytes: None,
+            check_interval: Duration::ZERO,
+        };
+        let mut retention = Box::pin(retention_stream(
+            &journal,
+            "test",
+            policy,
+            SharedClock::new(clock.clone()),
+        ));
+        block_on(retention.next());
+        assert_eq!(journal.oldest_entry_at(), Some(200));
+        clock.advance(100);
+        block_on(retention.next());
+        assert_eq!(journal.oldest_entry_at(), Some(300));
+    }
+}
@@ -0,0 +1,494 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// Ordered byte-oriented key-value storage persistent stores are built on.
+/// Backends are expected to be durable, failures to access them are fatal.
+pub trait KvBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+    fn put(&self, key: &[u8], value: &[u8]);
+    fn delete(&self, key: &[u8]);
+    /// Entries whose keys start with `prefix`, in key order.
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)>;
+    /// Apply operations of the batch in order, either all of them are persisted or none.
+    fn write_batch(&self, batch: WriteBatch);
+    /// Buffer subsequent writes until [KvBackend::commit] persists them as a single [WriteBatch].
+    /// Backends without buffering write through.
+    fn begin(&self) {}
+    fn commit(&self) {}
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BatchOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+impl BatchOp {
+    fn key(&self) -> &[u8] {
+        match self {
+            BatchOp::Put(key, _) | BatchOp::Delete(key) => key,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct WriteBatch(Vec<BatchOp>);
+
+impl WriteBatch {
+    pub fn new() -> Self {
+        Self(vec![])
+    }
+
+    pub fn put(&mut self, key: &[u8], value: &[u8]) {
+        self.0.push(BatchOp::Put(key.to_vec(), value.to_vec()));
+    }
+
+    pub fn delete(&mut self, key: &[u8]) {
+        self.0.push(BatchOp::Delete(key.to_vec()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn ops(&self) -> &[BatchOp] {
+        &self.0
+    }
+
+    /// Value of the key as of this batch: `Some(None)` if deleted, `None` if not touched.
+    fn lookup(&self, key: &[u8]) -> Option<Option<Vec<u8>>> {
+        self.0.iter().rev().find(|op| op.key() == key).map(|op| match op {
+            BatchOp::Put(_, value) => Some(value.clone()),
+            BatchOp::Delete(_) => None,
+        })
+    }
+}
+
+/// Backend buffering writes between [KvBackend::begin] and [KvBackend::commit], so that stores
+/// sharing it (e.g. state index and cache) are updated atomically.
+/// Reads observe buffered writes. Clones share the buffer.
+pub struct Batched<B> {
+    backend: B,
+    pending: Arc<Mutex<Pending>>,
+}
+
+/// Writes buffered by [Batched] along with the number of batches open at the moment.
+#[derive(Default)]
+struct Pending {
+    depth: usize,
+    batch: WriteBatch,
+}
+
+impl Pending {
+    fn active(&mut self) -> Option<&mut WriteBatch> {
+        (self.depth > 0).then_some(&mut self.batch)
+    }
+}
+
+impl<B> Batched<B> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            pending: Arc::new(Mutex::new(Pending::default())),
+        }
+    }
+}
+
+impl<B: Clone> Clone for Batched<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            pending: Arc::clone(&self.pending),
+        }
+    }
+}
+
+impl<B: KvBackend> KvBackend for Batched<B> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.pending.lock().active().and_then(|batch| batch.lookup(key)) {
+            Some(buffered) => buffered,
+            None => self.backend.get(key),
+        }
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        match self.pending.lock().active() {
+            Some(batch) => batch.put(key, value),
+            None => self.backend.put(key, value),
+        }
+    }
+
+    fn delete(&self, key: &[u8]) {
+        match self.pending.lock().active() {
+            Some(batch) => batch.delete(key),
+            None => self.backend.delete(key),
+        }
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let mut entries = self
+            .backend
+            .scan_prefix(prefix)
+            .into_iter()
+            .collect::<BTreeMap<_, _>>();
+        if let Some(batch) = self.pending.lock().active() {
+            for op in batch.ops().iter().filter(|op| op.key().starts_with(prefix)) {
+                match op {
+                    BatchOp::Put(key, value) => {
+                        entries.insert(key.clone(), value.clone());
+                    }
+                    BatchOp::Delete(key) => {
+                        entries.remove(key);
+                    }
+                }
+            }
+        }
+        entries.into_iter().collect()
+    }
+
+    fn write_batch(&self, batch: WriteBatch) {
+        match self.pending.lock().active() {
+            Some(pending) => pending.0.extend(batch.0),
+            None => self.backend.write_batch(batch),
+        }
+    }
+
+    /// Batches nest, writes of inner batches are committed along with the outermost one.
+    fn begin(&self) {
+        self.pending.lock().depth += 1;
+    }
+
+    fn commit(&self) {
+        let mut pending = self.pending.lock();
+        pending.depth = pending.depth.saturating_sub(1);
+        if pending.depth == 0 && !pending.batch.is_empty() {
+            self.backend.write_batch(std::mem::take(&mut pending.batch));
+        }
+    }
+}
+
+impl<B: KvBackend + ?Sized> KvBackend for Arc<B> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.as_ref().get(key)
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        self.as_ref().put(key, value)
+    }
+
+    fn delete(&self, key: &[u8]) {
+        self.as_ref().delete(key)
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.as_ref().scan_prefix(prefix)
+    }
+
+    fn write_batch(&self, batch: WriteBatch) {
+        self.as_ref().write_batch(batch)
+    }
+
+    fn begin(&self) {
+        self.as_ref().begin()
+    }
+
+    fn commit(&self) {
+        self.as_ref().commit()
+    }
+}
+
+impl KvBackend for rocksdb::DB {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        rocksdb::DB::get(self, key).unwrap()
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        rocksdb::DB::put(self, key, value).unwrap()
+    }
+
+    fn delete(&self, key: &[u8]) {
+        rocksdb::DB::delete(self, key).unwrap()
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.iterator(rocksdb::IteratorMode::From(prefix, rocksdb::Direction::Forward))
+            .map(|entry| entry.unwrap())
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+
+    fn write_batch(&self, batch: WriteBatch) {
+        let mut rocks_batch = rocksdb::WriteBatch::default();
+        for op in batch.0 {
+            match op {
+                BatchOp::Put(key, value) => rocks_batch.put(key, value),
+                BatchOp::Delete(key) => rocks_batch.delete(key),
+            }
+        }
+        self.write(rocks_batch).unwrap()
+    }
+}
+
+#[cfg(feature = "sled")]
+impl KvBackend for sled::Db {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        sled::Tree::get(self, key).unwrap().map(|value| value.to_vec())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        self.insert(key, value).unwrap();
+    }
+
+    fn delete(&self, key: &[u8]) {
+        self.remove(key).unwrap();
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        sled::Tree::scan_prefix(self, prefix)
+            .map(|entry| entry.unwrap())
+            .map(|(key, value)| (key.to_vec(), value.to_vec()))
+            .collect()
+    }
+
+    fn write_batch(&self, batch: WriteBatch) {
+        let mut sled_batch = sled::Batch::default();
+        for op in batch.0 {
+            match op {
+                BatchOp::Put(key, value) => sled_batch.insert(key, value),
+                BatchOp::Delete(key) => sled_batch.remove(key),
+            }
+        }
+        self.apply_batch(sled_batch).unwrap()
+    }
+}
+
+#[cfg(feature = "redb")]
+const REDB_TABLE: redb::TableDefinition<&[u8], &[u8]> = redb::TableDefinition::new("kv");
+
+/// redb keeps entries in named tables, everything is stored in a single one.
+#[cfg(feature = "redb")]
+pub struct RedbBackend(redb::Database);
+
+#[cfg(feature = "redb")]
+impl RedbBackend {
+    pub fn open(db_path: &str) -> Self {
+        if let Some(dir) = std::path::Path::new(db_path).parent() {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        let db = redb::Database::create(db_path).unwrap();
+        let tx = db.begin_write().unwrap();
+        tx.open_table(REDB_TABLE).unwrap();
+        tx.commit().unwrap();
+        Self(db)
+    }
+}
+
+#[cfg(feature = "redb")]
+impl KvBackend for RedbBackend {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        let tx = self.0.begin_read().unwrap();
+        let table = tx.open_table(REDB_TABLE).unwrap();
+        let value = table.get(key).unwrap();
+        value.map(|value| value.value().to_vec())
+    }
+
+    fn put(&self, key: &[u8], value: &[u8]) {
+        let tx = self.0.begin_write().unwrap();
+        tx.open_table(REDB_TABLE).unwrap().insert(key, value).unwrap();
+        tx.commit().unwrap();
+    }
+
+    fn delete(&self, key: &[u8]) {
+        let tx = self.0.begin_write().unwrap();
+        tx.open_table(REDB_TABLE).unwrap().remove(key).unwrap();
+        tx.commit().unwrap();
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> Vec<(Vec<u8>, Vec<u8>)> {
+        let tx = self.0.begin_read().unwrap();
+        let table = tx.open_table(REDB_TABLE).unwrap();
+        let entries = table
+            .range(prefix..)
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .map(|(key, value)| (key.value().to_vec(), value.value().to_vec()))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .collect();
+        entries
+    }
+
+    fn write_batch(&self, batch: WriteBatch) {
+        let tx = self.0.begin_write().unwrap();
+        {
+            let mut table = tx.open_table(REDB_TABLE).unwrap();
+            for op in batch.0 {
+                match op {
+                    BatchOp::Put(key, value) => {
+                        table.insert(key.as_slice(), value.as_slice()).unwrap();
+                    }
+                    BatchOp::Delete(key) => {
+                        table.remove(key.as_slice()).unwrap();
+                    }
+                }
+            }
+        }
+        tx.commit().unwrap();
+    }
+}
+
+/// Storage engine a persistent store is opened with.
+/// Engines other than RocksDB are only available when the crate is built with the matching feature.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "backend", rename_all = "camelCase")]
+pub enum KvBackendConfig {
+    #[serde(rename = "rocksdb")]
+    RocksDB {
+        #[serde(rename = "dbPath")]
+        db_path: String,
+    },
+    #[cfg(feature = "sled")]
+    Sled {
+        #[serde(rename = "dbPath")]
+        db_path: String,
+    },
+    #[cfg(feature = "redb")]
+    Redb {
+        #[serde(rename = "dbPath")]
+        db_path: String,
+    },
+}
+
+pub fn open_backend(conf: &KvBackendConfig) -> Arc<dyn KvBackend + Send + Sync> {
+    match conf {
+        KvBackendConfig::RocksDB { db_path } => Arc::new(rocksdb::DB::open_default(db_path).unwrap()),
+        #[cfg(feature = "sled")]
+        KvBackendConfig::Sled { db_path } => Arc::new(sled::open(db_path).unwrap()),
+        #[cfg(feature = "redb")]
+        KvBackendConfig::Redb { db_path } => Arc::new(RedbBackend::open(db_path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use crate::kv_backend::{open_backend, Batched, KvBackend, KvBackendConfig, WriteBatch};
+
+    /// Behaviour every backend must conform to.
+    fn conformance<B: KvBackend>(backend: B) {
+        assert_eq!(backend.get(b"a:1"), None);
+        backend.put(b"a:1", b"x");
+        assert_eq!(backend.get(b"a:1"), Some(b"x".to_vec()));
+        backend.put(b"a:1", b"y");
+        assert_eq!(backend.get(b"a:1"), Some(b"y".to_vec()));
+        backend.put(b"a:3", b"z");
+        backend.put(b"a:2", b"w");
+        backend.put(b"b:1", b"v");
+        backend.put(b"", b"u");
+        assert_eq!(
+            backend.scan_prefix(b"a:"),
+            vec![
+                (b"a:1".to_vec(), b"y".to_vec()),
+                (b"a:2".to_vec(), b"w".to_vec()),
+                (b"a:3".to_vec(), b"z".to_vec()),
+            ]
+        );
+        backend.delete(b"a:2");
+        backend.delete(b"a:4");
+        assert_eq!(backend.get(b"a:2"), None);
+        assert_eq!(backend.scan_prefix(b"a:").len(), 2);
+        assert_eq!(backend.scan_prefix(b"c:"), vec![]);
+        assert_eq!(backend.scan_prefix(b"").len(), 4);
+        let mut batch = WriteBatch::new();
+        batch.put(b"c:1", b"t");
+        batch.delete(b"a:1");
+        batch.put(b"c:2", b"s");
+        batch.delete(b"c:2");
+        backend.write_batch(batch);
+        assert_eq!(backend.get(b"a:1"), None);
+        assert_eq!(backend.scan_prefix(b"c:"), vec![(b"c:1".to_vec(), b"t".to_vec())]);
+    }
+
+    fn db_path_for_test() -> String {
+        format!("./tmp/{}", rand::thread_rng().next_u32())
+    }
+
+    #[test]
+    fn rocksdb_conforms() {
+        conformance(open_backend(&KvBackendConfig::RocksDB {
+            db_path: db_path_for_test(),
+        }));
+    }
+
+    #[cfg(feature = "sled")]
+    #[test]
+    fn sled_conforms() {
+        conformance(open_backend(&KvBackendConfig::Sled {
+            db_path: db_path_for_test(),
+        }));
+    }
+
+    #[cfg(feature = "redb")]
+    #[test]
+    fn redb_conforms() {
+        conformance(open_backend(&KvBackendConfig::Redb {
+            db_path: db_path_for_test(),
+        }));
+    }
+
+    #[test]
+    fn batched_writes_are_visible_only_through_batch_until_commit() {
+        let backend = open_backend(&KvBackendConfig::RocksDB {
+            db_path: db_path_for_test(),
+        });
+        let batched = Batched::new(backend.clone());
+        batched.put(b"k:0", b"0");
+        batched.begin();
+        batched.put(b"k:1", b"1");
+        batched.delete(b"k:0");
+        assert_eq!(batched.get(b"k:1"), Some(b"1".to_vec()));
+        assert_eq!(batched.scan_prefix(b"k:"), vec![(b"k:1".to_vec(), b"1".to_vec())]);
+        assert_eq!(backend.get(b"k:0"), Some(b"0".to_vec()));
+        assert_eq!(backend.get(b"k:1"), None);
+        batched.commit();
+        assert_eq!(backend.get(b"k:0"), None);
+        assert_eq!(backend.get(b"k:1"), Some(b"1".to_vec()));
+    }
+
+    #[test]
+    fn nested_batch_is_committed_with_outermost_one() {
+        let backend = open_backend(&KvBackendConfig::RocksDB {
+            db_path: db_path_for_test(),
+        });
+        let batched = Batched::new(backend.clone());
+        batched.begin();
+        batched.put(b"k:0", b"0");
+        batched.begin();
+        batched.put(b"k:1", b"1");
+        batched.commit();
+        assert_eq!(backend.get(b"k:1"), None);
+        assert_eq!(batched.get(b"k:1"), Some(b"1".to_vec()));
+        batched.commit();
+        assert_eq!(backend.get(b"k:0"), Some(b"0".to_vec()));
+        assert_eq!(backend.get(b"k:1"), Some(b"1".to_vec()));
+        batched.put(b"k:2", b"2");
+        assert_eq!(backend.get(b"k:2"), Some(b"2".to_vec()));
+    }
+
+    #[test]
+    fn backend_config_is_read_from_json() {
+        let conf = serde_json::from_str::<KvBackendConfig>(r#"{"backend": "rocksdb", "dbPath": "state"}"#);
+        assert!(matches!(conf, Ok(KvBackendConfig::RocksDB { db_path }) if db_path == "state"));
+    }
+
+    #[test]
+    fn batched_backend_conforms() {
+        conformance(Batched::new(open_backend(&KvBackendConfig::RocksDB {
+            db_path: db_path_for_test(),
+        })));
+    }
+}
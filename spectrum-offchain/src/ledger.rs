@@ -22,3 +22,9 @@ where
 pub trait IntoLedger<Repr, Ctx> {
     fn into_ledger(self, ctx: Ctx) -> Repr;
 }
+
+/// Tries to encode domain entity into on-chain representation.
+pub trait TryIntoLedger<Repr, Ctx> {
+    type Error;
+    fn try_into_ledger(self, ctx: Ctx) -> Result<Repr, Self::Error>;
+}
@@ -2,15 +2,25 @@ pub mod backlog;
 pub mod binary;
 pub mod box_resolver;
 pub mod circular_filter;
+pub mod clock;
 pub mod combinators;
+pub mod config;
 pub mod data;
 pub mod event_sink;
 pub mod executor;
+pub mod health;
+pub mod journal;
+pub mod kv_backend;
 pub mod ledger;
 pub mod maker;
 pub mod network;
 pub mod partitioning;
-pub(crate) mod rocks;
+pub mod quarantine;
+pub mod rocks;
+pub mod sensitive;
 pub mod streaming;
+pub mod sync_progress;
 pub mod tx_hash;
+pub mod tx_journal;
 pub mod tx_prover;
+pub mod tx_validator;
@@ -2,3 +2,8 @@
 pub trait Maker<T> {
     fn make(ctx: &T) -> Self;
 }
+
+/// Narrow context [Self] down to the resource identified by [K].
+pub trait Specialize<K> {
+    fn specialize(&self, key: &K) -> Self;
+}
@@ -1,4 +1,160 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use futures::future::select_ok;
+use rand::Rng;
+
 #[async_trait::async_trait]
 pub trait Network<Tx, Err> {
     async fn submit_tx(&mut self, tx: Tx) -> Result<(), Err>;
 }
+
+/// Rejection caused by validators of particular inputs.
+pub trait FailedScripts<Ref> {
+    /// Inputs whose scripts failed validation.
+    /// Empty unless the rejection is a script failure which names the spent inputs.
+    fn failed_script_inputs(&self) -> HashSet<Ref>;
+}
+
+/// Submits each TX to all endpoints concurrently, first success wins.
+/// If all endpoints fail the error of the last one to respond is returned.
+#[derive(Debug, Clone)]
+pub struct Broadcast<N>(Vec<N>);
+
+impl<N> Broadcast<N> {
+    pub fn new(primary: N, others: Vec<N>) -> Self {
+        let mut endpoints = vec![primary];
+        endpoints.extend(others);
+        Self(endpoints)
+    }
+}
+
+#[async_trait::async_trait]
+impl<Tx, Err, N> Network<Tx, Err> for Broadcast<N>
+where
+    Tx: Clone + Send,
+    Err: Send,
+    N: Network<Tx, Err> + Send,
+{
+    async fn submit_tx(&mut self, tx: Tx) -> Result<(), Err> {
+        let submissions = self.0.iter_mut().map(|endpoint| endpoint.submit_tx(tx.clone()));
+        select_ok(submissions).await.map(|_| ())
+    }
+}
+
+/// Whether a failed submission is worth retrying.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ErrorClass {
+    /// Connection reset, timeout, etc. Same TX may go through later.
+    Transient,
+    /// TX is invalid, resubmitting it will fail again.
+    Deterministic,
+}
+
+/// Exponential backoff for transient submission failures.
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn should_retry(&self, class: ErrorClass, attempts_done: u32) -> bool {
+        class == ErrorClass::Transient && attempts_done < self.max_attempts
+    }
+
+    /// Delay before retry #`attempt` (counting from 0), jittered within [d/2, d].
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let half = exp / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=exp - half)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Outcomes of TX submissions.
+#[derive(Debug, Default)]
+pub struct SubmissionMetrics {
+    accepted: AtomicU64,
+    rejected: AtomicU64,
+    transient_failures: AtomicU64,
+    retries: AtomicU64,
+}
+
+impl SubmissionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_accepted(&self) {
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn on_failure(&self, class: ErrorClass) {
+        match class {
+            ErrorClass::Transient => &self.transient_failures,
+            ErrorClass::Deterministic => &self.rejected,
+        }
+        .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn on_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn accepted(&self) -> u64 {
+        self.accepted.load(Ordering::Relaxed)
+    }
+
+    pub fn rejected(&self) -> u64 {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    pub fn transient_failures(&self) -> u64 {
+        self.transient_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::network::{ErrorClass, RetryPolicy};
+
+    #[test]
+    fn backoff_is_bounded() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+        };
+        for attempt in 0..64 {
+            let exp =
+                (Duration::from_millis(100) * 2u32.saturating_pow(attempt.min(31))).min(policy.max_delay);
+            let delay = policy.backoff(attempt);
+            assert!(delay >= exp / 2 && delay <= exp);
+        }
+        assert!(policy.should_retry(ErrorClass::Transient, 4));
+        assert!(!policy.should_retry(ErrorClass::Transient, 5));
+        assert!(!policy.should_retry(ErrorClass::Deterministic, 0));
+    }
+}
@@ -1,7 +1,16 @@
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::hash::{Hash, Hasher};
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::info;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::rocks::{migrate, Migration, RocksConfig};
 
 /// Partitioned resource `R`.
 /// `K` - partitioning key;
@@ -9,6 +18,8 @@ use std::marker::PhantomData;
 #[derive(Clone)]
 pub struct Partitioned<const N: usize, K, R> {
     inner: [R; N],
+    /// Keys pinned to particular partitions, the rest is partitioned by hash.
+    assignment: Arc<HashMap<K, usize>>,
     pd: PhantomData<K>,
 }
 
@@ -19,6 +30,7 @@ where
     pub fn new(partitions: [R; N]) -> Self {
         Self {
             inner: partitions,
+            assignment: Arc::new(HashMap::new()),
             pd: PhantomData::default(),
         }
     }
@@ -29,9 +41,18 @@ where
     {
         Self {
             inner: <[R; N]>::try_from(partitions).unwrap(),
+            assignment: Arc::new(HashMap::new()),
             pd: PhantomData::default(),
         }
     }
+
+    /// Pin keys to partitions, e.g. according to [balanced_assignment].
+    pub fn with_assignment(self, assignment: HashMap<K, usize>) -> Self {
+        Self {
+            assignment: Arc::new(assignment),
+            ..self
+        }
+    }
 }
 
 impl<const N: usize, R> Partitioned<N, usize, R> {
@@ -45,14 +66,26 @@ impl<const N: usize, R> Partitioned<N, usize, R> {
 
 impl<const N: usize, K, R> Partitioned<N, K, R>
 where
-    K: Hash,
+    K: Hash + Eq,
 {
+    pub fn partition_of(&self, key: &K) -> usize {
+        match self.assignment.get(key) {
+            Some(partition) => partition % N,
+            None => (hash_partitioning_key(key) % N as u64) as usize,
+        }
+    }
+
     pub fn get(&self, key: K) -> &R {
-        &self.inner[(hash_partitioning_key(key) % N as u64) as usize]
+        &self.inner[self.partition_of(&key)]
     }
 
     pub fn get_mut(&mut self, key: K) -> &mut R {
-        &mut self.inner[(hash_partitioning_key(key) % N as u64) as usize]
+        let partition = self.partition_of(&key);
+        &mut self.inner[partition]
+    }
+
+    pub fn into_inner(self) -> [R; N] {
+        self.inner
     }
 }
 
@@ -61,3 +94,130 @@ pub fn hash_partitioning_key<K: Hash>(key: K) -> u64 {
     key.hash(&mut hasher);
     hasher.finish()
 }
+
+/// Counts events per partitioning key.
+#[derive(Clone)]
+pub struct LoadMeter<K>(Arc<Mutex<HashMap<K, u64>>>);
+
+impl<K: Hash + Eq> LoadMeter<K> {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    pub fn observe(&self, key: K) {
+        *self.0.lock().unwrap().entry(key).or_default() += 1;
+    }
+
+    /// Loads accumulated so far, the meter is reset.
+    pub fn take(&self) -> HashMap<K, u64> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+/// Spread keys across `num_partitions` so that total loads of partitions are as even as possible.
+/// Heaviest keys are placed first, each one into the least loaded partition.
+pub fn balanced_assignment<K: Hash + Eq + Ord>(
+    loads: HashMap<K, u64>,
+    num_partitions: usize,
+) -> HashMap<K, usize> {
+    let mut keys = loads.into_iter().collect::<Vec<_>>();
+    keys.sort_by(|(k0, l0), (k1, l1)| l1.cmp(l0).then_with(|| k0.cmp(k1)));
+    let mut partition_loads = vec![0u64; num_partitions.max(1)];
+    let mut assignment = HashMap::new();
+    for (key, load) in keys {
+        let (lightest, _) = partition_loads
+            .iter()
+            .enumerate()
+            .min_by_key(|(ix, l)| (**l, *ix))
+            .unwrap();
+        partition_loads[lightest] += load;
+        assignment.insert(key, lightest);
+    }
+    assignment
+}
+
+/// Migrations of the assignment store, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+const ASSIGNMENT_KEY: &[u8] = b"assignment";
+
+/// Durable assignment of keys to partitions.
+/// Partitions own the state of their keys, so a new assignment takes effect on the next start.
+#[derive(Clone)]
+pub struct AssignmentRocksDB {
+    db: Arc<rocksdb::DB>,
+}
+
+impl AssignmentRocksDB {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = rocksdb::DB::open_default(conf.db_path).unwrap();
+        migrate(&db, "partition_assignment", MIGRATIONS);
+        Self { db: Arc::new(db) }
+    }
+
+    pub fn load<K: Hash + Eq + DeserializeOwned>(&self) -> HashMap<K, usize> {
+        self.db
+            .get(ASSIGNMENT_KEY)
+            .unwrap()
+            .and_then(|raw| bincode::deserialize::<Vec<(K, usize)>>(&raw).ok())
+            .map(|assignment| assignment.into_iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn save<K: Serialize>(&self, assignment: HashMap<K, usize>) {
+        let assignment = assignment.into_iter().collect::<Vec<_>>();
+        self.db
+            .put(ASSIGNMENT_KEY, bincode::serialize(&assignment).unwrap())
+            .unwrap();
+    }
+}
+
+/// Periodically plan a balanced assignment from loads observed within the last `interval`.
+pub async fn rebalance_periodically<K>(
+    meter: LoadMeter<K>,
+    store: AssignmentRocksDB,
+    num_partitions: usize,
+    interval: Duration,
+) where
+    K: Hash + Eq + Ord + Serialize,
+{
+    loop {
+        tokio::time::sleep(interval).await;
+        let loads = meter.take();
+        if !loads.is_empty() {
+            info!(target: "partitioning", "Planned assignment of {} keys", loads.len());
+            store.save(balanced_assignment(loads, num_partitions));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::partitioning::{balanced_assignment, Partitioned};
+
+    #[test]
+    fn hot_keys_land_in_different_partitions() {
+        let loads = HashMap::from([("a", 100), ("b", 90), ("c", 10), ("d", 5), ("e", 5)]);
+        let assignment = balanced_assignment(loads, 2);
+        assert_ne!(assignment["a"], assignment["b"]);
+        let load_of = |p: usize| {
+            [("a", 100), ("b", 90), ("c", 10), ("d", 5), ("e", 5)]
+                .iter()
+                .filter(|(k, _)| assignment[k] == p)
+                .map(|(_, l)| l)
+                .sum::<u64>()
+        };
+        assert_eq!(load_of(0), 105);
+        assert_eq!(load_of(1), 105);
+    }
+
+    #[test]
+    fn assigned_keys_override_hash_partitioning() {
+        let partitioned = Partitioned::<2, &str, u8>::new([0, 1]);
+        let hashed = partitioned.partition_of(&"a");
+        let pinned = partitioned.with_assignment(HashMap::from([("a", 1 - hashed)]));
+        assert_eq!(*pinned.get("a"), (1 - hashed) as u8);
+    }
+}
@@ -0,0 +1,81 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::rocks::{migrate, Migration, RocksConfig};
+
+/// Maker excluded from matchmaking because of repeated deterministic failures.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedMaker<Pair, StableId> {
+    pub pair: Pair,
+    pub maker: StableId,
+    /// UNIX time (seconds) the maker was (re-)quarantined at.
+    pub since: u64,
+}
+
+/// Durable list of quarantined makers which survives restarts of the agent.
+pub trait Quarantine<Pair, StableId> {
+    fn put(&mut self, entry: QuarantinedMaker<Pair, StableId>);
+    /// Forget maker once it proved to be healthy.
+    fn release(&mut self, maker: &StableId);
+    fn entries(&self) -> Vec<QuarantinedMaker<Pair, StableId>>;
+}
+
+/// Migrations of the quarantine list, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+pub struct QuarantineRocksDB<Pair, StableId> {
+    db: Arc<rocksdb::DB>,
+    pd: PhantomData<(Pair, StableId)>,
+}
+
+impl<Pair, StableId> QuarantineRocksDB<Pair, StableId> {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = rocksdb::DB::open_default(conf.db_path).unwrap();
+        migrate(&db, "quarantine", MIGRATIONS);
+        Self {
+            db: Arc::new(db),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<Pair, StableId> Clone for QuarantineRocksDB<Pair, StableId> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<Pair, StableId> Quarantine<Pair, StableId> for QuarantineRocksDB<Pair, StableId>
+where
+    Pair: Serialize + DeserializeOwned,
+    StableId: Serialize + DeserializeOwned,
+{
+    fn put(&mut self, entry: QuarantinedMaker<Pair, StableId>) {
+        self.db
+            .put(
+                bincode::serialize(&entry.maker).unwrap(),
+                bincode::serialize(&entry).unwrap(),
+            )
+            .unwrap();
+    }
+
+    fn release(&mut self, maker: &StableId) {
+        self.db.delete(bincode::serialize(maker).unwrap()).unwrap();
+    }
+
+    fn entries(&self) -> Vec<QuarantinedMaker<Pair, StableId>> {
+        self.db
+            .iterator(rocksdb::IteratorMode::Start)
+            .filter_map(|i| {
+                let (_, v) = i.unwrap();
+                bincode::deserialize(&v).ok()
+            })
+            .collect()
+    }
+}
@@ -1,6 +1,143 @@
+use log::info;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RocksConfig {
     pub db_path: String,
 }
+
+/// Key the schema version of a DB is stored under.
+const SCHEMA_VERSION_KEY: &[u8] = b"__schema_version";
+
+/// Upgrades a DB from schema version `n` to `n + 1`, where `n` is the position of the migration.
+pub type Migration<DB> = fn(&DB) -> Result<(), rocksdb::Error>;
+
+/// Storage which keeps track of the version of its schema.
+pub trait SchemaVersioned {
+    fn schema_version(&self) -> Option<u32>;
+    fn set_schema_version(&self, version: u32);
+    fn is_empty(&self) -> bool;
+}
+
+impl SchemaVersioned for rocksdb::DB {
+    fn schema_version(&self) -> Option<u32> {
+        self.get(SCHEMA_VERSION_KEY)
+            .unwrap()
+            .and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn set_schema_version(&self, version: u32) {
+        self.put(SCHEMA_VERSION_KEY, bincode::serialize(&version).unwrap())
+            .unwrap()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.iterator(rocksdb::IteratorMode::Start).next().is_none()
+    }
+}
+
+impl SchemaVersioned for rocksdb::OptimisticTransactionDB {
+    fn schema_version(&self) -> Option<u32> {
+        self.get(SCHEMA_VERSION_KEY)
+            .unwrap()
+            .and_then(|raw| bincode::deserialize(&raw).ok())
+    }
+
+    fn set_schema_version(&self, version: u32) {
+        self.put(SCHEMA_VERSION_KEY, bincode::serialize(&version).unwrap())
+            .unwrap()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.iterator(rocksdb::IteratorMode::Start).next().is_none()
+    }
+}
+
+/// Bring the DB to the latest schema version, which is the number of `migrations`.
+/// DBs written before versioning was introduced are considered to be at version 0,
+/// fresh ones are stamped with the latest version right away.
+/// Version is bumped after every step, so an interrupted upgrade resumes where it stopped.
+pub fn migrate<DB: SchemaVersioned>(db: &DB, name: &str, migrations: &[Migration<DB>]) {
+    let latest = migrations.len() as u32;
+    let current = match db.schema_version() {
+        Some(version) => version,
+        None if db.is_empty() => latest,
+        None => 0,
+    };
+    if current > latest {
+        panic!(
+            "{} DB has schema version {}, while at most {} is supported",
+            name, current, latest
+        );
+    }
+    for (version, migration) in migrations.iter().enumerate().skip(current as usize) {
+        let next_version = version as u32 + 1;
+        info!("Migrating {} DB to schema version {}", name, next_version);
+        if let Err(err) = migration(db) {
+            panic!(
+                "Migration of {} DB to schema version {} failed: {}",
+                name, next_version, err
+            );
+        }
+        db.set_schema_version(next_version);
+    }
+    db.set_schema_version(latest);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use crate::rocks::{migrate, Migration, SchemaVersioned};
+
+    fn db_for_test() -> rocksdb::DB {
+        let rnd = rand::thread_rng().next_u32();
+        rocksdb::DB::open_default(format!("./tmp/{}", rnd)).unwrap()
+    }
+
+    fn rename_key(db: &rocksdb::DB) -> Result<(), rocksdb::Error> {
+        if let Some(value) = db.get(b"old")? {
+            db.put(b"new", value)?;
+            db.delete(b"old")?;
+        }
+        Ok(())
+    }
+
+    fn double_value(db: &rocksdb::DB) -> Result<(), rocksdb::Error> {
+        if let Some(value) = db.get(b"new")? {
+            db.put(b"new", [value.clone(), value].concat())?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn fresh_db_is_stamped_with_latest_version() {
+        let db = db_for_test();
+        let migrations: [Migration<rocksdb::DB>; 2] = [rename_key, double_value];
+        migrate(&db, "test", &migrations);
+        assert_eq!(db.schema_version(), Some(2));
+    }
+
+    #[test]
+    fn legacy_db_is_migrated_step_by_step() {
+        let db = db_for_test();
+        let migrations: [Migration<rocksdb::DB>; 2] = [rename_key, double_value];
+        db.put(b"old", b"x").unwrap();
+        migrate(&db, "test", &migrations[..1]);
+        assert_eq!(db.schema_version(), Some(1));
+        assert_eq!(db.get(b"new").unwrap(), Some(b"x".to_vec()));
+        migrate(&db, "test", &migrations);
+        assert_eq!(db.schema_version(), Some(2));
+        assert_eq!(db.get(b"new").unwrap(), Some(b"xx".to_vec()));
+        assert_eq!(db.get(b"old").unwrap(), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn db_from_newer_release_is_rejected() {
+        let db = db_for_test();
+        let migrations: [Migration<rocksdb::DB>; 1] = [rename_key];
+        db.set_schema_version(3);
+        migrate(&db, "test", &migrations);
+    }
+}
@@ -0,0 +1,64 @@
+use std::fmt::{Debug, Display, Formatter};
+
+const REDACTED: &str = "<redacted>";
+
+/// Secret (private key, API token, ...) which is never revealed by `Display` or `Debug`,
+/// so config structs holding it are safe to log. Use [Sensitive::expose] to access the value.
+#[derive(Clone, Eq, PartialEq, serde::Deserialize)]
+#[serde(transparent)]
+pub struct Sensitive<T>(T);
+
+impl<T> Sensitive<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn into_exposed(self) -> T {
+        self.0
+    }
+
+    pub fn map<R, F: FnOnce(T) -> R>(self, f: F) -> Sensitive<R> {
+        Sensitive(f(self.0))
+    }
+}
+
+impl<T> From<T> for Sensitive<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+impl<T> Debug for Sensitive<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> Display for Sensitive<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sensitive::Sensitive;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Conf {
+        key: Sensitive<String>,
+    }
+
+    #[test]
+    fn secret_is_not_revealed_by_formatting() {
+        let conf: Conf = serde_json::from_str(r#"{"key": "xprv1secret"}"#).unwrap();
+        assert_eq!(conf.key.expose(), "xprv1secret");
+        assert!(!format!("{:?}", conf).contains("xprv1secret"));
+        assert!(!format!("{:#?}", conf).contains("xprv1secret"));
+        assert_eq!(conf.key.to_string(), "<redacted>");
+    }
+}
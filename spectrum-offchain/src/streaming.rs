@@ -2,6 +2,16 @@ use std::pin::Pin;
 
 use futures::Stream;
 
+pub use crate::streaming::buffered_by_key::{buffered_ordered_by_key, BufferedOrderedByKey};
+pub use crate::streaming::parallel::map_parallel_ordered;
+pub use crate::streaming::retry::retry_with_backoff;
+pub use crate::streaming::timeout::{timeout_per_item, ItemTimedOut, TimeoutPerItem};
+
+pub mod buffered_by_key;
+pub mod parallel;
+pub mod retry;
+pub mod timeout;
+
 pub fn boxed<'a, T>(s: impl Stream<Item = T> + 'a) -> Pin<Box<dyn Stream<Item = T> + 'a>> {
     Box::pin(s)
 }
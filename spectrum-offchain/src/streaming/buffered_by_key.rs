@@ -0,0 +1,134 @@
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::stream::{Fuse, FuturesUnordered};
+use futures::{Stream, StreamExt};
+use pin_project_lite::pin_project;
+
+pin_project! {
+    #[must_use = "streams do nothing unless polled"]
+    pub struct BufferedOrderedByKey<S, K, Fut: Future> {
+        #[pin]
+        upstream: Fuse<S>,
+        in_flight: FuturesUnordered<Tagged<K, Fut>>,
+        limit: usize,
+        next_seq: u64,
+        // Sequence numbers of unfinished futures by key, in arrival order.
+        queues: HashMap<K, VecDeque<u64>>,
+        // Finished futures held back until their predecessors with the same key finish.
+        finished: HashMap<u64, Fut::Output>,
+        ready: VecDeque<Fut::Output>,
+    }
+}
+
+/// Runs up to `limit` futures from `stream` concurrently. Outputs of futures with the same key
+/// are emitted in the order the futures arrived in, outputs of different keys are not ordered.
+pub fn buffered_ordered_by_key<S, K, Fut>(stream: S, limit: usize) -> BufferedOrderedByKey<S, K, Fut>
+where
+    S: Stream<Item = (K, Fut)>,
+    K: Clone + Eq + Hash + Unpin,
+    Fut: Future,
+{
+    BufferedOrderedByKey {
+        upstream: stream.fuse(),
+        in_flight: FuturesUnordered::new(),
+        limit: limit.max(1),
+        next_seq: 0,
+        queues: HashMap::new(),
+        finished: HashMap::new(),
+        ready: VecDeque::new(),
+    }
+}
+
+impl<S, K, Fut> Stream for BufferedOrderedByKey<S, K, Fut>
+where
+    S: Stream<Item = (K, Fut)>,
+    K: Clone + Eq + Hash + Unpin,
+    Fut: Future,
+{
+    type Item = Fut::Output;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            while this.in_flight.len() + this.finished.len() < *this.limit {
+                match this.upstream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some((key, fut))) => {
+                        let seq = *this.next_seq;
+                        *this.next_seq += 1;
+                        this.queues.entry(key.clone()).or_default().push_back(seq);
+                        this.in_flight.push(Tagged {
+                            seq,
+                            key: Some(key),
+                            fut: Box::pin(fut),
+                        });
+                    }
+                    _ => break,
+                }
+            }
+            if let Some(out) = this.ready.pop_front() {
+                return Poll::Ready(Some(out));
+            }
+            match this.in_flight.poll_next_unpin(cx) {
+                Poll::Ready(Some((seq, key, out))) => {
+                    this.finished.insert(seq, out);
+                    if let Some(queue) = this.queues.get_mut(&key) {
+                        while let Some(out) = queue.front().and_then(|s| this.finished.remove(s)) {
+                            queue.pop_front();
+                            this.ready.push_back(out);
+                        }
+                        if queue.is_empty() {
+                            this.queues.remove(&key);
+                        }
+                    }
+                }
+                // Nothing in flight, so nothing can be held back either.
+                Poll::Ready(None) if this.upstream.is_done() => return Poll::Ready(None),
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+struct Tagged<K, Fut> {
+    seq: u64,
+    key: Option<K>,
+    fut: Pin<Box<Fut>>,
+}
+
+impl<K: Unpin, Fut: Future> Future for Tagged<K, Fut> {
+    type Output = (u64, K, Fut::Output);
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.fut.as_mut().poll(cx) {
+            Poll::Ready(out) => {
+                Poll::Ready((self.seq, self.key.take().expect("polled after completion"), out))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{stream, FutureExt, StreamExt};
+    use futures_timer::Delay;
+
+    use crate::streaming::buffered_ordered_by_key;
+
+    #[tokio::test]
+    async fn preserves_order_within_key_only() {
+        let items = vec![("a", 1, 30), ("a", 2, 0), ("b", 3, 10)]
+            .into_iter()
+            .map(|(key, out, millis)| (key, Delay::new(Duration::from_millis(millis)).map(move |_| out)));
+        let outputs = buffered_ordered_by_key(stream::iter(items), 8)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(outputs, vec![3, 1, 2]);
+    }
+}
@@ -0,0 +1,40 @@
+use futures::{FutureExt, Stream, StreamExt};
+
+/// Applies CPU-bound `f` to items of `stream` on blocking worker threads, up to `parallelism`
+/// items at a time. Results are emitted in the order items arrived in regardless of which
+/// finished first, so downstream state transitions observe the original sequence.
+pub fn map_parallel_ordered<S, A, B, F>(stream: S, parallelism: usize, f: F) -> impl Stream<Item = B>
+where
+    S: Stream<Item = A>,
+    A: Send + 'static,
+    B: Send + 'static,
+    F: Fn(A) -> B + Send + Copy + 'static,
+{
+    stream
+        .map(move |item| {
+            tokio::task::spawn_blocking(move || f(item)).map(|res| res.expect("Worker panicked"))
+        })
+        .buffered(parallelism.max(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use futures::{stream, StreamExt};
+
+    use crate::streaming::map_parallel_ordered;
+
+    #[tokio::test]
+    async fn preserves_arrival_order() {
+        // Earlier items take longer, so they finish last.
+        let outputs = map_parallel_ordered(stream::iter(0..8u64), 4, |i| {
+            thread::sleep(Duration::from_millis(40 - i * 5));
+            i * 10
+        })
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(outputs, (0..8).map(|i| i * 10).collect::<Vec<_>>());
+    }
+}
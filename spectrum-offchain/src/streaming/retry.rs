@@ -0,0 +1,75 @@
+use std::fmt::Display;
+use std::future::Future;
+
+use futures_timer::Delay;
+use log::warn;
+
+use crate::network::RetryPolicy;
+
+/// Run `op` until it succeeds, backing off between attempts as the `policy` prescribes.
+/// The last error is returned once `policy.max_attempts` retries are exhausted.
+pub async fn retry_with_backoff<T, E, F, Fut>(policy: RetryPolicy, mut op: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: Display,
+{
+    let mut attempts_done = 0;
+    loop {
+        match op().await {
+            Ok(res) => return Ok(res),
+            Err(err) if attempts_done < policy.max_attempts => {
+                let delay = policy.backoff(attempts_done);
+                warn!(
+                    "Attempt #{} failed: {}, retrying in {:?}",
+                    attempts_done, err, delay
+                );
+                Delay::new(delay).await;
+                attempts_done += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    use crate::network::RetryPolicy;
+    use crate::streaming::retry_with_backoff;
+
+    const POLICY: RetryPolicy = RetryPolicy {
+        max_attempts: 2,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(2),
+    };
+
+    #[tokio::test]
+    async fn retries_until_success() {
+        let calls = Cell::new(0);
+        let res = retry_with_backoff(POLICY, || async {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err("not yet")
+            } else {
+                Ok(calls.get())
+            }
+        })
+        .await;
+        assert_eq!(res, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let calls = Cell::new(0);
+        let res: Result<(), _> = retry_with_backoff(POLICY, || async {
+            calls.set(calls.get() + 1);
+            Err("never")
+        })
+        .await;
+        assert_eq!(res, Err("never"));
+        assert_eq!(calls.get(), 3);
+    }
+}
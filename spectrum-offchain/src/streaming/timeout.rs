@@ -0,0 +1,53 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use derive_more::Display;
+use futures::Stream;
+use futures_timer::Delay;
+use pin_project_lite::pin_project;
+
+/// Upstream didn't produce an item in time.
+#[derive(Debug, Display, Copy, Clone, Eq, PartialEq)]
+#[display(fmt = "No item within {:?}", _0)]
+pub struct ItemTimedOut(pub Duration);
+
+pin_project! {
+    #[must_use = "streams do nothing unless polled"]
+    pub struct TimeoutPerItem<S> {
+        #[pin]
+        stream: S,
+        timeout: Duration,
+        timer: Option<Delay>,
+    }
+}
+
+/// Emits [ItemTimedOut] each time `stream` stays silent for `timeout`.
+/// The upstream is kept, so the consumer decides whether to wait further or give up.
+pub fn timeout_per_item<S: Stream>(stream: S, timeout: Duration) -> TimeoutPerItem<S> {
+    TimeoutPerItem {
+        stream,
+        timeout,
+        timer: None,
+    }
+}
+
+impl<S: Stream> Stream for TimeoutPerItem<S> {
+    type Item = Result<S::Item, ItemTimedOut>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        if let Poll::Ready(next) = this.stream.poll_next(cx) {
+            *this.timer = None;
+            return Poll::Ready(next.map(Ok));
+        }
+        let timeout = *this.timeout;
+        let timer = this.timer.get_or_insert_with(|| Delay::new(timeout));
+        if Pin::new(timer).poll(cx).is_ready() {
+            *this.timer = None;
+            return Poll::Ready(Some(Err(ItemTimedOut(timeout))));
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Progress of chain synchronization shared between the sync loop and its observers.
+#[derive(Debug, Default)]
+pub struct SyncProgress {
+    /// Slot of the latest processed point.
+    current_slot: AtomicU64,
+    /// Slot of the node's tip.
+    tip_slot: AtomicU64,
+}
+
+impl SyncProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_point(&self, slot: u64) {
+        self.current_slot.store(slot, Ordering::Relaxed);
+    }
+
+    pub fn on_tip(&self, slot: u64) {
+        self.tip_slot.store(slot, Ordering::Relaxed);
+    }
+
+    pub fn current_slot(&self) -> u64 {
+        self.current_slot.load(Ordering::Relaxed)
+    }
+
+    pub fn tip_slot(&self) -> u64 {
+        self.tip_slot.load(Ordering::Relaxed)
+    }
+
+    pub fn lag_slots(&self) -> u64 {
+        self.tip_slot().saturating_sub(self.current_slot())
+    }
+
+    /// Lag in time, assuming one-second slots.
+    pub fn lag(&self) -> Duration {
+        Duration::from_secs(self.lag_slots())
+    }
+
+    pub fn lags_more_than(&self, max_lag_slots: u64) -> bool {
+        self.lag_slots() > max_lag_slots
+    }
+}
+
+/// Holds back actions relying on fresh chain state while sync lags too far behind the tip.
+#[derive(Debug, Clone)]
+pub struct SyncLagGuard {
+    progress: Arc<SyncProgress>,
+    max_lag_slots: u64,
+}
+
+impl SyncLagGuard {
+    pub fn new(progress: Arc<SyncProgress>, max_lag_slots: u64) -> Self {
+        Self {
+            progress,
+            max_lag_slots,
+        }
+    }
+
+    pub fn is_lagging(&self) -> bool {
+        self.progress.lags_more_than(self.max_lag_slots)
+    }
+
+    pub fn current_slot(&self) -> u64 {
+        self.progress.current_slot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sync_progress::SyncProgress;
+
+    #[test]
+    fn lag_never_underflows() {
+        let progress = SyncProgress::new();
+        progress.on_tip(100);
+        progress.on_point(40);
+        assert_eq!(progress.lag_slots(), 60);
+        assert!(progress.lags_more_than(59));
+        assert!(!progress.lags_more_than(60));
+        // Tip may be reported later than the point it covers.
+        progress.on_point(120);
+        assert_eq!(progress.lag_slots(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::kv_backend::KvBackend;
+use crate::rocks::{migrate, Migration, RocksConfig};
+
+/// TX submitted by the executor whose outcome is not yet known.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingTx<Pair, TxHash, Ver> {
+    pub pair: Pair,
+    pub tx_hash: TxHash,
+    pub consumed_versions: Vec<Ver>,
+    /// UNIX time (seconds) of submission.
+    pub submitted_at: u64,
+}
+
+/// Durable record of in-flight TXs which survives restarts of the agent.
+pub trait TxJournal<Pair, TxHash, Ver> {
+    fn record(&mut self, tx: PendingTx<Pair, TxHash, Ver>);
+    /// Forget TX once its outcome is known.
+    fn resolve(&mut self, tx_hash: &TxHash);
+    fn pending(&self) -> Vec<PendingTx<Pair, TxHash, Ver>>;
+}
+
+/// Migrations of the journal, see [migrate].
+const MIGRATIONS: &[Migration<rocksdb::DB>] = &[];
+
+pub struct TxJournalRocksDB<Pair, TxHash, Ver> {
+    db: Arc<rocksdb::DB>,
+    pd: PhantomData<(Pair, TxHash, Ver)>,
+}
+
+impl<Pair, TxHash, Ver> TxJournalRocksDB<Pair, TxHash, Ver> {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = rocksdb::DB::open_default(conf.db_path).unwrap();
+        migrate(&db, "tx_journal", MIGRATIONS);
+        Self {
+            db: Arc::new(db),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<Pair, TxHash, Ver> Clone for TxJournalRocksDB<Pair, TxHash, Ver> {
+    fn clone(&self) -> Self {
+        Self {
+            db: Arc::clone(&self.db),
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<Pair, TxHash, Ver> TxJournal<Pair, TxHash, Ver> for TxJournalRocksDB<Pair, TxHash, Ver>
+where
+    Pair: Serialize + DeserializeOwned,
+    TxHash: Serialize + DeserializeOwned,
+    Ver: Serialize + DeserializeOwned,
+{
+    fn record(&mut self, tx: PendingTx<Pair, TxHash, Ver>) {
+        self.db
+            .put(
+                bincode::serialize(&tx.tx_hash).unwrap(),
+                bincode::serialize(&tx).unwrap(),
+            )
+            .unwrap();
+    }
+
+    fn resolve(&mut self, tx_hash: &TxHash) {
+        self.db.delete(bincode::serialize(tx_hash).unwrap()).unwrap();
+    }
+
+    fn pending(&self) -> Vec<PendingTx<Pair, TxHash, Ver>> {
+        self.db
+            .iterator(rocksdb::IteratorMode::Start)
+            .filter_map(|i| {
+                let (_, v) = i.unwrap();
+                bincode::deserialize(&v).ok()
+            })
+            .collect()
+    }
+}
+
+/// [TxJournal] persisted in a [KvBackend].
+/// Keys are prefixed, so the backend can be shared with the state index, wrap it into
+/// [crate::kv_backend::Batched] to record TXs along with the state they produced.
+pub struct PersistentTxJournal<B, Pair, TxHash, Ver> {
+    backend: B,
+    pd: PhantomData<(Pair, TxHash, Ver)>,
+}
+
+impl<B, Pair, TxHash, Ver> PersistentTxJournal<B, Pair, TxHash, Ver> {
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            pd: PhantomData,
+        }
+    }
+}
+
+impl<B: Clone, Pair, TxHash, Ver> Clone for PersistentTxJournal<B, Pair, TxHash, Ver> {
+    fn clone(&self) -> Self {
+        Self::new(self.backend.clone())
+    }
+}
+
+const PENDING_TX_PREFIX: u8 = 9u8;
+
+fn pending_tx_key<TxHash: Serialize>(tx_hash: &TxHash) -> Vec<u8> {
+    let mut key = vec![PENDING_TX_PREFIX];
+    key.extend(bincode::serialize(tx_hash).unwrap());
+    key
+}
+
+impl<B, Pair, TxHash, Ver> TxJournal<Pair, TxHash, Ver> for PersistentTxJournal<B, Pair, TxHash, Ver>
+where
+    B: KvBackend,
+    Pair: Serialize + DeserializeOwned,
+    TxHash: Serialize + DeserializeOwned,
+    Ver: Serialize + DeserializeOwned,
+{
+    fn record(&mut self, tx: PendingTx<Pair, TxHash, Ver>) {
+        self.backend
+            .put(&pending_tx_key(&tx.tx_hash), &bincode::serialize(&tx).unwrap());
+    }
+
+    fn resolve(&mut self, tx_hash: &TxHash) {
+        self.backend.delete(&pending_tx_key(tx_hash));
+    }
+
+    fn pending(&self) -> Vec<PendingTx<Pair, TxHash, Ver>> {
+        self.backend
+            .scan_prefix(&[PENDING_TX_PREFIX])
+            .into_iter()
+            .filter_map(|(_, v)| bincode::deserialize(&v).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use crate::kv_backend::{open_backend, Batched, KvBackend, KvBackendConfig};
+    use crate::tx_journal::{PendingTx, PersistentTxJournal, TxJournal};
+
+    #[test]
+    fn journal_is_written_along_with_batch() {
+        let backend = open_backend(&KvBackendConfig::RocksDB {
+            db_path: format!("./tmp/{}", rand::thread_rng().next_u32()),
+        });
+        let batched = Batched::new(backend.clone());
+        let mut journal = PersistentTxJournal::<_, u8, u64, u32>::new(batched.clone());
+        batched.begin();
+        journal.record(PendingTx {
+            pair: 1,
+            tx_hash: 2,
+            consumed_versions: vec![3],
+            submitted_at: 4,
+        });
+        assert_eq!(journal.pending().len(), 1);
+        assert!(PersistentTxJournal::<_, u8, u64, u32>::new(backend.clone())
+            .pending()
+            .is_empty());
+        batched.commit();
+        let restored = PersistentTxJournal::<_, u8, u64, u32>::new(backend.clone()).pending();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].consumed_versions, vec![3]);
+        journal.resolve(&2);
+        assert!(PersistentTxJournal::<_, u8, u64, u32>::new(backend)
+            .pending()
+            .is_empty());
+    }
+}
@@ -0,0 +1,8 @@
+use std::fmt::Display;
+
+/// Dry-run checks of a TX candidate performed before it gets proved and submitted.
+pub trait TxValidator<TxCandidate, Bearer> {
+    type Error: Display;
+    /// Validate `candidate` against the outputs it consumes.
+    fn validate(&self, candidate: &TxCandidate, consumed: &[Bearer]) -> Result<(), Self::Error>;
+}
@@ -0,0 +1,206 @@
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+
+use cml_crypto::{RawBytesEncoding, ScriptHash};
+use derive_more::From;
+use hex::FromHexError;
+use spectrum_offchain::data::Has;
+
+use crate::entities::onchain::inflation_box::compute_inflation_box_script_hash;
+use crate::entities::onchain::permission_manager::compute_perm_manager_policy_id;
+use crate::entities::onchain::smart_farm::compute_mint_farm_auth_token_policy_id;
+use crate::entities::onchain::voting_escrow::{
+    compute_mint_weighting_power_policy_id, compute_voting_escrow_policy_id,
+};
+use crate::entities::onchain::weighting_poll::compute_mint_wp_auth_token_policy_id;
+use crate::protocol_config::{
+    EDaoMSigAuthPolicy, FactoryAuthPolicy, FarmAuthPolicy, GTAuthPolicy, PermManagerAuthPolicy, SplashPolicy,
+    VEFactoryAuthPolicy, WPAuthPolicy,
+};
+use crate::GenesisEpochStartTime;
+
+/// Unparametrized validator.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaoScriptData {
+    /// Hex-encoded flat script.
+    pub script: String,
+    /// Expected hash of the script once parameters are applied.
+    /// Can only be known upfront for validators parametrized by protocol constants.
+    pub hash: Option<ScriptHash>,
+}
+
+/// Bundle of DAO validators deployed to a particular network.
+#[derive(Clone, Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaoScriptBytes {
+    pub inflation: DaoScriptData,
+    pub wp_factory: DaoScriptData,
+    pub mint_wp_auth_token: DaoScriptData,
+    pub voting_escrow: DaoScriptData,
+    pub mint_weighting_power: DaoScriptData,
+    pub mint_farm_auth_token: DaoScriptData,
+    pub perm_manager: DaoScriptData,
+}
+
+#[derive(Debug, From)]
+pub enum DeploymentError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    #[from(ignore)]
+    MalformedScript(&'static str, FromHexError),
+    #[from(ignore)]
+    HashMismatch {
+        validator: &'static str,
+        expected: ScriptHash,
+        actual: ScriptHash,
+    },
+}
+
+impl Display for DeploymentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeploymentError::Io(err) => write!(f, "Cannot read deployment: {}", err),
+            DeploymentError::Json(err) => write!(f, "Invalid deployment: {}", err),
+            DeploymentError::MalformedScript(validator, err) => {
+                write!(f, "Malformed script '{}': {}", validator, err)
+            }
+            DeploymentError::HashMismatch {
+                validator,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "Hash of '{}' is {} while {} is expected",
+                validator,
+                actual.to_hex(),
+                expected.to_hex()
+            ),
+        }
+    }
+}
+
+impl DaoScriptBytes {
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, DeploymentError> {
+        let raw_deployment = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&raw_deployment)?)
+    }
+
+    /// Make sure all scripts are well-formed and those parametrized by protocol constants
+    /// hash to the values declared in the bundle.
+    pub fn verify<Ctx>(&self, ctx: &Ctx) -> Result<(), DeploymentError>
+    where
+        Ctx: Has<SplashPolicy>
+            + Has<WPAuthPolicy>
+            + Has<FarmAuthPolicy>
+            + Has<FactoryAuthPolicy>
+            + Has<VEFactoryAuthPolicy>
+            + Has<EDaoMSigAuthPolicy>
+            + Has<PermManagerAuthPolicy>
+            + Has<GTAuthPolicy>
+            + Has<GenesisEpochStartTime>,
+    {
+        for (validator, data) in self.named() {
+            hex::decode(&data.script).map_err(|err| DeploymentError::MalformedScript(validator, err))?;
+        }
+        let splash_policy = ctx.select::<SplashPolicy>().0;
+        let wp_auth_policy = ctx.select::<WPAuthPolicy>().0;
+        let factory_auth_policy = ctx.select::<FactoryAuthPolicy>().0;
+        let genesis_time = ctx.select::<GenesisEpochStartTime>().0;
+        let weighting_power_policy = compute_mint_weighting_power_policy_id(
+            genesis_time,
+            wp_auth_policy,
+            ctx.select::<GTAuthPolicy>().0,
+            &self.mint_weighting_power.script,
+        );
+        check_hash(
+            "inflation",
+            &self.inflation,
+            compute_inflation_box_script_hash(
+                splash_policy,
+                wp_auth_policy,
+                weighting_power_policy,
+                genesis_time,
+                &self.inflation.script,
+            ),
+        )?;
+        check_hash(
+            "mintWpAuthToken",
+            &self.mint_wp_auth_token,
+            compute_mint_wp_auth_token_policy_id(
+                splash_policy,
+                ctx.select::<FarmAuthPolicy>().0,
+                factory_auth_policy,
+                genesis_time,
+                &self.mint_wp_auth_token.script,
+            ),
+        )?;
+        check_hash(
+            "votingEscrow",
+            &self.voting_escrow,
+            compute_voting_escrow_policy_id(
+                ctx.select::<VEFactoryAuthPolicy>().0,
+                &self.voting_escrow.script,
+            ),
+        )?;
+        check_hash(
+            "mintFarmAuthToken",
+            &self.mint_farm_auth_token,
+            compute_mint_farm_auth_token_policy_id(
+                splash_policy,
+                factory_auth_policy,
+                &self.mint_farm_auth_token.script,
+            ),
+        )?;
+        check_hash(
+            "permManager",
+            &self.perm_manager,
+            compute_perm_manager_policy_id(
+                ctx.select::<EDaoMSigAuthPolicy>().0,
+                ctx.select::<PermManagerAuthPolicy>().0,
+                &self.perm_manager.script,
+            ),
+        )
+    }
+
+    fn named(&self) -> [(&'static str, &DaoScriptData); 7] {
+        [
+            ("inflation", &self.inflation),
+            ("wpFactory", &self.wp_factory),
+            ("mintWpAuthToken", &self.mint_wp_auth_token),
+            ("votingEscrow", &self.voting_escrow),
+            ("mintWeightingPower", &self.mint_weighting_power),
+            ("mintFarmAuthToken", &self.mint_farm_auth_token),
+            ("permManager", &self.perm_manager),
+        ]
+    }
+}
+
+fn check_hash(
+    validator: &'static str,
+    data: &DaoScriptData,
+    actual: ScriptHash,
+) -> Result<(), DeploymentError> {
+    match data.hash {
+        Some(expected) if expected != actual => Err(DeploymentError::HashMismatch {
+            validator,
+            expected,
+            actual,
+        }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DaoScriptBytes;
+
+    #[test]
+    fn mainnet_bundle_is_well_formed() {
+        let scripts: DaoScriptBytes =
+            serde_json::from_str(include_str!("../resources/mainnet.scripts.json")).unwrap();
+        for (_, data) in scripts.named() {
+            assert!(hex::decode(&data.script).is_ok());
+        }
+    }
+}
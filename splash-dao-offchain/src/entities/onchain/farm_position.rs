@@ -0,0 +1,186 @@
+use std::fmt::Formatter;
+
+use cml_chain::plutus::{ConstrPlutusData, ExUnits, PlutusData};
+use cml_chain::PolicyId;
+use cml_crypto::{Ed25519KeyHash, RawBytesEncoding};
+
+use serde::{Deserialize, Serialize};
+use spectrum_cardano_lib::plutus_data::{
+    update_typed_datum, ConstrPlutusDataExtension, DatumUpdateError, IntoPlutusData, PlutusDataExtension,
+};
+use spectrum_cardano_lib::types::TryFromPData;
+use spectrum_cardano_lib::{AssetName, OutputRef, Token};
+use spectrum_offchain::data::{Identifier, Stable};
+
+use crate::entities::onchain::smart_farm::FarmId;
+use crate::entities::Snapshot;
+use crate::time::ProtocolEpoch;
+
+pub type FarmPositionSnapshot = Snapshot<FarmPosition, OutputRef>;
+
+/// Identified by the position NFT.
+#[derive(
+    Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash, Debug, derive_more::From, Serialize, Deserialize,
+)]
+pub struct FarmPositionId(Token);
+
+impl Identifier for FarmPositionId {
+    type For = FarmPositionSnapshot;
+}
+
+impl std::fmt::Display for FarmPositionId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format!("FarmPositionId: {}.{}", self.0 .0, self.0 .1))
+    }
+}
+
+/// Stake of a user in a [SmartFarm].
+///
+/// [SmartFarm]: crate::entities::onchain::smart_farm::SmartFarm
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FarmPosition {
+    pub id: FarmPositionId,
+    pub farm_id: FarmId,
+    pub owner: Ed25519KeyHash,
+    pub stake: u64,
+    /// Rewards are claimed up to this epoch (inclusive).
+    pub last_claimed_epoch: ProtocolEpoch,
+}
+
+impl Stable for FarmPosition {
+    type StableId = FarmPositionId;
+    fn stable_id(&self) -> Self::StableId {
+        self.id
+    }
+    fn is_quasi_permanent(&self) -> bool {
+        false
+    }
+}
+
+/// Emission distributed to the farm in the given epoch.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct EpochRewards {
+    pub epoch: ProtocolEpoch,
+    pub emission: u64,
+    /// Total stake in the farm during the epoch.
+    pub total_stake: u64,
+}
+
+impl FarmPosition {
+    /// Rewards accrued since the last claim. Only completed epochs (preceding `current_epoch`) count.
+    pub fn accrued_rewards(&self, rewards: &[EpochRewards], current_epoch: ProtocolEpoch) -> u64 {
+        rewards
+            .iter()
+            .filter(|r| r.epoch > self.last_claimed_epoch && r.epoch < current_epoch && r.total_stake > 0)
+            .map(|r| ((r.emission as u128) * (self.stake as u128) / (r.total_stake as u128)) as u64)
+            .sum()
+    }
+}
+
+struct DatumMapping {
+    id: usize,
+    farm_id: usize,
+    owner: usize,
+    stake: usize,
+    last_claimed_epoch: usize,
+}
+
+const DATUM_MAPPING: DatumMapping = DatumMapping {
+    id: 0,
+    farm_id: 1,
+    owner: 2,
+    stake: 3,
+    last_claimed_epoch: 4,
+};
+
+impl TryFromPData for FarmPosition {
+    fn try_from_pd(data: PlutusData) -> Option<Self> {
+        let mut cpd = data.into_constr_pd()?;
+        let mut id_cpd = cpd.take_field(DATUM_MAPPING.id)?.into_constr_pd()?;
+        let policy = PolicyId::from_raw_bytes(&id_cpd.take_field(0)?.into_bytes()?).ok()?;
+        let name = AssetName::try_from(id_cpd.take_field(1)?.into_bytes()?).ok()?;
+        Some(Self {
+            id: FarmPositionId::from((policy, name)),
+            farm_id: FarmId(cpd.take_field(DATUM_MAPPING.farm_id)?.into_u64()?),
+            owner: Ed25519KeyHash::from_raw_bytes(&cpd.take_field(DATUM_MAPPING.owner)?.into_bytes()?)
+                .ok()?,
+            stake: cpd.take_field(DATUM_MAPPING.stake)?.into_u64()?,
+            last_claimed_epoch: cpd.take_field(DATUM_MAPPING.last_claimed_epoch)?.into_u64()? as u32,
+        })
+    }
+}
+
+pub fn update_farm_position(
+    data: &mut PlutusData,
+    last_claimed_epoch: ProtocolEpoch,
+) -> Result<(), DatumUpdateError> {
+    update_typed_datum::<FarmPosition, _>(
+        data,
+        [(
+            DATUM_MAPPING.last_claimed_epoch,
+            PlutusData::new_integer(last_claimed_epoch.into()),
+        )],
+    )
+}
+
+pub enum FarmPositionAction {
+    /// Claim rewards from the farm spent at `farm_in_ix`.
+    Claim { farm_in_ix: u32, successor_out_ix: u32 },
+}
+
+impl IntoPlutusData for FarmPositionAction {
+    fn into_pd(self) -> PlutusData {
+        match self {
+            FarmPositionAction::Claim {
+                farm_in_ix,
+                successor_out_ix,
+            } => PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+                0,
+                vec![
+                    PlutusData::new_integer(farm_in_ix.into()),
+                    PlutusData::new_integer(successor_out_ix.into()),
+                ],
+            )),
+        }
+    }
+}
+
+pub const FARM_POSITION_EX_UNITS: ExUnits = ExUnits {
+    mem: 500_000,
+    steps: 200_000_000,
+    encodings: None,
+};
+
+#[cfg(test)]
+mod tests {
+    use cml_chain::PolicyId;
+    use cml_crypto::Ed25519KeyHash;
+
+    use spectrum_cardano_lib::AssetName;
+
+    use crate::entities::onchain::smart_farm::FarmId;
+
+    use super::{EpochRewards, FarmPosition, FarmPositionId};
+
+    #[test]
+    fn rewards_accrue_over_unclaimed_completed_epochs() {
+        let position = FarmPosition {
+            id: FarmPositionId::from((
+                PolicyId::from([0u8; 28]),
+                AssetName::utf8_unsafe(String::from("fp")),
+            )),
+            farm_id: FarmId(0),
+            owner: Ed25519KeyHash::from([0u8; 28]),
+            stake: 25,
+            last_claimed_epoch: 1,
+        };
+        let rewards = [1, 2, 3, 4].map(|epoch| EpochRewards {
+            epoch,
+            emission: 1000,
+            total_stake: 100,
+        });
+        // Epoch 1 is claimed already, epoch 4 is in progress.
+        assert_eq!(position.accrued_rewards(&rewards, 4), 500);
+        assert_eq!(position.accrued_rewards(&rewards, 2), 0);
+    }
+}
@@ -2,26 +2,26 @@ use cml_chain::plutus::{ExUnits, PlutusData};
 
 use cml_chain::PolicyId;
 use cml_crypto::{RawBytesEncoding, ScriptHash};
-use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use serde::{Deserialize, Serialize};
+use spectrum_cardano_lib::plutus_data::{DatumUpdateError, IntoPlutusData};
 use spectrum_cardano_lib::{TaggedAmount, Token};
 use spectrum_offchain::data::{EntitySnapshot, Identifier, Stable};
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 use uplc_pallas_codec::utils::{Int, PlutusBytes};
 
 use crate::assets::Splash;
-use crate::constants::INFLATION_SCRIPT;
 use crate::routines::inflation::InflationBoxSnapshot;
 use crate::time::{epoch_end, NetworkTime, ProtocolEpoch};
 use crate::{constants, GenesisEpochStartTime};
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct InflationBoxId(Token);
 
 impl Identifier for InflationBoxId {
     type For = InflationBoxSnapshot;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct InflationBox {
     pub last_processed_epoch: ProtocolEpoch,
     pub splash_reserves: TaggedAmount<Splash>,
@@ -72,8 +72,16 @@ impl Stable for InflationBox {
     }
 }
 
-pub fn unsafe_update_ibox_state(data: &mut PlutusData, last_processed_epoch: ProtocolEpoch) {
+/// Datum of the inflation box is a bare integer, so it is replaced as a whole.
+pub fn update_ibox_state(
+    data: &mut PlutusData,
+    last_processed_epoch: ProtocolEpoch,
+) -> Result<(), DatumUpdateError> {
+    if !matches!(data, PlutusData::Integer(_)) {
+        return Err(DatumUpdateError::Malformed);
+    }
     *data = PlutusData::new_integer(last_processed_epoch.into());
+    Ok(())
 }
 
 pub const INFLATION_BOX_EX_UNITS: ExUnits = ExUnits {
@@ -87,6 +95,7 @@ pub fn compute_inflation_box_script_hash(
     wp_auth_policy: PolicyId,
     weighting_power_policy: PolicyId,
     zeroth_epoch_start: u64,
+    script: &str,
 ) -> ScriptHash {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(splash_policy.to_raw_bytes().to_vec())),
@@ -94,5 +103,5 @@ pub fn compute_inflation_box_script_hash(
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(weighting_power_policy.to_raw_bytes().to_vec())),
         uplc::PlutusData::BigInt(uplc::BigInt::Int(Int::from(zeroth_epoch_start as i64))),
     ]);
-    apply_params_validator(params_pd, INFLATION_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
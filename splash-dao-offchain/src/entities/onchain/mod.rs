@@ -1,3 +1,4 @@
+pub mod farm_position;
 pub mod inflation_box;
 pub mod permission_manager;
 pub mod poll_factory;
@@ -3,25 +3,26 @@ use std::fmt::Formatter;
 use cml_chain::{plutus::ExUnits, PolicyId};
 use cml_crypto::RawBytesEncoding;
 use derive_more::From;
+use serde::{Deserialize, Serialize};
 use spectrum_cardano_lib::Token;
 use spectrum_offchain::data::{Identifier, Stable};
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 
-use crate::{constants::PERM_MANAGER_SCRIPT, routines::inflation::PermManagerSnapshot};
+use crate::routines::inflation::PermManagerSnapshot;
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, From)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, From, Serialize, Deserialize)]
 pub struct PermManagerId(Token);
 
 impl Identifier for PermManagerId {
     type For = PermManagerSnapshot;
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct PermManager {
     pub stable_id: PermManagerStableId,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct PermManagerStableId {
     edao_msig_policy: PolicyId,
     /// An NFT mint once on setup.
@@ -50,6 +51,7 @@ impl Stable for PermManager {
 pub fn compute_perm_manager_policy_id(
     edao_msig_policy: PolicyId,
     perm_manager_auth_policy: PolicyId,
+    script: &str,
 ) -> PolicyId {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BoundedBytes(uplc_pallas_codec::utils::PlutusBytes::from(
@@ -59,7 +61,7 @@ pub fn compute_perm_manager_policy_id(
             perm_manager_auth_policy.to_raw_bytes().to_vec(),
         )),
     ]);
-    apply_params_validator(params_pd, PERM_MANAGER_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
 
 pub const PERM_MANAGER_EX_UNITS: ExUnits = ExUnits {
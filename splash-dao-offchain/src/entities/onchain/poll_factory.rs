@@ -4,14 +4,16 @@ use cml_chain::plutus::{ConstrPlutusData, ExUnits, PlutusData};
 
 use cml_chain::PolicyId;
 use cml_crypto::{RawBytesEncoding, ScriptHash};
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
+use serde::{Deserialize, Serialize};
+use spectrum_cardano_lib::plutus_data::{
+    update_constr_fields, ConstrPlutusDataExtension, DatumUpdateError, IntoPlutusData,
+};
 use spectrum_cardano_lib::{TaggedAmount, Token};
 use spectrum_offchain::data::{Identifier, Stable};
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 use uplc_pallas_codec::utils::PlutusBytes;
 
 use crate::assets::Splash;
-use crate::constants::WP_FACTORY_SCRIPT;
 use crate::entities::onchain::smart_farm::FarmId;
 use crate::entities::onchain::weighting_poll::WeightingPoll;
 use crate::routines::inflation::PollFactorySnapshot;
@@ -19,13 +21,14 @@ use crate::time::ProtocolEpoch;
 
 use super::weighting_poll::WeightingPollStableId;
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub struct PollFactoryId(Token);
 
 impl Identifier for PollFactoryId {
     type For = PollFactorySnapshot;
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct PollFactory {
     pub last_poll_epoch: ProtocolEpoch,
     pub active_farms: Vec<FarmId>,
@@ -58,7 +61,7 @@ impl PollFactory {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct PollFactoryStableId {
     /// Auth policy of all weighting polls.
     pub wp_auth_policy: PolicyId,
@@ -85,9 +88,11 @@ impl Stable for PollFactory {
     }
 }
 
-pub fn unsafe_update_factory_state(data: &mut PlutusData, last_poll_epoch: ProtocolEpoch) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(0, PlutusData::new_integer(last_poll_epoch.into()))
+pub fn update_factory_state(
+    data: &mut PlutusData,
+    last_poll_epoch: ProtocolEpoch,
+) -> Result<(), DatumUpdateError> {
+    update_constr_fields(data, [(0, PlutusData::new_integer(last_poll_epoch.into()))])
 }
 
 pub enum PollFactoryAction {
@@ -150,10 +155,11 @@ pub const GOV_PROXY_EX_UNITS: ExUnits = ExUnits {
 pub fn compute_wp_factory_script_hash(
     wp_auth_policy: PolicyId,
     gov_witness_script_hash: ScriptHash,
+    script: &str,
 ) -> ScriptHash {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(wp_auth_policy.to_raw_bytes().to_vec())),
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(gov_witness_script_hash.to_raw_bytes().to_vec())),
     ]);
-    apply_params_validator(params_pd, WP_FACTORY_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
@@ -4,13 +4,16 @@ use cml_chain::{
     PolicyId,
 };
 use cml_crypto::RawBytesEncoding;
+use serde::{Deserialize, Serialize};
 use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData};
 use spectrum_offchain::data::{Identifier, Stable};
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 
-use crate::{constants::MINT_FARM_AUTH_TOKEN_SCRIPT, routines::inflation::SmartFarmSnapshot};
+use crate::routines::inflation::SmartFarmSnapshot;
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Debug, Hash, derive_more::Display)]
+#[derive(
+    Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Debug, Hash, derive_more::Display, Serialize, Deserialize,
+)]
 pub struct FarmId(pub u64);
 
 impl Identifier for FarmId {
@@ -23,7 +26,7 @@ impl IntoPlutusData for FarmId {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct SmartFarm {
     pub farm_id: FarmId,
 }
@@ -56,7 +59,13 @@ impl IntoPlutusData for Redeemer {
 
 pub enum Action {
     Charge,
-    DistributeRewards { perm_manager_input_ix: u32 },
+    DistributeRewards {
+        perm_manager_input_ix: u32,
+    },
+    /// Pay accrued rewards to the owner of the position.
+    ClaimRewards {
+        position_in_ix: u32,
+    },
 }
 
 impl IntoPlutusData for Action {
@@ -69,6 +78,10 @@ impl IntoPlutusData for Action {
                 1,
                 vec![PlutusData::Integer(BigInteger::from(perm_manager_input_ix))],
             )),
+            Action::ClaimRewards { position_in_ix } => PlutusData::ConstrPlutusData(ConstrPlutusData::new(
+                2,
+                vec![PlutusData::Integer(BigInteger::from(position_in_ix))],
+            )),
         }
     }
 }
@@ -76,6 +89,7 @@ impl IntoPlutusData for Action {
 pub fn compute_mint_farm_auth_token_policy_id(
     splash_policy: PolicyId,
     factory_auth_policy: PolicyId,
+    script: &str,
 ) -> PolicyId {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BoundedBytes(uplc_pallas_codec::utils::PlutusBytes::from(
@@ -85,7 +99,7 @@ pub fn compute_mint_farm_auth_token_policy_id(
             factory_auth_policy.to_raw_bytes().to_vec(),
         )),
     ]);
-    apply_params_validator(params_pd, MINT_FARM_AUTH_TOKEN_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
 
 pub const FARM_EX_UNITS: ExUnits = ExUnits {
@@ -11,8 +11,9 @@ use cml_chain::{
 use cml_crypto::{PublicKey, RawBytesEncoding, ScriptHash};
 use uplc_pallas_codec::utils::{Int, PlutusBytes};
 
+use serde::{Deserialize, Serialize};
 use spectrum_cardano_lib::{
-    plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension},
+    plutus_data::{update_constr_fields, ConstrPlutusDataExtension, DatumUpdateError, IntoPlutusData},
     Token,
 };
 use spectrum_offchain::{
@@ -22,20 +23,23 @@ use spectrum_offchain::{
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 
 use crate::{
-    constants::{MAX_LOCK_TIME_SECONDS, MINT_WEIGHTING_POWER_SCRIPT, VOTING_ESCROW_SCRIPT},
+    constants::{EPOCH_LEN, MAX_LOCK_TIME_SECONDS, MILLIS_IN_SECOND},
+    deployment::DaoScriptBytes,
     protocol_config::{NodeMagic, OperatorCreds, VEFactoryAuthPolicy},
     routines::inflation::VotingEscrowSnapshot,
     time::{NetworkTime, ProtocolEpoch},
 };
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash, Debug)]
+#[derive(
+    Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash, Debug, derive_more::From, Serialize, Deserialize,
+)]
 pub struct VotingEscrowId(Token);
 
 impl Identifier for VotingEscrowId {
     type For = VotingEscrowSnapshot;
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub struct VotingEscrow {
     pub gov_token_amount: u64,
     pub gt_policy: PolicyId,
@@ -43,44 +47,105 @@ pub struct VotingEscrow {
     pub stable_id: VotingEscrowStableId,
     pub max_ex_fee: u32,
     pub version: u32,
+    pub curve: DecayCurve,
+}
+
+/// Shape of voting power decay over the remaining lock time.
+/// Selected by the version of the voting escrow datum.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DecayCurve {
+    /// Power decays continuously in proportion to the remaining lock time.
+    Linear,
+    /// Remaining lock time is only accounted in whole epochs.
+    StepWise,
+    /// Power decays with the square of the remaining lock time.
+    Quadratic,
+}
+
+impl DecayCurve {
+    pub fn from_datum_version(datum_version: u32) -> Option<Self> {
+        match datum_version {
+            0 => Some(DecayCurve::Linear),
+            1 => Some(DecayCurve::StepWise),
+            2 => Some(DecayCurve::Quadratic),
+            _ => None,
+        }
+    }
+
+    pub fn datum_version(self) -> u32 {
+        match self {
+            DecayCurve::Linear => 0,
+            DecayCurve::StepWise => 1,
+            DecayCurve::Quadratic => 2,
+        }
+    }
+
+    /// Mirrors the computation done by the voting escrow validator.
+    pub fn voting_power(self, gov_token_amount: u64, remaining_lock_secs: u64) -> u64 {
+        let amount = gov_token_amount as u128;
+        let max_lock = MAX_LOCK_TIME_SECONDS as u128;
+        let power = match self {
+            DecayCurve::Linear => amount * remaining_lock_secs as u128 / max_lock,
+            DecayCurve::StepWise => {
+                let epoch_len_secs = EPOCH_LEN / MILLIS_IN_SECOND;
+                let whole_epochs_secs = remaining_lock_secs - remaining_lock_secs % epoch_len_secs;
+                amount * whole_epochs_secs as u128 / max_lock
+            }
+            DecayCurve::Quadratic => {
+                let remaining = remaining_lock_secs as u128;
+                amount.saturating_mul(remaining * remaining) / (max_lock * max_lock)
+            }
+        };
+        power.min(u64::MAX as u128) as u64
+    }
 }
 
 impl VotingEscrow {
-    pub fn voting_power(&self, current_posix_time: u64) -> u64 {
+    /// Whether the locked tokens can be redeemed at the given time.
+    pub fn lock_expired(&self, current_posix_time_millis: NetworkTime) -> bool {
         match self.locked_until {
-            Lock::Def(network_time) => {
-                if network_time < current_posix_time {
-                    0
-                } else {
-                    self.gov_token_amount * (network_time - current_posix_time) / 1000 / MAX_LOCK_TIME_SECONDS
-                }
-            }
-            Lock::Indef(d) => self.gov_token_amount * d.as_secs() / MAX_LOCK_TIME_SECONDS,
+            Lock::Def(network_time) => network_time < current_posix_time_millis,
+            Lock::Indef(_) => false,
         }
     }
 
+    pub fn voting_power(&self, current_posix_time: u64) -> u64 {
+        let remaining_lock_secs = match self.locked_until {
+            Lock::Def(network_time) => network_time.saturating_sub(current_posix_time) / MILLIS_IN_SECOND,
+            Lock::Indef(d) => d.as_secs(),
+        };
+        self.curve
+            .voting_power(self.gov_token_amount, remaining_lock_secs)
+    }
+
     fn create_datum(&self, pk: PublicKey) -> PlutusData {
-        PlutusData::ConstrPlutusData(ConstrPlutusData::new(
-            0,
-            vec![
-                self.locked_until.into_pd(),
-                PlutusData::new_bytes(pk.to_raw_bytes().to_vec()),
-                PlutusData::new_integer(self.max_ex_fee.into()),
-                PlutusData::new_integer(self.version.into()),
-                PlutusData::new_integer(0_u32.into()), // last_wp_epoch == 0
-                PlutusData::new_integer(0_u32.into()), // last_gp_deadline == 0
-            ],
-        ))
+        let mut fields = vec![
+            self.locked_until.into_pd(),
+            PlutusData::new_bytes(pk.to_raw_bytes().to_vec()),
+            PlutusData::new_integer(self.max_ex_fee.into()),
+            PlutusData::new_integer(self.version.into()),
+            PlutusData::new_integer(0_u32.into()), // last_wp_epoch == 0
+            PlutusData::new_integer(0_u32.into()), // last_gp_deadline == 0
+        ];
+        // Legacy datums (linear decay) don't carry the datum version.
+        if self.curve != DecayCurve::Linear {
+            fields.push(PlutusData::new_integer(self.curve.datum_version().into()));
+        }
+        PlutusData::ConstrPlutusData(ConstrPlutusData::new(0, fields))
     }
 }
 
 impl<Ctx> IntoLedger<TransactionOutput, Ctx> for VotingEscrow
 where
-    Ctx: Has<VEFactoryAuthPolicy> + Has<OperatorCreds> + Has<NodeMagic>,
+    Ctx: Has<VEFactoryAuthPolicy> + Has<OperatorCreds> + Has<NodeMagic> + Has<DaoScriptBytes>,
 {
     fn into_ledger(self, ctx: Ctx) -> TransactionOutput {
+        let scripts = ctx.select::<DaoScriptBytes>();
         let OperatorCreds(operator_sk, _, _) = ctx.select::<OperatorCreds>();
-        let voting_escrow_policy = compute_voting_escrow_policy_id(ctx.select::<VEFactoryAuthPolicy>().0);
+        let voting_escrow_policy = compute_voting_escrow_policy_id(
+            ctx.select::<VEFactoryAuthPolicy>().0,
+            &scripts.voting_escrow.script,
+        );
         let datum = self.create_datum(operator_sk.to_public());
 
         let cred = StakeCredential::new_script(voting_escrow_policy);
@@ -91,7 +156,7 @@ where
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct VotingEscrowStableId {
     ve_factory_auth_policy: PolicyId,
 }
@@ -115,7 +180,7 @@ impl Stable for VotingEscrow {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum Lock {
     Def(NetworkTime),
     Indef(Duration),
@@ -136,10 +201,29 @@ impl IntoPlutusData for Lock {
     }
 }
 
-pub fn unsafe_update_ve_state(data: &mut PlutusData, last_poll_epoch: ProtocolEpoch) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(4, PlutusData::new_integer(last_poll_epoch.into()))
+pub fn update_ve_state(
+    data: &mut PlutusData,
+    last_poll_epoch: ProtocolEpoch,
+) -> Result<(), DatumUpdateError> {
+    update_constr_fields(data, [(4, PlutusData::new_integer(last_poll_epoch.into()))])
+}
+
+/// Sets new lock and version of the voting escrow.
+/// Version is bumped on every owner action so that the signed authorization can't be replayed.
+pub fn update_ve_lock(
+    data: &mut PlutusData,
+    locked_until: Lock,
+    version: u32,
+) -> Result<(), DatumUpdateError> {
+    update_constr_fields(
+        data,
+        [
+            (0, locked_until.into_pd()),
+            (3, PlutusData::new_integer(version.into())),
+        ],
+    )
 }
+
 pub enum VotingEscrowAction {
     /// Apply governance action.
     Governance,
@@ -263,18 +347,102 @@ pub fn compute_mint_weighting_power_policy_id(
     zeroth_epoch_start: u64,
     proposal_auth_policy: PolicyId,
     gt_policy: PolicyId,
+    script: &str,
 ) -> PolicyId {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BigInt(uplc::BigInt::Int(Int::from(zeroth_epoch_start as i64))),
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(proposal_auth_policy.to_raw_bytes().to_vec())),
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(gt_policy.to_raw_bytes().to_vec())),
     ]);
-    apply_params_validator(params_pd, MINT_WEIGHTING_POWER_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
 
-pub fn compute_voting_escrow_policy_id(ve_factory_auth_policy: PolicyId) -> PolicyId {
+pub fn compute_voting_escrow_policy_id(ve_factory_auth_policy: PolicyId, script: &str) -> PolicyId {
     let params_pd = uplc::PlutusData::Array(vec![uplc::PlutusData::BoundedBytes(PlutusBytes::from(
         ve_factory_auth_policy.to_raw_bytes().to_vec(),
     ))]);
-    apply_params_validator(params_pd, VOTING_ESCROW_SCRIPT)
+    apply_params_validator(params_pd, script)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use crate::constants::{EPOCH_LEN, MAX_LOCK_TIME_SECONDS, MILLIS_IN_SECOND};
+
+    use super::DecayCurve;
+
+    const CURVES: [DecayCurve; 3] = [DecayCurve::Linear, DecayCurve::StepWise, DecayCurve::Quadratic];
+    const SAMPLES: usize = 10_000;
+
+    #[test]
+    fn power_is_monotone_non_increasing() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for curve in CURVES {
+            for _ in 0..SAMPLES {
+                let amount = rng.gen_range(0..=u64::MAX / 2);
+                let remaining = rng.gen_range(0..=MAX_LOCK_TIME_SECONDS);
+                let elapsed = rng.gen_range(0..=remaining);
+                assert!(
+                    curve.voting_power(amount, remaining - elapsed) <= curve.voting_power(amount, remaining),
+                    "{:?} increases: amount={}, remaining={}, elapsed={}",
+                    curve,
+                    amount,
+                    remaining,
+                    elapsed
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn power_is_bounded_by_locked_amount() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for curve in CURVES {
+            for _ in 0..SAMPLES {
+                let amount = rng.gen();
+                let remaining = rng.gen_range(0..=MAX_LOCK_TIME_SECONDS);
+                assert!(curve.voting_power(amount, remaining) <= amount);
+            }
+            assert_eq!(curve.voting_power(1_000_000, MAX_LOCK_TIME_SECONDS), 1_000_000);
+            assert_eq!(curve.voting_power(1_000_000, 0), 0);
+        }
+    }
+
+    /// Reference values computed the same way as the voting escrow validator does.
+    #[test]
+    fn power_matches_validator() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        for _ in 0..SAMPLES {
+            let amount = rng.gen_range(0..=u32::MAX as u64);
+            let remaining = rng.gen_range(0..=MAX_LOCK_TIME_SECONDS);
+            assert_eq!(
+                DecayCurve::Linear.voting_power(amount, remaining),
+                amount * remaining / MAX_LOCK_TIME_SECONDS
+            );
+        }
+        let epoch_secs = EPOCH_LEN / MILLIS_IN_SECOND;
+        let half_max_lock = MAX_LOCK_TIME_SECONDS / 2;
+        assert_eq!(
+            DecayCurve::Quadratic.voting_power(1_000_000, half_max_lock),
+            250_000
+        );
+        assert_eq!(
+            DecayCurve::StepWise.voting_power(MAX_LOCK_TIME_SECONDS, epoch_secs - 1),
+            0
+        );
+        assert_eq!(
+            DecayCurve::StepWise.voting_power(MAX_LOCK_TIME_SECONDS, 2 * epoch_secs + 1),
+            2 * epoch_secs
+        );
+    }
+
+    #[test]
+    fn datum_version_roundtrip() {
+        for curve in CURVES {
+            assert_eq!(DecayCurve::from_datum_version(curve.datum_version()), Some(curve));
+        }
+        assert_eq!(DecayCurve::from_datum_version(3), None);
+    }
 }
@@ -11,14 +11,16 @@ use cml_crypto::RawBytesEncoding;
 use derive_more::From;
 use uplc_pallas_codec::utils::{Int, PlutusBytes};
 
-use spectrum_cardano_lib::plutus_data::{ConstrPlutusDataExtension, IntoPlutusData, PlutusDataExtension};
+use serde::{Deserialize, Serialize};
+use spectrum_cardano_lib::plutus_data::{update_constr_fields, DatumUpdateError, IntoPlutusData};
 use spectrum_cardano_lib::{TaggedAmount, Token};
 use spectrum_offchain::data::{Has, Identifier, Stable};
 use spectrum_offchain::ledger::IntoLedger;
 use spectrum_offchain_cardano::parametrized_validators::apply_params_validator;
 
 use crate::assets::Splash;
-use crate::constants::{MINT_WP_AUTH_TOKEN_SCRIPT, SPLASH_NAME};
+use crate::constants::SPLASH_NAME;
+use crate::deployment::DaoScriptBytes;
 use crate::entities::onchain::smart_farm::FarmId;
 use crate::entities::onchain::voting_escrow::compute_mint_weighting_power_policy_id;
 use crate::protocol_config::{GTAuthPolicy, NodeMagic, SplashPolicy, WPAuthPolicy};
@@ -26,14 +28,14 @@ use crate::routines::inflation::WeightingPollSnapshot;
 use crate::time::{epoch_end, epoch_start, NetworkTime, ProtocolEpoch};
 use crate::GenesisEpochStartTime;
 
-#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, From)]
+#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, From, Serialize, Deserialize)]
 pub struct WeightingPollId(Token);
 
 impl Identifier for WeightingPollId {
     type For = WeightingPollSnapshot;
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct WeightingPoll {
     pub epoch: ProtocolEpoch,
     pub distribution: Vec<(FarmId, u64)>,
@@ -49,14 +51,17 @@ where
         + Has<GenesisEpochStartTime>
         + Has<WPAuthPolicy>
         + Has<GTAuthPolicy>
-        + Has<NodeMagic>,
+        + Has<NodeMagic>
+        + Has<DaoScriptBytes>,
 {
     fn into_ledger(self, ctx: Ctx) -> TransactionOutput {
+        let scripts = ctx.select::<DaoScriptBytes>();
         let weighting_poll_policy = ctx.select::<WPAuthPolicy>().0;
         let weighting_power_policy = compute_mint_weighting_power_policy_id(
             self.epoch as u64,
             ctx.select::<WPAuthPolicy>().0,
             ctx.select::<GTAuthPolicy>().0,
+            &scripts.mint_weighting_power.script,
         );
         let datum = create_datum(
             &self,
@@ -103,7 +108,7 @@ fn create_datum(
     ))
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
 pub struct WeightingPollStableId {
     /// The validator will ensure preservation of a token = (`auth_policy`, `binder`).
     pub auth_policy: PolicyId,
@@ -213,9 +218,11 @@ fn distribution_to_plutus_data(distribution: &[(FarmId, u64)]) -> PlutusData {
     PlutusData::new_list(list)
 }
 
-pub fn unsafe_update_wp_state(data: &mut PlutusData, new_distribution: &[(FarmId, u64)]) {
-    let cpd = data.get_constr_pd_mut().unwrap();
-    cpd.set_field(0, distribution_to_plutus_data(new_distribution))
+pub fn update_wp_state(
+    data: &mut PlutusData,
+    new_distribution: &[(FarmId, u64)],
+) -> Result<(), DatumUpdateError> {
+    update_constr_fields(data, [(0, distribution_to_plutus_data(new_distribution))])
 }
 
 pub enum PollAction {
@@ -280,6 +287,7 @@ pub fn compute_mint_wp_auth_token_policy_id(
     farm_auth_policy: PolicyId,
     factory_auth_policy: PolicyId,
     zeroth_epoch_start: u64,
+    script: &str,
 ) -> PolicyId {
     let params_pd = uplc::PlutusData::Array(vec![
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(splash_policy.to_raw_bytes().to_vec())),
@@ -287,5 +295,5 @@ pub fn compute_mint_wp_auth_token_policy_id(
         uplc::PlutusData::BoundedBytes(PlutusBytes::from(factory_auth_policy.to_raw_bytes().to_vec())),
         uplc::PlutusData::BigInt(uplc::BigInt::Int(Int::from(zeroth_epoch_start as i64))),
     ]);
-    apply_params_validator(params_pd, MINT_WP_AUTH_TOKEN_SCRIPT)
+    apply_params_validator(params_pd, script)
 }
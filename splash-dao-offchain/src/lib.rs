@@ -4,12 +4,14 @@ use crate::time::NetworkTime;
 
 mod assets;
 pub mod constants;
+pub mod deployment;
 pub mod entities;
-mod protocol_config;
-mod routine;
+pub mod protocol_config;
+pub mod routine;
 pub mod routines;
 pub mod state_projection;
 pub mod time;
+pub mod user_actions;
 
 #[derive(Copy, Clone, Eq, PartialEq, From, Into, Debug)]
 pub struct GenesisEpochStartTime(NetworkTime);
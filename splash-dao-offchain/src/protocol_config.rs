@@ -4,9 +4,11 @@ use cml_chain::PolicyId;
 use cml_crypto::{Ed25519KeyHash, PrivateKey};
 use spectrum_cardano_lib::collateral::Collateral;
 use spectrum_offchain::data::Has;
+use spectrum_offchain::sensitive::Sensitive;
 use spectrum_offchain_cardano::creds::operator_creds;
 use type_equalities::IsEqual;
 
+use crate::deployment::DaoScriptBytes;
 use crate::entities::onchain::inflation_box::InflationBoxId;
 use crate::entities::onchain::permission_manager::PermManagerId;
 use crate::entities::onchain::poll_factory::PollFactoryId;
@@ -15,7 +17,7 @@ use crate::time::ProtocolEpoch;
 use crate::GenesisEpochStartTime;
 
 pub struct ProtocolConfig {
-    pub operator_sk: String,
+    pub operator_sk: Sensitive<String>,
     pub node_magic: u64,
     pub reward_address: cml_chain::address::RewardAddress,
     pub collateral: Collateral,
@@ -28,6 +30,7 @@ pub struct ProtocolConfig {
     pub wpoll_auth_ref_script: TransactionUnspentOutput,
     pub farm_auth_policy: PolicyId,
     pub farm_auth_ref_script: TransactionUnspentOutput,
+    pub farm_position_ref_script: TransactionUnspentOutput,
     pub factory_auth_policy: PolicyId,
     pub ve_factory_auth_policy: PolicyId,
     pub voting_escrow_ref_script: TransactionUnspentOutput,
@@ -38,6 +41,7 @@ pub struct ProtocolConfig {
     pub perm_manager_auth_policy: PolicyId,
     pub gt_policy: PolicyId,
     pub genesis_time: GenesisEpochStartTime,
+    pub dao_scripts: DaoScriptBytes,
 }
 
 impl ProtocolConfig {
@@ -70,6 +74,9 @@ pub struct FarmAuthPolicy(pub PolicyId);
 #[derive(Debug, Clone)]
 pub struct FarmAuthRefScriptOutput(pub TransactionUnspentOutput);
 
+#[derive(Debug, Clone)]
+pub struct FarmPositionRefScriptOutput(pub TransactionUnspentOutput);
+
 #[derive(Debug, Clone)]
 pub struct FactoryAuthPolicy(pub PolicyId);
 
@@ -153,6 +160,12 @@ impl Has<FarmAuthRefScriptOutput> for ProtocolConfig {
     }
 }
 
+impl Has<FarmPositionRefScriptOutput> for ProtocolConfig {
+    fn select<U: IsEqual<FarmPositionRefScriptOutput>>(&self) -> FarmPositionRefScriptOutput {
+        FarmPositionRefScriptOutput(self.farm_position_ref_script.clone())
+    }
+}
+
 impl Has<FactoryAuthPolicy> for ProtocolConfig {
     fn select<U: IsEqual<FactoryAuthPolicy>>(&self) -> FactoryAuthPolicy {
         FactoryAuthPolicy(self.factory_auth_policy)
@@ -207,6 +220,12 @@ impl Has<GenesisEpochStartTime> for ProtocolConfig {
     }
 }
 
+impl Has<DaoScriptBytes> for ProtocolConfig {
+    fn select<U: IsEqual<DaoScriptBytes>>(&self) -> DaoScriptBytes {
+        self.dao_scripts.clone()
+    }
+}
+
 impl Has<NodeMagic> for ProtocolConfig {
     fn select<U: IsEqual<NodeMagic>>(&self) -> NodeMagic {
         NodeMagic(self.node_magic)
@@ -215,7 +234,8 @@ impl Has<NodeMagic> for ProtocolConfig {
 
 impl Has<OperatorCreds> for ProtocolConfig {
     fn select<U: IsEqual<OperatorCreds>>(&self) -> OperatorCreds {
-        let (operator_sk, operator_pkh, operator_addr) = operator_creds(&self.operator_sk, self.node_magic);
+        let (operator_sk, operator_pkh, operator_addr) =
+            operator_creds(self.operator_sk.expose(), self.node_magic);
         OperatorCreds(operator_sk, operator_pkh, operator_addr)
     }
 }
@@ -25,6 +25,13 @@ pub struct Routine<B> {
 }
 
 impl<B> Routine<B> {
+    pub fn new(behaviour: B) -> Self {
+        Self {
+            behaviour,
+            waker: None,
+        }
+    }
+
     fn next_attempt_in(&mut self, delay: Duration) {
         let _ = self.waker.insert(Delay::new(delay));
     }
@@ -1,12 +1,10 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use cml_chain::address::Address;
 use cml_chain::assets::AssetBundle;
 use cml_chain::builders::input_builder::SingleInputBuilder;
 use cml_chain::builders::mint_builder::SingleMintBuilder;
 use cml_chain::builders::output_builder::{SingleOutputBuilderResult, TransactionOutputBuilder};
 use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
-use cml_chain::builders::tx_builder::{ChangeSelectionAlgo, SignedTxBuilder};
+use cml_chain::builders::tx_builder::{ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput};
 use cml_chain::builders::withdrawal_builder::SingleWithdrawalBuilder;
 use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
 use cml_chain::plutus::RedeemerTag;
@@ -19,31 +17,36 @@ use uplc_pallas_traverse::ComputeHash;
 use bloom_offchain::execution_engine::bundled::Bundled;
 use spectrum_cardano_lib::collateral::Collateral;
 use spectrum_cardano_lib::hash::hash_transaction_canonical;
-use spectrum_cardano_lib::plutus_data::IntoPlutusData;
+use spectrum_cardano_lib::plutus_data::{DatumUpdateError, IntoPlutusData};
 use spectrum_cardano_lib::protocol_params::constant_tx_builder;
 use spectrum_cardano_lib::transaction::TransactionOutputExtension;
 use spectrum_cardano_lib::{AssetName, OutputRef};
+use spectrum_offchain::clock::{Clock, SharedClock};
 use spectrum_offchain::data::event::{Predicted, Traced};
 use spectrum_offchain::data::{EntitySnapshot, Has, Stable};
 use spectrum_offchain::ledger::IntoLedger;
+use spectrum_offchain_cardano::script::{delayed_redeemer, ready_redeemer, ScriptInput, TxInputs};
 
 use crate::assets::SPLASH_AC;
 use crate::constants::{self};
+use crate::deployment::DaoScriptBytes;
 use crate::entities::offchain::voting_order::VotingOrder;
-use crate::entities::onchain::inflation_box::{compute_inflation_box_script_hash, INFLATION_BOX_EX_UNITS};
+use crate::entities::onchain::inflation_box::{
+    compute_inflation_box_script_hash, update_ibox_state, INFLATION_BOX_EX_UNITS,
+};
 use crate::entities::onchain::permission_manager::{compute_perm_manager_policy_id, PERM_MANAGER_EX_UNITS};
 use crate::entities::onchain::poll_factory::{
-    compute_wp_factory_script_hash, unsafe_update_factory_state, FactoryRedeemer, PollFactoryAction,
+    compute_wp_factory_script_hash, update_factory_state, FactoryRedeemer, PollFactoryAction,
     GOV_PROXY_EX_UNITS, WP_FACTORY_EX_UNITS,
 };
 use crate::entities::onchain::smart_farm::{self, compute_mint_farm_auth_token_policy_id, FARM_EX_UNITS};
 use crate::entities::onchain::voting_escrow::{
-    self, compute_mint_weighting_power_policy_id, compute_voting_escrow_policy_id, unsafe_update_ve_state,
+    self, compute_mint_weighting_power_policy_id, compute_voting_escrow_policy_id, update_ve_state,
     VotingEscrowAction, VotingEscrowAuthorizedAction, ORDER_WITNESS_EX_UNITS, VOTING_ESCROW_EX_UNITS,
     WEIGHTING_POWER_EX_UNITS,
 };
 use crate::entities::onchain::weighting_poll::{
-    self, compute_mint_wp_auth_token_policy_id, unsafe_update_wp_state, MintAction, WeightingPoll,
+    self, compute_mint_wp_auth_token_policy_id, update_wp_state, MintAction, WeightingPoll,
     MINT_WP_AUTH_EX_UNITS,
 };
 use crate::entities::Snapshot;
@@ -67,12 +70,15 @@ pub trait InflationActions<Bearer> {
         &self,
         inflation_box: Bundled<InflationBoxSnapshot, Bearer>,
         factory: Bundled<PollFactorySnapshot, Bearer>,
-    ) -> (
-        SignedTxBuilder,
-        Traced<Predicted<Bundled<InflationBoxSnapshot, Bearer>>>,
-        Traced<Predicted<Bundled<PollFactorySnapshot, Bearer>>>,
-        Traced<Predicted<Bundled<WeightingPollSnapshot, Bearer>>>,
-    );
+    ) -> Result<
+        (
+            SignedTxBuilder,
+            Traced<Predicted<Bundled<InflationBoxSnapshot, Bearer>>>,
+            Traced<Predicted<Bundled<PollFactorySnapshot, Bearer>>>,
+            Traced<Predicted<Bundled<WeightingPollSnapshot, Bearer>>>,
+        ),
+        DatumUpdateError,
+    >;
     async fn eliminate_wpoll(
         &self,
         weighting_poll: Bundled<WeightingPollSnapshot, Bearer>,
@@ -81,11 +87,14 @@ pub trait InflationActions<Bearer> {
         &self,
         weighting_poll: Bundled<WeightingPollSnapshot, Bearer>,
         order: (VotingOrder, Bundled<VotingEscrowSnapshot, Bearer>),
-    ) -> (
-        SignedTxBuilder,
-        Traced<Predicted<Bundled<WeightingPollSnapshot, Bearer>>>,
-        Traced<Predicted<Bundled<VotingEscrowSnapshot, Bearer>>>,
-    );
+    ) -> Result<
+        (
+            SignedTxBuilder,
+            Traced<Predicted<Bundled<WeightingPollSnapshot, Bearer>>>,
+            Traced<Predicted<Bundled<VotingEscrowSnapshot, Bearer>>>,
+        ),
+        DatumUpdateError,
+    >;
     async fn distribute_inflation(
         &self,
         weighting_poll: Bundled<WeightingPollSnapshot, Bearer>,
@@ -109,7 +118,7 @@ impl<Ctx> InflationActions<TransactionOutput> for CardanoInflationActions<Ctx>
 where
     Ctx: Send
         + Sync
-        + Copy
+        + Clone
         + Has<Reward>
         + Has<Collateral>
         + Has<SplashPolicy>
@@ -129,19 +138,25 @@ where
         + Has<GTAuthPolicy>
         + Has<NodeMagic>
         + Has<OperatorCreds>
-        + Has<GenesisEpochStartTime>,
+        + Has<GenesisEpochStartTime>
+        + Has<DaoScriptBytes>
+        + Has<SharedClock>,
 {
     async fn create_wpoll(
         &self,
         Bundled(inflation_box, inflation_box_in): Bundled<InflationBoxSnapshot, TransactionOutput>,
         Bundled(factory, factory_in): Bundled<PollFactorySnapshot, TransactionOutput>,
-    ) -> (
-        SignedTxBuilder,
-        Traced<Predicted<Bundled<InflationBoxSnapshot, TransactionOutput>>>,
-        Traced<Predicted<Bundled<PollFactorySnapshot, TransactionOutput>>>,
-        Traced<Predicted<Bundled<WeightingPollSnapshot, TransactionOutput>>>,
-    ) {
+    ) -> Result<
+        (
+            SignedTxBuilder,
+            Traced<Predicted<Bundled<InflationBoxSnapshot, TransactionOutput>>>,
+            Traced<Predicted<Bundled<PollFactorySnapshot, TransactionOutput>>>,
+            Traced<Predicted<Bundled<WeightingPollSnapshot, TransactionOutput>>>,
+        ),
+        DatumUpdateError,
+    > {
         let mut tx_builder = constant_tx_builder();
+        let scripts = self.ctx.select::<DaoScriptBytes>();
 
         let wpoll_auth_policy = self.ctx.select::<WPAuthPolicy>().0;
         let splash_policy = self.ctx.select::<SplashPolicy>().0;
@@ -154,6 +169,7 @@ where
             self.ctx.select::<GenesisEpochStartTime>().0,
             wpoll_auth_policy,
             self.ctx.select::<GTAuthPolicy>().0,
+            &scripts.mint_weighting_power.script,
         );
 
         let inflation_script_hash = compute_inflation_box_script_hash(
@@ -161,29 +177,27 @@ where
             wpoll_auth_policy,
             weighting_power_policy,
             genesis_time,
+            &scripts.inflation.script,
         );
-        let inflation_script = PartialPlutusWitness::new(
-            PlutusScriptWitness::Ref(inflation_script_hash),
-            cml_chain::plutus::PlutusData::Integer(BigInteger::from(0)),
-        );
-
-        let inflation_input = SingleInputBuilder::new(
-            TransactionInput::from(*(inflation_box.version())),
-            inflation_box_in.clone(),
-        )
-        .plutus_script_inline_datum(inflation_script, vec![])
-        .unwrap();
-
-        let inflation_input_tx_hash = inflation_input.input.transaction_id;
+        let prev_ib_version = *inflation_box.version();
+        let mut inputs = TxInputs::new();
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(
+                TransactionInput::from(prev_ib_version),
+                inflation_box_in.clone(),
+            ),
+            script: inflation_script_hash,
+            redeemer: ready_redeemer(cml_chain::plutus::PlutusData::Integer(BigInteger::from(0))),
+            ex_units: INFLATION_BOX_EX_UNITS,
+            required_signers: vec![],
+        });
 
         tx_builder.add_reference_input(self.ctx.select::<InflationBoxRefScriptOutput>().0.clone());
-        tx_builder.add_input(inflation_input).unwrap();
 
-        let prev_ib_version = *inflation_box.version();
         let (next_inflation_box, emission_rate) = inflation_box.get().release_next_tranche();
         let mut inflation_box_out = inflation_box_in.clone();
         if let Some(data_mut) = inflation_box_out.data_mut() {
-            unsafe_update_factory_state(data_mut, next_inflation_box.last_processed_epoch);
+            update_ibox_state(data_mut, next_inflation_box.last_processed_epoch)?;
         }
         inflation_box_out.sub_asset(*SPLASH_AC, emission_rate.untag());
         let inflation_output = SingleOutputBuilderResult::new(inflation_box_out.clone());
@@ -193,57 +207,39 @@ where
         let wp_factory_script_hash = compute_wp_factory_script_hash(
             wpoll_auth_policy,
             factory.get().stable_id.gov_witness_script_hash,
+            &scripts.wp_factory.script,
         );
 
         let factory_redeemer = FactoryRedeemer {
             successor_ix: 1,
             action: PollFactoryAction::CreatePoll,
         };
-        let wp_factory_script = PartialPlutusWitness::new(
-            PlutusScriptWitness::Ref(wp_factory_script_hash),
-            factory_redeemer.into_pd(),
-        );
-
-        let wp_factory_input =
-            SingleInputBuilder::new(TransactionInput::from(*(factory.version())), factory_in.clone())
-                .plutus_script_inline_datum(wp_factory_script, vec![])
-                .unwrap();
-
-        let wp_factory_input_tx_hash = wp_factory_input.input.transaction_id;
+        let prev_factory_version = *factory.version();
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(
+                TransactionInput::from(prev_factory_version),
+                factory_in.clone(),
+            ),
+            script: wp_factory_script_hash,
+            redeemer: ready_redeemer(factory_redeemer.into_pd()),
+            ex_units: WP_FACTORY_EX_UNITS,
+            required_signers: vec![],
+        });
 
         tx_builder.add_reference_input(self.ctx.select::<PollFactoryRefScriptOutput>().0.clone());
-        tx_builder.add_input(wp_factory_input).unwrap();
+        let inputs_ordering = inputs.project_onto(&mut tx_builder).unwrap();
 
         let gov_witness_script_hash = factory.get().stable_id.gov_witness_script_hash;
-        let prev_factory_version = *factory.version();
         let (next_factory, fresh_wpoll) = factory
             .unwrap()
             .next_weighting_poll(farm_auth_policy, emission_rate);
         let mut factory_out = factory_in.clone();
         if let Some(data_mut) = factory_out.data_mut() {
-            unsafe_update_factory_state(data_mut, next_factory.last_poll_epoch);
+            update_factory_state(data_mut, next_factory.last_poll_epoch)?;
         }
 
-        let mint_action = if inflation_input_tx_hash < wp_factory_input_tx_hash {
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 0),
-                INFLATION_BOX_EX_UNITS,
-            );
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 1),
-                WP_FACTORY_EX_UNITS,
-            );
-            MintAction::MintAuthToken { factory_in_ix: 1 }
-        } else {
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 0),
-                WP_FACTORY_EX_UNITS,
-            );
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 1),
-                INFLATION_BOX_EX_UNITS,
-            );
-            MintAction::MintAuthToken { factory_in_ix: 0 }
+        let mint_action = MintAction::MintAuthToken {
+            factory_in_ix: inputs_ordering.index_of(&prev_factory_version) as u32,
         };
 
         // Mint wp_auth token
@@ -252,6 +248,7 @@ where
             farm_auth_policy,
             self.ctx.select::<FactoryAuthPolicy>().0,
             genesis_time,
+            &scripts.mint_wp_auth_token.script,
         );
         let mint_wp_auth_token_witness = PartialPlutusWitness::new(
             PlutusScriptWitness::Ref(mint_wp_auth_token_script_hash),
@@ -270,7 +267,7 @@ where
         );
 
         // Contracts require that weighting_poll output resides at index 1.
-        let mut wpoll_out = fresh_wpoll.clone().into_ledger(self.ctx);
+        let mut wpoll_out = fresh_wpoll.clone().into_ledger(self.ctx.clone());
         // Add wp_auth_token to this output.
         let asset_pair = OrderedHashMap::from_iter(vec![(asset, 1)]);
         let ord_hash_map = OrderedHashMap::from_iter(vec![(mint_wp_auth_token_script_hash, asset_pair)]);
@@ -352,12 +349,12 @@ where
             )),
             Some(prev_factory_version),
         );
-        (
+        Ok((
             signed_tx_builder,
             next_traced_ibox,
             next_traced_factory,
             fresh_wpoll,
-        )
+        ))
     }
 
     async fn eliminate_wpoll(
@@ -365,6 +362,7 @@ where
         Bundled(weighting_poll, weighting_poll_in): Bundled<WeightingPollSnapshot, TransactionOutput>,
     ) -> SignedTxBuilder {
         let mut tx_builder = constant_tx_builder();
+        let scripts = self.ctx.select::<DaoScriptBytes>();
 
         let splash_policy = self.ctx.select::<SplashPolicy>().0;
         let genesis_time = self.ctx.select::<GenesisEpochStartTime>().0;
@@ -377,6 +375,7 @@ where
             farm_auth_policy,
             factory_auth_policy,
             genesis_time,
+            &scripts.mint_wp_auth_token.script,
         );
 
         let redeemer = weighting_poll::PollAction::Destroy;
@@ -434,12 +433,16 @@ where
             VotingOrder,
             Bundled<VotingEscrowSnapshot, TransactionOutput>,
         ),
-    ) -> (
-        SignedTxBuilder,
-        Traced<Predicted<Bundled<WeightingPollSnapshot, TransactionOutput>>>,
-        Traced<Predicted<Bundled<VotingEscrowSnapshot, TransactionOutput>>>,
-    ) {
+    ) -> Result<
+        (
+            SignedTxBuilder,
+            Traced<Predicted<Bundled<WeightingPollSnapshot, TransactionOutput>>>,
+            Traced<Predicted<Bundled<VotingEscrowSnapshot, TransactionOutput>>>,
+        ),
+        DatumUpdateError,
+    > {
         let mut tx_builder = constant_tx_builder();
+        let scripts = self.ctx.select::<DaoScriptBytes>();
 
         let prev_ve_version = voting_escrow.version();
         let prev_wp_version = weighting_poll.version();
@@ -447,7 +450,7 @@ where
         // Voting escrow
         let mut voting_escrow_out = ve_box_in.clone();
         if let Some(data_mut) = voting_escrow_out.data_mut() {
-            unsafe_update_ve_state(data_mut, weighting_poll.get().epoch);
+            update_ve_state(data_mut, weighting_poll.get().epoch)?;
         }
 
         let authorized_action = VotingEscrowAuthorizedAction {
@@ -467,23 +470,18 @@ where
         let wpoll_auth_ref_script = self.ctx.select::<WPAuthRefScriptOutput>().0;
         let weighting_power_ref_script = self.ctx.select::<WeightingPowerRefScriptOutput>().0;
 
-        let voting_escrow_script_hash = compute_voting_escrow_policy_id(ve_factory_auth_policy);
-        let voting_escrow_script = PartialPlutusWitness::new(
-            PlutusScriptWitness::Ref(voting_escrow_script_hash),
-            authorized_action.into_pd(),
-        );
-
-        let voting_escrow_input = SingleInputBuilder::new(
-            TransactionInput::from(*(voting_escrow.version())),
-            ve_box_in.clone(),
-        )
-        .plutus_script_inline_datum(voting_escrow_script, vec![])
-        .unwrap();
-
-        let voting_escrow_input_tx_hash = voting_escrow_input.input.transaction_id;
+        let voting_escrow_script_hash =
+            compute_voting_escrow_policy_id(ve_factory_auth_policy, &scripts.voting_escrow.script);
+        let mut inputs = TxInputs::new();
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(TransactionInput::from(*prev_ve_version), ve_box_in.clone()),
+            script: voting_escrow_script_hash,
+            redeemer: ready_redeemer(authorized_action.into_pd()),
+            ex_units: VOTING_ESCROW_EX_UNITS,
+            required_signers: vec![],
+        });
 
         tx_builder.add_reference_input(voting_escrow_ref_script);
-        tx_builder.add_input(voting_escrow_input).unwrap();
 
         // weighting_poll
         let weighting_poll_script_hash = compute_mint_wp_auth_token_policy_id(
@@ -491,23 +489,21 @@ where
             farm_auth_policy,
             factory_auth_policy,
             genesis_time,
+            &scripts.mint_wp_auth_token.script,
         );
-        let weighting_poll_script = PartialPlutusWitness::new(
-            PlutusScriptWitness::Ref(weighting_poll_script_hash),
-            weighting_poll::PollAction::Vote.into_pd(),
-        );
-
-        let weighting_poll_input = SingleInputBuilder::new(
-            TransactionInput::from(*(weighting_poll.version())),
-            weighting_poll_in.clone(),
-        )
-        .plutus_script_inline_datum(weighting_poll_script, vec![])
-        .unwrap();
-
-        let weighting_poll_input_tx_hash = weighting_poll_input.input.transaction_id;
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(
+                TransactionInput::from(*prev_wp_version),
+                weighting_poll_in.clone(),
+            ),
+            script: weighting_poll_script_hash,
+            redeemer: ready_redeemer(weighting_poll::PollAction::Vote.into_pd()),
+            ex_units: MINT_WP_AUTH_EX_UNITS,
+            required_signers: vec![],
+        });
 
         tx_builder.add_reference_input(wpoll_auth_ref_script);
-        tx_builder.add_input(weighting_poll_input).unwrap();
+        let inputs_ordering = inputs.project_onto(&mut tx_builder).unwrap();
 
         // Compute the policy for `mint_weighting_power`, to allow us to add the weighting power to WeightingPoll's
         // UTxO.
@@ -515,12 +511,13 @@ where
             weighting_poll.get().epoch as u64,
             wpoll_auth_policy,
             voting_escrow.get().gt_policy,
+            &scripts.mint_weighting_power.script,
         );
-        let current_posix_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let current_posix_time = self.ctx.select::<SharedClock>().unix_time_secs();
 
         let mut wpoll_out = weighting_poll_in.clone();
         if let Some(data_mut) = wpoll_out.data_mut() {
-            unsafe_update_wp_state(data_mut, &order.distribution);
+            update_wp_state(data_mut, &order.distribution)?;
         }
         let weighting_power = voting_escrow.get().voting_power(current_posix_time);
         wpoll_out.add_asset(
@@ -545,34 +542,10 @@ where
         tx_builder.add_output(voting_escrow_output).unwrap();
 
         // Mint weighting power
-        let mint_action = if voting_escrow_input_tx_hash < weighting_poll_input_tx_hash {
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 0),
-                VOTING_ESCROW_EX_UNITS,
-            );
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 1),
-                MINT_WP_AUTH_EX_UNITS,
-            );
-            voting_escrow::MintAction::MintPower {
-                binder: weighting_poll.get().epoch,
-                ve_in_ix: 0,
-                proposal_in_ix: 1,
-            }
-        } else {
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 1),
-                VOTING_ESCROW_EX_UNITS,
-            );
-            tx_builder.set_exunits(
-                RedeemerWitnessKey::new(RedeemerTag::Spend, 0),
-                MINT_WP_AUTH_EX_UNITS,
-            );
-            voting_escrow::MintAction::MintPower {
-                binder: weighting_poll.get().epoch,
-                ve_in_ix: 1,
-                proposal_in_ix: 0,
-            }
+        let mint_action = voting_escrow::MintAction::MintPower {
+            binder: weighting_poll.get().epoch,
+            ve_in_ix: inputs_ordering.index_of(prev_ve_version) as u32,
+            proposal_in_ix: inputs_ordering.index_of(prev_wp_version) as u32,
         };
 
         let mint_weighting_power_script = PartialPlutusWitness::new(
@@ -646,7 +619,7 @@ where
             Some(*prev_ve_version),
         );
 
-        (signed_tx_builder, fresh_wp, fresh_ve)
+        Ok((signed_tx_builder, fresh_wp, fresh_ve))
     }
 
     async fn distribute_inflation(
@@ -662,6 +635,7 @@ where
         Traced<Predicted<Bundled<PermManagerSnapshot, TransactionOutput>>>,
     ) {
         let mut tx_builder = constant_tx_builder();
+        let scripts = self.ctx.select::<DaoScriptBytes>();
 
         let genesis_time = self.ctx.select::<GenesisEpochStartTime>().0;
         let farm_auth_policy = self.ctx.select::<FarmAuthPolicy>().0;
@@ -687,116 +661,68 @@ where
             farm_auth_policy,
             factory_auth_policy,
             genesis_time,
+            &scripts.mint_wp_auth_token.script,
         );
 
         // Setting TX inputs
-        enum InputType {
-            WPoll,
-            Farm,
-            PermManager,
-        }
-
-        let mut typed_inputs = vec![
-            (
-                TransactionInput::from(*weighting_poll.version()).transaction_id,
-                InputType::WPoll,
-            ),
-            (
-                TransactionInput::from(*farm.version()).transaction_id,
-                InputType::Farm,
-            ),
-            (
-                TransactionInput::from(*perm_manager.version()).transaction_id,
-                InputType::PermManager,
-            ),
-        ];
-
-        typed_inputs.sort_by_key(|(tx_hash, _)| *tx_hash);
-        let farm_in_ix = typed_inputs
-            .iter()
-            .position(|(_, t)| matches!(t, InputType::Farm))
-            .unwrap() as u32;
-        let perm_manager_input_ix = typed_inputs
-            .iter()
-            .position(|(_, t)| matches!(t, InputType::PermManager))
-            .unwrap() as u32;
-
-        for (i, (_, input_type)) in typed_inputs.into_iter().enumerate() {
-            match input_type {
-                InputType::WPoll => {
-                    let redeemer = weighting_poll::PollAction::Distribute {
-                        farm_ix: farm.get().farm_id.0 as u32,
-                        farm_in_ix,
-                    };
-                    let weighting_poll_script = PartialPlutusWitness::new(
-                        PlutusScriptWitness::Ref(weighting_poll_script_hash),
-                        redeemer.into_pd(),
-                    );
-
-                    let weighting_poll_input = SingleInputBuilder::new(
-                        TransactionInput::from(*(weighting_poll.version())),
-                        weighting_poll_in.clone(),
-                    )
-                    .plutus_script_inline_datum(weighting_poll_script, vec![])
-                    .unwrap();
-                    tx_builder.add_reference_input(wpoll_auth_ref_script.clone());
-                    tx_builder.add_input(weighting_poll_input).unwrap();
-                    tx_builder.set_exunits(
-                        RedeemerWitnessKey::new(RedeemerTag::Spend, i as u64),
-                        MINT_WP_AUTH_EX_UNITS,
-                    );
-                }
-                InputType::Farm => {
-                    let redeemer = smart_farm::Redeemer {
-                        successor_out_ix: 1,
-                        action: smart_farm::Action::DistributeRewards {
-                            perm_manager_input_ix,
-                        },
-                    };
-                    let smart_farm_script_hash =
-                        compute_mint_farm_auth_token_policy_id(splash_policy, factory_auth_policy);
-                    let smart_farm_script = PartialPlutusWitness::new(
-                        PlutusScriptWitness::Ref(smart_farm_script_hash),
-                        redeemer.into_pd(),
-                    );
-
-                    let smart_farm_input = SingleInputBuilder::new(
-                        TransactionInput::from(*(farm.version())),
-                        weighting_poll_in.clone(),
-                    )
-                    .plutus_script_inline_datum(smart_farm_script, vec![])
-                    .unwrap();
-                    tx_builder.add_reference_input(farm_auth_ref_script.clone());
-                    tx_builder.add_input(smart_farm_input).unwrap();
-                    tx_builder.set_exunits(
-                        RedeemerWitnessKey::new(RedeemerTag::Spend, i as u64),
-                        FARM_EX_UNITS,
-                    );
+        let wpoll_ref = *weighting_poll.version();
+        let farm_ref = *farm.version();
+        let perm_manager_ref = *perm_manager.version();
+        let farm_ix = farm.get().farm_id.0 as u32;
+        let smart_farm_script_hash = compute_mint_farm_auth_token_policy_id(
+            splash_policy,
+            factory_auth_policy,
+            &scripts.mint_farm_auth_token.script,
+        );
+        let perm_manager_script_hash = compute_perm_manager_policy_id(
+            edao_msig_policy,
+            perm_manager_auth_policy,
+            &scripts.perm_manager.script,
+        );
+        let mut inputs = TxInputs::new();
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(TransactionInput::from(wpoll_ref), weighting_poll_in.clone()),
+            script: weighting_poll_script_hash,
+            redeemer: delayed_redeemer(move |ordering| {
+                weighting_poll::PollAction::Distribute {
+                    farm_ix,
+                    farm_in_ix: ordering.index_of(&farm_ref) as u32,
                 }
-                InputType::PermManager => {
-                    let perm_manager_script_hash =
-                        compute_perm_manager_policy_id(edao_msig_policy, perm_manager_auth_policy);
-
-                    let perm_manager_script = PartialPlutusWitness::new(
-                        PlutusScriptWitness::Ref(perm_manager_script_hash),
-                        cml_chain::plutus::PlutusData::Integer(BigInteger::from(2)), // set successor_out_ix to 2
-                    );
-
-                    let perm_manager_input = SingleInputBuilder::new(
-                        TransactionInput::from(*(perm_manager.version())),
-                        weighting_poll_in.clone(),
-                    )
-                    .plutus_script_inline_datum(perm_manager_script, vec![])
-                    .unwrap();
-                    tx_builder.add_reference_input(perm_manager_box_ref_script.clone());
-                    tx_builder.add_input(perm_manager_input).unwrap();
-                    tx_builder.set_exunits(
-                        RedeemerWitnessKey::new(RedeemerTag::Spend, i as u64),
-                        PERM_MANAGER_EX_UNITS,
-                    );
+                .into_pd()
+            }),
+            ex_units: MINT_WP_AUTH_EX_UNITS,
+            required_signers: vec![],
+        });
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(TransactionInput::from(farm_ref), farm_in.clone()),
+            script: smart_farm_script_hash,
+            redeemer: delayed_redeemer(move |ordering| {
+                smart_farm::Redeemer {
+                    successor_out_ix: 1,
+                    action: smart_farm::Action::DistributeRewards {
+                        perm_manager_input_ix: ordering.index_of(&perm_manager_ref) as u32,
+                    },
                 }
-            }
-        }
+                .into_pd()
+            }),
+            ex_units: FARM_EX_UNITS,
+            required_signers: vec![],
+        });
+        inputs.add_script_input(ScriptInput {
+            utxo: TransactionUnspentOutput::new(
+                TransactionInput::from(perm_manager_ref),
+                perm_manager_in.clone(),
+            ),
+            script: perm_manager_script_hash,
+            // set successor_out_ix to 2
+            redeemer: ready_redeemer(cml_chain::plutus::PlutusData::Integer(BigInteger::from(2))),
+            ex_units: PERM_MANAGER_EX_UNITS,
+            required_signers: vec![],
+        });
+        tx_builder.add_reference_input(wpoll_auth_ref_script);
+        tx_builder.add_reference_input(farm_auth_ref_script);
+        tx_builder.add_reference_input(perm_manager_box_ref_script);
+        inputs.project_onto(&mut tx_builder).unwrap();
 
         // Adjust splash values in weighting_poll and farm.
         let splash_emission = weighting_poll.get().emission_rate.untag() * farm_weight
@@ -878,6 +804,8 @@ fn compute_epoch_asset_name(epoch: u32) -> cml_chain::assets::AssetName {
 mod tests {
     use cml_crypto::ScriptHash;
 
+    use crate::deployment::DaoScriptBytes;
+
     use super::compute_mint_wp_auth_token_policy_id;
 
     #[test]
@@ -886,11 +814,14 @@ mod tests {
         let farm_auth_policy = create_dummy_policy_id(1);
         let factory_auth_policy = create_dummy_policy_id(2);
         let zeroth_epoch_start = 100;
+        let scripts: DaoScriptBytes =
+            serde_json::from_str(include_str!("../../../resources/mainnet.scripts.json")).unwrap();
         let _ = compute_mint_wp_auth_token_policy_id(
             splash_policy,
             farm_auth_policy,
             factory_auth_policy,
             zeroth_epoch_start,
+            &scripts.mint_wp_auth_token.script,
         );
     }
 
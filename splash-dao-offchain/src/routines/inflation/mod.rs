@@ -3,6 +3,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use bloom_offchain::execution_engine::bundled::Bundled;
 use cml_chain::transaction::Transaction;
+use log::{info, trace, warn};
 use spectrum_cardano_lib::OutputRef;
 use spectrum_offchain::backlog::ResilientBacklog;
 use spectrum_offchain::data::event::{AnyMod, Confirmed};
@@ -23,7 +24,7 @@ use crate::protocol_config::ProtocolConfig;
 use crate::routine::{retry_in, RoutineBehaviour, ToRoutine};
 use crate::routines::inflation::actions::InflationActions;
 use crate::state_projection::{StateProjectionRead, StateProjectionWrite};
-use crate::time::{NetworkTimeProvider, ProtocolEpoch};
+use crate::time::{NetworkTime, NetworkTimeProvider, ProtocolEpoch};
 
 pub mod actions;
 
@@ -43,8 +44,52 @@ pub struct Behaviour<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer,
     prover: OperatorProver<'a>,
 }
 
+impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
+    Behaviour<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
+{
+    pub fn new(
+        inflation_box: IB,
+        poll_factory: PF,
+        weighting_poll: WP,
+        voting_escrow: VE,
+        smart_farm: SF,
+        perm_manager: PM,
+        backlog: Backlog,
+        ntp: Time,
+        actions: Actions,
+        conf: ProtocolConfig,
+        network: Net,
+        prover: OperatorProver<'a>,
+    ) -> Self {
+        Self {
+            inflation_box,
+            poll_factory,
+            weighting_poll,
+            voting_escrow,
+            smart_farm,
+            perm_manager,
+            backlog,
+            ntp,
+            actions,
+            conf,
+            pd: PhantomData,
+            network,
+            prover,
+        }
+    }
+}
+
 const DEF_DELAY: Duration = Duration::new(5, 0);
 
+/// Routine never polls more often than this, even when a deadline is imminent.
+const MIN_DELAY: Duration = Duration::from_millis(500);
+
+/// Delay before the next attempt while no votes are pending.
+/// Routine wakes up by the end of the voting window to proceed to distribution right away.
+fn idle_delay(poll_ends_in: NetworkTime) -> Duration {
+    DEF_DELAY.min(Duration::from_millis(poll_ends_in)).max(MIN_DELAY)
+}
+
 pub type InflationBoxSnapshot = Snapshot<InflationBox, OutputRef>;
 pub type PollFactorySnapshot = Snapshot<PollFactory, OutputRef>;
 pub type WeightingPollSnapshot = Snapshot<WeightingPoll, OutputRef>;
@@ -91,10 +136,7 @@ where
             RoutineState::Uninitialized => retry_in(DEF_DELAY),
             RoutineState::PendingCreatePoll(state) => self.try_create_wpoll(state).await,
             RoutineState::WeightingInProgress(state) => self.try_apply_votes(state).await,
-            RoutineState::DistributionInProgress(state) => {
-                self.try_distribute_inflation(state).await;
-                None
-            }
+            RoutineState::DistributionInProgress(state) => self.try_distribute_inflation(state).await,
             RoutineState::PendingEliminatePoll(state) => self.try_eliminate_poll(state).await,
         }
     }
@@ -178,9 +220,16 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
                 }),
                 Some(wp) => match wp.as_erased().0.get().state(genesis, now) {
                     PollState::WeightingOngoing(st) => {
+                        let poll_ends_in = wp
+                            .as_erased()
+                            .0
+                            .get()
+                            .voting_deadline_time(genesis)
+                            .saturating_sub(now);
                         RoutineState::WeightingInProgress(WeightingInProgress {
                             weighting_poll: wp,
                             next_pending_order: self.next_order(st).await,
+                            poll_ends_in,
                         })
                     }
                     PollState::DistributionOngoing(next_farm) => {
@@ -222,9 +271,22 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
         if let (AnyMod::Confirmed(inflation_box), AnyMod::Confirmed(factory)) = (inflation_box, poll_factory)
         {
             let (signed_tx, next_inflation_box, next_factory, next_wpoll) =
-                self.actions.create_wpoll(inflation_box.0, factory.0).await;
+                match self.actions.create_wpoll(inflation_box.0, factory.0).await {
+                    Ok(res) => res,
+                    Err(err) => {
+                        warn!("Cannot create weighting poll: {}", err);
+                        return retry_in(DEF_DELAY);
+                    }
+                };
             let tx = self.prover.prove(signed_tx);
-            self.network.submit_tx(tx).await.unwrap();
+            if let Err(err) = self.network.submit_tx(tx).await {
+                warn!("Failed to create weighting poll: {:?}", err);
+                return retry_in(DEF_DELAY);
+            }
+            info!(
+                "Created weighting poll for epoch {}",
+                next_wpoll.state.0 .0.get().epoch
+            );
             self.inflation_box.write(next_inflation_box).await;
             self.poll_factory.write(next_factory).await;
             self.weighting_poll.write(next_wpoll).await;
@@ -238,26 +300,47 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
         WeightingInProgress {
             weighting_poll,
             next_pending_order,
+            poll_ends_in,
         }: WeightingInProgress<Bearer>,
     ) -> Option<ToRoutine>
     where
         WP: StateProjectionWrite<WeightingPollSnapshot, Bearer>,
         VE: StateProjectionWrite<VotingEscrowSnapshot, Bearer>,
+        Backlog: ResilientBacklog<VotingOrder>,
         Actions: InflationActions<Bearer>,
         Net: Network<Transaction, TxRejected> + Clone + std::marker::Sync + std::marker::Send,
     {
-        if let Some(next_order) = next_pending_order {
-            let (signed_tx, next_wpoll, next_ve) = self
+        if let Some((order, voting_escrow)) = next_pending_order {
+            let (signed_tx, next_wpoll, next_ve) = match self
                 .actions
-                .execute_order(weighting_poll.erased(), next_order)
-                .await;
+                .execute_order(weighting_poll.erased(), (order.clone(), voting_escrow))
+                .await
+            {
+                Ok(res) => res,
+                Err(err) => {
+                    warn!("Cannot apply vote: {}", err);
+                    self.backlog.recharge(order).await;
+                    return retry_in(DEF_DELAY);
+                }
+            };
             let tx = self.prover.prove(signed_tx);
-            self.network.submit_tx(tx).await.unwrap();
+            if let Err(err) = self.network.submit_tx(tx).await {
+                warn!("Failed to apply vote: {:?}", err);
+                self.backlog.recharge(order).await;
+                return retry_in(DEF_DELAY);
+            }
+            let wpoll = next_wpoll.state.0 .0.get();
+            info!(
+                "Vote applied to weighting poll of epoch {}, total weight {}",
+                wpoll.epoch,
+                wpoll.reserves_splash()
+            );
             self.weighting_poll.write(next_wpoll).await;
             self.voting_escrow.write(next_ve).await;
             return None;
         }
-        retry_in(DEF_DELAY)
+        trace!("No votes pending, weighting ends in {}ms", poll_ends_in);
+        retry_in(idle_delay(poll_ends_in))
     }
 
     async fn try_distribute_inflation(
@@ -268,7 +351,8 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
             perm_manager,
             next_farm_weight,
         }: DistributionInProgress<Bearer>,
-    ) where
+    ) -> Option<ToRoutine>
+    where
         WP: StateProjectionWrite<WeightingPollSnapshot, Bearer>,
         SF: StateProjectionWrite<SmartFarmSnapshot, Bearer>,
         PM: StateProjectionWrite<PermManagerSnapshot, Bearer>,
@@ -285,10 +369,14 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
             )
             .await;
         let tx = self.prover.prove(signed_tx);
-        self.network.submit_tx(tx).await.unwrap();
+        if let Err(err) = self.network.submit_tx(tx).await {
+            warn!("Failed to distribute inflation: {:?}", err);
+            return retry_in(DEF_DELAY);
+        }
         self.weighting_poll.write(next_wpoll).await;
         self.smart_farm.write(next_sf).await;
         self.perm_manager.write(next_pm).await;
+        None
     }
 
     async fn try_eliminate_poll(
@@ -300,9 +388,14 @@ impl<'a, IB, PF, WP, VE, SF, PM, Backlog, Time, Actions, Bearer, Net>
         Net: Network<Transaction, TxRejected> + Clone + std::marker::Sync + std::marker::Send,
     {
         if let AnyMod::Confirmed(Confirmed(weighting_poll)) = weighting_poll {
+            let epoch = weighting_poll.0.get().epoch;
             let signed_tx = self.actions.eliminate_wpoll(weighting_poll).await;
             let tx = self.prover.prove(signed_tx);
-            self.network.submit_tx(tx).await.unwrap();
+            if let Err(err) = self.network.submit_tx(tx).await {
+                warn!("Failed to eliminate weighting poll: {:?}", err);
+                return retry_in(DEF_DELAY);
+            }
+            info!("Eliminated weighting poll of epoch {}", epoch);
             return None;
         }
         retry_in(DEF_DELAY)
@@ -331,6 +424,8 @@ pub struct PendingCreatePoll<Out> {
 pub struct WeightingInProgress<Out> {
     weighting_poll: AnyMod<Bundled<WeightingPollSnapshot, Out>>,
     next_pending_order: Option<(VotingOrder, Bundled<VotingEscrowSnapshot, Out>)>,
+    /// Time left until the end of the voting window.
+    poll_ends_in: NetworkTime,
 }
 
 pub struct DistributionInProgress<Out> {
@@ -349,12 +444,14 @@ mod tests {
     use std::sync::Arc;
 
     use async_trait::async_trait;
+    use serde::Serialize;
     use tokio::sync::Mutex;
 
     use bloom_offchain::execution_engine::bundled::Bundled;
     use spectrum_offchain::data::event::{AnyMod, Predicted, Traced};
     use spectrum_offchain::data::{EntitySnapshot, Identifier};
 
+    use crate::routines::inflation::{idle_delay, DEF_DELAY, MIN_DELAY};
     use crate::state_projection::{StateProjectionRead, StateProjectionWrite};
 
     struct StateProjection<T: EntitySnapshot, B>(Arc<Mutex<Option<AnyMod<Bundled<T, B>>>>>);
@@ -378,9 +475,16 @@ mod tests {
     {
         async fn read<I>(&self, id: I) -> Option<AnyMod<Bundled<T, B>>>
         where
-            I: Identifier<For = T> + Send,
+            I: Identifier<For = T> + Serialize + Send,
         {
             self.0.lock().await.clone()
         }
     }
+
+    #[test]
+    fn idle_delay_is_bounded_by_voting_deadline() {
+        assert_eq!(idle_delay(60_000), DEF_DELAY);
+        assert_eq!(idle_delay(2_000), std::time::Duration::from_millis(2_000));
+        assert_eq!(idle_delay(0), MIN_DELAY);
+    }
 }
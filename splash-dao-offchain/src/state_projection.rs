@@ -1,7 +1,10 @@
 use bloom_offchain::execution_engine::bundled::Bundled;
-use spectrum_offchain::data::event::{AnyMod, Predicted, Traced};
+use serde::Serialize;
+use spectrum_offchain::data::event::{AnyMod, Confirmed, Predicted, Traced, Unconfirmed};
 use spectrum_offchain::data::{EntitySnapshot, Identifier};
 
+pub mod rocksdb;
+
 /// Projection of [T] state relative to the ledger.
 #[async_trait::async_trait]
 pub trait StateProjectionRead<T, B>
@@ -10,7 +13,7 @@ where
 {
     async fn read<I>(&self, id: I) -> Option<AnyMod<Bundled<T, B>>>
     where
-        I: Identifier<For = T> + Send;
+        I: Identifier<For = T> + Serialize + Send;
 }
 
 #[async_trait::async_trait]
@@ -20,3 +23,26 @@ where
 {
     async fn write(&self, entity: Traced<Predicted<Bundled<T, B>>>);
 }
+
+/// Keeps projection of [T] in sync with the ledger.
+#[async_trait::async_trait]
+pub trait StateProjectionSync<T, B>
+where
+    T: EntitySnapshot,
+{
+    /// State of the entity was included in a block.
+    async fn confirm<I>(&self, id: I, entity: Confirmed<Bundled<T, B>>)
+    where
+        I: Identifier<For = T> + Serialize + Send;
+    /// State of the entity appeared in mempool.
+    async fn unconfirm<I>(&self, id: I, entity: Unconfirmed<Bundled<T, B>>)
+    where
+        I: Identifier<For = T> + Serialize + Send;
+    /// Discard given state of the entity, e.g. when the block that produced it is rolled back.
+    /// Previously confirmed state of the entity is restored.
+    async fn rollback(&self, ver: T::Version);
+    /// Entity is gone for good.
+    async fn eliminate<I>(&self, id: I)
+    where
+        I: Identifier<For = T> + Serialize + Send;
+}
@@ -0,0 +1,359 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use async_std::task::spawn_blocking;
+use log::{trace, warn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use spectrum_offchain::binary::{prefixed_key, raw_prefixed_key};
+use spectrum_offchain::data::event::{AnyMod, Confirmed, Predicted, Traced, Unconfirmed};
+use spectrum_offchain::data::{EntitySnapshot, Identifier};
+use spectrum_offchain::rocks::{migrate, Migration, RocksConfig};
+
+use crate::state_projection::{StateProjectionRead, StateProjectionSync, StateProjectionWrite};
+
+/// Migrations of persisted DAO entities.
+const MIGRATIONS: &[Migration<rocksdb::OptimisticTransactionDB>] = &[];
+
+/// Persistent projection of DAO entities.
+/// One instance is expected to hold entities of a single type.
+pub struct StateProjectionRocksDB {
+    pub db: Arc<rocksdb::OptimisticTransactionDB>,
+}
+
+impl StateProjectionRocksDB {
+    pub fn new(conf: RocksConfig) -> Self {
+        let db = rocksdb::OptimisticTransactionDB::open_default(conf.db_path).unwrap();
+        migrate(&db, "state_projection", MIGRATIONS);
+        Self { db: Arc::new(db) }
+    }
+}
+
+const STATE_PREFIX: &str = "state";
+/// Links state to the identifier of the entity.
+const STATE_ID_PREFIX: &str = "state:id";
+const PREDICTION_LINK_PREFIX: &str = "prediction:link";
+/// Links confirmed state to the state it superseded.
+const CONFIRMATION_LINK_PREFIX: &str = "confirmation:link";
+const LAST_PREDICTED_PREFIX: &str = "predicted:last";
+const LAST_CONFIRMED_PREFIX: &str = "confirmed:last";
+const LAST_UNCONFIRMED_PREFIX: &str = "unconfirmed:last";
+
+fn get_version<V: DeserializeOwned>(db: &rocksdb::OptimisticTransactionDB, key: Vec<u8>) -> Option<V> {
+    db.get(key)
+        .unwrap()
+        .and_then(|bytes| bincode::deserialize(&bytes).ok())
+}
+
+fn get_state<T, B, V>(db: &rocksdb::OptimisticTransactionDB, ver: &V) -> Option<Bundled<T, B>>
+where
+    T: DeserializeOwned,
+    B: DeserializeOwned,
+    V: Serialize,
+{
+    db.get(prefixed_key(STATE_PREFIX, ver))
+        .unwrap()
+        .and_then(|bytes| bincode::deserialize::<(T, B)>(&bytes).ok())
+        .map(|(entity, bearer)| Bundled(entity, bearer))
+}
+
+fn put_state<T, B>(
+    tx: &rocksdb::Transaction<rocksdb::OptimisticTransactionDB>,
+    id_bytes: &[u8],
+    Bundled(entity, bearer): &Bundled<T, B>,
+) where
+    T: EntitySnapshot + Serialize,
+    T::Version: Serialize,
+    B: Serialize,
+{
+    let ver = entity.version();
+    tx.put(
+        prefixed_key(STATE_PREFIX, &ver),
+        bincode::serialize(&(entity, bearer)).unwrap(),
+    )
+    .unwrap();
+    tx.put(prefixed_key(STATE_ID_PREFIX, &ver), id_bytes).unwrap();
+}
+
+/// Whether `ver` is a (transitive) prediction made on top of `anchor`.
+fn is_linking<V>(db: &rocksdb::OptimisticTransactionDB, ver: V, anchor: V) -> bool
+where
+    V: Serialize + DeserializeOwned + Eq,
+{
+    let mut head = ver;
+    loop {
+        match get_version::<V>(db, prefixed_key(PREDICTION_LINK_PREFIX, &head)) {
+            None => return false,
+            Some(prev) if prev == anchor => return true,
+            Some(prev) => head = prev,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, B> StateProjectionRead<T, B> for StateProjectionRocksDB
+where
+    T: EntitySnapshot + Serialize + DeserializeOwned + Send + 'static,
+    T::Version: Serialize + DeserializeOwned + Send,
+    B: Serialize + DeserializeOwned + Send + 'static,
+{
+    async fn read<I>(&self, id: I) -> Option<AnyMod<Bundled<T, B>>>
+    where
+        I: Identifier<For = T> + Serialize + Send,
+    {
+        let db = self.db.clone();
+        let confirmed_key = prefixed_key(LAST_CONFIRMED_PREFIX, &id);
+        let unconfirmed_key = prefixed_key(LAST_UNCONFIRMED_PREFIX, &id);
+        let predicted_key = prefixed_key(LAST_PREDICTED_PREFIX, &id);
+        spawn_blocking(move || {
+            let confirmed = get_version::<T::Version>(&db, confirmed_key);
+            let unconfirmed = get_version::<T::Version>(&db, unconfirmed_key);
+            let predicted = get_version::<T::Version>(&db, predicted_key);
+            let anchor = unconfirmed.or(confirmed);
+            match (anchor, predicted) {
+                // Prediction is only valid if it's built on top of the latest known state.
+                (Some(anchor), Some(pred)) if pred != anchor && is_linking(&db, pred, anchor) => {
+                    predicted_state(&db, pred)
+                }
+                _ => match (unconfirmed, confirmed) {
+                    (Some(ver), _) => get_state(&db, &ver).map(|s| AnyMod::Unconfirmed(Unconfirmed(s))),
+                    (None, Some(ver)) => get_state(&db, &ver).map(|s| AnyMod::Confirmed(Confirmed(s))),
+                    (None, None) => None,
+                },
+            }
+        })
+        .await
+    }
+}
+
+fn predicted_state<T, B>(
+    db: &rocksdb::OptimisticTransactionDB,
+    ver: T::Version,
+) -> Option<AnyMod<Bundled<T, B>>>
+where
+    T: EntitySnapshot + DeserializeOwned,
+    T::Version: Serialize + DeserializeOwned,
+    B: DeserializeOwned,
+{
+    let prev_state_id = get_version(db, prefixed_key(PREDICTION_LINK_PREFIX, &ver));
+    get_state(db, &ver).map(|s| AnyMod::Predicted(Traced::new(Predicted(s), prev_state_id)))
+}
+
+#[async_trait::async_trait]
+impl<T, B> StateProjectionWrite<T, B> for StateProjectionRocksDB
+where
+    T: EntitySnapshot + Serialize + Send + 'static,
+    T::Version: Serialize + Send,
+    B: Serialize + Send + 'static,
+{
+    async fn write(&self, entity: Traced<Predicted<Bundled<T, B>>>) {
+        let db = self.db.clone();
+        spawn_blocking(move || {
+            let Traced {
+                state: Predicted(state),
+                prev_state_id,
+            } = entity;
+            // Predictions are indexed under the identifier of the state they are derived from.
+            // Brand new entities become visible once observed on-chain.
+            let id_bytes = prev_state_id
+                .as_ref()
+                .and_then(|prev| db.get(prefixed_key(STATE_ID_PREFIX, prev)).unwrap());
+            match (id_bytes, prev_state_id) {
+                (Some(id_bytes), Some(prev)) => {
+                    let ver = state.version();
+                    let tx = db.transaction();
+                    put_state(&tx, &id_bytes, &state);
+                    tx.put(
+                        raw_prefixed_key(LAST_PREDICTED_PREFIX, &id_bytes),
+                        bincode::serialize(&ver).unwrap(),
+                    )
+                    .unwrap();
+                    tx.put(
+                        prefixed_key(PREDICTION_LINK_PREFIX, &ver),
+                        bincode::serialize(&prev).unwrap(),
+                    )
+                    .unwrap();
+                    tx.commit().unwrap();
+                }
+                _ => trace!("Prediction {} is not linked to any known state", state.version()),
+            }
+        })
+        .await
+    }
+}
+
+#[async_trait::async_trait]
+impl<T, B> StateProjectionSync<T, B> for StateProjectionRocksDB
+where
+    T: EntitySnapshot + Serialize + Send + 'static,
+    T::Version: Serialize + DeserializeOwned + Send + Debug,
+    B: Serialize + Send + 'static,
+{
+    async fn confirm<I>(&self, id: I, Confirmed(state): Confirmed<Bundled<T, B>>)
+    where
+        I: Identifier<For = T> + Serialize + Send,
+    {
+        let db = self.db.clone();
+        let id_bytes = bincode::serialize(&id).unwrap();
+        spawn_blocking(move || {
+            let ver = state.version();
+            let index_key = raw_prefixed_key(LAST_CONFIRMED_PREFIX, &id_bytes);
+            let tx = db.transaction();
+            put_state(&tx, &id_bytes, &state);
+            let ver_bytes = bincode::serialize(&ver).unwrap();
+            // Same state may be observed again, e.g. when the chain is replayed after restart.
+            if let Some(prev) = tx.get(&index_key).unwrap().filter(|prev| *prev != ver_bytes) {
+                tx.put(prefixed_key(CONFIRMATION_LINK_PREFIX, &ver), prev)
+                    .unwrap();
+            }
+            tx.put(index_key, ver_bytes).unwrap();
+            // Confirmed state supersedes the one observed in mempool.
+            tx.delete(raw_prefixed_key(LAST_UNCONFIRMED_PREFIX, &id_bytes))
+                .unwrap();
+            tx.commit().unwrap();
+        })
+        .await
+    }
+
+    async fn unconfirm<I>(&self, id: I, Unconfirmed(state): Unconfirmed<Bundled<T, B>>)
+    where
+        I: Identifier<For = T> + Serialize + Send,
+    {
+        let db = self.db.clone();
+        let id_bytes = bincode::serialize(&id).unwrap();
+        spawn_blocking(move || {
+            let ver = state.version();
+            let tx = db.transaction();
+            put_state(&tx, &id_bytes, &state);
+            tx.put(
+                raw_prefixed_key(LAST_UNCONFIRMED_PREFIX, &id_bytes),
+                bincode::serialize(&ver).unwrap(),
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        })
+        .await
+    }
+
+    async fn rollback(&self, ver: T::Version) {
+        let db = self.db.clone();
+        spawn_blocking(move || {
+            let tx = db.transaction();
+            if let Some(id_bytes) = tx.get(prefixed_key(STATE_ID_PREFIX, &ver)).unwrap() {
+                let ver_bytes = bincode::serialize(&ver).unwrap();
+                for prefix in [LAST_PREDICTED_PREFIX, LAST_UNCONFIRMED_PREFIX] {
+                    let index_key = raw_prefixed_key(prefix, &id_bytes);
+                    if tx.get(&index_key).unwrap().as_ref() == Some(&ver_bytes) {
+                        tx.delete(index_key).unwrap();
+                    }
+                }
+                let confirmed_index_key = raw_prefixed_key(LAST_CONFIRMED_PREFIX, &id_bytes);
+                if tx.get(&confirmed_index_key).unwrap().as_ref() == Some(&ver_bytes) {
+                    match tx.get(prefixed_key(CONFIRMATION_LINK_PREFIX, &ver)).unwrap() {
+                        Some(prev) => {
+                            warn!("Rolling back confirmed state {:?}", ver);
+                            tx.put(confirmed_index_key, prev).unwrap();
+                        }
+                        None => tx.delete(confirmed_index_key).unwrap(),
+                    }
+                }
+                tx.delete(prefixed_key(STATE_PREFIX, &ver)).unwrap();
+                tx.delete(prefixed_key(STATE_ID_PREFIX, &ver)).unwrap();
+                tx.delete(prefixed_key(PREDICTION_LINK_PREFIX, &ver)).unwrap();
+                tx.delete(prefixed_key(CONFIRMATION_LINK_PREFIX, &ver)).unwrap();
+            }
+            tx.commit().unwrap();
+        })
+        .await
+    }
+
+    async fn eliminate<I>(&self, id: I)
+    where
+        I: Identifier<For = T> + Serialize + Send,
+    {
+        let db = self.db.clone();
+        let id_bytes = bincode::serialize(&id).unwrap();
+        spawn_blocking(move || {
+            let tx = db.transaction();
+            for prefix in [
+                LAST_PREDICTED_PREFIX,
+                LAST_UNCONFIRMED_PREFIX,
+                LAST_CONFIRMED_PREFIX,
+            ] {
+                tx.delete(raw_prefixed_key(prefix, &id_bytes)).unwrap();
+            }
+            tx.commit().unwrap();
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use cml_crypto::TransactionHash;
+    use rand::RngCore;
+
+    use bloom_offchain::execution_engine::bundled::Bundled;
+    use spectrum_cardano_lib::OutputRef;
+    use spectrum_offchain::data::event::{AnyMod, Confirmed, Predicted, Traced};
+
+    use crate::entities::onchain::smart_farm::{FarmId, SmartFarm};
+    use crate::entities::Snapshot;
+    use crate::routines::inflation::SmartFarmSnapshot;
+    use crate::state_projection::{StateProjectionRead, StateProjectionSync, StateProjectionWrite};
+
+    use super::StateProjectionRocksDB;
+
+    fn rocks_db_for_test() -> StateProjectionRocksDB {
+        let rnd = rand::thread_rng().next_u32();
+        StateProjectionRocksDB {
+            db: Arc::new(rocksdb::OptimisticTransactionDB::open_default(format!("./tmp/{}", rnd)).unwrap()),
+        }
+    }
+
+    fn farm(ix: u64) -> Bundled<SmartFarmSnapshot, ()> {
+        Bundled(
+            Snapshot::new(
+                SmartFarm { farm_id: FarmId(0) },
+                OutputRef::new(TransactionHash::from([ix as u8; 32]), ix),
+            ),
+            (),
+        )
+    }
+
+    async fn read_version(db: &StateProjectionRocksDB) -> Option<(OutputRef, bool)> {
+        let state: Option<AnyMod<Bundled<SmartFarmSnapshot, ()>>> = db.read(FarmId(0)).await;
+        state.map(|s| match s {
+            AnyMod::Confirmed(Confirmed(Bundled(s, _))) => (*s.version(), true),
+            other => (*other.erased().0.version(), false),
+        })
+    }
+
+    #[tokio::test]
+    async fn confirmed_state_is_restored_on_rollback() {
+        let db = rocks_db_for_test();
+        let (s1, s2) = (farm(1), farm(2));
+        let (v1, v2) = (*s1.0.version(), *s2.0.version());
+        db.confirm(FarmId(0), Confirmed(s1)).await;
+        db.confirm(FarmId(0), Confirmed(s2)).await;
+        assert_eq!(read_version(&db).await, Some((v2, true)));
+        db.rollback(v2).await;
+        assert_eq!(read_version(&db).await, Some((v1, true)));
+    }
+
+    #[tokio::test]
+    async fn prediction_on_top_of_confirmed_state_is_preferred() {
+        let db = rocks_db_for_test();
+        let (s1, s2) = (farm(1), farm(2));
+        let (v1, v2) = (*s1.0.version(), *s2.0.version());
+        db.confirm(FarmId(0), Confirmed(s1)).await;
+        db.write(Traced::new(Predicted(s2), Some(v1))).await;
+        assert_eq!(read_version(&db).await, Some((v2, false)));
+        db.rollback(v2).await;
+        assert_eq!(read_version(&db).await, Some((v1, true)));
+    }
+}
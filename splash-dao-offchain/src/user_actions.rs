@@ -0,0 +1,552 @@
+use cml_chain::address::{Address, RewardAddress};
+use cml_chain::assets::MultiAsset;
+use cml_chain::builders::input_builder::{InputBuilderError, SingleInputBuilder};
+use cml_chain::builders::output_builder::{
+    OutputBuilderError, SingleOutputBuilderResult, TransactionOutputBuilder,
+};
+use cml_chain::builders::redeemer_builder::RedeemerWitnessKey;
+use cml_chain::builders::tx_builder::{
+    ChangeSelectionAlgo, SignedTxBuilder, TransactionUnspentOutput, TxBuilderError,
+};
+use cml_chain::builders::withdrawal_builder::{SingleWithdrawalBuilder, WithdrawalBuilderError};
+use cml_chain::builders::witness_builder::{PartialPlutusWitness, PlutusScriptWitness};
+use cml_chain::certs::StakeCredential;
+use cml_chain::plutus::{PlutusData, RedeemerTag};
+use cml_chain::transaction::{TransactionInput, TransactionOutput};
+use cml_chain::Coin;
+use cml_crypto::ScriptHash;
+use derive_more::From;
+
+use bloom_offchain::execution_engine::bundled::Bundled;
+use spectrum_cardano_lib::collateral::Collateral;
+use spectrum_cardano_lib::hash::hash_transaction_canonical;
+use spectrum_cardano_lib::plutus_data::{DatumUpdateError, IntoPlutusData};
+use spectrum_cardano_lib::protocol_params::{constant_tx_builder, COINS_PER_UTXO_BYTE};
+use spectrum_cardano_lib::transaction::TransactionOutputExtension;
+use spectrum_cardano_lib::OutputRef;
+use spectrum_offchain::data::event::{Predicted, Traced};
+use spectrum_offchain::data::Has;
+
+use crate::assets::SPLASH_AC;
+use crate::deployment::DaoScriptBytes;
+use crate::entities::onchain::farm_position::{
+    update_farm_position, EpochRewards, FarmPosition, FarmPositionAction, FarmPositionId,
+    FarmPositionSnapshot, FARM_POSITION_EX_UNITS,
+};
+use crate::entities::onchain::smart_farm::{
+    self, compute_mint_farm_auth_token_policy_id, FarmId, FARM_EX_UNITS,
+};
+use crate::entities::onchain::voting_escrow::{
+    compute_voting_escrow_policy_id, update_ve_lock, Lock, VotingEscrow, VotingEscrowAction,
+    VotingEscrowAuthorizedAction, ORDER_WITNESS_EX_UNITS, VOTING_ESCROW_EX_UNITS,
+};
+use crate::entities::Snapshot;
+use crate::protocol_config::{
+    FactoryAuthPolicy, FarmAuthRefScriptOutput, FarmPositionRefScriptOutput, NodeMagic, SplashPolicy,
+    VEFactoryAuthPolicy, VotingEscrowRefScriptOutput,
+};
+use crate::routines::inflation::{SmartFarmSnapshot, VotingEscrowSnapshot};
+use crate::time::{NetworkTime, ProtocolEpoch};
+
+/// Authorization of an owner action issued against a specific version of the [VotingEscrow].
+#[derive(Clone, Debug)]
+pub struct OwnerAuthorization {
+    /// Hash of the script authorized to witness the TX.
+    pub witness: ScriptHash,
+    /// Version of the voting escrow the owner signed.
+    pub version: u32,
+    /// Owner's signature.
+    pub signature: Vec<u8>,
+}
+
+/// Actions an owner can perform on their [VotingEscrow].
+#[derive(Clone, Debug)]
+pub enum VotingEscrowUserAction {
+    /// Move the lock deadline further.
+    ExtendLock(Lock),
+    /// Add ADA to fund execution of governance actions.
+    AddBudget(Coin),
+    /// Withdraw locked tokens once the lock is expired.
+    Redeem {
+        /// VE factory input along with its witness. The factory authorizes redemption of the escrow.
+        ve_factory: (TransactionUnspentOutput, PartialPlutusWitness),
+        /// Where to send the redeemed tokens.
+        redeemer_address: Address,
+    },
+}
+
+#[derive(Debug, From)]
+pub enum VotingEscrowUserError {
+    /// Authorization was issued for a different version of the voting escrow.
+    VersionMismatch {
+        expected: u32,
+        actual: u32,
+    },
+    /// New lock doesn't extend the current one.
+    LockNotExtended,
+    /// Lock is still active, tokens can't be redeemed yet.
+    LockActive,
+    /// Voting escrow output carries no inline datum to update.
+    MissingDatum,
+    /// Input expected in the TX is missing from it.
+    MissingInput(OutputRef),
+    TxBuilder(TxBuilderError),
+    InputBuilder(InputBuilderError),
+    WithdrawalBuilder(WithdrawalBuilderError),
+    Datum(DatumUpdateError),
+}
+
+/// Builds TXs on behalf of [VotingEscrow] owners.
+/// Change (if any) is returned to the `funding` address, TXs are signed by the user.
+pub fn build_voting_escrow_user_tx<Ctx>(
+    Bundled(voting_escrow, ve_box_in): Bundled<VotingEscrowSnapshot, TransactionOutput>,
+    action: VotingEscrowUserAction,
+    auth: OwnerAuthorization,
+    funding: TransactionUnspentOutput,
+    now: NetworkTime,
+    ctx: Ctx,
+) -> Result<
+    (
+        SignedTxBuilder,
+        Option<Traced<Predicted<Bundled<VotingEscrowSnapshot, TransactionOutput>>>>,
+    ),
+    VotingEscrowUserError,
+>
+where
+    Ctx: Has<Collateral>
+        + Has<VEFactoryAuthPolicy>
+        + Has<VotingEscrowRefScriptOutput>
+        + Has<NodeMagic>
+        + Has<DaoScriptBytes>,
+{
+    let ve = *voting_escrow.get();
+    if auth.version != ve.version {
+        return Err(VotingEscrowUserError::VersionMismatch {
+            expected: ve.version,
+            actual: auth.version,
+        });
+    }
+    let prev_ve_version = *voting_escrow.version();
+    let ve_input = TransactionInput::from(prev_ve_version);
+    let funding_ref = OutputRef::from(funding.input.clone());
+    let mut input_refs = vec![prev_ve_version, funding_ref];
+
+    let mut tx_builder = constant_tx_builder();
+
+    let (ve_action, next_ve) = match action {
+        VotingEscrowUserAction::ExtendLock(locked_until) => {
+            if !extends(ve.locked_until, locked_until) {
+                return Err(VotingEscrowUserError::LockNotExtended);
+            }
+            let next_ve = VotingEscrow {
+                locked_until,
+                version: ve.version + 1,
+                ..ve
+            };
+            let ve_out = relocked_output(&ve_box_in, locked_until, next_ve.version)?;
+            (VotingEscrowAction::AddBudgetOrExtend, Some((next_ve, ve_out)))
+        }
+        VotingEscrowUserAction::AddBudget(lovelace) => {
+            let next_ve = VotingEscrow {
+                version: ve.version + 1,
+                ..ve
+            };
+            let mut ve_out = relocked_output(&ve_box_in, ve.locked_until, next_ve.version)?;
+            ve_out.add_asset(spectrum_cardano_lib::AssetClass::Native, lovelace);
+            (VotingEscrowAction::AddBudgetOrExtend, Some((next_ve, ve_out)))
+        }
+        VotingEscrowUserAction::Redeem {
+            ve_factory: (factory_utxo, factory_witness),
+            redeemer_address,
+        } => {
+            if !ve.lock_expired(now) {
+                return Err(VotingEscrowUserError::LockActive);
+            }
+            let factory_ref = OutputRef::from(factory_utxo.input.clone());
+            input_refs.push(factory_ref);
+            input_refs.sort();
+            let ve_factory_in_ix = input_index(&input_refs, factory_ref)
+                .ok_or(VotingEscrowUserError::MissingInput(factory_ref))?
+                as u32;
+            let factory_in = SingleInputBuilder::new(factory_utxo.input, factory_utxo.output.clone())
+                .plutus_script_inline_datum(factory_witness, vec![])?;
+            tx_builder.add_input(factory_in)?;
+            // Factory is preserved as is.
+            tx_builder.add_output(SingleOutputBuilderResult::new(factory_utxo.output))?;
+            let redeemed = TransactionOutput::new(redeemer_address, ve_box_in.amount().clone(), None, None);
+            tx_builder.add_output(SingleOutputBuilderResult::new(redeemed))?;
+            tx_builder.set_exunits(
+                RedeemerWitnessKey::new(RedeemerTag::Spend, ve_factory_in_ix as u64),
+                VOTING_ESCROW_EX_UNITS,
+            );
+            (VotingEscrowAction::Redeem { ve_factory_in_ix }, None)
+        }
+    };
+
+    let authorized_action = VotingEscrowAuthorizedAction {
+        action: ve_action,
+        witness: auth.witness,
+        version: auth.version,
+        signature: auth.signature,
+    };
+    let voting_escrow_script = PartialPlutusWitness::new(
+        PlutusScriptWitness::Ref(compute_voting_escrow_policy_id(
+            ctx.select::<VEFactoryAuthPolicy>().0,
+            &ctx.select::<DaoScriptBytes>().voting_escrow.script,
+        )),
+        authorized_action.into_pd(),
+    );
+    let ve_in = SingleInputBuilder::new(ve_input, ve_box_in)
+        .plutus_script_inline_datum(voting_escrow_script, vec![])?;
+    let funding_addr = funding.output.address().clone();
+    let funding_in = SingleInputBuilder::new(funding.input, funding.output).payment_key()?;
+
+    tx_builder.add_reference_input(ctx.select::<VotingEscrowRefScriptOutput>().0);
+    tx_builder.add_input(ve_in)?;
+    tx_builder.add_input(funding_in)?;
+    tx_builder.add_collateral(ctx.select::<Collateral>().0)?;
+
+    input_refs.sort();
+    let ve_in_ix = input_index(&input_refs, prev_ve_version)
+        .ok_or(VotingEscrowUserError::MissingInput(prev_ve_version))? as u64;
+    tx_builder.set_exunits(
+        RedeemerWitnessKey::new(RedeemerTag::Spend, ve_in_ix),
+        VOTING_ESCROW_EX_UNITS,
+    );
+
+    // Voting escrow is spent only if the script the owner authorized witnesses the TX.
+    let witness_address = RewardAddress::new(
+        ctx.select::<NodeMagic>().0 as u8,
+        StakeCredential::new_script(auth.witness),
+    );
+    let witness = PartialPlutusWitness::new(
+        PlutusScriptWitness::Ref(auth.witness),
+        PlutusData::new_list(vec![]),
+    );
+    tx_builder
+        .add_withdrawal(SingleWithdrawalBuilder::new(witness_address, 0).plutus_script(witness, vec![])?);
+    tx_builder.set_exunits(
+        RedeemerWitnessKey::new(RedeemerTag::Reward, 0),
+        ORDER_WITNESS_EX_UNITS,
+    );
+
+    let next_ve_state = match next_ve {
+        Some((next_ve, ve_out)) => {
+            tx_builder.add_output(SingleOutputBuilderResult::new(ve_out.clone()))?;
+            Some((next_ve, ve_out))
+        }
+        None => None,
+    };
+
+    let signed_tx_builder = tx_builder.build(ChangeSelectionAlgo::Default, &funding_addr)?;
+    let tx_hash = hash_transaction_canonical(&signed_tx_builder.body());
+
+    let predicted_ve = next_ve_state.map(|(next_ve, ve_out)| {
+        Traced::new(
+            Predicted(Bundled(
+                Snapshot::new(next_ve, OutputRef::new(tx_hash, 0)),
+                ve_out,
+            )),
+            Some(prev_ve_version),
+        )
+    });
+
+    Ok((signed_tx_builder, predicted_ve))
+}
+
+/// Copy of the voting escrow output with the given lock and version set in its datum.
+fn relocked_output(
+    ve_box_in: &TransactionOutput,
+    locked_until: Lock,
+    version: u32,
+) -> Result<TransactionOutput, VotingEscrowUserError> {
+    let mut ve_out = ve_box_in.clone();
+    let data_mut = ve_out.data_mut().ok_or(VotingEscrowUserError::MissingDatum)?;
+    update_ve_lock(data_mut, locked_until, version)?;
+    Ok(ve_out)
+}
+
+/// Position of the input among sorted inputs of the TX.
+fn input_index(sorted_inputs: &[OutputRef], input: OutputRef) -> Option<usize> {
+    sorted_inputs.iter().position(|i| *i == input)
+}
+
+fn extends(current: Lock, next: Lock) -> bool {
+    match (current, next) {
+        (Lock::Def(current), Lock::Def(next)) => next > current,
+        (Lock::Indef(current), Lock::Indef(next)) => next > current,
+        (Lock::Def(_), Lock::Indef(_)) => true,
+        (Lock::Indef(_), Lock::Def(_)) => false,
+    }
+}
+
+/// Claim of rewards accrued by a single farm position.
+#[derive(Clone, Debug)]
+pub struct FarmClaim {
+    pub farm: Bundled<SmartFarmSnapshot, TransactionOutput>,
+    pub position: Bundled<FarmPositionSnapshot, TransactionOutput>,
+    /// Per-epoch emissions of the farm, at least for all unclaimed epochs.
+    pub rewards: Vec<EpochRewards>,
+    /// Where to send claimed SPLASH.
+    pub reward_address: Address,
+}
+
+#[derive(Debug, From)]
+pub enum FarmClaimError {
+    /// Position doesn't belong to the farm it's claimed from.
+    FarmMismatch {
+        farm: FarmId,
+        position: FarmPositionId,
+    },
+    /// Same farm appears in the batch more than once.
+    DuplicateFarm(FarmId),
+    NothingToClaim(FarmPositionId),
+    /// Position output isn't locked by a script.
+    PositionNotAtScript(FarmPositionId),
+    /// Position output carries no inline datum to update.
+    MissingDatum(FarmPositionId),
+    /// Input expected in the TX is missing from it.
+    MissingInput(OutputRef),
+    TxBuilder(TxBuilderError),
+    InputBuilder(InputBuilderError),
+    OutputBuilder(OutputBuilderError),
+    Datum(DatumUpdateError),
+}
+
+type ClaimOutcome = (
+    Traced<Predicted<Bundled<SmartFarmSnapshot, TransactionOutput>>>,
+    Traced<Predicted<Bundled<FarmPositionSnapshot, TransactionOutput>>>,
+);
+
+/// Builds a TX claiming rewards of all given positions at once.
+/// Outputs are laid out in triples (farm, position, reward) in the order of `claims`.
+/// Rewards of all epochs completed before `current_epoch` are claimed.
+pub fn build_farm_claim_tx<Ctx>(
+    claims: Vec<FarmClaim>,
+    current_epoch: ProtocolEpoch,
+    funding: TransactionUnspentOutput,
+    ctx: Ctx,
+) -> Result<(SignedTxBuilder, Vec<ClaimOutcome>), FarmClaimError>
+where
+    Ctx: Has<Collateral>
+        + Has<SplashPolicy>
+        + Has<FactoryAuthPolicy>
+        + Has<FarmAuthRefScriptOutput>
+        + Has<FarmPositionRefScriptOutput>
+        + Has<DaoScriptBytes>,
+{
+    let mut input_refs = vec![OutputRef::from(funding.input.clone())];
+    for (i, claim) in claims.iter().enumerate() {
+        let farm = claim.farm.0.get();
+        let position = claim.position.0.get();
+        if farm.farm_id != position.farm_id {
+            return Err(FarmClaimError::FarmMismatch {
+                farm: farm.farm_id,
+                position: position.id,
+            });
+        }
+        if claims[..i].iter().any(|c| c.farm.0.get().farm_id == farm.farm_id) {
+            return Err(FarmClaimError::DuplicateFarm(farm.farm_id));
+        }
+        input_refs.push(*claim.farm.0.version());
+        input_refs.push(*claim.position.0.version());
+    }
+    input_refs.sort();
+    let input_ix = |r: OutputRef| {
+        input_index(&input_refs, r)
+            .map(|ix| ix as u32)
+            .ok_or(FarmClaimError::MissingInput(r))
+    };
+
+    let farm_script_hash = compute_mint_farm_auth_token_policy_id(
+        ctx.select::<SplashPolicy>().0,
+        ctx.select::<FactoryAuthPolicy>().0,
+        &ctx.select::<DaoScriptBytes>().mint_farm_auth_token.script,
+    );
+
+    let mut tx_builder = constant_tx_builder();
+    tx_builder.add_reference_input(ctx.select::<FarmAuthRefScriptOutput>().0);
+    tx_builder.add_reference_input(ctx.select::<FarmPositionRefScriptOutput>().0);
+
+    let mut next_states = vec![];
+    for (k, claim) in claims.into_iter().enumerate() {
+        let FarmClaim {
+            farm: Bundled(farm, farm_in),
+            position: Bundled(position, position_in),
+            rewards,
+            reward_address,
+        } = claim;
+        let farm_out_ix = 3 * k as u32;
+        let position_out_ix = farm_out_ix + 1;
+        let prev_farm_version = *farm.version();
+        let prev_position_version = *position.version();
+        let farm_in_ix = input_ix(prev_farm_version)?;
+        let position_in_ix = input_ix(prev_position_version)?;
+
+        let position_state = *position.get();
+        let reward = position_state.accrued_rewards(&rewards, current_epoch);
+        if reward == 0 {
+            return Err(FarmClaimError::NothingToClaim(position_state.id));
+        }
+
+        let farm_redeemer = smart_farm::Redeemer {
+            successor_out_ix: farm_out_ix,
+            action: smart_farm::Action::ClaimRewards { position_in_ix },
+        };
+        let farm_witness = PartialPlutusWitness::new(
+            PlutusScriptWitness::Ref(farm_script_hash),
+            farm_redeemer.into_pd(),
+        );
+        let mut farm_out = farm_in.clone();
+        farm_out.sub_asset(*SPLASH_AC, reward);
+        tx_builder.add_input(
+            SingleInputBuilder::new(TransactionInput::from(prev_farm_version), farm_in)
+                .plutus_script_inline_datum(farm_witness, vec![])?,
+        )?;
+        tx_builder.set_exunits(
+            RedeemerWitnessKey::new(RedeemerTag::Spend, farm_in_ix as u64),
+            FARM_EX_UNITS,
+        );
+
+        // Position is validated by its own script, the hash of which is its payment credential.
+        let position_script_hash = position_in
+            .script_hash()
+            .ok_or(FarmClaimError::PositionNotAtScript(position_state.id))?;
+        let position_redeemer = FarmPositionAction::Claim {
+            farm_in_ix,
+            successor_out_ix: position_out_ix,
+        };
+        let position_witness = PartialPlutusWitness::new(
+            PlutusScriptWitness::Ref(position_script_hash),
+            position_redeemer.into_pd(),
+        );
+        let next_epoch_claimed = current_epoch.saturating_sub(1);
+        let mut position_out = position_in.clone();
+        let data_mut = position_out
+            .data_mut()
+            .ok_or(FarmClaimError::MissingDatum(position_state.id))?;
+        update_farm_position(data_mut, next_epoch_claimed)?;
+        tx_builder.add_input(
+            SingleInputBuilder::new(TransactionInput::from(prev_position_version), position_in)
+                .plutus_script_inline_datum(position_witness, vec![])?,
+        )?;
+        tx_builder.set_exunits(
+            RedeemerWitnessKey::new(RedeemerTag::Spend, position_in_ix as u64),
+            FARM_POSITION_EX_UNITS,
+        );
+
+        let (splash_policy, splash_name) = SPLASH_AC.into_token().unwrap();
+        let mut reward_asset = MultiAsset::new();
+        reward_asset.set(splash_policy, splash_name.into(), reward);
+        let reward_out = TransactionOutputBuilder::new()
+            .with_address(reward_address)
+            .next()?
+            .with_asset_and_min_required_coin(reward_asset, COINS_PER_UTXO_BYTE)?
+            .build()?;
+
+        tx_builder.add_output(SingleOutputBuilderResult::new(farm_out.clone()))?;
+        tx_builder.add_output(SingleOutputBuilderResult::new(position_out.clone()))?;
+        tx_builder.add_output(reward_out)?;
+
+        let next_position = FarmPosition {
+            last_claimed_epoch: next_epoch_claimed,
+            ..position_state
+        };
+        next_states.push((
+            (farm.get().clone(), farm_out, prev_farm_version, farm_out_ix),
+            (
+                next_position,
+                position_out,
+                prev_position_version,
+                position_out_ix,
+            ),
+        ));
+    }
+
+    let funding_addr = funding.output.address().clone();
+    tx_builder.add_input(SingleInputBuilder::new(funding.input, funding.output).payment_key()?)?;
+    tx_builder.add_collateral(ctx.select::<Collateral>().0)?;
+
+    let signed_tx_builder = tx_builder.build(ChangeSelectionAlgo::Default, &funding_addr)?;
+    let tx_hash = hash_transaction_canonical(&signed_tx_builder.body());
+
+    let outcomes = next_states
+        .into_iter()
+        .map(
+            |(
+                (farm, farm_out, prev_farm_version, farm_out_ix),
+                (position, position_out, prev_position_version, position_out_ix),
+            )| {
+                let predicted_farm = Traced::new(
+                    Predicted(Bundled(
+                        Snapshot::new(farm, OutputRef::new(tx_hash, farm_out_ix as u64)),
+                        farm_out,
+                    )),
+                    Some(prev_farm_version),
+                );
+                let predicted_position = Traced::new(
+                    Predicted(Bundled(
+                        Snapshot::new(position, OutputRef::new(tx_hash, position_out_ix as u64)),
+                        position_out,
+                    )),
+                    Some(prev_position_version),
+                );
+                (predicted_farm, predicted_position)
+            },
+        )
+        .collect();
+
+    Ok((signed_tx_builder, outcomes))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use cml_chain::address::EnterpriseAddress;
+    use cml_chain::certs::StakeCredential;
+    use cml_chain::transaction::TransactionOutput;
+    use cml_chain::Value;
+    use cml_crypto::{Ed25519KeyHash, TransactionHash};
+
+    use spectrum_cardano_lib::OutputRef;
+
+    use crate::entities::onchain::voting_escrow::Lock;
+
+    use super::{extends, input_index, relocked_output, VotingEscrowUserError};
+
+    #[test]
+    fn lock_extension_is_monotonic() {
+        assert!(extends(Lock::Def(1000), Lock::Def(2000)));
+        assert!(!extends(Lock::Def(2000), Lock::Def(1000)));
+        assert!(extends(Lock::Def(2000), Lock::Indef(Duration::from_secs(1))));
+        assert!(!extends(
+            Lock::Indef(Duration::from_secs(10)),
+            Lock::Indef(Duration::from_secs(5))
+        ));
+    }
+
+    #[test]
+    fn escrow_without_datum_is_not_relocked() {
+        let address =
+            EnterpriseAddress::new(0, StakeCredential::new_pub_key(Ed25519KeyHash::from([1u8; 28])))
+                .to_address();
+        let ve_box = TransactionOutput::new(address, Value::from(5_000_000), None, None);
+        assert!(matches!(
+            relocked_output(&ve_box, Lock::Def(1000), 2),
+            Err(VotingEscrowUserError::MissingDatum)
+        ));
+    }
+
+    #[test]
+    fn inputs_are_looked_up_among_sorted_ones() {
+        let inputs = [1u8, 2]
+            .map(|tag| OutputRef::new(TransactionHash::from([tag; 32]), 0))
+            .to_vec();
+        assert_eq!(input_index(&inputs, inputs[1]), Some(1));
+        assert_eq!(
+            input_index(&inputs, OutputRef::new(TransactionHash::from([3u8; 32]), 0)),
+            None
+        );
+    }
+}